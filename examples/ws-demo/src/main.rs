@@ -1,5 +1,3 @@
-use std::pin::Pin;
-
 use futures::StreamExt;
 use volans::{
     Transport,
@@ -10,15 +8,6 @@ use volans::{
     ws,
 };
 
-#[derive(Default, Debug, Clone, Copy)]
-pub struct TokioExecutor;
-
-impl swarm::Executor for TokioExecutor {
-    fn exec(&self, future: Pin<Box<dyn Future<Output = ()> + Send>>) {
-        tokio::spawn(future);
-    }
-}
-
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
     tracing_subscriber::fmt()
@@ -103,7 +92,7 @@ async fn start_bridge() -> anyhow::Result<()> {
         transport_client,
         client_behavior,
         local_peer_id,
-        swarm::connection::PoolConfig::new(Box::new(TokioExecutor)),
+        swarm::connection::PoolConfig::with_tokio_executor(),
     );
 
     // 对外服务
@@ -129,7 +118,7 @@ async fn start_bridge() -> anyhow::Result<()> {
         transport_server,
         server_behavior,
         local_peer_id,
-        swarm::connection::PoolConfig::new(Box::new(TokioExecutor)),
+        swarm::connection::PoolConfig::with_tokio_executor(),
     );
 
     tokio::spawn(async move {
@@ -216,7 +205,7 @@ async fn start_backend() -> anyhow::Result<()> {
         transport,
         behavior,
         local_peer_id,
-        swarm::connection::PoolConfig::new(Box::new(TokioExecutor)),
+        swarm::connection::PoolConfig::with_tokio_executor(),
     );
 
     let _ = swarm.listen_on(addr.clone())?;
@@ -262,27 +251,23 @@ async fn start_client() -> anyhow::Result<()> {
 
     let muxing_upgrade = muxing::Config::new();
 
-    // // 对外服务
-    let direct_transport = ws::Config::new();
+    // 对外服务
+    let direct_transport = ws::Config::new()
+        .upgrade()
+        .authenticate(identify_upgrade.clone())
+        .multiplex(muxing_upgrade.clone())
+        .boxed();
 
     let (bridge_transport, bridge_behavior) = volans::bridge::client::new();
 
     let transport = bridge_transport
-        .choice(direct_transport)
         .upgrade()
         .authenticate(identify_upgrade)
         .multiplex(muxing_upgrade)
+        .boxed()
+        .or_transport(direct_transport)
         .boxed();
 
-    // .upgrade()
-    // .authenticate(identify_upgrade)
-    // .multiplex(muxing_upgrade)
-    // .boxed()
-    // .choice(direct_transport)
-    // .boxed()
-    // .map(|e, _| e.into_inner())
-    // .boxed();
-
     let behavior = ClientOutboundBehavior {
         ping: volans::ping::outbound::Behavior::default(),
         bridge: bridge_behavior,
@@ -292,7 +277,7 @@ async fn start_client() -> anyhow::Result<()> {
         transport,
         behavior,
         local_peer_id,
-        swarm::connection::PoolConfig::new(Box::new(TokioExecutor)),
+        swarm::connection::PoolConfig::with_tokio_executor(),
     );
 
     let mut bytes = [0u8; 32];
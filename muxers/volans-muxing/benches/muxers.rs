@@ -0,0 +1,118 @@
+//! 多路复用器的吞吐量与往返时延
+//!
+//! 两个节点经本地 TCP 连接，使用 `volans-perf` 在同一个连接上反复测试，
+//! 只统计测试本身的耗时，不含建立连接的时间。
+
+use std::time::Duration;
+
+use criterion::{BenchmarkId, Criterion, Throughput, criterion_group, criterion_main};
+use futures::StreamExt;
+use tokio::runtime::Builder;
+use volans_core::{PeerId, Transport, identity::KeyPair, muxing::StreamMuxerBox, transport};
+use volans_perf::{Benchmark, Report};
+use volans_swarm::{client, connection::PoolConfig, server};
+use volans_swarm_test::{SwarmExt, connect, ephemeral_key_pair, wait_for_event};
+
+#[derive(Debug, Clone, Copy)]
+enum Muxer {
+    Muxing,
+    Yamux,
+}
+
+fn build_transport(key_pair: &KeyPair, muxer: Muxer) -> transport::Boxed<(PeerId, StreamMuxerBox)> {
+    let upgrade = volans_tcp::Config::new()
+        .upgrade()
+        .authenticate(volans_plaintext::Config::new(key_pair.verifying_key()));
+    match muxer {
+        Muxer::Muxing => upgrade.multiplex(volans_muxing::Config::new()).boxed(),
+        Muxer::Yamux => upgrade
+            .multiplex(volans_yamux::UpgradeConfig::new())
+            .boxed(),
+    }
+}
+
+type Dialer = client::Swarm<volans_perf::client::Behavior>;
+
+/// 建立连接并在后台驱动监听端，返回拨号端与监听端的节点 ID
+async fn setup(muxer: Muxer) -> (Dialer, PeerId) {
+    let key_pair = ephemeral_key_pair();
+    let mut dialer = client::Swarm::new(
+        build_transport(&key_pair, muxer),
+        volans_perf::client::Behavior::default(),
+        PeerId::from_public_key(&key_pair.verifying_key()),
+        PoolConfig::with_tokio_executor(),
+    );
+    let key_pair = ephemeral_key_pair();
+    let mut listener = server::Swarm::new(
+        build_transport(&key_pair, muxer),
+        volans_perf::server::Behavior::default(),
+        PeerId::from_public_key(&key_pair.verifying_key()),
+        PoolConfig::with_tokio_executor(),
+    );
+    connect(&mut dialer, &mut listener).await;
+    let listener_peer = *listener.local_peer_id();
+    tokio::spawn(async move {
+        loop {
+            listener.next().await;
+        }
+    });
+    (dialer, listener_peer)
+}
+
+async fn run(dialer: &mut Dialer, peer_id: PeerId, benchmark: Benchmark) -> Report {
+    dialer.behavior_mut().perf(peer_id, benchmark);
+    let event = wait_for_event(dialer, |event| Dialer::into_behavior_event(event).ok()).await;
+    event.result.expect("perf run")
+}
+
+fn muxers(c: &mut Criterion) {
+    // 监听端任务只在 `block_on` 期间推进，测试时两端都在运行
+    let rt = Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .expect("tokio runtime");
+    for muxer in [Muxer::Muxing, Muxer::Yamux] {
+        let (mut dialer, peer_id) = rt.block_on(setup(muxer));
+        let mut group = c.benchmark_group(format!("{muxer:?}").to_lowercase());
+        for bytes in [64 * 1024, 1024 * 1024, 16 * 1024 * 1024] {
+            group.throughput(Throughput::Bytes(bytes));
+            for (name, benchmark) in [
+                ("upload", Benchmark::Upload(bytes)),
+                ("download", Benchmark::Download(bytes)),
+            ] {
+                group.bench_with_input(
+                    BenchmarkId::new(name, bytes),
+                    &benchmark,
+                    |b, &benchmark| {
+                        b.iter_custom(|iters| {
+                            rt.block_on(async {
+                                let mut total = Duration::ZERO;
+                                for _ in 0..iters {
+                                    total += run(&mut dialer, peer_id, benchmark).await.duration;
+                                }
+                                total
+                            })
+                        })
+                    },
+                );
+            }
+        }
+        // 所有轮次在同一个子流上进行，不含子流协商
+        group.throughput(Throughput::Elements(1));
+        group.bench_function("latency", |b| {
+            b.iter_custom(|iters| {
+                let rounds = u32::try_from(iters).unwrap_or(u32::MAX);
+                let report = rt.block_on(run(&mut dialer, peer_id, Benchmark::Latency(rounds)));
+                report.rtts.iter().sum()
+            })
+        });
+        group.finish();
+    }
+}
+
+criterion_group! {
+    name = benches;
+    config = Criterion::default().sample_size(20);
+    targets = muxers
+}
+criterion_main!(benches);
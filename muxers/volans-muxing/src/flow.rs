@@ -0,0 +1,340 @@
+//! 基于信用的子流流量控制
+//!
+//! `/v2/muxing` 在底层子流之上按帧传输数据，帧头为 1 字节类型加 4 字节大端数值：
+//!
+//! - `0` 数据帧，数值为随后负载的长度
+//! - `1` 窗口更新帧，数值为归还给对端的发送额度
+//! - `2` 关闭帧，发送方不再写入数据，数值为 0
+//!
+//! 双方以 [`DEFAULT_RECEIVE_WINDOW`] 作为初始额度，配置了更大接收窗口的一方在子流建立后
+//! 立即以窗口更新帧补足差额。接收方在上层读走半个窗口的数据后归还额度，
+//! 发送方额度耗尽时停止写入，单个子流缓冲的数据不会超过接收窗口。
+//!
+//! 半关闭以关闭帧表示，关闭写入的一方仍可发送窗口更新，双方都关闭后才关闭底层子流。
+
+use std::{
+    collections::VecDeque,
+    io,
+    pin::Pin,
+    task::{Context, Poll, Waker},
+};
+
+use futures::{AsyncRead, AsyncWrite, ready};
+use muxing::Stream;
+
+/// 双方约定的初始接收窗口，也是可配置窗口的下限
+pub const DEFAULT_RECEIVE_WINDOW: u32 = 256 * 1024;
+/// 默认的单个数据帧最大负载
+pub const DEFAULT_MAX_FRAME_SIZE: u32 = 16 * 1024;
+
+const FRAME_DATA: u8 = 0;
+const FRAME_WINDOW_UPDATE: u8 = 1;
+const FRAME_CLOSE: u8 = 2;
+const HEADER_LEN: usize = 5;
+const READ_CHUNK: usize = 4096;
+
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct FlowConfig {
+    pub(crate) receive_window: u32,
+    pub(crate) max_frame_size: u32,
+}
+
+/// 多路复用器输出的子流，`/v1/muxing` 下直接透传底层子流
+#[derive(Debug)]
+pub struct Substream {
+    inner: Stream,
+    flow: Option<Box<Flow>>,
+}
+
+impl Substream {
+    pub(crate) fn new(inner: Stream, config: Option<FlowConfig>) -> Self {
+        Substream {
+            inner,
+            flow: config.map(|config| Box::new(Flow::new(config))),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Flow {
+    receive_window: u32,
+    max_frame_size: u32,
+    /// 对端授予的剩余发送额度
+    send_credit: u64,
+    /// 已编码、尚未写入底层子流的帧
+    pending_out: Vec<u8>,
+    written: usize,
+    header: [u8; HEADER_LEN],
+    header_len: usize,
+    /// 当前数据帧尚未读取的负载长度
+    data_remaining: usize,
+    recv_buffer: VecDeque<u8>,
+    /// 上层已读走、尚未归还给对端的字节数
+    unacked: u32,
+    /// 对端不再发送数据
+    eof: bool,
+    /// 底层子流的读取端已结束，之后不会再收到窗口更新
+    inner_eof: bool,
+    /// 本端已发出关闭帧
+    closed: bool,
+    read_waker: Option<Waker>,
+    write_waker: Option<Waker>,
+}
+
+impl Flow {
+    fn new(config: FlowConfig) -> Self {
+        let receive_window = config.receive_window.max(DEFAULT_RECEIVE_WINDOW);
+        let mut flow = Flow {
+            receive_window,
+            max_frame_size: config.max_frame_size.max(1),
+            send_credit: u64::from(DEFAULT_RECEIVE_WINDOW),
+            pending_out: Vec::new(),
+            written: 0,
+            header: [0; HEADER_LEN],
+            header_len: 0,
+            data_remaining: 0,
+            recv_buffer: VecDeque::new(),
+            unacked: 0,
+            eof: false,
+            inner_eof: false,
+            closed: false,
+            read_waker: None,
+            write_waker: None,
+        };
+        if receive_window > DEFAULT_RECEIVE_WINDOW {
+            flow.queue_frame(
+                FRAME_WINDOW_UPDATE,
+                receive_window - DEFAULT_RECEIVE_WINDOW,
+                &[],
+            );
+        }
+        flow
+    }
+
+    fn queue_frame(&mut self, kind: u8, value: u32, payload: &[u8]) {
+        self.pending_out.push(kind);
+        self.pending_out.extend_from_slice(&value.to_be_bytes());
+        self.pending_out.extend_from_slice(payload);
+    }
+
+    /// 记录上层读走的字节，累计到半个窗口时归还额度
+    fn consume(&mut self, n: usize) {
+        self.unacked += n as u32;
+        // 对端已不再发送，无需归还额度
+        if !self.eof && self.unacked >= self.receive_window / 2 {
+            self.queue_frame(FRAME_WINDOW_UPDATE, self.unacked, &[]);
+            self.unacked = 0;
+        }
+    }
+
+    fn poll_write_pending(
+        &mut self,
+        inner: &mut Stream,
+        cx: &mut Context<'_>,
+    ) -> Poll<io::Result<()>> {
+        while self.written < self.pending_out.len() {
+            let n =
+                ready!(Pin::new(&mut *inner).poll_write(cx, &self.pending_out[self.written..]))?;
+            if n == 0 {
+                return Poll::Ready(Err(io::ErrorKind::WriteZero.into()));
+            }
+            self.written += n;
+        }
+        self.pending_out.clear();
+        self.written = 0;
+        Poll::Ready(Ok(()))
+    }
+
+    /// 从底层子流读取一段数据并解析帧，返回 `Ready(Ok(()))` 表示有进展
+    fn poll_fill(&mut self, inner: &mut Stream, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        if self.data_remaining > 0 {
+            let mut chunk = [0u8; READ_CHUNK];
+            let len = self.data_remaining.min(READ_CHUNK);
+            let n = ready!(Pin::new(&mut *inner).poll_read(cx, &mut chunk[..len]))?;
+            if n == 0 {
+                return Poll::Ready(Err(io::ErrorKind::UnexpectedEof.into()));
+            }
+            self.recv_buffer.extend(&chunk[..n]);
+            self.data_remaining -= n;
+            return Poll::Ready(Ok(()));
+        }
+
+        let n = ready!(Pin::new(&mut *inner).poll_read(cx, &mut self.header[self.header_len..]))?;
+        if n == 0 {
+            if self.header_len == 0 {
+                self.eof = true;
+                self.inner_eof = true;
+                return Poll::Ready(Ok(()));
+            }
+            return Poll::Ready(Err(io::ErrorKind::UnexpectedEof.into()));
+        }
+        self.header_len += n;
+        if self.header_len < HEADER_LEN {
+            return Poll::Ready(Ok(()));
+        }
+        self.header_len = 0;
+
+        let value = u32::from_be_bytes([
+            self.header[1],
+            self.header[2],
+            self.header[3],
+            self.header[4],
+        ]);
+        match self.header[0] {
+            FRAME_DATA => {
+                let received = self.recv_buffer.len() + self.unacked as usize + value as usize;
+                if received > self.receive_window as usize {
+                    return Poll::Ready(Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        "muxing peer exceeded receive window",
+                    )));
+                }
+                self.data_remaining = value as usize;
+            }
+            FRAME_WINDOW_UPDATE => {
+                self.send_credit += u64::from(value);
+            }
+            FRAME_CLOSE => {
+                self.eof = true;
+            }
+            kind => {
+                return Poll::Ready(Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("unknown muxing frame type {kind}"),
+                )));
+            }
+        }
+        Poll::Ready(Ok(()))
+    }
+
+    // 读写双方可能在不同任务中推进底层子流，有进展时唤醒另一方
+    fn wake_reader(&mut self) {
+        if let Some(waker) = self.read_waker.take() {
+            waker.wake();
+        }
+    }
+
+    fn wake_writer(&mut self) {
+        if let Some(waker) = self.write_waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+impl AsyncRead for Substream {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        let Some(flow) = this.flow.as_deref_mut() else {
+            return Pin::new(&mut this.inner).poll_read(cx, buf);
+        };
+        loop {
+            // 窗口更新写不出去时留待下次，不阻塞读取
+            if let Poll::Ready(Err(e)) = flow.poll_write_pending(&mut this.inner, cx) {
+                return Poll::Ready(Err(e));
+            }
+            if !flow.recv_buffer.is_empty() {
+                let n = buf.len().min(flow.recv_buffer.len());
+                for (dst, src) in buf.iter_mut().zip(flow.recv_buffer.drain(..n)) {
+                    *dst = src;
+                }
+                flow.consume(n);
+                let _ = flow.poll_write_pending(&mut this.inner, cx);
+                return Poll::Ready(Ok(n));
+            }
+            if flow.eof {
+                if flow.closed && flow.pending_out.is_empty() {
+                    let _ = Pin::new(&mut this.inner).poll_close(cx);
+                }
+                return Poll::Ready(Ok(0));
+            }
+            flow.read_waker = Some(cx.waker().clone());
+            ready!(flow.poll_fill(&mut this.inner, cx))?;
+            flow.wake_writer();
+        }
+    }
+}
+
+impl AsyncWrite for Substream {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        let Some(flow) = this.flow.as_deref_mut() else {
+            return Pin::new(&mut this.inner).poll_write(cx, buf);
+        };
+        ready!(flow.poll_write_pending(&mut this.inner, cx))?;
+        if flow.closed {
+            return Poll::Ready(Err(io::ErrorKind::WriteZero.into()));
+        }
+        if buf.is_empty() {
+            return Poll::Ready(Ok(0));
+        }
+        // 额度耗尽时读取底层子流等待窗口更新，期间收到的数据受接收窗口约束
+        while flow.send_credit == 0 {
+            if flow.inner_eof {
+                return Poll::Ready(Err(io::ErrorKind::BrokenPipe.into()));
+            }
+            flow.write_waker = Some(cx.waker().clone());
+            ready!(flow.poll_fill(&mut this.inner, cx))?;
+            flow.wake_reader();
+        }
+        let n = buf
+            .len()
+            .min(flow.send_credit as usize)
+            .min(flow.max_frame_size as usize);
+        flow.send_credit -= n as u64;
+        flow.queue_frame(FRAME_DATA, n as u32, &buf[..n]);
+        if let Poll::Ready(Err(e)) = flow.poll_write_pending(&mut this.inner, cx) {
+            return Poll::Ready(Err(e));
+        }
+        Poll::Ready(Ok(n))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        if let Some(flow) = this.flow.as_deref_mut() {
+            ready!(flow.poll_write_pending(&mut this.inner, cx))?;
+        }
+        Pin::new(&mut this.inner).poll_flush(cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        let Some(flow) = this.flow.as_deref_mut() else {
+            return Pin::new(&mut this.inner).poll_close(cx);
+        };
+        if !flow.closed {
+            flow.queue_frame(FRAME_CLOSE, 0, &[]);
+            flow.closed = true;
+        }
+        match ready!(flow.poll_write_pending(&mut this.inner, cx)) {
+            Ok(()) => {}
+            // 对端已关闭子流，关闭帧无需送达
+            Err(e) if e.kind() == io::ErrorKind::WriteZero => {
+                return Pin::new(&mut this.inner).poll_close(cx);
+            }
+            Err(e) => return Poll::Ready(Err(e)),
+        }
+        // 对端仍在发送时保留底层子流的写入端，以便继续归还额度
+        if !flow.eof {
+            return Pin::new(&mut this.inner).poll_flush(cx);
+        }
+        Pin::new(&mut this.inner).poll_close(cx)
+    }
+}
+
+impl Drop for Substream {
+    fn drop(&mut self) {
+        // 已发出关闭帧时关闭底层子流的写入端，避免丢弃时重置子流
+        if self.flow.as_ref().is_some_and(|flow| flow.closed) {
+            let mut cx = Context::from_waker(futures::task::noop_waker_ref());
+            let _ = Pin::new(&mut self.inner).poll_close(&mut cx);
+        }
+    }
+}
@@ -0,0 +1,180 @@
+//! `/v2/muxing` 的连接保活
+//!
+//! 客户端在连接建立后首先打开控制子流并发送一个 Ping，服务端把第一个入站子流作为控制子流，
+//! 不交给上层。控制子流沿用 [`Substream`](crate::Substream) 的 5 字节帧头，类型 `2` 为 Ping、
+//! `3` 为 Pong，收到 Ping 必须回复数值相同的 Pong。启用保活的一方按间隔发送 Ping，
+//! 超时未收到任何帧时以 [`io::ErrorKind::TimedOut`] 关闭连接。
+
+use std::{
+    io,
+    pin::Pin,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use futures::{AsyncRead, AsyncWrite, FutureExt};
+use futures_timer::Delay;
+use muxing::{Endpoint, Stream};
+
+const FRAME_PING: u8 = 2;
+const FRAME_PONG: u8 = 3;
+const HEADER_LEN: usize = 5;
+
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct KeepAliveConfig {
+    pub(crate) interval: Duration,
+    pub(crate) timeout: Duration,
+}
+
+#[derive(Debug)]
+pub(crate) struct Control {
+    /// 客户端尚未打开控制子流
+    opening: bool,
+    /// 服务端尚未收到控制子流
+    accepting: bool,
+    stream: Option<Stream>,
+    config: Option<KeepAliveConfig>,
+    next_ping: Option<Delay>,
+    deadline: Option<Delay>,
+    nonce: u32,
+    header: [u8; HEADER_LEN],
+    header_len: usize,
+    pending_out: Vec<u8>,
+    written: usize,
+}
+
+impl Control {
+    pub(crate) fn new(endpoint: &Endpoint, config: Option<KeepAliveConfig>) -> Self {
+        let client = matches!(endpoint, Endpoint::Client);
+        let mut control = Control {
+            opening: client,
+            accepting: !client,
+            stream: None,
+            config,
+            next_ping: None,
+            deadline: None,
+            nonce: 0,
+            header: [0; HEADER_LEN],
+            header_len: 0,
+            pending_out: Vec::new(),
+            written: 0,
+        };
+        // 对端在收到第一帧时才感知到新的子流
+        if client {
+            control.queue_frame(FRAME_PING, 0);
+        }
+        control
+    }
+
+    pub(crate) fn reset_deadline(&mut self) {
+        if let (Some(config), Some(deadline)) = (self.config, self.deadline.as_mut()) {
+            deadline.reset(config.timeout);
+        }
+    }
+
+    pub(crate) fn needs_outbound(&self) -> bool {
+        self.opening
+    }
+
+    pub(crate) fn wants_inbound(&self) -> bool {
+        self.accepting
+    }
+
+    pub(crate) fn on_stream(&mut self, stream: Stream) {
+        self.opening = false;
+        self.accepting = false;
+        self.stream = Some(stream);
+        if let Some(config) = self.config {
+            self.next_ping = Some(Delay::new(config.interval));
+            self.deadline = Some(Delay::new(config.timeout));
+        }
+    }
+
+    fn queue_frame(&mut self, kind: u8, value: u32) {
+        self.pending_out.push(kind);
+        self.pending_out.extend_from_slice(&value.to_be_bytes());
+    }
+
+    /// 推进控制子流，只在保活超时或控制子流出错时返回
+    pub(crate) fn poll(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        loop {
+            if self.stream.is_none() {
+                return Poll::Pending;
+            }
+            if let Some(deadline) = self.deadline.as_mut()
+                && deadline.poll_unpin(cx).is_ready()
+            {
+                return Poll::Ready(Err(io::Error::new(
+                    io::ErrorKind::TimedOut,
+                    "muxing keep-alive timeout",
+                )));
+            }
+            if let Some(config) = self.config
+                && let Some(next_ping) = self.next_ping.as_mut()
+                && next_ping.poll_unpin(cx).is_ready()
+            {
+                next_ping.reset(config.interval);
+                self.nonce = self.nonce.wrapping_add(1);
+                self.queue_frame(FRAME_PING, self.nonce);
+                continue;
+            }
+
+            let stream = self.stream.as_mut().expect("Control stream is open");
+            if self.written < self.pending_out.len() {
+                match Pin::new(&mut *stream).poll_write(cx, &self.pending_out[self.written..]) {
+                    Poll::Ready(Ok(0)) => {
+                        return Poll::Ready(Err(io::ErrorKind::WriteZero.into()));
+                    }
+                    Poll::Ready(Ok(n)) => {
+                        self.written += n;
+                        if self.written == self.pending_out.len() {
+                            self.pending_out.clear();
+                            self.written = 0;
+                            let _ = Pin::new(&mut *stream).poll_flush(cx);
+                        }
+                        continue;
+                    }
+                    Poll::Ready(Err(e)) => return Poll::Ready(Err(e)),
+                    Poll::Pending => {}
+                }
+            }
+
+            match Pin::new(&mut *stream).poll_read(cx, &mut self.header[self.header_len..]) {
+                Poll::Ready(Ok(0)) => {
+                    // 对端关闭了控制子流，连接随后会被关闭，不再保活
+                    tracing::debug!("Muxing control stream closed by remote");
+                    self.stream = None;
+                    self.next_ping = None;
+                    self.deadline = None;
+                    return Poll::Pending;
+                }
+                Poll::Ready(Ok(n)) => {
+                    self.header_len += n;
+                    if self.header_len < HEADER_LEN {
+                        continue;
+                    }
+                    self.header_len = 0;
+                    self.reset_deadline();
+                    let value = u32::from_be_bytes([
+                        self.header[1],
+                        self.header[2],
+                        self.header[3],
+                        self.header[4],
+                    ]);
+                    match self.header[0] {
+                        FRAME_PING => self.queue_frame(FRAME_PONG, value),
+                        FRAME_PONG => {}
+                        kind => {
+                            return Poll::Ready(Err(io::Error::new(
+                                io::ErrorKind::InvalidData,
+                                format!("unknown muxing control frame type {kind}"),
+                            )));
+                        }
+                    }
+                }
+                Poll::Ready(Err(e)) => return Poll::Ready(Err(e)),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
@@ -1,14 +1,26 @@
-use volans_core::{
-    StreamMuxer, UpgradeInfo,
-    upgrade::{InboundConnectionUpgrade, OutboundConnectionUpgrade},
-};
+mod flow;
+mod keep_alive;
+
+pub use flow::{DEFAULT_MAX_FRAME_SIZE, DEFAULT_RECEIVE_WINDOW, Substream};
 use futures::{AsyncRead, AsyncWrite, future, ready};
 pub use muxing::{Connection, ConnectionError, Endpoint, Stream};
 use std::{
     collections::VecDeque,
-    io, iter,
+    io,
     pin::Pin,
     task::{Context, Poll, Waker},
+    time::Duration,
+    vec,
+};
+pub use volans_core::muxing::InboundOverflow;
+use volans_core::{
+    StreamMuxer, UpgradeInfo,
+    upgrade::{InboundConnectionUpgrade, OutboundConnectionUpgrade},
+};
+
+use crate::{
+    flow::FlowConfig,
+    keep_alive::{Control, KeepAliveConfig},
 };
 
 #[derive(Debug)]
@@ -16,6 +28,16 @@ pub struct Muxer<C> {
     connection: Connection<C>,
     inbound_stream_buffer: VecDeque<Stream>,
     inbound_stream_waker: Option<Waker>,
+    max_buffered_inbound_streams: usize,
+    inbound_overflow: InboundOverflow,
+    /// 背压模式下等待缓冲区腾出空间的 `poll` 任务
+    poll_waker: Option<Waker>,
+    dropped_inbound_streams: u64,
+    protocol: &'static str,
+    /// 协商为 `/v2/muxing` 时启用的子流流量控制
+    flow: Option<FlowConfig>,
+    /// `/v2/muxing` 的控制子流
+    control: Option<Control>,
 }
 
 impl<C> Muxer<C>
@@ -25,36 +47,96 @@ where
     pub fn new(connection: Connection<C>) -> Self {
         Muxer {
             connection,
-            inbound_stream_buffer: VecDeque::with_capacity(MAX_BUFFERED_INBOUND_STREAMS),
+            inbound_stream_buffer: VecDeque::new(),
             inbound_stream_waker: None,
+            max_buffered_inbound_streams: DEFAULT_MAX_BUFFERED_INBOUND_STREAMS,
+            inbound_overflow: InboundOverflow::default(),
+            poll_waker: None,
+            dropped_inbound_streams: 0,
+            protocol: PROTOCOL_V1,
+            flow: None,
+            control: None,
         }
     }
 
+    /// 设置入站子流缓冲区的上限及溢出时的处理方式
+    pub fn with_inbound_buffer(mut self, max: usize, overflow: InboundOverflow) -> Self {
+        self.max_buffered_inbound_streams = max;
+        self.inbound_overflow = overflow;
+        self
+    }
+
+    /// 按 `/v2/muxing` 启用子流流量控制及控制子流
+    pub(crate) fn with_v2(mut self, flow: FlowConfig, control: Control) -> Self {
+        self.protocol = PROTOCOL_V2;
+        self.flow = Some(flow);
+        self.control = Some(control);
+        self
+    }
+
+    fn pop_inbound(&mut self) -> Option<Stream> {
+        let stream = self.inbound_stream_buffer.pop_front()?;
+        if let Some(waker) = self.poll_waker.take() {
+            waker.wake();
+        }
+        Some(stream)
+    }
+
     fn poll_inner(&mut self, cx: &mut Context<'_>) -> Poll<Result<Stream, ConnectionError>> {
-        let stream =
-            ready!(self.connection.poll_next_inbound(cx)?).ok_or(ConnectionError::Closed)?;
-        Poll::Ready(Ok(stream))
+        loop {
+            // 对端关闭连接时以 `UnexpectedEof` 报告，便于上层与其他错误区分
+            let stream = ready!(self.connection.poll_next_inbound(cx)?)
+                .ok_or_else(|| ConnectionError::Io(io::ErrorKind::UnexpectedEof.into()))?;
+            if let Some(control) = self.control.as_mut()
+                && control.wants_inbound()
+            {
+                control.on_stream(stream);
+                // 由 `poll` 推进控制子流
+                cx.waker().wake_by_ref();
+                continue;
+            }
+            return Poll::Ready(Ok(stream));
+        }
+    }
+
+    /// 客户端在打开其他子流之前先打开控制子流
+    fn poll_open_control(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), ConnectionError>> {
+        if let Some(control) = self.control.as_mut()
+            && control.needs_outbound()
+        {
+            let stream = ready!(self.connection.poll_new_outbound(cx))?;
+            control.on_stream(stream);
+            cx.waker().wake_by_ref();
+        }
+        Poll::Ready(Ok(()))
     }
 }
 
-const MAX_BUFFERED_INBOUND_STREAMS: usize = 256;
+const DEFAULT_MAX_BUFFERED_INBOUND_STREAMS: usize = 256;
+/// 不带流量控制的初始版本，仅为兼容旧节点保留
+const PROTOCOL_V1: &str = "/v1/muxing";
+/// 子流按帧传输并带有基于信用的流量控制，帧格式见 [`Substream`]，另有一个用于保活的控制子流
+///
+/// 帧格式、窗口或控制子流语义的任何变化都必须使用新的协议名，旧版本继续保留在协商列表中。
+const PROTOCOL_V2: &str = "/v2/muxing";
 
 impl<C> StreamMuxer for Muxer<C>
 where
     C: AsyncRead + AsyncWrite + Unpin + 'static,
 {
-    type Substream = Stream;
+    type Substream = Substream;
     type Error = ConnectionError;
 
     fn poll_inbound(
         mut self: Pin<&mut Self>,
         cx: &mut Context<'_>,
     ) -> Poll<Result<Self::Substream, Self::Error>> {
-        if let Some(stream) = self.inbound_stream_buffer.pop_front() {
-            return Poll::Ready(Ok(stream));
+        let flow = self.flow;
+        if let Some(stream) = self.pop_inbound() {
+            return Poll::Ready(Ok(Substream::new(stream, flow)));
         }
         if let Poll::Ready(res) = self.poll_inner(cx) {
-            return Poll::Ready(res);
+            return Poll::Ready(res.map(|stream| Substream::new(stream, flow)));
         }
         self.inbound_stream_waker = Some(cx.waker().clone());
         Poll::Pending
@@ -64,17 +146,40 @@ where
         mut self: Pin<&mut Self>,
         cx: &mut Context<'_>,
     ) -> Poll<Result<Self::Substream, Self::Error>> {
-        self.as_mut().connection.poll_new_outbound(cx)
+        ready!(self.poll_open_control(cx))?;
+        let flow = self.flow;
+        self.as_mut()
+            .connection
+            .poll_new_outbound(cx)
+            .map_ok(|stream| Substream::new(stream, flow))
     }
 
     #[tracing::instrument(level = "trace", name = "StreamMuxer::poll", skip(self, cx))]
     fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
         let mut this = self.as_mut();
+        let full = this.inbound_stream_buffer.len() >= this.max_buffered_inbound_streams;
+        if full && this.inbound_overflow == InboundOverflow::Backpressure {
+            // 暂停读取期间收不到对端的帧，恢复后重新计算保活超时
+            if let Some(control) = this.control.as_mut() {
+                control.reset_deadline();
+            }
+            this.poll_waker = Some(cx.waker().clone());
+            return Poll::Pending;
+        }
+        if let Poll::Ready(Err(e)) = this.poll_open_control(cx) {
+            return Poll::Ready(Err(e));
+        }
+        if let Some(control) = this.control.as_mut()
+            && let Poll::Ready(Err(e)) = control.poll(cx)
+        {
+            return Poll::Ready(Err(ConnectionError::Io(e)));
+        }
         let inbound_stream = ready!(this.poll_inner(cx))?;
 
-        if this.inbound_stream_buffer.len() >= MAX_BUFFERED_INBOUND_STREAMS {
+        if full {
+            this.dropped_inbound_streams += 1;
             tracing::warn!(
-                "{}: Inbound stream buffer is full, dropping stream:",
+                "{}: Inbound stream buffer is full, dropping stream",
                 inbound_stream
             );
             drop(inbound_stream);
@@ -93,39 +198,133 @@ where
     fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
         self.as_mut().connection.poll_close(cx)
     }
+
+    fn protocol(&self) -> Option<&'static str> {
+        Some(self.protocol)
+    }
+
+    fn dropped_inbound_streams(&self) -> u64 {
+        self.dropped_inbound_streams
+    }
 }
 
 #[derive(Debug, Clone)]
-pub struct Config(muxing::Config);
+pub struct Config {
+    inner: muxing::Config,
+    max_buffered_inbound_streams: usize,
+    inbound_overflow: InboundOverflow,
+    keep_alive: Option<KeepAliveConfig>,
+    flow_control: bool,
+    receive_window: u32,
+    max_frame_size: u32,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            inner: muxing::Config::default(),
+            max_buffered_inbound_streams: DEFAULT_MAX_BUFFERED_INBOUND_STREAMS,
+            inbound_overflow: InboundOverflow::default(),
+            keep_alive: None,
+            flow_control: true,
+            receive_window: DEFAULT_RECEIVE_WINDOW,
+            max_frame_size: DEFAULT_MAX_FRAME_SIZE,
+        }
+    }
+}
 
 impl Config {
     pub fn new() -> Self {
-        Config(muxing::Config::default())
+        Config::default()
     }
 
     pub fn set_max_active_streams(&mut self, max_active_streams: usize) -> &mut Self {
-        self.0.set_max_active_streams(max_active_streams);
+        self.inner.set_max_active_streams(max_active_streams);
         self
     }
 
     pub fn set_read_after_close(&mut self, read_after_close: bool) -> &mut Self {
-        self.0.set_read_after_close(read_after_close);
+        self.inner.set_read_after_close(read_after_close);
         self
     }
-}
 
-impl Default for Config {
-    fn default() -> Self {
-        Config(muxing::Config::default())
+    /// 等待上层取走的入站子流上限，默认 256
+    pub fn set_max_buffered_inbound_streams(&mut self, max: usize) -> &mut Self {
+        self.max_buffered_inbound_streams = max;
+        self
+    }
+
+    /// 入站子流缓冲区已满时的处理方式，默认丢弃
+    pub fn set_inbound_overflow(&mut self, overflow: InboundOverflow) -> &mut Self {
+        self.inbound_overflow = overflow;
+        self
+    }
+
+    /// 是否协商带流量控制的 `/v2/muxing`，默认开启，对端只支持 `/v1/muxing` 时自动回退
+    pub fn set_flow_control(&mut self, flow_control: bool) -> &mut Self {
+        self.flow_control = flow_control;
+        self
+    }
+
+    /// 每个子流的接收窗口，不小于 [`DEFAULT_RECEIVE_WINDOW`]
+    pub fn set_receive_window(&mut self, receive_window: u32) -> &mut Self {
+        if receive_window < DEFAULT_RECEIVE_WINDOW {
+            tracing::warn!(
+                "Muxing receive window {} is below the minimum {}, raising it",
+                receive_window,
+                DEFAULT_RECEIVE_WINDOW
+            );
+        }
+        self.receive_window = receive_window.max(DEFAULT_RECEIVE_WINDOW);
+        self
+    }
+
+    /// 单个数据帧的最大负载，更大的写入会被拆分
+    pub fn set_max_frame_size(&mut self, max_frame_size: u32) -> &mut Self {
+        self.max_frame_size = max_frame_size.max(1);
+        self
+    }
+
+    /// 启用连接保活，每隔 `interval` 发送 Ping，`timeout` 内未收到对端任何控制帧时关闭连接
+    ///
+    /// 保活依赖 `/v2/muxing` 的控制子流，协商为 `/v1/muxing` 时不生效。未启用保活的一方仍会回复 Pong。
+    pub fn set_keep_alive(&mut self, interval: Duration, timeout: Duration) -> &mut Self {
+        self.keep_alive = Some(KeepAliveConfig { interval, timeout });
+        self
+    }
+
+    fn new_muxer<C>(self, socket: C, endpoint: Endpoint, info: &str) -> Muxer<C>
+    where
+        C: AsyncRead + AsyncWrite + Unpin + 'static,
+    {
+        let control = Control::new(&endpoint, self.keep_alive);
+        let connection = Connection::new(socket, self.inner, endpoint);
+        let muxer = Muxer::new(connection)
+            .with_inbound_buffer(self.max_buffered_inbound_streams, self.inbound_overflow);
+        if info != PROTOCOL_V2 {
+            if self.keep_alive.is_some() {
+                tracing::debug!("Muxing keep-alive is unavailable on {}", PROTOCOL_V1);
+            }
+            return muxer;
+        }
+        let flow = FlowConfig {
+            receive_window: self.receive_window,
+            max_frame_size: self.max_frame_size,
+        };
+        muxer.with_v2(flow, control)
     }
 }
 
 impl UpgradeInfo for Config {
     type Info = &'static str;
-    type InfoIter = iter::Once<Self::Info>;
+    type InfoIter = vec::IntoIter<Self::Info>;
 
     fn protocol_info(&self) -> Self::InfoIter {
-        iter::once("/v1/muxing")
+        if self.flow_control {
+            vec![PROTOCOL_V2, PROTOCOL_V1].into_iter()
+        } else {
+            vec![PROTOCOL_V1].into_iter()
+        }
     }
 }
 
@@ -137,9 +336,8 @@ where
     type Error = io::Error;
     type Future = future::Ready<Result<Self::Output, Self::Error>>;
 
-    fn upgrade_inbound(self, socket: C, _info: Self::Info) -> Self::Future {
-        let connection = Connection::new(socket, self.0, Endpoint::Server);
-        future::ready(Ok(Muxer::new(connection)))
+    fn upgrade_inbound(self, socket: C, info: Self::Info) -> Self::Future {
+        future::ready(Ok(self.new_muxer(socket, Endpoint::Server, info)))
     }
 }
 
@@ -151,8 +349,7 @@ where
     type Error = io::Error;
     type Future = future::Ready<Result<Self::Output, Self::Error>>;
 
-    fn upgrade_outbound(self, socket: C, _info: Self::Info) -> Self::Future {
-        let connection = Connection::new(socket, self.0, Endpoint::Client);
-        future::ready(Ok(Muxer::new(connection)))
+    fn upgrade_outbound(self, socket: C, info: Self::Info) -> Self::Future {
+        future::ready(Ok(self.new_muxer(socket, Endpoint::Client, info)))
     }
 }
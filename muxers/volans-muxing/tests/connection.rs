@@ -0,0 +1,144 @@
+use std::{io, task::Poll, time::Duration};
+
+use futures::{AsyncWriteExt, future};
+use tokio_util::compat::{Compat, TokioAsyncReadCompatExt};
+use volans_core::{
+    StreamMuxer,
+    muxing::StreamMuxerExt,
+    upgrade::{InboundConnectionUpgrade, OutboundConnectionUpgrade},
+};
+use volans_muxing::{Config, ConnectionError, InboundOverflow, Muxer, Substream};
+
+const PROTOCOL: &str = "/v2/muxing";
+
+type Io = Compat<tokio::io::DuplexStream>;
+
+async fn muxers(dialer: Config, listener: Config) -> (Muxer<Io>, Muxer<Io>) {
+    let (a, b) = tokio::io::duplex(64 * 1024);
+    let (dialer, listener) = future::join(
+        dialer.upgrade_outbound(a.compat(), PROTOCOL),
+        listener.upgrade_inbound(b.compat(), PROTOCOL),
+    )
+    .await;
+    (dialer.unwrap(), listener.unwrap())
+}
+
+/// 在后台驱动连接，丢弃入站子流
+fn drive(mut muxer: Muxer<Io>) {
+    tokio::spawn(future::poll_fn(move |cx| {
+        if let Poll::Ready(Err(_)) = muxer.poll_unpin(cx) {
+            return Poll::Ready(());
+        }
+        while let Poll::Ready(Ok(_)) = muxer.poll_inbound_unpin(cx) {}
+        Poll::Pending
+    }));
+}
+
+/// 打开 `count` 个子流并各写入一个字节，使对端收到子流
+async fn open_streams(mut dialer: Muxer<Io>, count: usize) -> Vec<Substream> {
+    let mut streams = Vec::new();
+    for _ in 0..count {
+        streams.push(
+            future::poll_fn(|cx| dialer.poll_outbound_unpin(cx))
+                .await
+                .unwrap(),
+        );
+    }
+    drive(dialer);
+    for stream in &mut streams {
+        stream.write_all(&[1]).await.unwrap();
+        stream.flush().await.unwrap();
+    }
+    streams
+}
+
+fn inbound_buffer(max: usize, overflow: InboundOverflow) -> Config {
+    let mut config = Config::new();
+    config
+        .set_max_buffered_inbound_streams(max)
+        .set_inbound_overflow(overflow);
+    config
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn full_inbound_buffer_drops_streams() {
+    let (dialer, mut listener) =
+        muxers(Config::new(), inbound_buffer(1, InboundOverflow::Drop)).await;
+    let _streams = open_streams(dialer, 3).await;
+
+    // 只驱动连接而不取走子流，超出缓冲区的子流被丢弃并计数
+    let dropped = future::poll_fn(|cx| {
+        let _ = listener.poll_unpin(cx);
+        match listener.dropped_inbound_streams() {
+            2 => Poll::Ready(()),
+            _ => Poll::Pending,
+        }
+    });
+    tokio::time::timeout(Duration::from_secs(5), dropped)
+        .await
+        .unwrap();
+    let first = future::poll_fn(|cx| listener.poll_inbound_unpin(cx)).await;
+    assert!(first.is_ok());
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn full_inbound_buffer_applies_backpressure() {
+    let (dialer, mut listener) = muxers(
+        Config::new(),
+        inbound_buffer(1, InboundOverflow::Backpressure),
+    )
+    .await;
+    let _streams = open_streams(dialer, 3).await;
+
+    // 缓冲区已满时暂停读取连接而不是丢弃子流
+    let drive_only = future::poll_fn(|cx| listener.poll_unpin(cx));
+    assert!(
+        tokio::time::timeout(Duration::from_millis(100), drive_only)
+            .await
+            .is_err()
+    );
+    assert_eq!(listener.dropped_inbound_streams(), 0);
+
+    // 取走缓冲的子流后继续读取，所有子流都能收到
+    for _ in 0..3 {
+        let inbound = future::poll_fn(|cx| {
+            let _ = listener.poll_unpin(cx);
+            listener.poll_inbound_unpin(cx)
+        });
+        tokio::time::timeout(Duration::from_secs(5), inbound)
+            .await
+            .unwrap()
+            .unwrap();
+    }
+    assert_eq!(listener.dropped_inbound_streams(), 0);
+}
+
+fn keep_alive() -> Config {
+    let mut config = Config::new();
+    config.set_keep_alive(Duration::from_millis(20), Duration::from_millis(100));
+    config
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn keep_alive_with_responsive_peer() {
+    let (mut dialer, listener) = muxers(keep_alive(), Config::new()).await;
+    // 未启用保活的一方仍回复 Pong
+    drive(listener);
+    let poll = future::poll_fn(|cx| dialer.poll_unpin(cx));
+    assert!(
+        tokio::time::timeout(Duration::from_millis(500), poll)
+            .await
+            .is_err()
+    );
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn keep_alive_times_out_on_dead_peer() {
+    // 对端的连接不再被驱动，收不到任何控制帧
+    let (mut dialer, _listener) = muxers(keep_alive(), Config::new()).await;
+    let poll = future::poll_fn(|cx| dialer.poll_unpin(cx));
+    match tokio::time::timeout(Duration::from_secs(5), poll).await {
+        Ok(Err(ConnectionError::Io(e))) => assert_eq!(e.kind(), io::ErrorKind::TimedOut),
+        result => panic!("unexpected result: {result:?}"),
+    }
+}
@@ -0,0 +1,87 @@
+use futures::StreamExt;
+use volans_core::{PeerId, Transport, identity::KeyPair};
+use volans_swarm::{client, connection::PoolConfig, server};
+use volans_swarm_test::{SwarmExt, connect, ephemeral_key_pair, next_behavior_event};
+
+fn identify(_: &KeyPair) -> volans_identify::Behavior {
+    volans_identify::Behavior::new(volans_identify::Config::new("/test/1.0.0"))
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn transfer_beyond_receive_window() {
+    use futures::{AsyncReadExt, AsyncWriteExt};
+    use volans_swarm::StreamProtocol;
+
+    const BULK: StreamProtocol = StreamProtocol::new("/bulk/1.0.0");
+    const LEN: usize = 4 * volans_muxing::DEFAULT_RECEIVE_WINDOW as usize;
+
+    let mut dialer = client::Swarm::new_ephemeral(|_| volans_stream::client::Behavior::new());
+    let mut listener = server::Swarm::new_ephemeral(|_| volans_stream::server::Behavior::new());
+    connect(&mut dialer, &mut listener).await;
+
+    let listener_peer = *listener.local_peer_id();
+    let mut control = dialer.behavior().control();
+    let mut incoming = listener.behavior_mut().accept(BULK).unwrap();
+    tokio::spawn(async move {
+        loop {
+            listener.next().await;
+        }
+    });
+    tokio::spawn(async move {
+        loop {
+            dialer.next().await;
+        }
+    });
+    tokio::spawn(async move {
+        while let Some((_, _, mut stream)) = incoming.next().await {
+            let mut buf = vec![0; LEN];
+            stream.read_exact(&mut buf).await.unwrap();
+            assert!(buf.iter().enumerate().all(|(i, b)| *b == i as u8));
+            stream.write_all(b"done").await.unwrap();
+            stream.flush().await.unwrap();
+        }
+    });
+
+    // 写入量超过接收窗口，依赖对端读取后归还的额度才能写完
+    let mut stream = control.open_stream(listener_peer, BULK).await.unwrap();
+    let data: Vec<u8> = (0..LEN).map(|i| i as u8).collect();
+    stream.write_all(&data).await.unwrap();
+    let mut buf = [0; 4];
+    stream.read_exact(&mut buf).await.unwrap();
+    assert_eq!(&buf, b"done");
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn negotiate_muxer_choice() {
+    use volans_core::muxing::MuxerChoice;
+
+    let key_pair = ephemeral_key_pair();
+    let transport = volans_tcp::Config::new()
+        .upgrade()
+        .authenticate(volans_plaintext::Config::new(key_pair.verifying_key()))
+        .multiplex(MuxerChoice::new(
+            volans_yamux::UpgradeConfig::new(),
+            volans_muxing::Config::new(),
+        ))
+        .boxed();
+    let mut dialer = client::Swarm::new(
+        transport,
+        identify(&key_pair),
+        PeerId::from_public_key(&key_pair.verifying_key()),
+        PoolConfig::with_tokio_executor(),
+    );
+    // 监听方只支持 muxing
+    let mut listener = server::Swarm::new_ephemeral(identify);
+    connect(&mut dialer, &mut listener).await;
+
+    let listener_peer = *listener.local_peer_id();
+    tokio::spawn(async move {
+        loop {
+            listener.next().await;
+        }
+    });
+    match next_behavior_event(&mut dialer).await {
+        volans_identify::Event::Received { peer_id, .. } => assert_eq!(peer_id, listener_peer),
+        event => panic!("unexpected event: {event:?}"),
+    }
+}
@@ -1,7 +1,7 @@
 use futures::{AsyncRead, AsyncWrite, future, ready};
 use std::{
     collections::VecDeque,
-    io, iter,
+    fmt, io, iter,
     pin::Pin,
     task::{Context, Poll, Waker},
 };
@@ -10,6 +10,7 @@ use volans_core::{
     upgrade::{InboundConnectionUpgrade, OutboundConnectionUpgrade},
 };
 
+pub use volans_core::muxing::InboundOverflow;
 pub use yamux::{Config, Connection, ConnectionError, Mode, Stream};
 
 #[derive(Debug)]
@@ -17,6 +18,11 @@ pub struct Muxer<C> {
     connection: Connection<C>,
     inbound_stream_buffer: VecDeque<Stream>,
     inbound_stream_waker: Option<Waker>,
+    max_buffered_inbound_streams: usize,
+    inbound_overflow: InboundOverflow,
+    /// 背压模式下等待缓冲区腾出空间的 `poll` 任务
+    poll_waker: Option<Waker>,
+    dropped_inbound_streams: u64,
 }
 
 impl<C> Muxer<C>
@@ -26,13 +32,25 @@ where
     pub fn new(connection: Connection<C>) -> Self {
         Muxer {
             connection,
-            inbound_stream_buffer: VecDeque::with_capacity(MAX_BUFFERED_INBOUND_STREAMS),
+            inbound_stream_buffer: VecDeque::new(),
             inbound_stream_waker: None,
+            max_buffered_inbound_streams: DEFAULT_MAX_BUFFERED_INBOUND_STREAMS,
+            inbound_overflow: InboundOverflow::default(),
+            poll_waker: None,
+            dropped_inbound_streams: 0,
         }
     }
+
+    /// 设置入站子流缓冲区的上限及溢出时的处理方式
+    pub fn with_inbound_buffer(mut self, max: usize, overflow: InboundOverflow) -> Self {
+        self.max_buffered_inbound_streams = max;
+        self.inbound_overflow = overflow;
+        self
+    }
 }
 
-const MAX_BUFFERED_INBOUND_STREAMS: usize = 256;
+const DEFAULT_MAX_BUFFERED_INBOUND_STREAMS: usize = 256;
+const PROTOCOL_NAME: &str = "/v1/yamux";
 
 impl<C> StreamMuxer for Muxer<C>
 where
@@ -46,6 +64,9 @@ where
         cx: &mut Context<'_>,
     ) -> Poll<Result<Self::Substream, Self::Error>> {
         if let Some(stream) = self.inbound_stream_buffer.pop_front() {
+            if let Some(waker) = self.poll_waker.take() {
+                waker.wake();
+            }
             return Poll::Ready(Ok(stream));
         }
         self.inbound_stream_waker = Some(cx.waker().clone());
@@ -61,10 +82,17 @@ where
 
     fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
         let mut this = self.as_mut();
+        let full = this.inbound_stream_buffer.len() >= this.max_buffered_inbound_streams;
+        if full && this.inbound_overflow == InboundOverflow::Backpressure {
+            this.poll_waker = Some(cx.waker().clone());
+            return Poll::Pending;
+        }
+        // 对端关闭连接时以 `UnexpectedEof` 报告，便于上层与其他错误区分
         let inbound_stream = ready!(this.connection.poll_next_inbound(cx))
-            .ok_or_else(|| ConnectionError::Closed)??;
+            .ok_or_else(|| ConnectionError::Io(io::ErrorKind::UnexpectedEof.into()))??;
 
-        if this.inbound_stream_buffer.len() >= MAX_BUFFERED_INBOUND_STREAMS {
+        if full {
+            this.dropped_inbound_streams += 1;
             tracing::warn!(
                 "Inbound stream buffer is full, dropping stream: {}",
                 inbound_stream.id()
@@ -85,20 +113,159 @@ where
     fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
         self.as_mut().connection.poll_close(cx)
     }
-}
 
-#[derive(Debug, Clone)]
-pub struct UpgradeConfig(Config);
+    fn protocol(&self) -> Option<&'static str> {
+        Some(PROTOCOL_NAME)
+    }
 
-impl From<Config> for UpgradeConfig {
-    fn from(config: Config) -> Self {
-        UpgradeConfig(config)
+    fn dropped_inbound_streams(&self) -> u64 {
+        self.dropped_inbound_streams
     }
 }
 
+/// 每个子流的默认接收窗口，yamux 要求连接接收窗口不小于 `max_num_streams` 倍该值
+const DEFAULT_STREAM_WINDOW: usize = 256 * 1024;
+
+/// yamux 升级配置
+///
+/// yamux 0.13 会根据往返时延自动调整子流窗口，因此只能限制整个连接的接收窗口。
+#[derive(Debug, Clone)]
+pub struct UpgradeConfig {
+    max_connection_receive_window: Option<usize>,
+    max_num_streams: usize,
+    read_after_close: bool,
+    split_send_size: usize,
+    max_buffered_inbound_streams: usize,
+    inbound_overflow: InboundOverflow,
+}
+
 impl Default for UpgradeConfig {
     fn default() -> Self {
-        UpgradeConfig(Config::default())
+        Self {
+            max_connection_receive_window: Some(1024 * 1024 * 1024),
+            max_num_streams: 512,
+            read_after_close: true,
+            split_send_size: 16 * 1024,
+            max_buffered_inbound_streams: DEFAULT_MAX_BUFFERED_INBOUND_STREAMS,
+            inbound_overflow: InboundOverflow::default(),
+        }
+    }
+}
+
+impl UpgradeConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 连接内所有子流接收窗口之和的上限，`None` 表示不限制
+    pub fn with_max_connection_receive_window(mut self, size: Option<usize>) -> Self {
+        self.max_connection_receive_window = size;
+        self
+    }
+
+    /// 单个连接允许的最大子流数量
+    pub fn with_max_num_streams(mut self, count: usize) -> Self {
+        self.max_num_streams = count;
+        self
+    }
+
+    /// 连接关闭后是否允许读取已缓冲的数据
+    pub fn with_read_after_close(mut self, read_after_close: bool) -> Self {
+        self.read_after_close = read_after_close;
+        self
+    }
+
+    /// 单个数据帧的最大负载，更大的写入会被拆分
+    pub fn with_split_send_size(mut self, size: usize) -> Self {
+        self.split_send_size = size;
+        self
+    }
+
+    /// 等待上层取走的入站子流上限
+    pub fn with_max_buffered_inbound_streams(mut self, max: usize) -> Self {
+        self.max_buffered_inbound_streams = max;
+        self
+    }
+
+    /// 入站子流缓冲区已满时的处理方式，默认丢弃
+    pub fn with_inbound_overflow(mut self, overflow: InboundOverflow) -> Self {
+        self.inbound_overflow = overflow;
+        self
+    }
+
+    pub fn max_connection_receive_window(&self) -> Option<usize> {
+        self.max_connection_receive_window
+    }
+
+    pub fn max_num_streams(&self) -> usize {
+        self.max_num_streams
+    }
+
+    pub fn read_after_close(&self) -> bool {
+        self.read_after_close
+    }
+
+    pub fn split_send_size(&self) -> usize {
+        self.split_send_size
+    }
+
+    pub fn max_buffered_inbound_streams(&self) -> usize {
+        self.max_buffered_inbound_streams
+    }
+
+    pub fn inbound_overflow(&self) -> InboundOverflow {
+        self.inbound_overflow
+    }
+
+    fn to_yamux_config(&self) -> Config {
+        let min_window = self.max_num_streams.saturating_mul(DEFAULT_STREAM_WINDOW);
+        let window = self.max_connection_receive_window.map(|window| {
+            if window < min_window {
+                tracing::warn!(
+                    "Yamux receive window {} is below {} required by {} streams, raising it",
+                    window,
+                    min_window,
+                    self.max_num_streams
+                );
+            }
+            window.max(min_window)
+        });
+        let mut config = Config::default();
+        // yamux 在每次设置时校验窗口与子流数量，先解除窗口限制
+        config
+            .set_max_connection_receive_window(None)
+            .set_max_num_streams(self.max_num_streams)
+            .set_max_connection_receive_window(window)
+            .set_read_after_close(self.read_after_close)
+            .set_split_send_size(self.split_send_size);
+        config
+    }
+
+    fn new_muxer<C>(&self, socket: C, mode: Mode) -> Muxer<C>
+    where
+        C: AsyncRead + AsyncWrite + Unpin + 'static,
+    {
+        let connection = Connection::new(socket, self.to_yamux_config(), mode);
+        Muxer::new(connection)
+            .with_inbound_buffer(self.max_buffered_inbound_streams, self.inbound_overflow)
+    }
+}
+
+impl fmt::Display for UpgradeConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "yamux(max_num_streams: {}, ", self.max_num_streams)?;
+        match self.max_connection_receive_window {
+            Some(window) => write!(f, "max_connection_receive_window: {window}, ")?,
+            None => write!(f, "max_connection_receive_window: unlimited, ")?,
+        }
+        write!(
+            f,
+            "split_send_size: {}, read_after_close: {}, max_buffered_inbound_streams: {}, inbound_overflow: {:?})",
+            self.split_send_size,
+            self.read_after_close,
+            self.max_buffered_inbound_streams,
+            self.inbound_overflow
+        )
     }
 }
 
@@ -107,7 +274,7 @@ impl UpgradeInfo for UpgradeConfig {
     type InfoIter = iter::Once<Self::Info>;
 
     fn protocol_info(&self) -> Self::InfoIter {
-        iter::once("/v1/yamux")
+        iter::once(PROTOCOL_NAME)
     }
 }
 
@@ -120,8 +287,7 @@ where
     type Future = future::Ready<Result<Self::Output, Self::Error>>;
 
     fn upgrade_inbound(self, socket: C, _: Self::Info) -> Self::Future {
-        let connection = Connection::new(socket, self.0, Mode::Client);
-        future::ready(Ok(Muxer::new(connection)))
+        future::ready(Ok(self.new_muxer(socket, Mode::Client)))
     }
 }
 
@@ -134,7 +300,6 @@ where
     type Future = future::Ready<Result<Self::Output, Self::Error>>;
 
     fn upgrade_outbound(self, socket: C, _: Self::Info) -> Self::Future {
-        let connection = Connection::new(socket, self.0, Mode::Server);
-        future::ready(Ok(Muxer::new(connection)))
+        future::ready(Ok(self.new_muxer(socket, Mode::Server)))
     }
 }
@@ -0,0 +1,155 @@
+use std::{task::Poll, time::Duration};
+
+use futures::{AsyncReadExt, AsyncWriteExt, StreamExt, channel::mpsc, future};
+use tokio_util::compat::{Compat, TokioAsyncReadCompatExt};
+use volans_core::{
+    StreamMuxer,
+    muxing::StreamMuxerExt,
+    upgrade::{InboundConnectionUpgrade, OutboundConnectionUpgrade},
+};
+use volans_yamux::{ConnectionError, InboundOverflow, Muxer, Stream, UpgradeConfig};
+
+const PROTOCOL: &str = "/v1/yamux";
+
+type Io = Compat<tokio::io::DuplexStream>;
+
+async fn muxers(dialer: UpgradeConfig, listener: UpgradeConfig) -> (Muxer<Io>, Muxer<Io>) {
+    let (a, b) = tokio::io::duplex(64 * 1024);
+    let (dialer, listener) = future::join(
+        dialer.upgrade_outbound(a.compat(), PROTOCOL),
+        listener.upgrade_inbound(b.compat(), PROTOCOL),
+    )
+    .await;
+    (dialer.unwrap(), listener.unwrap())
+}
+
+async fn open(muxer: &mut Muxer<Io>) -> Result<Stream, ConnectionError> {
+    future::poll_fn(|cx| muxer.poll_outbound_unpin(cx)).await
+}
+
+/// 在后台驱动连接，收到的入站子流从返回的通道取出
+fn drive(mut muxer: Muxer<Io>) -> mpsc::UnboundedReceiver<Stream> {
+    let (sender, receiver) = mpsc::unbounded();
+    tokio::spawn(future::poll_fn(move |cx| {
+        if let Poll::Ready(Err(_)) = muxer.poll_unpin(cx) {
+            return Poll::Ready(());
+        }
+        while let Poll::Ready(stream) = muxer.poll_inbound_unpin(cx) {
+            let _ = sender.unbounded_send(stream.unwrap());
+        }
+        Poll::Pending
+    }));
+    receiver
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn max_num_streams_limits_outbound() {
+    let (mut dialer, _listener) = muxers(
+        UpgradeConfig::new().with_max_num_streams(2),
+        UpgradeConfig::new(),
+    )
+    .await;
+    let _first = open(&mut dialer).await.unwrap();
+    let _second = open(&mut dialer).await.unwrap();
+    assert!(matches!(
+        open(&mut dialer).await,
+        Err(ConnectionError::TooManyStreams)
+    ));
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn transfer_split_into_small_frames() {
+    const LEN: usize = 64 * 1024;
+
+    let (mut dialer, listener) = muxers(
+        UpgradeConfig::new().with_split_send_size(1024),
+        UpgradeConfig::new(),
+    )
+    .await;
+    let mut stream = open(&mut dialer).await.unwrap();
+    drive(dialer);
+    let mut incoming = drive(listener);
+
+    let data: Vec<u8> = (0..LEN).map(|i| i as u8).collect();
+    stream.write_all(&data).await.unwrap();
+    stream.flush().await.unwrap();
+
+    let mut inbound = incoming.next().await.unwrap();
+    let mut buf = vec![0; LEN];
+    inbound.read_exact(&mut buf).await.unwrap();
+    assert_eq!(buf, data);
+}
+
+/// 打开 `count` 个子流并各写入一个字节，使对端收到子流
+async fn open_streams(mut dialer: Muxer<Io>, count: usize) -> Vec<Stream> {
+    let mut streams = Vec::new();
+    for _ in 0..count {
+        streams.push(open(&mut dialer).await.unwrap());
+    }
+    drive(dialer);
+    for stream in &mut streams {
+        stream.write_all(&[1]).await.unwrap();
+        stream.flush().await.unwrap();
+    }
+    streams
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn full_inbound_buffer_drops_streams() {
+    let (dialer, mut listener) = muxers(
+        UpgradeConfig::new(),
+        UpgradeConfig::new()
+            .with_max_buffered_inbound_streams(1)
+            .with_inbound_overflow(InboundOverflow::Drop),
+    )
+    .await;
+    let _streams = open_streams(dialer, 3).await;
+
+    // 只驱动连接而不取走子流，超出缓冲区的子流被丢弃并计数
+    let dropped = future::poll_fn(|cx| {
+        let _ = listener.poll_unpin(cx);
+        match listener.dropped_inbound_streams() {
+            2 => Poll::Ready(()),
+            _ => Poll::Pending,
+        }
+    });
+    tokio::time::timeout(Duration::from_secs(5), dropped)
+        .await
+        .unwrap();
+    let first = future::poll_fn(|cx| listener.poll_inbound_unpin(cx)).await;
+    assert!(first.is_ok());
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn full_inbound_buffer_applies_backpressure() {
+    let (dialer, mut listener) = muxers(
+        UpgradeConfig::new(),
+        UpgradeConfig::new()
+            .with_max_buffered_inbound_streams(1)
+            .with_inbound_overflow(InboundOverflow::Backpressure),
+    )
+    .await;
+    let _streams = open_streams(dialer, 3).await;
+
+    // 缓冲区已满时暂停读取连接而不是丢弃子流
+    let drive_only = future::poll_fn(|cx| listener.poll_unpin(cx));
+    assert!(
+        tokio::time::timeout(Duration::from_millis(100), drive_only)
+            .await
+            .is_err()
+    );
+    assert_eq!(listener.dropped_inbound_streams(), 0);
+
+    // 取走缓冲的子流后继续读取，所有子流都能收到
+    for _ in 0..3 {
+        let inbound = future::poll_fn(|cx| {
+            let _ = listener.poll_unpin(cx);
+            listener.poll_inbound_unpin(cx)
+        });
+        tokio::time::timeout(Duration::from_secs(5), inbound)
+            .await
+            .unwrap()
+            .unwrap();
+    }
+    assert_eq!(listener.dropped_inbound_streams(), 0);
+}
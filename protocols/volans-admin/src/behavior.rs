@@ -0,0 +1,290 @@
+use std::{
+    collections::{BTreeMap, HashMap, HashSet, VecDeque},
+    task::{Context, Poll, Waker},
+};
+
+use volans_core::{Multiaddr, PeerId};
+use volans_request::{Responder, server};
+use volans_swarm::{
+    BandwidthStats, BehaviorEvent, ConnectionDenied, ConnectionId, DialOpts, ListenerEvent,
+    NetworkBehavior, NetworkIncomingBehavior, NetworkOutgoingBehavior, THandlerAction,
+    THandlerEvent,
+    behavior::{CloseConnection, ListenAddresses},
+    error::CloseReason,
+    handler::InboundOnlyHandler,
+};
+
+use crate::{
+    Codec, Config, ConnectionStatus, Direction, NodeStatus, PROTOCOL_NAME, ProtocolStats,
+    REJECT_UNAUTHORIZED, Request, Response,
+};
+
+#[derive(Debug)]
+pub enum Event {
+    /// 授权节点下发的命令，`BlockPeer` 需要应用写入阻止列表，如 `volans-allow-block-list`
+    Command { peer_id: PeerId, request: Request },
+    /// 未授权节点的请求被拒绝
+    Unauthorized { peer_id: PeerId },
+}
+
+/// 应答管理请求的行为
+///
+/// 管理请求只在入站连接上处理。拨号命令需要节点运行在 duplex Swarm 中，
+/// 只实现入站行为的节点会回复拨号命令，但不会发起拨号。
+pub struct Behavior {
+    local_peer_id: PeerId,
+    authorized_peers: HashSet<PeerId>,
+    inner: server::Behavior<Codec>,
+    listen_addrs: ListenAddresses,
+    connections: HashMap<ConnectionId, ConnectionStatus>,
+    protocols: BTreeMap<String, ProtocolStats>,
+    pending_dials: VecDeque<DialOpts>,
+    events: VecDeque<BehaviorEvent<Event, THandlerAction<Self>>>,
+    dial_waker: Option<Waker>,
+}
+
+impl Behavior {
+    pub fn new(local_peer_id: PeerId, config: Config) -> Self {
+        let request_config =
+            volans_request::Config::default().with_request_timeout(config.request_timeout);
+        Self {
+            local_peer_id,
+            authorized_peers: config.authorized_peers,
+            inner: server::Behavior::with_codec(Codec::new(), [PROTOCOL_NAME], request_config),
+            listen_addrs: ListenAddresses::new(),
+            connections: HashMap::new(),
+            protocols: BTreeMap::new(),
+            pending_dials: VecDeque::new(),
+            events: VecDeque::new(),
+            dial_waker: None,
+        }
+    }
+
+    /// 允许节点发送管理请求，节点已被允许时返回 `false`
+    pub fn authorize(&mut self, peer_id: PeerId) -> bool {
+        self.authorized_peers.insert(peer_id)
+    }
+
+    /// 撤销节点的授权，之后的请求被拒绝
+    pub fn revoke(&mut self, peer_id: &PeerId) -> bool {
+        self.authorized_peers.remove(peer_id)
+    }
+
+    /// 更新状态中按子流协议统计的流量，通常来自 Swarm 的 `bandwidth().by_stream_protocol()`
+    pub fn update_protocol_stats<I>(&mut self, stats: I)
+    where
+        I: IntoIterator<Item = (String, BandwidthStats)>,
+    {
+        self.protocols = stats
+            .into_iter()
+            .map(|(protocol, stats)| (protocol, stats.into()))
+            .collect();
+    }
+
+    /// 当前的节点状态
+    pub fn status(&self) -> NodeStatus {
+        let peers: HashSet<_> = self.connections.values().map(|c| c.peer_id).collect();
+        NodeStatus {
+            peer_id: self.local_peer_id,
+            listen_addrs: self.listen_addrs.iter().cloned().collect(),
+            peers: peers.into_iter().collect(),
+            connections: self.connections.values().cloned().collect(),
+            protocols: self.protocols.clone(),
+        }
+    }
+
+    fn on_request(&mut self, peer_id: PeerId, request: Request, responder: Responder<Response>) {
+        if !self.authorized_peers.contains(&peer_id) {
+            tracing::debug!(%peer_id, "Rejecting unauthorized admin request");
+            let _ = responder.reject(REJECT_UNAUTHORIZED);
+            self.events
+                .push_back(BehaviorEvent::Behavior(Event::Unauthorized { peer_id }));
+            return;
+        }
+        let response = match &request {
+            Request::Status => {
+                let _ = responder.send_response(Response::Status(self.status()));
+                return;
+            }
+            Request::Dial { peer_id, addresses } => {
+                if addresses.is_empty() && peer_id.is_none() {
+                    Response::Error {
+                        message: "no peer or address to dial".to_string(),
+                    }
+                } else {
+                    self.pending_dials
+                        .push_back(DialOpts::new(None, *peer_id).with_addresses(addresses.clone()));
+                    if let Some(waker) = self.dial_waker.take() {
+                        waker.wake();
+                    }
+                    Response::Ok
+                }
+            }
+            Request::Disconnect { peer_id } => {
+                if self.connections.values().any(|c| c.peer_id == *peer_id) {
+                    self.events.push_back(BehaviorEvent::CloseConnection {
+                        peer_id: *peer_id,
+                        connection: CloseConnection::All,
+                    });
+                    Response::Ok
+                } else {
+                    Response::Error {
+                        message: format!("peer {peer_id} is not connected"),
+                    }
+                }
+            }
+            Request::BlockPeer { .. } => Response::Ok,
+        };
+        let accepted = response == Response::Ok;
+        let _ = responder.send_response(response);
+        if accepted {
+            self.events
+                .push_back(BehaviorEvent::Behavior(Event::Command { peer_id, request }));
+        }
+    }
+}
+
+impl NetworkBehavior for Behavior {
+    type ConnectionHandler = InboundOnlyHandler<server::Handler<Codec>>;
+    type Event = Event;
+
+    fn on_connection_handler_event(
+        &mut self,
+        id: ConnectionId,
+        peer_id: PeerId,
+        event: THandlerEvent<Self>,
+    ) {
+        self.inner.on_connection_handler_event(id, peer_id, event);
+    }
+
+    fn poll(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<BehaviorEvent<Self::Event, THandlerAction<Self>>> {
+        loop {
+            if let Some(event) = self.events.pop_front() {
+                return Poll::Ready(event);
+            }
+            match self.inner.poll(cx) {
+                Poll::Ready(BehaviorEvent::Behavior(server::Event::Request {
+                    peer_id,
+                    request,
+                    responder,
+                    ..
+                })) => self.on_request(peer_id, request, responder),
+                Poll::Ready(BehaviorEvent::Behavior(server::Event::Failure {
+                    peer_id,
+                    cause,
+                    ..
+                })) => {
+                    tracing::debug!(%peer_id, "Admin request failed: {}", cause);
+                }
+                Poll::Ready(BehaviorEvent::Behavior(server::Event::ResponseSent { .. })) => {}
+                Poll::Ready(event) => {
+                    return Poll::Ready(event.map_event(|_| unreachable!("handled above")));
+                }
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
+
+impl NetworkIncomingBehavior for Behavior {
+    fn handle_established_connection(
+        &mut self,
+        id: ConnectionId,
+        peer_id: PeerId,
+        local_addr: &Multiaddr,
+        remote_addr: &Multiaddr,
+    ) -> Result<Self::ConnectionHandler, ConnectionDenied> {
+        let handler = NetworkIncomingBehavior::handle_established_connection(
+            &mut self.inner,
+            id,
+            peer_id,
+            local_addr,
+            remote_addr,
+        )?;
+        Ok(InboundOnlyHandler::new(handler))
+    }
+
+    fn on_connection_established(
+        &mut self,
+        id: ConnectionId,
+        peer_id: PeerId,
+        _local_addr: &Multiaddr,
+        remote_addr: &Multiaddr,
+    ) {
+        self.connections.insert(
+            id,
+            ConnectionStatus {
+                peer_id,
+                direction: Direction::Inbound,
+                remote_addr: remote_addr.clone(),
+            },
+        );
+    }
+
+    fn on_connection_closed(
+        &mut self,
+        id: ConnectionId,
+        _peer_id: PeerId,
+        _local_addr: &Multiaddr,
+        _remote_addr: &Multiaddr,
+        _reason: &CloseReason,
+    ) {
+        self.connections.remove(&id);
+    }
+
+    fn on_listener_event(&mut self, event: ListenerEvent<'_>) {
+        self.listen_addrs.on_listener_event(&event);
+    }
+}
+
+impl NetworkOutgoingBehavior for Behavior {
+    fn handle_pending_connection(
+        &mut self,
+        _id: ConnectionId,
+        _maybe_peer: Option<PeerId>,
+        addr: &Option<Multiaddr>,
+    ) -> Result<Option<Multiaddr>, ConnectionDenied> {
+        Ok(addr.clone())
+    }
+
+    fn handle_established_connection(
+        &mut self,
+        _id: ConnectionId,
+        _peer_id: PeerId,
+        _addr: &Multiaddr,
+    ) -> Result<Self::ConnectionHandler, ConnectionDenied> {
+        Ok(InboundOnlyHandler::disabled())
+    }
+
+    fn on_connection_established(&mut self, id: ConnectionId, peer_id: PeerId, addr: &Multiaddr) {
+        self.connections.insert(
+            id,
+            ConnectionStatus {
+                peer_id,
+                direction: Direction::Outbound,
+                remote_addr: addr.clone(),
+            },
+        );
+    }
+
+    fn on_connection_closed(
+        &mut self,
+        id: ConnectionId,
+        _peer_id: PeerId,
+        _addr: &Multiaddr,
+        _reason: &CloseReason,
+    ) {
+        self.connections.remove(&id);
+    }
+
+    fn poll_dial(&mut self, cx: &mut Context<'_>) -> Poll<DialOpts> {
+        if let Some(opts) = self.pending_dials.pop_front() {
+            return Poll::Ready(opts);
+        }
+        self.dial_waker = Some(cx.waker().clone());
+        Poll::Pending
+    }
+}
@@ -0,0 +1,151 @@
+//! 节点管理协议
+//!
+//! 运行中的节点加入 [`Behavior`] 后，授权的管理端可以通过 `/v1/admin` 请求查询节点状态
+//! （监听地址、已连接节点、连接与按子流协议统计的流量），并下发拨号、断开与阻止节点的命令。
+//! 请求与响应使用 JSON 编码，管理端使用 [`Client`] 发送 [`Request`]。
+
+mod behavior;
+
+pub use behavior::{Behavior, Event};
+
+use std::{
+    collections::{BTreeMap, HashSet},
+    time::Duration,
+};
+
+use serde::{Deserialize, Serialize};
+use volans_core::{Multiaddr, PeerId};
+use volans_request::codec::JsonCodec;
+use volans_swarm::{BandwidthStats, StreamProtocol};
+
+pub const PROTOCOL_NAME: StreamProtocol = StreamProtocol::new("/v1/admin");
+
+/// 未授权节点的请求使用的拒绝码
+pub const REJECT_UNAUTHORIZED: u32 = 403;
+
+pub type Codec = JsonCodec<Request, Response>;
+
+/// 管理端使用的请求行为，以 [`PROTOCOL_NAME`] 发送 [`Request`]
+pub type Client = volans_request::client::Behavior<Codec>;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "command", rename_all = "snake_case")]
+pub enum Request {
+    /// 查询节点状态
+    Status,
+    /// 拨号节点，按顺序尝试给出的地址
+    Dial {
+        peer_id: Option<PeerId>,
+        addresses: Vec<Multiaddr>,
+    },
+    /// 关闭与节点的所有连接
+    Disconnect { peer_id: PeerId },
+    /// 阻止节点，由应用写入阻止列表
+    BlockPeer { peer_id: PeerId },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "result", rename_all = "snake_case")]
+pub enum Response {
+    Status(NodeStatus),
+    /// 命令已接受，拨号的结果不会回复
+    Ok,
+    Error {
+        message: String,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NodeStatus {
+    pub peer_id: PeerId,
+    pub listen_addrs: Vec<Multiaddr>,
+    pub peers: Vec<PeerId>,
+    pub connections: Vec<ConnectionStatus>,
+    /// 按子流协议统计的流量，由应用通过 [`Behavior::update_protocol_stats`] 更新
+    pub protocols: BTreeMap<String, ProtocolStats>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ConnectionStatus {
+    pub peer_id: PeerId,
+    pub direction: Direction,
+    pub remote_addr: Multiaddr,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Direction {
+    Inbound,
+    Outbound,
+}
+
+/// 收发的字节数
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProtocolStats {
+    pub inbound: u64,
+    pub outbound: u64,
+}
+
+impl From<BandwidthStats> for ProtocolStats {
+    fn from(stats: BandwidthStats) -> Self {
+        Self {
+            inbound: stats.inbound,
+            outbound: stats.outbound,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Config {
+    authorized_peers: HashSet<PeerId>,
+    request_timeout: Duration,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            authorized_peers: HashSet::new(),
+            request_timeout: Duration::from_secs(30),
+        }
+    }
+}
+
+impl Config {
+    /// 允许发送管理请求的节点，默认为空，拒绝所有请求
+    pub fn with_authorized_peers(mut self, peers: impl IntoIterator<Item = PeerId>) -> Self {
+        self.authorized_peers = peers.into_iter().collect();
+        self
+    }
+
+    pub fn with_request_timeout(mut self, timeout: Duration) -> Self {
+        self.request_timeout = timeout;
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn json_encoding() {
+        let peer_id = PeerId::random();
+        let request = Request::Disconnect { peer_id };
+        let json = serde_json::to_value(&request).unwrap();
+        assert_eq!(json["command"], "disconnect");
+        assert_eq!(serde_json::from_value::<Request>(json).unwrap(), request);
+
+        let json = serde_json::to_value(Request::Status).unwrap();
+        assert_eq!(json, serde_json::json!({ "command": "status" }));
+
+        let response = Response::Status(NodeStatus {
+            peer_id,
+            listen_addrs: vec!["/ip4/127.0.0.1/tcp/4001".parse().unwrap()],
+            peers: Vec::new(),
+            connections: Vec::new(),
+            protocols: BTreeMap::from([("/v1/ping".to_string(), ProtocolStats::default())]),
+        });
+        let json = serde_json::to_vec(&response).unwrap();
+        assert_eq!(serde_json::from_slice::<Response>(&json).unwrap(), response);
+    }
+}
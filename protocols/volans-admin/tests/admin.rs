@@ -0,0 +1,64 @@
+use futures::StreamExt;
+use volans_core::PeerId;
+use volans_swarm::{client, duplex};
+use volans_swarm_test::{SwarmExt, connect};
+
+#[tokio::test(flavor = "current_thread")]
+async fn admin_status_and_authorization() {
+    use volans_admin::{Client, Codec, PROTOCOL_NAME, REJECT_UNAUTHORIZED, Request, Response};
+    use volans_request::{Config, OutboundFailure};
+
+    let mut admin =
+        client::Swarm::new_ephemeral(|_| Client::with_codec(Codec::new(), Config::default()));
+    let mut intruder =
+        client::Swarm::new_ephemeral(|_| Client::with_codec(Codec::new(), Config::default()));
+    let admin_peer = *admin.local_peer_id();
+    let mut node = duplex::Swarm::new_ephemeral(|key_pair| {
+        let config = volans_admin::Config::default().with_authorized_peers([admin_peer]);
+        volans_admin::Behavior::new(PeerId::from_public_key(&key_pair.verifying_key()), config)
+    });
+    connect(&mut admin, &mut node).await;
+    connect(&mut intruder, &mut node).await;
+
+    let node_peer = *node.local_peer_id();
+    let admin = spawn_with_control(admin);
+    let intruder = spawn_with_control(intruder);
+    tokio::spawn(async move {
+        loop {
+            node.next().await;
+        }
+    });
+
+    let response = admin
+        .request(node_peer, PROTOCOL_NAME, Request::Status)
+        .await
+        .unwrap();
+    let Response::Status(status) = response else {
+        panic!("unexpected response: {response:?}");
+    };
+    assert_eq!(status.peer_id, node_peer);
+    assert!(status.peers.contains(&admin_peer));
+    assert_eq!(status.connections.len(), 2);
+    assert!(!status.listen_addrs.is_empty());
+
+    let error = intruder
+        .request(node_peer, PROTOCOL_NAME, Request::Status)
+        .await
+        .unwrap_err();
+    assert!(matches!(
+        error,
+        OutboundFailure::Rejected(REJECT_UNAUTHORIZED)
+    ));
+
+    fn spawn_with_control(
+        mut swarm: client::Swarm<Client>,
+    ) -> volans_request::client::Control<Codec> {
+        let control = swarm.behavior().control();
+        tokio::spawn(async move {
+            loop {
+                swarm.next().await;
+            }
+        });
+        control
+    }
+}
@@ -0,0 +1,211 @@
+//! 节点允许/阻止列表
+//!
+//! `Behavior<AllowedPeers>` 只接受列表中的节点，`Behavior<BlockedPeers>` 拒绝列表中的节点。
+//! 入站连接在身份确认后检查，出站连接在拨号前（已知 `PeerId` 时）及建立后检查。
+
+use std::{
+    collections::{HashSet, VecDeque},
+    convert::Infallible,
+    task::{Context, Poll, Waker},
+};
+
+use volans_core::{Multiaddr, PeerId};
+use volans_swarm::{
+    BehaviorEvent, ConnectionDenied, ConnectionId, NetworkBehavior, NetworkIncomingBehavior,
+    NetworkOutgoingBehavior, THandlerAction, THandlerEvent, behavior::CloseConnection,
+    handler::DummyHandler,
+};
+
+/// 允许列表，未在列表中的节点均被拒绝
+#[derive(Debug, Clone, Default)]
+pub struct AllowedPeers {
+    peers: HashSet<PeerId>,
+}
+
+/// 阻止列表，列表中的节点均被拒绝
+#[derive(Debug, Clone, Default)]
+pub struct BlockedPeers {
+    peers: HashSet<PeerId>,
+}
+
+/// 节点不在允许列表中
+#[derive(Debug, thiserror::Error)]
+#[error("Peer {peer} is not in the allow list")]
+pub struct NotAllowed {
+    peer: PeerId,
+}
+
+impl NotAllowed {
+    pub fn peer(&self) -> &PeerId {
+        &self.peer
+    }
+}
+
+/// 节点在阻止列表中
+#[derive(Debug, thiserror::Error)]
+#[error("Peer {peer} is in the block list")]
+pub struct Blocked {
+    peer: PeerId,
+}
+
+impl Blocked {
+    pub fn peer(&self) -> &PeerId {
+        &self.peer
+    }
+}
+
+pub trait Enforce: Send + 'static {
+    fn enforce(&self, peer: &PeerId) -> Result<(), ConnectionDenied>;
+}
+
+impl Enforce for AllowedPeers {
+    fn enforce(&self, peer: &PeerId) -> Result<(), ConnectionDenied> {
+        if self.peers.contains(peer) {
+            Ok(())
+        } else {
+            Err(ConnectionDenied::new(NotAllowed { peer: *peer }))
+        }
+    }
+}
+
+impl Enforce for BlockedPeers {
+    fn enforce(&self, peer: &PeerId) -> Result<(), ConnectionDenied> {
+        if self.peers.contains(peer) {
+            Err(ConnectionDenied::new(Blocked { peer: *peer }))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Behavior<S> {
+    state: S,
+    close_connections: VecDeque<PeerId>,
+    waker: Option<Waker>,
+}
+
+impl<S> Behavior<S> {
+    fn close_peer(&mut self, peer: PeerId) {
+        self.close_connections.push_back(peer);
+        if let Some(waker) = self.waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+impl Behavior<AllowedPeers> {
+    /// 已允许的节点
+    pub fn allowed_peers(&self) -> &HashSet<PeerId> {
+        &self.state.peers
+    }
+
+    /// 允许节点连接，返回该节点之前是否未被允许
+    pub fn allow_peer(&mut self, peer: PeerId) -> bool {
+        self.state.peers.insert(peer)
+    }
+
+    /// 取消允许，并关闭与该节点的现有连接
+    pub fn disallow_peer(&mut self, peer: PeerId) -> bool {
+        let removed = self.state.peers.remove(&peer);
+        if removed {
+            self.close_peer(peer);
+        }
+        removed
+    }
+}
+
+impl Behavior<BlockedPeers> {
+    /// 已阻止的节点
+    pub fn blocked_peers(&self) -> &HashSet<PeerId> {
+        &self.state.peers
+    }
+
+    /// 阻止节点连接，并关闭与该节点的现有连接
+    pub fn block_peer(&mut self, peer: PeerId) -> bool {
+        let inserted = self.state.peers.insert(peer);
+        if inserted {
+            self.close_peer(peer);
+        }
+        inserted
+    }
+
+    /// 解除阻止，返回该节点之前是否被阻止
+    pub fn unblock_peer(&mut self, peer: PeerId) -> bool {
+        self.state.peers.remove(&peer)
+    }
+}
+
+impl<S> NetworkBehavior for Behavior<S>
+where
+    S: Enforce,
+{
+    type ConnectionHandler = DummyHandler;
+    type Event = Infallible;
+
+    fn on_connection_handler_event(
+        &mut self,
+        _id: ConnectionId,
+        _peer_id: PeerId,
+        event: THandlerEvent<Self>,
+    ) {
+        unreachable!("Unexpected event: {:?}", event);
+    }
+
+    fn poll(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<BehaviorEvent<Self::Event, THandlerAction<Self>>> {
+        if let Some(peer_id) = self.close_connections.pop_front() {
+            return Poll::Ready(BehaviorEvent::CloseConnection {
+                peer_id,
+                connection: CloseConnection::All,
+            });
+        }
+        self.waker = Some(cx.waker().clone());
+        Poll::Pending
+    }
+}
+
+impl<S> NetworkIncomingBehavior for Behavior<S>
+where
+    S: Enforce,
+{
+    fn handle_established_connection(
+        &mut self,
+        _id: ConnectionId,
+        peer_id: PeerId,
+        _local_addr: &Multiaddr,
+        _remote_addr: &Multiaddr,
+    ) -> Result<Self::ConnectionHandler, ConnectionDenied> {
+        self.state.enforce(&peer_id)?;
+        Ok(DummyHandler)
+    }
+}
+
+impl<S> NetworkOutgoingBehavior for Behavior<S>
+where
+    S: Enforce,
+{
+    fn handle_pending_connection(
+        &mut self,
+        _id: ConnectionId,
+        maybe_peer: Option<PeerId>,
+        addr: &Option<Multiaddr>,
+    ) -> Result<Option<Multiaddr>, ConnectionDenied> {
+        if let Some(peer_id) = maybe_peer {
+            self.state.enforce(&peer_id)?;
+        }
+        Ok(addr.clone())
+    }
+
+    fn handle_established_connection(
+        &mut self,
+        _id: ConnectionId,
+        peer_id: PeerId,
+        _addr: &Multiaddr,
+    ) -> Result<Self::ConnectionHandler, ConnectionDenied> {
+        self.state.enforce(&peer_id)?;
+        Ok(DummyHandler)
+    }
+}
@@ -0,0 +1,76 @@
+use volans_core::PeerId;
+use volans_swarm::{DialOpts, client, error::DialError, server};
+use volans_swarm_test::{SwarmExt, connect, listen, next_swarm_event, unused_addr, wait_for_event};
+
+#[tokio::test(flavor = "current_thread")]
+async fn deny_blocked_peer() {
+    use volans_allow_block_list::{Behavior, Blocked, BlockedPeers};
+
+    let blocked = PeerId::random();
+    let mut dialer = client::Swarm::new_ephemeral(|_| Behavior::<BlockedPeers>::default());
+    dialer.behavior_mut().block_peer(blocked);
+
+    let error = dialer
+        .dial(DialOpts::new(Some(unused_addr()), Some(blocked)))
+        .unwrap_err();
+    assert_eq!(
+        error.denied_by::<Blocked>().map(Blocked::peer),
+        Some(&blocked)
+    );
+    let DialError::Denied { cause } = error else {
+        panic!("unexpected error: {error:?}");
+    };
+    assert!(cause.is::<Blocked>());
+    assert!(cause.downcast::<Blocked>().is_ok());
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn deny_incoming_peer_not_allowed() {
+    use volans_allow_block_list::{AllowedPeers, Behavior, BlockedPeers, NotAllowed};
+
+    let mut listener = server::Swarm::new_ephemeral(|_| Behavior::<AllowedPeers>::default());
+    let mut dialer = client::Swarm::new_ephemeral(|_| Behavior::<BlockedPeers>::default());
+    let dialer_peer = *dialer.local_peer_id();
+
+    let addr = listen(&mut listener).await;
+    dialer
+        .dial(DialOpts::new(Some(addr), Some(*listener.local_peer_id())))
+        .unwrap();
+    let error = loop {
+        tokio::select! {
+            event = next_swarm_event(&mut listener) => {
+                if let server::SwarmEvent::IncomingConnectionError { error, .. } = event {
+                    break error;
+                }
+            }
+            _ = next_swarm_event(&mut dialer) => {}
+        }
+    };
+    assert_eq!(
+        error.denied_by::<NotAllowed>().map(NotAllowed::peer),
+        Some(&dialer_peer)
+    );
+
+    // 允许之后可以建立连接
+    assert!(listener.behavior_mut().allow_peer(dialer_peer));
+    connect(&mut dialer, &mut listener).await;
+    assert!(listener.is_peer_connected(&dialer_peer));
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn block_peer_closes_connection() {
+    use volans_allow_block_list::{Behavior, BlockedPeers};
+
+    let mut listener = server::Swarm::new_ephemeral(|_| Behavior::<BlockedPeers>::default());
+    let mut dialer = client::Swarm::new_ephemeral(|_| Behavior::<BlockedPeers>::default());
+    let dialer_peer = *dialer.local_peer_id();
+    connect(&mut dialer, &mut listener).await;
+
+    assert!(listener.behavior_mut().block_peer(dialer_peer));
+    wait_for_event(&mut listener, |event| match event {
+        server::SwarmEvent::ConnectionClosed { peer_id, .. } if peer_id == dialer_peer => Some(()),
+        _ => None,
+    })
+    .await;
+    assert!(!listener.is_peer_connected(&dialer_peer));
+}
@@ -2,7 +2,7 @@ pub mod handler;
 
 pub mod behavior;
 
-pub use behavior::Behavior;
+pub use behavior::{Behavior, Event};
 
 use crate::transport;
 
@@ -1,23 +1,40 @@
 use std::{
-    collections::HashMap,
-    convert::Infallible,
+    collections::{HashMap, HashSet, VecDeque},
     task::{Context, Poll},
+    time::Duration,
 };
 
-use futures::{StreamExt, channel::mpsc};
+use futures::{FutureExt, StreamExt, channel::mpsc};
+use futures_timer::Delay;
 use volans_core::{Multiaddr, PeerId, multiaddr::Protocol};
 use volans_swarm::{
-    BehaviorEvent, ConnectionDenied, ConnectionId, NetworkBehavior, NetworkIncomingBehavior,
-    THandlerAction, THandlerEvent,
+    BehaviorEvent, ConnectionDenied, ConnectionId, DialOpts, NetworkBehavior,
+    NetworkIncomingBehavior, NetworkOutgoingBehavior, PeerCondition, THandlerAction, THandlerEvent,
+    error::{CloseReason, DialError},
 };
 
-use crate::transport::{Connection, IncomingRelayedConnection, TransportRequest};
+use crate::transport::{Connection, IncomingRelayedConnection, ListenerNotice, TransportRequest};
 
 use super::handler;
 
+/// 需要保持连接的中继
+struct Relay {
+    addr: Multiaddr,
+    connections: HashSet<ConnectionId>,
+    /// 下一次重连的等待时间
+    backoff: Duration,
+    redial: Option<Delay>,
+    /// 曾经连接过且当前已断开
+    lost: bool,
+}
+
 pub struct Behavior {
     transport_request_receiver: mpsc::Receiver<TransportRequest>,
-    listener: Option<mpsc::Sender<IncomingRelayedConnection>>,
+    listener: Option<mpsc::Sender<ListenerNotice>>,
+    relays: HashMap<PeerId, Relay>,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+    pending_events: VecDeque<BehaviorEvent<Event, THandlerAction<Self>>>,
 }
 
 impl Behavior {
@@ -25,13 +42,124 @@ impl Behavior {
         Self {
             transport_request_receiver,
             listener: None,
+            relays: HashMap::new(),
+            initial_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(60),
+            pending_events: VecDeque::new(),
+        }
+    }
+
+    /// 中继重连的退避时间，从 `initial` 起每次失败翻倍，不超过 `max`
+    pub fn with_reconnect_backoff(mut self, initial: Duration, max: Duration) -> Self {
+        self.initial_backoff = initial;
+        self.max_backoff = max.max(initial);
+        self
+    }
+
+    /// 连接中继并保持连接，断开后按退避时间重连
+    ///
+    /// 连接期间经由中继到达本地的地址加入 `/circuit` 监听器。
+    pub fn add_relay(&mut self, relay_peer_id: PeerId, relay_addr: Multiaddr) {
+        self.relays.insert(
+            relay_peer_id,
+            Relay {
+                addr: relay_addr,
+                connections: HashSet::new(),
+                backoff: self.initial_backoff,
+                redial: Some(Delay::new(Duration::ZERO)),
+                lost: false,
+            },
+        );
+    }
+
+    /// 不再保持与中继的连接，已有连接不受影响
+    pub fn remove_relay(&mut self, relay_peer_id: &PeerId) -> bool {
+        let Some(relay) = self.relays.remove(relay_peer_id) else {
+            return false;
+        };
+        if !relay.connections.is_empty() {
+            self.notify_listener(ListenerNotice::AddressExpired(circuit_addr(
+                &relay.addr,
+                *relay_peer_id,
+            )));
+        }
+        true
+    }
+
+    /// 当前已连接的中继
+    pub fn connected_relays(&self) -> impl Iterator<Item = &PeerId> {
+        self.relays
+            .iter()
+            .filter(|(_, relay)| !relay.connections.is_empty())
+            .map(|(peer_id, _)| peer_id)
+    }
+
+    fn on_relay_connected(&mut self, id: ConnectionId, peer_id: PeerId) {
+        let Some(relay) = self.relays.get_mut(&peer_id) else {
+            return;
+        };
+        relay.redial = None;
+        if !relay.connections.insert(id) || relay.connections.len() > 1 {
+            return;
+        }
+        relay.backoff = self.initial_backoff;
+        let addr = circuit_addr(&relay.addr, peer_id);
+        if std::mem::take(&mut relay.lost) {
+            tracing::info!(%peer_id, "Relay reconnected");
+            self.pending_events
+                .push_back(BehaviorEvent::Behavior(Event::RelayReconnected {
+                    relay_peer_id: peer_id,
+                }));
+        }
+        self.notify_listener(ListenerNotice::NewAddress(addr));
+    }
+
+    fn on_relay_closed(&mut self, id: ConnectionId, peer_id: PeerId) {
+        let Some(relay) = self.relays.get_mut(&peer_id) else {
+            return;
+        };
+        if !relay.connections.remove(&id) || !relay.connections.is_empty() {
+            return;
+        }
+        relay.lost = true;
+        let backoff = schedule_redial(relay, self.max_backoff);
+        let addr = circuit_addr(&relay.addr, peer_id);
+        tracing::warn!(%peer_id, "Relay connection lost, reconnecting in {:?}", backoff);
+        self.pending_events
+            .push_back(BehaviorEvent::Behavior(Event::RelayConnectionLost {
+                relay_peer_id: peer_id,
+                backoff,
+            }));
+        self.notify_listener(ListenerNotice::AddressExpired(addr));
+    }
+
+    /// 通知监听器，监听器已关闭时丢弃，等待新的监听请求
+    fn notify_listener(&mut self, notice: ListenerNotice) {
+        let Some(sender) = self.listener.as_mut() else {
+            return;
+        };
+        if let Err(e) = sender.try_send(notice) {
+            if e.is_disconnected() {
+                tracing::debug!("Circuit listener closed");
+                self.listener = None;
+            } else {
+                tracing::error!("Failed to notify circuit listener: {}", e);
+            }
         }
     }
 }
 
+/// 按当前退避时间安排重连并翻倍退避时间，返回本次的等待时间
+fn schedule_redial(relay: &mut Relay, max_backoff: Duration) -> Duration {
+    let backoff = relay.backoff;
+    relay.redial = Some(Delay::new(backoff));
+    relay.backoff = (backoff * 2).min(max_backoff);
+    backoff
+}
+
 impl NetworkBehavior for Behavior {
     type ConnectionHandler = handler::Handler;
-    type Event = Infallible;
+    type Event = Event;
 
     fn on_connection_handler_event(
         &mut self,
@@ -45,25 +173,21 @@ impl NetworkBehavior for Behavior {
             src_relayed_addr,
         }: THandlerEvent<Self>,
     ) {
-        match self.listener {
-            Some(ref mut sender) => {
-                let r = sender.try_send(IncomingRelayedConnection::new(
-                    Connection::new_accepting(circuit),
-                    src_peer_id,
-                    peer_id,
-                    src_relayed_addr,
-                ));
-                if let Err(e) = r {
-                    tracing::error!("Failed to send incoming relayed connection: {}", e);
-                }
-            }
-            None => {
-                tracing::warn!(
-                    "No listener found for remote address: {}",
-                    relay_remote_addr
-                );
-            }
+        if self.listener.is_none() {
+            tracing::warn!(
+                "No listener found for remote address: {}",
+                relay_remote_addr
+            );
+            return;
         }
+        self.notify_listener(ListenerNotice::Incoming(Box::new(
+            IncomingRelayedConnection::new(
+                Connection::new_accepting(circuit),
+                src_peer_id,
+                peer_id,
+                src_relayed_addr,
+            ),
+        )));
     }
 
     fn poll(
@@ -71,6 +195,9 @@ impl NetworkBehavior for Behavior {
         cx: &mut Context<'_>,
     ) -> Poll<BehaviorEvent<Self::Event, THandlerAction<Self>>> {
         loop {
+            if let Some(event) = self.pending_events.pop_front() {
+                return Poll::Ready(event);
+            }
             match self.transport_request_receiver.poll_next_unpin(cx) {
                 Poll::Ready(Some(TransportRequest::ListenRequest {
                     local_addr,
@@ -78,6 +205,16 @@ impl NetworkBehavior for Behavior {
                 })) => {
                     tracing::debug!("Circuit Listening on: {:?}", local_addr);
                     self.listener = Some(listener_sender);
+                    // 新的监听器接管已连接中继的地址
+                    let addrs = self
+                        .relays
+                        .iter()
+                        .filter(|(_, relay)| !relay.connections.is_empty())
+                        .map(|(peer_id, relay)| circuit_addr(&relay.addr, *peer_id))
+                        .collect::<Vec<_>>();
+                    for addr in addrs {
+                        self.notify_listener(ListenerNotice::NewAddress(addr));
+                    }
                     continue;
                 }
                 Poll::Ready(Some(TransportRequest::DialRequest { .. })) => {
@@ -101,6 +238,125 @@ impl NetworkIncomingBehavior for Behavior {
         remote_addr: &Multiaddr,
     ) -> Result<Self::ConnectionHandler, ConnectionDenied> {
         let relay_addr = remote_addr.clone().with(Protocol::Peer(peer_id));
-        Ok(handler::Handler::new(relay_addr))
+        Ok(handler::Handler::new(relay_addr).with_keep_alive(self.relays.contains_key(&peer_id)))
+    }
+
+    fn on_connection_established(
+        &mut self,
+        id: ConnectionId,
+        peer_id: PeerId,
+        _local_addr: &Multiaddr,
+        _remote_addr: &Multiaddr,
+    ) {
+        self.on_relay_connected(id, peer_id);
+    }
+
+    fn on_connection_closed(
+        &mut self,
+        id: ConnectionId,
+        peer_id: PeerId,
+        _local_addr: &Multiaddr,
+        _remote_addr: &Multiaddr,
+        _reason: &CloseReason,
+    ) {
+        self.on_relay_closed(id, peer_id);
+    }
+}
+
+/// 在 duplex Swarm 中主动连接 [`Behavior::add_relay`] 添加的中继
+impl NetworkOutgoingBehavior for Behavior {
+    fn handle_pending_connection(
+        &mut self,
+        _id: ConnectionId,
+        _maybe_peer: Option<PeerId>,
+        addr: &Option<Multiaddr>,
+    ) -> Result<Option<Multiaddr>, ConnectionDenied> {
+        Ok(addr.clone())
+    }
+
+    fn handle_established_connection(
+        &mut self,
+        _id: ConnectionId,
+        peer_id: PeerId,
+        addr: &Multiaddr,
+    ) -> Result<Self::ConnectionHandler, ConnectionDenied> {
+        let relay_addr = with_peer(addr, peer_id);
+        Ok(handler::Handler::new(relay_addr).with_keep_alive(self.relays.contains_key(&peer_id)))
+    }
+
+    fn on_connection_established(&mut self, id: ConnectionId, peer_id: PeerId, _addr: &Multiaddr) {
+        self.on_relay_connected(id, peer_id);
+    }
+
+    fn on_connection_closed(
+        &mut self,
+        id: ConnectionId,
+        peer_id: PeerId,
+        _addr: &Multiaddr,
+        _reason: &CloseReason,
+    ) {
+        self.on_relay_closed(id, peer_id);
+    }
+
+    fn on_dial_failure(
+        &mut self,
+        _id: ConnectionId,
+        peer_id: Option<PeerId>,
+        _addr: Option<&Multiaddr>,
+        error: &DialError,
+    ) {
+        let Some(peer_id) = peer_id else {
+            return;
+        };
+        let Some(relay) = self.relays.get_mut(&peer_id) else {
+            return;
+        };
+        if !relay.connections.is_empty() || relay.redial.is_some() {
+            return;
+        }
+        let backoff = schedule_redial(relay, self.max_backoff);
+        tracing::debug!(%peer_id, "Dial relay failed, retrying in {:?}: {}", backoff, error);
     }
+
+    fn poll_dial(&mut self, cx: &mut Context<'_>) -> Poll<DialOpts> {
+        for (peer_id, relay) in self.relays.iter_mut() {
+            let Some(redial) = relay.redial.as_mut() else {
+                continue;
+            };
+            if redial.poll_unpin(cx).is_pending() {
+                continue;
+            }
+            relay.redial = None;
+            tracing::debug!(%peer_id, "Dialing relay");
+            return Poll::Ready(
+                DialOpts::new(Some(relay.addr.clone()), Some(*peer_id))
+                    .with_condition(PeerCondition::DisconnectedAndNotDialing),
+            );
+        }
+        Poll::Pending
+    }
+}
+
+fn with_peer(addr: &Multiaddr, peer_id: PeerId) -> Multiaddr {
+    let mut addr = addr.clone();
+    if !matches!(addr.iter().last(), Some(Protocol::Peer(_))) {
+        addr.push(Protocol::Peer(peer_id));
+    }
+    addr
+}
+
+/// 经由中继到达本地的地址
+fn circuit_addr(relay_addr: &Multiaddr, relay_peer_id: PeerId) -> Multiaddr {
+    with_peer(relay_addr, relay_peer_id).with(Protocol::Circuit)
+}
+
+#[derive(Debug)]
+pub enum Event {
+    /// 与中继的连接全部断开，经由该中继的地址失效，`backoff` 后重连
+    RelayConnectionLost {
+        relay_peer_id: PeerId,
+        backoff: Duration,
+    },
+    /// 断开后重新连上中继，经由该中继的地址重新加入监听
+    RelayReconnected { relay_peer_id: PeerId },
 }
@@ -7,10 +7,14 @@ use std::{
 
 use futures::FutureExt;
 use futures_bounded::FuturesSet;
-use volans_core::{Multiaddr, PeerId, upgrade::ReadyUpgrade};
+use volans_core::{
+    Multiaddr, PeerId,
+    upgrade::{PendingUpgrade, ReadyUpgrade},
+};
 use volans_swarm::{
-    ConnectionHandler, ConnectionHandlerEvent, InboundStreamHandler, InboundUpgradeSend,
-    StreamProtocol, SubstreamProtocol,
+    ConnectionHandler, ConnectionHandlerEvent, InboundStreamHandler, InboundUpgradeSend, KeepAlive,
+    OutboundStreamHandler, OutboundUpgradeSend, StreamProtocol, StreamUpgradeError,
+    SubstreamProtocol,
 };
 
 use crate::protocol;
@@ -19,6 +23,7 @@ use crate::protocol;
 pub struct Handler {
     relay_remote_addr: Multiaddr,
     inbound_pending_circuits: FuturesSet<Result<protocol::Relay, protocol::Error>>,
+    keep_alive: bool,
 }
 
 impl Handler {
@@ -29,8 +34,15 @@ impl Handler {
                 || futures_bounded::Delay::futures_timer(Duration::from_secs(15)),
                 10, // 最大并行处理数
             ),
+            keep_alive: false,
         }
     }
+
+    /// 与需要保持的中继之间的连接，空闲时也不关闭
+    pub fn with_keep_alive(mut self, keep_alive: bool) -> Self {
+        self.keep_alive = keep_alive;
+        self
+    }
 }
 
 impl ConnectionHandler for Handler {
@@ -41,6 +53,14 @@ impl ConnectionHandler for Handler {
         // No actions to handle
     }
 
+    fn keep_alive(&self) -> KeepAlive {
+        if self.keep_alive {
+            KeepAlive::Yes
+        } else {
+            KeepAlive::Idle
+        }
+    }
+
     fn poll(&mut self, cx: &mut Context<'_>) -> Poll<ConnectionHandlerEvent<Self::Event>> {
         loop {
             match self.inbound_pending_circuits.poll_unpin(cx) {
@@ -109,6 +129,35 @@ impl InboundStreamHandler for Handler {
     }
 }
 
+/// 后端主动连接中继时不发起出站子流
+impl OutboundStreamHandler for Handler {
+    type OutboundUpgrade = PendingUpgrade<StreamProtocol>;
+    type OutboundUserData = Infallible;
+
+    fn on_fully_negotiated(
+        &mut self,
+        user_data: Self::OutboundUserData,
+        _stream: <Self::OutboundUpgrade as OutboundUpgradeSend>::Output,
+    ) {
+        match user_data {}
+    }
+
+    fn on_upgrade_error(
+        &mut self,
+        user_data: Self::OutboundUserData,
+        _error: StreamUpgradeError<<Self::OutboundUpgrade as OutboundUpgradeSend>::Error>,
+    ) {
+        match user_data {}
+    }
+
+    fn poll_outbound_request(
+        &mut self,
+        _cx: &mut Context<'_>,
+    ) -> Poll<SubstreamProtocol<Self::OutboundUpgrade, Self::OutboundUserData>> {
+        Poll::Pending
+    }
+}
+
 pub struct NewCircuitAccept {
     pub(crate) relay_remote_addr: Multiaddr,
     pub(crate) circuit: protocol::Circuit,
@@ -3,12 +3,100 @@
 /// 2、Transport 收到中继请求。 向Behavior发送 DialOpts
 /// 3、Behavior 处理 DialOpts，向 PeerRelayServer 发送OutboundRequest
 /// 4、OutboundRequest 协商成功后，通知Transport 建立连接(Substream -> Connection)
+///
+/// 配置多个中继时，`behavior.connect(dst)` 按 [`Selection`] 排序依次尝试各中继，
+/// `/circuit/peer/{dst}` 形式的地址拨号时补全为首选中继的地址。
+///
+/// 在 duplex Swarm 中监听 `/circuit` 地址后，客户端与后端一样接受中继转来的连接，
+/// 已连接的中继地址加入监听器，两个客户端可以经由中继互相连接。
 mod behavior;
 mod handler;
 
-pub use behavior::Behavior;
+pub use behavior::{Behavior, Selection};
 
-use crate::transport;
+use std::{fmt, time::Duration};
+
+use volans_core::PeerId;
+
+use crate::{protocol::ConnectError, transport};
+
+/// 建立电路的阶段
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stage {
+    /// 拨号中继
+    RelayDial,
+    /// 与中继协商子流
+    RelayNegotiation,
+    /// 中继连接目标节点
+    BackendConnect,
+}
+
+impl fmt::Display for Stage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Stage::RelayDial => write!(f, "relay dial"),
+            Stage::RelayNegotiation => write!(f, "relay negotiation"),
+            Stage::BackendConnect => write!(f, "backend connect"),
+        }
+    }
+}
+
+/// 建立电路各阶段的耗时，未经历的阶段为 `None`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CircuitTimings {
+    /// 到中继的拨号，已有连接时为 `None`
+    pub relay_dial: Option<Duration>,
+    pub relay_negotiation: Option<Duration>,
+    pub backend_connect: Option<Duration>,
+}
+
+impl CircuitTimings {
+    pub fn total(&self) -> Duration {
+        [
+            self.relay_dial,
+            self.relay_negotiation,
+            self.backend_connect,
+        ]
+        .into_iter()
+        .flatten()
+        .sum()
+    }
+}
+
+/// 建立电路失败的阶段与原因，拨号时作为传输层错误返回
+#[derive(Debug, Clone, thiserror::Error)]
+#[error("Circuit failed at {stage}: {cause}")]
+pub struct CircuitFailure {
+    pub stage: Stage,
+    #[source]
+    pub cause: ConnectError,
+}
+
+impl CircuitFailure {
+    pub(crate) fn new(stage: Stage, cause: impl Into<ConnectError>) -> Self {
+        Self {
+            stage,
+            cause: cause.into(),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum Event {
+    /// 经由中继建立了到目标节点的电路
+    CircuitEstablished {
+        relay_peer_id: PeerId,
+        dst_peer_id: PeerId,
+        timings: CircuitTimings,
+    },
+    /// 建立电路失败，失败前各阶段的耗时与拨号返回的错误一致
+    CircuitFailed {
+        relay_peer_id: PeerId,
+        dst_peer_id: PeerId,
+        timings: CircuitTimings,
+        error: CircuitFailure,
+    },
+}
 
 pub fn new() -> (transport::Config, Behavior) {
     let (transport, transport_receiver) = transport::Config::new();
@@ -1,6 +1,5 @@
 use std::{
     collections::{HashMap, HashSet, VecDeque},
-    convert::Infallible,
     io,
     task::{Context, Poll},
     time::Duration,
@@ -8,51 +7,284 @@ use std::{
 
 use either::Either;
 use futures::{StreamExt, channel::mpsc};
-use volans_core::{Multiaddr, PeerId};
+use volans_core::{Multiaddr, PeerId, TransportError, multiaddr::Protocol};
 use volans_swarm::{
-    BehaviorEvent, ConnectionDenied, ConnectionId, DialOpts, NetworkBehavior,
-    NetworkOutgoingBehavior, PeerCondition, THandlerAction, THandlerEvent,
+    BehaviorEvent, ConnectionDenied, ConnectionId, DialOpts, DialStrategy, NetworkBehavior,
+    NetworkIncomingBehavior, NetworkOutgoingBehavior, PeerCondition, THandlerAction, THandlerEvent,
     behavior::NotifyHandler,
-    error::{ConnectionError, DialError},
+    error::{CloseReason, DialError},
     handler::DummyHandler,
 };
 
-use crate::{MultiaddrExt, transport::TransportRequest};
+use crate::{
+    MultiaddrExt,
+    backend::handler::NewCircuitAccept,
+    client::{CircuitFailure, Event, Stage},
+    protocol::ConnectError,
+    relay_peer_id,
+    transport::{Connection, IncomingRelayedConnection, ListenerNotice, TransportRequest},
+};
 
 use super::handler;
+
+/// 配置了多个中继时的选择策略
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Selection {
+    /// 每次拨号依次轮换首选中继
+    #[default]
+    RoundRobin,
+    /// 往返时延最低的中继优先，时延由 [`Behavior::record_rtt`] 提供
+    LowestRtt,
+    /// 沿用上一次连通的中继，失败后才切换
+    Sticky,
+}
+
+struct RelayInfo {
+    peer_id: PeerId,
+    addr: Multiaddr,
+    rtt: Option<Duration>,
+    /// 上次连通后经由该中继拨号失败的次数
+    failures: u32,
+}
+
 pub struct Behavior {
     transport_receiver: mpsc::Receiver<TransportRequest>,
+    listener: Option<mpsc::Sender<ListenerNotice>>,
     direct_connections: HashMap<PeerId, HashSet<ConnectionId>>,
     pending_channels: HashMap<PeerId, VecDeque<handler::NewOutboundBridgeRequest>>,
-    dial_peers: VecDeque<(PeerId, Option<Multiaddr>)>,
-    pending_events: VecDeque<BehaviorEvent<Infallible, THandlerAction<Self>>>,
+    dial_peers: VecDeque<DialOpts>,
+    pending_events: VecDeque<BehaviorEvent<Event, THandlerAction<Self>>>,
     timeout: Duration,
+    relays: Vec<RelayInfo>,
+    selection: Selection,
+    next_relay: usize,
+    sticky_relay: Option<PeerId>,
 }
 
 impl Behavior {
     pub fn new(transport_receiver: mpsc::Receiver<TransportRequest>) -> Self {
         Self {
             transport_receiver,
+            listener: None,
             direct_connections: HashMap::new(),
             pending_channels: HashMap::new(),
             dial_peers: VecDeque::new(),
             pending_events: VecDeque::new(),
             timeout: Duration::from_secs(15), // Default timeout for outbound requests
+            relays: Vec::new(),
+            selection: Selection::default(),
+            next_relay: 0,
+            sticky_relay: None,
+        }
+    }
+
+    pub fn with_selection(mut self, selection: Selection) -> Self {
+        self.selection = selection;
+        self
+    }
+
+    /// 添加可用的中继，`relay_addr` 不含中继的 `PeerId`
+    ///
+    /// 已连接的中继地址加入 `/circuit` 监听器。
+    pub fn add_relay(&mut self, relay_peer_id: PeerId, relay_addr: Multiaddr) {
+        match self.relays.iter_mut().find(|r| r.peer_id == relay_peer_id) {
+            Some(relay) => relay.addr = relay_addr,
+            None => {
+                self.relays.push(RelayInfo {
+                    peer_id: relay_peer_id,
+                    addr: relay_addr,
+                    rtt: None,
+                    failures: 0,
+                });
+                if self.direct_connections.contains_key(&relay_peer_id) {
+                    self.notify_relay_address(relay_peer_id, true);
+                }
+            }
+        }
+    }
+
+    pub fn remove_relay(&mut self, relay_peer_id: &PeerId) -> bool {
+        if self.direct_connections.contains_key(relay_peer_id) {
+            self.notify_relay_address(*relay_peer_id, false);
+        }
+        let len = self.relays.len();
+        self.relays.retain(|r| r.peer_id != *relay_peer_id);
+        if self.sticky_relay.as_ref() == Some(relay_peer_id) {
+            self.sticky_relay = None;
+        }
+        self.relays.len() != len
+    }
+
+    /// 记录到中继的往返时延，通常来自 ping 行为的事件
+    pub fn record_rtt(&mut self, relay_peer_id: &PeerId, rtt: Duration) {
+        if let Some(relay) = self.relays.iter_mut().find(|r| r.peer_id == *relay_peer_id) {
+            relay.rtt = Some(rtt);
+        }
+    }
+
+    /// 经由各个中继到达 `dst_peer_id` 的地址，按选择策略排序，失败过的中继排在最后
+    pub fn relayed_addresses(&mut self, dst_peer_id: PeerId) -> Vec<Multiaddr> {
+        self.select_relays()
+            .into_iter()
+            .map(|relay| {
+                relay
+                    .addr
+                    .clone()
+                    .with(Protocol::Peer(relay.peer_id))
+                    .with(Protocol::Circuit)
+                    .with(Protocol::Peer(dst_peer_id))
+            })
+            .collect()
+    }
+
+    /// 经由中继连接 `dst_peer_id`，首选中继不通时依次尝试其余中继
+    ///
+    /// 建立的连接地址中包含实际使用的中继。
+    pub fn connect(&mut self, dst_peer_id: PeerId) {
+        let addrs = self.relayed_addresses(dst_peer_id);
+        if addrs.is_empty() {
+            tracing::warn!("No relay configured to connect {}", dst_peer_id);
+            return;
+        }
+        self.dial_peers.push_back(
+            DialOpts::peer(dst_peer_id)
+                .with_addresses(addrs)
+                .with_strategy(DialStrategy::Sequential),
+        );
+    }
+
+    fn select_relays(&mut self) -> Vec<&RelayInfo> {
+        let mut relays: Vec<&RelayInfo> = self.relays.iter().collect();
+        match self.selection {
+            Selection::RoundRobin => {
+                if !relays.is_empty() {
+                    let mid = self.next_relay % relays.len();
+                    relays.rotate_left(mid);
+                    self.next_relay = self.next_relay.wrapping_add(1);
+                }
+            }
+            Selection::LowestRtt => relays.sort_by_key(|r| (r.rtt.is_none(), r.rtt)),
+            Selection::Sticky => {
+                if let Some(sticky) = self.sticky_relay {
+                    relays.sort_by_key(|r| r.peer_id != sticky);
+                }
+            }
+        }
+        relays.sort_by_key(|r| r.failures > 0);
+        relays
+    }
+
+    /// 经由中继到达本地的地址，只有添加过的中继才会加入监听器
+    fn relay_circuit_addr(&self, relay_peer_id: PeerId) -> Option<Multiaddr> {
+        let relay = self.relays.iter().find(|r| r.peer_id == relay_peer_id)?;
+        Some(
+            relay
+                .addr
+                .clone()
+                .with(Protocol::Peer(relay_peer_id))
+                .with(Protocol::Circuit),
+        )
+    }
+
+    fn notify_relay_address(&mut self, relay_peer_id: PeerId, connected: bool) {
+        let Some(addr) = self.relay_circuit_addr(relay_peer_id) else {
+            return;
+        };
+        if connected {
+            self.notify_listener(ListenerNotice::NewAddress(addr));
+        } else {
+            self.notify_listener(ListenerNotice::AddressExpired(addr));
+        }
+    }
+
+    /// 通知监听器，监听器已关闭时丢弃，等待新的监听请求
+    fn notify_listener(&mut self, notice: ListenerNotice) {
+        let Some(sender) = self.listener.as_mut() else {
+            return;
+        };
+        if let Err(e) = sender.try_send(notice) {
+            if e.is_disconnected() {
+                tracing::debug!("Circuit listener closed");
+                self.listener = None;
+            } else {
+                tracing::error!("Failed to notify circuit listener: {}", e);
+            }
+        }
+    }
+
+    fn on_relay_result(&mut self, relay_peer_id: PeerId, success: bool) {
+        let Some(relay) = self.relays.iter_mut().find(|r| r.peer_id == relay_peer_id) else {
+            return;
+        };
+        if success {
+            relay.failures = 0;
+            self.sticky_relay = Some(relay_peer_id);
+        } else {
+            relay.failures += 1;
+            if self.sticky_relay == Some(relay_peer_id) {
+                self.sticky_relay = None;
+            }
         }
     }
 }
 
 impl NetworkBehavior for Behavior {
     type ConnectionHandler = Either<DummyHandler, handler::Handler>;
-    type Event = Infallible;
+    type Event = Event;
 
     fn on_connection_handler_event(
         &mut self,
         _id: ConnectionId,
-        _peer_id: PeerId,
-        _event: THandlerEvent<Self>,
+        peer_id: PeerId,
+        event: THandlerEvent<Self>,
     ) {
-        unreachable!("This behavior does not handle connection events directly.");
+        let NewCircuitAccept {
+            relay_remote_addr,
+            circuit,
+            src_peer_id,
+            dst_peer_id: _,
+            src_relayed_addr,
+        } = match event {
+            Either::Left(never) => match never {},
+            Either::Right(handler::Event::CircuitAccepted(accept)) => *accept,
+            Either::Right(handler::Event::OutboundCircuit {
+                dst_peer_id,
+                timings,
+                result,
+            }) => {
+                let event = match result {
+                    Ok(()) => Event::CircuitEstablished {
+                        relay_peer_id: peer_id,
+                        dst_peer_id,
+                        timings,
+                    },
+                    Err(error) => Event::CircuitFailed {
+                        relay_peer_id: peer_id,
+                        dst_peer_id,
+                        timings,
+                        error,
+                    },
+                };
+                self.pending_events
+                    .push_back(BehaviorEvent::Behavior(event));
+                return;
+            }
+        };
+        if self.listener.is_none() {
+            tracing::warn!(
+                "No circuit listener for inbound circuit via: {}",
+                relay_remote_addr
+            );
+            return;
+        }
+        self.notify_listener(ListenerNotice::Incoming(Box::new(
+            IncomingRelayedConnection::new(
+                Connection::new_accepting(circuit),
+                src_peer_id,
+                peer_id,
+                src_relayed_addr,
+            ),
+        )));
     }
 
     fn poll(
@@ -85,11 +317,10 @@ impl NetworkBehavior for Behavior {
                         return Poll::Ready(BehaviorEvent::HandlerAction {
                             peer_id: relay_peer_id,
                             handler: NotifyHandler::One(connection_id),
-                            action: Either::Right(handler::NewOutboundBridgeRequest {
-                                dst_addresses: vec![],
+                            action: Either::Right(handler::NewOutboundBridgeRequest::new(
                                 dst_peer_id,
                                 send_back,
-                            }),
+                            )),
                         });
                     } else {
                         tracing::debug!(
@@ -99,17 +330,34 @@ impl NetworkBehavior for Behavior {
                         self.pending_channels
                             .entry(relay_peer_id)
                             .or_default()
-                            .push_back(handler::NewOutboundBridgeRequest {
-                                dst_addresses: vec![],
+                            .push_back(handler::NewOutboundBridgeRequest::new(
                                 dst_peer_id,
                                 send_back,
-                            });
-                        self.dial_peers.push_back((relay_peer_id, Some(relay_addr)));
+                            ));
+                        self.dial_peers.push_back(
+                            DialOpts::new(Some(relay_addr), Some(relay_peer_id))
+                                .with_condition(PeerCondition::DisconnectedAndNotDialing),
+                        );
                         continue;
                     }
                 }
-                Poll::Ready(Some(TransportRequest::ListenRequest { .. })) => {
-                    tracing::error!("Unexpected ListenRequest in Behavior");
+                Poll::Ready(Some(TransportRequest::ListenRequest {
+                    local_addr,
+                    listener_sender,
+                })) => {
+                    tracing::debug!("Circuit Listening on: {:?}", local_addr);
+                    self.listener = Some(listener_sender);
+                    // 新的监听器接管已连接中继的地址
+                    let relays = self
+                        .relays
+                        .iter()
+                        .map(|r| r.peer_id)
+                        .filter(|peer_id| self.direct_connections.contains_key(peer_id))
+                        .collect::<Vec<_>>();
+                    for relay_peer_id in relays {
+                        self.notify_relay_address(relay_peer_id, true);
+                    }
+                    continue;
                 }
                 Poll::Pending | Poll::Ready(None) => {}
             }
@@ -119,15 +367,41 @@ impl NetworkBehavior for Behavior {
 }
 
 impl NetworkOutgoingBehavior for Behavior {
+    /// 不含中继部分的 `/circuit/p2p/{dst}` 地址按选择策略补全为首选中继的地址
+    fn handle_pending_connection(
+        &mut self,
+        _id: ConnectionId,
+        maybe_peer: Option<PeerId>,
+        addr: &Option<Multiaddr>,
+    ) -> Result<Option<Multiaddr>, ConnectionDenied> {
+        let Some(addr) = addr else {
+            return Ok(None);
+        };
+        if !matches!(addr.iter().next(), Some(Protocol::Circuit)) {
+            return Ok(None);
+        }
+        let dst_peer_id = addr
+            .iter()
+            .find_map(|p| match p {
+                Protocol::Peer(peer_id) => Some(peer_id),
+                _ => None,
+            })
+            .or(maybe_peer);
+        Ok(dst_peer_id.and_then(|dst| self.relayed_addresses(dst).into_iter().next()))
+    }
+
     fn handle_established_connection(
         &mut self,
         _id: ConnectionId,
-        _peer_id: PeerId,
+        peer_id: PeerId,
         addr: &Multiaddr,
     ) -> Result<Self::ConnectionHandler, ConnectionDenied> {
         if !addr.is_circuit() {
             // 如果是待处理的请求，返回对应的处理器
-            Ok(Either::Right(handler::Handler::new(self.timeout)))
+            Ok(Either::Right(handler::Handler::new(
+                with_peer(addr, peer_id),
+                self.timeout,
+            )))
         } else {
             // 否则返回一个空的处理器
             Ok(Either::Left(DummyHandler))
@@ -142,15 +416,21 @@ impl NetworkOutgoingBehavior for Behavior {
             addr
         );
 
+        if let Some(relay_peer_id) = relay_peer_id(addr) {
+            self.on_relay_result(relay_peer_id, true);
+        }
+
         if !addr.is_circuit() {
-            self.direct_connections
-                .entry(peer_id)
-                .or_default()
-                .insert(id);
+            let connections = self.direct_connections.entry(peer_id).or_default();
+            connections.insert(id);
+            if connections.len() == 1 {
+                self.notify_relay_address(peer_id, true);
+            }
 
             // 处理拨号成功，移出正在排队的请求
             if let Some(mut requests) = self.pending_channels.remove(&peer_id) {
-                while let Some(request) = requests.pop_front() {
+                while let Some(mut request) = requests.pop_front() {
+                    request.timings.relay_dial = Some(request.stage_started.elapsed());
                     let connection_id = self
                         .direct_connections
                         .get(&peer_id)
@@ -172,14 +452,15 @@ impl NetworkOutgoingBehavior for Behavior {
         id: ConnectionId,
         peer_id: PeerId,
         addr: &Multiaddr,
-        _reason: Option<&ConnectionError>,
+        _reason: &CloseReason,
     ) {
-        if !addr.is_circuit() {
-            if let Some(connections) = self.direct_connections.get_mut(&peer_id) {
-                connections.remove(&id);
-                if connections.is_empty() {
-                    self.direct_connections.remove(&peer_id);
-                }
+        if !addr.is_circuit()
+            && let Some(connections) = self.direct_connections.get_mut(&peer_id)
+        {
+            connections.remove(&id);
+            if connections.is_empty() {
+                self.direct_connections.remove(&peer_id);
+                self.notify_relay_address(peer_id, false);
             }
         }
     }
@@ -197,28 +478,227 @@ impl NetworkOutgoingBehavior for Behavior {
             addr,
             error,
         );
-        if let Some(peer_id) = peer_id {
-            if let Some(requests) = self.pending_channels.get_mut(&peer_id) {
-                if let Some(request) = requests.pop_front() {
-                    tracing::error!("Dial failed for request: {:?}", request);
-                    let _ = request.send_back.send(Err(io::Error::new(
-                        io::ErrorKind::Other,
-                        "Dial failed",
-                    )
-                    .into()));
-                }
-            }
+        // 经由中继的拨号失败，降低该中继的优先级
+        let failed_relays: Vec<PeerId> = match error {
+            DialError::AllAttemptsFailed { errors } => errors
+                .iter()
+                .filter(|(_, error)| matches!(error, TransportError::Other(_)))
+                .filter_map(|(addr, _)| relay_peer_id(addr))
+                .collect(),
+            _ => addr.and_then(relay_peer_id).into_iter().collect(),
+        };
+        for relay in failed_relays {
+            self.on_relay_result(relay, false);
+        }
+        if let Some(peer_id) = peer_id
+            && let Some(requests) = self.pending_channels.get_mut(&peer_id)
+            && let Some(mut request) = requests.pop_front()
+        {
+            tracing::error!("Dial failed for request: {:?}", request);
+            request.timings.relay_dial = Some(request.stage_started.elapsed());
+            let error = CircuitFailure::new(
+                Stage::RelayDial,
+                ConnectError::Io(io::Error::other(error.to_string())),
+            );
+            let _ = request.send_back.send(Err(error.clone()));
+            self.pending_events
+                .push_back(BehaviorEvent::Behavior(Event::CircuitFailed {
+                    relay_peer_id: peer_id,
+                    dst_peer_id: request.dst_peer_id,
+                    timings: request.timings,
+                    error,
+                }));
         }
     }
 
     fn poll_dial(&mut self, _cx: &mut Context<'_>) -> Poll<DialOpts> {
-        if let Some((peer, relay_addr)) = self.dial_peers.pop_front() {
-            tracing::debug!("Dialing Bridge peer: {:?}", peer);
-            return Poll::Ready(
-                DialOpts::new(relay_addr, Some(peer))
-                    .with_condition(PeerCondition::DisconnectedAndNotDialing),
-            );
+        if let Some(opts) = self.dial_peers.pop_front() {
+            tracing::debug!("Dialing Bridge peer: {:?}", opts.peer_id());
+            return Poll::Ready(opts);
         }
         Poll::Pending
     }
 }
+
+/// 在 duplex Swarm 中接受中继转来的连接
+///
+/// 中继连接本地时使用与后端相同的方式转交中继，需要先监听 `/circuit` 地址。
+impl NetworkIncomingBehavior for Behavior {
+    fn handle_established_connection(
+        &mut self,
+        _id: ConnectionId,
+        peer_id: PeerId,
+        _local_addr: &Multiaddr,
+        remote_addr: &Multiaddr,
+    ) -> Result<Self::ConnectionHandler, ConnectionDenied> {
+        Ok(Either::Right(handler::Handler::new(
+            with_peer(remote_addr, peer_id),
+            self.timeout,
+        )))
+    }
+}
+
+fn with_peer(addr: &Multiaddr, peer_id: PeerId) -> Multiaddr {
+    let mut addr = addr.clone();
+    if !matches!(addr.iter().last(), Some(Protocol::Peer(_))) {
+        addr.push(Protocol::Peer(peer_id));
+    }
+    addr
+}
+
+#[cfg(test)]
+mod tests {
+    use std::pin::{Pin, pin};
+
+    use futures::task::noop_waker_ref;
+    use volans_core::{Listener, ListenerEvent, SocketOptions, Transport};
+
+    use super::*;
+    use crate::transport;
+
+    fn new_behavior(selection: Selection, relays: &[PeerId]) -> Behavior {
+        let (_, receiver) = transport::Config::new();
+        let mut behavior = Behavior::new(receiver).with_selection(selection);
+        for (i, relay) in relays.iter().enumerate() {
+            let addr = format!("/ip4/10.0.0.{}/tcp/4001", i + 1).parse().unwrap();
+            behavior.add_relay(*relay, addr);
+        }
+        behavior
+    }
+
+    fn order(behavior: &mut Behavior) -> Vec<PeerId> {
+        behavior.select_relays().iter().map(|r| r.peer_id).collect()
+    }
+
+    #[test]
+    fn round_robin_skips_failed_relays() {
+        let relays = [PeerId::random(), PeerId::random(), PeerId::random()];
+        let mut behavior = new_behavior(Selection::RoundRobin, &relays);
+        assert_eq!(order(&mut behavior), relays);
+        assert_eq!(order(&mut behavior), [relays[1], relays[2], relays[0]]);
+
+        behavior.on_relay_result(relays[0], false);
+        assert_eq!(order(&mut behavior), [relays[2], relays[1], relays[0]]);
+        behavior.on_relay_result(relays[0], true);
+        assert_eq!(order(&mut behavior), relays);
+    }
+
+    #[test]
+    fn lowest_rtt_and_sticky() {
+        let relays = [PeerId::random(), PeerId::random(), PeerId::random()];
+        let mut behavior = new_behavior(Selection::LowestRtt, &relays);
+        behavior.record_rtt(&relays[2], Duration::from_millis(10));
+        behavior.record_rtt(&relays[0], Duration::from_millis(30));
+        assert_eq!(order(&mut behavior), [relays[2], relays[0], relays[1]]);
+
+        let mut behavior = new_behavior(Selection::Sticky, &relays);
+        behavior.on_relay_result(relays[1], true);
+        assert_eq!(order(&mut behavior), [relays[1], relays[0], relays[2]]);
+        behavior.on_relay_result(relays[1], false);
+        assert_eq!(order(&mut behavior), [relays[0], relays[2], relays[1]]);
+    }
+
+    #[test]
+    fn relayed_address_contains_relay() {
+        let relays = [PeerId::random()];
+        let dst = PeerId::random();
+        let mut behavior = new_behavior(Selection::RoundRobin, &relays);
+        let addr = behavior.relayed_addresses(dst).remove(0);
+        assert_eq!(
+            addr,
+            format!(
+                "/ip4/10.0.0.1/tcp/4001/peer/{}/circuit/peer/{dst}",
+                relays[0]
+            )
+            .parse()
+            .unwrap()
+        );
+        assert_eq!(crate::relay_peer_id(&addr), Some(relays[0]));
+    }
+
+    fn poll_listener(listener: Pin<&mut transport::ListenerBackend>) -> Option<Multiaddr> {
+        let mut cx = Context::from_waker(noop_waker_ref());
+        match listener.poll_event(&mut cx) {
+            Poll::Ready(ListenerEvent::NewAddress(addr)) => Some(addr),
+            Poll::Ready(ListenerEvent::AddressExpired(_)) => None,
+            _ => panic!("unexpected listener event"),
+        }
+    }
+
+    #[test]
+    fn circuit_listener_follows_relay_connection() {
+        let (transport, receiver) = transport::Config::new();
+        let relay = PeerId::random();
+        let relay_addr: Multiaddr = "/ip4/10.0.0.1/tcp/4001".parse().unwrap();
+        let mut behavior = Behavior::new(receiver);
+        behavior.add_relay(relay, relay_addr.clone());
+
+        let mut listener = pin!(
+            transport
+                .listen(Protocol::Circuit.into(), &SocketOptions::default())
+                .unwrap()
+        );
+        assert_eq!(
+            poll_listener(listener.as_mut()),
+            Some(Protocol::Circuit.into())
+        );
+        let mut cx = Context::from_waker(noop_waker_ref());
+        assert!(behavior.poll(&mut cx).is_pending());
+
+        let id = ConnectionId::new_unchecked(1);
+        let addr = relay_addr.with(Protocol::Peer(relay));
+        NetworkOutgoingBehavior::on_connection_established(&mut behavior, id, relay, &addr);
+        assert_eq!(
+            poll_listener(listener.as_mut()),
+            Some(addr.clone().with(Protocol::Circuit))
+        );
+        NetworkOutgoingBehavior::on_connection_closed(
+            &mut behavior,
+            id,
+            relay,
+            &addr,
+            &CloseReason::RemoteClosed,
+        );
+        assert_eq!(poll_listener(listener.as_mut()), None);
+    }
+
+    #[test]
+    fn relay_dial_failure_reports_stage() {
+        let (_, receiver) = transport::Config::new();
+        let mut behavior = Behavior::new(receiver);
+        let relay = PeerId::random();
+        let dst = PeerId::random();
+        let (send_back, mut result) = futures::channel::oneshot::channel();
+        behavior
+            .pending_channels
+            .entry(relay)
+            .or_default()
+            .push_back(handler::NewOutboundBridgeRequest::new(dst, send_back));
+
+        NetworkOutgoingBehavior::on_dial_failure(
+            &mut behavior,
+            ConnectionId::new_unchecked(1),
+            Some(relay),
+            None,
+            &DialError::Timeout,
+        );
+        let error = result.try_recv().unwrap().unwrap().err().unwrap();
+        assert_eq!(error.stage, Stage::RelayDial);
+
+        let mut cx = Context::from_waker(noop_waker_ref());
+        match behavior.poll(&mut cx) {
+            Poll::Ready(BehaviorEvent::Behavior(Event::CircuitFailed {
+                relay_peer_id,
+                dst_peer_id,
+                timings,
+                error,
+            })) => {
+                assert_eq!((relay_peer_id, dst_peer_id), (relay, dst));
+                assert!(timings.relay_dial.is_some());
+                assert!(timings.relay_negotiation.is_none());
+                assert_eq!(error.stage, Stage::RelayDial);
+            }
+            _ => panic!("expected circuit failure"),
+        }
+    }
+}
@@ -1,47 +1,98 @@
 use std::{
     collections::VecDeque,
-    convert::Infallible,
     fmt, io,
     task::{Context, Poll},
-    time::Duration,
+    time::{Duration, Instant},
 };
 
 use futures::{FutureExt, channel::oneshot};
-use futures_bounded::{Delay, FuturesTupleSet};
+use futures_bounded::{Delay, FuturesSet, FuturesTupleSet};
 use volans_codec::Bytes;
 use volans_core::{Multiaddr, PeerId, upgrade::ReadyUpgrade};
 use volans_swarm::{
-    ConnectionHandler, ConnectionHandlerEvent, OutboundStreamHandler, OutboundUpgradeSend,
-    StreamProtocol, StreamUpgradeError, Substream, SubstreamProtocol,
+    ConnectionHandler, ConnectionHandlerEvent, InboundStreamHandler, InboundUpgradeSend,
+    OutboundStreamHandler, OutboundUpgradeSend, StreamProtocol, StreamUpgradeError, Substream,
+    SubstreamProtocol,
 };
 
-use crate::{protocol, transport::Connection};
+use crate::{
+    backend::handler::NewCircuitAccept,
+    client::{CircuitFailure, CircuitTimings, Stage},
+    protocol,
+    transport::Connection,
+};
 
+/// 客户端与中继之间的连接，出站发起中继请求，入站接受中继转来的连接
 pub struct Handler {
+    relay_remote_addr: Multiaddr,
     outbound_requests: VecDeque<NewOutboundBridgeRequest>,
     pending_outbound: Option<NewOutboundBridgeRequest>,
     outbound_circuit_requests: FuturesTupleSet<
         Result<(Substream, Bytes), protocol::ConnectError>,
-        oneshot::Sender<Result<Connection, protocol::ConnectError>>,
+        NewOutboundBridgeRequest,
     >,
+    inbound_pending_circuits: FuturesSet<Result<protocol::Relay, protocol::Error>>,
+    pending_events: VecDeque<Event>,
+}
+
+#[derive(Debug)]
+pub enum Event {
+    /// 中继转来的连接
+    CircuitAccepted(Box<NewCircuitAccept>),
+    /// 出站电路的结果，结果已交给传输层
+    OutboundCircuit {
+        dst_peer_id: PeerId,
+        timings: CircuitTimings,
+        result: Result<(), CircuitFailure>,
+    },
 }
 
 impl Handler {
-    pub fn new(timeout: Duration) -> Self {
+    pub fn new(relay_remote_addr: Multiaddr, timeout: Duration) -> Self {
         Self {
+            relay_remote_addr,
             outbound_requests: VecDeque::new(),
             pending_outbound: None,
             outbound_circuit_requests: FuturesTupleSet::new(
                 move || Delay::futures_timer(timeout),
                 10,
             ),
+            inbound_pending_circuits: FuturesSet::new(move || Delay::futures_timer(timeout), 10),
+            pending_events: VecDeque::new(),
         }
     }
+
+    /// 将出站电路的结果交给传输层，并上报给行为
+    fn finish_outbound(
+        &mut self,
+        request: NewOutboundBridgeRequest,
+        result: Result<Connection, CircuitFailure>,
+    ) {
+        let NewOutboundBridgeRequest {
+            dst_peer_id,
+            send_back,
+            timings,
+            ..
+        } = request;
+        let report = match &result {
+            Ok(_) => Ok(()),
+            Err(error) => {
+                tracing::debug!("Outbound circuit to {} failed: {}", dst_peer_id, error);
+                Err(error.clone())
+            }
+        };
+        let _ = send_back.send(result);
+        self.pending_events.push_back(Event::OutboundCircuit {
+            dst_peer_id,
+            timings,
+            result: report,
+        });
+    }
 }
 
 impl ConnectionHandler for Handler {
     type Action = NewOutboundBridgeRequest;
-    type Event = Infallible;
+    type Event = Event;
 
     fn handle_action(&mut self, action: Self::Action) {
         // 等待处理的请求
@@ -50,19 +101,53 @@ impl ConnectionHandler for Handler {
 
     fn poll(&mut self, cx: &mut Context<'_>) -> Poll<ConnectionHandlerEvent<Self::Event>> {
         loop {
+            if let Some(event) = self.pending_events.pop_front() {
+                return Poll::Ready(ConnectionHandlerEvent::Notify(event));
+            }
             match self.outbound_circuit_requests.poll_unpin(cx) {
-                Poll::Ready((Ok(Ok((stream, read_buffer))), send_back)) => {
-                    tracing::debug!("Outbound circuit request succeeded");
-                    let _ = send_back.send(Ok(Connection::new_accepted(stream, read_buffer)));
+                Poll::Ready((result, mut request)) => {
+                    request.timings.backend_connect = Some(request.stage_started.elapsed());
+                    let result = match result {
+                        Ok(Ok((stream, read_buffer))) => {
+                            tracing::debug!("Outbound circuit request succeeded");
+                            Ok(Connection::new_accepted(stream, read_buffer))
+                        }
+                        Ok(Err(error)) => Err(CircuitFailure::new(Stage::BackendConnect, error)),
+                        Err(_) => Err(CircuitFailure::new(
+                            Stage::BackendConnect,
+                            io::Error::new(io::ErrorKind::TimedOut, "Backend connect timed out"),
+                        )),
+                    };
+                    self.finish_outbound(request, result);
                     continue;
                 }
-                Poll::Ready((Ok(Err(error)), send_back)) => {
-                    tracing::debug!("Outbound circuit request failed: {}", error);
-                    let _ = send_back.send(Err(error));
+                Poll::Pending => {}
+            }
+            match self.inbound_pending_circuits.poll_unpin(cx) {
+                Poll::Ready(Ok(Ok(protocol::Relay {
+                    circuit,
+                    src_peer_id,
+                    dst_peer_id,
+                    src_relayed_addr,
+                }))) => {
+                    tracing::debug!("Inbound circuit from {} accepted", src_peer_id);
+                    let event = NewCircuitAccept {
+                        relay_remote_addr: self.relay_remote_addr.clone(),
+                        circuit,
+                        src_peer_id,
+                        dst_peer_id,
+                        src_relayed_addr,
+                    };
+                    return Poll::Ready(ConnectionHandlerEvent::Notify(Event::CircuitAccepted(
+                        Box::new(event),
+                    )));
+                }
+                Poll::Ready(Ok(Err(error))) => {
+                    tracing::debug!("Inbound circuit error: {:?}", error);
                     continue;
                 }
-                Poll::Ready((Err(error), _)) => {
-                    tracing::debug!("Outbound circuit request failed: {}", error);
+                Poll::Ready(Err(error)) => {
+                    tracing::debug!("Inbound circuit timed out: {:?}", error);
                     continue;
                 }
                 Poll::Pending => {}
@@ -76,6 +161,37 @@ impl ConnectionHandler for Handler {
     }
 }
 
+/// 在 duplex Swarm 中接受中继转来的连接
+impl InboundStreamHandler for Handler {
+    type InboundUpgrade = ReadyUpgrade<StreamProtocol>;
+    type InboundUserData = ();
+
+    fn listen_protocol(&self) -> SubstreamProtocol<Self::InboundUpgrade, Self::InboundUserData> {
+        SubstreamProtocol::new(ReadyUpgrade::new(protocol::PROTOCOL_NAME), ())
+    }
+
+    fn on_fully_negotiated(
+        &mut self,
+        _user_data: Self::InboundUserData,
+        stream: <Self::InboundUpgrade as InboundUpgradeSend>::Output,
+    ) {
+        let result = self
+            .inbound_pending_circuits
+            .try_push(protocol::handle_bridge_relay_connect(stream).boxed());
+        if result.is_err() {
+            tracing::warn!("Drop inbound circuit: because we are at capacity");
+        }
+    }
+
+    fn on_upgrade_error(
+        &mut self,
+        _user_data: Self::InboundUserData,
+        error: <Self::InboundUpgrade as InboundUpgradeSend>::Error,
+    ) {
+        tracing::debug!("Inbound circuit upgrade error: {:?}", error);
+    }
+}
+
 impl OutboundStreamHandler for Handler {
     type OutboundUpgrade = ReadyUpgrade<StreamProtocol>;
     type OutboundUserData = ();
@@ -85,23 +201,22 @@ impl OutboundStreamHandler for Handler {
         _user_data: Self::OutboundUserData,
         stream: <Self::OutboundUpgrade as OutboundUpgradeSend>::Output,
     ) {
-        let NewOutboundBridgeRequest {
-            dst_peer_id,
-            send_back,
-            ..
-        } = self
+        let mut request = self
             .pending_outbound
             .take()
             .expect("Pending request should exist");
+        let dst_peer_id = request.dst_peer_id;
 
         tracing::debug!(
             "Bridge upgrade successful for backend peer: {:?}",
             dst_peer_id
         );
+        request.timings.relay_negotiation = Some(request.stage_started.elapsed());
+        request.stage_started = Instant::now();
 
         let result = self.outbound_circuit_requests.try_push(
             protocol::make_bridge_connect(stream, dst_peer_id, vec![]).boxed(),
-            send_back,
+            request,
         );
 
         if result.is_err() {
@@ -115,52 +230,79 @@ impl OutboundStreamHandler for Handler {
         error: StreamUpgradeError<<Self::OutboundUpgrade as OutboundUpgradeSend>::Error>,
     ) {
         // 升级失败，通知请求者
-        let NewOutboundBridgeRequest {
-            dst_peer_id,
-            send_back,
-            ..
-        } = self
+        let mut request = self
             .pending_outbound
             .take()
             .expect("Pending request should exist");
+        request.timings.relay_negotiation = Some(request.stage_started.elapsed());
 
         let error = match error {
             StreamUpgradeError::Timeout => protocol::ConnectError::Io(io::Error::new(
                 io::ErrorKind::TimedOut,
                 "Outbound upgrade timed out",
             )),
-            StreamUpgradeError::NegotiationFailed => protocol::ConnectError::Unsupported,
+            StreamUpgradeError::NegotiationFailed { .. } => protocol::ConnectError::Unsupported,
+            StreamUpgradeError::RemoteReset => {
+                protocol::ConnectError::Io(io::ErrorKind::ConnectionReset.into())
+            }
             StreamUpgradeError::Io(err) => protocol::ConnectError::Io(err),
             StreamUpgradeError::Apply(_) => unreachable!("Apply error should not happen here"),
         };
-        tracing::debug!("Bridge upgrade error for peer {:?}: {}", dst_peer_id, error);
-        let _ = send_back.send(Err(error));
+        tracing::debug!(
+            "Bridge upgrade error for peer {:?}: {}",
+            request.dst_peer_id,
+            error
+        );
+        self.finish_outbound(
+            request,
+            Err(CircuitFailure::new(Stage::RelayNegotiation, error)),
+        );
     }
 
     fn poll_outbound_request(
         &mut self,
         _cx: &mut Context<'_>,
     ) -> Poll<SubstreamProtocol<Self::OutboundUpgrade, Self::OutboundUserData>> {
-        if self.pending_outbound.is_none() {
-            if let Some(request) = self.outbound_requests.pop_front() {
-                tracing::debug!(
-                    "Preparing outbound request to relay: {:?}",
-                    request.dst_peer_id
-                );
-                let upgrade = ReadyUpgrade::new(protocol::PROTOCOL_NAME);
-                self.pending_outbound = Some(request);
-                // 准备发送请求
-                return Poll::Ready(SubstreamProtocol::new(upgrade, ()));
-            }
+        if self.pending_outbound.is_none()
+            && let Some(mut request) = self.outbound_requests.pop_front()
+        {
+            request.stage_started = Instant::now();
+            tracing::debug!(
+                "Preparing outbound request to relay: {:?}",
+                request.dst_peer_id
+            );
+            let upgrade = ReadyUpgrade::new(protocol::PROTOCOL_NAME);
+            self.pending_outbound = Some(request);
+            // 准备发送请求
+            return Poll::Ready(SubstreamProtocol::new(upgrade, ()));
         }
         Poll::Pending
     }
 }
 
 pub struct NewOutboundBridgeRequest {
+    #[allow(dead_code)]
     pub(crate) dst_addresses: Vec<Multiaddr>,
     pub(crate) dst_peer_id: PeerId,
-    pub(crate) send_back: oneshot::Sender<Result<Connection, protocol::ConnectError>>,
+    pub(crate) send_back: oneshot::Sender<Result<Connection, CircuitFailure>>,
+    /// 当前阶段开始的时间
+    pub(crate) stage_started: Instant,
+    pub(crate) timings: CircuitTimings,
+}
+
+impl NewOutboundBridgeRequest {
+    pub(crate) fn new(
+        dst_peer_id: PeerId,
+        send_back: oneshot::Sender<Result<Connection, CircuitFailure>>,
+    ) -> Self {
+        Self {
+            dst_addresses: vec![],
+            dst_peer_id,
+            send_back,
+            stage_started: Instant::now(),
+            timings: CircuitTimings::default(),
+        }
+    }
 }
 
 impl fmt::Debug for NewOutboundBridgeRequest {
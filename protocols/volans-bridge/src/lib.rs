@@ -1,5 +1,4 @@
-use volans_core::{Multiaddr, multiaddr::Protocol};
-use volans_swarm::StreamProtocol;
+use volans_core::{Multiaddr, PeerId, multiaddr::Protocol};
 
 // 后端处理
 pub mod backend;
@@ -7,10 +6,27 @@ pub mod backend;
 pub mod client;
 // 中继服务，包括客户端和服务端
 pub mod relay;
+// 后端向中继申请名额
+pub mod reservation;
 
 pub(crate) mod protocol;
 pub mod transport;
 
+pub use protocol::{ConnectError, v2::StatusCode};
+
+/// 中继地址中 `/circuit` 之前的中继节点，即连接实际使用的中继
+pub fn relay_peer_id(addr: &Multiaddr) -> Option<PeerId> {
+    let mut relay = None;
+    for protocol in addr.iter() {
+        match protocol {
+            Protocol::Peer(peer_id) => relay = Some(peer_id),
+            Protocol::Circuit => return relay,
+            _ => {}
+        }
+    }
+    None
+}
+
 pub(crate) trait MultiaddrExt {
     fn is_circuit(&self) -> bool;
 }
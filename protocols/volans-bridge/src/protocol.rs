@@ -1,15 +1,18 @@
-use std::{io, str::FromStr};
+use std::{io, str::FromStr, time::Duration};
 
 use futures::{SinkExt, StreamExt};
 use volans_codec::{Bytes, Framed, FramedParts, ProtobufUviCodec};
 use volans_core::{Multiaddr, PeerId};
 use volans_swarm::{StreamProtocol, Substream};
 
-pub mod v1 {
-    include!(concat!(env!("OUT_DIR"), "/volans.bridge.v1.rs"));
+pub mod v2 {
+    include!(concat!(env!("OUT_DIR"), "/volans.bridge.v2.rs"));
 }
 
-pub(crate) const PROTOCOL_NAME: StreamProtocol = StreamProtocol::new("/v1/bridge");
+use v2::StatusCode;
+
+pub(crate) const PROTOCOL_NAME: StreamProtocol = StreamProtocol::new("/v2/bridge");
+pub(crate) const RESERVE_PROTOCOL_NAME: StreamProtocol = StreamProtocol::new("/v2/bridge/reserve");
 
 const MAX_MESSAGE_SIZE: usize = 1024; // 1 MB
 
@@ -20,10 +23,10 @@ pub(crate) async fn make_bridge_connect(
 ) -> Result<(Substream, Bytes), ConnectError> {
     let mut framed = Framed::new(
         io,
-        ProtobufUviCodec::<v1::BridgeConnect>::new(MAX_MESSAGE_SIZE),
+        ProtobufUviCodec::<v2::BridgeConnect>::new(MAX_MESSAGE_SIZE),
     );
-    let message = v1::BridgeConnect {
-        peer: Some(v1::Peer {
+    let message = v2::BridgeConnect {
+        peer: Some(v2::Peer {
             id: dst_peer_id.into_base58(),
             addresses: addresses.into_iter().map(|a| a.to_string()).collect(),
         }),
@@ -34,7 +37,7 @@ pub(crate) async fn make_bridge_connect(
 
     let parts = framed
         .into_parts()
-        .map_codec(|_| ProtobufUviCodec::<v1::BridgeStatus>::new(MAX_MESSAGE_SIZE));
+        .map_codec(|_| ProtobufUviCodec::<v2::BridgeStatus>::new(MAX_MESSAGE_SIZE));
 
     let mut framed = Framed::from_parts(parts);
     // 等待响应
@@ -44,22 +47,18 @@ pub(crate) async fn make_bridge_connect(
         "Failed to read status",
     )))??;
 
-    match status.code() {
-        v1::BridgeCode::Ok => {
-            let FramedParts {
-                io,
-                read_buffer,
-                write_buffer,
-                ..
-            } = framed.into_parts();
-            assert!(
-                write_buffer.is_empty(),
-                "Expect a flushed Framed to have an empty write buffer."
-            );
-            Ok((io, read_buffer.freeze()))
-        }
-        code => Err(ConnectError::BridgeCode(code)),
-    }
+    check_status(status.code)?;
+    let FramedParts {
+        io,
+        read_buffer,
+        write_buffer,
+        ..
+    } = framed.into_parts();
+    assert!(
+        write_buffer.is_empty(),
+        "Expect a flushed Framed to have an empty write buffer."
+    );
+    Ok((io, read_buffer.freeze()))
 }
 
 pub(crate) async fn make_bridge_relay_connect(
@@ -70,9 +69,9 @@ pub(crate) async fn make_bridge_relay_connect(
 ) -> Result<(Substream, Bytes), ConnectError> {
     let mut dst_framed = Framed::new(
         io,
-        ProtobufUviCodec::<v1::BridgeRelayConnect>::new(MAX_MESSAGE_SIZE),
+        ProtobufUviCodec::<v2::BridgeRelayConnect>::new(MAX_MESSAGE_SIZE),
     );
-    let message = v1::BridgeRelayConnect {
+    let message = v2::BridgeRelayConnect {
         src_peer_id: src_peer_id.into_base58(),
         dst_peer_id: dst_peer_id.into_base58(),
         src_relayed_addr: relayed_addr.to_string(),
@@ -82,7 +81,7 @@ pub(crate) async fn make_bridge_relay_connect(
 
     let parts = dst_framed
         .into_parts()
-        .map_codec(|_| ProtobufUviCodec::<v1::BridgeStatus>::new(MAX_MESSAGE_SIZE));
+        .map_codec(|_| ProtobufUviCodec::<v2::BridgeStatus>::new(MAX_MESSAGE_SIZE));
 
     let mut dst_framed = Framed::from_parts(parts);
 
@@ -91,39 +90,92 @@ pub(crate) async fn make_bridge_relay_connect(
         io::ErrorKind::UnexpectedEof,
         "Failed to read status",
     ))??;
-    match status.code() {
-        v1::BridgeCode::Ok => {
-            let FramedParts {
-                io,
-                read_buffer,
-                write_buffer,
-                ..
-            } = dst_framed.into_parts();
-            assert!(
-                write_buffer.is_empty(),
-                "Expect a flushed Framed to have an empty write buffer."
-            );
-            Ok((io, read_buffer.freeze()))
-        }
-        code => Err(ConnectError::BridgeCode(code)),
+    check_status(status.code)?;
+    let FramedParts {
+        io,
+        read_buffer,
+        write_buffer,
+        ..
+    } = dst_framed.into_parts();
+    assert!(
+        write_buffer.is_empty(),
+        "Expect a flushed Framed to have an empty write buffer."
+    );
+    Ok((io, read_buffer.freeze()))
+}
+
+// 向中继服务申请中继名额，返回实际授予的有效期
+pub(crate) async fn make_reserve(io: Substream, ttl: Duration) -> Result<Duration, ConnectError> {
+    let mut framed = Framed::new(
+        io,
+        ProtobufUviCodec::<v2::BridgeReserve>::new(MAX_MESSAGE_SIZE),
+    );
+    framed
+        .send(v2::BridgeReserve { ttl: ttl.as_secs() })
+        .await?;
+    framed.flush().await?;
+
+    let parts = framed
+        .into_parts()
+        .map_codec(|_| ProtobufUviCodec::<v2::BridgeReservation>::new(MAX_MESSAGE_SIZE));
+    let mut framed = Framed::from_parts(parts);
+
+    let reservation = framed.next().await.ok_or(io::Error::new(
+        io::ErrorKind::UnexpectedEof,
+        "Failed to read reservation",
+    ))??;
+    check_status(reservation.code)?;
+    Ok(Duration::from_secs(reservation.ttl))
+}
+
+/// 对端回复的状态码，未知的状态码视为消息格式错误
+fn check_status(code: i32) -> Result<(), ConnectError> {
+    match StatusCode::try_from(code) {
+        Ok(StatusCode::Ok) => Ok(()),
+        Ok(code) => Err(ConnectError::Denied(code)),
+        Err(_) => Err(ConnectError::Denied(StatusCode::MalformedMessage)),
     }
 }
 
 #[derive(Debug, thiserror::Error)]
-pub(crate) enum ConnectError {
+pub enum ConnectError {
     #[error("Bridge unsupported")]
     Unsupported,
-    #[error("Invalid protocol")]
-    BridgeCode(v1::BridgeCode),
+    /// 中继或目标节点以状态码拒绝了请求
+    #[error("Bridge denied: {}", .0.as_str_name())]
+    Denied(StatusCode),
     #[error("I/O error")]
     Io(#[from] io::Error),
 }
 
+/// I/O 错误按类型与描述复制，以便同时交给传输层与行为事件
+impl Clone for ConnectError {
+    fn clone(&self) -> Self {
+        match self {
+            ConnectError::Unsupported => ConnectError::Unsupported,
+            ConnectError::Denied(code) => ConnectError::Denied(*code),
+            ConnectError::Io(error) => {
+                ConnectError::Io(io::Error::new(error.kind(), error.to_string()))
+            }
+        }
+    }
+}
+
+impl ConnectError {
+    /// 对端拒绝时的状态码
+    pub fn status(&self) -> Option<StatusCode> {
+        match self {
+            ConnectError::Denied(code) => Some(*code),
+            _ => None,
+        }
+    }
+}
+
 // 处理一个桥接连接请求
 pub(crate) async fn handle_bridge_connect(io: Substream) -> Result<Bridge, Error> {
     let mut framed = Framed::new(
         io,
-        ProtobufUviCodec::<v1::BridgeConnect>::new(MAX_MESSAGE_SIZE),
+        ProtobufUviCodec::<v2::BridgeConnect>::new(MAX_MESSAGE_SIZE),
     );
 
     let request = framed.next().await.ok_or(Error::Io(io::Error::new(
@@ -131,18 +183,17 @@ pub(crate) async fn handle_bridge_connect(io: Substream) -> Result<Bridge, Error
         "Failed to read request",
     )))??;
 
-    let peer = request.peer.ok_or(ProtocolError::MissingPeer)?;
-
-    let dst_peer_id = PeerId::try_from_base58(&peer.id).map_err(ProtocolError::from)?;
-    let dst_addresses = peer
-        .addresses
-        .into_iter()
-        .map(|r| Multiaddr::from_str(&r))
-        .collect::<Result<Vec<_>, _>>()
-        .map_err(ProtocolError::from)?;
+    let circuit = Circuit::new(framed);
+    let (dst_peer_id, dst_addresses) = match parse_peer(request.peer) {
+        Ok(parsed) => parsed,
+        Err(e) => {
+            circuit.deny(StatusCode::MalformedMessage).await?;
+            return Err(e.into());
+        }
+    };
 
     Ok(Bridge {
-        circuit: Circuit::new(framed),
+        circuit,
         dst_peer_id,
         dst_addresses,
     })
@@ -152,18 +203,21 @@ pub(crate) async fn handle_bridge_connect(io: Substream) -> Result<Bridge, Error
 pub(crate) async fn handle_bridge_relay_connect(io: Substream) -> Result<Relay, Error> {
     let mut framed = Framed::new(
         io,
-        ProtobufUviCodec::<v1::BridgeRelayConnect>::new(MAX_MESSAGE_SIZE),
+        ProtobufUviCodec::<v2::BridgeRelayConnect>::new(MAX_MESSAGE_SIZE),
     );
     let request = framed.next().await.ok_or(Error::Io(io::Error::new(
         io::ErrorKind::UnexpectedEof,
         "Failed to read relay request",
     )))??;
 
-    let src_peer_id = PeerId::try_from_base58(&request.src_peer_id).map_err(ProtocolError::from)?;
-    let dst_peer_id = PeerId::try_from_base58(&request.dst_peer_id).map_err(ProtocolError::from)?;
-    let src_relayed_addr =
-        Multiaddr::from_str(&request.src_relayed_addr).map_err(ProtocolError::from)?;
     let circuit = Circuit::new(framed);
+    let (src_peer_id, dst_peer_id, src_relayed_addr) = match parse_relay_connect(&request) {
+        Ok(parsed) => parsed,
+        Err(e) => {
+            circuit.deny(StatusCode::MalformedMessage).await?;
+            return Err(e.into());
+        }
+    };
     Ok(Relay {
         circuit,
         src_peer_id,
@@ -172,8 +226,76 @@ pub(crate) async fn handle_bridge_relay_connect(io: Substream) -> Result<Relay,
     })
 }
 
+fn parse_peer(peer: Option<v2::Peer>) -> Result<(PeerId, Vec<Multiaddr>), ProtocolError> {
+    let peer = peer.ok_or(ProtocolError::MissingPeer)?;
+    let peer_id = PeerId::try_from_base58(&peer.id)?;
+    let addresses = peer
+        .addresses
+        .into_iter()
+        .map(|r| Multiaddr::from_str(&r))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok((peer_id, addresses))
+}
+
+fn parse_relay_connect(
+    request: &v2::BridgeRelayConnect,
+) -> Result<(PeerId, PeerId, Multiaddr), ProtocolError> {
+    Ok((
+        PeerId::try_from_base58(&request.src_peer_id)?,
+        PeerId::try_from_base58(&request.dst_peer_id)?,
+        Multiaddr::from_str(&request.src_relayed_addr)?,
+    ))
+}
+
+// 处理一个中继名额申请
+pub(crate) async fn handle_reserve(io: Substream) -> Result<Reservation, Error> {
+    let mut framed = Framed::new(
+        io,
+        ProtobufUviCodec::<v2::BridgeReserve>::new(MAX_MESSAGE_SIZE),
+    );
+    let request = framed.next().await.ok_or(Error::Io(io::Error::new(
+        io::ErrorKind::UnexpectedEof,
+        "Failed to read reserve request",
+    )))??;
+
+    let parts = framed
+        .into_parts()
+        .map_codec(|_| ProtobufUviCodec::<v2::BridgeReservation>::new(MAX_MESSAGE_SIZE));
+    Ok(Reservation {
+        framed: Framed::from_parts(parts),
+        ttl: Duration::from_secs(request.ttl),
+    })
+}
+
+pub(crate) struct Reservation {
+    framed: Framed<Substream, ProtobufUviCodec<v2::BridgeReservation>>,
+    pub(crate) ttl: Duration,
+}
+
+impl Reservation {
+    pub(crate) async fn accept(self, ttl: Duration) -> Result<(), io::Error> {
+        self.send(StatusCode::Ok, ttl).await
+    }
+
+    pub(crate) async fn deny(self, code: StatusCode) -> Result<(), io::Error> {
+        self.send(code, Duration::ZERO).await
+    }
+
+    async fn send(mut self, code: StatusCode, ttl: Duration) -> Result<(), io::Error> {
+        self.framed
+            .send(v2::BridgeReservation {
+                code: code as i32,
+                ttl: ttl.as_secs(),
+            })
+            .await?;
+        self.framed.flush().await?;
+        self.framed.close().await?;
+        Ok(())
+    }
+}
+
 pub(crate) struct Circuit {
-    framed: Framed<Substream, ProtobufUviCodec<v1::BridgeStatus>>,
+    framed: Framed<Substream, ProtobufUviCodec<v2::BridgeStatus>>,
 }
 
 impl Circuit {
@@ -183,7 +305,7 @@ impl Circuit {
     {
         let parts = framed
             .into_parts()
-            .map_codec(|_| ProtobufUviCodec::<v1::BridgeStatus>::new(MAX_MESSAGE_SIZE));
+            .map_codec(|_| ProtobufUviCodec::<v2::BridgeStatus>::new(MAX_MESSAGE_SIZE));
 
         let framed = Framed::from_parts(parts);
 
@@ -199,7 +321,7 @@ pub(crate) struct Bridge {
 
 impl Circuit {
     pub(crate) async fn accept(mut self) -> Result<(Substream, Bytes), io::Error> {
-        self.send(v1::BridgeCode::Ok).await?;
+        self.send(StatusCode::Ok).await?;
 
         let FramedParts {
             io,
@@ -215,14 +337,14 @@ impl Circuit {
         Ok((io, read_buffer.freeze()))
     }
 
-    pub(crate) async fn deny(mut self, code: v1::BridgeCode) -> Result<(), io::Error> {
+    pub(crate) async fn deny(mut self, code: StatusCode) -> Result<(), io::Error> {
         self.send(code).await?;
         Ok(())
     }
 
-    async fn send(&mut self, code: v1::BridgeCode) -> Result<(), io::Error> {
+    async fn send(&mut self, code: StatusCode) -> Result<(), io::Error> {
         self.framed
-            .send(v1::BridgeStatus { code: code as i32 })
+            .send(v2::BridgeStatus { code: code as i32 })
             .await?;
         self.framed.flush().await?;
         Ok(())
@@ -247,7 +369,7 @@ pub(crate) enum Error {
 impl From<Error> for io::Error {
     fn from(e: Error) -> Self {
         match e {
-            Error::Protocol(e) => io::Error::new(io::ErrorKind::Other, e),
+            Error::Protocol(e) => io::Error::other(e),
             Error::Io(e) => e,
         }
     }
@@ -263,3 +385,19 @@ pub(crate) enum ProtocolError {
     #[error(transparent)]
     InvalidMultiaddr(#[from] volans_core::multiaddr::Error),
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn status_code_to_error() {
+        assert!(check_status(StatusCode::Ok as i32).is_ok());
+        let error = check_status(StatusCode::NoReservation as i32).unwrap_err();
+        assert_eq!(error.status(), Some(StatusCode::NoReservation));
+        assert_eq!(error.to_string(), "Bridge denied: NO_RESERVATION");
+        // 未知的状态码不能被当作成功
+        let error = check_status(42).unwrap_err();
+        assert_eq!(error.status(), Some(StatusCode::MalformedMessage));
+    }
+}
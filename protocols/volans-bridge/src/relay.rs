@@ -10,10 +10,22 @@ pub mod client;
 
 pub mod server;
 
+mod resource;
+
+pub use resource::{CircuitInfo, CircuitLimit, Config, Denied};
+
+pub(crate) use resource::{CircuitGuard, Resources};
+
 pub fn new(local_peer_id: PeerId) -> (server::Behavior, client::Behavior) {
+    with_config(local_peer_id, Config::default())
+}
+
+/// 使用指定的资源限制创建中继服务
+pub fn with_config(local_peer_id: PeerId, config: Config) -> (server::Behavior, client::Behavior) {
     let (tx, rx) = mpsc::unbounded();
 
-    let server_behavior = server::Behavior::new(local_peer_id, tx);
+    let server_behavior =
+        server::Behavior::with_resources(local_peer_id, tx, Resources::new(config));
     let client_behavior = client::Behavior::new(rx);
     (server_behavior, client_behavior)
 }
@@ -23,9 +35,10 @@ pub struct CircuitRequest {
     pub relayed_addr: Multiaddr,
     pub dst_peer_id: PeerId,
     pub dst_addresses: Vec<Multiaddr>,
-    pub circuit: protocol::Circuit,
+    pub(crate) circuit: protocol::Circuit,
     pub src_peer_id: PeerId,
     pub src_connection_id: ConnectionId,
+    pub(crate) guard: CircuitGuard,
 }
 
 impl fmt::Debug for CircuitRequest {
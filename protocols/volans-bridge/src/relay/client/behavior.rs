@@ -1,7 +1,7 @@
 use std::{
     collections::{HashMap, VecDeque},
-    convert::Infallible,
     task::{Context, Poll},
+    time::Duration,
 };
 
 use either::Either;
@@ -11,11 +11,11 @@ use volans_swarm::{
     BehaviorEvent, ConnectionDenied, ConnectionId, DialOpts, NetworkBehavior,
     NetworkOutgoingBehavior, THandlerAction, THandlerEvent,
     behavior::NotifyHandler,
-    error::{ConnectionError, DialError},
+    error::{CloseReason, DialError},
     handler::DummyHandler,
 };
 
-use crate::relay::CircuitRequest;
+use crate::relay::{CircuitLimit, CircuitRequest};
 
 use super::handler;
 
@@ -23,7 +23,7 @@ use super::handler;
 pub struct Behavior {
     request_receiver: mpsc::UnboundedReceiver<CircuitRequest>,
     dial_requests: HashMap<ConnectionId, CircuitRequest>,
-    pending_events: VecDeque<BehaviorEvent<Infallible, THandlerAction<Self>>>,
+    pending_events: VecDeque<BehaviorEvent<Event, THandlerAction<Self>>>,
 }
 
 impl Behavior {
@@ -38,7 +38,7 @@ impl Behavior {
 
 impl NetworkBehavior for Behavior {
     type ConnectionHandler = Either<DummyHandler, handler::Handler>;
-    type Event = Infallible;
+    type Event = Event;
 
     fn on_connection_handler_event(
         &mut self,
@@ -46,19 +46,47 @@ impl NetworkBehavior for Behavior {
         _peer_id: PeerId,
         event: THandlerEvent<Self>,
     ) {
-        unimplemented!("Unexpected event: {:?}", event);
+        let event = match event {
+            Either::Right(handler::Event::Opened {
+                src_peer_id,
+                dst_peer_id,
+            }) => Event::CircuitOpened {
+                src_peer_id,
+                dst_peer_id,
+            },
+            Either::Right(handler::Event::Closed {
+                src_peer_id,
+                dst_peer_id,
+                bytes_forwarded,
+                duration,
+            }) => Event::CircuitClosed {
+                src_peer_id,
+                dst_peer_id,
+                bytes_forwarded,
+                duration,
+            },
+            Either::Right(handler::Event::LimitReached {
+                src_peer_id,
+                dst_peer_id,
+                limit,
+            }) => Event::CircuitLimitReached {
+                src_peer_id,
+                dst_peer_id,
+                limit,
+            },
+        };
+        self.pending_events
+            .push_back(BehaviorEvent::Behavior(event));
     }
 
     fn poll(
         &mut self,
         _cx: &mut Context<'_>,
     ) -> Poll<BehaviorEvent<Self::Event, THandlerAction<Self>>> {
-        loop {
-            if let Some(event) = self.pending_events.pop_front() {
-                return Poll::Ready(event);
-            }
-            return Poll::Pending;
+        if let Some(event) = self.pending_events.pop_front() {
+            return Poll::Ready(event);
         }
+        Poll::Pending
     }
 }
 
@@ -97,7 +125,7 @@ impl NetworkOutgoingBehavior for Behavior {
         id: ConnectionId,
         _peer_id: PeerId,
         _addr: &Multiaddr,
-        _reason: Option<&ConnectionError>,
+        _reason: &CloseReason,
     ) {
         if let Some(request) = self.dial_requests.remove(&id) {
             // 处理拨号失败
@@ -130,3 +158,26 @@ impl NetworkOutgoingBehavior for Behavior {
         Poll::Ready(dial_opts)
     }
 }
+
+#[derive(Debug)]
+#[allow(clippy::enum_variant_names)]
+pub enum Event {
+    /// 中继开始在两端之间转发数据
+    CircuitOpened {
+        src_peer_id: PeerId,
+        dst_peer_id: PeerId,
+    },
+    /// 中继结束，附带累计转发的字节数和存活时间
+    CircuitClosed {
+        src_peer_id: PeerId,
+        dst_peer_id: PeerId,
+        bytes_forwarded: u64,
+        duration: Duration,
+    },
+    /// 中继触发转发字节数、存活时间或空闲时间限制而被关闭
+    CircuitLimitReached {
+        src_peer_id: PeerId,
+        dst_peer_id: PeerId,
+        limit: CircuitLimit,
+    },
+}
@@ -1,32 +1,41 @@
 use std::{
     collections::VecDeque,
-    convert::Infallible,
-    fmt, io,
+    io,
     pin::Pin,
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
     task::{Context, Poll},
     time::Duration,
 };
 
 use futures::{
-    AsyncBufRead, AsyncRead, AsyncWrite, AsyncWriteExt, FutureExt, StreamExt, channel::oneshot,
-    future::BoxFuture, io::BufReader, ready, stream::FuturesUnordered,
+    AsyncBufRead, AsyncRead, AsyncWrite, AsyncWriteExt, FutureExt, StreamExt, future::BoxFuture,
+    io::BufReader, ready, stream::FuturesUnordered,
 };
 use futures_bounded::FuturesSet;
+use futures_timer::Delay;
 use volans_codec::Bytes;
-use volans_core::{Multiaddr, OutboundUpgrade, PeerId, UpgradeInfo, upgrade::ReadyUpgrade};
+use volans_core::{PeerId, upgrade::ReadyUpgrade};
 use volans_swarm::{
-    ConnectionHandler, ConnectionHandlerEvent, OutboundStreamHandler, OutboundUpgradeSend,
-    StreamProtocol, StreamUpgradeError, Substream, SubstreamProtocol,
+    ConnectionHandler, ConnectionHandlerEvent, KeepAlive, OutboundStreamHandler,
+    OutboundUpgradeSend, StreamProtocol, StreamUpgradeError, Substream, SubstreamProtocol,
+    error::SubstreamIdleTimeout,
 };
 
-use crate::{protocol, relay::CircuitRequest};
+use crate::{
+    StatusCode, protocol,
+    relay::{CircuitGuard, CircuitLimit, CircuitRequest},
+};
 
 /// 中继服务器处理连接Backend的请求
 pub struct Handler {
     requested_streams: VecDeque<CircuitRequest>,
     /// 待向Backend发送的请求
     pending_streams: VecDeque<CircuitRequest>,
-    circuits: FuturesUnordered<BoxFuture<'static, Result<(), io::Error>>>,
+    pending_events: VecDeque<Event>,
+    circuits: FuturesUnordered<BoxFuture<'static, (CircuitGuard, Result<(), CircuitError>)>>,
     /// 正在和Backend建立连接的流
     outbound_circuit_requests: FuturesSet<Result<CircuitParts, protocol::ConnectError>>,
 }
@@ -36,6 +45,7 @@ impl Handler {
         Self {
             requested_streams: VecDeque::new(),
             pending_streams: VecDeque::new(),
+            pending_events: VecDeque::new(),
             circuits: FuturesUnordered::new(),
             outbound_circuit_requests: FuturesSet::new(
                 || futures_bounded::Delay::futures_timer(Duration::from_secs(15)),
@@ -47,7 +57,7 @@ impl Handler {
 
 impl ConnectionHandler for Handler {
     type Action = CircuitRequest;
-    type Event = Infallible;
+    type Event = Event;
 
     fn handle_action(&mut self, action: Self::Action) {
         // 待向Backend发送的请求
@@ -59,16 +69,33 @@ impl ConnectionHandler for Handler {
         self.requested_streams.push_back(action);
     }
 
+    fn keep_alive(&self) -> KeepAlive {
+        // 转发中的中继保持连接
+        KeepAlive::from(!self.requested_streams.is_empty() || !self.circuits.is_empty())
+    }
+
     fn poll(&mut self, cx: &mut Context<'_>) -> Poll<ConnectionHandlerEvent<Self::Event>> {
         loop {
+            if let Some(event) = self.pending_events.pop_front() {
+                return Poll::Ready(ConnectionHandlerEvent::Notify(event));
+            }
             match self.outbound_circuit_requests.poll_unpin(cx) {
                 Poll::Ready(Ok(Ok(CircuitParts {
                     mut src_stream,
                     src_pending_data,
-                    dst_peer_id: _,
+                    src_peer_id,
+                    dst_peer_id,
                     mut dst_stream,
                     dst_pending_data,
+                    mut guard,
                 }))) => {
+                    let bytes_forwarded = guard.establish();
+                    let max_bytes = guard.max_bytes();
+                    let max_duration = guard.max_duration();
+                    if let Some(idle) = guard.max_idle() {
+                        src_stream.set_idle_timeout(idle);
+                        dst_stream.set_idle_timeout(idle);
+                    }
                     // 创建流之间的复制任务
                     let copy_fut = async move {
                         let (result_1, result_2) = futures::future::join(
@@ -79,13 +106,20 @@ impl ConnectionHandler for Handler {
                         result_1?;
                         result_2?;
 
-                        let copy_fut = CopyFuture::new(src_stream, dst_stream);
+                        let copy_fut = CopyFuture::new(src_stream, dst_stream, bytes_forwarded)
+                            .with_limits(max_bytes, max_duration);
 
                         tracing::info!("Copy ...stream");
                         copy_fut.await?;
                         Ok(())
                     };
-                    self.circuits.push(copy_fut.boxed());
+                    // 复制结束后随 guard 归还中继名额
+                    self.circuits
+                        .push(copy_fut.map(move |result| (guard, result)).boxed());
+                    self.pending_events.push_back(Event::Opened {
+                        src_peer_id,
+                        dst_peer_id,
+                    });
                     continue;
                 }
                 Poll::Ready(Ok(Err(e))) => {
@@ -100,12 +134,32 @@ impl ConnectionHandler for Handler {
             }
 
             match self.circuits.poll_next_unpin(cx) {
-                Poll::Ready(Some(Ok(()))) => {
-                    tracing::debug!("Circuit copy completed successfully");
-                    continue;
-                }
-                Poll::Ready(Some(Err(e))) => {
-                    tracing::error!("Circuit copy failed: {:?}", e);
+                Poll::Ready(Some((guard, result))) => {
+                    let src_peer_id = guard.src_peer_id();
+                    let dst_peer_id = guard.dst_peer_id();
+                    match result {
+                        Ok(()) => tracing::debug!("Circuit copy completed successfully"),
+                        Err(CircuitError::Limit(limit)) => {
+                            tracing::debug!(
+                                "Circuit {:?} -> {:?} reached limit: {:?}",
+                                src_peer_id,
+                                dst_peer_id,
+                                limit
+                            );
+                            self.pending_events.push_back(Event::LimitReached {
+                                src_peer_id,
+                                dst_peer_id,
+                                limit,
+                            });
+                        }
+                        Err(e) => tracing::error!("Circuit copy failed: {:?}", e),
+                    }
+                    self.pending_events.push_back(Event::Closed {
+                        src_peer_id,
+                        dst_peer_id,
+                        bytes_forwarded: guard.bytes_forwarded(),
+                        duration: guard.duration(),
+                    });
                     continue;
                 }
 
@@ -137,6 +191,7 @@ impl OutboundStreamHandler for Handler {
             circuit,
             src_peer_id,
             src_connection_id,
+            guard,
         } = self.pending_streams.pop_front().expect("No pending stream");
         // 将流与流之间进行绑定
         tracing::debug!(
@@ -152,11 +207,10 @@ impl OutboundStreamHandler for Handler {
         let fut = async move {
             let (dst_stream, dst_read_buffer) = match connect_fut.await {
                 Ok(dst) => dst,
-                Err(protocol::ConnectError::BridgeCode(code)) => {
-                    circuit.deny(code).await?;
-                    return Err(protocol::ConnectError::BridgeCode(code));
-                }
                 Err(e) => {
+                    // 目标拒绝时转告源端，其余错误视为无法连接目标
+                    let code = e.status().unwrap_or(StatusCode::ConnectionFailed);
+                    circuit.deny(code).await?;
                     return Err(e);
                 }
             };
@@ -164,9 +218,11 @@ impl OutboundStreamHandler for Handler {
             Ok(CircuitParts {
                 src_stream,
                 src_pending_data: src_read_buffer,
+                src_peer_id,
                 dst_peer_id,
                 dst_stream,
                 dst_pending_data: dst_read_buffer,
+                guard,
             })
         };
         let result = self.outbound_circuit_requests.try_push(fut.boxed());
@@ -204,21 +260,49 @@ impl OutboundStreamHandler for Handler {
     }
 }
 
-pub struct NewCircuitRequest {
-    pub sender: oneshot::Sender<Result<Substream, StreamUpgradeError<Infallible>>>,
+#[derive(Debug)]
+pub enum Event {
+    Opened {
+        src_peer_id: PeerId,
+        dst_peer_id: PeerId,
+    },
+    Closed {
+        src_peer_id: PeerId,
+        dst_peer_id: PeerId,
+        bytes_forwarded: u64,
+        duration: Duration,
+    },
+    /// 中继触发转发字节数、存活时间或空闲时间限制而被关闭
+    LimitReached {
+        src_peer_id: PeerId,
+        dst_peer_id: PeerId,
+        limit: CircuitLimit,
+    },
 }
 
-impl fmt::Debug for NewCircuitRequest {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        f.debug_struct("NewCircuitRequest")
-            .field("sender", &self.sender)
-            .finish()
+#[derive(Debug, thiserror::Error)]
+enum CircuitError {
+    #[error("I/O error")]
+    Io(io::Error),
+    #[error("Circuit limit reached: {0:?}")]
+    Limit(CircuitLimit),
+}
+
+impl From<io::Error> for CircuitError {
+    fn from(error: io::Error) -> Self {
+        if SubstreamIdleTimeout::is(&error) {
+            return CircuitError::Limit(CircuitLimit::Idle);
+        }
+        CircuitError::Io(error)
     }
 }
 
 struct CopyFuture<S, D> {
     src: BufReader<S>,
     dst: BufReader<D>,
+    max_bytes: Option<u64>,
+    bytes_forwarded: Arc<AtomicU64>,
+    deadline: Option<Delay>,
 }
 
 impl<S, D> CopyFuture<S, D>
@@ -226,12 +310,21 @@ where
     S: AsyncRead + AsyncWrite + Unpin,
     D: AsyncRead + AsyncWrite + Unpin,
 {
-    pub fn new(src: S, dst: D) -> Self {
+    pub fn new(src: S, dst: D, bytes_forwarded: Arc<AtomicU64>) -> Self {
         Self {
             src: BufReader::new(src),
             dst: BufReader::new(dst),
+            max_bytes: None,
+            bytes_forwarded,
+            deadline: None,
         }
     }
+
+    pub fn with_limits(mut self, max_bytes: Option<u64>, max_duration: Option<Duration>) -> Self {
+        self.max_bytes = max_bytes;
+        self.deadline = max_duration.map(Delay::new);
+        self
+    }
 }
 
 impl<S, D> Future for CopyFuture<S, D>
@@ -239,10 +332,15 @@ where
     S: AsyncRead + AsyncWrite + Unpin,
     D: AsyncRead + AsyncWrite + Unpin,
 {
-    type Output = io::Result<()>;
+    type Output = Result<(), CircuitError>;
 
     fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
         let this = &mut *self;
+        if let Some(deadline) = this.deadline.as_mut()
+            && deadline.poll_unpin(cx).is_ready()
+        {
+            return Poll::Ready(Err(CircuitError::Limit(CircuitLimit::Duration)));
+        }
         loop {
             enum Status {
                 Pending,
@@ -250,18 +348,29 @@ where
                 Progressed,
             }
             let src_status = match forward_data(&mut this.src, &mut this.dst, cx) {
-                Poll::Ready(Err(e)) => return Poll::Ready(Err(e)),
+                Poll::Ready(Err(e)) => return Poll::Ready(Err(e.into())),
                 Poll::Ready(Ok(0)) => Status::Done,
-                Poll::Ready(Ok(_)) => Status::Progressed,
+                Poll::Ready(Ok(n)) => {
+                    this.bytes_forwarded.fetch_add(n, Ordering::Relaxed);
+                    Status::Progressed
+                }
                 Poll::Pending => Status::Pending,
             };
 
             let dst_status = match forward_data(&mut this.dst, &mut this.src, cx) {
-                Poll::Ready(Err(e)) => return Poll::Ready(Err(e)),
+                Poll::Ready(Err(e)) => return Poll::Ready(Err(e.into())),
                 Poll::Ready(Ok(0)) => Status::Done,
-                Poll::Ready(Ok(_)) => Status::Progressed,
+                Poll::Ready(Ok(n)) => {
+                    this.bytes_forwarded.fetch_add(n, Ordering::Relaxed);
+                    Status::Progressed
+                }
                 Poll::Pending => Status::Pending,
             };
+            if let Some(max_bytes) = this.max_bytes
+                && this.bytes_forwarded.load(Ordering::Relaxed) > max_bytes
+            {
+                return Poll::Ready(Err(CircuitError::Limit(CircuitLimit::Bytes)));
+            }
             match (src_status, dst_status) {
                 (Status::Done, Status::Done) => return Poll::Ready(Ok(())),
                 (Status::Progressed, _) | (_, Status::Progressed) => {}
@@ -297,7 +406,7 @@ fn forward_data<S: AsyncBufRead + Unpin, D: AsyncWrite + Unpin>(
         "Forwarding {} bytes: {:?}, str:{}",
         buffer.len(),
         buffer,
-        String::from_utf8_lossy(&buffer)
+        String::from_utf8_lossy(buffer)
     );
 
     let i = ready!(Pin::new(dst).poll_write(cx, buffer))?;
@@ -312,7 +421,9 @@ fn forward_data<S: AsyncBufRead + Unpin, D: AsyncWrite + Unpin>(
 struct CircuitParts {
     src_stream: Substream,
     src_pending_data: Bytes,
+    src_peer_id: PeerId,
     dst_peer_id: PeerId,
     dst_stream: Substream,
     dst_pending_data: Bytes,
+    guard: CircuitGuard,
 }
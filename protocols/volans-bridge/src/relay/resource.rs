@@ -0,0 +1,344 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+use volans_core::PeerId;
+
+use crate::StatusCode;
+
+/// 中继资源限制
+#[derive(Debug, Clone)]
+pub struct Config {
+    reservation_required: bool,
+    max_reservations: usize,
+    max_reservation_ttl: Duration,
+    max_circuits_per_peer: usize,
+    max_circuit_bytes: Option<u64>,
+    max_circuit_duration: Option<Duration>,
+    max_circuit_idle: Option<Duration>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            reservation_required: false,
+            max_reservations: 128,
+            max_reservation_ttl: Duration::from_secs(60 * 60),
+            max_circuits_per_peer: 16,
+            max_circuit_bytes: None,
+            max_circuit_duration: None,
+            max_circuit_idle: None,
+        }
+    }
+}
+
+impl Config {
+    /// 目标节点必须持有有效的名额才允许建立中继
+    pub fn with_reservation_required(mut self, required: bool) -> Self {
+        self.reservation_required = required;
+        self
+    }
+
+    pub fn with_max_reservations(mut self, max: usize) -> Self {
+        self.max_reservations = max;
+        self
+    }
+
+    pub fn with_max_reservation_ttl(mut self, ttl: Duration) -> Self {
+        self.max_reservation_ttl = ttl;
+        self
+    }
+
+    /// 单个节点（作为源或目标）同时存在的中继数量上限
+    pub fn with_max_circuits_per_peer(mut self, max: usize) -> Self {
+        self.max_circuits_per_peer = max;
+        self
+    }
+
+    /// 单个中继双向转发的字节数上限
+    pub fn with_max_circuit_bytes(mut self, max: u64) -> Self {
+        self.max_circuit_bytes = Some(max);
+        self
+    }
+
+    /// 单个中继的最长存活时间
+    pub fn with_max_circuit_duration(mut self, duration: Duration) -> Self {
+        self.max_circuit_duration = Some(duration);
+        self
+    }
+
+    /// 中继任一端的子流没有读写进展的最长时间
+    pub fn with_max_circuit_idle(mut self, duration: Duration) -> Self {
+        self.max_circuit_idle = Some(duration);
+        self
+    }
+}
+
+/// 拒绝名额申请或中继请求的原因
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Denied {
+    /// 目标节点没有有效的名额
+    NoReservation,
+    /// 名额数量已达上限
+    ReservationLimit,
+    /// 节点的并发中继数量已达上限
+    CircuitLimit(PeerId),
+}
+
+impl Denied {
+    pub(crate) fn code(&self) -> StatusCode {
+        match self {
+            Denied::NoReservation => StatusCode::NoReservation,
+            Denied::ReservationLimit => StatusCode::ReservationRefused,
+            Denied::CircuitLimit(_) => StatusCode::ResourceLimitExceeded,
+        }
+    }
+}
+
+/// 中继运行中触发的限制
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CircuitLimit {
+    Bytes,
+    Duration,
+    /// 子流空闲超时
+    Idle,
+}
+
+/// 正在转发数据的中继
+#[derive(Debug, Clone)]
+pub struct CircuitInfo {
+    pub src_peer: PeerId,
+    pub dst_peer: PeerId,
+    pub established_at: Instant,
+    /// 双向累计转发的字节数
+    pub bytes_forwarded: u64,
+}
+
+struct ActiveCircuit {
+    src_peer_id: PeerId,
+    dst_peer_id: PeerId,
+    established_at: Instant,
+    bytes_forwarded: Arc<AtomicU64>,
+}
+
+#[derive(Default)]
+struct State {
+    reservations: HashMap<PeerId, Instant>,
+    circuits: HashMap<PeerId, usize>,
+    next_circuit_id: u64,
+    active: HashMap<u64, ActiveCircuit>,
+}
+
+/// 中继服务端和中继客户端共享的资源统计
+#[derive(Clone)]
+pub(crate) struct Resources {
+    config: Arc<Config>,
+    state: Arc<Mutex<State>>,
+}
+
+impl Resources {
+    pub(crate) fn new(config: Config) -> Self {
+        Self {
+            config: Arc::new(config),
+            state: Arc::default(),
+        }
+    }
+
+    /// 申请或续期名额，返回实际授予的有效期
+    pub(crate) fn reserve(&self, peer_id: PeerId, ttl: Duration) -> Result<Duration, Denied> {
+        let mut state = self.state.lock().expect("lock poisoned");
+        let now = Instant::now();
+        state.reservations.retain(|_, expires| *expires > now);
+
+        if !state.reservations.contains_key(&peer_id)
+            && state.reservations.len() >= self.config.max_reservations
+        {
+            return Err(Denied::ReservationLimit);
+        }
+        let ttl = match ttl.is_zero() {
+            true => self.config.max_reservation_ttl,
+            false => ttl.min(self.config.max_reservation_ttl),
+        };
+        state.reservations.insert(peer_id, now + ttl);
+        Ok(ttl)
+    }
+
+    /// 占用一个中继，返回的 guard 释放时归还
+    pub(crate) fn open_circuit(
+        &self,
+        src_peer_id: PeerId,
+        dst_peer_id: PeerId,
+    ) -> Result<CircuitGuard, Denied> {
+        let mut state = self.state.lock().expect("lock poisoned");
+        if self.config.reservation_required {
+            match state.reservations.get(&dst_peer_id) {
+                Some(expires) if *expires > Instant::now() => {}
+                _ => return Err(Denied::NoReservation),
+            }
+        }
+        for peer_id in [src_peer_id, dst_peer_id] {
+            if state.circuits.get(&peer_id).copied().unwrap_or_default()
+                >= self.config.max_circuits_per_peer
+            {
+                return Err(Denied::CircuitLimit(peer_id));
+            }
+        }
+        *state.circuits.entry(src_peer_id).or_default() += 1;
+        *state.circuits.entry(dst_peer_id).or_default() += 1;
+        state.next_circuit_id += 1;
+
+        Ok(CircuitGuard {
+            id: state.next_circuit_id,
+            resources: self.clone(),
+            src_peer_id,
+            dst_peer_id,
+            established_at: None,
+            bytes_forwarded: Arc::default(),
+        })
+    }
+
+    /// 正在转发数据的中继快照
+    pub(crate) fn circuits(&self) -> Vec<CircuitInfo> {
+        let state = self.state.lock().expect("lock poisoned");
+        state
+            .active
+            .values()
+            .map(|circuit| CircuitInfo {
+                src_peer: circuit.src_peer_id,
+                dst_peer: circuit.dst_peer_id,
+                established_at: circuit.established_at,
+                bytes_forwarded: circuit.bytes_forwarded.load(Ordering::Relaxed),
+            })
+            .collect()
+    }
+
+    fn close_circuit(&self, id: u64, src_peer_id: PeerId, dst_peer_id: PeerId) {
+        let mut state = self.state.lock().expect("lock poisoned");
+        state.active.remove(&id);
+        for peer_id in [src_peer_id, dst_peer_id] {
+            if let Some(count) = state.circuits.get_mut(&peer_id) {
+                *count -= 1;
+                if *count == 0 {
+                    state.circuits.remove(&peer_id);
+                }
+            }
+        }
+    }
+}
+
+/// 已占用的中继名额
+pub(crate) struct CircuitGuard {
+    id: u64,
+    resources: Resources,
+    src_peer_id: PeerId,
+    dst_peer_id: PeerId,
+    established_at: Option<Instant>,
+    bytes_forwarded: Arc<AtomicU64>,
+}
+
+impl CircuitGuard {
+    /// 开始转发数据，返回转发字节数的计数器
+    pub(crate) fn establish(&mut self) -> Arc<AtomicU64> {
+        let established_at = Instant::now();
+        self.established_at = Some(established_at);
+        self.resources
+            .state
+            .lock()
+            .expect("lock poisoned")
+            .active
+            .insert(
+                self.id,
+                ActiveCircuit {
+                    src_peer_id: self.src_peer_id,
+                    dst_peer_id: self.dst_peer_id,
+                    established_at,
+                    bytes_forwarded: self.bytes_forwarded.clone(),
+                },
+            );
+        self.bytes_forwarded.clone()
+    }
+
+    pub(crate) fn src_peer_id(&self) -> PeerId {
+        self.src_peer_id
+    }
+
+    pub(crate) fn dst_peer_id(&self) -> PeerId {
+        self.dst_peer_id
+    }
+
+    pub(crate) fn bytes_forwarded(&self) -> u64 {
+        self.bytes_forwarded.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn duration(&self) -> Duration {
+        self.established_at
+            .map(|established_at| established_at.elapsed())
+            .unwrap_or_default()
+    }
+
+    pub(crate) fn max_bytes(&self) -> Option<u64> {
+        self.resources.config.max_circuit_bytes
+    }
+
+    pub(crate) fn max_duration(&self) -> Option<Duration> {
+        self.resources.config.max_circuit_duration
+    }
+
+    pub(crate) fn max_idle(&self) -> Option<Duration> {
+        self.resources.config.max_circuit_idle
+    }
+}
+
+impl Drop for CircuitGuard {
+    fn drop(&mut self) {
+        self.resources
+            .close_circuit(self.id, self.src_peer_id, self.dst_peer_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use volans_core::identity::KeyPair;
+
+    use super::*;
+
+    fn peer_id(seed: u8) -> PeerId {
+        PeerId::from_public_key(&KeyPair::from_bytes(&[seed; 32]).verifying_key())
+    }
+
+    #[test]
+    fn circuit_limits() {
+        let resources = Resources::new(
+            Config::default()
+                .with_reservation_required(true)
+                .with_max_circuits_per_peer(1),
+        );
+        let (src, dst) = (peer_id(1), peer_id(2));
+
+        assert_eq!(
+            resources.open_circuit(src, dst).err(),
+            Some(Denied::NoReservation)
+        );
+        assert_eq!(
+            resources.reserve(dst, Duration::ZERO),
+            Ok(Duration::from_secs(60 * 60))
+        );
+
+        let mut guard = resources.open_circuit(src, dst).unwrap();
+        assert!(resources.circuits().is_empty());
+        guard.establish().fetch_add(10, Ordering::Relaxed);
+        assert_eq!(resources.circuits()[0].bytes_forwarded, 10);
+        assert_eq!(
+            resources.open_circuit(peer_id(3), dst).err(),
+            Some(Denied::CircuitLimit(dst))
+        );
+        drop(guard);
+        assert!(resources.circuits().is_empty());
+        assert!(resources.open_circuit(peer_id(3), dst).is_ok());
+    }
+}
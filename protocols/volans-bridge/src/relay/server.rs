@@ -7,4 +7,4 @@
 mod behavior;
 mod handler;
 
-pub use behavior::Behavior;
+pub use behavior::{Behavior, Event};
@@ -1,7 +1,7 @@
 use std::{
     collections::VecDeque,
-    convert::Infallible,
     task::{Context, Poll},
+    time::Duration,
 };
 
 use futures::channel::mpsc;
@@ -11,13 +11,15 @@ use volans_swarm::{
     THandlerAction, THandlerEvent,
 };
 
-use crate::relay::CircuitRequest;
+use crate::relay::{CircuitInfo, CircuitRequest, Config, Denied, Resources};
 
 use super::handler;
 
 pub struct Behavior {
     local_peer_id: PeerId,
+    resources: Resources,
     pending_requests: VecDeque<CircuitRequest>,
+    pending_events: VecDeque<Event>,
     request_sender: mpsc::UnboundedSender<CircuitRequest>,
 }
 
@@ -25,41 +27,81 @@ impl Behavior {
     pub fn new(
         local_peer_id: PeerId,
         request_sender: mpsc::UnboundedSender<CircuitRequest>,
+    ) -> Self {
+        Self::with_resources(
+            local_peer_id,
+            request_sender,
+            Resources::new(Config::default()),
+        )
+    }
+
+    pub(crate) fn with_resources(
+        local_peer_id: PeerId,
+        request_sender: mpsc::UnboundedSender<CircuitRequest>,
+        resources: Resources,
     ) -> Self {
         Self {
             local_peer_id,
+            resources,
             pending_requests: VecDeque::new(),
+            pending_events: VecDeque::new(),
             request_sender,
         }
     }
+
+    /// 当前正在转发数据的中继
+    pub fn circuits(&self) -> impl Iterator<Item = CircuitInfo> + use<> {
+        self.resources.circuits().into_iter()
+    }
 }
 
 impl NetworkBehavior for Behavior {
     type ConnectionHandler = handler::Handler;
-    type Event = Infallible;
+    type Event = Event;
 
     fn on_connection_handler_event(
         &mut self,
         id: ConnectionId,
         peer_id: PeerId,
-        handler::CircuitAccepted {
-            dst_peer_id,
-            dst_addresses,
-            circuit,
-            relayed_addr,
-        }: THandlerEvent<Self>,
+        event: THandlerEvent<Self>,
     ) {
-        // 客户端发起一个中继请求,
-        let request = CircuitRequest {
-            relayed_addr,
-            dst_peer_id,
-            dst_addresses,
-            src_peer_id: peer_id,
-            src_connection_id: id,
-            circuit,
-        };
-        // 写入待处理请求队列
-        self.pending_requests.push_back(request);
+        match event {
+            handler::Event::CircuitAccepted(accepted) => {
+                let handler::CircuitAccepted {
+                    dst_peer_id,
+                    dst_addresses,
+                    circuit,
+                    relayed_addr,
+                    guard,
+                } = *accepted;
+                // 客户端发起一个中继请求,
+                let request = CircuitRequest {
+                    relayed_addr,
+                    dst_peer_id,
+                    dst_addresses,
+                    src_peer_id: peer_id,
+                    src_connection_id: id,
+                    circuit,
+                    guard,
+                };
+                // 写入待处理请求队列
+                self.pending_requests.push_back(request);
+            }
+            handler::Event::CircuitDenied {
+                dst_peer_id,
+                reason,
+            } => self.pending_events.push_back(Event::CircuitDenied {
+                src_peer_id: peer_id,
+                dst_peer_id,
+                reason,
+            }),
+            handler::Event::ReservationAccepted { ttl } => self
+                .pending_events
+                .push_back(Event::ReservationAccepted { peer_id, ttl }),
+            handler::Event::ReservationDenied { reason } => self
+                .pending_events
+                .push_back(Event::ReservationDenied { peer_id, reason }),
+        }
     }
 
     fn poll(
@@ -67,6 +109,9 @@ impl NetworkBehavior for Behavior {
         _cx: &mut Context<'_>,
     ) -> Poll<BehaviorEvent<Self::Event, THandlerAction<Self>>> {
         loop {
+            if let Some(event) = self.pending_events.pop_front() {
+                return Poll::Ready(BehaviorEvent::Behavior(event));
+            }
             if let Some(request) = self.pending_requests.pop_front() {
                 // 发送请求给客户端
                 tracing::debug!("Sending request: {:?}", request);
@@ -95,6 +140,24 @@ impl NetworkIncomingBehavior for Behavior {
             .with(Protocol::Circuit)
             .with(Protocol::Peer(peer_id));
 
-        Ok(handler::Handler::new(relay_addr))
+        Ok(handler::Handler::new(
+            peer_id,
+            relay_addr,
+            self.resources.clone(),
+        ))
     }
 }
+
+#[derive(Debug)]
+pub enum Event {
+    /// 后端节点申请或续期名额成功
+    ReservationAccepted { peer_id: PeerId, ttl: Duration },
+    /// 后端节点的名额申请被拒绝
+    ReservationDenied { peer_id: PeerId, reason: Denied },
+    /// 中继请求因资源限制被拒绝
+    CircuitDenied {
+        src_peer_id: PeerId,
+        dst_peer_id: PeerId,
+        reason: Denied,
+    },
+}
@@ -1,45 +1,75 @@
 use std::{
     collections::VecDeque,
     convert::Infallible,
-    fmt,
+    fmt, io,
     task::{Context, Poll},
     time::Duration,
 };
 
-use futures::FutureExt;
+use futures::{FutureExt, future::Either};
 use futures_bounded::{Delay, FuturesSet};
-use volans_core::{Multiaddr, PeerId, upgrade::ReadyUpgrade};
+use volans_core::{
+    Multiaddr, PeerId,
+    upgrade::{ReadyUpgrade, SelectUpgrade},
+};
 use volans_swarm::{
     ConnectionHandler, ConnectionHandlerEvent, InboundStreamHandler, InboundUpgradeSend,
     StreamProtocol, SubstreamProtocol,
 };
 
-use crate::protocol;
+use crate::{
+    protocol,
+    relay::{CircuitGuard, Denied, Resources},
+};
 
 /// 中继服务器处理前端客户端求，
 /// 通过 relay client 连接到后端 backend
 pub struct Handler {
-    pending_events: VecDeque<CircuitAccepted>,
+    peer_id: PeerId,
+    resources: Resources,
+    pending_events: VecDeque<Event>,
     inbound_circuit_requests: FuturesSet<Result<protocol::Bridge, protocol::Error>>,
+    inbound_reserve_requests: FuturesSet<Result<protocol::Reservation, protocol::Error>>,
+    /// 正在回复拒绝或名额结果的流
+    pending_responses: FuturesSet<Result<(), io::Error>>,
     relayed_addr: Multiaddr,
 }
 
 impl Handler {
-    pub fn new(relayed_addr: Multiaddr) -> Self {
+    pub(crate) fn new(peer_id: PeerId, relayed_addr: Multiaddr, resources: Resources) -> Self {
         Self {
+            peer_id,
+            resources,
             relayed_addr,
             pending_events: VecDeque::new(),
             inbound_circuit_requests: FuturesSet::new(
                 || Delay::futures_timer(Duration::from_secs(15)),
                 10, // 最大同时处理
             ),
+            inbound_reserve_requests: FuturesSet::new(
+                || Delay::futures_timer(Duration::from_secs(15)),
+                10,
+            ),
+            pending_responses: FuturesSet::new(
+                || Delay::futures_timer(Duration::from_secs(15)),
+                10,
+            ),
+        }
+    }
+
+    fn push_response<F>(&mut self, fut: F)
+    where
+        F: Future<Output = Result<(), io::Error>> + Send + 'static,
+    {
+        if self.pending_responses.try_push(fut.boxed()).is_err() {
+            tracing::warn!("Failed to push pending response(channel full), dropping stream");
         }
     }
 }
 
 impl ConnectionHandler for Handler {
     type Action = Infallible;
-    type Event = CircuitAccepted;
+    type Event = Event;
 
     fn handle_action(&mut self, _action: Self::Action) {
         // No actions to handle
@@ -56,12 +86,25 @@ impl ConnectionHandler for Handler {
                     dst_peer_id,
                     dst_addresses,
                 }))) => {
-                    let event = CircuitAccepted {
+                    let guard = match self.resources.open_circuit(self.peer_id, dst_peer_id) {
+                        Ok(guard) => guard,
+                        Err(reason) => {
+                            tracing::debug!("Circuit to {:?} denied: {:?}", dst_peer_id, reason);
+                            self.push_response(circuit.deny(reason.code()));
+                            let event = Event::CircuitDenied {
+                                dst_peer_id,
+                                reason,
+                            };
+                            return Poll::Ready(ConnectionHandlerEvent::Notify(event));
+                        }
+                    };
+                    let event = Event::CircuitAccepted(Box::new(CircuitAccepted {
                         relayed_addr: self.relayed_addr.clone(),
                         circuit,
                         dst_peer_id,
                         dst_addresses,
-                    };
+                        guard,
+                    }));
                     return Poll::Ready(ConnectionHandlerEvent::Notify(event));
                 }
                 Poll::Ready(Ok(Err(err))) => {
@@ -75,6 +118,40 @@ impl ConnectionHandler for Handler {
                 Poll::Pending => {}
             }
 
+            match self.inbound_reserve_requests.poll_unpin(cx) {
+                Poll::Ready(Ok(Ok(reservation))) => {
+                    let event = match self.resources.reserve(self.peer_id, reservation.ttl) {
+                        Ok(ttl) => {
+                            self.push_response(reservation.accept(ttl));
+                            Event::ReservationAccepted { ttl }
+                        }
+                        Err(reason) => {
+                            self.push_response(reservation.deny(reason.code()));
+                            Event::ReservationDenied { reason }
+                        }
+                    };
+                    return Poll::Ready(ConnectionHandlerEvent::Notify(event));
+                }
+                Poll::Ready(Ok(Err(err))) => {
+                    tracing::error!("Inbound reserve request failed: {:?}", err);
+                    continue;
+                }
+                Poll::Ready(Err(_)) => {
+                    tracing::error!("Inbound reserve request timeout");
+                    continue;
+                }
+                Poll::Pending => {}
+            }
+
+            match self.pending_responses.poll_unpin(cx) {
+                Poll::Ready(Ok(Err(err))) => {
+                    tracing::debug!("Failed to send response: {:?}", err);
+                    continue;
+                }
+                Poll::Ready(Ok(Ok(()))) | Poll::Ready(Err(_)) => continue,
+                Poll::Pending => {}
+            }
+
             return Poll::Pending;
         }
     }
@@ -85,11 +162,15 @@ impl ConnectionHandler for Handler {
 }
 
 impl InboundStreamHandler for Handler {
-    type InboundUpgrade = ReadyUpgrade<StreamProtocol>;
+    type InboundUpgrade = SelectUpgrade<ReadyUpgrade<StreamProtocol>, ReadyUpgrade<StreamProtocol>>;
     type InboundUserData = ();
 
     fn listen_protocol(&self) -> SubstreamProtocol<Self::InboundUpgrade, Self::InboundUserData> {
-        SubstreamProtocol::new(ReadyUpgrade::new(protocol::PROTOCOL_NAME), ())
+        let upgrade = SelectUpgrade::new(
+            ReadyUpgrade::new(protocol::PROTOCOL_NAME),
+            ReadyUpgrade::new(protocol::RESERVE_PROTOCOL_NAME),
+        );
+        SubstreamProtocol::new(upgrade, ())
     }
 
     fn on_fully_negotiated(
@@ -97,12 +178,19 @@ impl InboundStreamHandler for Handler {
         _user_data: Self::InboundUserData,
         stream: <Self::InboundUpgrade as InboundUpgradeSend>::Output,
     ) {
-        let result = self
-            .inbound_circuit_requests
-            .try_push(protocol::handle_bridge_connect(stream).boxed());
+        let result = match stream {
+            Either::Left(stream) => self
+                .inbound_circuit_requests
+                .try_push(protocol::handle_bridge_connect(stream).boxed())
+                .map_err(|_| ()),
+            Either::Right(stream) => self
+                .inbound_reserve_requests
+                .try_push(protocol::handle_reserve(stream).boxed())
+                .map_err(|_| ()),
+        };
 
         if result.is_err() {
-            tracing::warn!("Failed to push inbound circuit request(channel full), dropping stream");
+            tracing::warn!("Failed to push inbound request(channel full), dropping stream");
         }
     }
 
@@ -114,11 +202,20 @@ impl InboundStreamHandler for Handler {
     }
 }
 
+#[derive(Debug)]
+pub enum Event {
+    CircuitAccepted(Box<CircuitAccepted>),
+    CircuitDenied { dst_peer_id: PeerId, reason: Denied },
+    ReservationAccepted { ttl: Duration },
+    ReservationDenied { reason: Denied },
+}
+
 pub struct CircuitAccepted {
     pub(crate) relayed_addr: Multiaddr,
     pub(crate) circuit: protocol::Circuit,
     pub(crate) dst_peer_id: PeerId,
     pub(crate) dst_addresses: Vec<Multiaddr>,
+    pub(crate) guard: CircuitGuard,
 }
 impl fmt::Debug for CircuitAccepted {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
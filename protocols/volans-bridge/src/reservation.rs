@@ -0,0 +1,8 @@
+/// 后端向中继服务申请名额
+/// 1、behavior.reserve(relay_peer_id, relay_addr) 连接中继服务
+/// 2、连接建立后发起名额申请，中继返回实际授予的有效期
+/// 3、有效期过半后自动续期，直至连接关闭
+mod behavior;
+mod handler;
+
+pub use behavior::{Behavior, Event};
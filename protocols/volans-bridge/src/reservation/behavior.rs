@@ -0,0 +1,184 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use either::Either;
+use volans_core::{Multiaddr, PeerId, multiaddr::Protocol};
+use volans_swarm::{
+    BehaviorEvent, ConnectionDenied, ConnectionId, DialOpts, NetworkBehavior,
+    NetworkOutgoingBehavior, PeerCondition, THandlerAction, THandlerEvent,
+    error::{CloseReason, DialError},
+    handler::DummyHandler,
+};
+
+use crate::protocol::ConnectError;
+
+use super::handler;
+
+/// 后端向中继服务申请名额的行为
+pub struct Behavior {
+    ttl: Duration,
+    timeout: Duration,
+    relays: HashMap<PeerId, Multiaddr>,
+    dial_peers: VecDeque<(PeerId, Multiaddr)>,
+    pending_events: VecDeque<BehaviorEvent<Event, THandlerAction<Self>>>,
+}
+
+impl Behavior {
+    /// `ttl` 为申请的名额有效期，为 0 时使用中继允许的最大值
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            timeout: Duration::from_secs(15),
+            relays: HashMap::new(),
+            dial_peers: VecDeque::new(),
+            pending_events: VecDeque::new(),
+        }
+    }
+
+    /// 连接中继服务并申请名额，连接期间自动续期
+    pub fn reserve(&mut self, relay_peer_id: PeerId, relay_addr: Multiaddr) {
+        self.relays.insert(relay_peer_id, relay_addr.clone());
+        self.dial_peers.push_back((relay_peer_id, relay_addr));
+    }
+}
+
+impl NetworkBehavior for Behavior {
+    type ConnectionHandler = Either<DummyHandler, handler::Handler>;
+    type Event = Event;
+
+    fn on_connection_handler_event(
+        &mut self,
+        _id: ConnectionId,
+        peer_id: PeerId,
+        event: THandlerEvent<Self>,
+    ) {
+        let event = match event {
+            Either::Right(handler::Event::Accepted(ttl)) => {
+                // 名额生效后可经由中继被连接，中继地址作为外部地址候选
+                if let Some(relay_addr) = self.relays.get(&peer_id) {
+                    self.pending_events
+                        .push_back(BehaviorEvent::NewExternalAddrCandidate {
+                            peer_id,
+                            addr: circuit_addr(relay_addr, peer_id),
+                        });
+                }
+                Event::ReservationAccepted {
+                    relay_peer_id: peer_id,
+                    ttl,
+                }
+            }
+            Either::Right(handler::Event::Failed(error)) => Event::ReservationFailed {
+                relay_peer_id: peer_id,
+                error,
+            },
+        };
+        self.pending_events
+            .push_back(BehaviorEvent::Behavior(event));
+    }
+
+    fn poll(
+        &mut self,
+        _cx: &mut Context<'_>,
+    ) -> Poll<BehaviorEvent<Self::Event, THandlerAction<Self>>> {
+        if let Some(event) = self.pending_events.pop_front() {
+            return Poll::Ready(event);
+        }
+        Poll::Pending
+    }
+}
+
+impl NetworkOutgoingBehavior for Behavior {
+    fn handle_pending_connection(
+        &mut self,
+        _id: ConnectionId,
+        _maybe_peer: Option<PeerId>,
+        addr: &Option<Multiaddr>,
+    ) -> Result<Option<Multiaddr>, ConnectionDenied> {
+        Ok(addr.clone())
+    }
+
+    fn handle_established_connection(
+        &mut self,
+        _id: ConnectionId,
+        peer_id: PeerId,
+        _addr: &Multiaddr,
+    ) -> Result<Self::ConnectionHandler, ConnectionDenied> {
+        if self.relays.contains_key(&peer_id) {
+            Ok(Either::Right(handler::Handler::new(self.ttl, self.timeout)))
+        } else {
+            Ok(Either::Left(DummyHandler))
+        }
+    }
+
+    fn on_connection_closed(
+        &mut self,
+        _id: ConnectionId,
+        peer_id: PeerId,
+        _addr: &Multiaddr,
+        _reason: &CloseReason,
+    ) {
+        if self.relays.contains_key(&peer_id) {
+            self.pending_events
+                .push_back(BehaviorEvent::Behavior(Event::ReservationClosed {
+                    relay_peer_id: peer_id,
+                }));
+        }
+    }
+
+    fn on_dial_failure(
+        &mut self,
+        _id: ConnectionId,
+        peer_id: Option<PeerId>,
+        _addr: Option<&Multiaddr>,
+        error: &DialError,
+    ) {
+        if let Some(peer_id) = peer_id
+            && self.relays.contains_key(&peer_id)
+        {
+            tracing::warn!("Dial relay {:?} failed: {:?}", peer_id, error);
+            self.pending_events
+                .push_back(BehaviorEvent::Behavior(Event::ReservationClosed {
+                    relay_peer_id: peer_id,
+                }));
+        }
+    }
+
+    fn poll_dial(&mut self, _cx: &mut Context<'_>) -> Poll<DialOpts> {
+        if let Some((peer_id, relay_addr)) = self.dial_peers.pop_front() {
+            tracing::debug!("Dialing relay for reservation: {:?}", peer_id);
+            return Poll::Ready(
+                DialOpts::new(Some(relay_addr), Some(peer_id))
+                    .with_condition(PeerCondition::DisconnectedAndNotDialing),
+            );
+        }
+        Poll::Pending
+    }
+}
+
+/// 经由中继到达本地的地址
+fn circuit_addr(relay_addr: &Multiaddr, relay_peer_id: PeerId) -> Multiaddr {
+    let mut addr = relay_addr.clone();
+    if !matches!(addr.iter().last(), Some(Protocol::Peer(_))) {
+        addr.push(Protocol::Peer(relay_peer_id));
+    }
+    addr.with(Protocol::Circuit)
+}
+
+#[derive(Debug)]
+pub enum Event {
+    /// 中继授予或续期了名额
+    ReservationAccepted {
+        relay_peer_id: PeerId,
+        ttl: Duration,
+    },
+    /// 名额申请失败，中继拒绝时为 `ConnectError::Denied`
+    ReservationFailed {
+        relay_peer_id: PeerId,
+        error: ConnectError,
+    },
+    /// 与中继的连接断开，名额不再续期
+    ReservationClosed { relay_peer_id: PeerId },
+}
@@ -0,0 +1,143 @@
+use std::{
+    collections::VecDeque,
+    convert::Infallible,
+    io,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use futures::FutureExt;
+use futures_bounded::{Delay, FuturesSet};
+use volans_core::upgrade::ReadyUpgrade;
+use volans_swarm::{
+    ConnectionHandler, ConnectionHandlerEvent, KeepAlive, OutboundStreamHandler,
+    OutboundUpgradeSend, StreamProtocol, StreamUpgradeError, SubstreamProtocol, timer,
+};
+
+use crate::protocol;
+
+/// 在与中继服务的连接上申请并续期名额
+pub struct Handler {
+    ttl: Duration,
+    requested: bool,
+    pending_outbound: bool,
+    pending_events: VecDeque<Event>,
+    reserving: FuturesSet<Result<Duration, protocol::ConnectError>>,
+    renewal: Option<timer::Delay>,
+}
+
+impl Handler {
+    pub fn new(ttl: Duration, timeout: Duration) -> Self {
+        Self {
+            ttl,
+            requested: true,
+            pending_outbound: false,
+            pending_events: VecDeque::new(),
+            reserving: FuturesSet::new(move || Delay::futures_timer(timeout), 1),
+            renewal: None,
+        }
+    }
+}
+
+impl ConnectionHandler for Handler {
+    type Action = Infallible;
+    type Event = Event;
+
+    fn handle_action(&mut self, _action: Self::Action) {
+        // No actions to handle
+    }
+
+    fn keep_alive(&self) -> KeepAlive {
+        // 名额有效期间保持连接
+        KeepAlive::Yes
+    }
+
+    fn poll(&mut self, cx: &mut Context<'_>) -> Poll<ConnectionHandlerEvent<Self::Event>> {
+        if let Some(event) = self.pending_events.pop_front() {
+            return Poll::Ready(ConnectionHandlerEvent::Notify(event));
+        }
+        match self.reserving.poll_unpin(cx) {
+            Poll::Ready(Ok(Ok(ttl))) => {
+                if !ttl.is_zero() {
+                    self.renewal = Some(timer::Delay::new(ttl / 2));
+                }
+                Poll::Ready(ConnectionHandlerEvent::Notify(Event::Accepted(ttl)))
+            }
+            Poll::Ready(Ok(Err(error))) => {
+                Poll::Ready(ConnectionHandlerEvent::Notify(Event::Failed(error)))
+            }
+            Poll::Ready(Err(_)) => {
+                let error = io::Error::new(io::ErrorKind::TimedOut, "Reservation timed out");
+                Poll::Ready(ConnectionHandlerEvent::Notify(Event::Failed(error.into())))
+            }
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+impl OutboundStreamHandler for Handler {
+    type OutboundUpgrade = ReadyUpgrade<StreamProtocol>;
+    type OutboundUserData = ();
+
+    fn on_fully_negotiated(
+        &mut self,
+        _user_data: Self::OutboundUserData,
+        stream: <Self::OutboundUpgrade as OutboundUpgradeSend>::Output,
+    ) {
+        self.pending_outbound = false;
+        let result = self
+            .reserving
+            .try_push(protocol::make_reserve(stream, self.ttl).boxed());
+        if result.is_err() {
+            tracing::warn!("Dropping reservation request: a reservation is already in progress");
+        }
+    }
+
+    fn on_upgrade_error(
+        &mut self,
+        _user_data: Self::OutboundUserData,
+        error: StreamUpgradeError<<Self::OutboundUpgrade as OutboundUpgradeSend>::Error>,
+    ) {
+        self.pending_outbound = false;
+        let error = match error {
+            StreamUpgradeError::Timeout => protocol::ConnectError::Io(io::Error::new(
+                io::ErrorKind::TimedOut,
+                "Outbound upgrade timed out",
+            )),
+            StreamUpgradeError::NegotiationFailed { .. } => protocol::ConnectError::Unsupported,
+            StreamUpgradeError::RemoteReset => {
+                protocol::ConnectError::Io(io::ErrorKind::ConnectionReset.into())
+            }
+            StreamUpgradeError::Io(err) => protocol::ConnectError::Io(err),
+            StreamUpgradeError::Apply(_) => unreachable!("Apply error should not happen here"),
+        };
+        self.pending_events.push_back(Event::Failed(error));
+    }
+
+    fn poll_outbound_request(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<SubstreamProtocol<Self::OutboundUpgrade, Self::OutboundUserData>> {
+        // 续期在这里检查，连接在 `poll` 之前轮询子流请求
+        if let Some(renewal) = self.renewal.as_mut()
+            && renewal.poll_unpin(cx).is_ready()
+        {
+            tracing::debug!("Renewing relay reservation");
+            self.renewal = None;
+            self.requested = true;
+        }
+        if self.requested && !self.pending_outbound {
+            self.requested = false;
+            self.pending_outbound = true;
+            let upgrade = ReadyUpgrade::new(protocol::RESERVE_PROTOCOL_NAME);
+            return Poll::Ready(SubstreamProtocol::new(upgrade, ()));
+        }
+        Poll::Pending
+    }
+}
+
+#[derive(Debug)]
+pub enum Event {
+    Accepted(Duration),
+    Failed(protocol::ConnectError),
+}
@@ -13,14 +13,12 @@ use futures::{
 };
 use volans_codec::Bytes;
 use volans_core::{
-    Listener, ListenerEvent, Multiaddr, PeerId, Transport, TransportError, multiaddr::Protocol,
+    Listener, ListenerEvent, Multiaddr, PeerId, SocketOptions, Transport, TransportError,
+    multiaddr::Protocol,
 };
 use volans_swarm::Substream;
 
-use crate::{
-    MultiaddrExt,
-    protocol::{Circuit, ConnectError},
-};
+use crate::{MultiaddrExt, client::CircuitFailure, protocol::Circuit};
 
 pub struct Config {
     behavior_sender: mpsc::Sender<TransportRequest>,
@@ -33,14 +31,6 @@ impl Config {
     }
 }
 
-pub(crate) struct DialRequest {
-    relay_addr: Multiaddr,
-    relay_peer_id: PeerId,
-    dst_addr: Option<Multiaddr>,
-    dst_peer_id: PeerId,
-    send_back: oneshot::Sender<Result<Connection, io::Error>>,
-}
-
 impl Transport for Config {
     type Output = Connection;
     type Error = Error;
@@ -77,7 +67,11 @@ impl Transport for Config {
         }
         .boxed())
     }
-    fn listen(&self, addr: Multiaddr) -> Result<Self::Listener, TransportError<Self::Error>> {
+    fn listen(
+        &self,
+        addr: Multiaddr,
+        _: &SocketOptions,
+    ) -> Result<Self::Listener, TransportError<Self::Error>> {
         if !addr.is_circuit() {
             return Err(TransportError::NotSupported(addr));
         }
@@ -107,7 +101,7 @@ pub struct ListenerBackend {
     local_addr: Multiaddr,
     pending_request: Option<TransportRequest>,
     behavior_sender: mpsc::Sender<TransportRequest>,
-    incoming_stream: mpsc::Receiver<IncomingRelayedConnection>,
+    incoming_stream: mpsc::Receiver<ListenerNotice>,
     closed: bool,
     waker: Option<Waker>,
     pending_events: VecDeque<ListenerEvent<<Self as Listener>::Upgrade, <Self as Listener>::Error>>,
@@ -131,25 +125,35 @@ impl Listener for ListenerBackend {
                 return Poll::Ready(ListenerEvent::Closed(Ok(())));
             }
 
-            if self.pending_request.is_some() {
-                if self.behavior_sender.poll_ready(cx).is_ready() {
-                    if let Some(request) = self.pending_request.take() {
-                        let _ = self.behavior_sender.start_send(request);
-                        let addr = self.local_addr.clone();
-                        self.pending_events
-                            .push_back(ListenerEvent::NewAddress(addr));
-                        continue;
-                    }
-                }
+            if self.pending_request.is_some()
+                && self.behavior_sender.poll_ready(cx).is_ready()
+                && let Some(request) = self.pending_request.take()
+            {
+                let _ = self.behavior_sender.start_send(request);
+                let addr = self.local_addr.clone();
+                self.pending_events
+                    .push_back(ListenerEvent::NewAddress(addr));
+                continue;
             }
 
             match self.incoming_stream.poll_next_unpin(cx) {
-                Poll::Ready(Some(IncomingRelayedConnection {
-                    stream,
-                    src_peer_id,
-                    relay_peer_id: _,
-                    relay_addr,
-                })) => {
+                Poll::Ready(Some(ListenerNotice::NewAddress(addr))) => {
+                    self.pending_events
+                        .push_back(ListenerEvent::NewAddress(addr));
+                    continue;
+                }
+                Poll::Ready(Some(ListenerNotice::AddressExpired(addr))) => {
+                    self.pending_events
+                        .push_back(ListenerEvent::AddressExpired(addr));
+                    continue;
+                }
+                Poll::Ready(Some(ListenerNotice::Incoming(incoming))) => {
+                    let IncomingRelayedConnection {
+                        stream,
+                        src_peer_id,
+                        relay_peer_id: _,
+                        relay_addr,
+                    } = *incoming;
                     tracing::info!("Received incoming relayed connection from: {}", src_peer_id);
                     self.pending_events.push_back(ListenerEvent::Incoming {
                         local_addr: relay_addr.with(Protocol::Circuit),
@@ -189,6 +193,7 @@ pub struct IncomingRelayedConnection {
     /// 源端的 PeerId
     src_peer_id: PeerId,
     /// 中继端的 PeerId
+    #[allow(dead_code)]
     relay_peer_id: PeerId,
     /// 中继端的地址
     relay_addr: Multiaddr,
@@ -210,16 +215,26 @@ impl IncomingRelayedConnection {
     }
 }
 
+/// 后端行为发往中继监听器的通知
+pub enum ListenerNotice {
+    /// 经由中继到达的新连接
+    Incoming(Box<IncomingRelayedConnection>),
+    /// 与中继的连接建立，可经由该中继到达本地
+    NewAddress(Multiaddr),
+    /// 与中继的连接断开
+    AddressExpired(Multiaddr),
+}
+
 pub enum TransportRequest {
     DialRequest {
         relay_addr: Multiaddr,
         relay_peer_id: PeerId,
         dst_peer_id: PeerId,
-        send_back: oneshot::Sender<Result<Connection, ConnectError>>,
+        send_back: oneshot::Sender<Result<Connection, CircuitFailure>>,
     },
     ListenRequest {
         local_addr: Multiaddr,
-        listener_sender: mpsc::Sender<IncomingRelayedConnection>,
+        listener_sender: mpsc::Sender<ListenerNotice>,
     },
 }
 
@@ -237,8 +252,9 @@ pub enum Error {
     BehaviorSend(#[from] mpsc::SendError),
     #[error("Transport error: {0}")]
     BehaviorResponse(#[from] oneshot::Canceled),
+    /// 建立电路失败，包含失败的阶段
     #[error("Transport error: {0}")]
-    ConnectError(#[from] ConnectError),
+    Circuit(#[from] CircuitFailure),
     #[error("I/O error: {0}")]
     Io(#[from] io::Error),
 }
@@ -305,7 +321,7 @@ impl Connection {
                     let (substream, read_buffer) = circuit.accept().await?;
                     Ok(ConnectionState::Accepted {
                         read_buffer,
-                        substream,
+                        substream: Box::new(substream),
                     })
                 }
                 .boxed(),
@@ -317,7 +333,7 @@ impl Connection {
         Connection {
             state: ConnectionState::Accepted {
                 read_buffer,
-                substream,
+                substream: Box::new(substream),
             },
         }
     }
@@ -329,7 +345,7 @@ pub(crate) enum ConnectionState {
     },
     Accepted {
         read_buffer: Bytes,
-        substream: Substream,
+        substream: Box<Substream>,
     },
 }
 
@@ -0,0 +1,47 @@
+use futures::StreamExt;
+use volans_core::identity::KeyPair;
+use volans_swarm::{duplex, server};
+use volans_swarm_test::{SwarmExt, listen, next_behavior_event};
+
+fn identify(_: &KeyPair) -> volans_identify::Behavior {
+    volans_identify::Behavior::new(volans_identify::Config::new("/test/1.0.0"))
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn reconnect_bridge_relay() {
+    use std::time::Duration;
+    use volans_bridge::{backend, transport};
+
+    let mut relay = server::Swarm::new_ephemeral(identify);
+    let relay_addr = listen(&mut relay).await;
+    let relay_peer = *relay.local_peer_id();
+    let mut backend = duplex::Swarm::new_ephemeral(|_| {
+        let (_, receiver) = transport::Config::new();
+        let mut behavior = backend::Behavior::new(receiver)
+            .with_reconnect_backoff(Duration::from_millis(10), Duration::from_millis(100));
+        behavior.add_relay(relay_peer, relay_addr);
+        behavior
+    });
+    // 中继关闭第一条连接，之后正常接受
+    tokio::spawn(async move {
+        let mut closed = false;
+        loop {
+            if let Some(server::SwarmEvent::ConnectionEstablished { connection_id, .. }) =
+                relay.next().await
+                && !closed
+            {
+                closed = relay.close_connection(connection_id);
+            }
+        }
+    });
+
+    assert!(matches!(
+        next_behavior_event(&mut backend).await,
+        backend::Event::RelayConnectionLost { relay_peer_id, .. } if relay_peer_id == relay_peer
+    ));
+    assert!(matches!(
+        next_behavior_event(&mut backend).await,
+        backend::Event::RelayReconnected { relay_peer_id } if relay_peer_id == relay_peer
+    ));
+    assert_eq!(backend.behavior().connected_relays().count(), 1);
+}
@@ -0,0 +1,157 @@
+use futures::channel::oneshot;
+use volans_bridge::{
+    backend, client as bridge_client,
+    relay::{self, client::Event as RelayEvent},
+};
+use volans_core::{PeerId, Transport, identity::KeyPair, multiaddr::Protocol};
+use volans_swarm::{DialOpts, NetworkOutgoingBehavior, client, connection::PoolConfig, server};
+use volans_swarm_test::{SwarmExt, ephemeral_key_pair, ephemeral_parts, listen, next_swarm_event};
+
+fn peer_id(key_pair: &KeyPair) -> PeerId {
+    PeerId::from_public_key(&key_pair.verifying_key())
+}
+
+// 单独的行为不保留拨号地址，与应用中一样组合后使用
+#[derive(NetworkOutgoingBehavior)]
+#[behavior(prelude = "volans_swarm::derive_prelude")]
+struct Client {
+    bridge: bridge_client::Behavior,
+}
+
+#[derive(NetworkOutgoingBehavior)]
+#[behavior(prelude = "volans_swarm::derive_prelude")]
+struct RelayClient {
+    relay: relay::client::Behavior,
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn circuit_opened_and_closed() {
+    // 中继节点分别以服务端接受请求、以客户端连接后端
+    let relay_key = ephemeral_key_pair();
+    let relay_peer = peer_id(&relay_key);
+    let (relay_server, relay_client) = relay::new(relay_peer);
+    let (transport, _) = ephemeral_parts(&relay_key);
+    let mut relay_in = server::Swarm::from_parts(
+        transport,
+        relay_server,
+        relay_peer,
+        PoolConfig::with_tokio_executor(),
+    );
+    let (transport, _) = ephemeral_parts(&relay_key);
+    let mut relay_out = client::Swarm::from_parts(
+        transport,
+        RelayClient {
+            relay: relay_client,
+        },
+        relay_peer,
+        PoolConfig::with_tokio_executor(),
+    );
+    let relay_addr = listen(&mut relay_in).await;
+
+    let backend_key = ephemeral_key_pair();
+    let backend_peer = peer_id(&backend_key);
+    let (bridge, behavior) = backend::new();
+    let mut backend = server::Swarm::from_parts(
+        bridge
+            .choice(volans_tcp::Config::new())
+            .upgrade()
+            .authenticate(volans_plaintext::Config::new(backend_key.verifying_key()))
+            .multiplex(volans_muxing::Config::new())
+            .boxed(),
+        behavior,
+        backend_peer,
+        PoolConfig::with_tokio_executor(),
+    );
+    let backend_addr = listen(&mut backend).await;
+    backend.listen_on(Protocol::Circuit.into()).unwrap();
+    relay_out
+        .peer_store_mut()
+        .add_address(backend_peer, backend_addr);
+
+    let client_key = ephemeral_key_pair();
+    let client_peer = peer_id(&client_key);
+    let (bridge, behavior) = bridge_client::new();
+    let mut client = client::Swarm::from_parts(
+        bridge
+            .upgrade()
+            .authenticate(volans_plaintext::Config::new(client_key.verifying_key()))
+            .multiplex(volans_muxing::Config::new())
+            .boxed()
+            .or_transport(ephemeral_parts(&client_key).0)
+            .boxed(),
+        Client { bridge: behavior },
+        client_peer,
+        PoolConfig::with_tokio_executor(),
+    );
+    client
+        .dial(DialOpts::new(
+            Some(
+                relay_addr
+                    .with(Protocol::Peer(relay_peer))
+                    .with(Protocol::Circuit)
+                    .with(Protocol::Peer(backend_peer)),
+            ),
+            None,
+        ))
+        .unwrap();
+
+    tokio::spawn(async move {
+        loop {
+            next_swarm_event(&mut backend).await;
+        }
+    });
+    // 经由中继与后端建立连接后通知，任务结束时中继被关闭
+    let (established_sender, mut established) = oneshot::channel();
+    let client_task = tokio::spawn(async move {
+        let mut established_sender = Some(established_sender);
+        loop {
+            if let client::SwarmEvent::ConnectionEstablished { peer_id, .. } =
+                next_swarm_event(&mut client).await
+                && peer_id == backend_peer
+                && let Some(sender) = established_sender.take()
+            {
+                let _ = sender.send(());
+            }
+        }
+    });
+
+    let mut opened = false;
+    let mut closing = false;
+    let bytes_forwarded = loop {
+        tokio::select! {
+            event = next_swarm_event(&mut relay_out) => match event {
+                client::SwarmEvent::Behavior(RelayClientEvent::Relay(RelayEvent::CircuitOpened {
+                    src_peer_id,
+                    dst_peer_id,
+                })) => {
+                    assert_eq!((src_peer_id, dst_peer_id), (client_peer, backend_peer));
+                    opened = true;
+                }
+                client::SwarmEvent::Behavior(RelayClientEvent::Relay(RelayEvent::CircuitClosed {
+                    src_peer_id,
+                    dst_peer_id,
+                    bytes_forwarded,
+                    ..
+                })) => {
+                    assert_eq!((src_peer_id, dst_peer_id), (client_peer, backend_peer));
+                    break bytes_forwarded;
+                }
+                _ => {}
+            },
+            _ = next_swarm_event(&mut relay_in) => {}
+            _ = &mut established, if !closing => {
+                assert!(opened);
+                let circuits = relay_in.behavior().circuits().collect::<Vec<_>>();
+                assert_eq!(circuits.len(), 1);
+                assert_eq!(circuits[0].src_peer, client_peer);
+                assert_eq!(circuits[0].dst_peer, backend_peer);
+                // 握手经由中继转发
+                assert!(circuits[0].bytes_forwarded > 0);
+                client_task.abort();
+                closing = true;
+            }
+        }
+    };
+    assert!(bytes_forwarded > 0);
+    assert_eq!(relay_in.behavior().circuits().count(), 0);
+}
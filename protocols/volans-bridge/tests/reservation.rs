@@ -0,0 +1,55 @@
+use std::time::Duration;
+
+use futures::channel::mpsc;
+use volans_bridge::{relay, reservation};
+use volans_core::PeerId;
+use volans_swarm::{client, server};
+use volans_swarm_test::sim::{self, SimSwarm, Simulation};
+
+#[test]
+fn renew_reservation_on_simulated_clock() {
+    let sim = Simulation::new();
+    let (sender, _receiver) = mpsc::unbounded();
+    let mut relay: SimSwarm<server::Swarm<_>> = SimSwarm::new(&sim, |key_pair| {
+        relay::server::Behavior::new(PeerId::from_public_key(&key_pair.verifying_key()), sender)
+    });
+    let relay_addr = sim::listen(&sim, &mut relay);
+    let relay_peer = *relay.local_peer_id();
+    let ttl = Duration::from_secs(60);
+    let mut backend: SimSwarm<client::Swarm<_>> = SimSwarm::new(&sim, |_| {
+        let mut behavior = reservation::Behavior::new(ttl);
+        behavior.reserve(relay_peer, relay_addr);
+        behavior
+    });
+
+    let accepted = |events: Vec<reservation::Event>| {
+        events
+            .into_iter()
+            .filter(|event| {
+                matches!(
+                    event,
+                    reservation::Event::ReservationAccepted { relay_peer_id, ttl: granted }
+                        if *relay_peer_id == relay_peer && *granted == ttl
+                )
+            })
+            .count()
+    };
+    sim::run_until_stalled(&sim, &mut [&mut backend, &mut relay]);
+    assert_eq!(accepted(backend.take_behavior_events()), 1);
+
+    // 有效期过半时续期
+    sim::advance(
+        &sim,
+        &mut [&mut backend, &mut relay],
+        ttl / 2 - Duration::from_secs(1),
+    );
+    assert_eq!(accepted(backend.take_behavior_events()), 0);
+    sim::advance(
+        &sim,
+        &mut [&mut backend, &mut relay],
+        Duration::from_secs(1),
+    );
+    assert_eq!(accepted(backend.take_behavior_events()), 1);
+    sim::advance(&sim, &mut [&mut backend, &mut relay], ttl);
+    assert_eq!(accepted(backend.take_behavior_events()), 2);
+}
@@ -0,0 +1,410 @@
+//! 连接数限制
+//!
+//! 在 `handle_pending_connection` / `handle_established_connection` 中检查计数，
+//! 超出限制时以 [`Exceeded`] 拒绝连接。
+
+use std::{
+    collections::{HashMap, HashSet},
+    convert::Infallible,
+    fmt,
+    hash::Hash,
+    net::IpAddr,
+    task::{Context, Poll},
+};
+
+use volans_core::{Multiaddr, PeerId, multiaddr::Protocol};
+use volans_swarm::{
+    BehaviorEvent, ConnectionDenied, ConnectionId, NetworkBehavior, NetworkIncomingBehavior,
+    NetworkOutgoingBehavior, THandlerAction, THandlerEvent,
+    error::{CloseReason, DialError, ListenError},
+    handler::DummyHandler,
+};
+
+/// 连接数限制配置，`None` 表示不限制
+#[derive(Debug, Clone, Default)]
+pub struct ConnectionLimits {
+    max_pending_incoming: Option<u32>,
+    max_pending_outgoing: Option<u32>,
+    max_established_incoming: Option<u32>,
+    max_established_outgoing: Option<u32>,
+    max_established_per_peer: Option<u32>,
+    max_established_total: Option<u32>,
+    max_incoming_per_ip: Option<u32>,
+}
+
+impl ConnectionLimits {
+    /// 入站握手中的连接数上限
+    pub fn with_max_pending_incoming(mut self, limit: Option<u32>) -> Self {
+        self.max_pending_incoming = limit;
+        self
+    }
+
+    /// 出站拨号中的连接数上限
+    pub fn with_max_pending_outgoing(mut self, limit: Option<u32>) -> Self {
+        self.max_pending_outgoing = limit;
+        self
+    }
+
+    /// 已建立的入站连接数上限
+    pub fn with_max_established_incoming(mut self, limit: Option<u32>) -> Self {
+        self.max_established_incoming = limit;
+        self
+    }
+
+    /// 已建立的出站连接数上限
+    pub fn with_max_established_outgoing(mut self, limit: Option<u32>) -> Self {
+        self.max_established_outgoing = limit;
+        self
+    }
+
+    /// 单个节点已建立的连接数上限
+    pub fn with_max_established_per_peer(mut self, limit: Option<u32>) -> Self {
+        self.max_established_per_peer = limit;
+        self
+    }
+
+    /// 已建立的连接总数上限
+    pub fn with_max_established(mut self, limit: Option<u32>) -> Self {
+        self.max_established_total = limit;
+        self
+    }
+
+    /// 单个远端 IP 的入站连接数上限，包含握手中的连接
+    pub fn with_max_incoming_per_ip(mut self, limit: Option<u32>) -> Self {
+        self.max_incoming_per_ip = limit;
+        self
+    }
+}
+
+/// 超出的限制类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LimitKind {
+    PendingIncoming,
+    PendingOutgoing,
+    EstablishedIncoming,
+    EstablishedOutgoing,
+    EstablishedPerPeer,
+    EstablishedTotal,
+    IncomingPerIp,
+}
+
+impl fmt::Display for LimitKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LimitKind::PendingIncoming => write!(f, "pending incoming"),
+            LimitKind::PendingOutgoing => write!(f, "pending outgoing"),
+            LimitKind::EstablishedIncoming => write!(f, "established incoming"),
+            LimitKind::EstablishedOutgoing => write!(f, "established outgoing"),
+            LimitKind::EstablishedPerPeer => write!(f, "established per peer"),
+            LimitKind::EstablishedTotal => write!(f, "established total"),
+            LimitKind::IncomingPerIp => write!(f, "incoming per ip"),
+        }
+    }
+}
+
+/// 连接被拒绝的原因，可通过 [`ConnectionDenied::downcast_ref`] 获取
+#[derive(Debug, Clone, Copy, thiserror::Error)]
+#[error("Connection limit exceeded: {kind} (limit: {limit})")]
+pub struct Exceeded {
+    limit: u32,
+    kind: LimitKind,
+}
+
+impl Exceeded {
+    pub fn limit(&self) -> u32 {
+        self.limit
+    }
+
+    pub fn kind(&self) -> LimitKind {
+        self.kind
+    }
+}
+
+fn check_limit(
+    limit: Option<u32>,
+    current: usize,
+    kind: LimitKind,
+) -> Result<(), ConnectionDenied> {
+    match limit {
+        Some(limit) if current >= limit as usize => {
+            tracing::debug!("Connection denied, {} limit {} reached", kind, limit);
+            Err(ConnectionDenied::new(Exceeded { limit, kind }))
+        }
+        _ => Ok(()),
+    }
+}
+
+fn remove_from<K>(map: &mut HashMap<K, HashSet<ConnectionId>>, key: &K, id: ConnectionId)
+where
+    K: Eq + Hash,
+{
+    if let Some(ids) = map.get_mut(key) {
+        ids.remove(&id);
+        if ids.is_empty() {
+            map.remove(key);
+        }
+    }
+}
+
+fn remote_ip(addr: &Multiaddr) -> Option<IpAddr> {
+    addr.iter().find_map(|p| match p {
+        Protocol::Ip4(ip) => Some(ip.into()),
+        Protocol::Ip6(ip) => Some(ip.into()),
+        _ => None,
+    })
+}
+
+pub struct Behavior {
+    limits: ConnectionLimits,
+
+    pending_incoming: HashSet<ConnectionId>,
+    pending_outgoing: HashSet<ConnectionId>,
+    established_incoming: HashSet<ConnectionId>,
+    established_outgoing: HashSet<ConnectionId>,
+    established_per_peer: HashMap<PeerId, HashSet<ConnectionId>>,
+    incoming_per_ip: HashMap<IpAddr, HashSet<ConnectionId>>,
+}
+
+impl Behavior {
+    pub fn new(limits: ConnectionLimits) -> Self {
+        Self {
+            limits,
+            pending_incoming: HashSet::new(),
+            pending_outgoing: HashSet::new(),
+            established_incoming: HashSet::new(),
+            established_outgoing: HashSet::new(),
+            established_per_peer: HashMap::new(),
+            incoming_per_ip: HashMap::new(),
+        }
+    }
+
+    /// 运行时调整限制，只影响之后的连接
+    pub fn limits_mut(&mut self) -> &mut ConnectionLimits {
+        &mut self.limits
+    }
+
+    fn check_established(&self, peer_id: &PeerId) -> Result<(), ConnectionDenied> {
+        check_limit(
+            self.limits.max_established_per_peer,
+            self.established_per_peer
+                .get(peer_id)
+                .map(HashSet::len)
+                .unwrap_or(0),
+            LimitKind::EstablishedPerPeer,
+        )?;
+        check_limit(
+            self.limits.max_established_total,
+            self.established_incoming.len() + self.established_outgoing.len(),
+            LimitKind::EstablishedTotal,
+        )
+    }
+
+    fn on_closed(&mut self, id: ConnectionId, peer_id: PeerId) {
+        self.established_incoming.remove(&id);
+        self.established_outgoing.remove(&id);
+        remove_from(&mut self.established_per_peer, &peer_id, id);
+    }
+}
+
+impl NetworkBehavior for Behavior {
+    type ConnectionHandler = DummyHandler;
+    type Event = Infallible;
+
+    fn on_connection_handler_event(
+        &mut self,
+        _id: ConnectionId,
+        _peer_id: PeerId,
+        event: THandlerEvent<Self>,
+    ) {
+        unreachable!("Unexpected event: {:?}", event);
+    }
+
+    fn poll(
+        &mut self,
+        _cx: &mut Context<'_>,
+    ) -> Poll<BehaviorEvent<Self::Event, THandlerAction<Self>>> {
+        Poll::Pending
+    }
+}
+
+impl NetworkIncomingBehavior for Behavior {
+    fn handle_pending_connection(
+        &mut self,
+        id: ConnectionId,
+        _local_addr: &Multiaddr,
+        remote_addr: &Multiaddr,
+    ) -> Result<(), ConnectionDenied> {
+        check_limit(
+            self.limits.max_pending_incoming,
+            self.pending_incoming.len(),
+            LimitKind::PendingIncoming,
+        )?;
+        if let Some(ip) = remote_ip(remote_addr) {
+            check_limit(
+                self.limits.max_incoming_per_ip,
+                self.incoming_per_ip.get(&ip).map(HashSet::len).unwrap_or(0),
+                LimitKind::IncomingPerIp,
+            )?;
+            self.incoming_per_ip.entry(ip).or_default().insert(id);
+        }
+        self.pending_incoming.insert(id);
+        Ok(())
+    }
+
+    fn handle_established_connection(
+        &mut self,
+        id: ConnectionId,
+        peer_id: PeerId,
+        _local_addr: &Multiaddr,
+        _remote_addr: &Multiaddr,
+    ) -> Result<Self::ConnectionHandler, ConnectionDenied> {
+        self.pending_incoming.remove(&id);
+        check_limit(
+            self.limits.max_established_incoming,
+            self.established_incoming.len(),
+            LimitKind::EstablishedIncoming,
+        )?;
+        self.check_established(&peer_id)?;
+        Ok(DummyHandler)
+    }
+
+    fn on_connection_established(
+        &mut self,
+        id: ConnectionId,
+        peer_id: PeerId,
+        _local_addr: &Multiaddr,
+        _remote_addr: &Multiaddr,
+    ) {
+        self.established_incoming.insert(id);
+        self.established_per_peer
+            .entry(peer_id)
+            .or_default()
+            .insert(id);
+    }
+
+    fn on_connection_closed(
+        &mut self,
+        id: ConnectionId,
+        peer_id: PeerId,
+        _local_addr: &Multiaddr,
+        remote_addr: &Multiaddr,
+        _reason: &CloseReason,
+    ) {
+        self.on_closed(id, peer_id);
+        if let Some(ip) = remote_ip(remote_addr) {
+            remove_from(&mut self.incoming_per_ip, &ip, id);
+        }
+    }
+
+    fn on_listen_failure(
+        &mut self,
+        id: ConnectionId,
+        _peer_id: Option<PeerId>,
+        _local_addr: &Multiaddr,
+        remote_addr: &Multiaddr,
+        _error: &ListenError,
+    ) {
+        self.pending_incoming.remove(&id);
+        if let Some(ip) = remote_ip(remote_addr) {
+            remove_from(&mut self.incoming_per_ip, &ip, id);
+        }
+    }
+}
+
+impl NetworkOutgoingBehavior for Behavior {
+    fn handle_pending_connection(
+        &mut self,
+        id: ConnectionId,
+        _maybe_peer: Option<PeerId>,
+        addr: &Option<Multiaddr>,
+    ) -> Result<Option<Multiaddr>, ConnectionDenied> {
+        check_limit(
+            self.limits.max_pending_outgoing,
+            self.pending_outgoing.len(),
+            LimitKind::PendingOutgoing,
+        )?;
+        self.pending_outgoing.insert(id);
+        Ok(addr.clone())
+    }
+
+    fn handle_established_connection(
+        &mut self,
+        id: ConnectionId,
+        peer_id: PeerId,
+        _addr: &Multiaddr,
+    ) -> Result<Self::ConnectionHandler, ConnectionDenied> {
+        self.pending_outgoing.remove(&id);
+        check_limit(
+            self.limits.max_established_outgoing,
+            self.established_outgoing.len(),
+            LimitKind::EstablishedOutgoing,
+        )?;
+        self.check_established(&peer_id)?;
+        Ok(DummyHandler)
+    }
+
+    fn on_connection_established(&mut self, id: ConnectionId, peer_id: PeerId, _addr: &Multiaddr) {
+        self.established_outgoing.insert(id);
+        self.established_per_peer
+            .entry(peer_id)
+            .or_default()
+            .insert(id);
+    }
+
+    fn on_connection_closed(
+        &mut self,
+        id: ConnectionId,
+        peer_id: PeerId,
+        _addr: &Multiaddr,
+        _reason: &CloseReason,
+    ) {
+        self.on_closed(id, peer_id);
+    }
+
+    fn on_dial_failure(
+        &mut self,
+        id: ConnectionId,
+        _peer_id: Option<PeerId>,
+        _addr: Option<&Multiaddr>,
+        _error: &DialError,
+    ) {
+        self.pending_outgoing.remove(&id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn incoming_per_ip_limit() {
+        let mut behavior =
+            Behavior::new(ConnectionLimits::default().with_max_incoming_per_ip(Some(1)));
+        let local: Multiaddr = "/ip4/127.0.0.1/tcp/8080".parse().unwrap();
+        let remote: Multiaddr = "/ip4/10.0.0.1/tcp/50000".parse().unwrap();
+        let first = ConnectionId::new_unchecked(1);
+
+        NetworkIncomingBehavior::handle_pending_connection(&mut behavior, first, &local, &remote)
+            .unwrap();
+        let denied = NetworkIncomingBehavior::handle_pending_connection(
+            &mut behavior,
+            ConnectionId::new_unchecked(2),
+            &local,
+            &remote,
+        )
+        .unwrap_err();
+        assert_eq!(
+            denied.downcast_ref::<Exceeded>().map(Exceeded::kind),
+            Some(LimitKind::IncomingPerIp)
+        );
+
+        behavior.on_listen_failure(first, None, &local, &remote, &ListenError::Aborted);
+        NetworkIncomingBehavior::handle_pending_connection(
+            &mut behavior,
+            ConnectionId::new_unchecked(3),
+            &local,
+            &remote,
+        )
+        .unwrap();
+    }
+}
@@ -0,0 +1,221 @@
+//! 由真实连接触发各项限制，连接结束后名额释放
+
+use futures::StreamExt;
+use volans_connection_limits::{Behavior, ConnectionLimits, Exceeded, LimitKind};
+use volans_core::{Multiaddr, PeerId, multiaddr::Protocol};
+use volans_swarm::{ConnectionId, DialOpts, PeerCondition, client, server};
+use volans_swarm_test::{SwarmExt, connect, listen, next_swarm_event, wait_for_event};
+
+/// 不设限制的监听节点，在后台驱动
+async fn spawn_listener() -> (PeerId, Multiaddr) {
+    let mut listener = server::Swarm::new_ephemeral(|_| Behavior::new(ConnectionLimits::default()));
+    let addr = listen(&mut listener).await;
+    let peer_id = *listener.local_peer_id();
+    tokio::spawn(async move {
+        loop {
+            listener.next().await;
+        }
+    });
+    (peer_id, addr)
+}
+
+fn dial_opts(peer_id: PeerId, addr: &Multiaddr) -> DialOpts {
+    DialOpts::new(Some(addr.clone()), Some(peer_id)).with_condition(PeerCondition::Always)
+}
+
+/// 拨号并等待连接建立
+async fn dial(dialer: &mut client::Swarm<Behavior>, opts: DialOpts) -> ConnectionId {
+    let connection_id = opts.connection_id();
+    dialer.dial(opts).unwrap();
+    wait_for_event(dialer, |event| match event {
+        client::SwarmEvent::ConnectionEstablished { connection_id, .. } => Some(connection_id),
+        client::SwarmEvent::OutgoingConnectionError { error, .. } => {
+            panic!("unexpected dial error: {error:?}")
+        }
+        _ => None,
+    })
+    .await;
+    connection_id
+}
+
+/// 拨号并等待在连接建立时被拒绝
+async fn dial_denied(dialer: &mut client::Swarm<Behavior>, opts: DialOpts) -> Option<LimitKind> {
+    dialer.dial(opts).unwrap();
+    let error = wait_for_event(dialer, |event| match event {
+        client::SwarmEvent::OutgoingConnectionError { error, .. } => Some(error),
+        _ => None,
+    })
+    .await;
+    error.denied_by::<Exceeded>().map(Exceeded::kind)
+}
+
+async fn close(dialer: &mut client::Swarm<Behavior>, id: ConnectionId) {
+    assert!(dialer.close_connection(id));
+    wait_for_event(dialer, |event| match event {
+        client::SwarmEvent::ConnectionClosed { connection_id, .. } if connection_id == id => {
+            Some(())
+        }
+        _ => None,
+    })
+    .await;
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn established_per_peer_limit() {
+    let mut dialer = client::Swarm::new_ephemeral(|_| {
+        Behavior::new(ConnectionLimits::default().with_max_established_per_peer(Some(1)))
+    });
+    let (peer_id, addr) = spawn_listener().await;
+    let first = dial(&mut dialer, dial_opts(peer_id, &addr)).await;
+
+    assert_eq!(
+        dial_denied(&mut dialer, dial_opts(peer_id, &addr)).await,
+        Some(LimitKind::EstablishedPerPeer)
+    );
+    // 其他节点不受影响
+    let (other, other_addr) = spawn_listener().await;
+    dial(&mut dialer, dial_opts(other, &other_addr)).await;
+
+    close(&mut dialer, first).await;
+    dial(&mut dialer, dial_opts(peer_id, &addr)).await;
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn established_outgoing_limit() {
+    let mut dialer = client::Swarm::new_ephemeral(|_| {
+        Behavior::new(ConnectionLimits::default().with_max_established_outgoing(Some(1)))
+    });
+    let (first_peer, first_addr) = spawn_listener().await;
+    let (second_peer, second_addr) = spawn_listener().await;
+    let first = dial(&mut dialer, dial_opts(first_peer, &first_addr)).await;
+
+    assert_eq!(
+        dial_denied(&mut dialer, dial_opts(second_peer, &second_addr)).await,
+        Some(LimitKind::EstablishedOutgoing)
+    );
+
+    close(&mut dialer, first).await;
+    dial(&mut dialer, dial_opts(second_peer, &second_addr)).await;
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn pending_outgoing_limit() {
+    // 只接受 TCP 连接，握手不会完成
+    let stalled = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = stalled.local_addr().unwrap().port();
+    let stalled_addr: Multiaddr = format!("/ip4/127.0.0.1/tcp/{port}").parse().unwrap();
+
+    let mut dialer = client::Swarm::new_ephemeral(|_| {
+        Behavior::new(ConnectionLimits::default().with_max_pending_outgoing(Some(1)))
+    });
+    let (peer_id, addr) = spawn_listener().await;
+    let stalled_opts = DialOpts::new(Some(stalled_addr), None);
+    let stalled_id = stalled_opts.connection_id();
+    dialer.dial(stalled_opts).unwrap();
+
+    let error = dialer.dial(dial_opts(peer_id, &addr)).unwrap_err();
+    assert_eq!(
+        error.denied_by::<Exceeded>().map(Exceeded::kind),
+        Some(LimitKind::PendingOutgoing)
+    );
+
+    // 放弃握手中的拨号后可以再次拨号
+    assert!(dialer.abort_dial(stalled_id));
+    wait_for_event(&mut dialer, |event| match event {
+        client::SwarmEvent::OutgoingConnectionError { connection_id, .. }
+            if connection_id == stalled_id =>
+        {
+            Some(())
+        }
+        _ => None,
+    })
+    .await;
+    dial(&mut dialer, dial_opts(peer_id, &addr)).await;
+}
+
+/// 驱动监听节点与拨号节点，直到监听节点拒绝一个入站连接
+async fn wait_for_denied_incoming(
+    listener: &mut server::Swarm<Behavior>,
+    dialer: &mut client::Swarm<Behavior>,
+) -> Option<LimitKind> {
+    loop {
+        tokio::select! {
+            event = next_swarm_event(listener) => {
+                if let server::SwarmEvent::IncomingConnectionError { error, .. } = event {
+                    return error.denied_by::<Exceeded>().map(Exceeded::kind);
+                }
+            }
+            _ = next_swarm_event(dialer) => {}
+        }
+    }
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn established_total_limit() {
+    let mut listener = server::Swarm::new_ephemeral(|_| {
+        Behavior::new(ConnectionLimits::default().with_max_established(Some(1)))
+    });
+    let mut first = client::Swarm::new_ephemeral(|_| Behavior::new(ConnectionLimits::default()));
+    let mut second = client::Swarm::new_ephemeral(|_| Behavior::new(ConnectionLimits::default()));
+    let first_peer = *first.local_peer_id();
+    connect(&mut first, &mut listener).await;
+    let addr = listener.listeners().next().cloned().unwrap();
+    tokio::spawn(async move {
+        loop {
+            first.next().await;
+        }
+    });
+
+    second
+        .dial(dial_opts(*listener.local_peer_id(), &addr))
+        .unwrap();
+    assert_eq!(
+        wait_for_denied_incoming(&mut listener, &mut second).await,
+        Some(LimitKind::EstablishedTotal)
+    );
+
+    // 第一个连接关闭后，第二个节点可以连接
+    let connection_id = *listener.connected_connections().next().unwrap();
+    assert!(listener.close_connection(connection_id));
+    wait_for_event(&mut listener, |event| match event {
+        server::SwarmEvent::ConnectionClosed { peer_id, .. } if peer_id == first_peer => Some(()),
+        _ => None,
+    })
+    .await;
+    connect(&mut second, &mut listener).await;
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn pending_incoming_limit() {
+    let mut listener = server::Swarm::new_ephemeral(|_| {
+        Behavior::new(ConnectionLimits::default().with_max_pending_incoming(Some(1)))
+    });
+    let mut dialer = client::Swarm::new_ephemeral(|_| Behavior::new(ConnectionLimits::default()));
+    let addr = listen(&mut listener).await;
+    let port = addr
+        .iter()
+        .find_map(|protocol| match protocol {
+            Protocol::Tcp(port) => Some(port),
+            _ => None,
+        })
+        .unwrap();
+
+    // 只建立 TCP 连接而不握手，占用入站握手的名额
+    let stalled = std::net::TcpStream::connect(("127.0.0.1", port)).unwrap();
+    dialer
+        .dial(dial_opts(*listener.local_peer_id(), &addr))
+        .unwrap();
+    assert_eq!(
+        wait_for_denied_incoming(&mut listener, &mut dialer).await,
+        Some(LimitKind::PendingIncoming)
+    );
+
+    // 未完成的握手失败后名额释放
+    drop(stalled);
+    wait_for_event(&mut listener, |event| match event {
+        server::SwarmEvent::IncomingConnectionError { .. } => Some(()),
+        _ => None,
+    })
+    .await;
+    connect(&mut dialer, &mut listener).await;
+}
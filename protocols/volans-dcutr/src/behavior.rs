@@ -0,0 +1,307 @@
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    task::{Context, Poll, Waker},
+};
+
+use either::Either;
+use volans_core::{Endpoint, Multiaddr, PeerId, multiaddr::Protocol};
+use volans_swarm::{
+    BehaviorEvent, ConnectionDenied, ConnectionId, DialOpts, ListenAddresses, ListenerEvent,
+    NetworkBehavior, NetworkIncomingBehavior, NetworkOutgoingBehavior, THandlerAction,
+    THandlerEvent,
+    behavior::CloseConnection,
+    error::{CloseReason, DialError},
+    handler::DummyHandler,
+};
+
+use crate::{Config, Error, Event, Handler, HandlerEvent, is_relayed};
+
+pub struct Behavior {
+    config: Config,
+    listen_addresses: ListenAddresses,
+    /// 由外部（如 identify）观察到的本地地址，Swarm 确认的外部地址会自动加入
+    external_addresses: HashSet<Multiaddr>,
+    /// 等待直连的中继连接
+    pending_upgrades: HashMap<PeerId, ConnectionId>,
+    /// 直连拨号对应的对端
+    direct_dials: HashMap<ConnectionId, PeerId>,
+    dial_queue: VecDeque<DialOpts>,
+    pending_events: VecDeque<BehaviorEvent<Event, THandlerAction<Self>>>,
+    waker: Option<Waker>,
+}
+
+impl Behavior {
+    pub fn new(config: Config) -> Self {
+        Self {
+            config,
+            listen_addresses: ListenAddresses::default(),
+            external_addresses: HashSet::new(),
+            pending_upgrades: HashMap::new(),
+            direct_dials: HashMap::new(),
+            dial_queue: VecDeque::new(),
+            pending_events: VecDeque::new(),
+            waker: None,
+        }
+    }
+
+    /// 添加对端观察到的本地地址，作为直连候选地址发给对端
+    pub fn add_external_address(&mut self, addr: Multiaddr) {
+        self.external_addresses.insert(addr);
+    }
+
+    pub fn remove_external_address(&mut self, addr: &Multiaddr) {
+        self.external_addresses.remove(addr);
+    }
+
+    /// 可被直连的候选地址，排除中继地址与未指定地址
+    fn candidates(&self) -> Vec<Multiaddr> {
+        self.external_addresses
+            .iter()
+            .chain(self.listen_addresses.iter())
+            .filter(|addr| !is_relayed(addr) && !is_unspecified(addr))
+            .cloned()
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .collect()
+    }
+
+    fn new_handler(&self, endpoint: Endpoint, addr: &Multiaddr) -> Either<DummyHandler, Handler> {
+        if is_relayed(addr) {
+            Either::Right(Handler::new(endpoint, &self.config, self.candidates()))
+        } else {
+            Either::Left(DummyHandler)
+        }
+    }
+
+    fn push_event(&mut self, event: BehaviorEvent<Event, THandlerAction<Self>>) {
+        self.pending_events.push_back(event);
+        if let Some(waker) = self.waker.take() {
+            waker.wake();
+        }
+    }
+
+    fn dial_direct(
+        &mut self,
+        relayed_connection_id: ConnectionId,
+        peer_id: PeerId,
+        addrs: Vec<Multiaddr>,
+    ) {
+        let addrs = addrs
+            .into_iter()
+            .filter(|addr| !is_relayed(addr))
+            .collect::<Vec<_>>();
+        if addrs.is_empty() {
+            self.push_event(BehaviorEvent::Behavior(
+                Event::DirectConnectionUpgradeFailed {
+                    remote_peer_id: peer_id,
+                    relayed_connection_id,
+                    error: Error::NoAddresses,
+                },
+            ));
+            return;
+        }
+        tracing::debug!("Dialing {:?} directly on {:?}", peer_id, addrs);
+        self.pending_upgrades.insert(peer_id, relayed_connection_id);
+        for addr in addrs {
+            let opts = DialOpts::new(Some(addr), Some(peer_id));
+            self.direct_dials.insert(opts.connection_id(), peer_id);
+            self.dial_queue.push_back(opts);
+        }
+        if let Some(waker) = self.waker.take() {
+            waker.wake();
+        }
+    }
+
+    fn on_connection_established(&mut self, id: ConnectionId, peer_id: PeerId, addr: &Multiaddr) {
+        if is_relayed(addr) {
+            return;
+        }
+        let Some(relayed_connection_id) = self.pending_upgrades.remove(&peer_id) else {
+            return;
+        };
+        self.direct_dials.retain(|_, peer| *peer != peer_id);
+        self.push_event(BehaviorEvent::Behavior(
+            Event::DirectConnectionUpgradeSucceeded {
+                remote_peer_id: peer_id,
+                relayed_connection_id,
+                direct_connection_id: id,
+            },
+        ));
+        if self.config.close_relayed {
+            self.push_event(BehaviorEvent::CloseConnection {
+                peer_id,
+                connection: CloseConnection::One(relayed_connection_id),
+            });
+        }
+    }
+
+    fn on_connection_closed(&mut self, id: ConnectionId, peer_id: PeerId) {
+        if self.pending_upgrades.get(&peer_id) == Some(&id) {
+            self.pending_upgrades.remove(&peer_id);
+            self.direct_dials.retain(|_, peer| *peer != peer_id);
+        }
+    }
+}
+
+impl Default for Behavior {
+    fn default() -> Self {
+        Self::new(Config::default())
+    }
+}
+
+impl NetworkBehavior for Behavior {
+    type ConnectionHandler = Either<DummyHandler, Handler>;
+    type Event = Event;
+
+    fn on_connection_handler_event(
+        &mut self,
+        id: ConnectionId,
+        peer_id: PeerId,
+        event: THandlerEvent<Self>,
+    ) {
+        match event {
+            Either::Left(never) => match never {},
+            Either::Right(HandlerEvent::Dial(addrs)) => self.dial_direct(id, peer_id, addrs),
+            Either::Right(HandlerEvent::Error(error)) => self.push_event(BehaviorEvent::Behavior(
+                Event::DirectConnectionUpgradeFailed {
+                    remote_peer_id: peer_id,
+                    relayed_connection_id: id,
+                    error: Error::Io(error),
+                },
+            )),
+        }
+    }
+
+    fn poll(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<BehaviorEvent<Self::Event, THandlerAction<Self>>> {
+        if let Some(event) = self.pending_events.pop_front() {
+            return Poll::Ready(event);
+        }
+        self.waker = Some(cx.waker().clone());
+        Poll::Pending
+    }
+}
+
+impl NetworkIncomingBehavior for Behavior {
+    fn handle_established_connection(
+        &mut self,
+        _id: ConnectionId,
+        _peer_id: PeerId,
+        local_addr: &Multiaddr,
+        _remote_addr: &Multiaddr,
+    ) -> Result<Self::ConnectionHandler, ConnectionDenied> {
+        // 经中继接受的连接，中继地址在本地地址中，远端地址只有对端的 PeerId
+        Ok(self.new_handler(Endpoint::Listener, local_addr))
+    }
+
+    fn on_connection_established(
+        &mut self,
+        id: ConnectionId,
+        peer_id: PeerId,
+        local_addr: &Multiaddr,
+        _remote_addr: &Multiaddr,
+    ) {
+        Behavior::on_connection_established(self, id, peer_id, local_addr);
+    }
+
+    fn on_connection_closed(
+        &mut self,
+        id: ConnectionId,
+        peer_id: PeerId,
+        _local_addr: &Multiaddr,
+        _remote_addr: &Multiaddr,
+        _reason: &CloseReason,
+    ) {
+        Behavior::on_connection_closed(self, id, peer_id);
+    }
+
+    fn on_listener_event(&mut self, event: ListenerEvent<'_>) {
+        self.listen_addresses.on_listener_event(&event);
+        match event {
+            ListenerEvent::ExternalAddrConfirmed(confirmed) => {
+                self.add_external_address(confirmed.addr.clone())
+            }
+            ListenerEvent::ExternalAddrExpired(expired) => {
+                self.remove_external_address(expired.addr)
+            }
+            _ => {}
+        }
+    }
+}
+
+impl NetworkOutgoingBehavior for Behavior {
+    fn handle_pending_connection(
+        &mut self,
+        _id: ConnectionId,
+        _maybe_peer: Option<PeerId>,
+        addr: &Option<Multiaddr>,
+    ) -> Result<Option<Multiaddr>, ConnectionDenied> {
+        Ok(addr.clone())
+    }
+
+    fn handle_established_connection(
+        &mut self,
+        _id: ConnectionId,
+        _peer_id: PeerId,
+        addr: &Multiaddr,
+    ) -> Result<Self::ConnectionHandler, ConnectionDenied> {
+        Ok(self.new_handler(Endpoint::Dialer, addr))
+    }
+
+    fn on_connection_established(&mut self, id: ConnectionId, peer_id: PeerId, addr: &Multiaddr) {
+        Behavior::on_connection_established(self, id, peer_id, addr);
+    }
+
+    fn on_connection_closed(
+        &mut self,
+        id: ConnectionId,
+        peer_id: PeerId,
+        _addr: &Multiaddr,
+        _reason: &CloseReason,
+    ) {
+        Behavior::on_connection_closed(self, id, peer_id);
+    }
+
+    fn on_dial_failure(
+        &mut self,
+        id: ConnectionId,
+        _peer_id: Option<PeerId>,
+        _addr: Option<&Multiaddr>,
+        error: &DialError,
+    ) {
+        let Some(peer_id) = self.direct_dials.remove(&id) else {
+            return;
+        };
+        tracing::debug!("Direct dial to {:?} failed: {:?}", peer_id, error);
+        if self.direct_dials.values().any(|peer| *peer == peer_id) {
+            return;
+        }
+        if let Some(relayed_connection_id) = self.pending_upgrades.remove(&peer_id) {
+            self.push_event(BehaviorEvent::Behavior(
+                Event::DirectConnectionUpgradeFailed {
+                    remote_peer_id: peer_id,
+                    relayed_connection_id,
+                    error: Error::DialFailed,
+                },
+            ));
+        }
+    }
+
+    fn poll_dial(&mut self, cx: &mut Context<'_>) -> Poll<DialOpts> {
+        if let Some(opts) = self.dial_queue.pop_front() {
+            return Poll::Ready(opts);
+        }
+        self.waker = Some(cx.waker().clone());
+        Poll::Pending
+    }
+}
+
+fn is_unspecified(addr: &Multiaddr) -> bool {
+    addr.iter().any(|p| match p {
+        Protocol::Ip4(ip) => ip.is_unspecified(),
+        Protocol::Ip6(ip) => ip.is_unspecified(),
+        _ => false,
+    })
+}
@@ -0,0 +1,163 @@
+use std::{
+    convert::Infallible,
+    io,
+    task::{Context, Poll},
+};
+
+use futures::FutureExt;
+use futures_bounded::{Delay, FuturesSet};
+use futures_timer::Delay as Timer;
+use volans_core::{Endpoint, Multiaddr, upgrade::ReadyUpgrade};
+use volans_swarm::{
+    ConnectionHandler, ConnectionHandlerEvent, InboundStreamHandler, InboundUpgradeSend, KeepAlive,
+    OutboundStreamHandler, OutboundUpgradeSend, StreamProtocol, StreamUpgradeError, Substream,
+    SubstreamProtocol,
+};
+
+use crate::{Config, protocol};
+
+#[derive(Debug)]
+pub enum HandlerEvent {
+    /// 协商完成，立即拨号对端的候选地址
+    Dial(Vec<Multiaddr>),
+    Error(io::Error),
+}
+
+/// 仅在中继连接上运行，拨号端发起一次协商，监听端只响应
+pub struct Handler {
+    endpoint: Endpoint,
+    local_addrs: Vec<Multiaddr>,
+    max_packet_size: usize,
+    outbound_requested: bool,
+    attempts: FuturesSet<io::Result<Vec<Multiaddr>>>,
+    pending_error: Option<io::Error>,
+}
+
+impl Handler {
+    pub(crate) fn new(endpoint: Endpoint, config: &Config, local_addrs: Vec<Multiaddr>) -> Self {
+        let timeout = config.timeout;
+        Self {
+            endpoint,
+            local_addrs,
+            max_packet_size: config.max_packet_size,
+            outbound_requested: false,
+            attempts: FuturesSet::new(move || Delay::futures_timer(timeout), 1),
+            pending_error: None,
+        }
+    }
+
+    fn push_attempt(&mut self, endpoint: Endpoint, stream: Substream) {
+        let local_addrs = self.local_addrs.clone();
+        let max_packet_size = self.max_packet_size;
+        let future = match endpoint {
+            Endpoint::Dialer => async move {
+                let (remote_addrs, rtt) =
+                    protocol::connect_outbound(stream, local_addrs, max_packet_size).await?;
+                // SYNC 到达对端约需半个往返时间，之后双方同时拨号
+                Timer::new(rtt / 2).await;
+                Ok(remote_addrs)
+            }
+            .boxed(),
+            Endpoint::Listener => {
+                protocol::connect_inbound(stream, local_addrs, max_packet_size).boxed()
+            }
+        };
+        if self.attempts.try_push(future).is_err() {
+            tracing::debug!("Dropping dcutr stream: upgrade already in progress");
+        }
+    }
+}
+
+impl ConnectionHandler for Handler {
+    type Action = Infallible;
+    type Event = HandlerEvent;
+
+    fn handle_action(&mut self, action: Self::Action) {
+        match action {}
+    }
+
+    fn keep_alive(&self) -> KeepAlive {
+        KeepAlive::from(!self.attempts.is_empty())
+    }
+
+    fn poll(&mut self, cx: &mut Context<'_>) -> Poll<ConnectionHandlerEvent<Self::Event>> {
+        if let Some(error) = self.pending_error.take() {
+            return Poll::Ready(ConnectionHandlerEvent::Notify(HandlerEvent::Error(error)));
+        }
+        let event = match self.attempts.poll_unpin(cx) {
+            Poll::Ready(Ok(Ok(addrs))) => HandlerEvent::Dial(addrs),
+            Poll::Ready(Ok(Err(error))) => HandlerEvent::Error(error),
+            Poll::Ready(Err(_)) => HandlerEvent::Error(io::ErrorKind::TimedOut.into()),
+            Poll::Pending => return Poll::Pending,
+        };
+        Poll::Ready(ConnectionHandlerEvent::Notify(event))
+    }
+}
+
+impl InboundStreamHandler for Handler {
+    type InboundUpgrade = ReadyUpgrade<StreamProtocol>;
+    type InboundUserData = ();
+
+    fn listen_protocol(&self) -> SubstreamProtocol<Self::InboundUpgrade, Self::InboundUserData> {
+        SubstreamProtocol::new(ReadyUpgrade::new(protocol::PROTOCOL_NAME), ())
+    }
+
+    fn on_fully_negotiated(
+        &mut self,
+        _user_data: Self::InboundUserData,
+        protocol: <Self::InboundUpgrade as InboundUpgradeSend>::Output,
+    ) {
+        self.push_attempt(Endpoint::Listener, protocol);
+    }
+
+    fn on_upgrade_error(
+        &mut self,
+        _user_data: Self::InboundUserData,
+        error: <Self::InboundUpgrade as InboundUpgradeSend>::Error,
+    ) {
+        tracing::debug!("Dcutr inbound upgrade error: {}", error);
+    }
+}
+
+impl OutboundStreamHandler for Handler {
+    type OutboundUpgrade = ReadyUpgrade<StreamProtocol>;
+    type OutboundUserData = ();
+
+    fn on_fully_negotiated(
+        &mut self,
+        _user_data: Self::OutboundUserData,
+        protocol: <Self::OutboundUpgrade as OutboundUpgradeSend>::Output,
+    ) {
+        self.push_attempt(Endpoint::Dialer, protocol);
+    }
+
+    fn on_upgrade_error(
+        &mut self,
+        _user_data: Self::OutboundUserData,
+        error: StreamUpgradeError<<Self::OutboundUpgrade as OutboundUpgradeSend>::Error>,
+    ) {
+        self.pending_error = Some(match error {
+            StreamUpgradeError::Timeout => io::ErrorKind::TimedOut.into(),
+            StreamUpgradeError::NegotiationFailed { .. } => {
+                io::Error::new(io::ErrorKind::Unsupported, "dcutr protocol not supported")
+            }
+            StreamUpgradeError::RemoteReset => io::ErrorKind::ConnectionReset.into(),
+            StreamUpgradeError::Io(error) => error,
+            StreamUpgradeError::Apply(error) => match error {},
+        });
+    }
+
+    fn poll_outbound_request(
+        &mut self,
+        _cx: &mut Context<'_>,
+    ) -> Poll<SubstreamProtocol<Self::OutboundUpgrade, Self::OutboundUserData>> {
+        if self.endpoint != Endpoint::Dialer || self.outbound_requested {
+            return Poll::Pending;
+        }
+        self.outbound_requested = true;
+        Poll::Ready(SubstreamProtocol::new(
+            ReadyUpgrade::new(protocol::PROTOCOL_NAME),
+            (),
+        ))
+    }
+}
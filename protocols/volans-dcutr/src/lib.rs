@@ -0,0 +1,83 @@
+//! 中继连接升级为直连
+//!
+//! 经中继建立连接后，由拨号端在中继连接上打开子流，双方交换可被直连的候选地址并测量往返时间。
+//! 拨号端发送 SYNC 后等待半个往返时间再拨号，监听端收到 SYNC 后立即拨号，使双方的拨号在 NAT
+//! 上几乎同时发生。任意一条直连建立后关闭中继连接。
+//!
+//! 双方都需要拨号，因此需配合 `duplex::Swarm` 使用；TCP 传输建议开启端口复用。
+
+mod behavior;
+mod handler;
+mod protocol;
+
+pub use behavior::Behavior;
+pub use handler::{Handler, HandlerEvent};
+
+use std::{io, time::Duration};
+
+use volans_core::{Multiaddr, PeerId, multiaddr::Protocol};
+use volans_swarm::ConnectionId;
+
+#[derive(Debug, Clone)]
+pub struct Config {
+    timeout: Duration,
+    max_packet_size: usize,
+    close_relayed: bool,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            timeout: Duration::from_secs(10),
+            max_packet_size: 4096,
+            close_relayed: true,
+        }
+    }
+}
+
+impl Config {
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn with_max_packet_size(mut self, size: usize) -> Self {
+        self.max_packet_size = size;
+        self
+    }
+
+    /// 直连建立后是否关闭中继连接
+    pub fn with_close_relayed(mut self, close_relayed: bool) -> Self {
+        self.close_relayed = close_relayed;
+        self
+    }
+}
+
+#[derive(Debug)]
+pub enum Event {
+    /// 直连已建立
+    DirectConnectionUpgradeSucceeded {
+        remote_peer_id: PeerId,
+        relayed_connection_id: ConnectionId,
+        direct_connection_id: ConnectionId,
+    },
+    DirectConnectionUpgradeFailed {
+        remote_peer_id: PeerId,
+        relayed_connection_id: ConnectionId,
+        error: Error,
+    },
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("Remote peer has no direct addresses")]
+    NoAddresses,
+    #[error("All direct dials failed")]
+    DialFailed,
+    #[error("I/O error")]
+    Io(#[from] io::Error),
+}
+
+pub(crate) fn is_relayed(addr: &Multiaddr) -> bool {
+    addr.iter().any(|p| p == Protocol::Circuit)
+}
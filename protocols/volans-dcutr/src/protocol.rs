@@ -0,0 +1,97 @@
+use std::{
+    io,
+    time::{Duration, Instant},
+};
+
+use futures::{SinkExt, StreamExt};
+use volans_codec::{Framed, ProtobufUviCodec};
+use volans_core::Multiaddr;
+use volans_swarm::{StreamProtocol, Substream};
+
+pub mod v1 {
+    include!(concat!(env!("OUT_DIR"), "/volans.dcutr.v1.rs"));
+}
+
+use v1::hole_punch::Type;
+
+pub(crate) const PROTOCOL_NAME: StreamProtocol = StreamProtocol::new("/v1/dcutr");
+
+type HolePunchStream = Framed<Substream, ProtobufUviCodec<v1::HolePunch>>;
+
+/// 拨号端发送 CONNECT，收到对端 CONNECT 后发送 SYNC，返回对端地址与往返时间
+pub(crate) async fn connect_outbound(
+    stream: Substream,
+    local_addrs: Vec<Multiaddr>,
+    max_packet_size: usize,
+) -> io::Result<(Vec<Multiaddr>, Duration)> {
+    let mut stream = Framed::new(stream, ProtobufUviCodec::new(max_packet_size));
+    let start = Instant::now();
+    stream.send(encode(Type::Connect, &local_addrs)).await?;
+    let remote = recv(&mut stream, Type::Connect).await?;
+    let rtt = start.elapsed();
+    stream.send(encode(Type::Sync, &[])).await?;
+    stream.close().await?;
+    Ok((decode_addrs(remote), rtt))
+}
+
+/// 监听端回复 CONNECT，收到 SYNC 后返回对端地址
+pub(crate) async fn connect_inbound(
+    stream: Substream,
+    local_addrs: Vec<Multiaddr>,
+    max_packet_size: usize,
+) -> io::Result<Vec<Multiaddr>> {
+    let mut stream = Framed::new(stream, ProtobufUviCodec::new(max_packet_size));
+    let remote = recv(&mut stream, Type::Connect).await?;
+    stream.send(encode(Type::Connect, &local_addrs)).await?;
+    recv(&mut stream, Type::Sync).await?;
+    stream.close().await?;
+    Ok(decode_addrs(remote))
+}
+
+async fn recv(stream: &mut HolePunchStream, expected: Type) -> io::Result<v1::HolePunch> {
+    let message = stream
+        .next()
+        .await
+        .ok_or_else(|| io::Error::from(io::ErrorKind::UnexpectedEof))??;
+    if message.r#type() != expected {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("expected {expected:?}, got {:?}", message.r#type()),
+        ));
+    }
+    Ok(message)
+}
+
+fn encode(r#type: Type, addrs: &[Multiaddr]) -> v1::HolePunch {
+    v1::HolePunch {
+        r#type: r#type as i32,
+        addrs: addrs.iter().map(|a| a.to_string()).collect(),
+    }
+}
+
+/// 忽略无法解析的地址
+fn decode_addrs(message: v1::HolePunch) -> Vec<Multiaddr> {
+    message
+        .addrs
+        .iter()
+        .filter_map(|a| a.parse().ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn addrs_roundtrip() {
+        let addrs: Vec<Multiaddr> = vec![
+            "/ip4/10.0.0.1/tcp/9000".parse().unwrap(),
+            "/ip6/::1/tcp/9001".parse().unwrap(),
+        ];
+        let mut message = encode(Type::Connect, &addrs);
+        assert_eq!(message.r#type(), Type::Connect);
+
+        message.addrs.push("not an address".to_string());
+        assert_eq!(decode_addrs(message), addrs);
+    }
+}
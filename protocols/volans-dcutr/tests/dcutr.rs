@@ -0,0 +1,258 @@
+//! 客户端经由 volans-bridge 中继连接后端，再尝试升级为直连
+//!
+//! 中继与后端之间使用进程内传输，客户端的传输层不支持进程内地址时直连必然失败。
+
+use futures::StreamExt;
+use volans_bridge::{backend, client as bridge_client, relay};
+use volans_core::{
+    ConnectedPoint, Multiaddr, PeerId, Transport, identity::KeyPair, multiaddr::Protocol,
+    muxing::StreamMuxerBox, transport,
+};
+use volans_dcutr::{Error, Event};
+use volans_swarm::{
+    ConnectionId, DialOpts, NetworkDuplexBehavior, NetworkOutgoingBehavior, client,
+    connection::PoolConfig, duplex, server,
+};
+use volans_swarm_test::{
+    ListenSwarm, SwarmExt, ephemeral_key_pair, ephemeral_parts, listen, memory::MemoryTransport,
+    wait_for_event,
+};
+
+#[derive(NetworkOutgoingBehavior)]
+#[behavior(prelude = "volans_swarm::derive_prelude")]
+struct RelayClient {
+    relay: relay::client::Behavior,
+}
+
+#[derive(NetworkDuplexBehavior)]
+#[behavior(prelude = "volans_swarm::derive_prelude")]
+struct Backend {
+    bridge: backend::Behavior,
+    dcutr: volans_dcutr::Behavior,
+}
+
+#[derive(NetworkDuplexBehavior)]
+#[behavior(prelude = "volans_swarm::derive_prelude")]
+struct Client {
+    bridge: bridge_client::Behavior,
+    dcutr: volans_dcutr::Behavior,
+}
+
+fn peer_id(key_pair: &KeyPair) -> PeerId {
+    PeerId::from_public_key(&key_pair.verifying_key())
+}
+
+fn memory_transport(key_pair: &KeyPair) -> transport::Boxed<(PeerId, StreamMuxerBox)> {
+    MemoryTransport
+        .upgrade()
+        .authenticate(volans_plaintext::Config::new(key_pair.verifying_key()))
+        .multiplex(volans_muxing::Config::new())
+        .boxed()
+}
+
+async fn listen_on_memory<S: ListenSwarm>(swarm: &mut S) -> Multiaddr {
+    swarm
+        .listen_on(Protocol::Memory(0).into())
+        .expect("listen on memory");
+    wait_for_event(swarm, |event| S::new_listen_addr(&event).cloned()).await
+}
+
+/// 只在进程内地址上监听的后端，同时接受经中继的连接
+async fn backend() -> (duplex::Swarm<Backend>, Multiaddr) {
+    let key_pair = ephemeral_key_pair();
+    let (bridge, behavior) = backend::new();
+    let mut backend = duplex::Swarm::from_parts(
+        bridge
+            .choice(MemoryTransport)
+            .upgrade()
+            .authenticate(volans_plaintext::Config::new(key_pair.verifying_key()))
+            .multiplex(volans_muxing::Config::new())
+            .boxed(),
+        Backend {
+            bridge: behavior,
+            dcutr: volans_dcutr::Behavior::default(),
+        },
+        peer_id(&key_pair),
+        PoolConfig::with_tokio_executor(),
+    );
+    let addr = listen_on_memory(&mut backend).await;
+    backend.listen_on(Protocol::Circuit.into()).unwrap();
+    (backend, addr)
+}
+
+/// 在后台运行的中继，以 TCP 接受客户端，经进程内传输连接后端，返回中继地址
+async fn spawn_relay(backend_peer: PeerId, backend_addr: Multiaddr) -> Multiaddr {
+    let key_pair = ephemeral_key_pair();
+    let relay_peer = peer_id(&key_pair);
+    let (relay_server, relay_client) = relay::new(relay_peer);
+    let mut relay_in = server::Swarm::from_parts(
+        ephemeral_parts(&key_pair).0,
+        relay_server,
+        relay_peer,
+        PoolConfig::with_tokio_executor(),
+    );
+    let mut relay_out = client::Swarm::from_parts(
+        memory_transport(&key_pair),
+        RelayClient {
+            relay: relay_client,
+        },
+        relay_peer,
+        PoolConfig::with_tokio_executor(),
+    );
+    relay_out
+        .peer_store_mut()
+        .add_address(backend_peer, backend_addr);
+    let relay_addr = listen(&mut relay_in).await;
+    tokio::spawn(async move {
+        loop {
+            tokio::select! {
+                _ = relay_in.next() => {}
+                _ = relay_out.next() => {}
+            }
+        }
+    });
+    relay_addr.with(Protocol::Peer(relay_peer))
+}
+
+/// 经中继拨号的客户端，`direct` 为 `true` 时可以拨号并监听进程内地址
+async fn client(direct: bool) -> duplex::Swarm<Client> {
+    let key_pair = ephemeral_key_pair();
+    let (bridge, behavior) = bridge_client::new();
+    let transport = bridge
+        .upgrade()
+        .authenticate(volans_plaintext::Config::new(key_pair.verifying_key()))
+        .multiplex(volans_muxing::Config::new())
+        .boxed()
+        .or_transport(ephemeral_parts(&key_pair).0)
+        .boxed();
+    let transport = if direct {
+        transport.or_transport(memory_transport(&key_pair)).boxed()
+    } else {
+        transport
+    };
+    let mut client = duplex::Swarm::from_parts(
+        transport,
+        Client {
+            bridge: behavior,
+            dcutr: volans_dcutr::Behavior::default(),
+        },
+        peer_id(&key_pair),
+        PoolConfig::with_tokio_executor(),
+    );
+    if direct {
+        listen_on_memory(&mut client).await;
+    }
+    client
+}
+
+/// 客户端经中继拨号后端，返回中继连接的 ID
+async fn relayed(direct: bool) -> (duplex::Swarm<Client>, duplex::Swarm<Backend>, ConnectionId) {
+    let (backend, backend_addr) = backend().await;
+    let backend_peer = *backend.local_peer_id();
+    let relay_addr = spawn_relay(backend_peer, backend_addr).await;
+    let mut client = client(direct).await;
+    let opts = DialOpts::new(
+        Some(
+            relay_addr
+                .with(Protocol::Circuit)
+                .with(Protocol::Peer(backend_peer)),
+        ),
+        None,
+    );
+    let relayed_id = opts.connection_id();
+    client.dial(opts).unwrap();
+    (client, backend, relayed_id)
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn upgrade_relayed_connection() {
+    let (mut client, mut backend, relayed_id) = relayed(true).await;
+    let backend_peer = *backend.local_peer_id();
+
+    let mut direct_id = None;
+    let mut relayed_closed = false;
+    while direct_id.is_none() || !relayed_closed {
+        tokio::select! {
+            event = client.next() => match event.unwrap() {
+                duplex::SwarmEvent::Behavior(ClientEvent::Dcutr(event)) => match event {
+                    Event::DirectConnectionUpgradeSucceeded {
+                        remote_peer_id,
+                        relayed_connection_id,
+                        direct_connection_id,
+                    } => {
+                        assert_eq!(remote_peer_id, backend_peer);
+                        assert_eq!(relayed_connection_id, relayed_id);
+                        direct_id = Some(direct_connection_id);
+                    }
+                    event => panic!("unexpected dcutr event: {event:?}"),
+                },
+                duplex::SwarmEvent::ConnectionClosed { connection_id, .. }
+                    if connection_id == relayed_id =>
+                {
+                    relayed_closed = true;
+                }
+                _ => {}
+            },
+            _ = backend.next() => {}
+        }
+    }
+
+    // 中继连接关闭后仍经直连与后端保持连接
+    let direct_id = direct_id.unwrap();
+    let info = client
+        .connection_info(direct_id)
+        .expect("direct connection");
+    assert_eq!(info.peer_id, backend_peer);
+    let addr = match info.endpoint {
+        ConnectedPoint::Dialer { addr } => addr,
+        ConnectedPoint::Listener { remote_addr, .. } => remote_addr,
+    };
+    assert!(addr.iter().all(|protocol| protocol != Protocol::Circuit));
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn report_failed_upgrade() {
+    let (mut client, mut backend, relayed_id) = relayed(false).await;
+    let backend_peer = *backend.local_peer_id();
+    let client_peer = *client.local_peer_id();
+
+    // 客户端无法拨号后端的进程内地址，后端则没有客户端的候选地址
+    let mut client_error = None;
+    let mut backend_error = None;
+    while client_error.is_none() || backend_error.is_none() {
+        tokio::select! {
+            event = client.next() => {
+                if let duplex::SwarmEvent::Behavior(ClientEvent::Dcutr(
+                    Event::DirectConnectionUpgradeFailed {
+                        remote_peer_id,
+                        relayed_connection_id,
+                        error,
+                    },
+                )) = event.unwrap()
+                {
+                    assert_eq!(remote_peer_id, backend_peer);
+                    assert_eq!(relayed_connection_id, relayed_id);
+                    client_error = Some(error);
+                }
+            }
+            event = backend.next() => {
+                if let duplex::SwarmEvent::Behavior(BackendEvent::Dcutr(
+                    Event::DirectConnectionUpgradeFailed {
+                        remote_peer_id,
+                        error,
+                        ..
+                    },
+                )) = event.unwrap()
+                {
+                    assert_eq!(remote_peer_id, client_peer);
+                    backend_error = Some(error);
+                }
+            }
+        }
+    }
+    assert!(matches!(client_error, Some(Error::DialFailed)));
+    assert!(matches!(backend_error, Some(Error::NoAddresses)));
+
+    // 升级失败不影响中继连接
+    assert!(client.connection_info(relayed_id).is_some());
+}
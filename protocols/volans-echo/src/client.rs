@@ -0,0 +1,314 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    io,
+    task::{Context, Poll, Waker},
+    time::Duration,
+};
+
+use futures::FutureExt;
+use futures_bounded::{Delay, FuturesMap};
+use volans_core::{Multiaddr, PeerId, upgrade::ReadyUpgrade};
+use volans_swarm::{
+    BehaviorEvent, ConnectionDenied, ConnectionHandler, ConnectionHandlerEvent, ConnectionId,
+    DialOpts, KeepAlive, NetworkBehavior, NetworkOutgoingBehavior, OutboundStreamHandler,
+    OutboundUpgradeSend, StreamProtocol, StreamUpgradeError, SubstreamProtocol, THandlerAction,
+    THandlerEvent,
+    behavior::NotifyHandler,
+    error::{CloseReason, DialError},
+};
+
+use crate::{Config, EchoId, Failure, protocol};
+
+/// 拨号端发起回显，每次回显使用一个子流
+pub struct Handler {
+    config: Config,
+    pending: VecDeque<(EchoId, Vec<u8>)>,
+    pending_events: VecDeque<(EchoId, Result<Duration, Failure>)>,
+    running: FuturesMap<EchoId, Result<Duration, Failure>>,
+}
+
+impl Handler {
+    pub fn new(config: Config) -> Self {
+        let timeout = config.timeout;
+        Self {
+            running: FuturesMap::new(
+                move || Delay::futures_timer(timeout),
+                config.max_concurrent_echoes,
+            ),
+            config,
+            pending: VecDeque::new(),
+            pending_events: VecDeque::new(),
+        }
+    }
+}
+
+impl ConnectionHandler for Handler {
+    type Action = (EchoId, Vec<u8>);
+    type Event = (EchoId, Result<Duration, Failure>);
+
+    fn handle_action(&mut self, action: Self::Action) {
+        self.pending.push_back(action);
+    }
+
+    fn keep_alive(&self) -> KeepAlive {
+        KeepAlive::from(!self.pending.is_empty() || !self.running.is_empty())
+    }
+
+    fn poll_close(&mut self, _: &mut Context<'_>) -> Poll<Option<Self::Event>> {
+        Poll::Ready(self.pending_events.pop_front())
+    }
+
+    fn poll(&mut self, cx: &mut Context<'_>) -> Poll<ConnectionHandlerEvent<Self::Event>> {
+        if let Some(event) = self.pending_events.pop_front() {
+            return Poll::Ready(ConnectionHandlerEvent::Notify(event));
+        }
+        let event = match self.running.poll_unpin(cx) {
+            Poll::Ready((id, Ok(result))) => (id, result),
+            Poll::Ready((id, Err(_))) => (id, Err(Failure::Timeout)),
+            Poll::Pending => return Poll::Pending,
+        };
+        Poll::Ready(ConnectionHandlerEvent::Notify(event))
+    }
+}
+
+impl OutboundStreamHandler for Handler {
+    type OutboundUpgrade = ReadyUpgrade<StreamProtocol>;
+    type OutboundUserData = (EchoId, Vec<u8>);
+
+    fn on_fully_negotiated(
+        &mut self,
+        (id, payload): Self::OutboundUserData,
+        stream: <Self::OutboundUpgrade as OutboundUpgradeSend>::Output,
+    ) {
+        let echo = protocol::send_echo(stream, payload).boxed();
+        if self.running.try_push(id, echo).is_err() {
+            self.pending_events
+                .push_back((id, Err(Failure::Io(io::Error::other("max echoes reached")))));
+        }
+    }
+
+    fn on_upgrade_error(
+        &mut self,
+        (id, _): Self::OutboundUserData,
+        error: StreamUpgradeError<<Self::OutboundUpgrade as OutboundUpgradeSend>::Error>,
+    ) {
+        let failure = match error {
+            StreamUpgradeError::Timeout => Failure::Timeout,
+            StreamUpgradeError::NegotiationFailed { .. } => Failure::Unsupported,
+            StreamUpgradeError::RemoteReset => Failure::RemoteReset,
+            StreamUpgradeError::Io(error) => Failure::Io(error),
+            StreamUpgradeError::Apply(error) => match error {},
+        };
+        self.pending_events.push_back((id, Err(failure)));
+    }
+
+    fn poll_outbound_request(
+        &mut self,
+        _cx: &mut Context<'_>,
+    ) -> Poll<SubstreamProtocol<Self::OutboundUpgrade, Self::OutboundUserData>> {
+        if let Some(echo) = self.pending.pop_front() {
+            let protocol = SubstreamProtocol::new(ReadyUpgrade::new(protocol::PROTOCOL_NAME), echo)
+                .with_timeout(self.config.timeout);
+            return Poll::Ready(protocol);
+        }
+        Poll::Pending
+    }
+}
+
+#[derive(Debug)]
+pub struct Event {
+    pub echo_id: EchoId,
+    pub peer_id: PeerId,
+    /// 返回的数据与发送的一致时为往返时延
+    pub result: Result<Duration, Failure>,
+}
+
+/// 发起回显的行为，未连接的节点先拨号
+pub struct Behavior {
+    config: Config,
+    connections: HashMap<PeerId, Vec<ConnectionId>>,
+    /// 已交给连接处理器的回显
+    running: HashMap<EchoId, (PeerId, ConnectionId)>,
+    /// 等待连接的回显
+    pending_echoes: HashMap<PeerId, Vec<(EchoId, Vec<u8>)>>,
+    pending_dials: VecDeque<PeerId>,
+    events: VecDeque<BehaviorEvent<Event, THandlerAction<Self>>>,
+    waker: Option<Waker>,
+}
+
+impl Behavior {
+    pub fn new(config: Config) -> Self {
+        Self {
+            config,
+            connections: HashMap::new(),
+            running: HashMap::new(),
+            pending_echoes: HashMap::new(),
+            pending_dials: VecDeque::new(),
+            events: VecDeque::new(),
+            waker: None,
+        }
+    }
+
+    /// 向节点发送数据并等待原样返回，结果通过 [`Event`] 上报
+    pub fn echo(&mut self, peer_id: PeerId, payload: impl Into<Vec<u8>>) -> EchoId {
+        let id = EchoId::next();
+        let payload = payload.into();
+        match self.connections.get(&peer_id).and_then(|c| c.first()) {
+            Some(connection) => self.start_echo(peer_id, *connection, id, payload),
+            None => {
+                let echoes = self.pending_echoes.entry(peer_id).or_default();
+                if echoes.is_empty() {
+                    self.pending_dials.push_back(peer_id);
+                }
+                echoes.push((id, payload));
+            }
+        }
+        self.wake();
+        id
+    }
+
+    fn start_echo(
+        &mut self,
+        peer_id: PeerId,
+        connection: ConnectionId,
+        id: EchoId,
+        payload: Vec<u8>,
+    ) {
+        self.running.insert(id, (peer_id, connection));
+        self.events.push_back(BehaviorEvent::HandlerAction {
+            peer_id,
+            handler: NotifyHandler::One(connection),
+            action: (id, payload),
+        });
+    }
+
+    fn report(&mut self, echo_id: EchoId, peer_id: PeerId, result: Result<Duration, Failure>) {
+        self.events.push_back(BehaviorEvent::Behavior(Event {
+            echo_id,
+            peer_id,
+            result,
+        }));
+        self.wake();
+    }
+
+    fn wake(&mut self) {
+        if let Some(waker) = self.waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+impl Default for Behavior {
+    fn default() -> Self {
+        Self::new(Config::default())
+    }
+}
+
+impl NetworkBehavior for Behavior {
+    type ConnectionHandler = Handler;
+    type Event = Event;
+
+    fn on_connection_handler_event(
+        &mut self,
+        _id: ConnectionId,
+        peer_id: PeerId,
+        (echo_id, result): THandlerEvent<Self>,
+    ) {
+        if self.running.remove(&echo_id).is_some() {
+            self.report(echo_id, peer_id, result);
+        }
+    }
+
+    fn poll(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<BehaviorEvent<Self::Event, THandlerAction<Self>>> {
+        if let Some(event) = self.events.pop_front() {
+            return Poll::Ready(event);
+        }
+        self.waker = Some(cx.waker().clone());
+        Poll::Pending
+    }
+}
+
+impl NetworkOutgoingBehavior for Behavior {
+    fn handle_established_connection(
+        &mut self,
+        _id: ConnectionId,
+        _peer_id: PeerId,
+        _addr: &Multiaddr,
+    ) -> Result<Self::ConnectionHandler, ConnectionDenied> {
+        Ok(Handler::new(self.config.clone()))
+    }
+
+    fn handle_pending_connection(
+        &mut self,
+        _id: ConnectionId,
+        _maybe_peer: Option<PeerId>,
+        addr: &Option<Multiaddr>,
+    ) -> Result<Option<Multiaddr>, ConnectionDenied> {
+        Ok(addr.clone())
+    }
+
+    fn on_connection_established(&mut self, id: ConnectionId, peer_id: PeerId, _addr: &Multiaddr) {
+        self.connections.entry(peer_id).or_default().push(id);
+        for (echo_id, payload) in self.pending_echoes.remove(&peer_id).unwrap_or_default() {
+            self.start_echo(peer_id, id, echo_id, payload);
+        }
+        self.wake();
+    }
+
+    fn on_connection_closed(
+        &mut self,
+        id: ConnectionId,
+        peer_id: PeerId,
+        _addr: &Multiaddr,
+        _reason: &CloseReason,
+    ) {
+        if let Some(connections) = self.connections.get_mut(&peer_id) {
+            connections.retain(|c| *c != id);
+            if connections.is_empty() {
+                self.connections.remove(&peer_id);
+            }
+        }
+        // 连接关闭前处理器上报的结果已经送达，剩下的回显不会再有结果
+        let closed: Vec<_> = self
+            .running
+            .iter()
+            .filter(|(_, (_, connection))| *connection == id)
+            .map(|(echo_id, _)| *echo_id)
+            .collect();
+        for echo_id in closed {
+            self.running.remove(&echo_id);
+            self.report(echo_id, peer_id, Err(Failure::ConnectionClosed));
+        }
+    }
+
+    fn on_dial_failure(
+        &mut self,
+        _id: ConnectionId,
+        peer_id: Option<PeerId>,
+        _addr: Option<&Multiaddr>,
+        _error: &DialError,
+    ) {
+        let Some(peer_id) = peer_id else {
+            return;
+        };
+        if self.connections.contains_key(&peer_id) {
+            return;
+        }
+        for (echo_id, _) in self.pending_echoes.remove(&peer_id).unwrap_or_default() {
+            self.report(echo_id, peer_id, Err(Failure::Dial));
+        }
+    }
+
+    fn poll_dial(&mut self, cx: &mut Context<'_>) -> Poll<DialOpts> {
+        while let Some(peer_id) = self.pending_dials.pop_front() {
+            if self.pending_echoes.contains_key(&peer_id) {
+                return Poll::Ready(DialOpts::new(None, Some(peer_id)));
+            }
+        }
+        self.waker = Some(cx.waker().clone());
+        Poll::Pending
+    }
+}
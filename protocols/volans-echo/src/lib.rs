@@ -0,0 +1,92 @@
+//! 回显协议
+//!
+//! 客户端为每次回显打开一个 `/v1/echo` 子流，发送数据后关闭写端，服务端读到流结束后将
+//! 数据原样返回。客户端校验返回的数据并上报往返时延，可用于验证新的传输层或中继是否连通，
+//! 也是最简单的协议实现参考。[`client`] 通过 [`client::Event`] 上报结果，[`server`]
+//! 上报应答过的回显。
+
+pub mod client;
+mod protocol;
+pub mod server;
+
+pub use protocol::PROTOCOL_NAME;
+
+use std::{
+    fmt, io,
+    sync::atomic::{AtomicUsize, Ordering},
+    time::Duration,
+};
+
+static NEXT_ECHO_ID: AtomicUsize = AtomicUsize::new(0);
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct EchoId(usize);
+
+impl EchoId {
+    pub(crate) fn next() -> Self {
+        EchoId(NEXT_ECHO_ID.fetch_add(1, Ordering::SeqCst))
+    }
+}
+
+impl fmt::Display for EchoId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Config {
+    timeout: Duration,
+    max_concurrent_echoes: usize,
+    max_payload: usize,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            timeout: Duration::from_secs(20),
+            max_concurrent_echoes: 10,
+            max_payload: 64 * 1024,
+        }
+    }
+}
+
+impl Config {
+    /// 单次回显的超时时间，包括子流协商
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// 每个连接上同时进行的回显上限
+    pub fn with_max_concurrent_echoes(mut self, count: usize) -> Self {
+        self.max_concurrent_echoes = count.max(1);
+        self
+    }
+
+    /// 服务端单次回显的最大字节数，默认为 64 KiB
+    pub fn with_max_payload(mut self, size: usize) -> Self {
+        self.max_payload = size;
+        self
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum Failure {
+    #[error("Echo timeout")]
+    Timeout,
+    #[error("Echo protocol not supported")]
+    Unsupported,
+    #[error("Echo stream reset by remote")]
+    RemoteReset,
+    #[error("Echo payload of {size} bytes exceeds limit")]
+    TooLarge { size: usize },
+    #[error("Echoed payload does not match")]
+    Mismatch,
+    #[error("Failed to dial peer")]
+    Dial,
+    #[error("Connection closed during echo")]
+    ConnectionClosed,
+    #[error("Echo io error: {0}")]
+    Io(#[from] io::Error),
+}
@@ -0,0 +1,70 @@
+use std::time::{Duration, Instant};
+
+use futures::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use volans_swarm::StreamProtocol;
+
+use crate::Failure;
+
+pub const PROTOCOL_NAME: StreamProtocol = StreamProtocol::new("/v1/echo");
+
+/// 读取到流结束，超过 `limit` 字节时返回 [`Failure::TooLarge`]
+async fn read_to_end<S>(stream: &mut S, limit: usize) -> Result<Vec<u8>, Failure>
+where
+    S: AsyncRead + Unpin,
+{
+    let mut payload = Vec::new();
+    stream
+        .take(limit as u64 + 1)
+        .read_to_end(&mut payload)
+        .await?;
+    if payload.len() > limit {
+        return Err(Failure::TooLarge {
+            size: payload.len(),
+        });
+    }
+    Ok(payload)
+}
+
+/// 发送数据并关闭写端，校验服务端返回的数据，返回往返时延
+pub(crate) async fn send_echo<S>(mut stream: S, payload: Vec<u8>) -> Result<Duration, Failure>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let started = Instant::now();
+    stream.write_all(&payload).await?;
+    stream.close().await?;
+    let echoed = read_to_end(&mut stream, payload.len())
+        .await
+        .map_err(|error| match error {
+            Failure::TooLarge { .. } => Failure::Mismatch,
+            error => error,
+        })?;
+    if echoed != payload {
+        return Err(Failure::Mismatch);
+    }
+    Ok(started.elapsed())
+}
+
+/// 读到流结束后原样返回，超过 `max_payload` 字节时不应答，返回回显的字节数
+pub(crate) async fn recv_echo<S>(mut stream: S, max_payload: usize) -> Result<usize, Failure>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let payload = read_to_end(&mut stream, max_payload).await?;
+    stream.write_all(&payload).await?;
+    stream.close().await?;
+    Ok(payload.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::{executor::block_on, io::Cursor};
+
+    use super::*;
+
+    #[test]
+    fn reject_too_large() {
+        let result = block_on(recv_echo(Cursor::new(vec![0u8; 1024]), 512));
+        assert!(matches!(result, Err(Failure::TooLarge { size: 513 })));
+    }
+}
@@ -0,0 +1,161 @@
+use std::{
+    collections::VecDeque,
+    convert::Infallible,
+    task::{Context, Poll, Waker},
+};
+
+use futures::FutureExt;
+use futures_bounded::{Delay, FuturesSet};
+use volans_core::{Multiaddr, PeerId, upgrade::ReadyUpgrade};
+use volans_swarm::{
+    BehaviorEvent, ConnectionDenied, ConnectionHandler, ConnectionHandlerEvent, ConnectionId,
+    InboundStreamHandler, InboundUpgradeSend, KeepAlive, NetworkBehavior, NetworkIncomingBehavior,
+    StreamProtocol, SubstreamProtocol, THandlerAction, THandlerEvent,
+};
+
+use crate::{Config, Failure, protocol};
+
+/// 监听端应答回显
+pub struct Handler {
+    config: Config,
+    running: FuturesSet<Result<usize, Failure>>,
+}
+
+impl Handler {
+    pub fn new(config: Config) -> Self {
+        let timeout = config.timeout;
+        Self {
+            running: FuturesSet::new(
+                move || Delay::futures_timer(timeout),
+                config.max_concurrent_echoes,
+            ),
+            config,
+        }
+    }
+}
+
+impl ConnectionHandler for Handler {
+    type Action = Infallible;
+    type Event = Result<usize, Failure>;
+
+    fn handle_action(&mut self, action: Self::Action) {
+        match action {}
+    }
+
+    fn keep_alive(&self) -> KeepAlive {
+        KeepAlive::from(!self.running.is_empty())
+    }
+
+    fn poll(&mut self, cx: &mut Context<'_>) -> Poll<ConnectionHandlerEvent<Self::Event>> {
+        match self.running.poll_unpin(cx) {
+            Poll::Ready(Ok(result)) => Poll::Ready(ConnectionHandlerEvent::Notify(result)),
+            Poll::Ready(Err(_)) => {
+                Poll::Ready(ConnectionHandlerEvent::Notify(Err(Failure::Timeout)))
+            }
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+impl InboundStreamHandler for Handler {
+    type InboundUpgrade = ReadyUpgrade<StreamProtocol>;
+    type InboundUserData = ();
+
+    fn listen_protocol(&self) -> SubstreamProtocol<Self::InboundUpgrade, Self::InboundUserData> {
+        SubstreamProtocol::new(ReadyUpgrade::new(protocol::PROTOCOL_NAME), ())
+    }
+
+    fn on_fully_negotiated(
+        &mut self,
+        _user_data: Self::InboundUserData,
+        stream: <Self::InboundUpgrade as InboundUpgradeSend>::Output,
+    ) {
+        let echo = protocol::recv_echo(stream, self.config.max_payload).boxed();
+        if self.running.try_push(echo).is_err() {
+            tracing::debug!("Dropping echo stream: too many concurrent echoes");
+        }
+    }
+
+    fn on_upgrade_error(
+        &mut self,
+        _user_data: Self::InboundUserData,
+        error: <Self::InboundUpgrade as InboundUpgradeSend>::Error,
+    ) {
+        tracing::debug!("Echo inbound upgrade error: {}", error);
+    }
+}
+
+#[derive(Debug)]
+pub struct Event {
+    pub connection: ConnectionId,
+    pub peer_id: PeerId,
+    /// 回显的字节数
+    pub result: Result<usize, Failure>,
+}
+
+/// 应答回显的行为
+pub struct Behavior {
+    config: Config,
+    events: VecDeque<Event>,
+    waker: Option<Waker>,
+}
+
+impl Behavior {
+    pub fn new(config: Config) -> Self {
+        Self {
+            config,
+            events: VecDeque::new(),
+            waker: None,
+        }
+    }
+}
+
+impl Default for Behavior {
+    fn default() -> Self {
+        Self::new(Config::default())
+    }
+}
+
+impl NetworkBehavior for Behavior {
+    type ConnectionHandler = Handler;
+    type Event = Event;
+
+    fn on_connection_handler_event(
+        &mut self,
+        id: ConnectionId,
+        peer_id: PeerId,
+        event: THandlerEvent<Self>,
+    ) {
+        self.events.push_back(Event {
+            connection: id,
+            peer_id,
+            result: event,
+        });
+        if let Some(waker) = self.waker.take() {
+            waker.wake();
+        }
+    }
+
+    fn poll(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<BehaviorEvent<Self::Event, THandlerAction<Self>>> {
+        if let Some(event) = self.events.pop_front() {
+            return Poll::Ready(BehaviorEvent::Behavior(event));
+        }
+        self.waker = Some(cx.waker().clone());
+        Poll::Pending
+    }
+}
+
+impl NetworkIncomingBehavior for Behavior {
+    fn handle_established_connection(
+        &mut self,
+        _id: ConnectionId,
+        _peer_id: PeerId,
+        _local_addr: &Multiaddr,
+        _remote_addr: &Multiaddr,
+    ) -> Result<Self::ConnectionHandler, ConnectionDenied> {
+        Ok(Handler::new(self.config.clone()))
+    }
+}
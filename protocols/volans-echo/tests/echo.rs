@@ -0,0 +1,34 @@
+use futures::StreamExt;
+use volans_swarm::{client, server};
+use volans_swarm_test::{SwarmExt, listen, next_behavior_event};
+
+#[tokio::test(flavor = "current_thread")]
+async fn echo_payload() {
+    use volans_echo::Config;
+
+    let mut dialer = client::Swarm::new_ephemeral(|_| volans_echo::client::Behavior::default());
+    let mut listener = server::Swarm::new_ephemeral(|_| {
+        volans_echo::server::Behavior::new(Config::default().with_max_payload(1024))
+    });
+    let addr = listen(&mut listener).await;
+    let listener_peer = *listener.local_peer_id();
+    dialer.peer_store_mut().add_address(listener_peer, addr);
+    tokio::spawn(async move {
+        loop {
+            listener.next().await;
+        }
+    });
+
+    // 第一次回显触发拨号
+    let echo_id = dialer.behavior_mut().echo(listener_peer, b"hello".to_vec());
+    let event = next_behavior_event(&mut dialer).await;
+    assert_eq!(event.echo_id, echo_id);
+    assert_eq!(event.peer_id, listener_peer);
+    assert!(event.result.is_ok());
+
+    // 超过服务端上限时不应答
+    let echo_id = dialer.behavior_mut().echo(listener_peer, vec![7u8; 2048]);
+    let event = next_behavior_event(&mut dialer).await;
+    assert_eq!(event.echo_id, echo_id);
+    assert!(event.result.is_err());
+}
@@ -0,0 +1,193 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    task::{Context, Poll, Waker},
+};
+
+use volans_core::{Endpoint, Multiaddr, PeerId};
+use volans_swarm::{
+    BehaviorEvent, ConnectionDenied, ConnectionId, ListenAddresses, ListenerEvent, NetworkBehavior,
+    NetworkIncomingBehavior, NetworkOutgoingBehavior, THandlerAction, THandlerEvent,
+    behavior::NotifyHandler, error::CloseReason,
+};
+
+use crate::{Config, Event, Handler, HandlerEvent, Info};
+
+pub struct Behavior {
+    config: Config,
+    listen_addresses: ListenAddresses,
+    connections: HashMap<ConnectionId, PeerId>,
+    /// 最近一次收到的节点信息
+    infos: HashMap<PeerId, Info>,
+    pending_events: VecDeque<BehaviorEvent<Event, Vec<Multiaddr>>>,
+    waker: Option<Waker>,
+}
+
+impl Behavior {
+    pub fn new(config: Config) -> Self {
+        Self {
+            config,
+            listen_addresses: ListenAddresses::default(),
+            connections: HashMap::new(),
+            infos: HashMap::new(),
+            pending_events: VecDeque::new(),
+            waker: None,
+        }
+    }
+
+    /// 已连接节点最近一次宣告的信息
+    pub fn info(&self, peer_id: &PeerId) -> Option<&Info> {
+        self.infos.get(peer_id)
+    }
+
+    fn new_handler(&self, endpoint: Endpoint, remote_addr: &Multiaddr) -> Handler {
+        Handler::new(
+            endpoint,
+            &self.config,
+            self.listen_addresses.iter().cloned().collect(),
+            remote_addr.clone(),
+        )
+    }
+
+    fn on_connection_closed(&mut self, id: ConnectionId, peer_id: PeerId) {
+        self.connections.remove(&id);
+        if !self.connections.values().any(|p| *p == peer_id) {
+            self.infos.remove(&peer_id);
+        }
+    }
+
+    fn push_listen_addresses(&mut self) {
+        let addresses = self.listen_addresses.iter().cloned().collect::<Vec<_>>();
+        for (id, peer_id) in &self.connections {
+            self.pending_events.push_back(BehaviorEvent::HandlerAction {
+                peer_id: *peer_id,
+                handler: NotifyHandler::One(*id),
+                action: addresses.clone(),
+            });
+        }
+        if let Some(waker) = self.waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+impl NetworkBehavior for Behavior {
+    type ConnectionHandler = Handler;
+    type Event = Event;
+
+    fn on_connection_handler_event(
+        &mut self,
+        _id: ConnectionId,
+        peer_id: PeerId,
+        event: THandlerEvent<Self>,
+    ) {
+        let event = match event {
+            HandlerEvent::Identified(info) => {
+                // 对端宣告的监听地址交由 Swarm 的地址簿记录
+                for addr in &info.listen_addrs {
+                    self.pending_events
+                        .push_back(BehaviorEvent::NewPeerAddress {
+                            peer_id,
+                            addr: addr.clone(),
+                        });
+                }
+                // 对端观察到的本地地址作为外部地址候选
+                if let Some(addr) = &info.observed_addr {
+                    self.pending_events
+                        .push_back(BehaviorEvent::NewExternalAddrCandidate {
+                            peer_id,
+                            addr: addr.clone(),
+                        });
+                }
+                self.infos.insert(peer_id, info.clone());
+                Event::Received { peer_id, info }
+            }
+            HandlerEvent::Error(error) => Event::Error { peer_id, error },
+        };
+        self.pending_events
+            .push_back(BehaviorEvent::Behavior(event));
+    }
+
+    fn poll(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<BehaviorEvent<Self::Event, THandlerAction<Self>>> {
+        if let Some(event) = self.pending_events.pop_front() {
+            return Poll::Ready(event);
+        }
+        self.waker = Some(cx.waker().clone());
+        Poll::Pending
+    }
+}
+
+impl NetworkIncomingBehavior for Behavior {
+    fn handle_established_connection(
+        &mut self,
+        _id: ConnectionId,
+        _peer_id: PeerId,
+        _local_addr: &Multiaddr,
+        remote_addr: &Multiaddr,
+    ) -> Result<Self::ConnectionHandler, ConnectionDenied> {
+        Ok(self.new_handler(Endpoint::Listener, remote_addr))
+    }
+
+    fn on_connection_established(
+        &mut self,
+        id: ConnectionId,
+        peer_id: PeerId,
+        _local_addr: &Multiaddr,
+        _remote_addr: &Multiaddr,
+    ) {
+        self.connections.insert(id, peer_id);
+    }
+
+    fn on_connection_closed(
+        &mut self,
+        id: ConnectionId,
+        peer_id: PeerId,
+        _local_addr: &Multiaddr,
+        _remote_addr: &Multiaddr,
+        _reason: &CloseReason,
+    ) {
+        Behavior::on_connection_closed(self, id, peer_id);
+    }
+
+    fn on_listener_event(&mut self, event: ListenerEvent<'_>) {
+        if self.listen_addresses.on_listener_event(&event) {
+            self.push_listen_addresses();
+        }
+    }
+}
+
+impl NetworkOutgoingBehavior for Behavior {
+    fn handle_established_connection(
+        &mut self,
+        _id: ConnectionId,
+        _peer_id: PeerId,
+        addr: &Multiaddr,
+    ) -> Result<Self::ConnectionHandler, ConnectionDenied> {
+        Ok(self.new_handler(Endpoint::Dialer, addr))
+    }
+
+    fn handle_pending_connection(
+        &mut self,
+        _id: ConnectionId,
+        _maybe_peer: Option<PeerId>,
+        addr: &Option<Multiaddr>,
+    ) -> Result<Option<Multiaddr>, ConnectionDenied> {
+        Ok(addr.clone())
+    }
+
+    fn on_connection_established(&mut self, id: ConnectionId, peer_id: PeerId, _addr: &Multiaddr) {
+        self.connections.insert(id, peer_id);
+    }
+
+    fn on_connection_closed(
+        &mut self,
+        id: ConnectionId,
+        peer_id: PeerId,
+        _addr: &Multiaddr,
+        _reason: &CloseReason,
+    ) {
+        Behavior::on_connection_closed(self, id, peer_id);
+    }
+}
@@ -0,0 +1,188 @@
+use std::{
+    io,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use futures::FutureExt;
+use futures_bounded::{Delay, FuturesSet};
+use futures_timer::Delay as Timer;
+use volans_core::{Endpoint, Multiaddr, upgrade::ReadyUpgrade};
+use volans_swarm::{
+    ConnectionHandler, ConnectionHandlerEvent, InboundStreamHandler, InboundUpgradeSend, KeepAlive,
+    OutboundStreamHandler, OutboundUpgradeSend, StreamProtocol, StreamUpgradeError,
+    SubstreamProtocol,
+};
+
+use crate::{Config, Info, protocol};
+
+#[derive(Debug)]
+pub enum HandlerEvent {
+    Identified(Info),
+    Error(io::Error),
+}
+
+/// 拨号端按间隔打开子流交换信息，监听端只响应
+pub struct Handler {
+    endpoint: Endpoint,
+    local: Info,
+    interval: Duration,
+    max_packet_size: usize,
+    trigger: Timer,
+    outbound_requested: bool,
+    /// 对端不支持协议后不再发起
+    disabled: bool,
+    exchanges: FuturesSet<io::Result<Info>>,
+    pending_error: Option<io::Error>,
+}
+
+impl Handler {
+    pub(crate) fn new(
+        endpoint: Endpoint,
+        config: &Config,
+        listen_addrs: Vec<Multiaddr>,
+        remote_addr: Multiaddr,
+    ) -> Self {
+        let timeout = config.timeout;
+        Self {
+            endpoint,
+            local: Info {
+                protocol_version: config.protocol_version.clone(),
+                agent_version: config.agent_version.clone(),
+                listen_addrs,
+                protocols: config.protocols.clone(),
+                observed_addr: Some(remote_addr),
+            },
+            interval: config.interval,
+            max_packet_size: config.max_packet_size,
+            trigger: Timer::new(Duration::ZERO),
+            outbound_requested: false,
+            disabled: false,
+            exchanges: FuturesSet::new(move || Delay::futures_timer(timeout), 2),
+            pending_error: None,
+        }
+    }
+
+    fn push_exchange(&mut self, endpoint: Endpoint, stream: volans_swarm::Substream) {
+        let local = self.local.clone();
+        let future = match endpoint {
+            Endpoint::Dialer => {
+                protocol::exchange_outbound(stream, local, self.max_packet_size).boxed()
+            }
+            Endpoint::Listener => {
+                protocol::exchange_inbound(stream, local, self.max_packet_size).boxed()
+            }
+        };
+        if self.exchanges.try_push(future).is_err() {
+            tracing::debug!("Dropping identify stream: too many pending exchanges");
+        }
+    }
+}
+
+impl ConnectionHandler for Handler {
+    /// 本地监听地址变化后更新的地址列表
+    type Action = Vec<Multiaddr>;
+    type Event = HandlerEvent;
+
+    fn handle_action(&mut self, action: Self::Action) {
+        self.local.listen_addrs = action;
+    }
+
+    fn keep_alive(&self) -> KeepAlive {
+        KeepAlive::from(!self.exchanges.is_empty())
+    }
+
+    fn poll(&mut self, cx: &mut Context<'_>) -> Poll<ConnectionHandlerEvent<Self::Event>> {
+        if let Some(error) = self.pending_error.take() {
+            return Poll::Ready(ConnectionHandlerEvent::Notify(HandlerEvent::Error(error)));
+        }
+        let event = match self.exchanges.poll_unpin(cx) {
+            Poll::Ready(Ok(Ok(info))) => HandlerEvent::Identified(info),
+            Poll::Ready(Ok(Err(error))) => HandlerEvent::Error(error),
+            Poll::Ready(Err(_)) => HandlerEvent::Error(io::ErrorKind::TimedOut.into()),
+            Poll::Pending => return Poll::Pending,
+        };
+        if self.endpoint == Endpoint::Dialer {
+            self.outbound_requested = false;
+            self.trigger.reset(self.interval);
+        }
+        Poll::Ready(ConnectionHandlerEvent::Notify(event))
+    }
+}
+
+impl InboundStreamHandler for Handler {
+    type InboundUpgrade = ReadyUpgrade<StreamProtocol>;
+    type InboundUserData = ();
+
+    fn listen_protocol(&self) -> SubstreamProtocol<Self::InboundUpgrade, Self::InboundUserData> {
+        SubstreamProtocol::new(ReadyUpgrade::new(protocol::PROTOCOL_NAME), ())
+    }
+
+    fn on_fully_negotiated(
+        &mut self,
+        _user_data: Self::InboundUserData,
+        protocol: <Self::InboundUpgrade as InboundUpgradeSend>::Output,
+    ) {
+        self.push_exchange(Endpoint::Listener, protocol);
+    }
+
+    fn on_upgrade_error(
+        &mut self,
+        _user_data: Self::InboundUserData,
+        error: <Self::InboundUpgrade as InboundUpgradeSend>::Error,
+    ) {
+        tracing::debug!("Identify inbound upgrade error: {}", error);
+    }
+}
+
+impl OutboundStreamHandler for Handler {
+    type OutboundUpgrade = ReadyUpgrade<StreamProtocol>;
+    type OutboundUserData = ();
+
+    fn on_fully_negotiated(
+        &mut self,
+        _user_data: Self::OutboundUserData,
+        protocol: <Self::OutboundUpgrade as OutboundUpgradeSend>::Output,
+    ) {
+        self.push_exchange(Endpoint::Dialer, protocol);
+    }
+
+    fn on_upgrade_error(
+        &mut self,
+        _user_data: Self::OutboundUserData,
+        error: StreamUpgradeError<<Self::OutboundUpgrade as OutboundUpgradeSend>::Error>,
+    ) {
+        self.outbound_requested = false;
+        self.trigger.reset(self.interval);
+        self.pending_error = Some(match error {
+            StreamUpgradeError::Timeout => io::ErrorKind::TimedOut.into(),
+            StreamUpgradeError::NegotiationFailed { .. } => {
+                self.disabled = true;
+                io::Error::new(
+                    io::ErrorKind::Unsupported,
+                    "identify protocol not supported",
+                )
+            }
+            StreamUpgradeError::RemoteReset => io::ErrorKind::ConnectionReset.into(),
+            StreamUpgradeError::Io(error) => error,
+            StreamUpgradeError::Apply(error) => match error {},
+        });
+    }
+
+    fn poll_outbound_request(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<SubstreamProtocol<Self::OutboundUpgrade, Self::OutboundUserData>> {
+        if self.endpoint != Endpoint::Dialer || self.disabled || self.outbound_requested {
+            return Poll::Pending;
+        }
+        if self.trigger.poll_unpin(cx).is_ready() {
+            self.outbound_requested = true;
+            return Poll::Ready(SubstreamProtocol::new(
+                ReadyUpgrade::new(protocol::PROTOCOL_NAME),
+                (),
+            ));
+        }
+        Poll::Pending
+    }
+}
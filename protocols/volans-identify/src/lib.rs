@@ -0,0 +1,87 @@
+//! 节点信息交换协议
+//!
+//! 连接建立后由拨号端打开子流，双方在同一条子流上交换协议版本、代理版本、
+//! 支持的协议与监听地址，之后按 [`Config::with_interval`] 周期性地重新交换。
+
+mod behavior;
+mod handler;
+mod protocol;
+
+pub use behavior::Behavior;
+pub use handler::{Handler, HandlerEvent};
+
+use std::time::Duration;
+
+use volans_core::{Multiaddr, PeerId};
+use volans_swarm::StreamProtocol;
+
+#[derive(Debug, Clone)]
+pub struct Config {
+    protocol_version: String,
+    agent_version: String,
+    protocols: Vec<StreamProtocol>,
+    interval: Duration,
+    timeout: Duration,
+    max_packet_size: usize,
+}
+
+impl Config {
+    pub fn new(protocol_version: impl Into<String>) -> Self {
+        Self {
+            protocol_version: protocol_version.into(),
+            agent_version: concat!("volans/", env!("CARGO_PKG_VERSION")).to_string(),
+            protocols: Vec::new(),
+            interval: Duration::from_secs(5 * 60),
+            timeout: Duration::from_secs(10),
+            max_packet_size: 4096,
+        }
+    }
+
+    pub fn with_agent_version(mut self, agent_version: impl Into<String>) -> Self {
+        self.agent_version = agent_version.into();
+        self
+    }
+
+    /// 对外宣告的本地支持的协议
+    pub fn with_protocols(mut self, protocols: impl IntoIterator<Item = StreamProtocol>) -> Self {
+        self.protocols = protocols.into_iter().collect();
+        self
+    }
+
+    /// 重新交换信息的间隔
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn with_max_packet_size(mut self, size: usize) -> Self {
+        self.max_packet_size = size;
+        self
+    }
+}
+
+/// 节点信息
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Info {
+    pub protocol_version: String,
+    pub agent_version: String,
+    pub listen_addrs: Vec<Multiaddr>,
+    pub protocols: Vec<StreamProtocol>,
+    /// 对端观察到的本地地址
+    pub observed_addr: Option<Multiaddr>,
+}
+
+#[derive(Debug)]
+pub enum Event {
+    /// 收到对端的节点信息
+    Received { peer_id: PeerId, info: Info },
+    Error {
+        peer_id: PeerId,
+        error: std::io::Error,
+    },
+}
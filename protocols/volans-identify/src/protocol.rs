@@ -0,0 +1,101 @@
+use std::io;
+
+use futures::{SinkExt, StreamExt};
+use volans_codec::{Framed, ProtobufUviCodec};
+use volans_swarm::{StreamProtocol, Substream};
+
+use crate::Info;
+
+pub mod v1 {
+    include!(concat!(env!("OUT_DIR"), "/volans.identify.v1.rs"));
+}
+
+pub(crate) const PROTOCOL_NAME: StreamProtocol = StreamProtocol::new("/v1/identify");
+
+/// 拨号端先发送本地信息，再读取对端信息
+pub(crate) async fn exchange_outbound(
+    stream: Substream,
+    local: Info,
+    max_packet_size: usize,
+) -> io::Result<Info> {
+    let mut stream = Framed::new(stream, ProtobufUviCodec::new(max_packet_size));
+    stream.send(encode_info(local)).await?;
+    let remote = recv(&mut stream).await?;
+    stream.close().await?;
+    Ok(remote)
+}
+
+/// 监听端先读取对端信息，再发送本地信息
+pub(crate) async fn exchange_inbound(
+    stream: Substream,
+    local: Info,
+    max_packet_size: usize,
+) -> io::Result<Info> {
+    let mut stream = Framed::new(stream, ProtobufUviCodec::new(max_packet_size));
+    let remote = recv(&mut stream).await?;
+    stream.send(encode_info(local)).await?;
+    stream.close().await?;
+    Ok(remote)
+}
+
+async fn recv(stream: &mut Framed<Substream, ProtobufUviCodec<v1::Identify>>) -> io::Result<Info> {
+    let message = stream
+        .next()
+        .await
+        .ok_or_else(|| io::Error::from(io::ErrorKind::UnexpectedEof))??;
+    Ok(decode_info(message))
+}
+
+fn encode_info(info: Info) -> v1::Identify {
+    v1::Identify {
+        protocol_version: info.protocol_version,
+        agent_version: info.agent_version,
+        listen_addrs: info.listen_addrs.iter().map(|a| a.to_string()).collect(),
+        observed_addr: info
+            .observed_addr
+            .map(|a| a.to_string())
+            .unwrap_or_default(),
+        protocols: info.protocols.iter().map(|p| p.to_string()).collect(),
+    }
+}
+
+/// 忽略无法解析的地址与协议
+fn decode_info(message: v1::Identify) -> Info {
+    Info {
+        protocol_version: message.protocol_version,
+        agent_version: message.agent_version,
+        listen_addrs: message
+            .listen_addrs
+            .iter()
+            .filter_map(|a| a.parse().ok())
+            .collect(),
+        protocols: message
+            .protocols
+            .into_iter()
+            .filter_map(|p| StreamProtocol::try_from_owned(p).ok())
+            .collect(),
+        observed_addr: Some(message.observed_addr)
+            .filter(|a| !a.is_empty())
+            .and_then(|a| a.parse().ok()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn info_roundtrip() {
+        let info = Info {
+            protocol_version: "/volans/1.0.0".to_string(),
+            agent_version: "test".to_string(),
+            listen_addrs: vec!["/ip4/127.0.0.1/tcp/8080".parse().unwrap()],
+            protocols: vec![PROTOCOL_NAME],
+            observed_addr: Some("/ip4/10.0.0.1/tcp/9000".parse().unwrap()),
+        };
+        assert_eq!(decode_info(encode_info(info.clone())), info);
+
+        let empty = decode_info(v1::Identify::default());
+        assert!(empty.observed_addr.is_none());
+    }
+}
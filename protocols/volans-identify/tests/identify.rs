@@ -0,0 +1,69 @@
+use futures::StreamExt;
+use volans_core::{ConnectedPoint, identity::KeyPair};
+use volans_swarm::{DialOpts, client, server};
+use volans_swarm_test::{SwarmExt, connect, next_behavior_event, wait_for_event};
+
+fn identify(_: &KeyPair) -> volans_identify::Behavior {
+    volans_identify::Behavior::new(volans_identify::Config::new("/test/1.0.0"))
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn connect_and_identify() {
+    let mut dialer = client::Swarm::new_ephemeral(identify);
+    let mut listener = server::Swarm::new_ephemeral(identify);
+    connect(&mut dialer, &mut listener).await;
+
+    let listener_peer = *listener.local_peer_id();
+    tokio::spawn(async move {
+        loop {
+            listener.next().await;
+        }
+    });
+    match next_behavior_event(&mut dialer).await {
+        volans_identify::Event::Received { peer_id, info } => {
+            assert_eq!(peer_id, listener_peer);
+            assert_eq!(info.protocol_version, "/test/1.0.0");
+        }
+        event => panic!("unexpected event: {event:?}"),
+    }
+
+    let bandwidth = dialer.bandwidth();
+    assert!(bandwidth.total().inbound > 0);
+    assert_eq!(bandwidth.by_protocol()["/ip4/tcp"], bandwidth.total());
+    let identify = bandwidth.by_stream_protocol()["/v1/identify"];
+    assert!(identify.inbound > 0);
+    assert!(identify.inbound <= bandwidth.total().inbound);
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn dial_known_peer() {
+    let mut dialer = client::Swarm::new_ephemeral(identify);
+    let mut listener = server::Swarm::new_ephemeral(identify);
+    connect(&mut dialer, &mut listener).await;
+
+    let listener_peer = *listener.local_peer_id();
+    tokio::spawn(async move {
+        loop {
+            listener.next().await;
+        }
+    });
+    let volans_identify::Event::Received { info, .. } = next_behavior_event(&mut dialer).await
+    else {
+        panic!("expected identify info");
+    };
+    let known: Vec<_> = dialer.peer_store().addresses(&listener_peer).collect();
+    assert_eq!(known, info.listen_addrs.iter().collect::<Vec<_>>());
+
+    let addr = dialer.dial(DialOpts::peer(listener_peer)).unwrap();
+    assert_eq!(addr, info.listen_addrs[0]);
+    let established = wait_for_event(&mut dialer, |event| match event {
+        client::SwarmEvent::ConnectionEstablished {
+            peer_id,
+            endpoint: ConnectedPoint::Dialer { addr },
+            ..
+        } => Some((peer_id, addr)),
+        _ => None,
+    })
+    .await;
+    assert_eq!(established, (listener_peer, addr));
+}
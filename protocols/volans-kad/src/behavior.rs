@@ -0,0 +1,692 @@
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    task::{Context, Poll, Waker},
+    time::{Duration, Instant},
+};
+
+use futures::FutureExt;
+use futures_timer::Delay;
+use volans_core::{Multiaddr, PeerId};
+use volans_swarm::{
+    BehaviorEvent, ConnectionDenied, ConnectionId, DialOpts, ListenAddresses, ListenerEvent,
+    NetworkBehavior, NetworkIncomingBehavior, NetworkOutgoingBehavior, PeerCondition,
+    THandlerAction, THandlerEvent,
+    behavior::NotifyHandler,
+    error::{CloseReason, DialError},
+};
+
+use crate::{
+    Config, Event, Handler, HandlerEvent, HandlerIn, InboundRequestId, NoKnownPeers, QueryError,
+    QueryId, QueryResult,
+    kbucket::{Entry, InsertResult, KBucketsTable, Key},
+    protocol::{self, v1},
+    query::{ClosestPeersIter, PeersIterState},
+    record::{MemoryStore, ProviderRecord, Record, RecordKey, StoreError},
+};
+
+/// 查询超时与记录过期的检查间隔
+const CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+enum QueryInfo {
+    Bootstrap,
+    GetClosestPeers,
+    GetRecord { record: Option<Record> },
+    GetProviders { providers: HashSet<PeerId> },
+    PutRecord { record: Record },
+    AddProvider { key: RecordKey },
+}
+
+enum QueryPhase {
+    /// 迭代查找最近节点
+    Iterating(ClosestPeersIter),
+    /// 将记录写入找到的最近节点
+    Storing {
+        pending: HashSet<PeerId>,
+        succeeded: usize,
+    },
+}
+
+struct Query {
+    target: Vec<u8>,
+    info: QueryInfo,
+    phase: QueryPhase,
+    started: Instant,
+}
+
+impl Query {
+    fn iterate_message(&self) -> v1::Message {
+        let ty = match self.info {
+            QueryInfo::GetRecord { .. } => v1::MessageType::GetValue,
+            QueryInfo::GetProviders { .. } => v1::MessageType::GetProviders,
+            _ => v1::MessageType::FindNode,
+        };
+        protocol::new_message(ty, self.target.clone())
+    }
+}
+
+pub struct Behavior {
+    local_peer_id: PeerId,
+    config: Config,
+    kbuckets: KBucketsTable,
+    store: MemoryStore,
+    queries: HashMap<QueryId, Query>,
+    next_query_id: u64,
+    /// 本地拨出的连接，只有这些连接可以打开出站子流
+    outbound_connections: HashMap<PeerId, Vec<ConnectionId>>,
+    /// 等待连接建立后发送的请求
+    pending_requests: HashMap<PeerId, Vec<(QueryId, v1::Message)>>,
+    pending_dials: VecDeque<DialOpts>,
+    listen_addresses: ListenAddresses,
+    check_timer: Delay,
+    pending_events: VecDeque<BehaviorEvent<Event, HandlerIn>>,
+    waker: Option<Waker>,
+}
+
+impl Behavior {
+    pub fn new(local_peer_id: PeerId, config: Config) -> Self {
+        Self {
+            local_peer_id,
+            kbuckets: KBucketsTable::new(local_peer_id, config.replication_factor),
+            config,
+            store: MemoryStore::default(),
+            queries: HashMap::new(),
+            next_query_id: 0,
+            outbound_connections: HashMap::new(),
+            pending_requests: HashMap::new(),
+            pending_dials: VecDeque::new(),
+            listen_addresses: ListenAddresses::default(),
+            check_timer: Delay::new(CHECK_INTERVAL),
+            pending_events: VecDeque::new(),
+            waker: None,
+        }
+    }
+
+    pub fn with_store(mut self, store: MemoryStore) -> Self {
+        self.store = store;
+        self
+    }
+
+    pub fn store_mut(&mut self) -> &mut MemoryStore {
+        &mut self.store
+    }
+
+    /// 将节点地址加入路由表，对应的 K 桶已满时返回 `false`
+    pub fn add_address(&mut self, peer_id: PeerId, addr: Multiaddr) -> bool {
+        self.kbuckets.insert(peer_id, Some(addr)) != InsertResult::Full
+    }
+
+    pub fn remove_peer(&mut self, peer_id: &PeerId) -> Option<Entry> {
+        self.kbuckets.remove(peer_id)
+    }
+
+    /// 路由表中的节点
+    pub fn peers(&self) -> impl Iterator<Item = &Entry> {
+        self.kbuckets.iter()
+    }
+
+    /// 查找离本地节点最近的节点以填充路由表
+    pub fn bootstrap(&mut self) -> Result<QueryId, NoKnownPeers> {
+        if self.kbuckets.iter().next().is_none() {
+            return Err(NoKnownPeers);
+        }
+        let target = self.local_peer_id.as_bytes().to_vec();
+        Ok(self.start_query(target, QueryInfo::Bootstrap))
+    }
+
+    pub fn get_closest_peers(&mut self, peer_id: PeerId) -> QueryId {
+        self.start_query(peer_id.as_bytes().to_vec(), QueryInfo::GetClosestPeers)
+    }
+
+    /// 查找记录，本地存在时直接返回
+    pub fn get_record(&mut self, key: RecordKey) -> QueryId {
+        if let Some(record) = self.store.get(&key).cloned() {
+            let id = self.next_query_id();
+            self.emit(Event::QueryResult {
+                id,
+                result: QueryResult::GetRecord(Ok(record)),
+            });
+            return id;
+        }
+        self.start_query(key.into_bytes(), QueryInfo::GetRecord { record: None })
+    }
+
+    /// 在本地存储记录并复制到离键最近的节点
+    pub fn put_record(&mut self, mut record: Record) -> Result<QueryId, StoreError> {
+        record.publisher = Some(self.local_peer_id);
+        if record.expires.is_none() {
+            record.expires = self.config.record_ttl.map(|ttl| Instant::now() + ttl);
+        }
+        self.store.put(record.clone())?;
+        let target = record.key.as_bytes().to_vec();
+        Ok(self.start_query(target, QueryInfo::PutRecord { record }))
+    }
+
+    /// 宣告本地节点为键的提供者
+    pub fn start_providing(&mut self, key: RecordKey) -> Result<QueryId, StoreError> {
+        self.store.add_provider(ProviderRecord {
+            key: key.clone(),
+            provider: self.local_peer_id,
+            addresses: self.listen_addresses.iter().cloned().collect(),
+            expires: self.config.provider_ttl.map(|ttl| Instant::now() + ttl),
+        })?;
+        let target = key.as_bytes().to_vec();
+        Ok(self.start_query(target, QueryInfo::AddProvider { key }))
+    }
+
+    pub fn stop_providing(&mut self, key: &RecordKey) {
+        self.store.remove_provider(key, &self.local_peer_id);
+    }
+
+    pub fn get_providers(&mut self, key: RecordKey) -> QueryId {
+        let providers = self
+            .store
+            .providers(&key)
+            .into_iter()
+            .map(|p| p.provider)
+            .collect();
+        self.start_query(key.into_bytes(), QueryInfo::GetProviders { providers })
+    }
+
+    fn next_query_id(&mut self) -> QueryId {
+        let id = QueryId(self.next_query_id);
+        self.next_query_id += 1;
+        id
+    }
+
+    fn start_query(&mut self, target: Vec<u8>, info: QueryInfo) -> QueryId {
+        let id = self.next_query_id();
+        let key = Key::new(&target);
+        let known = self
+            .kbuckets
+            .closest(&key, self.config.replication_factor)
+            .into_iter()
+            .map(|e| e.peer_id)
+            .collect::<Vec<_>>();
+        let iter = ClosestPeersIter::new(
+            key,
+            known,
+            self.config.parallelism,
+            self.config.replication_factor,
+        );
+        self.queries.insert(
+            id,
+            Query {
+                target,
+                info,
+                phase: QueryPhase::Iterating(iter),
+                started: Instant::now(),
+            },
+        );
+        self.wake();
+        id
+    }
+
+    fn store_message(&self, query: &Query) -> Option<v1::Message> {
+        match &query.info {
+            QueryInfo::PutRecord { record } => {
+                let mut message =
+                    protocol::new_message(v1::MessageType::PutValue, query.target.clone());
+                message.record = Some(protocol::encode_record(record));
+                Some(message)
+            }
+            QueryInfo::AddProvider { .. } => {
+                let mut message =
+                    protocol::new_message(v1::MessageType::AddProvider, query.target.clone());
+                let addresses = self.listen_addresses.iter().cloned().collect::<Vec<_>>();
+                message.provider_peers =
+                    vec![protocol::encode_peer(&self.local_peer_id, &addresses)];
+                Some(message)
+            }
+            _ => None,
+        }
+    }
+
+    fn advance_query(&mut self, id: QueryId) {
+        let Some(mut query) = self.queries.remove(&id) else {
+            return;
+        };
+        let mut requests = Vec::new();
+        let finished = loop {
+            match &mut query.phase {
+                QueryPhase::Iterating(iter) => match iter.next() {
+                    PeersIterState::Waiting(Some(peer_id)) => {
+                        requests.push((peer_id, query.iterate_message()));
+                    }
+                    PeersIterState::Waiting(None) => break false,
+                    PeersIterState::Finished => {
+                        let Some(message) = self.store_message(&query) else {
+                            break true;
+                        };
+                        let QueryPhase::Iterating(iter) = &query.phase else {
+                            unreachable!("query is iterating");
+                        };
+                        let pending = iter.result().into_iter().collect::<HashSet<_>>();
+                        for peer_id in &pending {
+                            requests.push((*peer_id, message.clone()));
+                        }
+                        query.phase = QueryPhase::Storing {
+                            pending,
+                            succeeded: 0,
+                        };
+                    }
+                },
+                QueryPhase::Storing { pending, .. } => break pending.is_empty(),
+            }
+        };
+        if finished {
+            self.finish_query(id, query, false);
+        } else {
+            self.queries.insert(id, query);
+        }
+        for (peer_id, message) in requests {
+            self.send_request(peer_id, id, message);
+        }
+    }
+
+    fn finish_query(&mut self, id: QueryId, query: Query, timed_out: bool) {
+        let closest = match &query.phase {
+            _ if timed_out => Err(QueryError::Timeout),
+            QueryPhase::Iterating(iter) => Ok(iter.result()),
+            QueryPhase::Storing { .. } => Ok(Vec::new()),
+        };
+        let stored = match query.phase {
+            QueryPhase::Storing { succeeded, .. } if succeeded > 0 => Ok(()),
+            _ if timed_out => Err(QueryError::Timeout),
+            _ => Err(QueryError::QuorumFailed),
+        };
+        let result = match query.info {
+            QueryInfo::Bootstrap => QueryResult::Bootstrap(closest),
+            QueryInfo::GetClosestPeers => QueryResult::GetClosestPeers(closest),
+            QueryInfo::GetRecord { record } => QueryResult::GetRecord(match record {
+                Some(record) => Ok(record),
+                None if timed_out => Err(QueryError::Timeout),
+                None => Err(QueryError::NotFound),
+            }),
+            QueryInfo::GetProviders { providers } => {
+                if timed_out && providers.is_empty() {
+                    QueryResult::GetProviders(Err(QueryError::Timeout))
+                } else {
+                    QueryResult::GetProviders(Ok(providers))
+                }
+            }
+            QueryInfo::PutRecord { record } => QueryResult::PutRecord(stored.map(|()| record.key)),
+            QueryInfo::AddProvider { key } => QueryResult::StartProviding(stored.map(|()| key)),
+        };
+        self.emit(Event::QueryResult { id, result });
+    }
+
+    /// 向节点发送请求，未连接时先拨号
+    fn send_request(&mut self, peer_id: PeerId, query_id: QueryId, message: v1::Message) {
+        if let Some(id) = self
+            .outbound_connections
+            .get(&peer_id)
+            .and_then(|c| c.first().copied())
+        {
+            self.pending_events.push_back(BehaviorEvent::HandlerAction {
+                peer_id,
+                handler: NotifyHandler::One(id),
+                action: HandlerIn::Request { query_id, message },
+            });
+            self.wake();
+            return;
+        }
+        let Some(addr) = self
+            .kbuckets
+            .get(&peer_id)
+            .and_then(|e| e.addresses.first().cloned())
+        else {
+            tracing::debug!("No known address for peer {}", peer_id);
+            self.on_request_failure(peer_id, query_id);
+            return;
+        };
+        let pending = self.pending_requests.entry(peer_id).or_default();
+        if pending.is_empty() {
+            self.pending_dials.push_back(
+                DialOpts::new(Some(addr), Some(peer_id)).with_condition(PeerCondition::NotDialing),
+            );
+        }
+        pending.push((query_id, message));
+        self.wake();
+    }
+
+    fn on_request_failure(&mut self, peer_id: PeerId, query_id: QueryId) {
+        let Some(query) = self.queries.get_mut(&query_id) else {
+            return;
+        };
+        match &mut query.phase {
+            QueryPhase::Iterating(iter) => iter.on_failure(&peer_id),
+            QueryPhase::Storing { pending, .. } => {
+                pending.remove(&peer_id);
+            }
+        }
+        self.wake();
+    }
+
+    fn on_request_success(&mut self, peer_id: PeerId, query_id: QueryId, message: v1::Message) {
+        let mut closer_peers = Vec::new();
+        for peer in &message.closer_peers {
+            let Some((closer, addresses)) = protocol::decode_peer(peer) else {
+                continue;
+            };
+            if closer == self.local_peer_id {
+                continue;
+            }
+            self.insert_discovered(closer, addresses);
+            closer_peers.push(closer);
+        }
+        let mut providers = Vec::new();
+        for peer in &message.provider_peers {
+            if let Some((provider, addresses)) = protocol::decode_peer(peer) {
+                if provider != self.local_peer_id {
+                    self.insert_discovered(provider, addresses);
+                }
+                providers.push(provider);
+            }
+        }
+
+        let Some(query) = self.queries.get_mut(&query_id) else {
+            return;
+        };
+        match &mut query.phase {
+            QueryPhase::Iterating(iter) => {
+                match &mut query.info {
+                    QueryInfo::GetRecord { record } => {
+                        if let Some(found) = message.record.map(protocol::decode_record)
+                            && found.key.as_bytes() == query.target
+                            && !found.is_expired(Instant::now())
+                        {
+                            *record = Some(found);
+                            iter.finish();
+                        }
+                    }
+                    QueryInfo::GetProviders { providers: found } => found.extend(providers),
+                    _ => {}
+                }
+                iter.on_success(&peer_id, closer_peers);
+            }
+            QueryPhase::Storing { pending, succeeded } => {
+                if pending.remove(&peer_id) {
+                    *succeeded += 1;
+                }
+            }
+        }
+        self.wake();
+    }
+
+    fn insert_discovered(&mut self, peer_id: PeerId, addresses: Vec<Multiaddr>) {
+        let mut inserted = false;
+        for addr in addresses {
+            inserted |= self.kbuckets.insert(peer_id, Some(addr)) == InsertResult::Inserted;
+        }
+        if inserted && let Some(entry) = self.kbuckets.get(&peer_id) {
+            let addresses = entry.addresses.clone();
+            self.emit(Event::RoutingUpdated { peer_id, addresses });
+        }
+    }
+
+    fn closer_peers(&self, key: &[u8], exclude: &PeerId) -> Vec<v1::Peer> {
+        self.kbuckets
+            .closest(&Key::new(key), self.config.replication_factor)
+            .into_iter()
+            .filter(|e| e.peer_id != *exclude)
+            .map(|e| protocol::encode_peer(&e.peer_id, &e.addresses))
+            .collect()
+    }
+
+    fn handle_inbound_request(
+        &mut self,
+        id: ConnectionId,
+        peer_id: PeerId,
+        request_id: InboundRequestId,
+        request: v1::Message,
+    ) {
+        let ty = v1::MessageType::try_from(request.r#type).unwrap_or(v1::MessageType::Ping);
+        let mut response = protocol::new_message(ty, request.key.clone());
+        let key = RecordKey::from(request.key);
+        match ty {
+            v1::MessageType::Ping => {}
+            v1::MessageType::FindNode => {
+                response.closer_peers = self.closer_peers(key.as_bytes(), &peer_id);
+            }
+            v1::MessageType::GetValue => {
+                response.record = self.store.get(&key).map(protocol::encode_record);
+                response.closer_peers = self.closer_peers(key.as_bytes(), &peer_id);
+            }
+            v1::MessageType::PutValue => {
+                if let Some(record) = request.record {
+                    let decoded = protocol::decode_record(record.clone());
+                    if decoded.key != key {
+                        tracing::debug!("Record key mismatch from peer {}", peer_id);
+                    } else if let Err(error) = self.store.put(decoded) {
+                        tracing::debug!("Failed to store record from peer {}: {}", peer_id, error);
+                    } else {
+                        response.record = Some(record);
+                    }
+                }
+            }
+            v1::MessageType::GetProviders => {
+                response.provider_peers = self
+                    .store
+                    .providers(&key)
+                    .iter()
+                    .map(|p| protocol::encode_peer(&p.provider, &p.addresses))
+                    .collect();
+                response.closer_peers = self.closer_peers(key.as_bytes(), &peer_id);
+            }
+            v1::MessageType::AddProvider => {
+                // 只接受节点宣告自身
+                for peer in &request.provider_peers {
+                    let Some((provider, addresses)) = protocol::decode_peer(peer) else {
+                        continue;
+                    };
+                    if provider != peer_id {
+                        continue;
+                    }
+                    let record = ProviderRecord {
+                        key: key.clone(),
+                        provider,
+                        addresses,
+                        expires: self.config.provider_ttl.map(|ttl| Instant::now() + ttl),
+                    };
+                    if let Err(error) = self.store.add_provider(record) {
+                        tracing::debug!("Failed to add provider {}: {}", peer_id, error);
+                    }
+                }
+            }
+        }
+        self.pending_events.push_back(BehaviorEvent::HandlerAction {
+            peer_id,
+            handler: NotifyHandler::One(id),
+            action: HandlerIn::Response {
+                request_id,
+                message: response,
+            },
+        });
+        self.wake();
+    }
+
+    fn on_connection_established(&mut self, id: ConnectionId, peer_id: PeerId) {
+        self.outbound_connections
+            .entry(peer_id)
+            .or_default()
+            .push(id);
+        for (query_id, message) in self.pending_requests.remove(&peer_id).unwrap_or_default() {
+            self.pending_events.push_back(BehaviorEvent::HandlerAction {
+                peer_id,
+                handler: NotifyHandler::One(id),
+                action: HandlerIn::Request { query_id, message },
+            });
+        }
+        self.wake();
+    }
+
+    fn on_connection_closed(&mut self, id: ConnectionId, peer_id: PeerId) {
+        if let Some(connections) = self.outbound_connections.get_mut(&peer_id) {
+            connections.retain(|c| *c != id);
+            if connections.is_empty() {
+                self.outbound_connections.remove(&peer_id);
+            }
+        }
+    }
+
+    fn emit(&mut self, event: Event) {
+        self.pending_events
+            .push_back(BehaviorEvent::Behavior(event));
+        self.wake();
+    }
+
+    fn wake(&mut self) {
+        if let Some(waker) = self.waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+impl NetworkBehavior for Behavior {
+    type ConnectionHandler = Handler;
+    type Event = Event;
+
+    fn on_connection_handler_event(
+        &mut self,
+        id: ConnectionId,
+        peer_id: PeerId,
+        event: THandlerEvent<Self>,
+    ) {
+        match event {
+            HandlerEvent::InboundRequest {
+                request_id,
+                message,
+            } => self.handle_inbound_request(id, peer_id, request_id, message),
+            HandlerEvent::OutboundResponse { query_id, message } => {
+                self.on_request_success(peer_id, query_id, message);
+            }
+            HandlerEvent::OutboundFailure { query_id, error } => {
+                tracing::debug!("Kad request to {} failed: {}", peer_id, error);
+                self.on_request_failure(peer_id, query_id);
+            }
+        }
+    }
+
+    fn poll(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<BehaviorEvent<Self::Event, THandlerAction<Self>>> {
+        if self.check_timer.poll_unpin(cx).is_ready() {
+            self.check_timer.reset(CHECK_INTERVAL);
+            self.store.remove_expired();
+            let expired = self
+                .queries
+                .iter()
+                .filter(|(_, q)| q.started.elapsed() >= self.config.query_timeout)
+                .map(|(id, _)| *id)
+                .collect::<Vec<_>>();
+            for id in expired {
+                if let Some(query) = self.queries.remove(&id) {
+                    self.finish_query(id, query, true);
+                }
+            }
+        }
+        let ids = self.queries.keys().copied().collect::<Vec<_>>();
+        for id in ids {
+            self.advance_query(id);
+        }
+        if let Some(event) = self.pending_events.pop_front() {
+            return Poll::Ready(event);
+        }
+        self.waker = Some(cx.waker().clone());
+        Poll::Pending
+    }
+}
+
+impl NetworkIncomingBehavior for Behavior {
+    fn handle_established_connection(
+        &mut self,
+        _id: ConnectionId,
+        _peer_id: PeerId,
+        _local_addr: &Multiaddr,
+        _remote_addr: &Multiaddr,
+    ) -> Result<Self::ConnectionHandler, ConnectionDenied> {
+        Ok(Handler::new(
+            self.config.request_timeout,
+            self.config.max_packet_size,
+        ))
+    }
+
+    fn on_listener_event(&mut self, event: ListenerEvent<'_>) {
+        self.listen_addresses.on_listener_event(&event);
+    }
+}
+
+impl NetworkOutgoingBehavior for Behavior {
+    fn handle_established_connection(
+        &mut self,
+        _id: ConnectionId,
+        _peer_id: PeerId,
+        _addr: &Multiaddr,
+    ) -> Result<Self::ConnectionHandler, ConnectionDenied> {
+        Ok(Handler::new(
+            self.config.request_timeout,
+            self.config.max_packet_size,
+        ))
+    }
+
+    /// 未指定地址时使用路由表中的地址
+    fn handle_pending_connection(
+        &mut self,
+        _id: ConnectionId,
+        maybe_peer: Option<PeerId>,
+        addr: &Option<Multiaddr>,
+    ) -> Result<Option<Multiaddr>, ConnectionDenied> {
+        if addr.is_some() {
+            return Ok(addr.clone());
+        }
+        Ok(maybe_peer
+            .and_then(|peer_id| self.kbuckets.get(&peer_id))
+            .and_then(|e| e.addresses.first().cloned()))
+    }
+
+    fn on_connection_established(&mut self, id: ConnectionId, peer_id: PeerId, addr: &Multiaddr) {
+        self.insert_discovered(peer_id, vec![addr.clone()]);
+        Behavior::on_connection_established(self, id, peer_id);
+    }
+
+    fn on_connection_closed(
+        &mut self,
+        id: ConnectionId,
+        peer_id: PeerId,
+        _addr: &Multiaddr,
+        _reason: &CloseReason,
+    ) {
+        Behavior::on_connection_closed(self, id, peer_id);
+    }
+
+    fn on_dial_failure(
+        &mut self,
+        _id: ConnectionId,
+        peer_id: Option<PeerId>,
+        addr: Option<&Multiaddr>,
+        error: &DialError,
+    ) {
+        // 正在拨号时，请求随连接建立一起发送
+        if matches!(error, DialError::PeerCondition(_)) {
+            return;
+        }
+        let Some(peer_id) = peer_id else {
+            return;
+        };
+        if let Some(addr) = addr {
+            self.kbuckets.remove_address(&peer_id, addr);
+        }
+        for (query_id, _) in self.pending_requests.remove(&peer_id).unwrap_or_default() {
+            self.on_request_failure(peer_id, query_id);
+        }
+    }
+
+    fn poll_dial(&mut self, cx: &mut Context<'_>) -> Poll<DialOpts> {
+        if let Some(opts) = self.pending_dials.pop_front() {
+            return Poll::Ready(opts);
+        }
+        self.waker = Some(cx.waker().clone());
+        Poll::Pending
+    }
+}
@@ -0,0 +1,273 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    io,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use futures::FutureExt;
+use futures_bounded::{Delay, FuturesSet, FuturesTupleSet};
+use volans_core::upgrade::ReadyUpgrade;
+use volans_swarm::{
+    ConnectionHandler, ConnectionHandlerEvent, InboundStreamHandler, InboundUpgradeSend, KeepAlive,
+    OutboundStreamHandler, OutboundUpgradeSend, StreamProtocol, StreamUpgradeError,
+    SubstreamProtocol,
+};
+
+use crate::{
+    QueryId,
+    protocol::{self, RpcStream, v1},
+};
+
+/// 连接内入站请求的标识
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct InboundRequestId(u64);
+
+#[derive(Debug)]
+pub enum HandlerIn {
+    Request {
+        query_id: QueryId,
+        message: v1::Message,
+    },
+    Response {
+        request_id: InboundRequestId,
+        message: v1::Message,
+    },
+}
+
+#[derive(Debug)]
+pub enum HandlerEvent {
+    InboundRequest {
+        request_id: InboundRequestId,
+        message: v1::Message,
+    },
+    OutboundResponse {
+        query_id: QueryId,
+        message: v1::Message,
+    },
+    OutboundFailure {
+        query_id: QueryId,
+        error: io::Error,
+    },
+}
+
+pub struct Handler {
+    max_packet_size: usize,
+    next_request_id: u64,
+    pending_requests: VecDeque<(QueryId, v1::Message)>,
+    outbound_requests: FuturesTupleSet<io::Result<v1::Message>, QueryId>,
+    inbound_requests: FuturesSet<io::Result<(v1::Message, RpcStream)>>,
+    /// 等待本地响应的入站子流
+    inbound_streams: HashMap<InboundRequestId, RpcStream>,
+    inbound_responses: FuturesSet<io::Result<()>>,
+    pending_events: VecDeque<HandlerEvent>,
+}
+
+impl Handler {
+    pub(crate) fn new(request_timeout: Duration, max_packet_size: usize) -> Self {
+        Self {
+            max_packet_size,
+            next_request_id: 0,
+            pending_requests: VecDeque::new(),
+            outbound_requests: FuturesTupleSet::new(
+                move || Delay::futures_timer(request_timeout),
+                32,
+            ),
+            inbound_requests: FuturesSet::new(move || Delay::futures_timer(request_timeout), 32),
+            inbound_streams: HashMap::new(),
+            inbound_responses: FuturesSet::new(move || Delay::futures_timer(request_timeout), 32),
+            pending_events: VecDeque::new(),
+        }
+    }
+}
+
+impl ConnectionHandler for Handler {
+    type Action = HandlerIn;
+    type Event = HandlerEvent;
+
+    fn handle_action(&mut self, action: Self::Action) {
+        match action {
+            HandlerIn::Request { query_id, message } => {
+                self.pending_requests.push_back((query_id, message));
+            }
+            HandlerIn::Response {
+                request_id,
+                message,
+            } => {
+                let Some(stream) = self.inbound_streams.remove(&request_id) else {
+                    tracing::debug!("Kad inbound stream {:?} already closed", request_id);
+                    return;
+                };
+                if self
+                    .inbound_responses
+                    .try_push(protocol::send_response(stream, message).boxed())
+                    .is_err()
+                {
+                    tracing::warn!("Dropping kad response: too many pending responses");
+                }
+            }
+        }
+    }
+
+    fn keep_alive(&self) -> KeepAlive {
+        KeepAlive::from(
+            !self.pending_requests.is_empty()
+                || !self.outbound_requests.is_empty()
+                || !self.inbound_requests.is_empty()
+                || !self.inbound_streams.is_empty()
+                || !self.inbound_responses.is_empty(),
+        )
+    }
+
+    fn poll(&mut self, cx: &mut Context<'_>) -> Poll<ConnectionHandlerEvent<Self::Event>> {
+        loop {
+            if let Some(event) = self.pending_events.pop_front() {
+                return Poll::Ready(ConnectionHandlerEvent::Notify(event));
+            }
+            match self.outbound_requests.poll_unpin(cx) {
+                Poll::Ready((Ok(Ok(message)), query_id)) => {
+                    return Poll::Ready(ConnectionHandlerEvent::Notify(
+                        HandlerEvent::OutboundResponse { query_id, message },
+                    ));
+                }
+                Poll::Ready((Ok(Err(error)), query_id)) => {
+                    return Poll::Ready(ConnectionHandlerEvent::Notify(
+                        HandlerEvent::OutboundFailure { query_id, error },
+                    ));
+                }
+                Poll::Ready((Err(_), query_id)) => {
+                    return Poll::Ready(ConnectionHandlerEvent::Notify(
+                        HandlerEvent::OutboundFailure {
+                            query_id,
+                            error: io::ErrorKind::TimedOut.into(),
+                        },
+                    ));
+                }
+                Poll::Pending => {}
+            }
+            match self.inbound_requests.poll_unpin(cx) {
+                Poll::Ready(Ok(Ok((message, stream)))) => {
+                    let request_id = InboundRequestId(self.next_request_id);
+                    self.next_request_id += 1;
+                    self.inbound_streams.insert(request_id, stream);
+                    return Poll::Ready(ConnectionHandlerEvent::Notify(
+                        HandlerEvent::InboundRequest {
+                            request_id,
+                            message,
+                        },
+                    ));
+                }
+                Poll::Ready(Ok(Err(error))) => {
+                    tracing::debug!("Failed to read kad request: {}", error);
+                    continue;
+                }
+                Poll::Ready(Err(_)) => {
+                    tracing::debug!("Kad inbound request timed out");
+                    continue;
+                }
+                Poll::Pending => {}
+            }
+            match self.inbound_responses.poll_unpin(cx) {
+                Poll::Ready(Ok(Err(error))) => {
+                    tracing::debug!("Failed to send kad response: {}", error);
+                    continue;
+                }
+                Poll::Ready(Err(_)) => {
+                    tracing::debug!("Kad response timed out");
+                    continue;
+                }
+                Poll::Ready(Ok(Ok(()))) => continue,
+                Poll::Pending => {}
+            }
+            return Poll::Pending;
+        }
+    }
+}
+
+impl InboundStreamHandler for Handler {
+    type InboundUpgrade = ReadyUpgrade<StreamProtocol>;
+    type InboundUserData = ();
+
+    fn listen_protocol(&self) -> SubstreamProtocol<Self::InboundUpgrade, Self::InboundUserData> {
+        SubstreamProtocol::new(ReadyUpgrade::new(protocol::PROTOCOL_NAME), ())
+    }
+
+    fn on_fully_negotiated(
+        &mut self,
+        _user_data: Self::InboundUserData,
+        protocol: <Self::InboundUpgrade as InboundUpgradeSend>::Output,
+    ) {
+        if self
+            .inbound_requests
+            .try_push(protocol::recv_request(protocol, self.max_packet_size).boxed())
+            .is_err()
+        {
+            tracing::warn!("Dropping kad inbound stream: too many pending requests");
+        }
+    }
+
+    fn on_upgrade_error(
+        &mut self,
+        _user_data: Self::InboundUserData,
+        error: <Self::InboundUpgrade as InboundUpgradeSend>::Error,
+    ) {
+        tracing::debug!("Kad inbound upgrade error: {}", error);
+    }
+}
+
+impl OutboundStreamHandler for Handler {
+    type OutboundUpgrade = ReadyUpgrade<StreamProtocol>;
+    type OutboundUserData = (QueryId, v1::Message);
+
+    fn on_fully_negotiated(
+        &mut self,
+        (query_id, message): Self::OutboundUserData,
+        protocol: <Self::OutboundUpgrade as OutboundUpgradeSend>::Output,
+    ) {
+        if self
+            .outbound_requests
+            .try_push(
+                protocol::request(protocol, message, self.max_packet_size).boxed(),
+                query_id,
+            )
+            .is_err()
+        {
+            self.pending_events
+                .push_back(HandlerEvent::OutboundFailure {
+                    query_id,
+                    error: io::Error::other("too many pending requests"),
+                });
+        }
+    }
+
+    fn on_upgrade_error(
+        &mut self,
+        (query_id, _): Self::OutboundUserData,
+        error: StreamUpgradeError<<Self::OutboundUpgrade as OutboundUpgradeSend>::Error>,
+    ) {
+        let error = match error {
+            StreamUpgradeError::Timeout => io::ErrorKind::TimedOut.into(),
+            StreamUpgradeError::NegotiationFailed { .. } => {
+                io::Error::new(io::ErrorKind::Unsupported, "kad protocol not supported")
+            }
+            StreamUpgradeError::RemoteReset => io::ErrorKind::ConnectionReset.into(),
+            StreamUpgradeError::Io(error) => error,
+            StreamUpgradeError::Apply(error) => match error {},
+        };
+        self.pending_events
+            .push_back(HandlerEvent::OutboundFailure { query_id, error });
+    }
+
+    fn poll_outbound_request(
+        &mut self,
+        _cx: &mut Context<'_>,
+    ) -> Poll<SubstreamProtocol<Self::OutboundUpgrade, Self::OutboundUserData>> {
+        if let Some(request) = self.pending_requests.pop_front() {
+            return Poll::Ready(SubstreamProtocol::new(
+                ReadyUpgrade::new(protocol::PROTOCOL_NAME),
+                request,
+            ));
+        }
+        Poll::Pending
+    }
+}
@@ -0,0 +1,186 @@
+use std::cmp::Ordering;
+
+use sha2::{Digest, Sha256};
+use volans_core::{Multiaddr, PeerId};
+
+use crate::RecordKey;
+
+/// Kademlia 空间中的键，为原始键的 SHA-256 摘要
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Key([u8; 32]);
+
+impl Key {
+    pub fn new(preimage: impl AsRef<[u8]>) -> Self {
+        Self(Sha256::digest(preimage.as_ref()).into())
+    }
+
+    pub fn distance(&self, other: &Key) -> Distance {
+        let mut distance = [0u8; 32];
+        for (i, byte) in distance.iter_mut().enumerate() {
+            *byte = self.0[i] ^ other.0[i];
+        }
+        Distance(distance)
+    }
+}
+
+impl From<PeerId> for Key {
+    fn from(peer_id: PeerId) -> Self {
+        Self::new(peer_id.as_bytes())
+    }
+}
+
+impl From<&RecordKey> for Key {
+    fn from(key: &RecordKey) -> Self {
+        Self::new(key.as_bytes())
+    }
+}
+
+/// XOR 距离，按大端字节序比较
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Distance([u8; 32]);
+
+impl Distance {
+    /// 距离所在的 K 桶下标，距离为 0 时返回 `None`
+    pub fn bucket_index(&self) -> Option<usize> {
+        let mut leading_zeros = 0;
+        for byte in self.0 {
+            if byte == 0 {
+                leading_zeros += 8;
+            } else {
+                leading_zeros += byte.leading_zeros() as usize;
+                return Some(255 - leading_zeros);
+            }
+        }
+        None
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Entry {
+    pub peer_id: PeerId,
+    pub addresses: Vec<Multiaddr>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum InsertResult {
+    Inserted,
+    Updated,
+    /// 对应的 K 桶已满
+    Full,
+}
+
+/// 路由表，每个桶内的节点按最近活跃时间排列，最久未活跃的在前
+pub(crate) struct KBucketsTable {
+    local_key: Key,
+    buckets: Vec<Vec<Entry>>,
+    bucket_size: usize,
+}
+
+impl KBucketsTable {
+    pub(crate) fn new(local_peer_id: PeerId, bucket_size: usize) -> Self {
+        Self {
+            local_key: Key::from(local_peer_id),
+            buckets: (0..256).map(|_| Vec::new()).collect(),
+            bucket_size,
+        }
+    }
+
+    fn bucket_index(&self, peer_id: &PeerId) -> Option<usize> {
+        self.local_key.distance(&Key::from(*peer_id)).bucket_index()
+    }
+
+    pub(crate) fn insert(&mut self, peer_id: PeerId, address: Option<Multiaddr>) -> InsertResult {
+        let Some(index) = self.bucket_index(&peer_id) else {
+            return InsertResult::Full;
+        };
+        let bucket_size = self.bucket_size;
+        let bucket = &mut self.buckets[index];
+        if let Some(position) = bucket.iter().position(|e| e.peer_id == peer_id) {
+            let mut entry = bucket.remove(position);
+            if let Some(address) = address
+                && !entry.addresses.contains(&address)
+            {
+                entry.addresses.push(address);
+            }
+            bucket.push(entry);
+            return InsertResult::Updated;
+        }
+        if bucket.len() >= bucket_size {
+            return InsertResult::Full;
+        }
+        bucket.push(Entry {
+            peer_id,
+            addresses: address.into_iter().collect(),
+        });
+        InsertResult::Inserted
+    }
+
+    pub(crate) fn remove_address(&mut self, peer_id: &PeerId, address: &Multiaddr) {
+        let Some(index) = self.bucket_index(peer_id) else {
+            return;
+        };
+        let bucket = &mut self.buckets[index];
+        if let Some(position) = bucket.iter().position(|e| e.peer_id == *peer_id) {
+            bucket[position].addresses.retain(|a| a != address);
+            if bucket[position].addresses.is_empty() {
+                bucket.remove(position);
+            }
+        }
+    }
+
+    pub(crate) fn remove(&mut self, peer_id: &PeerId) -> Option<Entry> {
+        let index = self.bucket_index(peer_id)?;
+        let bucket = &mut self.buckets[index];
+        let position = bucket.iter().position(|e| e.peer_id == *peer_id)?;
+        Some(bucket.remove(position))
+    }
+
+    pub(crate) fn get(&self, peer_id: &PeerId) -> Option<&Entry> {
+        let index = self.bucket_index(peer_id)?;
+        self.buckets[index].iter().find(|e| e.peer_id == *peer_id)
+    }
+
+    pub(crate) fn iter(&self) -> impl Iterator<Item = &Entry> {
+        self.buckets.iter().flatten()
+    }
+
+    /// 距离目标最近的 `num` 个节点
+    pub(crate) fn closest(&self, target: &Key, num: usize) -> Vec<&Entry> {
+        let mut entries = self.iter().collect::<Vec<_>>();
+        entries.sort_by(|a, b| compare(target, &a.peer_id, &b.peer_id));
+        entries.truncate(num);
+        entries
+    }
+}
+
+fn compare(target: &Key, a: &PeerId, b: &PeerId) -> Ordering {
+    target
+        .distance(&Key::from(*a))
+        .cmp(&target.distance(&Key::from(*b)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn closest_sorted_by_distance() {
+        let local = PeerId::random();
+        let mut table = KBucketsTable::new(local, 20);
+        let peers = (0..50).map(|_| PeerId::random()).collect::<Vec<_>>();
+        for peer in &peers {
+            table.insert(*peer, None);
+        }
+        assert_eq!(table.insert(local, None), InsertResult::Full);
+
+        let target = Key::from(PeerId::random());
+        let closest = table.closest(&target, 10);
+        assert_eq!(closest.len(), 10);
+        for pair in closest.windows(2) {
+            assert_ne!(
+                compare(&target, &pair[0].peer_id, &pair[1].peer_id),
+                Ordering::Greater
+            );
+        }
+    }
+}
@@ -0,0 +1,134 @@
+//! Kademlia 分布式哈希表
+//!
+//! 节点按 XOR 距离组织在 K 桶路由表中，通过迭代查询逼近目标键的最近节点，
+//! 在其上实现节点路由、键值记录与内容提供者记录。查询过程中发现的节点会写入路由表，
+//! 未连接的节点由 [`Behavior`] 自动拨号。
+
+mod behavior;
+mod handler;
+mod kbucket;
+mod protocol;
+mod query;
+mod record;
+
+pub use behavior::Behavior;
+pub use handler::{Handler, HandlerEvent, HandlerIn, InboundRequestId};
+pub use kbucket::{Distance, Entry, Key};
+pub use record::{MemoryStore, ProviderRecord, Record, RecordKey, StoreError};
+
+use std::{collections::HashSet, fmt, time::Duration};
+
+use volans_core::{Multiaddr, PeerId};
+
+#[derive(Debug, Clone)]
+pub struct Config {
+    replication_factor: usize,
+    parallelism: usize,
+    query_timeout: Duration,
+    request_timeout: Duration,
+    record_ttl: Option<Duration>,
+    provider_ttl: Option<Duration>,
+    max_packet_size: usize,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            replication_factor: 20,
+            parallelism: 3,
+            query_timeout: Duration::from_secs(60),
+            request_timeout: Duration::from_secs(10),
+            record_ttl: Some(Duration::from_secs(36 * 60 * 60)),
+            provider_ttl: Some(Duration::from_secs(24 * 60 * 60)),
+            max_packet_size: 16 * 1024,
+        }
+    }
+}
+
+impl Config {
+    /// K 值，即每个 K 桶的容量以及查询返回、记录复制的节点数
+    pub fn with_replication_factor(mut self, k: usize) -> Self {
+        assert!(k > 0, "replication factor must be greater than 0");
+        self.replication_factor = k;
+        self
+    }
+
+    /// 查询时同时进行的请求数
+    pub fn with_parallelism(mut self, alpha: usize) -> Self {
+        assert!(alpha > 0, "parallelism must be greater than 0");
+        self.parallelism = alpha;
+        self
+    }
+
+    pub fn with_query_timeout(mut self, timeout: Duration) -> Self {
+        self.query_timeout = timeout;
+        self
+    }
+
+    /// 单个请求的超时时间
+    pub fn with_request_timeout(mut self, timeout: Duration) -> Self {
+        self.request_timeout = timeout;
+        self
+    }
+
+    /// 发布记录的有效期，`None` 表示永久
+    pub fn with_record_ttl(mut self, ttl: Option<Duration>) -> Self {
+        self.record_ttl = ttl;
+        self
+    }
+
+    /// 提供者记录的有效期，`None` 表示永久
+    pub fn with_provider_ttl(mut self, ttl: Option<Duration>) -> Self {
+        self.provider_ttl = ttl;
+        self
+    }
+
+    pub fn with_max_packet_size(mut self, size: usize) -> Self {
+        self.max_packet_size = size;
+        self
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct QueryId(u64);
+
+impl fmt::Display for QueryId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+#[derive(Debug)]
+pub enum Event {
+    /// 查询完成
+    QueryResult { id: QueryId, result: QueryResult },
+    /// 路由表中加入了新节点
+    RoutingUpdated {
+        peer_id: PeerId,
+        addresses: Vec<Multiaddr>,
+    },
+}
+
+#[derive(Debug)]
+pub enum QueryResult {
+    Bootstrap(Result<Vec<PeerId>, QueryError>),
+    GetClosestPeers(Result<Vec<PeerId>, QueryError>),
+    GetRecord(Result<Record, QueryError>),
+    PutRecord(Result<RecordKey, QueryError>),
+    GetProviders(Result<HashSet<PeerId>, QueryError>),
+    StartProviding(Result<RecordKey, QueryError>),
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum QueryError {
+    #[error("Query timed out")]
+    Timeout,
+    #[error("Record not found")]
+    NotFound,
+    #[error("No peer accepted the record")]
+    QuorumFailed,
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("No known peers in routing table")]
+pub struct NoKnownPeers;
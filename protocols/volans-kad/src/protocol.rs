@@ -0,0 +1,98 @@
+use std::{
+    io,
+    time::{Duration, Instant},
+};
+
+use futures::{SinkExt, StreamExt};
+use volans_codec::{Framed, ProtobufUviCodec};
+use volans_core::{Multiaddr, PeerId};
+use volans_swarm::{StreamProtocol, Substream};
+
+use crate::{Record, RecordKey};
+
+pub mod v1 {
+    include!(concat!(env!("OUT_DIR"), "/volans.kad.v1.rs"));
+}
+
+pub(crate) const PROTOCOL_NAME: StreamProtocol = StreamProtocol::new("/v1/kad");
+
+pub(crate) type RpcStream = Framed<Substream, ProtobufUviCodec<v1::Message>>;
+
+/// 发送请求并等待响应
+pub(crate) async fn request(
+    stream: Substream,
+    message: v1::Message,
+    max_packet_size: usize,
+) -> io::Result<v1::Message> {
+    let mut stream = Framed::new(stream, ProtobufUviCodec::new(max_packet_size));
+    stream.send(message).await?;
+    let response = stream
+        .next()
+        .await
+        .ok_or_else(|| io::Error::from(io::ErrorKind::UnexpectedEof))??;
+    Ok(response)
+}
+
+pub(crate) async fn recv_request(
+    stream: Substream,
+    max_packet_size: usize,
+) -> io::Result<(v1::Message, RpcStream)> {
+    let mut stream = Framed::new(stream, ProtobufUviCodec::new(max_packet_size));
+    let request = stream
+        .next()
+        .await
+        .ok_or_else(|| io::Error::from(io::ErrorKind::UnexpectedEof))??;
+    Ok((request, stream))
+}
+
+pub(crate) async fn send_response(mut stream: RpcStream, message: v1::Message) -> io::Result<()> {
+    stream.send(message).await?;
+    stream.close().await?;
+    Ok(())
+}
+
+pub(crate) fn encode_peer(peer_id: &PeerId, addresses: &[Multiaddr]) -> v1::Peer {
+    v1::Peer {
+        id: peer_id.as_bytes().to_vec(),
+        addrs: addresses.iter().map(|a| a.to_string()).collect(),
+    }
+}
+
+/// 解析节点信息，忽略无法解析的地址
+pub(crate) fn decode_peer(peer: &v1::Peer) -> Option<(PeerId, Vec<Multiaddr>)> {
+    let peer_id = PeerId::try_from_slice(&peer.id).ok()?;
+    let addresses = peer.addrs.iter().filter_map(|a| a.parse().ok()).collect();
+    Some((peer_id, addresses))
+}
+
+pub(crate) fn encode_record(record: &Record) -> v1::Record {
+    v1::Record {
+        key: record.key.as_bytes().to_vec(),
+        value: record.value.clone(),
+        publisher: record
+            .publisher
+            .map(|p| p.as_bytes().to_vec())
+            .unwrap_or_default(),
+        ttl: record
+            .ttl(Instant::now())
+            .map(|ttl| ttl.as_secs().max(1))
+            .unwrap_or_default(),
+    }
+}
+
+pub(crate) fn decode_record(record: v1::Record) -> Record {
+    Record {
+        key: RecordKey::from(record.key),
+        value: record.value,
+        publisher: PeerId::try_from_slice(&record.publisher).ok(),
+        expires: (record.ttl > 0).then(|| Instant::now() + Duration::from_secs(record.ttl)),
+    }
+}
+
+pub(crate) fn new_message(ty: v1::MessageType, key: Vec<u8>) -> v1::Message {
+    v1::Message {
+        r#type: ty as i32,
+        key,
+        ..Default::default()
+    }
+}
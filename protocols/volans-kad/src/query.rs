@@ -0,0 +1,138 @@
+use std::collections::BTreeMap;
+
+use volans_core::PeerId;
+
+use crate::kbucket::{Distance, Key};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PeerState {
+    NotContacted,
+    Waiting,
+    Succeeded,
+    Failed,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum PeersIterState {
+    /// 需要联系下一个节点，`None` 表示等待进行中的请求
+    Waiting(Option<PeerId>),
+    Finished,
+}
+
+/// 迭代逼近目标键的最近节点
+pub(crate) struct ClosestPeersIter {
+    target: Key,
+    closest: BTreeMap<Distance, (PeerId, PeerState)>,
+    parallelism: usize,
+    num_results: usize,
+    finished: bool,
+}
+
+impl ClosestPeersIter {
+    pub(crate) fn new(
+        target: Key,
+        known: impl IntoIterator<Item = PeerId>,
+        parallelism: usize,
+        num_results: usize,
+    ) -> Self {
+        let closest = known
+            .into_iter()
+            .map(|peer| {
+                (
+                    target.distance(&Key::from(peer)),
+                    (peer, PeerState::NotContacted),
+                )
+            })
+            .collect();
+        Self {
+            target,
+            closest,
+            parallelism,
+            num_results,
+            finished: false,
+        }
+    }
+
+    pub(crate) fn on_success(
+        &mut self,
+        peer: &PeerId,
+        closer_peers: impl IntoIterator<Item = PeerId>,
+    ) {
+        if self.finished || !self.set_state(peer, PeerState::Succeeded) {
+            return;
+        }
+        for peer in closer_peers {
+            let distance = self.target.distance(&Key::from(peer));
+            self.closest
+                .entry(distance)
+                .or_insert((peer, PeerState::NotContacted));
+        }
+    }
+
+    pub(crate) fn on_failure(&mut self, peer: &PeerId) {
+        if !self.finished {
+            self.set_state(peer, PeerState::Failed);
+        }
+    }
+
+    fn set_state(&mut self, peer: &PeerId, state: PeerState) -> bool {
+        let distance = self.target.distance(&Key::from(*peer));
+        match self.closest.get_mut(&distance) {
+            Some((_, current)) if *current == PeerState::Waiting => {
+                *current = state;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// 提前结束迭代
+    pub(crate) fn finish(&mut self) {
+        self.finished = true;
+    }
+
+    pub(crate) fn next(&mut self) -> PeersIterState {
+        if self.finished {
+            return PeersIterState::Finished;
+        }
+        let mut waiting = 0;
+        let mut succeeded = 0;
+        for (peer, state) in self.closest.values_mut() {
+            match state {
+                PeerState::NotContacted => {
+                    if waiting < self.parallelism {
+                        *state = PeerState::Waiting;
+                        return PeersIterState::Waiting(Some(*peer));
+                    }
+                    return PeersIterState::Waiting(None);
+                }
+                PeerState::Waiting => waiting += 1,
+                PeerState::Succeeded => {
+                    succeeded += 1;
+                    if succeeded >= self.num_results && waiting == 0 {
+                        self.finished = true;
+                        return PeersIterState::Finished;
+                    }
+                }
+                PeerState::Failed => {}
+            }
+        }
+        if waiting > 0 {
+            PeersIterState::Waiting(None)
+        } else {
+            self.finished = true;
+            PeersIterState::Finished
+        }
+    }
+
+    /// 已成功响应的最近节点
+    pub(crate) fn result(&self) -> Vec<PeerId> {
+        self.closest
+            .values()
+            .filter(|(_, state)| *state == PeerState::Succeeded)
+            .map(|(peer, _)| *peer)
+            .take(self.num_results)
+            .collect()
+    }
+}
+
@@ -0,0 +1,187 @@
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+use volans_core::{Multiaddr, PeerId};
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct RecordKey(Vec<u8>);
+
+impl RecordKey {
+    pub fn new(key: impl AsRef<[u8]>) -> Self {
+        Self(key.as_ref().to_vec())
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.0
+    }
+}
+
+impl From<Vec<u8>> for RecordKey {
+    fn from(key: Vec<u8>) -> Self {
+        Self(key)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Record {
+    pub key: RecordKey,
+    pub value: Vec<u8>,
+    pub publisher: Option<PeerId>,
+    pub expires: Option<Instant>,
+}
+
+impl Record {
+    pub fn new(key: impl Into<RecordKey>, value: impl Into<Vec<u8>>) -> Self {
+        Self {
+            key: key.into(),
+            value: value.into(),
+            publisher: None,
+            expires: None,
+        }
+    }
+
+    pub fn is_expired(&self, now: Instant) -> bool {
+        self.expires.is_some_and(|t| now >= t)
+    }
+
+    /// 剩余有效期，永久记录返回 `None`
+    pub(crate) fn ttl(&self, now: Instant) -> Option<Duration> {
+        self.expires.map(|t| t.saturating_duration_since(now))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ProviderRecord {
+    pub key: RecordKey,
+    pub provider: PeerId,
+    pub addresses: Vec<Multiaddr>,
+    pub expires: Option<Instant>,
+}
+
+impl ProviderRecord {
+    pub fn is_expired(&self, now: Instant) -> bool {
+        self.expires.is_some_and(|t| now >= t)
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum StoreError {
+    #[error("Record store is full")]
+    MaxRecords,
+    #[error("Provider list of the key is full")]
+    MaxProviders,
+    #[error("Record value is too large")]
+    ValueTooLarge,
+}
+
+/// 内存中的记录存储
+#[derive(Debug)]
+pub struct MemoryStore {
+    records: HashMap<RecordKey, Record>,
+    providers: HashMap<RecordKey, Vec<ProviderRecord>>,
+    max_records: usize,
+    max_value_bytes: usize,
+    max_providers_per_key: usize,
+}
+
+impl Default for MemoryStore {
+    fn default() -> Self {
+        Self {
+            records: HashMap::new(),
+            providers: HashMap::new(),
+            max_records: 1024,
+            max_value_bytes: 65 * 1024,
+            max_providers_per_key: 20,
+        }
+    }
+}
+
+impl MemoryStore {
+    pub fn with_max_records(mut self, max: usize) -> Self {
+        self.max_records = max;
+        self
+    }
+
+    pub fn with_max_value_bytes(mut self, max: usize) -> Self {
+        self.max_value_bytes = max;
+        self
+    }
+
+    pub fn with_max_providers_per_key(mut self, max: usize) -> Self {
+        self.max_providers_per_key = max;
+        self
+    }
+
+    pub fn get(&self, key: &RecordKey) -> Option<&Record> {
+        self.records
+            .get(key)
+            .filter(|r| !r.is_expired(Instant::now()))
+    }
+
+    pub fn put(&mut self, record: Record) -> Result<(), StoreError> {
+        if record.value.len() > self.max_value_bytes {
+            return Err(StoreError::ValueTooLarge);
+        }
+        if !self.records.contains_key(&record.key) && self.records.len() >= self.max_records {
+            return Err(StoreError::MaxRecords);
+        }
+        self.records.insert(record.key.clone(), record);
+        Ok(())
+    }
+
+    pub fn remove(&mut self, key: &RecordKey) -> Option<Record> {
+        self.records.remove(key)
+    }
+
+    pub fn add_provider(&mut self, record: ProviderRecord) -> Result<(), StoreError> {
+        let providers = self.providers.entry(record.key.clone()).or_default();
+        if let Some(existing) = providers.iter_mut().find(|p| p.provider == record.provider) {
+            *existing = record;
+            return Ok(());
+        }
+        if providers.len() >= self.max_providers_per_key {
+            return Err(StoreError::MaxProviders);
+        }
+        providers.push(record);
+        Ok(())
+    }
+
+    pub fn providers(&self, key: &RecordKey) -> Vec<ProviderRecord> {
+        let now = Instant::now();
+        self.providers
+            .get(key)
+            .map(|providers| {
+                providers
+                    .iter()
+                    .filter(|p| !p.is_expired(now))
+                    .cloned()
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    pub fn remove_provider(&mut self, key: &RecordKey, provider: &PeerId) {
+        if let Some(providers) = self.providers.get_mut(key) {
+            providers.retain(|p| p.provider != *provider);
+            if providers.is_empty() {
+                self.providers.remove(key);
+            }
+        }
+    }
+
+    /// 清理过期的记录
+    pub fn remove_expired(&mut self) {
+        let now = Instant::now();
+        self.records.retain(|_, r| !r.is_expired(now));
+        self.providers.retain(|_, providers| {
+            providers.retain(|p| !p.is_expired(now));
+            !providers.is_empty()
+        });
+    }
+}
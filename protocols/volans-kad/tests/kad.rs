@@ -0,0 +1,163 @@
+use std::collections::HashSet;
+
+use futures::StreamExt;
+use volans_core::{Multiaddr, PeerId};
+use volans_kad::{
+    Behavior, Config, Event, Key, QueryError, QueryId, QueryResult, Record, RecordKey,
+};
+use volans_swarm::duplex;
+use volans_swarm_test::{SwarmExt, listen, wait_for_event};
+
+type Swarm = duplex::Swarm<Behavior>;
+
+/// 在本地随机端口上监听的节点
+async fn node() -> (Swarm, PeerId, Multiaddr) {
+    let mut swarm = Swarm::new_ephemeral(|key_pair| {
+        Behavior::new(
+            PeerId::from_public_key(&key_pair.verifying_key()),
+            Config::default(),
+        )
+    });
+    let addr = listen(&mut swarm).await;
+    let peer_id = *swarm.local_peer_id();
+    (swarm, peer_id, addr)
+}
+
+/// 创建 `len` 个节点，每个节点只知道下一个节点的地址
+async fn chain(len: usize) -> (Vec<Swarm>, Vec<(PeerId, Multiaddr)>) {
+    let mut swarms = Vec::new();
+    let mut peers = Vec::new();
+    for _ in 0..len {
+        let (swarm, peer_id, addr) = node().await;
+        swarms.push(swarm);
+        peers.push((peer_id, addr));
+    }
+    for (swarm, (peer_id, addr)) in swarms.iter_mut().zip(peers.iter().skip(1)) {
+        swarm.behavior_mut().add_address(*peer_id, addr.clone());
+    }
+    (swarms, peers)
+}
+
+fn spawn(mut swarm: Swarm) {
+    tokio::spawn(async move {
+        loop {
+            swarm.next().await;
+        }
+    });
+}
+
+/// 只知道 `peer` 的新节点
+async fn node_knowing((peer_id, addr): &(PeerId, Multiaddr)) -> Swarm {
+    let (mut swarm, _, _) = node().await;
+    swarm.behavior_mut().add_address(*peer_id, addr.clone());
+    swarm
+}
+
+async fn query_result(swarm: &mut Swarm, id: QueryId) -> QueryResult {
+    wait_for_event(swarm, |event| match event {
+        duplex::SwarmEvent::Behavior(Event::QueryResult { id: query, result }) if query == id => {
+            Some(result)
+        }
+        _ => None,
+    })
+    .await
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn closest_peers_through_chain() {
+    let (swarms, peers) = chain(4).await;
+    let mut swarms = swarms.into_iter();
+    let mut local = swarms.next().unwrap();
+    swarms.for_each(spawn);
+
+    let target = PeerId::random();
+    let id = local.behavior_mut().get_closest_peers(target);
+    let QueryResult::GetClosestPeers(Ok(closest)) = query_result(&mut local, id).await else {
+        panic!("expected closest peers");
+    };
+
+    // 沿途发现的节点都被查询到，结果按到目标的距离排序
+    let target = Key::new(target.as_bytes());
+    let mut expected = peers[1..]
+        .iter()
+        .map(|(peer_id, _)| *peer_id)
+        .collect::<Vec<_>>();
+    expected.sort_by_key(|peer_id| target.distance(&Key::new(peer_id.as_bytes())));
+    assert_eq!(closest, expected);
+
+    let known = local
+        .behavior()
+        .peers()
+        .map(|entry| entry.peer_id)
+        .collect::<HashSet<_>>();
+    assert_eq!(known, expected.into_iter().collect());
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn put_and_get_record() {
+    let (swarms, peers) = chain(3).await;
+    let mut swarms = swarms.into_iter();
+    let mut publisher = swarms.next().unwrap();
+    swarms.for_each(spawn);
+
+    let key = RecordKey::new("greeting");
+    let id = publisher
+        .behavior_mut()
+        .put_record(Record::new(key.clone(), "hello"))
+        .unwrap();
+    let QueryResult::PutRecord(Ok(stored)) = query_result(&mut publisher, id).await else {
+        panic!("expected record to be stored");
+    };
+    assert_eq!(stored, key);
+    spawn(publisher);
+
+    // 只知道链尾节点的新节点可以取回记录
+    let mut reader = node_knowing(&peers[2]).await;
+    let id = reader.behavior_mut().get_record(key.clone());
+    let QueryResult::GetRecord(Ok(record)) = query_result(&mut reader, id).await else {
+        panic!("expected record");
+    };
+    assert_eq!(record.key, key);
+    assert_eq!(record.value, b"hello");
+    assert_eq!(record.publisher, Some(peers[0].0));
+
+    let id = reader.behavior_mut().get_record(RecordKey::new("missing"));
+    assert!(matches!(
+        query_result(&mut reader, id).await,
+        QueryResult::GetRecord(Err(QueryError::NotFound))
+    ));
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn provide_and_find_providers() {
+    let (swarms, peers) = chain(3).await;
+    let mut swarms = swarms.into_iter();
+    let mut provider = swarms.next().unwrap();
+    swarms.for_each(spawn);
+
+    let key = RecordKey::new("content");
+    let id = provider
+        .behavior_mut()
+        .start_providing(key.clone())
+        .unwrap();
+    let QueryResult::StartProviding(Ok(provided)) = query_result(&mut provider, id).await else {
+        panic!("expected provider record to be stored");
+    };
+    assert_eq!(provided, key);
+    spawn(provider);
+
+    let mut seeker = node_knowing(&peers[2]).await;
+    let id = seeker.behavior_mut().get_providers(key);
+    let QueryResult::GetProviders(Ok(providers)) = query_result(&mut seeker, id).await else {
+        panic!("expected providers");
+    };
+    assert_eq!(providers, HashSet::from([peers[0].0]));
+
+    // 提供者的监听地址随记录一起传播
+    let entry = seeker
+        .behavior()
+        .peers()
+        .find(|entry| entry.peer_id == peers[0].0)
+        .expect("provider is in the routing table");
+    assert_eq!(entry.addresses, [peers[0].1.clone()]);
+}
@@ -0,0 +1,307 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    io,
+    task::{Context, Poll, Waker},
+};
+
+use futures::FutureExt;
+use futures_bounded::{Delay, FuturesMap};
+use volans_core::{Multiaddr, PeerId, upgrade::ReadyUpgrade};
+use volans_swarm::{
+    BehaviorEvent, ConnectionDenied, ConnectionHandler, ConnectionHandlerEvent, ConnectionId,
+    DialOpts, KeepAlive, NetworkBehavior, NetworkOutgoingBehavior, OutboundStreamHandler,
+    OutboundUpgradeSend, StreamProtocol, StreamUpgradeError, SubstreamProtocol, THandlerAction,
+    THandlerEvent,
+    behavior::NotifyHandler,
+    error::{CloseReason, DialError},
+};
+
+use crate::{Benchmark, Config, Failure, Report, RunId, protocol};
+
+/// 拨号端发起测试，每次测试使用一个子流
+pub struct Handler {
+    config: Config,
+    pending: VecDeque<(RunId, Benchmark)>,
+    pending_events: VecDeque<(RunId, Result<Report, Failure>)>,
+    running: FuturesMap<RunId, io::Result<Report>>,
+}
+
+impl Handler {
+    pub fn new(config: Config) -> Self {
+        let timeout = config.timeout;
+        Self {
+            running: FuturesMap::new(
+                move || Delay::futures_timer(timeout),
+                config.max_concurrent_runs,
+            ),
+            config,
+            pending: VecDeque::new(),
+            pending_events: VecDeque::new(),
+        }
+    }
+}
+
+impl ConnectionHandler for Handler {
+    type Action = (RunId, Benchmark);
+    type Event = (RunId, Result<Report, Failure>);
+
+    fn handle_action(&mut self, action: Self::Action) {
+        self.pending.push_back(action);
+    }
+
+    fn keep_alive(&self) -> KeepAlive {
+        KeepAlive::from(!self.pending.is_empty() || !self.running.is_empty())
+    }
+
+    fn poll_close(&mut self, _: &mut Context<'_>) -> Poll<Option<Self::Event>> {
+        Poll::Ready(self.pending_events.pop_front())
+    }
+
+    fn poll(&mut self, cx: &mut Context<'_>) -> Poll<ConnectionHandlerEvent<Self::Event>> {
+        if let Some(event) = self.pending_events.pop_front() {
+            return Poll::Ready(ConnectionHandlerEvent::Notify(event));
+        }
+        let event = match self.running.poll_unpin(cx) {
+            Poll::Ready((id, Ok(result))) => (id, result.map_err(Failure::Io)),
+            Poll::Ready((id, Err(_))) => (id, Err(Failure::Timeout)),
+            Poll::Pending => return Poll::Pending,
+        };
+        Poll::Ready(ConnectionHandlerEvent::Notify(event))
+    }
+}
+
+impl OutboundStreamHandler for Handler {
+    type OutboundUpgrade = ReadyUpgrade<StreamProtocol>;
+    type OutboundUserData = (RunId, Benchmark);
+
+    fn on_fully_negotiated(
+        &mut self,
+        (id, benchmark): Self::OutboundUserData,
+        stream: <Self::OutboundUpgrade as OutboundUpgradeSend>::Output,
+    ) {
+        let run = protocol::send_run(stream, benchmark).boxed();
+        if self.running.try_push(id, run).is_err() {
+            self.pending_events.push_back((
+                id,
+                Err(Failure::Io(io::Error::other("max perf runs reached"))),
+            ));
+        }
+    }
+
+    fn on_upgrade_error(
+        &mut self,
+        (id, _): Self::OutboundUserData,
+        error: StreamUpgradeError<<Self::OutboundUpgrade as OutboundUpgradeSend>::Error>,
+    ) {
+        let failure = match error {
+            StreamUpgradeError::Timeout => Failure::Timeout,
+            StreamUpgradeError::NegotiationFailed { .. } => Failure::Unsupported,
+            StreamUpgradeError::RemoteReset => Failure::Io(io::ErrorKind::ConnectionReset.into()),
+            StreamUpgradeError::Io(error) => Failure::Io(error),
+            StreamUpgradeError::Apply(error) => match error {},
+        };
+        self.pending_events.push_back((id, Err(failure)));
+    }
+
+    fn poll_outbound_request(
+        &mut self,
+        _cx: &mut Context<'_>,
+    ) -> Poll<SubstreamProtocol<Self::OutboundUpgrade, Self::OutboundUserData>> {
+        if let Some(run) = self.pending.pop_front() {
+            let protocol = SubstreamProtocol::new(ReadyUpgrade::new(protocol::PROTOCOL_NAME), run)
+                .with_timeout(self.config.timeout);
+            return Poll::Ready(protocol);
+        }
+        Poll::Pending
+    }
+}
+
+#[derive(Debug)]
+pub struct Event {
+    pub run_id: RunId,
+    pub peer_id: PeerId,
+    pub result: Result<Report, Failure>,
+}
+
+/// 发起测试的行为，未连接的节点先拨号
+pub struct Behavior {
+    config: Config,
+    connections: HashMap<PeerId, Vec<ConnectionId>>,
+    /// 已交给连接处理器的测试
+    running: HashMap<RunId, (PeerId, ConnectionId)>,
+    /// 等待连接的测试
+    pending_runs: HashMap<PeerId, Vec<(RunId, Benchmark)>>,
+    pending_dials: VecDeque<PeerId>,
+    events: VecDeque<BehaviorEvent<Event, THandlerAction<Self>>>,
+    waker: Option<Waker>,
+}
+
+impl Behavior {
+    pub fn new(config: Config) -> Self {
+        Self {
+            config,
+            connections: HashMap::new(),
+            running: HashMap::new(),
+            pending_runs: HashMap::new(),
+            pending_dials: VecDeque::new(),
+            events: VecDeque::new(),
+            waker: None,
+        }
+    }
+
+    /// 对节点运行一次测试，结果通过 [`Event`] 上报
+    pub fn perf(&mut self, peer_id: PeerId, benchmark: Benchmark) -> RunId {
+        let id = RunId::next();
+        match self.connections.get(&peer_id).and_then(|c| c.first()) {
+            Some(connection) => self.start_run(peer_id, *connection, id, benchmark),
+            None => {
+                let runs = self.pending_runs.entry(peer_id).or_default();
+                if runs.is_empty() {
+                    self.pending_dials.push_back(peer_id);
+                }
+                runs.push((id, benchmark));
+            }
+        }
+        self.wake();
+        id
+    }
+
+    fn start_run(&mut self, peer_id: PeerId, connection: ConnectionId, id: RunId, run: Benchmark) {
+        self.running.insert(id, (peer_id, connection));
+        self.events.push_back(BehaviorEvent::HandlerAction {
+            peer_id,
+            handler: NotifyHandler::One(connection),
+            action: (id, run),
+        });
+    }
+
+    fn report(&mut self, run_id: RunId, peer_id: PeerId, result: Result<Report, Failure>) {
+        self.events.push_back(BehaviorEvent::Behavior(Event {
+            run_id,
+            peer_id,
+            result,
+        }));
+        self.wake();
+    }
+
+    fn wake(&mut self) {
+        if let Some(waker) = self.waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+impl Default for Behavior {
+    fn default() -> Self {
+        Self::new(Config::default())
+    }
+}
+
+impl NetworkBehavior for Behavior {
+    type ConnectionHandler = Handler;
+    type Event = Event;
+
+    fn on_connection_handler_event(
+        &mut self,
+        _id: ConnectionId,
+        peer_id: PeerId,
+        (run_id, result): THandlerEvent<Self>,
+    ) {
+        if self.running.remove(&run_id).is_some() {
+            self.report(run_id, peer_id, result);
+        }
+    }
+
+    fn poll(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<BehaviorEvent<Self::Event, THandlerAction<Self>>> {
+        if let Some(event) = self.events.pop_front() {
+            return Poll::Ready(event);
+        }
+        self.waker = Some(cx.waker().clone());
+        Poll::Pending
+    }
+}
+
+impl NetworkOutgoingBehavior for Behavior {
+    fn handle_established_connection(
+        &mut self,
+        _id: ConnectionId,
+        _peer_id: PeerId,
+        _addr: &Multiaddr,
+    ) -> Result<Self::ConnectionHandler, ConnectionDenied> {
+        Ok(Handler::new(self.config.clone()))
+    }
+
+    fn handle_pending_connection(
+        &mut self,
+        _id: ConnectionId,
+        _maybe_peer: Option<PeerId>,
+        addr: &Option<Multiaddr>,
+    ) -> Result<Option<Multiaddr>, ConnectionDenied> {
+        Ok(addr.clone())
+    }
+
+    fn on_connection_established(&mut self, id: ConnectionId, peer_id: PeerId, _addr: &Multiaddr) {
+        self.connections.entry(peer_id).or_default().push(id);
+        for (run_id, benchmark) in self.pending_runs.remove(&peer_id).unwrap_or_default() {
+            self.start_run(peer_id, id, run_id, benchmark);
+        }
+        self.wake();
+    }
+
+    fn on_connection_closed(
+        &mut self,
+        id: ConnectionId,
+        peer_id: PeerId,
+        _addr: &Multiaddr,
+        _reason: &CloseReason,
+    ) {
+        if let Some(connections) = self.connections.get_mut(&peer_id) {
+            connections.retain(|c| *c != id);
+            if connections.is_empty() {
+                self.connections.remove(&peer_id);
+            }
+        }
+        // 连接关闭前处理器上报的结果已经送达，剩下的测试不会再有结果
+        let closed: Vec<_> = self
+            .running
+            .iter()
+            .filter(|(_, (_, connection))| *connection == id)
+            .map(|(run_id, _)| *run_id)
+            .collect();
+        for run_id in closed {
+            self.running.remove(&run_id);
+            self.report(run_id, peer_id, Err(Failure::ConnectionClosed));
+        }
+    }
+
+    fn on_dial_failure(
+        &mut self,
+        _id: ConnectionId,
+        peer_id: Option<PeerId>,
+        _addr: Option<&Multiaddr>,
+        _error: &DialError,
+    ) {
+        let Some(peer_id) = peer_id else {
+            return;
+        };
+        if self.connections.contains_key(&peer_id) {
+            return;
+        }
+        for (run_id, _) in self.pending_runs.remove(&peer_id).unwrap_or_default() {
+            self.report(run_id, peer_id, Err(Failure::Dial));
+        }
+    }
+
+    fn poll_dial(&mut self, cx: &mut Context<'_>) -> Poll<DialOpts> {
+        while let Some(peer_id) = self.pending_dials.pop_front() {
+            if self.pending_runs.contains_key(&peer_id) {
+                return Poll::Ready(DialOpts::new(None, Some(peer_id)));
+            }
+        }
+        self.waker = Some(cx.waker().clone());
+        Poll::Pending
+    }
+}
@@ -0,0 +1,134 @@
+//! 性能测试协议
+//!
+//! 客户端为每次测试打开一个 `/v1/perf` 子流，运行上传、下载或往返时延测试，服务端按请求
+//! 接收或发送数据。测试与传输和多路复用无关，可用于比较不同组合的吞吐量与时延。
+//! [`client`] 通过 [`client::Event`] 上报测试结果，[`server`] 上报应答过的测试。
+
+pub mod client;
+mod protocol;
+pub mod server;
+
+pub use protocol::PROTOCOL_NAME;
+
+use std::{
+    fmt, io,
+    sync::atomic::{AtomicUsize, Ordering},
+    time::Duration,
+};
+
+static NEXT_RUN_ID: AtomicUsize = AtomicUsize::new(0);
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct RunId(usize);
+
+impl RunId {
+    pub(crate) fn next() -> Self {
+        RunId(NEXT_RUN_ID.fetch_add(1, Ordering::SeqCst))
+    }
+}
+
+impl fmt::Display for RunId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// 一次测试的内容
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Benchmark {
+    /// 向服务端发送指定字节数
+    Upload(u64),
+    /// 从服务端接收指定字节数
+    Download(u64),
+    /// 在同一个子流上往返指定轮数
+    Latency(u32),
+}
+
+#[derive(Debug, Clone)]
+pub struct Config {
+    timeout: Duration,
+    max_concurrent_runs: usize,
+    max_bytes: u64,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            timeout: Duration::from_secs(60),
+            max_concurrent_runs: 10,
+            max_bytes: 1 << 30,
+        }
+    }
+}
+
+impl Config {
+    /// 单次测试的超时时间，包括子流协商
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// 每个连接上同时进行的测试上限
+    pub fn with_max_concurrent_runs(mut self, count: usize) -> Self {
+        self.max_concurrent_runs = count.max(1);
+        self
+    }
+
+    /// 服务端单次测试接收或发送的最大字节数，默认为 1 GiB
+    pub fn with_max_bytes(mut self, bytes: u64) -> Self {
+        self.max_bytes = bytes;
+        self
+    }
+}
+
+/// 一次测试的结果
+#[derive(Debug, Clone, PartialEq)]
+pub struct Report {
+    pub benchmark: Benchmark,
+    /// 发送的测试数据字节数，不含请求头
+    pub sent: u64,
+    /// 接收的测试数据字节数
+    pub received: u64,
+    /// 从发出请求到测试结束的时长
+    pub duration: Duration,
+    /// 每轮的往返时延，只有 [`Benchmark::Latency`] 会填充
+    pub rtts: Vec<Duration>,
+}
+
+impl Report {
+    /// 每秒传输的测试数据字节数
+    pub fn throughput(&self) -> f64 {
+        let secs = self.duration.as_secs_f64();
+        if secs == 0.0 {
+            return 0.0;
+        }
+        (self.sent + self.received) as f64 / secs
+    }
+
+    pub fn min_rtt(&self) -> Option<Duration> {
+        self.rtts.iter().min().copied()
+    }
+
+    pub fn avg_rtt(&self) -> Option<Duration> {
+        if self.rtts.is_empty() {
+            return None;
+        }
+        Some(self.rtts.iter().sum::<Duration>() / self.rtts.len() as u32)
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum Failure {
+    #[error("Perf run timeout")]
+    Timeout,
+    #[error("Perf protocol not supported")]
+    Unsupported,
+    #[error("Perf run of {bytes} bytes exceeds limit")]
+    TooLarge { bytes: u64 },
+    #[error("Failed to dial peer")]
+    Dial,
+    #[error("Connection closed during perf run")]
+    ConnectionClosed,
+    #[error("Perf io error: {0}")]
+    Io(#[from] io::Error),
+}
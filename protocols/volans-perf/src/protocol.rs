@@ -0,0 +1,217 @@
+use std::{
+    io,
+    time::{Duration, Instant},
+};
+
+use futures::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use volans_swarm::StreamProtocol;
+
+use crate::{Benchmark, Failure, Report};
+
+pub const PROTOCOL_NAME: StreamProtocol = StreamProtocol::new("/v1/perf");
+
+const KIND_UPLOAD: u8 = 0;
+const KIND_DOWNLOAD: u8 = 1;
+const KIND_LATENCY: u8 = 2;
+/// 请求头为 1 字节测试类型加 8 字节大端数值
+const HEADER_LEN: usize = 9;
+const CHUNK_SIZE: usize = 64 * 1024;
+
+impl Benchmark {
+    fn encode(&self) -> [u8; HEADER_LEN] {
+        let (kind, value) = match *self {
+            Benchmark::Upload(bytes) => (KIND_UPLOAD, bytes),
+            Benchmark::Download(bytes) => (KIND_DOWNLOAD, bytes),
+            Benchmark::Latency(rounds) => (KIND_LATENCY, u64::from(rounds)),
+        };
+        let mut header = [0u8; HEADER_LEN];
+        header[0] = kind;
+        header[1..].copy_from_slice(&value.to_be_bytes());
+        header
+    }
+
+    fn decode(header: [u8; HEADER_LEN]) -> io::Result<Self> {
+        let value = u64::from_be_bytes(header[1..].try_into().expect("8 bytes"));
+        match header[0] {
+            KIND_UPLOAD => Ok(Benchmark::Upload(value)),
+            KIND_DOWNLOAD => Ok(Benchmark::Download(value)),
+            KIND_LATENCY => u32::try_from(value)
+                .map(Benchmark::Latency)
+                .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "too many perf rounds")),
+            kind => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("unknown perf benchmark {kind}"),
+            )),
+        }
+    }
+}
+
+async fn send_bytes<S>(stream: &mut S, mut bytes: u64) -> io::Result<()>
+where
+    S: AsyncWrite + Unpin,
+{
+    let chunk = [0u8; CHUNK_SIZE];
+    while bytes > 0 {
+        let len = bytes.min(CHUNK_SIZE as u64) as usize;
+        stream.write_all(&chunk[..len]).await?;
+        bytes -= len as u64;
+    }
+    stream.flush().await
+}
+
+/// 读取到流结束，返回读取的字节数
+async fn drain<S>(stream: &mut S) -> io::Result<u64>
+where
+    S: AsyncRead + Unpin,
+{
+    let mut chunk = vec![0u8; CHUNK_SIZE];
+    let mut total = 0;
+    loop {
+        match stream.read(&mut chunk).await? {
+            0 => return Ok(total),
+            n => total += n as u64,
+        }
+    }
+}
+
+fn check_len(expected: u64, actual: u64) -> io::Result<()> {
+    if expected != actual {
+        return Err(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            format!("expected {expected} perf bytes, got {actual}"),
+        ));
+    }
+    Ok(())
+}
+
+/// 发起一次测试
+///
+/// 上传时服务端读到流结束后以 8 字节大端数值回复收到的字节数，下载时服务端发送指定字节数后
+/// 关闭子流，往返测试每轮发送 8 字节的轮次序号并等待服务端原样返回。
+pub(crate) async fn send_run<S>(mut stream: S, benchmark: Benchmark) -> io::Result<Report>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let started = Instant::now();
+    stream.write_all(&benchmark.encode()).await?;
+    let mut report = Report {
+        benchmark,
+        sent: 0,
+        received: 0,
+        duration: Duration::ZERO,
+        rtts: Vec::new(),
+    };
+    match benchmark {
+        Benchmark::Upload(bytes) => {
+            send_bytes(&mut stream, bytes).await?;
+            stream.close().await?;
+            let mut ack = [0u8; 8];
+            stream.read_exact(&mut ack).await?;
+            check_len(bytes, u64::from_be_bytes(ack))?;
+            report.sent = bytes;
+        }
+        Benchmark::Download(bytes) => {
+            stream.close().await?;
+            let received = drain(&mut stream).await?;
+            check_len(bytes, received)?;
+            report.received = bytes;
+        }
+        Benchmark::Latency(rounds) => {
+            stream.flush().await?;
+            let mut echo = [0u8; 8];
+            for round in 0..u64::from(rounds) {
+                let round_started = Instant::now();
+                stream.write_all(&round.to_be_bytes()).await?;
+                stream.flush().await?;
+                stream.read_exact(&mut echo).await?;
+                if u64::from_be_bytes(echo) != round {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        "perf round mismatch",
+                    ));
+                }
+                report.rtts.push(round_started.elapsed());
+            }
+            stream.close().await?;
+            report.sent = u64::from(rounds) * 8;
+            report.received = report.sent;
+        }
+    }
+    report.duration = started.elapsed();
+    Ok(report)
+}
+
+/// 应答一次测试，请求的字节数超过 `max_bytes` 时不应答
+pub(crate) async fn recv_run<S>(mut stream: S, max_bytes: u64) -> Result<Report, Failure>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let mut header = [0u8; HEADER_LEN];
+    stream.read_exact(&mut header).await?;
+    let started = Instant::now();
+    let benchmark = Benchmark::decode(header)?;
+    let mut report = Report {
+        benchmark,
+        sent: 0,
+        received: 0,
+        duration: Duration::ZERO,
+        rtts: Vec::new(),
+    };
+    match benchmark {
+        Benchmark::Upload(bytes) | Benchmark::Download(bytes) if bytes > max_bytes => {
+            return Err(Failure::TooLarge { bytes });
+        }
+        Benchmark::Upload(_) => {
+            report.received = drain(&mut stream).await?;
+            stream.write_all(&report.received.to_be_bytes()).await?;
+        }
+        Benchmark::Download(bytes) => {
+            send_bytes(&mut stream, bytes).await?;
+            report.sent = bytes;
+        }
+        Benchmark::Latency(rounds) => {
+            let mut echo = [0u8; 8];
+            for _ in 0..rounds {
+                stream.read_exact(&mut echo).await?;
+                stream.write_all(&echo).await?;
+                stream.flush().await?;
+            }
+            report.sent = u64::from(rounds) * 8;
+            report.received = report.sent;
+        }
+    }
+    stream.close().await?;
+    report.duration = started.elapsed();
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::{executor::block_on, io::Cursor};
+
+    use super::*;
+
+    #[test]
+    fn decode_header() {
+        for benchmark in [
+            Benchmark::Upload(1 << 40),
+            Benchmark::Download(0),
+            Benchmark::Latency(u32::MAX),
+        ] {
+            assert_eq!(Benchmark::decode(benchmark.encode()).unwrap(), benchmark);
+        }
+        let mut header = Benchmark::Latency(1).encode();
+        header[1] = 1;
+        assert!(Benchmark::decode(header).is_err());
+        header[0] = 9;
+        assert!(Benchmark::decode(header).is_err());
+    }
+
+    #[test]
+    fn reject_too_large() {
+        let mut request = Benchmark::Download(1024).encode().to_vec();
+        request.extend_from_slice(&[0; 8]);
+        let result = block_on(recv_run(Cursor::new(request), 512));
+        assert!(matches!(result, Err(Failure::TooLarge { bytes: 1024 })));
+    }
+}
@@ -0,0 +1,39 @@
+use futures::StreamExt;
+use volans_swarm::{client, server};
+use volans_swarm_test::{SwarmExt, listen, next_behavior_event};
+
+#[tokio::test(flavor = "current_thread")]
+async fn run_perf_benchmarks() {
+    use volans_perf::Benchmark;
+
+    let mut dialer = client::Swarm::new_ephemeral(|_| volans_perf::client::Behavior::default());
+    let mut listener = server::Swarm::new_ephemeral(|_| volans_perf::server::Behavior::default());
+    let addr = listen(&mut listener).await;
+    let listener_peer = *listener.local_peer_id();
+    dialer.peer_store_mut().add_address(listener_peer, addr);
+    tokio::spawn(async move {
+        loop {
+            listener.next().await;
+        }
+    });
+
+    // 第一次测试触发拨号
+    for benchmark in [
+        Benchmark::Upload(1024 * 1024),
+        Benchmark::Download(1024 * 1024),
+        Benchmark::Latency(10),
+    ] {
+        let run_id = dialer.behavior_mut().perf(listener_peer, benchmark);
+        let event = next_behavior_event(&mut dialer).await;
+        assert_eq!(event.run_id, run_id);
+        assert_eq!(event.peer_id, listener_peer);
+        let report = event.result.unwrap();
+        assert_eq!(report.benchmark, benchmark);
+        match benchmark {
+            Benchmark::Upload(bytes) => assert_eq!(report.sent, bytes),
+            Benchmark::Download(bytes) => assert_eq!(report.received, bytes),
+            Benchmark::Latency(rounds) => assert_eq!(report.rtts.len(), rounds as usize),
+        }
+        assert!(report.throughput() > 0.0);
+    }
+}
@@ -0,0 +1,183 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    task::{Context, Poll, Waker},
+};
+
+use volans_core::{Endpoint, Multiaddr, PeerId};
+use volans_swarm::{
+    BehaviorEvent, ConnectionDenied, ConnectionId, NetworkBehavior, NetworkIncomingBehavior,
+    NetworkOutgoingBehavior, THandlerAction, THandlerEvent, error::CloseReason,
+};
+
+use crate::{Config, Event, Handler, RttStats};
+
+/// 同时拨号与监听的 Ping 行为，拨出的连接发送 Ping，接入的连接应答
+pub struct Behavior {
+    config: Config,
+    connections: HashMap<ConnectionId, (PeerId, Endpoint)>,
+    stats: HashMap<PeerId, RttStats>,
+    events: VecDeque<Event>,
+    waker: Option<Waker>,
+}
+
+impl Behavior {
+    pub fn new(config: Config) -> Self {
+        Self {
+            config,
+            connections: HashMap::new(),
+            stats: HashMap::new(),
+            events: VecDeque::new(),
+            waker: None,
+        }
+    }
+
+    /// 已连接节点的往返时延统计
+    pub fn rtt(&self, peer_id: &PeerId) -> Option<&RttStats> {
+        self.stats.get(peer_id)
+    }
+
+    fn on_connection_closed(&mut self, id: ConnectionId, peer_id: PeerId) {
+        self.connections.remove(&id);
+        if !self.connections.values().any(|(p, _)| *p == peer_id) {
+            self.stats.remove(&peer_id);
+        }
+    }
+}
+
+impl Default for Behavior {
+    fn default() -> Self {
+        Self::new(Config::default())
+    }
+}
+
+impl NetworkBehavior for Behavior {
+    type ConnectionHandler = Handler;
+    type Event = Event;
+
+    fn on_connection_handler_event(
+        &mut self,
+        id: ConnectionId,
+        peer_id: PeerId,
+        event: THandlerEvent<Self>,
+    ) {
+        // 监听端的间隔是两次 Ping 之间的时长，不计入往返时延
+        let dialer = matches!(self.connections.get(&id), Some((_, Endpoint::Dialer)));
+        let stats = match &event {
+            Ok(rtt) if dialer => {
+                let stats = self
+                    .stats
+                    .entry(peer_id)
+                    .and_modify(|stats| stats.record(*rtt))
+                    .or_insert_with(|| RttStats::new(*rtt));
+                Some(*stats)
+            }
+            _ => None,
+        };
+        self.events.push_back(Event {
+            connection: id,
+            peer_id,
+            result: event,
+            stats,
+        });
+        if let Some(waker) = self.waker.take() {
+            waker.wake();
+        }
+    }
+
+    fn poll(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<BehaviorEvent<Self::Event, THandlerAction<Self>>> {
+        if let Some(event) = self.events.pop_front() {
+            return Poll::Ready(BehaviorEvent::Behavior(event));
+        }
+        self.waker = Some(cx.waker().clone());
+        Poll::Pending
+    }
+}
+
+impl NetworkIncomingBehavior for Behavior {
+    fn handle_established_connection(
+        &mut self,
+        _id: ConnectionId,
+        _peer_id: PeerId,
+        _local_addr: &Multiaddr,
+        _remote_addr: &Multiaddr,
+    ) -> Result<Self::ConnectionHandler, ConnectionDenied> {
+        Ok(Handler::new(Endpoint::Listener, self.config.clone()))
+    }
+
+    fn on_connection_established(
+        &mut self,
+        id: ConnectionId,
+        peer_id: PeerId,
+        _local_addr: &Multiaddr,
+        _remote_addr: &Multiaddr,
+    ) {
+        self.connections.insert(id, (peer_id, Endpoint::Listener));
+    }
+
+    fn on_connection_closed(
+        &mut self,
+        id: ConnectionId,
+        peer_id: PeerId,
+        _local_addr: &Multiaddr,
+        _remote_addr: &Multiaddr,
+        _reason: &CloseReason,
+    ) {
+        Behavior::on_connection_closed(self, id, peer_id);
+    }
+}
+
+impl NetworkOutgoingBehavior for Behavior {
+    fn handle_established_connection(
+        &mut self,
+        _id: ConnectionId,
+        _peer_id: PeerId,
+        _addr: &Multiaddr,
+    ) -> Result<Self::ConnectionHandler, ConnectionDenied> {
+        Ok(Handler::new(Endpoint::Dialer, self.config.clone()))
+    }
+
+    fn handle_pending_connection(
+        &mut self,
+        _id: ConnectionId,
+        _maybe_peer: Option<PeerId>,
+        addr: &Option<Multiaddr>,
+    ) -> Result<Option<Multiaddr>, ConnectionDenied> {
+        Ok(addr.clone())
+    }
+
+    fn on_connection_established(&mut self, id: ConnectionId, peer_id: PeerId, _addr: &Multiaddr) {
+        self.connections.insert(id, (peer_id, Endpoint::Dialer));
+    }
+
+    fn on_connection_closed(
+        &mut self,
+        id: ConnectionId,
+        peer_id: PeerId,
+        _addr: &Multiaddr,
+        _reason: &CloseReason,
+    ) {
+        Behavior::on_connection_closed(self, id, peer_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn rtt_stats() {
+        let mut stats = RttStats::new(Duration::from_millis(80));
+        stats.record(Duration::from_millis(40));
+        stats.record(Duration::from_millis(120));
+
+        assert_eq!(stats.min, Duration::from_millis(40));
+        assert_eq!(stats.avg, Duration::from_millis(80));
+        assert_eq!(stats.samples, 3);
+        assert_eq!(stats.ewma, Duration::from_micros(80_625));
+    }
+}
@@ -0,0 +1,128 @@
+use std::{
+    convert::Infallible,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use volans_core::{Endpoint, upgrade::ReadyUpgrade};
+use volans_swarm::{
+    ConnectionHandler, ConnectionHandlerEvent, InboundStreamHandler, InboundUpgradeSend, KeepAlive,
+    OutboundStreamHandler, OutboundUpgradeSend, StreamProtocol, StreamUpgradeError,
+    SubstreamProtocol,
+};
+
+use crate::{Config, Failure, inbound, outbound, protocol};
+
+/// 拨号端周期性发送 Ping，监听端应答
+pub enum Handler {
+    Dialer(outbound::Handler),
+    Listener(inbound::Handler),
+}
+
+impl Handler {
+    pub fn new(endpoint: Endpoint, config: Config) -> Self {
+        match endpoint {
+            Endpoint::Dialer => Handler::Dialer(outbound::Handler::new(config)),
+            Endpoint::Listener => Handler::Listener(inbound::Handler::new(config)),
+        }
+    }
+}
+
+impl ConnectionHandler for Handler {
+    type Action = Infallible;
+    type Event = Result<Duration, Failure>;
+
+    fn handle_action(&mut self, action: Self::Action) {
+        match action {}
+    }
+
+    fn keep_alive(&self) -> KeepAlive {
+        match self {
+            Handler::Dialer(handler) => handler.keep_alive(),
+            Handler::Listener(handler) => handler.keep_alive(),
+        }
+    }
+
+    fn poll_close(&mut self, cx: &mut Context<'_>) -> Poll<Option<Self::Event>> {
+        match self {
+            Handler::Dialer(handler) => handler.poll_close(cx),
+            Handler::Listener(handler) => handler
+                .poll_close(cx)
+                .map(|event| event.map(|result| result.map(|ping| ping.interval))),
+        }
+    }
+
+    fn poll(&mut self, cx: &mut Context<'_>) -> Poll<ConnectionHandlerEvent<Self::Event>> {
+        match self {
+            Handler::Dialer(handler) => handler.poll(cx),
+            Handler::Listener(handler) => handler
+                .poll(cx)
+                .map(|event| event.map_event(|result| result.map(|ping| ping.interval))),
+        }
+    }
+}
+
+impl InboundStreamHandler for Handler {
+    type InboundUpgrade = ReadyUpgrade<StreamProtocol>;
+    type InboundUserData = ();
+
+    fn listen_protocol(&self) -> SubstreamProtocol<Self::InboundUpgrade, Self::InboundUserData> {
+        SubstreamProtocol::new(ReadyUpgrade::new(protocol::PROTOCOL_NAME), ())
+    }
+
+    fn on_fully_negotiated(
+        &mut self,
+        user_data: Self::InboundUserData,
+        protocol: <Self::InboundUpgrade as InboundUpgradeSend>::Output,
+    ) {
+        match self {
+            Handler::Listener(handler) => handler.on_fully_negotiated(user_data, protocol),
+            Handler::Dialer(_) => tracing::debug!("Dropping inbound ping stream on dialer"),
+        }
+    }
+
+    fn on_upgrade_error(
+        &mut self,
+        user_data: Self::InboundUserData,
+        error: <Self::InboundUpgrade as InboundUpgradeSend>::Error,
+    ) {
+        if let Handler::Listener(handler) = self {
+            handler.on_upgrade_error(user_data, error);
+        }
+    }
+}
+
+impl OutboundStreamHandler for Handler {
+    type OutboundUpgrade = ReadyUpgrade<StreamProtocol>;
+    type OutboundUserData = ();
+
+    fn on_fully_negotiated(
+        &mut self,
+        user_data: Self::OutboundUserData,
+        protocol: <Self::OutboundUpgrade as OutboundUpgradeSend>::Output,
+    ) {
+        if let Handler::Dialer(handler) = self {
+            handler.on_fully_negotiated(user_data, protocol);
+        }
+    }
+
+    fn on_upgrade_error(
+        &mut self,
+        user_data: Self::OutboundUserData,
+        error: StreamUpgradeError<<Self::OutboundUpgrade as OutboundUpgradeSend>::Error>,
+    ) {
+        if let Handler::Dialer(handler) = self {
+            handler.on_upgrade_error(user_data, error);
+        }
+    }
+
+    fn poll_outbound_request(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<SubstreamProtocol<Self::OutboundUpgrade, Self::OutboundUserData>> {
+        match self {
+            Handler::Dialer(handler) => handler.poll_outbound_request(cx),
+            Handler::Listener(_) => Poll::Pending,
+        }
+    }
+}
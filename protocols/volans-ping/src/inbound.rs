@@ -7,22 +7,37 @@ use std::{
 };
 
 use futures::{FutureExt, future::BoxFuture};
-use futures_timer::Delay;
-use volans_core::{PeerId, Multiaddr, upgrade::ReadyUpgrade};
+use volans_core::{Multiaddr, PeerId, upgrade::ReadyUpgrade};
 use volans_swarm::{
     BehaviorEvent, ConnectionDenied, ConnectionHandler, ConnectionHandlerEvent, ConnectionId,
     InboundStreamHandler, InboundUpgradeSend, NetworkBehavior, NetworkIncomingBehavior,
-    StreamProtocol, Substream, SubstreamProtocol, THandlerAction, THandlerEvent,
+    StreamProtocol, Substream, SubstreamProtocol, THandlerAction, THandlerEvent, timer::Delay,
 };
 
-use crate::{Config, Event, Failure, protocol};
+use crate::{Config, Failure, protocol};
 
-type PongFuture = BoxFuture<'static, Result<Substream, io::Error>>;
+type PongFuture = BoxFuture<'static, Result<(Substream, usize), io::Error>>;
+
+/// 应答的一次 Ping
+#[derive(Debug, Clone, Copy)]
+pub struct InboundPing {
+    /// 距上次 Ping 的时间
+    pub interval: Duration,
+    /// 往返时延估计，即距上次应答的时间减去 [`Config`] 中的 Ping 间隔，
+    /// 假设对端使用相同的间隔，流上的第一次 Ping 没有估计值
+    pub rtt: Option<Duration>,
+    /// 负载字节数
+    pub bytes: usize,
+}
 
 pub struct Handler {
     interval: Delay,
     config: Config,
     last_ping: Instant,
+    /// 当前流上次应答的时间
+    last_pong: Option<Instant>,
+    /// 限速窗口内应答 Ping 的时间
+    recent_pings: VecDeque<Instant>,
     failed: bool,
     inbound: Option<PongFuture>,
     pending_errors: VecDeque<Failure>,
@@ -34,17 +49,41 @@ impl Handler {
             interval: Delay::new(config.interval * config.failures),
             config,
             last_ping: Instant::now(),
+            last_pong: None,
+            recent_pings: VecDeque::new(),
             failed: false,
             inbound: None,
             pending_errors: VecDeque::new(),
         }
     }
+
+    /// 检查负载大小与应答频率
+    fn check_limits(&mut self, bytes: usize, now: Instant) -> Result<(), Failure> {
+        if bytes > self.config.max_payload_size {
+            return Err(Failure::PayloadTooLarge { size: bytes });
+        }
+        let Some((max, per)) = self.config.max_inbound_rate else {
+            return Ok(());
+        };
+        while self
+            .recent_pings
+            .front()
+            .is_some_and(|at| now.duration_since(*at) >= per)
+        {
+            self.recent_pings.pop_front();
+        }
+        self.recent_pings.push_back(now);
+        if self.recent_pings.len() > max as usize {
+            return Err(Failure::RateExceeded);
+        }
+        Ok(())
+    }
 }
 
 impl ConnectionHandler for Handler {
     type Action = Infallible;
 
-    type Event = Result<Duration, Failure>;
+    type Event = Result<InboundPing, Failure>;
 
     fn handle_action(&mut self, _action: Self::Action) {
         unreachable!("Ping handler does not support actions");
@@ -70,17 +109,35 @@ impl ConnectionHandler for Handler {
             if let Some(fut) = self.inbound.as_mut() {
                 match fut.poll_unpin(cx) {
                     Poll::Pending => {}
-                    Poll::Ready(Ok(substream)) => {
+                    Poll::Ready(Ok((substream, bytes))) => {
+                        let now = Instant::now();
+                        if let Err(failure) = self.check_limits(bytes, now) {
+                            tracing::debug!("Closing connection, {}", failure);
+                            self.inbound = None;
+                            self.failed = true;
+                            self.pending_errors.push_back(failure);
+                            continue;
+                        }
                         //重新开始新的延迟
-                        self.inbound = Some(protocol::recv_ping(substream).boxed());
+                        self.inbound = Some(
+                            protocol::recv_ping(substream, self.config.max_payload_size).boxed(),
+                        );
                         // 重置为新的周期间隔
                         self.interval
                             .reset(self.config.interval * self.config.failures);
 
-                        let elapsed = self.last_ping.elapsed();
-                        self.last_ping = Instant::now();
+                        let ping = InboundPing {
+                            interval: now.duration_since(self.last_ping),
+                            rtt: self.last_pong.map(|pong| {
+                                now.duration_since(pong)
+                                    .saturating_sub(self.config.interval)
+                            }),
+                            bytes,
+                        };
+                        self.last_ping = now;
+                        self.last_pong = Some(now);
 
-                        return Poll::Ready(ConnectionHandlerEvent::Notify(Ok(elapsed)));
+                        return Poll::Ready(ConnectionHandlerEvent::Notify(Ok(ping)));
                     }
                     Poll::Ready(Err(err)) => {
                         self.inbound = None;
@@ -123,8 +180,9 @@ impl InboundStreamHandler for Handler {
         _user_data: Self::InboundUserData,
         protocol: <Self::InboundUpgrade as InboundUpgradeSend>::Output,
     ) {
-        self.inbound = Some(protocol::recv_ping(protocol).boxed());
+        self.inbound = Some(protocol::recv_ping(protocol, self.config.max_payload_size).boxed());
         self.last_ping = Instant::now();
+        self.last_pong = None;
     }
 
     fn on_upgrade_error(
@@ -138,6 +196,14 @@ impl InboundStreamHandler for Handler {
     }
 }
 
+#[derive(Debug)]
+pub struct Event {
+    pub connection: ConnectionId,
+    pub peer_id: PeerId,
+    pub result: Result<InboundPing, Failure>,
+}
+
+/// 应答 Ping 的行为，可通过 [`Config`] 限制每个连接的 Ping 频率与负载大小
 pub struct Behavior {
     config: Config,
     events: VecDeque<Event>,
@@ -1,7 +1,18 @@
+//! Ping 协议
+//!
+//! 拨号端按 [`Config`] 的间隔发送随机负载，监听端原样返回，用于测量往返时延并探测连接存活。
+//! [`inbound`] 与 [`outbound`] 分别用于只监听或只拨号的 Swarm，
+//! [`Behavior`] 可用于同时拨号与监听的 Swarm，并统计每个节点的往返时延。
+
+mod behavior;
+mod handler;
 pub mod inbound;
 pub mod outbound;
 mod protocol;
 
+pub use behavior::Behavior;
+pub use handler::Handler;
+
 use std::time::Duration;
 
 use volans_core::PeerId;
@@ -12,6 +23,8 @@ pub struct Config {
     timeout: Duration,
     interval: Duration,
     failures: u32,
+    max_inbound_rate: Option<(u32, Duration)>,
+    max_payload_size: usize,
 }
 
 impl Default for Config {
@@ -20,16 +33,44 @@ impl Default for Config {
             timeout: Duration::from_secs(1),
             interval: Duration::from_secs(10),
             failures: 3,
+            max_inbound_rate: None,
+            max_payload_size: protocol::PING_SIZE,
         }
     }
 }
 
+impl Config {
+    /// Ping 的发送间隔，[`inbound`] 据此估计对端的往返时延
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// [`inbound`] 在每个连接上 `per` 时间内最多应答 `max` 次 Ping，超出时关闭连接
+    pub fn with_max_inbound_rate(mut self, max: u32, per: Duration) -> Self {
+        self.max_inbound_rate = Some((max, per));
+        self
+    }
+
+    /// [`inbound`] 单次应答的最大负载，默认为标准 Ping 的 32 字节，超出时关闭连接
+    pub fn with_max_payload_size(mut self, size: usize) -> Self {
+        self.max_payload_size = size.max(protocol::PING_SIZE);
+        self
+    }
+}
+
 #[derive(Debug, thiserror::Error)]
 pub enum Failure {
     #[error("Ping timeout")]
     Timeout,
     #[error("Ping protocol not supported")]
     Unsupported,
+    #[error("Ping stream reset by remote")]
+    RemoteReset,
+    #[error("Ping rate limit exceeded")]
+    RateExceeded,
+    #[error("Ping payload of {size} bytes exceeds limit")]
+    PayloadTooLarge { size: usize },
     #[error("Ping protocol error: {error}")]
     Other {
         error: Box<dyn std::error::Error + Send + Sync + 'static>,
@@ -47,4 +88,34 @@ pub struct Event {
     pub connection: ConnectionId,
     pub peer_id: PeerId,
     pub result: Result<Duration, Failure>,
+    /// 本次 Ping 成功后该节点的往返时延统计，只有 [`Behavior`] 会填充
+    pub stats: Option<RttStats>,
+}
+
+/// 节点的往返时延统计
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RttStats {
+    pub min: Duration,
+    pub avg: Duration,
+    /// 指数加权移动平均，新样本权重为 1/8
+    pub ewma: Duration,
+    pub samples: u32,
+}
+
+impl RttStats {
+    fn new(rtt: Duration) -> Self {
+        Self {
+            min: rtt,
+            avg: rtt,
+            ewma: rtt,
+            samples: 1,
+        }
+    }
+
+    fn record(&mut self, rtt: Duration) {
+        self.min = self.min.min(rtt);
+        self.samples = self.samples.saturating_add(1);
+        self.avg = (self.avg * (self.samples - 1) + rtt) / self.samples;
+        self.ewma = (self.ewma * 7 + rtt) / 8;
+    }
 }
@@ -1,7 +1,7 @@
 use std::{
     collections::VecDeque,
     convert::Infallible,
-    io, mem,
+    mem,
     task::{Context, Poll, Waker},
     time::Duration,
 };
@@ -10,13 +10,12 @@ use futures::{
     FutureExt,
     future::{self, BoxFuture},
 };
-use futures_timer::Delay;
-use volans_core::{PeerId, Multiaddr, upgrade::ReadyUpgrade};
+use volans_core::{Multiaddr, PeerId, upgrade::ReadyUpgrade};
 use volans_swarm::{
     BehaviorEvent, ConnectionDenied, ConnectionHandler, ConnectionHandlerEvent, ConnectionId,
-    NetworkBehavior, NetworkOutgoingBehavior, OutboundStreamHandler, OutboundUpgradeSend,
-    StreamProtocol, StreamUpgradeError, Substream, SubstreamProtocol, THandlerAction,
-    THandlerEvent,
+    KeepAlive, NetworkBehavior, NetworkOutgoingBehavior, OutboundStreamHandler,
+    OutboundUpgradeSend, StreamProtocol, StreamUpgradeError, Substream, SubstreamPriority,
+    SubstreamProtocol, THandlerAction, THandlerEvent, timer::Delay,
 };
 
 use crate::{Config, Event, Failure, protocol};
@@ -66,6 +65,14 @@ impl ConnectionHandler for Handler {
         unreachable!("Ping handler does not support actions");
     }
 
+    fn keep_alive(&self) -> KeepAlive {
+        // 等待 Ping 应答时保持连接
+        match self.outbound {
+            OutboundState::OpenStream | OutboundState::Ping(_) => KeepAlive::Yes,
+            OutboundState::None | OutboundState::Idle(_) => KeepAlive::Idle,
+        }
+    }
+
     fn poll_close(&mut self, _: &mut Context<'_>) -> Poll<Option<Self::Event>> {
         if let Some(error) = self.pending_errors.pop_back() {
             return Poll::Ready(Some(Err(error)));
@@ -157,11 +164,9 @@ impl OutboundStreamHandler for Handler {
         self.outbound = OutboundState::None;
         self.interval.reset(Duration::new(0, 0));
         let error = match error {
-            StreamUpgradeError::Timeout => Failure::other(io::Error::new(
-                io::ErrorKind::TimedOut,
-                "Ping protocol negotiation timed out",
-            )),
-            StreamUpgradeError::NegotiationFailed => {
+            StreamUpgradeError::Timeout => Failure::Timeout,
+            StreamUpgradeError::RemoteReset => Failure::RemoteReset,
+            StreamUpgradeError::NegotiationFailed { .. } => {
                 debug_assert_eq!(self.state, State::Active);
                 self.state = State::Inactive { reported: false };
                 return;
@@ -177,18 +182,19 @@ impl OutboundStreamHandler for Handler {
         &mut self,
         cx: &mut Context<'_>,
     ) -> Poll<SubstreamProtocol<Self::OutboundUpgrade, Self::OutboundUserData>> {
-        match self.outbound {
-            OutboundState::None => match self.interval.poll_unpin(cx) {
+        if let OutboundState::None = self.outbound {
+            match self.interval.poll_unpin(cx) {
                 Poll::Pending => {}
                 Poll::Ready(()) => {
                     // 首次间隔到达， State: None -> OpenStream
                     self.outbound = OutboundState::OpenStream;
+                    // 负载较高时优先打开，避免误判连接失效
                     let protocol =
-                        SubstreamProtocol::new(ReadyUpgrade::new(protocol::PROTOCOL_NAME), ());
+                        SubstreamProtocol::new(ReadyUpgrade::new(protocol::PROTOCOL_NAME), ())
+                            .with_priority(SubstreamPriority::High);
                     return Poll::Ready(protocol);
                 }
-            },
-            _ => {}
+            }
         }
         Poll::Pending
     }
@@ -230,6 +236,7 @@ impl NetworkBehavior for Behavior {
             peer_id,
             connection: id,
             result: event,
+            stats: None,
         });
         if let Some(waker) = self.none_event_waker.take() {
             waker.wake();
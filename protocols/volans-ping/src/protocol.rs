@@ -3,24 +3,34 @@ use std::{
     time::{Duration, Instant},
 };
 
-use volans_swarm::StreamProtocol;
 use futures::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use volans_swarm::StreamProtocol;
 
 pub const PROTOCOL_NAME: StreamProtocol = StreamProtocol::new("/v1/ping");
 
-const PING_SIZE: usize = 32;
+pub(crate) const PING_SIZE: usize = 32;
 
-pub(crate) async fn recv_ping<S>(mut stream: S) -> io::Result<S>
+/// 读取至少一个标准 Ping 的负载并原样返回，返回读取的字节数
+///
+/// 负载超过 `max_size` 时不应答，返回的字节数为 `max_size + 1`。
+pub(crate) async fn recv_ping<S>(mut stream: S, max_size: usize) -> io::Result<(S, usize)>
 where
     S: AsyncRead + AsyncWrite + Unpin,
 {
-    let mut payload = [0u8; PING_SIZE];
-
-    stream.read_exact(&mut payload).await?;
-
-    stream.write_all(&payload).await?;
+    let mut payload = vec![0u8; max_size + 1];
+    let mut len = 0;
+    while len < PING_SIZE {
+        match stream.read(&mut payload[len..]).await? {
+            0 => return Err(io::ErrorKind::UnexpectedEof.into()),
+            n => len += n,
+        }
+    }
+    if len > max_size {
+        return Ok((stream, len));
+    }
+    stream.write_all(&payload[..len]).await?;
     stream.flush().await?;
-    Ok(stream)
+    Ok((stream, len))
 }
 
 pub(crate) async fn send_ping<S>(mut stream: S) -> io::Result<(S, Duration)>
@@ -0,0 +1,117 @@
+use futures::StreamExt;
+use volans_swarm::{duplex, server};
+use volans_swarm_test::{SwarmExt, connect, next_behavior_event, wait_for_event};
+
+#[tokio::test(flavor = "current_thread")]
+async fn limit_inbound_ping_rate() {
+    use std::time::Duration;
+
+    use volans_ping::{Behavior, Config, Failure, inbound};
+
+    let config = Config::default().with_interval(Duration::from_millis(20));
+    let mut dialer = duplex::Swarm::new_ephemeral(|_| Behavior::new(config.clone()));
+    let mut listener = server::Swarm::new_ephemeral(|_| {
+        inbound::Behavior::new(
+            config
+                .clone()
+                .with_max_inbound_rate(2, Duration::from_secs(60)),
+        )
+    });
+    connect(&mut dialer, &mut listener).await;
+    let dialer_peer = *dialer.local_peer_id();
+    tokio::spawn(async move {
+        loop {
+            dialer.next().await;
+        }
+    });
+
+    let first = next_behavior_event(&mut listener).await;
+    assert_eq!(first.peer_id, dialer_peer);
+    let first = first.result.unwrap();
+    assert_eq!(first.bytes, 32);
+    assert!(first.rtt.is_none());
+    let second = next_behavior_event(&mut listener).await.result.unwrap();
+    assert!(second.rtt.is_some());
+    assert!(matches!(
+        next_behavior_event(&mut listener).await.result,
+        Err(Failure::RateExceeded)
+    ));
+    wait_for_event(&mut listener, |event| match event {
+        server::SwarmEvent::ConnectionClosed { peer_id, .. } => Some(peer_id),
+        _ => None,
+    })
+    .await;
+}
+
+#[test]
+fn ping_on_simulated_interval() {
+    use std::time::Duration;
+
+    use volans_ping::{Behavior, Config, inbound};
+    use volans_swarm_test::sim::{self, SimSwarm, Simulation};
+
+    let sim = Simulation::new();
+    let config = Config::default().with_interval(Duration::from_secs(10));
+    let mut dialer: SimSwarm<duplex::Swarm<_>> =
+        SimSwarm::new(&sim, |_| Behavior::new(config.clone()));
+    let mut listener: SimSwarm<server::Swarm<_>> =
+        SimSwarm::new(&sim, |_| inbound::Behavior::new(config.clone()));
+    sim::connect(&sim, &mut dialer, &mut listener);
+
+    sim::advance(
+        &sim,
+        &mut [&mut dialer, &mut listener],
+        Duration::from_secs(9),
+    );
+    assert!(dialer.take_behavior_events().is_empty());
+    sim::advance(
+        &sim,
+        &mut [&mut dialer, &mut listener],
+        Duration::from_secs(1),
+    );
+    let pings = dialer.take_behavior_events();
+    assert_eq!(pings.len(), 1);
+    assert!(pings[0].result.is_ok());
+    // 之后每个间隔恰好一次
+    sim::advance(
+        &sim,
+        &mut [&mut dialer, &mut listener],
+        Duration::from_secs(25),
+    );
+    assert_eq!(dialer.take_behavior_events().len(), 2);
+    assert_eq!(listener.take_behavior_events().len(), 3);
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn report_stream_reset_by_remote() {
+    use std::time::Duration;
+
+    use volans_ping::{Behavior, Config, Failure, inbound};
+    use volans_swarm::connection::PoolConfig;
+    use volans_swarm_test::{ephemeral_key_pair, ephemeral_parts_with_muxer};
+
+    let config = Config::default().with_interval(Duration::from_millis(20));
+    // 对端的多路复用器丢弃所有入站子流，协商期间子流被重置
+    let mut muxer = volans_muxing::Config::new();
+    muxer
+        .set_max_buffered_inbound_streams(0)
+        .set_inbound_overflow(volans_muxing::InboundOverflow::Drop);
+    let (transport, listener_peer) = ephemeral_parts_with_muxer(&ephemeral_key_pair(), muxer);
+    let mut listener = server::Swarm::from_parts(
+        transport,
+        inbound::Behavior::new(config.clone()),
+        listener_peer,
+        PoolConfig::with_tokio_executor(),
+    );
+    let mut dialer = duplex::Swarm::new_ephemeral(|_| Behavior::new(config.clone()));
+    connect(&mut dialer, &mut listener).await;
+    tokio::spawn(async move {
+        loop {
+            listener.next().await;
+        }
+    });
+
+    let event = next_behavior_event(&mut dialer).await;
+    assert_eq!(event.peer_id, listener_peer);
+    assert!(matches!(event.result, Err(Failure::RemoteReset)));
+}
@@ -0,0 +1,545 @@
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    task::{Context, Poll, Waker},
+    time::Instant,
+};
+
+use futures::FutureExt;
+use futures_timer::Delay;
+use rand::seq::SliceRandom;
+use volans_core::{Endpoint, Multiaddr, PeerId, identity::KeyPair};
+use volans_swarm::{
+    BehaviorEvent, ConnectionDenied, ConnectionId, NetworkBehavior, NetworkIncomingBehavior,
+    NetworkOutgoingBehavior, THandlerAction, THandlerEvent, behavior::NotifyHandler,
+    error::CloseReason,
+};
+
+use crate::{
+    Config, Event, Handler, MessageId, PublishError, Topic,
+    protocol::{self, v1},
+};
+
+#[derive(Default)]
+struct PeerState {
+    connections: Vec<ConnectionId>,
+    topics: HashSet<Topic>,
+}
+
+pub struct Behavior {
+    keypair: KeyPair,
+    local_peer_id: PeerId,
+    config: Config,
+    sequence_number: u64,
+    /// 本地订阅的主题及其 Mesh 节点
+    mesh: HashMap<Topic, HashSet<PeerId>>,
+    peers: HashMap<PeerId, PeerState>,
+    seen: HashSet<MessageId>,
+    seen_order: VecDeque<(Instant, MessageId)>,
+    heartbeat: Delay,
+    pending_events: VecDeque<BehaviorEvent<Event, v1::Rpc>>,
+    waker: Option<Waker>,
+}
+
+impl Behavior {
+    pub fn new(keypair: KeyPair, config: Config) -> Self {
+        let local_peer_id = PeerId::from_public_key(&keypair.verifying_key());
+        Self {
+            keypair,
+            local_peer_id,
+            heartbeat: Delay::new(config.heartbeat_interval),
+            config,
+            sequence_number: rand::random(),
+            mesh: HashMap::new(),
+            peers: HashMap::new(),
+            seen: HashSet::new(),
+            seen_order: VecDeque::new(),
+            pending_events: VecDeque::new(),
+            waker: None,
+        }
+    }
+
+    /// 本地已订阅的主题
+    pub fn topics(&self) -> impl Iterator<Item = &Topic> {
+        self.mesh.keys()
+    }
+
+    /// 主题 Mesh 中的节点
+    pub fn mesh_peers(&self, topic: &Topic) -> impl Iterator<Item = &PeerId> {
+        self.mesh.get(topic).into_iter().flatten()
+    }
+
+    /// 订阅主题，已订阅时返回 `false`
+    pub fn subscribe(&mut self, topic: impl Into<Topic>) -> bool {
+        let topic = topic.into();
+        if self.mesh.contains_key(&topic) {
+            return false;
+        }
+        let mut grafted = self.subscribed_peers(&topic, &HashSet::new());
+        grafted.truncate(self.config.mesh_n);
+        let grafted = grafted.into_iter().collect::<HashSet<_>>();
+
+        let peers = self.peers.keys().copied().collect::<Vec<_>>();
+        for peer_id in peers {
+            let mut rpc = subscription_rpc(&topic, true);
+            if grafted.contains(&peer_id) {
+                rpc.control = Some(graft_control(&topic));
+            }
+            self.send(peer_id, rpc);
+        }
+        tracing::debug!("Subscribed to topic {}, mesh: {:?}", topic, grafted);
+        self.mesh.insert(topic, grafted);
+        true
+    }
+
+    /// 取消订阅主题，未订阅时返回 `false`
+    pub fn unsubscribe(&mut self, topic: &Topic) -> bool {
+        let Some(mesh) = self.mesh.remove(topic) else {
+            return false;
+        };
+        let peers = self.peers.keys().copied().collect::<Vec<_>>();
+        for peer_id in peers {
+            let mut rpc = subscription_rpc(topic, false);
+            if mesh.contains(&peer_id) {
+                rpc.control = Some(prune_control(topic));
+            }
+            self.send(peer_id, rpc);
+        }
+        true
+    }
+
+    /// 发布消息，已订阅时发送给 Mesh 节点，否则发送给订阅该主题的节点
+    pub fn publish(
+        &mut self,
+        topic: impl Into<Topic>,
+        data: impl Into<Vec<u8>>,
+    ) -> Result<MessageId, PublishError> {
+        let topic = topic.into();
+        let data = data.into();
+        if data.len() > self.config.max_transmit_size {
+            return Err(PublishError::MessageTooLarge { size: data.len() });
+        }
+
+        let recipients = match self.mesh.get(&topic) {
+            Some(mesh) if !mesh.is_empty() => mesh.iter().copied().collect::<Vec<_>>(),
+            _ => {
+                let mut peers = self.subscribed_peers(&topic, &HashSet::new());
+                peers.truncate(self.config.mesh_n);
+                peers
+            }
+        };
+        if recipients.is_empty() {
+            return Err(PublishError::InsufficientPeers(topic));
+        }
+
+        let sequence_number = self.sequence_number;
+        self.sequence_number = self.sequence_number.wrapping_add(1);
+        let message = protocol::sign_message(&self.keypair, &topic, sequence_number, data);
+        let id = MessageId::new(self.local_peer_id, sequence_number);
+        self.insert_seen(id);
+
+        for peer_id in recipients {
+            self.send(peer_id, publish_rpc(message.clone()));
+        }
+        Ok(id)
+    }
+
+    fn send(&mut self, peer_id: PeerId, rpc: v1::Rpc) {
+        self.pending_events.push_back(BehaviorEvent::HandlerAction {
+            peer_id,
+            handler: NotifyHandler::Any,
+            action: rpc,
+        });
+        if let Some(waker) = self.waker.take() {
+            waker.wake();
+        }
+    }
+
+    fn emit(&mut self, event: Event) {
+        self.pending_events
+            .push_back(BehaviorEvent::Behavior(event));
+        if let Some(waker) = self.waker.take() {
+            waker.wake();
+        }
+    }
+
+    /// 订阅了主题且不在 `exclude` 中的节点，顺序随机
+    fn subscribed_peers(&self, topic: &Topic, exclude: &HashSet<PeerId>) -> Vec<PeerId> {
+        let mut peers = self
+            .peers
+            .iter()
+            .filter(|(peer_id, state)| state.topics.contains(topic) && !exclude.contains(peer_id))
+            .map(|(peer_id, _)| *peer_id)
+            .collect::<Vec<_>>();
+        peers.shuffle(&mut rand::rng());
+        peers
+    }
+
+    fn insert_seen(&mut self, id: MessageId) -> bool {
+        if !self.seen.insert(id) {
+            return false;
+        }
+        self.seen_order.push_back((Instant::now(), id));
+        true
+    }
+
+    fn on_connection_established(&mut self, id: ConnectionId, peer_id: PeerId) {
+        let state = self.peers.entry(peer_id).or_default();
+        state.connections.push(id);
+        if state.connections.len() > 1 || self.mesh.is_empty() {
+            return;
+        }
+        let rpc = v1::Rpc {
+            subscriptions: self
+                .mesh
+                .keys()
+                .map(|topic| v1::SubOpts {
+                    subscribe: true,
+                    topic: topic.to_string(),
+                })
+                .collect(),
+            ..Default::default()
+        };
+        self.pending_events.push_back(BehaviorEvent::HandlerAction {
+            peer_id,
+            handler: NotifyHandler::One(id),
+            action: rpc,
+        });
+    }
+
+    fn on_connection_closed(&mut self, id: ConnectionId, peer_id: PeerId) {
+        let Some(state) = self.peers.get_mut(&peer_id) else {
+            return;
+        };
+        state.connections.retain(|c| *c != id);
+        if !state.connections.is_empty() {
+            return;
+        }
+        self.peers.remove(&peer_id);
+        for mesh in self.mesh.values_mut() {
+            mesh.remove(&peer_id);
+        }
+    }
+
+    fn handle_rpc(&mut self, peer_id: PeerId, rpc: v1::Rpc) {
+        for subscription in rpc.subscriptions {
+            self.handle_subscription(peer_id, subscription);
+        }
+        for message in rpc.publish {
+            self.handle_message(peer_id, message);
+        }
+        if let Some(control) = rpc.control {
+            for graft in control.graft {
+                self.handle_graft(peer_id, Topic::from(graft.topic));
+            }
+            for prune in control.prune {
+                let topic = Topic::from(prune.topic);
+                if let Some(mesh) = self.mesh.get_mut(&topic) {
+                    mesh.remove(&peer_id);
+                }
+            }
+        }
+    }
+
+    fn handle_subscription(&mut self, peer_id: PeerId, subscription: v1::SubOpts) {
+        let topic = Topic::from(subscription.topic);
+        let Some(state) = self.peers.get_mut(&peer_id) else {
+            return;
+        };
+        if subscription.subscribe {
+            if !state.topics.insert(topic.clone()) {
+                return;
+            }
+            if let Some(mesh) = self.mesh.get_mut(&topic)
+                && mesh.len() < self.config.mesh_n
+            {
+                mesh.insert(peer_id);
+                self.send(peer_id, graft_rpc(&topic));
+            }
+            self.emit(Event::Subscribed { peer_id, topic });
+        } else {
+            if !state.topics.remove(&topic) {
+                return;
+            }
+            if let Some(mesh) = self.mesh.get_mut(&topic) {
+                mesh.remove(&peer_id);
+            }
+            self.emit(Event::Unsubscribed { peer_id, topic });
+        }
+    }
+
+    fn handle_message(&mut self, propagation_source: PeerId, message: v1::Message) {
+        let Some(id) = protocol::verify_message(&message) else {
+            tracing::debug!(
+                "Dropping message with invalid signature from {}",
+                propagation_source
+            );
+            return;
+        };
+        if id.source == self.local_peer_id || !self.insert_seen(id) {
+            return;
+        }
+        let topic = Topic::from(message.topic.clone());
+        let Some(mesh) = self.mesh.get(&topic) else {
+            return;
+        };
+        let forward = mesh
+            .iter()
+            .filter(|peer_id| **peer_id != propagation_source && **peer_id != id.source)
+            .copied()
+            .collect::<Vec<_>>();
+        for peer_id in forward {
+            self.send(peer_id, publish_rpc(message.clone()));
+        }
+        self.emit(Event::Message {
+            topic,
+            source: id.source,
+            data: message.data,
+        });
+    }
+
+    fn handle_graft(&mut self, peer_id: PeerId, topic: Topic) {
+        match self.mesh.get_mut(&topic) {
+            Some(mesh) if mesh.len() < self.config.mesh_n_high || mesh.contains(&peer_id) => {
+                mesh.insert(peer_id);
+            }
+            _ => {
+                let rpc = v1::Rpc {
+                    control: Some(prune_control(&topic)),
+                    ..Default::default()
+                };
+                self.send(peer_id, rpc);
+            }
+        }
+    }
+
+    fn heartbeat(&mut self) {
+        let mut grafts = Vec::new();
+        let mut prunes = Vec::new();
+        let topics = self.mesh.keys().cloned().collect::<Vec<_>>();
+        for topic in topics {
+            let mesh = &self.mesh[&topic];
+            if mesh.len() < self.config.mesh_n_low {
+                let needed = self.config.mesh_n - mesh.len();
+                let candidates = self.subscribed_peers(&topic, mesh);
+                let mesh = self.mesh.get_mut(&topic).expect("topic is in mesh");
+                for peer_id in candidates.into_iter().take(needed) {
+                    mesh.insert(peer_id);
+                    grafts.push((peer_id, topic.clone()));
+                }
+            } else if mesh.len() > self.config.mesh_n_high {
+                let mut members = mesh.iter().copied().collect::<Vec<_>>();
+                members.shuffle(&mut rand::rng());
+                let excess = mesh.len() - self.config.mesh_n;
+                let mesh = self.mesh.get_mut(&topic).expect("topic is in mesh");
+                for peer_id in members.into_iter().take(excess) {
+                    mesh.remove(&peer_id);
+                    prunes.push((peer_id, topic.clone()));
+                }
+            }
+        }
+        for (peer_id, topic) in grafts {
+            self.send(peer_id, graft_rpc(&topic));
+        }
+        for (peer_id, topic) in prunes {
+            let rpc = v1::Rpc {
+                control: Some(prune_control(&topic)),
+                ..Default::default()
+            };
+            self.send(peer_id, rpc);
+        }
+
+        while let Some((at, id)) = self.seen_order.front() {
+            if at.elapsed() < self.config.seen_ttl {
+                break;
+            }
+            self.seen.remove(id);
+            self.seen_order.pop_front();
+        }
+    }
+}
+
+fn subscription_rpc(topic: &Topic, subscribe: bool) -> v1::Rpc {
+    v1::Rpc {
+        subscriptions: vec![v1::SubOpts {
+            subscribe,
+            topic: topic.to_string(),
+        }],
+        ..Default::default()
+    }
+}
+
+fn publish_rpc(message: v1::Message) -> v1::Rpc {
+    v1::Rpc {
+        publish: vec![message],
+        ..Default::default()
+    }
+}
+
+fn graft_rpc(topic: &Topic) -> v1::Rpc {
+    v1::Rpc {
+        control: Some(graft_control(topic)),
+        ..Default::default()
+    }
+}
+
+fn graft_control(topic: &Topic) -> v1::Control {
+    v1::Control {
+        graft: vec![v1::ControlGraft {
+            topic: topic.to_string(),
+        }],
+        prune: Vec::new(),
+    }
+}
+
+fn prune_control(topic: &Topic) -> v1::Control {
+    v1::Control {
+        graft: Vec::new(),
+        prune: vec![v1::ControlPrune {
+            topic: topic.to_string(),
+        }],
+    }
+}
+
+impl NetworkBehavior for Behavior {
+    type ConnectionHandler = Handler;
+    type Event = Event;
+
+    fn on_connection_handler_event(
+        &mut self,
+        _id: ConnectionId,
+        peer_id: PeerId,
+        event: THandlerEvent<Self>,
+    ) {
+        self.handle_rpc(peer_id, event);
+    }
+
+    fn poll(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<BehaviorEvent<Self::Event, THandlerAction<Self>>> {
+        if self.heartbeat.poll_unpin(cx).is_ready() {
+            self.heartbeat.reset(self.config.heartbeat_interval);
+            self.heartbeat();
+        }
+        if let Some(event) = self.pending_events.pop_front() {
+            return Poll::Ready(event);
+        }
+        self.waker = Some(cx.waker().clone());
+        Poll::Pending
+    }
+}
+
+impl NetworkIncomingBehavior for Behavior {
+    fn handle_established_connection(
+        &mut self,
+        _id: ConnectionId,
+        _peer_id: PeerId,
+        _local_addr: &Multiaddr,
+        _remote_addr: &Multiaddr,
+    ) -> Result<Self::ConnectionHandler, ConnectionDenied> {
+        Ok(Handler::new(
+            Endpoint::Listener,
+            self.config.max_transmit_size,
+        ))
+    }
+
+    fn on_connection_established(
+        &mut self,
+        id: ConnectionId,
+        peer_id: PeerId,
+        _local_addr: &Multiaddr,
+        _remote_addr: &Multiaddr,
+    ) {
+        Behavior::on_connection_established(self, id, peer_id);
+    }
+
+    fn on_connection_closed(
+        &mut self,
+        id: ConnectionId,
+        peer_id: PeerId,
+        _local_addr: &Multiaddr,
+        _remote_addr: &Multiaddr,
+        _reason: &CloseReason,
+    ) {
+        Behavior::on_connection_closed(self, id, peer_id);
+    }
+}
+
+impl NetworkOutgoingBehavior for Behavior {
+    fn handle_established_connection(
+        &mut self,
+        _id: ConnectionId,
+        _peer_id: PeerId,
+        _addr: &Multiaddr,
+    ) -> Result<Self::ConnectionHandler, ConnectionDenied> {
+        Ok(Handler::new(
+            Endpoint::Dialer,
+            self.config.max_transmit_size,
+        ))
+    }
+
+    fn handle_pending_connection(
+        &mut self,
+        _id: ConnectionId,
+        _maybe_peer: Option<PeerId>,
+        addr: &Option<Multiaddr>,
+    ) -> Result<Option<Multiaddr>, ConnectionDenied> {
+        Ok(addr.clone())
+    }
+
+    fn on_connection_established(&mut self, id: ConnectionId, peer_id: PeerId, _addr: &Multiaddr) {
+        Behavior::on_connection_established(self, id, peer_id);
+    }
+
+    fn on_connection_closed(
+        &mut self,
+        id: ConnectionId,
+        peer_id: PeerId,
+        _addr: &Multiaddr,
+        _reason: &CloseReason,
+    ) {
+        Behavior::on_connection_closed(self, id, peer_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::task::noop_waker_ref;
+
+    use super::*;
+
+    fn poll_event(behavior: &mut Behavior) -> Option<Event> {
+        let mut cx = Context::from_waker(noop_waker_ref());
+        loop {
+            match NetworkBehavior::poll(behavior, &mut cx) {
+                Poll::Ready(BehaviorEvent::Behavior(event)) => return Some(event),
+                Poll::Ready(_) => continue,
+                Poll::Pending => return None,
+            }
+        }
+    }
+
+    #[test]
+    fn drop_message_with_invalid_signature() {
+        let mut behavior = Behavior::new(KeyPair::from_bytes(&[1u8; 32]), Config::default());
+        let topic = Topic::new("news");
+        behavior.subscribe(topic.clone());
+
+        let publisher = KeyPair::from_bytes(&[2u8; 32]);
+        let propagation_source = PeerId::random();
+        let mut forged = protocol::sign_message(&publisher, &topic, 1, b"hello".to_vec());
+        forged.data = b"forged".to_vec();
+        behavior.handle_rpc(propagation_source, publish_rpc(forged));
+        assert!(poll_event(&mut behavior).is_none());
+
+        let signed = protocol::sign_message(&publisher, &topic, 2, b"hello".to_vec());
+        behavior.handle_rpc(propagation_source, publish_rpc(signed));
+        match poll_event(&mut behavior) {
+            Some(Event::Message { source, data, .. }) => {
+                assert_eq!(source, PeerId::from_public_key(&publisher.verifying_key()));
+                assert_eq!(data, b"hello");
+            }
+            event => panic!("unexpected event: {event:?}"),
+        }
+    }
+}
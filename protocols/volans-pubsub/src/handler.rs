@@ -0,0 +1,198 @@
+use std::{
+    collections::VecDeque,
+    task::{Context, Poll},
+};
+
+use futures::{SinkExt, StreamExt};
+use volans_core::{Endpoint, upgrade::ReadyUpgrade};
+use volans_swarm::{
+    ConnectionHandler, ConnectionHandlerEvent, InboundStreamHandler, InboundUpgradeSend, KeepAlive,
+    OutboundStreamHandler, OutboundUpgradeSend, StreamProtocol, StreamUpgradeError,
+    SubstreamProtocol,
+};
+
+use crate::protocol::{self, RpcStream, v1};
+
+/// 每个连接上只维护一条双向的 RPC 子流，由拨号端发起
+pub struct Handler {
+    endpoint: Endpoint,
+    max_transmit_size: usize,
+    stream: Option<RpcStream>,
+    outbound_requested: bool,
+    /// 子流失败或对端不支持后不再收发
+    closed: bool,
+    send_queue: VecDeque<v1::Rpc>,
+    flush_pending: bool,
+}
+
+impl Handler {
+    pub(crate) fn new(endpoint: Endpoint, max_transmit_size: usize) -> Self {
+        Self {
+            endpoint,
+            max_transmit_size,
+            stream: None,
+            outbound_requested: false,
+            closed: false,
+            send_queue: VecDeque::new(),
+            flush_pending: false,
+        }
+    }
+
+    fn on_stream(&mut self, stream: volans_swarm::Substream) {
+        if self.stream.is_some() || self.closed {
+            tracing::debug!("Pubsub stream already established, dropping new stream");
+            return;
+        }
+        self.stream = Some(protocol::new_stream(stream, self.max_transmit_size));
+    }
+
+    fn close(&mut self) {
+        self.stream = None;
+        self.closed = true;
+        self.send_queue.clear();
+        self.flush_pending = false;
+    }
+}
+
+impl ConnectionHandler for Handler {
+    type Action = v1::Rpc;
+    type Event = v1::Rpc;
+
+    fn handle_action(&mut self, action: Self::Action) {
+        if !self.closed {
+            self.send_queue.push_back(action);
+        }
+    }
+
+    fn keep_alive(&self) -> KeepAlive {
+        KeepAlive::from(!self.closed)
+    }
+
+    fn poll(&mut self, cx: &mut Context<'_>) -> Poll<ConnectionHandlerEvent<Self::Event>> {
+        loop {
+            let Some(stream) = self.stream.as_mut() else {
+                return Poll::Pending;
+            };
+
+            if !self.send_queue.is_empty() {
+                match stream.poll_ready_unpin(cx) {
+                    Poll::Ready(Ok(())) => {
+                        let rpc = self
+                            .send_queue
+                            .pop_front()
+                            .expect("send queue is not empty");
+                        if let Err(error) = stream.start_send_unpin(rpc) {
+                            tracing::debug!("Failed to send pubsub rpc: {}", error);
+                            self.close();
+                            continue;
+                        }
+                        self.flush_pending = true;
+                        continue;
+                    }
+                    Poll::Ready(Err(error)) => {
+                        tracing::debug!("Pubsub stream error: {}", error);
+                        self.close();
+                        continue;
+                    }
+                    Poll::Pending => {}
+                }
+            }
+
+            if self.flush_pending {
+                match stream.poll_flush_unpin(cx) {
+                    Poll::Ready(Ok(())) => self.flush_pending = false,
+                    Poll::Ready(Err(error)) => {
+                        tracing::debug!("Failed to flush pubsub stream: {}", error);
+                        self.close();
+                        continue;
+                    }
+                    Poll::Pending => {}
+                }
+            }
+
+            match stream.poll_next_unpin(cx) {
+                Poll::Ready(Some(Ok(rpc))) => {
+                    return Poll::Ready(ConnectionHandlerEvent::Notify(rpc));
+                }
+                Poll::Ready(Some(Err(error))) => {
+                    tracing::debug!("Failed to read pubsub rpc: {}", error);
+                    self.close();
+                    continue;
+                }
+                Poll::Ready(None) => {
+                    tracing::debug!("Pubsub stream closed by remote");
+                    self.close();
+                    continue;
+                }
+                Poll::Pending => {}
+            }
+            return Poll::Pending;
+        }
+    }
+}
+
+impl InboundStreamHandler for Handler {
+    type InboundUpgrade = ReadyUpgrade<StreamProtocol>;
+    type InboundUserData = ();
+
+    fn listen_protocol(&self) -> SubstreamProtocol<Self::InboundUpgrade, Self::InboundUserData> {
+        SubstreamProtocol::new(ReadyUpgrade::new(protocol::PROTOCOL_NAME), ())
+    }
+
+    fn on_fully_negotiated(
+        &mut self,
+        _user_data: Self::InboundUserData,
+        protocol: <Self::InboundUpgrade as InboundUpgradeSend>::Output,
+    ) {
+        self.on_stream(protocol);
+    }
+
+    fn on_upgrade_error(
+        &mut self,
+        _user_data: Self::InboundUserData,
+        error: <Self::InboundUpgrade as InboundUpgradeSend>::Error,
+    ) {
+        tracing::debug!("Pubsub inbound upgrade error: {}", error);
+    }
+}
+
+impl OutboundStreamHandler for Handler {
+    type OutboundUpgrade = ReadyUpgrade<StreamProtocol>;
+    type OutboundUserData = ();
+
+    fn on_fully_negotiated(
+        &mut self,
+        _user_data: Self::OutboundUserData,
+        protocol: <Self::OutboundUpgrade as OutboundUpgradeSend>::Output,
+    ) {
+        self.on_stream(protocol);
+    }
+
+    fn on_upgrade_error(
+        &mut self,
+        _user_data: Self::OutboundUserData,
+        error: StreamUpgradeError<<Self::OutboundUpgrade as OutboundUpgradeSend>::Error>,
+    ) {
+        match error {
+            StreamUpgradeError::NegotiationFailed { .. } => {
+                tracing::debug!("Remote does not support pubsub protocol");
+            }
+            error => tracing::debug!("Pubsub outbound upgrade error: {:?}", error),
+        }
+        self.close();
+    }
+
+    fn poll_outbound_request(
+        &mut self,
+        _cx: &mut Context<'_>,
+    ) -> Poll<SubstreamProtocol<Self::OutboundUpgrade, Self::OutboundUserData>> {
+        if self.endpoint == Endpoint::Dialer && !self.outbound_requested && !self.closed {
+            self.outbound_requested = true;
+            return Poll::Ready(SubstreamProtocol::new(
+                ReadyUpgrade::new(protocol::PROTOCOL_NAME),
+                (),
+            ));
+        }
+        Poll::Pending
+    }
+}
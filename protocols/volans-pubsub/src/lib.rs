@@ -0,0 +1,152 @@
+//! 基于 Mesh 的发布/订阅协议
+//!
+//! 每个连接上由拨号端打开一条双向 RPC 子流，服务端与客户端 Swarm 都可以使用同一个 [`Behavior`]。
+//! 消息由发布者的密钥签名，转发时只发送给主题 Mesh 中的节点，心跳周期内维持 Mesh 大小。
+
+mod behavior;
+mod handler;
+mod protocol;
+
+pub use behavior::Behavior;
+pub use handler::Handler;
+
+use std::{fmt, time::Duration};
+
+use volans_core::PeerId;
+
+#[derive(Debug, Clone)]
+pub struct Config {
+    mesh_n: usize,
+    mesh_n_low: usize,
+    mesh_n_high: usize,
+    heartbeat_interval: Duration,
+    seen_ttl: Duration,
+    max_transmit_size: usize,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            mesh_n: 6,
+            mesh_n_low: 4,
+            mesh_n_high: 12,
+            heartbeat_interval: Duration::from_secs(1),
+            seen_ttl: Duration::from_secs(120),
+            max_transmit_size: 65536,
+        }
+    }
+}
+
+impl Config {
+    /// Mesh 目标大小及上下限，心跳时低于 `low` 补充到 `n`，高于 `high` 裁剪到 `n`
+    pub fn with_mesh_size(mut self, n: usize, low: usize, high: usize) -> Self {
+        assert!(
+            low <= n && n <= high,
+            "mesh size must satisfy low <= n <= high"
+        );
+        self.mesh_n = n;
+        self.mesh_n_low = low;
+        self.mesh_n_high = high;
+        self
+    }
+
+    pub fn with_heartbeat_interval(mut self, interval: Duration) -> Self {
+        self.heartbeat_interval = interval;
+        self
+    }
+
+    /// 已处理消息的去重缓存时长
+    pub fn with_seen_ttl(mut self, ttl: Duration) -> Self {
+        self.seen_ttl = ttl;
+        self
+    }
+
+    /// 单个 RPC 帧的最大字节数
+    pub fn with_max_transmit_size(mut self, size: usize) -> Self {
+        self.max_transmit_size = size;
+        self
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Topic(String);
+
+impl Topic {
+    pub fn new(topic: impl Into<String>) -> Self {
+        Self(topic.into())
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<&str> for Topic {
+    fn from(topic: &str) -> Self {
+        Self::new(topic)
+    }
+}
+
+impl From<String> for Topic {
+    fn from(topic: String) -> Self {
+        Self(topic)
+    }
+}
+
+impl fmt::Display for Topic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+/// 消息 ID，由发布者与序号组成
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct MessageId {
+    source: PeerId,
+    sequence_number: u64,
+}
+
+impl MessageId {
+    pub(crate) fn new(source: PeerId, sequence_number: u64) -> Self {
+        Self {
+            source,
+            sequence_number,
+        }
+    }
+
+    pub fn source(&self) -> &PeerId {
+        &self.source
+    }
+
+    pub fn sequence_number(&self) -> u64 {
+        self.sequence_number
+    }
+}
+
+impl fmt::Display for MessageId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.source, self.sequence_number)
+    }
+}
+
+#[derive(Debug)]
+pub enum Event {
+    /// 收到已订阅主题的消息
+    Message {
+        topic: Topic,
+        source: PeerId,
+        data: Vec<u8>,
+    },
+    /// 对端订阅了主题
+    Subscribed { peer_id: PeerId, topic: Topic },
+    /// 对端取消订阅主题
+    Unsubscribed { peer_id: PeerId, topic: Topic },
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum PublishError {
+    #[error("Message is too large: {size} bytes")]
+    MessageTooLarge { size: usize },
+    #[error("No peers subscribed to topic {0}")]
+    InsufficientPeers(Topic),
+}
@@ -0,0 +1,81 @@
+use volans_codec::{Framed, ProtobufUviCodec};
+use volans_core::{
+    PeerId,
+    identity::{KeyPair, Signer},
+};
+use volans_swarm::{StreamProtocol, Substream};
+
+use crate::{MessageId, Topic};
+
+pub mod v1 {
+    include!(concat!(env!("OUT_DIR"), "/volans.pubsub.v1.rs"));
+}
+
+pub(crate) const PROTOCOL_NAME: StreamProtocol = StreamProtocol::new("/v1/pubsub");
+
+/// 签名内容的前缀，避免与其他协议的签名混用
+const SIGNING_PREFIX: &[u8] = b"volans-pubsub:";
+
+pub(crate) type RpcStream = Framed<Substream, ProtobufUviCodec<v1::Rpc>>;
+
+pub(crate) fn new_stream(io: Substream, max_transmit_size: usize) -> RpcStream {
+    Framed::new(io, ProtobufUviCodec::new(max_transmit_size))
+}
+
+fn signing_bytes(message: &v1::Message) -> Vec<u8> {
+    let unsigned = v1::Message {
+        signature: Vec::new(),
+        ..message.clone()
+    };
+    let mut bytes = SIGNING_PREFIX.to_vec();
+    prost::Message::encode(&unsigned, &mut bytes).expect("Vec<u8> provides capacity as needed");
+    bytes
+}
+
+pub(crate) fn sign_message(
+    keypair: &KeyPair,
+    topic: &Topic,
+    seqno: u64,
+    data: Vec<u8>,
+) -> v1::Message {
+    let mut message = v1::Message {
+        from: keypair.verifying_key().to_bytes().to_vec(),
+        data,
+        seqno,
+        topic: topic.as_str().to_string(),
+        signature: Vec::new(),
+    };
+    message.signature = keypair.sign(&signing_bytes(&message)).to_vec();
+    message
+}
+
+/// 校验消息签名，成功时返回消息 ID
+pub(crate) fn verify_message(message: &v1::Message) -> Option<MessageId> {
+    let source = PeerId::try_from_slice(&message.from).ok()?;
+    let public_key = source.public_key()?;
+    public_key
+        .verify(&signing_bytes(message), &message.signature)
+        .then(|| MessageId::new(source, message.seqno))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn signed_message_roundtrip() {
+        let keypair = KeyPair::from_bytes(&[7u8; 32]);
+        let topic = Topic::new("news");
+        let mut message = sign_message(&keypair, &topic, 42, b"hello".to_vec());
+
+        let id = verify_message(&message).expect("valid signature");
+        assert_eq!(
+            id.source(),
+            &PeerId::from_public_key(&keypair.verifying_key())
+        );
+        assert_eq!(id.sequence_number(), 42);
+
+        message.data = b"tampered".to_vec();
+        assert!(verify_message(&message).is_none());
+    }
+}
@@ -0,0 +1,143 @@
+use volans_core::identity::KeyPair;
+use volans_pubsub::{Behavior, Config, Event, Topic};
+use volans_swarm::{client, server};
+use volans_swarm_test::{SwarmExt, connect, next_behavior_event};
+
+fn pubsub(key_pair: &KeyPair) -> Behavior {
+    Behavior::new(key_pair.clone(), Config::default())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Side {
+    Client,
+    Server,
+}
+
+/// 同时驱动两个节点，直到某一方的行为事件满足 `matcher`
+async fn drive<T>(
+    client: &mut client::Swarm<Behavior>,
+    server: &mut server::Swarm<Behavior>,
+    mut matcher: impl FnMut(Side, Event) -> Option<T>,
+) -> T {
+    loop {
+        let matched = tokio::select! {
+            event = next_behavior_event(client) => matcher(Side::Client, event),
+            event = next_behavior_event(server) => matcher(Side::Server, event),
+        };
+        if let Some(value) = matched {
+            return value;
+        }
+    }
+}
+
+/// 双方订阅 `topics` 后建立连接，等待彼此收到全部订阅
+async fn subscribed_pair(topics: &[&str]) -> (client::Swarm<Behavior>, server::Swarm<Behavior>) {
+    let mut client = client::Swarm::new_ephemeral(pubsub);
+    let mut server = server::Swarm::new_ephemeral(pubsub);
+    for topic in topics {
+        client.behavior_mut().subscribe(*topic);
+        server.behavior_mut().subscribe(*topic);
+    }
+    connect(&mut client, &mut server).await;
+
+    let mut pending = topics.len() * 2;
+    drive(&mut client, &mut server, |_, event| {
+        if matches!(event, Event::Subscribed { .. }) {
+            pending -= 1;
+        }
+        (pending == 0).then_some(())
+    })
+    .await;
+    (client, server)
+}
+
+fn message(event: Event) -> Option<(Topic, volans_core::PeerId, Vec<u8>)> {
+    match event {
+        Event::Message {
+            topic,
+            source,
+            data,
+        } => Some((topic, source, data)),
+        _ => None,
+    }
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn publish_to_subscriber() {
+    let (mut client, mut server) = subscribed_pair(&["news"]).await;
+    let client_peer = *client.local_peer_id();
+    let server_peer = *server.local_peer_id();
+    assert_eq!(
+        server
+            .behavior()
+            .mesh_peers(&Topic::new("news"))
+            .collect::<Vec<_>>(),
+        [&client_peer]
+    );
+
+    client
+        .behavior_mut()
+        .publish("news", "from client")
+        .unwrap();
+    let received = drive(&mut client, &mut server, |side, event| {
+        assert_eq!(side, Side::Server);
+        message(event)
+    })
+    .await;
+    assert_eq!(
+        received,
+        (Topic::new("news"), client_peer, b"from client".to_vec())
+    );
+
+    server
+        .behavior_mut()
+        .publish("news", "from server")
+        .unwrap();
+    let received = drive(&mut client, &mut server, |side, event| {
+        assert_eq!(side, Side::Client);
+        message(event)
+    })
+    .await;
+    assert_eq!(
+        received,
+        (Topic::new("news"), server_peer, b"from server".to_vec())
+    );
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn unsubscribed_topic_is_not_delivered() {
+    let (mut client, mut server) = subscribed_pair(&["news", "sports"]).await;
+
+    // 取消订阅尚未送达，客户端仍会把消息发给服务端
+    assert!(server.behavior_mut().unsubscribe(&Topic::new("news")));
+    client.behavior_mut().publish("news", "dropped").unwrap();
+    client
+        .behavior_mut()
+        .publish("sports", "delivered")
+        .unwrap();
+
+    let mut delivered = false;
+    let mut unsubscribed = false;
+    drive(&mut client, &mut server, |side, event| {
+        match (side, event) {
+            (Side::Server, Event::Message { topic, data, .. }) => {
+                assert_eq!(topic, Topic::new("sports"));
+                assert_eq!(data, b"delivered");
+                delivered = true;
+            }
+            (Side::Client, Event::Unsubscribed { topic, .. }) => {
+                assert_eq!(topic, Topic::new("news"));
+                unsubscribed = true;
+            }
+            (side, event) => panic!("unexpected {side:?} event: {event:?}"),
+        }
+        (delivered && unsubscribed).then_some(())
+    })
+    .await;
+    assert!(
+        client
+            .behavior_mut()
+            .publish("news", "no subscribers")
+            .is_err()
+    );
+}
@@ -0,0 +1,314 @@
+//! 入站连接速率限制
+//!
+//! 按远端 IP 及全局各维护一个令牌桶，在 `handle_pending_connection` 中取令牌，
+//! 令牌不足时以 [`Exceeded`] 拒绝连接，被拒绝的数量按 [`Config::with_report_interval`]
+//! 周期汇总为 [`Event::RateLimited`]。
+
+use std::{
+    collections::HashMap,
+    fmt,
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+
+use futures::FutureExt;
+use futures_timer::Delay;
+use volans_core::{Multiaddr, PeerId, multiaddr::Protocol};
+use volans_swarm::{
+    BehaviorEvent, ConnectionDenied, ConnectionId, NetworkBehavior, NetworkIncomingBehavior,
+    THandlerAction, THandlerEvent, handler::DummyHandler,
+};
+
+/// 令牌桶配额：最多积累 `burst` 个令牌，每 `period` 补满 `burst` 个
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Quota {
+    burst: u32,
+    period: Duration,
+}
+
+impl Quota {
+    pub fn new(burst: u32, period: Duration) -> Self {
+        Self { burst, period }
+    }
+
+    /// 每秒 `n` 个连接
+    pub fn per_second(n: u32) -> Self {
+        Self::new(n, Duration::from_secs(1))
+    }
+
+    /// 每分钟 `n` 个连接
+    pub fn per_minute(n: u32) -> Self {
+        Self::new(n, Duration::from_secs(60))
+    }
+}
+
+/// 速率限制配置，`None` 表示不限制
+#[derive(Debug, Clone)]
+pub struct Config {
+    per_ip: Option<Quota>,
+    global: Option<Quota>,
+    report_interval: Duration,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            per_ip: None,
+            global: None,
+            report_interval: Duration::from_secs(10),
+        }
+    }
+}
+
+impl Config {
+    /// 单个远端 IP 的入站连接速率
+    pub fn with_per_ip(mut self, quota: Option<Quota>) -> Self {
+        self.per_ip = quota;
+        self
+    }
+
+    /// 所有入站连接的总速率
+    pub fn with_global(mut self, quota: Option<Quota>) -> Self {
+        self.global = quota;
+        self
+    }
+
+    /// 汇总上报被拒绝连接的周期
+    pub fn with_report_interval(mut self, interval: Duration) -> Self {
+        self.report_interval = interval;
+        self
+    }
+}
+
+/// 超出的限制范围
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scope {
+    PerIp,
+    Global,
+}
+
+impl fmt::Display for Scope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Scope::PerIp => write!(f, "per ip"),
+            Scope::Global => write!(f, "global"),
+        }
+    }
+}
+
+/// 连接被拒绝的原因，可通过 [`ConnectionDenied::downcast_ref`] 获取
+#[derive(Debug, Clone, Copy, thiserror::Error)]
+#[error("Connection rate limit exceeded: {scope}")]
+pub struct Exceeded {
+    scope: Scope,
+}
+
+impl Exceeded {
+    pub fn scope(&self) -> Scope {
+        self.scope
+    }
+}
+
+#[derive(Debug)]
+pub enum Event {
+    /// 上一个周期内来自 `addr` 的连接被拒绝了 `dropped` 次
+    ///
+    /// `addr` 为远端地址中的 IP 部分，不含 IP 的地址原样上报。
+    RateLimited { addr: Multiaddr, dropped: u64 },
+}
+
+#[derive(Debug)]
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+impl Bucket {
+    fn new(quota: &Quota, now: Instant) -> Self {
+        Self {
+            tokens: quota.burst as f64,
+            updated: now,
+        }
+    }
+
+    fn refill(&mut self, quota: &Quota, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.updated);
+        let rate = quota.burst as f64 / quota.period.as_secs_f64();
+        self.tokens = (self.tokens + elapsed.as_secs_f64() * rate).min(quota.burst as f64);
+        self.updated = now;
+    }
+
+    fn has_token(&mut self, quota: &Quota, now: Instant) -> bool {
+        self.refill(quota, now);
+        self.tokens >= 1.0
+    }
+
+    fn is_full(&mut self, quota: &Quota, now: Instant) -> bool {
+        self.refill(quota, now);
+        self.tokens >= quota.burst as f64
+    }
+}
+
+/// 远端地址中的 IP 部分
+fn source_addr(addr: &Multiaddr) -> Multiaddr {
+    addr.iter()
+        .find(|p| matches!(p, Protocol::Ip4(_) | Protocol::Ip6(_)))
+        .map(Multiaddr::from)
+        .unwrap_or_else(|| addr.clone())
+}
+
+pub struct Behavior {
+    config: Config,
+    global: Option<Bucket>,
+    per_ip: HashMap<Multiaddr, Bucket>,
+    dropped: HashMap<Multiaddr, u64>,
+    report_timer: Delay,
+    pending_events: Vec<Event>,
+}
+
+impl Behavior {
+    pub fn new(config: Config) -> Self {
+        let now = Instant::now();
+        Self {
+            global: config.global.as_ref().map(|q| Bucket::new(q, now)),
+            per_ip: HashMap::new(),
+            dropped: HashMap::new(),
+            report_timer: Delay::new(config.report_interval),
+            pending_events: Vec::new(),
+            config,
+        }
+    }
+
+    fn try_acquire(&mut self, source: &Multiaddr, now: Instant) -> Result<(), Exceeded> {
+        let per_ip = match &self.config.per_ip {
+            Some(quota) => {
+                let bucket = self
+                    .per_ip
+                    .entry(source.clone())
+                    .or_insert_with(|| Bucket::new(quota, now));
+                if !bucket.has_token(quota, now) {
+                    return Err(Exceeded {
+                        scope: Scope::PerIp,
+                    });
+                }
+                Some(bucket)
+            }
+            None => None,
+        };
+        if let (Some(quota), Some(global)) = (&self.config.global, self.global.as_mut()) {
+            if !global.has_token(quota, now) {
+                return Err(Exceeded {
+                    scope: Scope::Global,
+                });
+            }
+            global.tokens -= 1.0;
+        }
+        if let Some(bucket) = per_ip {
+            bucket.tokens -= 1.0;
+        }
+        Ok(())
+    }
+
+    fn report(&mut self) {
+        let now = Instant::now();
+        if let Some(quota) = &self.config.per_ip {
+            self.per_ip.retain(|_, bucket| !bucket.is_full(quota, now));
+        }
+        self.pending_events.extend(
+            self.dropped
+                .drain()
+                .map(|(addr, dropped)| Event::RateLimited { addr, dropped }),
+        );
+    }
+}
+
+impl NetworkBehavior for Behavior {
+    type ConnectionHandler = DummyHandler;
+    type Event = Event;
+
+    fn on_connection_handler_event(
+        &mut self,
+        _id: ConnectionId,
+        _peer_id: PeerId,
+        event: THandlerEvent<Self>,
+    ) {
+        unreachable!("Unexpected event: {:?}", event);
+    }
+
+    fn poll(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<BehaviorEvent<Self::Event, THandlerAction<Self>>> {
+        if self.report_timer.poll_unpin(cx).is_ready() {
+            self.report_timer.reset(self.config.report_interval);
+            self.report();
+        }
+        if let Some(event) = self.pending_events.pop() {
+            return Poll::Ready(BehaviorEvent::Behavior(event));
+        }
+        Poll::Pending
+    }
+}
+
+impl NetworkIncomingBehavior for Behavior {
+    fn handle_pending_connection(
+        &mut self,
+        _id: ConnectionId,
+        _local_addr: &Multiaddr,
+        remote_addr: &Multiaddr,
+    ) -> Result<(), ConnectionDenied> {
+        let source = source_addr(remote_addr);
+        self.try_acquire(&source, Instant::now()).map_err(|err| {
+            tracing::debug!(remote = %remote_addr, "Connection denied, {}", err);
+            *self.dropped.entry(source).or_default() += 1;
+            ConnectionDenied::new(err)
+        })
+    }
+
+    fn handle_established_connection(
+        &mut self,
+        _id: ConnectionId,
+        _peer_id: PeerId,
+        _local_addr: &Multiaddr,
+        _remote_addr: &Multiaddr,
+    ) -> Result<Self::ConnectionHandler, ConnectionDenied> {
+        Ok(DummyHandler)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn per_ip_and_global_buckets() {
+        let mut behavior = Behavior::new(
+            Config::default()
+                .with_per_ip(Some(Quota::new(2, Duration::from_secs(2))))
+                .with_global(Some(Quota::new(3, Duration::from_secs(3)))),
+        );
+        let a: Multiaddr = "/ip4/10.0.0.1".parse().unwrap();
+        let b: Multiaddr = "/ip4/10.0.0.2".parse().unwrap();
+        let now = Instant::now();
+
+        assert!(behavior.try_acquire(&a, now).is_ok());
+        assert!(behavior.try_acquire(&a, now).is_ok());
+        let err = behavior.try_acquire(&a, now).unwrap_err();
+        assert_eq!(err.scope(), Scope::PerIp);
+
+        assert!(behavior.try_acquire(&b, now).is_ok());
+        let err = behavior.try_acquire(&b, now).unwrap_err();
+        assert_eq!(err.scope(), Scope::Global);
+
+        // 每秒补充一个令牌
+        let later = now + Duration::from_secs(1);
+        assert!(behavior.try_acquire(&b, later).is_ok());
+        assert!(behavior.try_acquire(&a, later).is_err());
+    }
+
+    #[test]
+    fn source_addr_strips_transport() {
+        let remote: Multiaddr = "/ip4/10.0.0.1/tcp/50000".parse().unwrap();
+        assert_eq!(source_addr(&remote), "/ip4/10.0.0.1".parse().unwrap());
+    }
+}
@@ -14,6 +14,50 @@ use crate::{Discovery, DiscoveryEvent, Registry, RegistryError, ServiceInfo};
 pub struct Behavior<R: Registry> {
     discovery: R::Discovery,
     discovered: HashMap<PeerId, ServiceInfo>,
+    /// 每个服务名的轮询位置
+    cursors: HashMap<String, usize>,
+}
+
+/// 在同名服务中选择后端的策略
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Strategy {
+    /// 依次轮流选择
+    #[default]
+    RoundRobin,
+    /// 选择负载最小的节点，负载相同的节点之间轮流选择
+    LeastLoaded,
+}
+
+impl<R: Registry> Behavior<R> {
+    /// 使用指定注册中心的发现者，如已预置服务的 [`StaticRegistry`](crate::StaticRegistry)
+    pub fn new(registry: &R) -> Result<Self, RegistryError> {
+        Ok(Self {
+            discovered: HashMap::new(),
+            discovery: registry.discovery()?,
+            cursors: HashMap::new(),
+        })
+    }
+
+    /// 在已发现的同名服务中按策略选择一个后端
+    pub fn pick_backend(&mut self, name: &str, strategy: Strategy) -> Option<&ServiceInfo> {
+        let mut candidates: Vec<&ServiceInfo> = self
+            .discovered
+            .values()
+            .filter(|service| service.name == name)
+            .collect();
+        candidates.sort_by_key(|service| service.peer_id);
+        if strategy == Strategy::LeastLoaded {
+            let least = candidates.iter().map(|service| service.load).min()?;
+            candidates.retain(|service| service.load == least);
+        }
+        if candidates.is_empty() {
+            return None;
+        }
+        let cursor = self.cursors.entry(name.to_string()).or_default();
+        let picked = candidates[*cursor % candidates.len()];
+        *cursor = cursor.wrapping_add(1);
+        Some(picked)
+    }
 }
 
 impl<R: Registry> Default for Behavior<R> {
@@ -23,6 +67,7 @@ impl<R: Registry> Default for Behavior<R> {
             discovery: R::default()
                 .discovery()
                 .expect("Discovery should be available"),
+            cursors: HashMap::new(),
         }
     }
 }
@@ -72,17 +117,14 @@ impl<R: Registry> NetworkOutgoingBehavior for Behavior<R> {
         if addr.is_some() {
             return Ok(addr.clone());
         }
-        match maybe_peer {
-            Some(peer_id) => {
-                if let Some(service_info) = self.discovered.get(&peer_id) {
-                    let addr = service_info.addresses.iter().next().cloned();
-                    tracing::debug!("Using address {:?} for peer ID {}", addr, peer_id);
-                    return Ok(addr);
-                } else {
-                    tracing::warn!("Peer ID {} not found in discovered services", peer_id);
-                }
+        if let Some(peer_id) = maybe_peer {
+            if let Some(service_info) = self.discovered.get(&peer_id) {
+                let addr = service_info.addresses.first().cloned();
+                tracing::debug!("Using address {:?} for peer ID {}", addr, peer_id);
+                return Ok(addr);
+            } else {
+                tracing::warn!("Peer ID {} not found in discovered services", peer_id);
             }
-            None => {}
         }
         Ok(None)
     }
@@ -96,7 +138,7 @@ impl<R: Registry> NetworkOutgoingBehavior for Behavior<R> {
         Ok(DummyHandler)
     }
 
-    fn poll_dial(&mut self, cx: &mut Context<'_>) -> Poll<DialOpts> {
+    fn poll_dial(&mut self, _cx: &mut Context<'_>) -> Poll<DialOpts> {
         Poll::Pending
     }
 }
@@ -107,3 +149,54 @@ pub enum Event {
     Expired(ServiceInfo),
     RegistryError(RegistryError),
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use futures::FutureExt;
+
+    use super::*;
+    use crate::StaticRegistry;
+
+    fn service(load: u32) -> ServiceInfo {
+        ServiceInfo {
+            name: "api".to_string(),
+            peer_id: PeerId::random(),
+            addresses: vec!["/ip4/10.0.0.1/tcp/4001".parse().unwrap()],
+            metadata: HashMap::new(),
+            ttl: Duration::from_secs(60),
+            load,
+        }
+    }
+
+    #[test]
+    fn pick_by_strategy() {
+        let services = [service(5), service(1), service(1)];
+        let registry = StaticRegistry::new(services.clone());
+        let mut behavior = Behavior::new(&registry).unwrap();
+        while futures::future::poll_fn(|cx| behavior.poll(cx))
+            .now_or_never()
+            .is_some()
+        {}
+
+        let mut picked: Vec<PeerId> = (0..3)
+            .map(|_| {
+                behavior
+                    .pick_backend("api", Strategy::RoundRobin)
+                    .unwrap()
+                    .peer_id
+            })
+            .collect();
+        picked.sort();
+        let mut all: Vec<PeerId> = services.iter().map(|s| s.peer_id).collect();
+        all.sort();
+        assert_eq!(picked, all);
+
+        for _ in 0..4 {
+            let backend = behavior.pick_backend("api", Strategy::LeastLoaded).unwrap();
+            assert_eq!(backend.load, 1);
+        }
+        assert!(behavior.pick_backend("db", Strategy::RoundRobin).is_none());
+    }
+}
@@ -0,0 +1,75 @@
+//! 已注册服务的健康检查
+
+use std::{
+    net::{IpAddr, SocketAddr, TcpStream},
+    thread,
+    time::Duration,
+};
+
+use futures::{FutureExt, channel::oneshot, future::BoxFuture};
+use volans_core::{Multiaddr, multiaddr::Protocol};
+
+use crate::ServiceInfo;
+
+/// 检查服务是否可达，返回的 future 需要自行处理超时
+pub trait HealthCheck: Send + 'static {
+    fn check(&mut self, service: &ServiceInfo) -> BoxFuture<'static, bool>;
+}
+
+impl<F, Fut> HealthCheck for F
+where
+    F: FnMut(&ServiceInfo) -> Fut + Send + 'static,
+    Fut: Future<Output = bool> + Send + 'static,
+{
+    fn check(&mut self, service: &ServiceInfo) -> BoxFuture<'static, bool> {
+        self(service).boxed()
+    }
+}
+
+/// 以 TCP 连接检查服务地址，任一地址在超时内连接成功即视为健康
+///
+/// 连接在独立线程中进行，不依赖异步运行时，非 TCP 地址被忽略。
+#[derive(Debug, Clone)]
+pub struct TcpCheck {
+    timeout: Duration,
+}
+
+impl TcpCheck {
+    pub fn new(timeout: Duration) -> Self {
+        Self { timeout }
+    }
+}
+
+impl Default for TcpCheck {
+    fn default() -> Self {
+        Self::new(Duration::from_secs(5))
+    }
+}
+
+impl HealthCheck for TcpCheck {
+    fn check(&mut self, service: &ServiceInfo) -> BoxFuture<'static, bool> {
+        let addrs: Vec<SocketAddr> = service.addresses.iter().filter_map(socket_addr).collect();
+        let timeout = self.timeout;
+        let (sender, receiver) = oneshot::channel();
+        thread::spawn(move || {
+            let healthy = addrs
+                .iter()
+                .any(|addr| TcpStream::connect_timeout(addr, timeout).is_ok());
+            let _ = sender.send(healthy);
+        });
+        async move { receiver.await.unwrap_or(false) }.boxed()
+    }
+}
+
+fn socket_addr(addr: &Multiaddr) -> Option<SocketAddr> {
+    let mut protocols = addr.iter();
+    let ip = match protocols.next()? {
+        Protocol::Ip4(ip) => IpAddr::V4(ip),
+        Protocol::Ip6(ip) => IpAddr::V6(ip),
+        _ => return None,
+    };
+    match protocols.next()? {
+        Protocol::Tcp(port) => Some(SocketAddr::new(ip, port)),
+        _ => None,
+    }
+}
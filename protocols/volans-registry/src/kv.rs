@@ -0,0 +1,362 @@
+//! 基于外部键值存储的注册中心
+//!
+//! 服务以 `{prefix}{peer_id}` 为键、JSON 为值写入存储，并按 TTL 设置过期时间；
+//! 注册方在 TTL 过半时续期，节点退出后记录随键过期自动消失。
+//! 发现方先列出已有记录，再通过存储的变更通知产生发现与过期事件。
+
+#[cfg(feature = "redis")]
+mod redis;
+
+#[cfg(feature = "redis")]
+pub use redis::RedisStore;
+
+use std::{
+    collections::{HashMap, VecDeque},
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use async_trait::async_trait;
+use futures::{
+    FutureExt, StreamExt,
+    future::BoxFuture,
+    stream::{BoxStream, FuturesUnordered},
+};
+use futures_timer::Delay;
+use volans_core::PeerId;
+
+use crate::{
+    Discovery, DiscoveryEvent, RegisterEvent, Registry, RegistryError, ServiceInfo,
+    record::ServiceRecord,
+};
+
+const DEFAULT_PREFIX: &str = "/volans/services/";
+
+/// 键值存储的变更通知
+#[derive(Debug, Clone)]
+pub enum KvEvent {
+    Put { key: String, value: Vec<u8> },
+    Delete { key: String },
+}
+
+/// 键值变更通知流
+pub type WatchStream = BoxStream<'static, Result<KvEvent, RegistryError>>;
+
+/// 注册中心所需的异步键值存储接口
+#[async_trait]
+pub trait KvStore: Default + Clone + Send + Sync + 'static {
+    /// 写入并设置过期时间
+    async fn put(&self, key: String, value: Vec<u8>, ttl: Duration) -> Result<(), RegistryError>;
+
+    async fn delete(&self, key: String) -> Result<(), RegistryError>;
+
+    /// 列出指定前缀下的全部键值
+    async fn list(&self, prefix: String) -> Result<Vec<(String, Vec<u8>)>, RegistryError>;
+
+    /// 订阅指定前缀下的变更，键过期时产生 [`KvEvent::Delete`]
+    async fn watch(&self, prefix: String) -> Result<WatchStream, RegistryError>;
+}
+
+fn encode(service: &ServiceInfo) -> Vec<u8> {
+    serde_json::to_vec(&ServiceRecord::from(service)).expect("ServiceRecord serializes to JSON")
+}
+
+fn decode(value: &[u8]) -> Result<ServiceInfo, RegistryError> {
+    serde_json::from_slice::<ServiceRecord>(value)
+        .map(ServiceInfo::from)
+        .map_err(|e| RegistryError::Other(Box::new(e)))
+}
+
+type Operation = BoxFuture<'static, Result<Option<RegisterEvent>, RegistryError>>;
+
+pub struct KvRegistry<S: KvStore> {
+    store: S,
+    prefix: String,
+    registered: HashMap<PeerId, ServiceInfo>,
+    operations: FuturesUnordered<Operation>,
+    refresh_timer: Option<Delay>,
+}
+
+impl<S: KvStore> KvRegistry<S> {
+    pub fn new(store: S) -> Self {
+        Self {
+            store,
+            prefix: DEFAULT_PREFIX.to_string(),
+            registered: HashMap::new(),
+            operations: FuturesUnordered::new(),
+            refresh_timer: None,
+        }
+    }
+
+    /// 键前缀，注册方与发现方需一致
+    pub fn with_prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.prefix = prefix.into();
+        self
+    }
+
+    fn key(&self, peer_id: &PeerId) -> String {
+        format!("{}{}", self.prefix, peer_id)
+    }
+
+    fn put(&self, service: &ServiceInfo) -> BoxFuture<'static, Result<(), RegistryError>> {
+        let store = self.store.clone();
+        let key = self.key(&service.peer_id);
+        let value = encode(service);
+        let ttl = service.ttl;
+        async move { store.put(key, value, ttl).await }.boxed()
+    }
+
+    /// 续期间隔为已注册服务中最短 TTL 的一半
+    fn reset_refresh_timer(&mut self) {
+        self.refresh_timer = self
+            .registered
+            .values()
+            .map(|service| service.ttl / 2)
+            .min()
+            .map(Delay::new);
+    }
+}
+
+impl<S: KvStore> Default for KvRegistry<S> {
+    fn default() -> Self {
+        Self::new(S::default())
+    }
+}
+
+impl<S: KvStore> Registry for KvRegistry<S> {
+    type Discovery = KvDiscovery;
+
+    fn register(&mut self, service: ServiceInfo) -> Result<(), RegistryError> {
+        let put = self.put(&service);
+        self.registered.insert(service.peer_id, service.clone());
+        self.reset_refresh_timer();
+        self.operations.push(
+            async move { put.await.map(|()| Some(RegisterEvent::Registered(service))) }.boxed(),
+        );
+        Ok(())
+    }
+
+    fn deregister(&mut self, peer_id: PeerId) -> Result<(), RegistryError> {
+        self.registered
+            .remove(&peer_id)
+            .ok_or(RegistryError::ServiceNotFound)?;
+        self.reset_refresh_timer();
+        let store = self.store.clone();
+        let key = self.key(&peer_id);
+        self.operations.push(
+            async move {
+                store.delete(key).await?;
+                Ok(Some(RegisterEvent::Deregistered(peer_id)))
+            }
+            .boxed(),
+        );
+        Ok(())
+    }
+
+    fn discovery(&self) -> Result<Self::Discovery, RegistryError> {
+        let store = self.store.clone();
+        let prefix = self.prefix.clone();
+        let init = async move {
+            // 先订阅再列出，避免两者之间的变更丢失
+            let watch = store.watch(prefix.clone()).await?;
+            let existing = store.list(prefix).await?;
+            Ok((existing, watch))
+        }
+        .boxed();
+        Ok(KvDiscovery {
+            init: Some(init),
+            watch: None,
+            discovered: HashMap::new(),
+            pending_events: VecDeque::new(),
+        })
+    }
+
+    fn poll_next(&mut self, cx: &mut Context<'_>) -> Poll<Result<RegisterEvent, RegistryError>> {
+        if let Some(timer) = &mut self.refresh_timer
+            && timer.poll_unpin(cx).is_ready()
+        {
+            tracing::trace!("Refreshing {} registered services", self.registered.len());
+            for service in self.registered.values() {
+                let put = self.put(service);
+                self.operations
+                    .push(async move { put.await.map(|()| None) }.boxed());
+            }
+            self.reset_refresh_timer();
+            if let Some(timer) = &mut self.refresh_timer {
+                let _ = timer.poll_unpin(cx);
+            }
+        }
+        while let Poll::Ready(Some(result)) = self.operations.poll_next_unpin(cx) {
+            match result {
+                Ok(Some(event)) => return Poll::Ready(Ok(event)),
+                Ok(None) => {}
+                Err(err) => return Poll::Ready(Err(err)),
+            }
+        }
+        Poll::Pending
+    }
+}
+
+type Init = BoxFuture<'static, Result<(Vec<(String, Vec<u8>)>, WatchStream), RegistryError>>;
+
+pub struct KvDiscovery {
+    init: Option<Init>,
+    watch: Option<WatchStream>,
+    discovered: HashMap<String, ServiceInfo>,
+    pending_events: VecDeque<DiscoveryEvent>,
+}
+
+impl KvDiscovery {
+    fn on_put(&mut self, key: String, value: &[u8]) {
+        match decode(value) {
+            Ok(service) => {
+                if let Some(previous) = self.discovered.insert(key, service.clone()) {
+                    // 续期写入的内容不变时不重复通知
+                    if previous.addresses == service.addresses
+                        && previous.metadata == service.metadata
+                    {
+                        return;
+                    }
+                }
+                self.pending_events
+                    .push_back(DiscoveryEvent::Discovered(service));
+            }
+            Err(err) => tracing::warn!("Ignoring invalid service record {}: {}", key, err),
+        }
+    }
+}
+
+impl Discovery for KvDiscovery {
+    fn poll_watch(&mut self, cx: &mut Context<'_>) -> Poll<Result<DiscoveryEvent, RegistryError>> {
+        if let Some(init) = &mut self.init {
+            match init.poll_unpin(cx) {
+                Poll::Ready(Ok((existing, watch))) => {
+                    self.init = None;
+                    self.watch = Some(watch);
+                    for (key, value) in existing {
+                        self.on_put(key, &value);
+                    }
+                }
+                Poll::Ready(Err(err)) => {
+                    self.init = None;
+                    return Poll::Ready(Err(err));
+                }
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+        loop {
+            if let Some(event) = self.pending_events.pop_front() {
+                return Poll::Ready(Ok(event));
+            }
+            let Some(watch) = &mut self.watch else {
+                return Poll::Ready(Err(RegistryError::Closed));
+            };
+            match watch.poll_next_unpin(cx) {
+                Poll::Ready(Some(Ok(KvEvent::Put { key, value }))) => self.on_put(key, &value),
+                Poll::Ready(Some(Ok(KvEvent::Delete { key }))) => {
+                    if let Some(service) = self.discovered.remove(&key) {
+                        return Poll::Ready(Ok(DiscoveryEvent::Expired(service)));
+                    }
+                }
+                Poll::Ready(Some(Err(err))) => return Poll::Ready(Err(err)),
+                Poll::Ready(None) => {
+                    self.watch = None;
+                    return Poll::Ready(Err(RegistryError::Closed));
+                }
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use futures::channel::mpsc;
+
+    use super::*;
+
+    type Watcher = mpsc::UnboundedSender<Result<KvEvent, RegistryError>>;
+
+    /// 内存中的存储，忽略过期时间
+    #[derive(Clone, Default)]
+    struct MemoryStore {
+        entries: Arc<Mutex<HashMap<String, Vec<u8>>>>,
+        watchers: Arc<Mutex<Vec<Watcher>>>,
+    }
+
+    impl MemoryStore {
+        fn notify(&self, event: KvEvent) {
+            self.watchers
+                .lock()
+                .unwrap()
+                .retain(|w| w.unbounded_send(Ok(event.clone())).is_ok());
+        }
+    }
+
+    #[async_trait]
+    impl KvStore for MemoryStore {
+        async fn put(&self, key: String, value: Vec<u8>, _: Duration) -> Result<(), RegistryError> {
+            self.entries
+                .lock()
+                .unwrap()
+                .insert(key.clone(), value.clone());
+            self.notify(KvEvent::Put { key, value });
+            Ok(())
+        }
+
+        async fn delete(&self, key: String) -> Result<(), RegistryError> {
+            self.entries.lock().unwrap().remove(&key);
+            self.notify(KvEvent::Delete { key });
+            Ok(())
+        }
+
+        async fn list(&self, prefix: String) -> Result<Vec<(String, Vec<u8>)>, RegistryError> {
+            let entries = self.entries.lock().unwrap();
+            Ok(entries
+                .iter()
+                .filter(|(k, _)| k.starts_with(&prefix))
+                .map(|(k, v)| (k.clone(), v.clone()))
+                .collect())
+        }
+
+        async fn watch(&self, _prefix: String) -> Result<WatchStream, RegistryError> {
+            let (sender, receiver) = mpsc::unbounded();
+            self.watchers.lock().unwrap().push(sender);
+            Ok(receiver.boxed())
+        }
+    }
+
+    fn service(peer_id: PeerId) -> ServiceInfo {
+        ServiceInfo {
+            name: "node".to_string(),
+            peer_id,
+            addresses: vec!["/ip4/10.0.0.1/tcp/4001".parse().unwrap()],
+            metadata: HashMap::new(),
+            ttl: Duration::from_secs(60),
+            load: 0,
+        }
+    }
+
+    #[test]
+    fn register_and_discover() {
+        let store = MemoryStore::default();
+        let mut registry = KvRegistry::new(store.clone());
+        let existing = PeerId::random();
+        registry.register(service(existing)).unwrap();
+        let registered =
+            futures::executor::block_on(futures::future::poll_fn(|cx| registry.poll_next(cx)));
+        assert!(matches!(registered, Ok(RegisterEvent::Registered(_))));
+
+        let mut discovery = registry.discovery().unwrap();
+        let mut next = || futures::future::poll_fn(|cx| discovery.poll_watch(cx)).now_or_never();
+        assert!(matches!(next(), Some(Ok(DiscoveryEvent::Discovered(s))) if s.peer_id == existing));
+        assert!(next().is_none());
+
+        registry.deregister(existing).unwrap();
+        let _ = futures::future::poll_fn(|cx| registry.poll_next(cx)).now_or_never();
+        let mut next = || futures::future::poll_fn(|cx| discovery.poll_watch(cx)).now_or_never();
+        assert!(matches!(next(), Some(Ok(DiscoveryEvent::Expired(s))) if s.peer_id == existing));
+    }
+}
@@ -0,0 +1,125 @@
+use std::{sync::Arc, time::Duration};
+
+use async_trait::async_trait;
+use futures::{StreamExt, lock::Mutex};
+use redis::{AsyncCommands, Client, aio::MultiplexedConnection};
+
+use super::{KvEvent, KvStore, WatchStream};
+use crate::RegistryError;
+
+/// 基于 Redis 的存储，需要 tokio 运行时
+///
+/// 变更通知依赖 keyspace notifications，服务端需开启 `notify-keyspace-events`（至少 `Kg$x`）。
+#[derive(Clone)]
+pub struct RedisStore {
+    client: Client,
+    connection: Arc<Mutex<Option<MultiplexedConnection>>>,
+}
+
+impl RedisStore {
+    /// 首次使用时才建立连接
+    pub fn new(url: &str) -> Result<Self, RegistryError> {
+        Ok(Self {
+            client: Client::open(url)?,
+            connection: Arc::new(Mutex::new(None)),
+        })
+    }
+
+    async fn connection(&self) -> Result<MultiplexedConnection, RegistryError> {
+        let mut connection = self.connection.lock().await;
+        if let Some(connection) = connection.as_ref() {
+            return Ok(connection.clone());
+        }
+        let new = self.client.get_multiplexed_async_connection().await?;
+        *connection = Some(new.clone());
+        Ok(new)
+    }
+}
+
+impl Default for RedisStore {
+    fn default() -> Self {
+        Self::new("redis://127.0.0.1/").expect("Failed to create RedisStore")
+    }
+}
+
+#[async_trait]
+impl KvStore for RedisStore {
+    async fn put(&self, key: String, value: Vec<u8>, ttl: Duration) -> Result<(), RegistryError> {
+        let mut connection = self.connection().await?;
+        let ttl = ttl.as_millis().clamp(1, u64::MAX as u128) as u64;
+        let () = connection.pset_ex(key, value, ttl).await?;
+        Ok(())
+    }
+
+    async fn delete(&self, key: String) -> Result<(), RegistryError> {
+        let mut connection = self.connection().await?;
+        let _: usize = connection.del(key).await?;
+        Ok(())
+    }
+
+    async fn list(&self, prefix: String) -> Result<Vec<(String, Vec<u8>)>, RegistryError> {
+        let mut connection = self.connection().await?;
+        let pattern = format!("{prefix}*");
+        let mut keys = Vec::new();
+        let mut cursor = 0u64;
+        loop {
+            let (next, batch): (u64, Vec<String>) = redis::cmd("SCAN")
+                .arg(cursor)
+                .arg("MATCH")
+                .arg(&pattern)
+                .arg("COUNT")
+                .arg(100)
+                .query_async(&mut connection)
+                .await?;
+            keys.extend(batch);
+            if next == 0 {
+                break;
+            }
+            cursor = next;
+        }
+        if keys.is_empty() {
+            return Ok(Vec::new());
+        }
+        let values: Vec<Option<Vec<u8>>> = redis::cmd("MGET")
+            .arg(&keys)
+            .query_async(&mut connection)
+            .await?;
+        Ok(keys
+            .into_iter()
+            .zip(values)
+            .filter_map(|(key, value)| value.map(|value| (key, value)))
+            .collect())
+    }
+
+    async fn watch(&self, prefix: String) -> Result<WatchStream, RegistryError> {
+        let mut pubsub = self.client.get_async_pubsub().await?;
+        pubsub
+            .psubscribe(format!("__keyspace@*__:{prefix}*"))
+            .await?;
+        let connection = self.connection().await?;
+        let stream = pubsub.into_on_message().filter_map(move |msg| {
+            let mut connection = connection.clone();
+            async move {
+                // 频道名为 `__keyspace@<db>__:<key>`，负载为触发的命令
+                let key = msg.get_channel_name().split_once("__:")?.1.to_string();
+                let command: String = msg.get_payload().ok()?;
+                match command.as_str() {
+                    "set" => match connection.get::<_, Option<Vec<u8>>>(&key).await {
+                        Ok(Some(value)) => Some(Ok(KvEvent::Put { key, value })),
+                        Ok(None) => None,
+                        Err(err) => Some(Err(err.into())),
+                    },
+                    "del" | "expired" | "evicted" => Some(Ok(KvEvent::Delete { key })),
+                    _ => None,
+                }
+            }
+        });
+        Ok(stream.boxed())
+    }
+}
+
+impl From<redis::RedisError> for RegistryError {
+    fn from(error: redis::RedisError) -> Self {
+        RegistryError::Other(Box::new(error))
+    }
+}
@@ -1,6 +1,10 @@
 mod mdns;
+mod record;
+mod static_registry;
 
 pub mod discovery;
+pub mod health;
+pub mod kv;
 pub mod registry;
 
 use std::{
@@ -10,6 +14,7 @@ use std::{
 };
 
 pub use mdns::{MdnsDiscovery, MdnsRegistry};
+pub use static_registry::{StaticDiscovery, StaticRegistry};
 
 use volans_core::{Multiaddr, PeerId};
 
@@ -25,6 +30,8 @@ pub struct ServiceInfo {
     pub metadata: HashMap<String, String>,
     /// 服务的TTL（生存时间）
     pub ttl: Duration,
+    /// 节点上报的负载，数值越小越空闲，用于 [`discovery::Strategy::LeastLoaded`]
+    pub load: u32,
 }
 
 pub trait Registry: Default + Send + 'static {
@@ -74,6 +81,14 @@ pub struct Config {
     pub metadata: HashMap<String, String>,
     /// 服务的TTL（生存时间）
     pub ttl: Duration,
+    /// 健康检查的间隔，设置了 [`health::HealthCheck`] 时生效
+    pub health_check_interval: Duration,
+    /// 连续失败多少次后视为不健康
+    pub unhealthy_threshold: u32,
+    /// 不健康时是否注销服务，恢复后重新注册；为 `false` 时只上报事件
+    pub deregister_unhealthy: bool,
+    /// 采样负载的间隔，负载变化时重新注册服务
+    pub load_report_interval: Duration,
 }
 
 impl Default for Config {
@@ -82,6 +97,10 @@ impl Default for Config {
             name: "volans".to_string(),
             metadata: HashMap::new(),
             ttl: Duration::from_secs(60), // 默认TTL为60秒
+            health_check_interval: Duration::from_secs(30),
+            unhealthy_threshold: 3,
+            deregister_unhealthy: true,
+            load_report_interval: Duration::from_secs(10),
         }
     }
 }
@@ -13,6 +13,7 @@ use crate::{Discovery, DiscoveryEvent, RegisterEvent, Registry, RegistryError, S
 
 const PROPERTY_PEER_ID: &str = "PEER_ID";
 const PROPERTY_ADDR_PREFIX: &str = "DNS_ADDR_";
+const PROPERTY_LOAD: &str = "LOAD";
 
 const SERVICE_NAME_FQDN: &str = "_volans._udp.local.";
 
@@ -73,12 +74,10 @@ impl Registry for MdnsRegistry {
     }
 
     fn poll_next(&mut self, _cx: &mut Context<'_>) -> Poll<Result<RegisterEvent, RegistryError>> {
-        loop {
-            if let Some(event) = self.pending_events.pop_front() {
-                return Poll::Ready(Ok(event));
-            }
-            return Poll::Pending;
+        if let Some(event) = self.pending_events.pop_front() {
+            return Poll::Ready(Ok(event));
         }
+        Poll::Pending
     }
 }
 
@@ -104,13 +103,10 @@ impl Discovery for MdnsDiscovery {
                     }
                     ServiceEvent::ServiceRemoved(_, fullname) => {
                         tracing::trace!("mdns watch removed: {:?}", fullname);
-                        if let Some(peer_id) = self.fullname_map.remove(&fullname) {
-                            match self.discovered.remove(&peer_id) {
-                                Some(service_info) => {
-                                    return Poll::Ready(Ok(DiscoveryEvent::Expired(service_info)));
-                                }
-                                None => {}
-                            }
+                        if let Some(peer_id) = self.fullname_map.remove(&fullname)
+                            && let Some(service_info) = self.discovered.remove(&peer_id)
+                        {
+                            return Poll::Ready(Ok(DiscoveryEvent::Expired(service_info)));
                         }
                         continue;
                     }
@@ -158,6 +154,10 @@ impl TryFrom<mdns_sd::ServiceInfo> for ServiceInfo {
             })
             .map(|addr| addr.parse::<Multiaddr>())
             .collect::<Result<Vec<_>, _>>()?;
+        let load = info
+            .get_property_val_str(PROPERTY_LOAD)
+            .and_then(|load| load.parse().ok())
+            .unwrap_or_default();
 
         Ok(ServiceInfo {
             name,
@@ -165,6 +165,7 @@ impl TryFrom<mdns_sd::ServiceInfo> for ServiceInfo {
             addresses,
             metadata: HashMap::new(),
             ttl: Duration::from_secs(info.get_host_ttl() as u64),
+            load,
         })
     }
 }
@@ -176,6 +177,7 @@ impl TryFrom<ServiceInfo> for mdns_sd::ServiceInfo {
         let peer_id = service_info.peer_id.into_base58();
         let mut properties: HashMap<String, String> = HashMap::new();
         properties.insert(PROPERTY_PEER_ID.to_string(), peer_id.clone());
+        properties.insert(PROPERTY_LOAD.to_string(), service_info.load.to_string());
         for (key, value) in service_info.metadata {
             properties.insert(key, value);
         }
@@ -0,0 +1,51 @@
+use std::{collections::HashMap, time::Duration};
+
+use volans_core::{Multiaddr, PeerId};
+
+use crate::ServiceInfo;
+
+/// 未指定 TTL 时使用的值，视为不会过期
+pub(crate) const DEFAULT_TTL: Duration = Duration::from_secs(u32::MAX as u64);
+
+/// `ServiceInfo` 的 JSON 表示，用于配置文件与外部存储
+#[derive(serde::Serialize, serde::Deserialize)]
+pub(crate) struct ServiceRecord {
+    #[serde(default)]
+    name: String,
+    peer_id: PeerId,
+    addresses: Vec<Multiaddr>,
+    #[serde(default)]
+    metadata: HashMap<String, String>,
+    ttl_secs: Option<u64>,
+    #[serde(default)]
+    load: u32,
+}
+
+impl From<ServiceRecord> for ServiceInfo {
+    fn from(record: ServiceRecord) -> Self {
+        ServiceInfo {
+            name: record.name,
+            peer_id: record.peer_id,
+            addresses: record.addresses,
+            metadata: record.metadata,
+            ttl: record
+                .ttl_secs
+                .map(Duration::from_secs)
+                .unwrap_or(DEFAULT_TTL),
+            load: record.load,
+        }
+    }
+}
+
+impl From<&ServiceInfo> for ServiceRecord {
+    fn from(info: &ServiceInfo) -> Self {
+        ServiceRecord {
+            name: info.name.clone(),
+            peer_id: info.peer_id,
+            addresses: info.addresses.clone(),
+            metadata: info.metadata.clone(),
+            ttl_secs: Some(info.ttl.as_secs()),
+            load: info.load,
+        }
+    }
+}
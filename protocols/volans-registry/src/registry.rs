@@ -3,23 +3,44 @@ use std::{
     time::Duration,
 };
 
-use futures::FutureExt;
+use futures::{FutureExt, future::BoxFuture};
 use futures_timer::Delay;
 use volans_core::{Multiaddr, PeerId, multiaddr::Protocol};
 use volans_swarm::{
-    BehaviorEvent, ConnectionDenied, ConnectionId, ListenAddresses, ListenerEvent, NetworkBehavior,
-    NetworkIncomingBehavior, THandlerAction, THandlerEvent, handler::DummyHandler,
+    BehaviorEvent, ConnectionDenied, ConnectionId, ExternalAddresses, ListenAddresses,
+    ListenerEvent, NetworkBehavior, NetworkIncomingBehavior, THandlerAction, THandlerEvent,
+    handler::DummyHandler,
 };
 
-use crate::{Config, RegisterEvent, Registry, RegistryError, ServiceInfo};
+use crate::{Config, RegisterEvent, Registry, RegistryError, ServiceInfo, health::HealthCheck};
 
 pub struct Behavior<R: Registry> {
     local_peer_id: PeerId,
     registry: R,
     listen_addresses: ListenAddresses,
+    external_addresses: ExternalAddresses,
     pending_register: Option<ServiceInfo>,
     config: Config,
     retry_delay: Option<Delay>,
+    /// 需要注册的服务，不健康时注销的服务也保留在这里
+    service: Option<ServiceInfo>,
+    health: Option<Health>,
+    load: Option<LoadReporter>,
+}
+
+/// 健康检查的状态
+struct Health {
+    check: Box<dyn HealthCheck>,
+    timer: Option<Delay>,
+    running: Option<BoxFuture<'static, bool>>,
+    failures: u32,
+    unhealthy: bool,
+}
+
+struct LoadReporter {
+    source: Box<dyn FnMut() -> u32 + Send>,
+    timer: Option<Delay>,
+    current: u32,
 }
 
 impl<R: Registry> Behavior<R> {
@@ -28,15 +49,137 @@ impl<R: Registry> Behavior<R> {
             local_peer_id,
             registry,
             listen_addresses: ListenAddresses::default(),
+            external_addresses: ExternalAddresses::default(),
             pending_register: None,
             config,
             retry_delay: None,
+            service: None,
+            health: None,
+            load: None,
         }
     }
 
+    /// 定期检查注册的服务是否可达，检查间隔与阈值见 [`Config`]
+    pub fn with_health_check(mut self, check: impl HealthCheck) -> Self {
+        self.health = Some(Health {
+            check: Box::new(check),
+            timer: None,
+            running: None,
+            failures: 0,
+            unhealthy: false,
+        });
+        self
+    }
+
+    /// 按 [`Config::load_report_interval`] 采样负载，负载变化时重新注册服务
+    pub fn with_load_reporter<F>(mut self, source: F) -> Self
+    where
+        F: FnMut() -> u32 + Send + 'static,
+    {
+        self.load = Some(LoadReporter {
+            source: Box::new(source),
+            timer: None,
+            current: 0,
+        });
+        self
+    }
+
     pub fn config_mut(&mut self) -> &mut Config {
         &mut self.config
     }
+
+    /// 服务因健康检查失败被视为不健康
+    pub fn is_unhealthy(&self) -> bool {
+        self.health.as_ref().is_some_and(|health| health.unhealthy)
+    }
+
+    /// 在服务健康时安排注册
+    fn schedule_register(&mut self) {
+        // 不健康的服务等恢复后再注册
+        if self.is_unhealthy() && self.config.deregister_unhealthy {
+            return;
+        }
+        self.pending_register = self.service.clone();
+    }
+
+    fn poll_load(&mut self, cx: &mut Context<'_>) {
+        let Some(load) = &mut self.load else {
+            return;
+        };
+        loop {
+            match &mut load.timer {
+                Some(timer) => {
+                    if timer.poll_unpin(cx).is_pending() {
+                        return;
+                    }
+                    load.timer = None;
+                    let current = (load.source)();
+                    if current == load.current {
+                        continue;
+                    }
+                    load.current = current;
+                    if let Some(service) = &mut self.service {
+                        service.load = current;
+                        self.schedule_register();
+                    }
+                    return;
+                }
+                None => load.timer = Some(Delay::new(self.config.load_report_interval)),
+            }
+        }
+    }
+
+    fn poll_health(&mut self, cx: &mut Context<'_>) -> Poll<Event> {
+        let Some(health) = &mut self.health else {
+            return Poll::Pending;
+        };
+        let Some(service) = &self.service else {
+            return Poll::Pending;
+        };
+        loop {
+            if let Some(running) = &mut health.running {
+                let Poll::Ready(healthy) = running.poll_unpin(cx) else {
+                    return Poll::Pending;
+                };
+                health.running = None;
+                health.timer = Some(Delay::new(self.config.health_check_interval));
+                if healthy {
+                    health.failures = 0;
+                    if health.unhealthy {
+                        health.unhealthy = false;
+                        if self.config.deregister_unhealthy {
+                            self.pending_register = Some(service.clone());
+                        }
+                        return Poll::Ready(Event::ServiceRecovered(service.clone()));
+                    }
+                } else {
+                    health.failures += 1;
+                    tracing::debug!(failures = health.failures, "Service health check failed");
+                    if !health.unhealthy && health.failures >= self.config.unhealthy_threshold {
+                        health.unhealthy = true;
+                        if self.config.deregister_unhealthy {
+                            self.pending_register = None;
+                            if let Err(err) = self.registry.deregister(self.local_peer_id) {
+                                tracing::debug!("Failed to deregister unhealthy service: {}", err);
+                            }
+                        }
+                        return Poll::Ready(Event::ServiceUnhealthy(service.clone()));
+                    }
+                }
+                continue;
+            }
+            match &mut health.timer {
+                Some(timer) => {
+                    if timer.poll_unpin(cx).is_pending() {
+                        return Poll::Pending;
+                    }
+                    health.timer = None;
+                    health.running = Some(health.check.check(service));
+                }
+                None => health.timer = Some(Delay::new(self.config.health_check_interval)),
+            }
+        }
+    }
 }
 
 impl<R: Registry> NetworkBehavior for Behavior<R> {
@@ -56,15 +199,19 @@ impl<R: Registry> NetworkBehavior for Behavior<R> {
         &mut self,
         cx: &mut Context<'_>,
     ) -> Poll<BehaviorEvent<Self::Event, THandlerAction<Self>>> {
-        if self.retry_delay.is_none() {
-            if let Some(service_info) = self.pending_register.take() {
-                match self.registry.register(service_info.clone()) {
-                    Ok(()) => {}
-                    Err(err) => {
-                        self.pending_register = Some(service_info);
-                        self.retry_delay = Some(Delay::new(Duration::from_secs(10)));
-                        return Poll::Ready(BehaviorEvent::Behavior(Event::RegistryError(err)));
-                    }
+        if let Poll::Ready(event) = self.poll_health(cx) {
+            return Poll::Ready(BehaviorEvent::Behavior(event));
+        }
+        self.poll_load(cx);
+        if self.retry_delay.is_none()
+            && let Some(service_info) = self.pending_register.take()
+        {
+            match self.registry.register(service_info.clone()) {
+                Ok(()) => {}
+                Err(err) => {
+                    self.pending_register = Some(service_info);
+                    self.retry_delay = Some(Delay::new(Duration::from_secs(10)));
+                    return Poll::Ready(BehaviorEvent::Behavior(Event::RegistryError(err)));
                 }
             }
         }
@@ -104,14 +251,20 @@ impl<R: Registry> NetworkIncomingBehavior for Behavior<R> {
     }
 
     fn on_listener_event(&mut self, event: ListenerEvent<'_>) {
-        if self.listen_addresses.on_listener_event(&event) {
-            // 监听地址发生变化，可能需要重新注册服务
-            let address: Vec<Multiaddr> = self
-                .listen_addresses
+        let listen_changed = self.listen_addresses.on_listener_event(&event);
+        let external_changed = self.external_addresses.on_listener_event(&event);
+        if listen_changed || external_changed {
+            // 地址发生变化，可能需要重新注册服务，已确认的外部地址优先
+            let mut address: Vec<Multiaddr> = Vec::new();
+            for addr in self
+                .external_addresses
                 .iter()
-                .filter(|addr| is_network_address(addr))
-                .cloned()
-                .collect();
+                .chain(self.listen_addresses.iter())
+            {
+                if is_network_address(addr) && !address.contains(addr) {
+                    address.push(addr.clone());
+                }
+            }
 
             if address.is_empty() {
                 tracing::warn!("No valid network addresses found for registration");
@@ -124,8 +277,10 @@ impl<R: Registry> NetworkIncomingBehavior for Behavior<R> {
                 addresses: address,
                 metadata: self.config.metadata.clone(),
                 ttl: self.config.ttl,
+                load: self.load.as_ref().map_or(0, |load| load.current),
             };
-            self.pending_register = Some(info);
+            self.service = Some(info);
+            self.schedule_register();
         }
     }
 }
@@ -135,6 +290,10 @@ pub enum Event {
     Registered(ServiceInfo),
     Deregistered(PeerId),
     RegistryError(RegistryError),
+    /// 健康检查连续失败，服务被视为不健康
+    ServiceUnhealthy(ServiceInfo),
+    /// 不健康的服务重新通过健康检查
+    ServiceRecovered(ServiceInfo),
 }
 
 fn is_network_address(addr: &Multiaddr) -> bool {
@@ -150,3 +309,69 @@ fn is_network_address(addr: &Multiaddr) -> bool {
             | Protocol::Dns6(_)
     )
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    };
+
+    use futures::future;
+    use volans_swarm::{ListenerId, behavior::NewListenAddr};
+
+    use super::*;
+    use crate::StaticRegistry;
+
+    fn next_event(behavior: &mut Behavior<StaticRegistry>) -> Event {
+        futures::executor::block_on(future::poll_fn(|cx| {
+            behavior.poll(cx).map(|event| match event {
+                BehaviorEvent::Behavior(event) => event,
+                _ => unreachable!("registry only emits behavior events"),
+            })
+        }))
+    }
+
+    #[test]
+    fn deregister_unhealthy_service() {
+        let healthy = Arc::new(AtomicBool::new(true));
+        let check = {
+            let healthy = healthy.clone();
+            move |_: &ServiceInfo| future::ready(healthy.load(Ordering::SeqCst))
+        };
+        let config = Config {
+            health_check_interval: Duration::from_millis(10),
+            unhealthy_threshold: 2,
+            ..Config::default()
+        };
+        let registry = StaticRegistry::default();
+        let local_peer_id = PeerId::random();
+        let mut behavior =
+            Behavior::new(local_peer_id, registry.clone(), config).with_health_check(check);
+        let addr: Multiaddr = "/ip4/10.0.0.1/tcp/4001".parse().unwrap();
+        behavior.on_listener_event(ListenerEvent::NewListenAddr(NewListenAddr {
+            listener_id: ListenerId::next(),
+            addr: &addr,
+        }));
+        assert!(matches!(next_event(&mut behavior), Event::Registered(_)));
+
+        healthy.store(false, Ordering::SeqCst);
+        assert!(matches!(
+            next_event(&mut behavior),
+            Event::ServiceUnhealthy(_)
+        ));
+        assert!(behavior.is_unhealthy());
+        assert!(
+            matches!(next_event(&mut behavior), Event::Deregistered(peer) if peer == local_peer_id)
+        );
+        assert!(registry.services().is_empty());
+
+        healthy.store(true, Ordering::SeqCst);
+        assert!(matches!(
+            next_event(&mut behavior),
+            Event::ServiceRecovered(_)
+        ));
+        assert!(matches!(next_event(&mut behavior), Event::Registered(_)));
+        assert_eq!(registry.services().len(), 1);
+    }
+}
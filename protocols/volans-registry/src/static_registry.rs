@@ -0,0 +1,205 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    fs,
+    path::Path,
+    sync::{Arc, Mutex},
+    task::{Context, Poll},
+};
+
+use futures::{StreamExt, channel::mpsc};
+use volans_core::PeerId;
+
+use crate::{
+    Discovery, DiscoveryEvent, RegisterEvent, Registry, RegistryError, ServiceInfo,
+    record::ServiceRecord,
+};
+
+/// 由固定的服务列表构成的注册中心，用于无法使用 mDNS 的部署
+///
+/// 克隆出的实例共享同一份服务列表，运行时的增删会通知所有 [`StaticDiscovery`]。
+#[derive(Default)]
+pub struct StaticRegistry {
+    shared: Arc<Mutex<Shared>>,
+    pending_events: VecDeque<RegisterEvent>,
+}
+
+impl Clone for StaticRegistry {
+    fn clone(&self) -> Self {
+        Self {
+            shared: self.shared.clone(),
+            pending_events: VecDeque::new(),
+        }
+    }
+}
+
+#[derive(Default)]
+struct Shared {
+    services: HashMap<PeerId, ServiceInfo>,
+    watchers: Vec<mpsc::UnboundedSender<DiscoveryEvent>>,
+}
+
+impl Shared {
+    fn notify(&mut self, event: impl Fn() -> DiscoveryEvent) {
+        self.watchers
+            .retain(|watcher| watcher.unbounded_send(event()).is_ok());
+    }
+}
+
+impl StaticRegistry {
+    pub fn new(services: impl IntoIterator<Item = ServiceInfo>) -> Self {
+        let registry = Self::default();
+        for service in services {
+            registry.add(service);
+        }
+        registry
+    }
+
+    pub fn with_service(self, service: ServiceInfo) -> Self {
+        self.add(service);
+        self
+    }
+
+    /// 从 JSON 配置文件读取服务列表
+    ///
+    /// 文件内容为数组，每项包含 `peer_id`、`addresses`，可选 `name`、`metadata`、`ttl_secs`。
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, RegistryError> {
+        let data = fs::read(path).map_err(|e| RegistryError::Other(Box::new(e)))?;
+        let records: Vec<ServiceRecord> =
+            serde_json::from_slice(&data).map_err(|e| RegistryError::Other(Box::new(e)))?;
+        Ok(Self::new(records.into_iter().map(ServiceInfo::from)))
+    }
+
+    /// 添加或替换服务，并通知所有发现者
+    pub fn add(&self, service: ServiceInfo) {
+        let mut shared = self.shared.lock().expect("lock poisoned");
+        if let Some(previous) = shared.services.insert(service.peer_id, service.clone()) {
+            shared.notify(|| DiscoveryEvent::Expired(previous.clone()));
+        }
+        shared.notify(|| DiscoveryEvent::Discovered(service.clone()));
+    }
+
+    /// 移除服务，发现者收到 [`DiscoveryEvent::Expired`]
+    pub fn remove(&self, peer_id: &PeerId) -> Option<ServiceInfo> {
+        let mut shared = self.shared.lock().expect("lock poisoned");
+        let service = shared.services.remove(peer_id)?;
+        shared.notify(|| DiscoveryEvent::Expired(service.clone()));
+        Some(service)
+    }
+
+    pub fn services(&self) -> Vec<ServiceInfo> {
+        let shared = self.shared.lock().expect("lock poisoned");
+        shared.services.values().cloned().collect()
+    }
+}
+
+impl Registry for StaticRegistry {
+    type Discovery = StaticDiscovery;
+
+    fn register(&mut self, service: ServiceInfo) -> Result<(), RegistryError> {
+        self.add(service.clone());
+        self.pending_events
+            .push_back(RegisterEvent::Registered(service));
+        Ok(())
+    }
+
+    fn deregister(&mut self, peer_id: PeerId) -> Result<(), RegistryError> {
+        self.remove(&peer_id)
+            .ok_or(RegistryError::ServiceNotFound)?;
+        self.pending_events
+            .push_back(RegisterEvent::Deregistered(peer_id));
+        Ok(())
+    }
+
+    fn discovery(&self) -> Result<Self::Discovery, RegistryError> {
+        let (sender, receiver) = mpsc::unbounded();
+        let mut shared = self.shared.lock().expect("lock poisoned");
+        // 新的发现者先收到已有的全部服务
+        for service in shared.services.values() {
+            let _ = sender.unbounded_send(DiscoveryEvent::Discovered(service.clone()));
+        }
+        shared.watchers.push(sender);
+        Ok(StaticDiscovery { receiver })
+    }
+
+    fn poll_next(&mut self, _cx: &mut Context<'_>) -> Poll<Result<RegisterEvent, RegistryError>> {
+        if let Some(event) = self.pending_events.pop_front() {
+            return Poll::Ready(Ok(event));
+        }
+        Poll::Pending
+    }
+}
+
+pub struct StaticDiscovery {
+    receiver: mpsc::UnboundedReceiver<DiscoveryEvent>,
+}
+
+impl Discovery for StaticDiscovery {
+    fn poll_watch(&mut self, cx: &mut Context<'_>) -> Poll<Result<DiscoveryEvent, RegistryError>> {
+        match self.receiver.poll_next_unpin(cx) {
+            Poll::Ready(Some(event)) => Poll::Ready(Ok(event)),
+            Poll::Ready(None) => Poll::Ready(Err(RegistryError::Closed)),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use futures::FutureExt;
+
+    use super::*;
+    use crate::record::DEFAULT_TTL;
+
+    fn service(peer_id: PeerId) -> ServiceInfo {
+        ServiceInfo {
+            name: "bootstrap".to_string(),
+            peer_id,
+            addresses: vec!["/ip4/10.0.0.1/tcp/4001".parse().unwrap()],
+            metadata: HashMap::new(),
+            ttl: DEFAULT_TTL,
+            load: 0,
+        }
+    }
+
+    fn next(discovery: &mut StaticDiscovery) -> Option<DiscoveryEvent> {
+        futures::future::poll_fn(|cx| discovery.poll_watch(cx))
+            .now_or_never()
+            .map(Result::unwrap)
+    }
+
+    #[test]
+    fn seeded_and_runtime_services() {
+        let seed = PeerId::random();
+        let registry = StaticRegistry::new([service(seed)]);
+        let mut discovery = registry.discovery().unwrap();
+        assert!(
+            matches!(next(&mut discovery), Some(DiscoveryEvent::Discovered(s)) if s.peer_id == seed)
+        );
+        assert!(next(&mut discovery).is_none());
+
+        let added = PeerId::random();
+        registry.add(service(added));
+        assert!(
+            matches!(next(&mut discovery), Some(DiscoveryEvent::Discovered(s)) if s.peer_id == added)
+        );
+
+        registry.remove(&seed);
+        assert!(
+            matches!(next(&mut discovery), Some(DiscoveryEvent::Expired(s)) if s.peer_id == seed)
+        );
+    }
+
+    #[test]
+    fn records_from_json() {
+        let peer_id = PeerId::random();
+        let json = format!(
+            r#"[{{"peer_id": "{peer_id}", "addresses": ["/ip4/10.0.0.1/tcp/4001"], "ttl_secs": 30}}]"#
+        );
+        let records: Vec<ServiceRecord> = serde_json::from_str(&json).unwrap();
+        let service = ServiceInfo::from(records.into_iter().next().unwrap());
+        assert_eq!(service.peer_id, peer_id);
+        assert_eq!(service.ttl, Duration::from_secs(30));
+    }
+}
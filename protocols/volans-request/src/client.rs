@@ -4,19 +4,28 @@ pub use handler::Handler;
 
 use std::{
     collections::{HashMap, HashSet, VecDeque},
-    task::{Context, Poll},
+    io,
+    task::{Context, Poll, Waker},
+    time::Duration,
 };
 
-use smallvec::SmallVec;
+use futures::{
+    StreamExt,
+    channel::{mpsc, oneshot},
+};
+use smallvec::{SmallVec, smallvec};
 use volans_core::{Multiaddr, PeerId};
 use volans_swarm::{
     BehaviorEvent, ConnectionDenied, ConnectionId, DialOpts, NetworkBehavior,
     NetworkOutgoingBehavior, THandlerAction, THandlerEvent,
     behavior::NotifyHandler,
-    error::{ConnectionError, DialError},
+    error::{CloseReason, DialError},
 };
 
-use crate::{Codec, Config, OutboundFailure, RequestId, client::handler::OutboundRequest};
+use crate::{
+    Codec, Config, OutboundFailure, RequestId, Upgrade, Version,
+    client::handler::{Action, OutboundRequest},
+};
 
 pub struct Behavior<TCodec>
 where
@@ -26,9 +35,21 @@ where
     codec: TCodec,
     config: Config,
     pending_event: VecDeque<BehaviorEvent<Event<TCodec::Response>, THandlerAction<Self>>>,
-    pending_response: HashSet<RequestId>,
+    /// 已发送到连接上、等待响应的请求
+    pending_response: HashMap<RequestId, (PeerId, ConnectionId)>,
+    /// 每个连接上进行中的请求数量
+    inflight: HashMap<ConnectionId, usize>,
+    /// 等待连接或空闲子流的请求
     pending_requests: HashMap<PeerId, SmallVec<[OutboundRequest<TCodec>; 10]>>,
+    /// 需要拨号的节点，建立任一连接后移除
     pending_dial: HashSet<PeerId>,
+    waker: Option<Waker>,
+    /// 等待容量的 `poll_ready` 调用方
+    capacity_wakers: Vec<Waker>,
+    control_sender: mpsc::UnboundedSender<ControlRequest<TCodec>>,
+    control_receiver: mpsc::UnboundedReceiver<ControlRequest<TCodec>>,
+    /// 经 [`Control`] 发出的请求，响应直接交给调用方而不产生事件
+    control_waiters: HashMap<RequestId, ResponseSender<TCodec::Response>>,
 }
 
 impl<TCodec> Behavior<TCodec>
@@ -36,42 +57,313 @@ where
     TCodec: Codec + Clone + Send + 'static,
 {
     pub fn with_codec(codec: TCodec, config: Config) -> Self {
+        let (control_sender, control_receiver) = mpsc::unbounded();
         Self {
             clients: HashMap::new(),
             codec,
             config,
             pending_event: VecDeque::new(),
-            pending_response: HashSet::new(),
+            pending_response: HashMap::new(),
+            inflight: HashMap::new(),
             pending_requests: HashMap::new(),
             pending_dial: HashSet::new(),
+            waker: None,
+            capacity_wakers: Vec::new(),
+            control_sender,
+            control_receiver,
+            control_waiters: HashMap::new(),
         }
     }
 
+    /// 发送请求的句柄，可移动到其他任务中使用
+    pub fn control(&self) -> Control<TCodec> {
+        Control {
+            sender: self.control_sender.clone(),
+        }
+    }
+
+    /// 发送请求，等待的请求超过上限时以 [`OutboundFailure::Backpressure`] 产生 [`Event::Failure`]
     pub fn send_request(
         &mut self,
         peer_id: PeerId,
         protocol: TCodec::Protocol,
         request: TCodec::Request,
+    ) -> RequestId {
+        self.enqueue_request(peer_id, smallvec![protocol], request, None)
+    }
+
+    /// 发送请求并在多个协议版本中协商，优先使用高版本
+    ///
+    /// 协商出的版本通过 [`Event::Response`] 上报。
+    pub fn send_request_with_versions<P>(
+        &mut self,
+        peer_id: PeerId,
+        protocols: P,
+        request: TCodec::Request,
+    ) -> RequestId
+    where
+        P: IntoIterator<Item = TCodec::Protocol>,
+    {
+        let protocols = Upgrade::new_versioned(protocols).protocols;
+        self.enqueue_request(peer_id, protocols, request, None)
+    }
+
+    /// 发送请求并使用单独的超时时间，替代 `Config` 中的默认超时
+    pub fn send_request_with_timeout(
+        &mut self,
+        peer_id: PeerId,
+        protocol: TCodec::Protocol,
+        request: TCodec::Request,
+        timeout: Duration,
+    ) -> RequestId {
+        self.enqueue_request(peer_id, smallvec![protocol], request, Some(timeout))
+    }
+
+    /// 可以继续向节点发送请求时返回 `Ready`，否则在容量释放后唤醒
+    pub fn poll_ready(&mut self, peer_id: &PeerId, cx: &mut Context<'_>) -> Poll<()> {
+        if self.has_capacity(peer_id) {
+            return Poll::Ready(());
+        }
+        if !self.capacity_wakers.iter().any(|w| w.will_wake(cx.waker())) {
+            self.capacity_wakers.push(cx.waker().clone());
+        }
+        Poll::Pending
+    }
+
+    /// 取消请求并产生 [`OutboundFailure::Cancelled`]
+    ///
+    /// 已发送的请求关闭对应的子流，等待连接的请求直接移出队列。请求不存在或已完成时返回 `false`
+    pub fn cancel_request(&mut self, request_id: RequestId) -> bool {
+        if let Some((peer_id, connection_id)) = self.pending_response.get(&request_id).copied() {
+            self.remove_pending_response(request_id);
+            self.pending_event.push_back(BehaviorEvent::HandlerAction {
+                peer_id,
+                handler: NotifyHandler::One(connection_id),
+                action: Action::Cancel(request_id),
+            });
+            self.report(Event::Failure {
+                peer_id,
+                connection_id: Some(connection_id),
+                request_id,
+                cause: OutboundFailure::Cancelled,
+            });
+            self.wake();
+            return true;
+        }
+        let Some((peer_id, queue)) = self
+            .pending_requests
+            .iter_mut()
+            .find(|(_, queue)| queue.iter().any(|r| r.request_id == request_id))
+        else {
+            return false;
+        };
+        let peer_id = *peer_id;
+        queue.retain(|r| r.request_id != request_id);
+        if queue.is_empty() {
+            self.pending_requests.remove(&peer_id);
+            self.pending_dial.remove(&peer_id);
+        }
+        self.report(Event::Failure {
+            peer_id,
+            connection_id: None,
+            request_id,
+            cause: OutboundFailure::Cancelled,
+        });
+        self.wake_capacity();
+        self.wake();
+        true
+    }
+
+    /// 加入请求，无法加入时产生 [`Event::Failure`]
+    fn enqueue_request(
+        &mut self,
+        peer_id: PeerId,
+        protocols: SmallVec<[TCodec::Protocol; 2]>,
+        request: TCodec::Request,
+        timeout: Option<Duration>,
     ) -> RequestId {
         let request_id = RequestId::next();
+        if let Err(cause) =
+            self.try_enqueue_request(peer_id, request_id, protocols, request, timeout)
+        {
+            self.report(Event::Failure {
+                peer_id,
+                connection_id: None,
+                request_id,
+                cause,
+            });
+            self.wake();
+        }
+        request_id
+    }
+
+    fn try_enqueue_request(
+        &mut self,
+        peer_id: PeerId,
+        request_id: RequestId,
+        protocols: SmallVec<[TCodec::Protocol; 2]>,
+        request: TCodec::Request,
+        timeout: Option<Duration>,
+    ) -> Result<(), OutboundFailure> {
+        if protocols.is_empty() {
+            return Err(OutboundFailure::UnsupportedProtocols {
+                remote_protocols: Vec::new(),
+                local_protocols: Vec::new(),
+            });
+        }
+        if !self.has_capacity(&peer_id) {
+            return Err(OutboundFailure::Backpressure);
+        }
         let request = OutboundRequest {
             request_id,
             request,
-            protocol,
+            protocols,
+            timeout,
         };
         if let Some(request) = self.try_send_request(&peer_id, request) {
-            self.pending_dial.insert(peer_id);
+            if !self.clients.contains_key(&peer_id) {
+                self.pending_dial.insert(peer_id);
+            }
             self.pending_requests
                 .entry(peer_id)
                 .or_default()
                 .push(request);
         }
-        request_id
+        self.wake();
+        Ok(())
     }
 
-    // 移除Pending Response
+    fn has_capacity(&self, peer_id: &PeerId) -> bool {
+        self.idle_connection(peer_id).is_some()
+            || self.pending_requests.get(peer_id).map_or(0, |q| q.len())
+                < self.config.max_pending_requests_per_peer
+    }
+
+    /// 进行中请求最少且未达到上限的连接
+    fn idle_connection(&self, peer_id: &PeerId) -> Option<ConnectionId> {
+        let max = self.config.max_concurrent_requests_per_connection;
+        let inflight = |id: &ConnectionId| self.inflight.get(id).copied().unwrap_or(0);
+        self.clients
+            .get(peer_id)?
+            .iter()
+            .filter(|id| inflight(id) < max)
+            .min_by_key(|id| inflight(id))
+            .copied()
+    }
+
+    // 移除Pending Response，已取消的请求返回 `false`
     fn remove_pending_response(&mut self, request_id: RequestId) -> bool {
-        self.pending_response.remove(&request_id)
+        let Some((peer_id, connection_id)) = self.pending_response.remove(&request_id) else {
+            return false;
+        };
+        if let Some(count) = self.inflight.get_mut(&connection_id) {
+            *count = count.saturating_sub(1);
+        }
+        self.flush_pending_requests(peer_id);
+        true
+    }
+
+    /// 将等待中的请求发送到有空闲容量的连接上
+    fn flush_pending_requests(&mut self, peer_id: PeerId) {
+        if let Some(pending) = self.pending_requests.remove(&peer_id) {
+            let mut remaining = SmallVec::new();
+            let mut pending = pending.into_iter();
+            for request in pending.by_ref() {
+                if let Some(request) = self.try_send_request(&peer_id, request) {
+                    remaining.push(request);
+                    break;
+                }
+            }
+            remaining.extend(pending);
+            if !remaining.is_empty() {
+                self.pending_requests.insert(peer_id, remaining);
+            }
+        }
+        self.wake_capacity();
+    }
+
+    fn handle_control_request(&mut self, control: ControlRequest<TCodec>) {
+        let ControlRequest {
+            peer_id,
+            protocol,
+            request,
+            sender,
+        } = control;
+        if self.codec.is_streaming(&protocol) {
+            let _ = sender.send(Err(OutboundFailure::Io(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "Streaming responses are not supported by Control",
+            ))));
+            return;
+        }
+        let request_id = RequestId::next();
+        match self.try_enqueue_request(peer_id, request_id, smallvec![protocol], request, None) {
+            Ok(()) => {
+                self.control_waiters.insert(request_id, sender);
+            }
+            Err(error) => {
+                let _ = sender.send(Err(error));
+            }
+        }
+    }
+
+    /// 上报事件，[`Control`] 发出的请求的结果交给等待的调用方
+    fn report(&mut self, event: Event<TCodec::Response>) {
+        let event = match event {
+            Event::Response {
+                peer_id,
+                connection_id,
+                request_id,
+                protocol,
+                version,
+                response,
+            } => {
+                if let Some(waiter) = self.control_waiters.remove(&request_id) {
+                    let _ = waiter.send(Ok(response));
+                    return;
+                }
+                Event::Response {
+                    peer_id,
+                    connection_id,
+                    request_id,
+                    protocol,
+                    version,
+                    response,
+                }
+            }
+            Event::Failure {
+                peer_id,
+                connection_id,
+                request_id,
+                cause,
+            } => {
+                if let Some(waiter) = self.control_waiters.remove(&request_id) {
+                    let _ = waiter.send(Err(cause));
+                    return;
+                }
+                Event::Failure {
+                    peer_id,
+                    connection_id,
+                    request_id,
+                    cause,
+                }
+            }
+            event => event,
+        };
+        self.pending_event.push_back(BehaviorEvent::Behavior(event));
+    }
+
+    /// 在轮询之外产生了事件或拨号
+    fn wake(&mut self) {
+        if let Some(waker) = self.waker.take() {
+            waker.wake();
+        }
+    }
+
+    fn wake_capacity(&mut self) {
+        for waker in self.capacity_wakers.drain(..) {
+            waker.wake();
+        }
     }
 
     fn try_send_request(
@@ -79,22 +371,18 @@ where
         peer_id: &PeerId,
         request: OutboundRequest<TCodec>,
     ) -> Option<OutboundRequest<TCodec>> {
-        if let Some(connections) = self.clients.get_mut(peer_id) {
-            if connections.is_empty() {
-                return Some(request);
-            }
-            let index = request.request_id.0 & connections.len();
-            let connection_id = &mut connections[index];
-            self.pending_response.insert(request.request_id);
-            self.pending_event.push_back(BehaviorEvent::HandlerAction {
-                peer_id: *peer_id,
-                handler: NotifyHandler::One(*connection_id),
-                action: request,
-            });
-            None
-        } else {
-            Some(request)
-        }
+        let Some(connection_id) = self.idle_connection(peer_id) else {
+            return Some(request);
+        };
+        *self.inflight.entry(connection_id).or_default() += 1;
+        self.pending_response
+            .insert(request.request_id, (*peer_id, connection_id));
+        self.pending_event.push_back(BehaviorEvent::HandlerAction {
+            peer_id: *peer_id,
+            handler: NotifyHandler::One(connection_id),
+            action: Action::Request(request),
+        });
+        None
     }
 }
 
@@ -104,16 +392,94 @@ pub enum Event<TResponse> {
         peer_id: PeerId,
         connection_id: ConnectionId,
         request_id: RequestId,
+        /// 协商出的协议
+        protocol: String,
+        /// 协商出的协议版本，协议名不带版本时为 `None`
+        version: Option<Version>,
         response: TResponse,
     },
-    Failure {
+    /// 流式响应的一帧
+    ResponseChunk {
         peer_id: PeerId,
         connection_id: ConnectionId,
         request_id: RequestId,
+        chunk: TResponse,
+    },
+    /// 流式响应已结束
+    ResponseCompleted {
+        peer_id: PeerId,
+        connection_id: ConnectionId,
+        request_id: RequestId,
+    },
+    Failure {
+        peer_id: PeerId,
+        /// 请求尚未发送到连接上时为 `None`
+        connection_id: Option<ConnectionId>,
+        request_id: RequestId,
         cause: OutboundFailure,
     },
 }
 
+type ResponseSender<TResponse> = oneshot::Sender<Result<TResponse, OutboundFailure>>;
+
+struct ControlRequest<TCodec>
+where
+    TCodec: Codec,
+{
+    peer_id: PeerId,
+    protocol: TCodec::Protocol,
+    request: TCodec::Request,
+    sender: ResponseSender<TCodec::Response>,
+}
+
+/// 发送请求的句柄，可克隆
+///
+/// 请求由 [`Behavior`] 在 Swarm 轮询时发出，响应不再产生 [`Event::Response`] 或 [`Event::Failure`]。
+/// 不支持流式响应的协议。
+pub struct Control<TCodec>
+where
+    TCodec: Codec,
+{
+    sender: mpsc::UnboundedSender<ControlRequest<TCodec>>,
+}
+
+impl<TCodec> Clone for Control<TCodec>
+where
+    TCodec: Codec,
+{
+    fn clone(&self) -> Self {
+        Self {
+            sender: self.sender.clone(),
+        }
+    }
+}
+
+impl<TCodec> Control<TCodec>
+where
+    TCodec: Codec,
+{
+    /// 向 `peer_id` 发送请求，[`Behavior`] 处理完响应后返回
+    ///
+    /// 行为已被丢弃时返回 [`OutboundFailure::Cancelled`]。
+    pub async fn request(
+        &self,
+        peer_id: PeerId,
+        protocol: TCodec::Protocol,
+        request: TCodec::Request,
+    ) -> Result<TCodec::Response, OutboundFailure> {
+        let (sender, receiver) = oneshot::channel();
+        self.sender
+            .unbounded_send(ControlRequest {
+                peer_id,
+                protocol,
+                request,
+                sender,
+            })
+            .map_err(|_| OutboundFailure::Cancelled)?;
+        receiver.await.map_err(|_| OutboundFailure::Cancelled)?
+    }
+}
+
 impl<TCodec> NetworkBehavior for Behavior<TCodec>
 where
     TCodec: Codec + Clone + Send + 'static,
@@ -129,61 +495,121 @@ where
         match event {
             handler::Event::Response {
                 request_id,
+                protocol,
                 response,
             } => {
-                let removed = self.remove_pending_response(request_id);
-                debug_assert!(removed, "Response for unknown request: {request_id}");
+                if !self.remove_pending_response(request_id) {
+                    return;
+                }
+                self.report(Event::Response {
+                    peer_id,
+                    connection_id: id,
+                    request_id,
+                    protocol: protocol.as_ref().to_string(),
+                    version: Version::from_protocol(protocol.as_ref()),
+                    response,
+                });
+            }
+            handler::Event::ResponseChunk { request_id, chunk } => {
+                if !self.pending_response.contains_key(&request_id) {
+                    return;
+                }
                 self.pending_event
-                    .push_back(BehaviorEvent::Behavior(Event::Response {
+                    .push_back(BehaviorEvent::Behavior(Event::ResponseChunk {
                         peer_id,
                         connection_id: id,
                         request_id,
-                        response,
+                        chunk,
                     }));
             }
-            handler::Event::Unsupported(request_id) => {
-                let removed = self.remove_pending_response(request_id);
-                debug_assert!(removed, "Response for unknown request: {request_id}");
+            handler::Event::ResponseCompleted(request_id) => {
+                if !self.remove_pending_response(request_id) {
+                    return;
+                }
                 self.pending_event
-                    .push_back(BehaviorEvent::Behavior(Event::Failure {
+                    .push_back(BehaviorEvent::Behavior(Event::ResponseCompleted {
                         peer_id,
                         connection_id: id,
                         request_id,
-                        cause: OutboundFailure::UnsupportedProtocols,
                     }));
             }
+            handler::Event::Unsupported {
+                request_id,
+                remote_protocols,
+                local_protocols,
+            } => {
+                if !self.remove_pending_response(request_id) {
+                    return;
+                }
+                self.report(Event::Failure {
+                    peer_id,
+                    connection_id: Some(id),
+                    request_id,
+                    cause: OutboundFailure::UnsupportedProtocols {
+                        remote_protocols,
+                        local_protocols,
+                    },
+                });
+            }
+            handler::Event::RemoteReset(request_id) => {
+                if !self.remove_pending_response(request_id) {
+                    return;
+                }
+                self.report(Event::Failure {
+                    peer_id,
+                    connection_id: Some(id),
+                    request_id,
+                    cause: OutboundFailure::RemoteReset,
+                });
+            }
             handler::Event::StreamError { request_id, error } => {
-                let removed = self.remove_pending_response(request_id);
-                debug_assert!(removed, "Response for unknown request: {request_id}");
-                self.pending_event
-                    .push_back(BehaviorEvent::Behavior(Event::Failure {
-                        peer_id,
-                        connection_id: id,
-                        request_id,
-                        cause: error.into(),
-                    }));
+                if !self.remove_pending_response(request_id) {
+                    return;
+                }
+                self.report(Event::Failure {
+                    peer_id,
+                    connection_id: Some(id),
+                    request_id,
+                    cause: error.into(),
+                });
+            }
+            handler::Event::Rejected { request_id, code } => {
+                if !self.remove_pending_response(request_id) {
+                    return;
+                }
+                self.report(Event::Failure {
+                    peer_id,
+                    connection_id: Some(id),
+                    request_id,
+                    cause: OutboundFailure::Rejected(code),
+                });
             }
             handler::Event::Timeout(request_id) => {
-                let removed = self.remove_pending_response(request_id);
-                debug_assert!(removed, "Response for unknown request: {request_id}");
-                self.pending_event
-                    .push_back(BehaviorEvent::Behavior(Event::Failure {
-                        peer_id,
-                        connection_id: id,
-                        request_id,
-                        cause: OutboundFailure::Timeout,
-                    }));
+                if !self.remove_pending_response(request_id) {
+                    return;
+                }
+                self.report(Event::Failure {
+                    peer_id,
+                    connection_id: Some(id),
+                    request_id,
+                    cause: OutboundFailure::Timeout,
+                });
             }
         }
     }
 
     fn poll(
         &mut self,
-        _cx: &mut Context<'_>,
+        cx: &mut Context<'_>,
     ) -> Poll<BehaviorEvent<Self::Event, THandlerAction<Self>>> {
+        // 发送端由行为自身持有，通道不会结束
+        while let Poll::Ready(Some(control)) = self.control_receiver.poll_next_unpin(cx) {
+            self.handle_control_request(control);
+        }
         if let Some(event) = self.pending_event.pop_front() {
             return Poll::Ready(event);
         }
+        self.waker = Some(cx.waker().clone());
         Poll::Pending
     }
 }
@@ -192,18 +618,34 @@ impl<TCodec> NetworkOutgoingBehavior for Behavior<TCodec>
 where
     TCodec: Codec + Clone + Send + 'static,
 {
+    fn handle_pending_connection(
+        &mut self,
+        _id: ConnectionId,
+        _maybe_peer: Option<PeerId>,
+        addr: &Option<Multiaddr>,
+    ) -> Result<Option<Multiaddr>, ConnectionDenied> {
+        Ok(addr.clone())
+    }
+
     fn handle_established_connection(
         &mut self,
         _id: ConnectionId,
         _peer_id: PeerId,
         _addr: &Multiaddr,
     ) -> Result<Self::ConnectionHandler, ConnectionDenied> {
-        let handler = handler::Handler::new(self.codec.clone(), self.config.request_timeout);
+        let handler = handler::Handler::new(
+            self.codec.clone(),
+            self.config.request_timeout,
+            self.config.max_concurrent_requests_per_connection,
+        );
         Ok(handler)
     }
 
+    /// 无论连接由谁发起，等待中的请求都发送到新连接上，尚未发起的拨号不再需要
     fn on_connection_established(&mut self, id: ConnectionId, peer_id: PeerId, _addr: &Multiaddr) {
         self.clients.entry(peer_id).or_default().push(id);
+        self.pending_dial.remove(&peer_id);
+        self.flush_pending_requests(peer_id);
     }
 
     fn on_connection_closed(
@@ -211,8 +653,9 @@ where
         id: ConnectionId,
         peer_id: PeerId,
         _addr: &Multiaddr,
-        _reason: Option<&ConnectionError>,
+        _reason: &CloseReason,
     ) {
+        self.inflight.remove(&id);
         self.clients
             .entry(peer_id)
             .or_default()
@@ -224,6 +667,10 @@ where
             .unwrap_or(false)
         {
             self.clients.remove(&peer_id);
+            // 仍有等待中的请求时重新拨号
+            if self.pending_requests.contains_key(&peer_id) {
+                self.pending_dial.insert(peer_id);
+            }
         }
     }
 
@@ -232,29 +679,204 @@ where
         id: ConnectionId,
         peer_id: Option<PeerId>,
         _addr: Option<&Multiaddr>,
-        _error: &DialError,
+        error: &DialError,
     ) {
-        if let Some(peer) = peer_id {
-            if let Some(pending) = self.pending_requests.remove(&peer) {
-                for request in pending {
-                    let event = Event::Failure {
-                        peer_id: peer,
-                        connection_id: id,
-                        request_id: request.request_id,
-                        cause: OutboundFailure::DialFailure,
-                    };
-                    self.pending_event.push_back(BehaviorEvent::Behavior(event));
-                }
+        // 已有其他连接时请求继续等待该连接的空闲容量
+        if let Some(peer) = peer_id
+            && !self.clients.contains_key(&peer)
+            && let Some(pending) = self.pending_requests.remove(&peer)
+        {
+            for request in pending {
+                let cause = match error {
+                    DialError::NoAddress => OutboundFailure::NoKnownAddress,
+                    _ => OutboundFailure::DialFailure,
+                };
+                let event = Event::Failure {
+                    peer_id: peer,
+                    connection_id: Some(id),
+                    request_id: request.request_id,
+                    cause,
+                };
+                self.report(event);
             }
+            self.wake_capacity();
         }
     }
 
     fn poll_dial(&mut self, _cx: &mut Context<'_>) -> Poll<DialOpts> {
         if let Some(peer_id) = self.pending_dial.iter().next().cloned() {
             self.pending_dial.remove(&peer_id);
-            Poll::Ready(DialOpts::new(None, Some(peer_id)))
+            // 解析不到地址时由 Swarm 的地址簿查找，仍然没有地址则以 `NoAddress` 失败
+            let addrs = self
+                .config
+                .address_resolver
+                .as_ref()
+                .map(|resolver| resolver.resolve(&peer_id))
+                .unwrap_or_default();
+            Poll::Ready(DialOpts::peer(peer_id).with_addresses(addrs))
         } else {
             Poll::Pending
         }
     }
 }
+
+#[cfg(all(test, feature = "json"))]
+mod tests {
+    use futures::task::noop_waker_ref;
+    use volans_swarm::StreamProtocol;
+
+    use super::*;
+    use crate::codec::JsonCodec;
+
+    const ECHO: StreamProtocol = StreamProtocol::new("/echo/1.0.0");
+
+    type TestBehavior = Behavior<JsonCodec<String, String>>;
+
+    /// 发送到连接上的请求，其余事件视为失败
+    fn sent_requests(behavior: &mut TestBehavior) -> Vec<(ConnectionId, RequestId)> {
+        let mut cx = Context::from_waker(noop_waker_ref());
+        let mut sent = Vec::new();
+        while let Poll::Ready(event) = behavior.poll(&mut cx) {
+            match event {
+                BehaviorEvent::HandlerAction {
+                    handler: NotifyHandler::One(id),
+                    action: Action::Request(request),
+                    ..
+                } => sent.push((id, request.request_id)),
+                event => panic!("unexpected event: {event:?}"),
+            }
+        }
+        sent
+    }
+
+    #[test]
+    fn flush_queued_requests_on_connect() {
+        let config = Config::default().with_max_concurrent_requests_per_connection(1);
+        let mut behavior = TestBehavior::with_codec(JsonCodec::new(), config);
+        let mut cx = Context::from_waker(noop_waker_ref());
+        let peer = PeerId::random();
+        let addr: Multiaddr = "/ip4/127.0.0.1/tcp/4001".parse().unwrap();
+
+        let first = behavior.send_request(peer, ECHO, "a".into());
+        let second = behavior.send_request(peer, ECHO, "b".into());
+        assert!(sent_requests(&mut behavior).is_empty());
+
+        // 拨号发起前对端已连入
+        let id = ConnectionId::new_unchecked(1);
+        behavior.on_connection_established(id, peer, &addr);
+        assert!(behavior.poll_dial(&mut cx).is_pending());
+        assert_eq!(sent_requests(&mut behavior), [(id, first)]);
+
+        // 其他拨号失败时，请求继续等待已有连接
+        behavior.on_dial_failure(
+            ConnectionId::new_unchecked(2),
+            Some(peer),
+            None,
+            &DialError::Aborted,
+        );
+        assert!(sent_requests(&mut behavior).is_empty());
+
+        // 连接关闭后重新拨号，新连接建立时发送
+        behavior.on_connection_closed(id, peer, &addr, &CloseReason::RemoteClosed);
+        assert!(behavior.poll_dial(&mut cx).is_ready());
+        let id = ConnectionId::new_unchecked(3);
+        behavior.on_connection_established(id, peer, &addr);
+        assert_eq!(sent_requests(&mut behavior), [(id, second)]);
+    }
+
+    #[test]
+    fn report_backpressure_as_failure() {
+        let config = Config::default().with_max_pending_requests_per_peer(1);
+        let mut behavior = TestBehavior::with_codec(JsonCodec::new(), config);
+        let mut cx = Context::from_waker(noop_waker_ref());
+        let peer = PeerId::random();
+
+        behavior.send_request(peer, ECHO, "a".into());
+        assert!(behavior.poll_ready(&peer, &mut cx).is_pending());
+        let rejected = behavior.send_request(peer, ECHO, "b".into());
+        match behavior.poll(&mut cx) {
+            Poll::Ready(BehaviorEvent::Behavior(Event::Failure {
+                peer_id,
+                connection_id: None,
+                request_id,
+                cause: OutboundFailure::Backpressure,
+            })) => {
+                assert_eq!(peer_id, peer);
+                assert_eq!(request_id, rejected);
+            }
+            event => panic!("unexpected event: {event:?}"),
+        }
+    }
+
+    #[test]
+    fn cancel_queued_request() {
+        let mut behavior = TestBehavior::with_codec(JsonCodec::new(), Config::default());
+        let mut cx = Context::from_waker(noop_waker_ref());
+        let peer = PeerId::random();
+        let addr: Multiaddr = "/ip4/127.0.0.1/tcp/4001".parse().unwrap();
+
+        let cancelled = behavior.send_request(peer, ECHO, "a".into());
+        let kept = behavior.send_request(peer, ECHO, "b".into());
+        assert!(behavior.cancel_request(cancelled));
+        assert!(!behavior.cancel_request(cancelled));
+        match behavior.poll(&mut cx) {
+            Poll::Ready(BehaviorEvent::Behavior(Event::Failure {
+                connection_id: None,
+                request_id,
+                cause: OutboundFailure::Cancelled,
+                ..
+            })) => assert_eq!(request_id, cancelled),
+            event => panic!("unexpected event: {event:?}"),
+        }
+
+        // 连接建立后只发送未取消的请求
+        let id = ConnectionId::new_unchecked(1);
+        behavior.on_connection_established(id, peer, &addr);
+        assert_eq!(sent_requests(&mut behavior), [(id, kept)]);
+    }
+
+    #[test]
+    fn cancel_inflight_request() {
+        let mut behavior = TestBehavior::with_codec(JsonCodec::new(), Config::default());
+        let mut cx = Context::from_waker(noop_waker_ref());
+        let peer = PeerId::random();
+        let addr: Multiaddr = "/ip4/127.0.0.1/tcp/4001".parse().unwrap();
+
+        let id = ConnectionId::new_unchecked(1);
+        behavior.on_connection_established(id, peer, &addr);
+        let request_id = behavior.send_request(peer, ECHO, "a".into());
+        assert_eq!(sent_requests(&mut behavior), [(id, request_id)]);
+
+        assert!(behavior.cancel_request(request_id));
+        match behavior.poll(&mut cx) {
+            Poll::Ready(BehaviorEvent::HandlerAction {
+                handler: NotifyHandler::One(handler),
+                action: Action::Cancel(cancelled),
+                ..
+            }) => {
+                assert_eq!(handler, id);
+                assert_eq!(cancelled, request_id);
+            }
+            event => panic!("unexpected event: {event:?}"),
+        }
+        assert!(matches!(
+            behavior.poll(&mut cx),
+            Poll::Ready(BehaviorEvent::Behavior(Event::Failure {
+                connection_id: Some(_),
+                cause: OutboundFailure::Cancelled,
+                ..
+            }))
+        ));
+        // 对端随后返回的响应被丢弃
+        behavior.on_connection_handler_event(
+            id,
+            peer,
+            handler::Event::Response {
+                request_id,
+                protocol: ECHO,
+                response: "a".into(),
+            },
+        );
+        assert!(behavior.poll(&mut cx).is_pending());
+    }
+}
@@ -1,41 +1,105 @@
 use std::{
-    collections::VecDeque,
+    collections::{HashSet, VecDeque},
     fmt, io,
     task::{Context, Poll},
     time::Duration,
 };
 
-use futures::{AsyncWriteExt, FutureExt};
+use futures::{
+    AsyncWriteExt, StreamExt,
+    channel::mpsc,
+    future::{self, Either},
+};
 use futures_bounded::{Delay, FuturesMap};
+use smallvec::SmallVec;
 use volans_swarm::{
     ConnectionHandler, ConnectionHandlerEvent, OutboundStreamHandler, OutboundUpgradeSend,
-    StreamUpgradeError, SubstreamProtocol,
+    StreamUpgradeError, SubstreamProtocol, timer,
+};
+
+use crate::{
+    Codec, RequestId, Upgrade,
+    codec::{self, Rejected},
 };
 
-use crate::{Codec, RequestId, Upgrade};
+/// 子流的最长存活时间，请求本身的超时由每个请求单独控制
+const MAX_STREAM_LIFETIME: Duration = Duration::from_secs(60 * 60);
 
 pub struct Handler<TCodec>
 where
     TCodec: Codec,
 {
     codec: TCodec,
+    request_timeout: Duration,
     pending_outbound: VecDeque<OutboundRequest<TCodec>>,
     requested_outbound: VecDeque<OutboundRequest<TCodec>>,
+    /// 协商中被取消的请求，协商完成后直接丢弃子流
+    cancelled: HashSet<RequestId>,
     pending_events: VecDeque<Event<TCodec>>,
-    requesting: FuturesMap<RequestId, Result<Event<TCodec>, io::Error>>,
+    /// 流式响应的分片与结束事件，保证按到达顺序上报
+    stream_events: (
+        mpsc::UnboundedSender<Event<TCodec>>,
+        mpsc::UnboundedReceiver<Event<TCodec>>,
+    ),
+    /// 流式请求的事件经 `stream_events` 上报，完成时返回 `None`
+    requesting: FuturesMap<RequestId, Result<Option<Event<TCodec>>, io::Error>>,
 }
 
 impl<TCodec> Handler<TCodec>
 where
     TCodec: Codec + Send + 'static,
 {
-    pub fn new(codec: TCodec, stream_timeout: Duration) -> Self {
+    /// `request_timeout` 为未指定超时的请求使用的默认超时，
+    /// `max_concurrent_requests` 为同时进行的请求上限
+    pub fn new(codec: TCodec, request_timeout: Duration, max_concurrent_requests: usize) -> Self {
         Self {
             codec,
+            request_timeout,
             pending_outbound: VecDeque::new(),
             requested_outbound: VecDeque::new(),
+            cancelled: HashSet::new(),
             pending_events: VecDeque::new(),
-            requesting: FuturesMap::new(move || Delay::futures_timer(stream_timeout), 10),
+            stream_events: mpsc::unbounded(),
+            requesting: FuturesMap::new(
+                || Delay::futures_timer(MAX_STREAM_LIFETIME),
+                max_concurrent_requests,
+            ),
+        }
+    }
+
+    fn cancel(&mut self, request_id: RequestId) {
+        if let Some(index) = self
+            .pending_outbound
+            .iter()
+            .position(|r| r.request_id == request_id)
+        {
+            self.pending_outbound.remove(index);
+            return;
+        }
+        if self
+            .requested_outbound
+            .iter()
+            .any(|r| r.request_id == request_id)
+        {
+            self.cancelled.insert(request_id);
+            return;
+        }
+        // 丢弃请求的 Future 即关闭对应的子流
+        self.requesting.remove(request_id);
+    }
+}
+
+pub enum Action<TCodec: Codec> {
+    Request(OutboundRequest<TCodec>),
+    /// 取消请求，不再产生该请求的事件
+    Cancel(RequestId),
+}
+
+impl<TCodec: Codec> fmt::Debug for Action<TCodec> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Action::Request(request) => f.debug_tuple("Request").field(request).finish(),
+            Action::Cancel(request_id) => f.debug_tuple("Cancel").field(request_id).finish(),
         }
     }
 }
@@ -46,10 +110,31 @@ where
 {
     Response {
         request_id: RequestId,
+        /// 协商出的协议
+        protocol: TCodec::Protocol,
         response: TCodec::Response,
     },
-    Unsupported(RequestId),
+    /// 流式响应的一帧
+    ResponseChunk {
+        request_id: RequestId,
+        chunk: TCodec::Response,
+    },
+    /// 流式响应已结束
+    ResponseCompleted(RequestId),
+    /// 对端不支持请求的协议
+    Unsupported {
+        request_id: RequestId,
+        remote_protocols: Vec<String>,
+        local_protocols: Vec<String>,
+    },
     Timeout(RequestId),
+    /// 协商期间对端重置了流
+    RemoteReset(RequestId),
+    /// 服务端拒绝了请求
+    Rejected {
+        request_id: RequestId,
+        code: u32,
+    },
     StreamError {
         request_id: RequestId,
         error: io::Error,
@@ -62,18 +147,45 @@ where
 {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            Event::Response { request_id, .. } => f
+            Event::Response {
+                request_id,
+                protocol,
+                ..
+            } => f
                 .debug_struct("Response")
                 .field("request_id", request_id)
+                .field("protocol", &protocol.as_ref())
                 .finish_non_exhaustive(),
-            Event::Unsupported(request_id) => f
-                .debug_struct("UnsupportedProtocol")
+            Event::ResponseChunk { request_id, .. } => f
+                .debug_struct("ResponseChunk")
                 .field("request_id", request_id)
                 .finish_non_exhaustive(),
+            Event::ResponseCompleted(request_id) => f
+                .debug_tuple("ResponseCompleted")
+                .field(request_id)
+                .finish(),
+            Event::Unsupported {
+                request_id,
+                remote_protocols,
+                local_protocols,
+            } => f
+                .debug_struct("UnsupportedProtocol")
+                .field("request_id", request_id)
+                .field("remote_protocols", remote_protocols)
+                .field("local_protocols", local_protocols)
+                .finish(),
+            Event::RemoteReset(request_id) => {
+                f.debug_tuple("RemoteReset").field(request_id).finish()
+            }
             Event::Timeout(request_id) => f
                 .debug_struct("Timeout")
                 .field("request_id", request_id)
                 .finish_non_exhaustive(),
+            Event::Rejected { request_id, code } => f
+                .debug_struct("Rejected")
+                .field("request_id", request_id)
+                .field("code", code)
+                .finish(),
             Event::StreamError { request_id, error } => f
                 .debug_struct("StreamError")
                 .field("request_id", request_id)
@@ -86,7 +198,9 @@ where
 pub struct OutboundRequest<TCodec: Codec> {
     pub(crate) request_id: RequestId,
     pub(crate) request: TCodec::Request,
-    pub(crate) protocol: TCodec::Protocol,
+    /// 候选协议，按优先级排列
+    pub(crate) protocols: SmallVec<[TCodec::Protocol; 2]>,
+    pub(crate) timeout: Option<Duration>,
 }
 
 impl<TCodec: Codec> fmt::Debug for OutboundRequest<TCodec> {
@@ -99,11 +213,14 @@ impl<TCodec> ConnectionHandler for Handler<TCodec>
 where
     TCodec: Codec + Send + 'static,
 {
-    type Action = OutboundRequest<TCodec>;
+    type Action = Action<TCodec>;
     type Event = Event<TCodec>;
 
     fn handle_action(&mut self, action: Self::Action) {
-        self.pending_outbound.push_back(action);
+        match action {
+            Action::Request(request) => self.pending_outbound.push_back(request),
+            Action::Cancel(request_id) => self.cancel(request_id),
+        }
     }
 
     fn poll_close(&mut self, _: &mut Context<'_>) -> Poll<Option<Self::Event>> {
@@ -114,20 +231,27 @@ where
     }
 
     fn poll(&mut self, cx: &mut Context<'_>) -> Poll<ConnectionHandlerEvent<Self::Event>> {
-        match self.requesting.poll_unpin(cx) {
-            Poll::Ready((_, Ok(Ok(event)))) => {
+        loop {
+            if let Poll::Ready(Some(event)) = self.stream_events.1.poll_next_unpin(cx) {
                 return Poll::Ready(ConnectionHandlerEvent::Notify(event));
             }
-            Poll::Ready((request_id, Ok(Err(error)))) => {
-                return Poll::Ready(ConnectionHandlerEvent::Notify(Event::StreamError {
-                    request_id,
-                    error,
-                }));
-            }
-            Poll::Ready((request_id, Err(_))) => {
-                return Poll::Ready(ConnectionHandlerEvent::Notify(Event::Timeout(request_id)));
+            match self.requesting.poll_unpin(cx) {
+                Poll::Ready((_, Ok(Ok(None)))) => continue,
+                Poll::Ready((_, Ok(Ok(Some(event))))) => {
+                    return Poll::Ready(ConnectionHandlerEvent::Notify(event));
+                }
+                Poll::Ready((request_id, Ok(Err(error)))) => {
+                    let event = match Rejected::code(&error) {
+                        Some(code) => Event::Rejected { request_id, code },
+                        None => Event::StreamError { request_id, error },
+                    };
+                    return Poll::Ready(ConnectionHandlerEvent::Notify(event));
+                }
+                Poll::Ready((request_id, Err(_))) => {
+                    return Poll::Ready(ConnectionHandlerEvent::Notify(Event::Timeout(request_id)));
+                }
+                Poll::Pending => break,
             }
-            Poll::Pending => {}
         }
         if let Some(event) = self.pending_events.pop_front() {
             return Poll::Ready(ConnectionHandlerEvent::Notify(event));
@@ -153,23 +277,59 @@ where
             .pop_front()
             .expect("negotiated a stream without a pending message");
 
-        let mut codec = self.codec.clone();
         let request_id = message.request_id;
+        if self.cancelled.remove(&request_id) {
+            return;
+        }
+        let mut codec = self.codec.clone();
+        let timeout = message.timeout.unwrap_or(self.request_timeout);
+        let stream_events = self.stream_events.0.clone();
 
+        // 流式响应的超时作用于发送请求与每一帧，普通响应的超时作用于整个请求
         let fut = async move {
-            let write = codec.write_request(&protocol, &mut stream, message.request);
-            write.await?;
-            stream.close().await?;
-            let read = codec.read_response(&protocol, &mut stream);
-            let response = read.await?;
-
-            Ok(Event::Response {
-                request_id,
-                response,
-            })
+            if codec.is_streaming(&protocol) {
+                let write = async {
+                    codec
+                        .write_request(&protocol, &mut stream, message.request)
+                        .await?;
+                    stream.close().await
+                };
+                let Some(result) = within(timeout, write).await else {
+                    return Ok(Some(Event::Timeout(request_id)));
+                };
+                result?;
+                loop {
+                    let read = codec.read_response_chunk(&protocol, &mut stream);
+                    let Some(chunk) = within(timeout, read).await else {
+                        return Ok(Some(Event::Timeout(request_id)));
+                    };
+                    let Some(chunk) = chunk? else {
+                        break;
+                    };
+                    let _ =
+                        stream_events.unbounded_send(Event::ResponseChunk { request_id, chunk });
+                }
+                let _ = stream_events.unbounded_send(Event::ResponseCompleted(request_id));
+                return Ok(None);
+            }
+            let request = async {
+                let write = codec.write_request(&protocol, &mut stream, message.request);
+                write.await?;
+                stream.close().await?;
+                codec::read_status(&mut stream).await?;
+                codec.read_response(&protocol, &mut stream).await
+            };
+            match within(timeout, request).await {
+                Some(response) => Ok(Some(Event::Response {
+                    request_id,
+                    protocol,
+                    response: response?,
+                })),
+                None => Ok(Some(Event::Timeout(request_id))),
+            }
         };
 
-        if self.requesting.try_push(request_id, fut.boxed()).is_err() {
+        if self.requesting.try_push(request_id, fut).is_err() {
             self.pending_events.push_back(Event::StreamError {
                 request_id,
                 error: io::Error::other("max sub-streams reached"),
@@ -186,21 +346,34 @@ where
             .requested_outbound
             .pop_front()
             .expect("negotiated a stream without a pending message");
+        if self.cancelled.remove(&outbound.request_id) {
+            return;
+        }
 
         match error {
             StreamUpgradeError::Timeout => {
                 self.pending_events
                     .push_back(Event::Timeout(outbound.request_id));
             }
-            StreamUpgradeError::NegotiationFailed => {
+            StreamUpgradeError::NegotiationFailed {
+                remote_protocols,
+                local_protocols,
+            } => {
+                self.pending_events.push_back(Event::Unsupported {
+                    request_id: outbound.request_id,
+                    remote_protocols,
+                    local_protocols,
+                });
+            }
+            StreamUpgradeError::RemoteReset => {
                 self.pending_events
-                    .push_back(Event::Unsupported(outbound.request_id));
+                    .push_back(Event::RemoteReset(outbound.request_id));
             }
             StreamUpgradeError::Apply(_) => {}
             StreamUpgradeError::Io(error) => {
                 self.pending_events.push_back(Event::StreamError {
                     request_id: outbound.request_id,
-                    error: error,
+                    error,
                 });
             }
         }
@@ -211,10 +384,18 @@ where
         _cx: &mut Context<'_>,
     ) -> Poll<SubstreamProtocol<Self::OutboundUpgrade, Self::OutboundUserData>> {
         if let Some(request) = self.pending_outbound.pop_front() {
-            let protocol = request.protocol.clone();
+            let upgrade = Upgrade::new(request.protocols.clone());
             self.requested_outbound.push_back(request);
-            return Poll::Ready(SubstreamProtocol::new(Upgrade::new_single(protocol), ()));
+            return Poll::Ready(SubstreamProtocol::new(upgrade, ()));
         }
         Poll::Pending
     }
 }
+
+/// 在 `timeout` 内完成时返回结果，超时返回 `None`
+async fn within<F: Future>(timeout: Duration, fut: F) -> Option<F::Output> {
+    match future::select(std::pin::pin!(fut), timer::Delay::new(timeout)).await {
+        Either::Left((output, _)) => Some(output),
+        Either::Right(_) => None,
+    }
+}
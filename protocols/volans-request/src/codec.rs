@@ -13,11 +13,80 @@ pub use protobuf::ProtobufCodec;
 use std::io;
 
 use async_trait::async_trait;
-use futures::{AsyncRead, AsyncWrite};
+use futures::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+/// 流式响应帧的标记，后跟 4 字节大端长度与响应内容
+const CHUNK_DATA: u8 = 1;
+/// 流式响应的结束标记
+const CHUNK_END: u8 = 0;
+/// 服务端拒绝请求的标记，后跟 4 字节大端拒绝码
+///
+/// 非流式响应以 `CHUNK_DATA` 或该标记开头，流式响应可以在任意一帧位置拒绝。
+const CHUNK_REJECTED: u8 = 2;
+
+/// 服务端拒绝了请求
+#[derive(Debug, thiserror::Error)]
+#[error("Request rejected with code {0}")]
+pub(crate) struct Rejected(pub(crate) u32);
+
+impl Rejected {
+    /// 从读取响应的错误中取出拒绝码
+    pub(crate) fn code(error: &io::Error) -> Option<u32> {
+        error
+            .get_ref()
+            .and_then(|e| e.downcast_ref::<Rejected>())
+            .map(|Rejected(code)| *code)
+    }
+}
+
+async fn read_rejected<T>(io: &mut T) -> io::Error
+where
+    T: AsyncRead + Unpin + Send,
+{
+    let mut code = [0u8; 4];
+    match io.read_exact(&mut code).await {
+        Ok(()) => io::Error::other(Rejected(u32::from_be_bytes(code))),
+        Err(e) => e,
+    }
+}
+
+/// 读取非流式响应前的状态标记，被拒绝时返回携带拒绝码的错误
+pub(crate) async fn read_status<T>(io: &mut T) -> io::Result<()>
+where
+    T: AsyncRead + Unpin + Send,
+{
+    let mut tag = [0u8; 1];
+    io.read_exact(&mut tag).await?;
+    match tag[0] {
+        CHUNK_DATA => Ok(()),
+        CHUNK_REJECTED => Err(read_rejected(io).await),
+        _ => Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "invalid response tag",
+        )),
+    }
+}
+
+/// 写入非流式响应前的状态标记
+pub(crate) async fn write_accepted<T>(io: &mut T) -> io::Result<()>
+where
+    T: AsyncWrite + Unpin + Send,
+{
+    io.write_all(&[CHUNK_DATA]).await
+}
+
+/// 写入拒绝标记与拒绝码
+pub(crate) async fn write_rejected<T>(io: &mut T, code: u32) -> io::Result<()>
+where
+    T: AsyncWrite + Unpin + Send,
+{
+    io.write_all(&[CHUNK_REJECTED]).await?;
+    io.write_all(&code.to_be_bytes()).await
+}
 
 #[async_trait]
 pub trait Codec {
-    type Protocol: AsRef<str> + Send + Clone;
+    type Protocol: AsRef<str> + Send + Sync + Clone;
     type Request: Send;
     type Response: Send;
 
@@ -51,4 +120,61 @@ pub trait Codec {
     ) -> io::Result<()>
     where
         T: AsyncWrite + Unpin + Send;
+
+    /// 协议是否使用流式响应，服务端可以对一个请求发送多帧响应
+    fn is_streaming(&self, _protocol: &Self::Protocol) -> bool {
+        false
+    }
+
+    /// 读取一帧流式响应，读到结束标记时返回 `None`
+    async fn read_response_chunk<T>(
+        &mut self,
+        protocol: &Self::Protocol,
+        io: &mut T,
+    ) -> io::Result<Option<Self::Response>>
+    where
+        T: AsyncRead + Unpin + Send,
+    {
+        let mut tag = [0u8; 1];
+        io.read_exact(&mut tag).await?;
+        match tag[0] {
+            CHUNK_END => return Ok(None),
+            CHUNK_DATA => {}
+            CHUNK_REJECTED => return Err(read_rejected(io).await),
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "invalid chunk tag",
+                ));
+            }
+        }
+        let mut len = [0u8; 4];
+        io.read_exact(&mut len).await?;
+        let mut chunk = io.take(u32::from_be_bytes(len) as u64);
+        let response = self.read_response(protocol, &mut chunk).await?;
+        Ok(Some(response))
+    }
+
+    /// 写入一帧流式响应，`None` 写入结束标记
+    async fn write_response_chunk<T>(
+        &mut self,
+        protocol: &Self::Protocol,
+        io: &mut T,
+        chunk: Option<Self::Response>,
+    ) -> io::Result<()>
+    where
+        T: AsyncWrite + Unpin + Send,
+    {
+        let Some(response) = chunk else {
+            return io.write_all(&[CHUNK_END]).await;
+        };
+        let mut buffer = Vec::new();
+        self.write_response(protocol, &mut buffer, response).await?;
+        let len = u32::try_from(buffer.len())
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "chunk too large"))?;
+        io.write_all(&[CHUNK_DATA]).await?;
+        io.write_all(&len.to_be_bytes()).await?;
+        io.write_all(&buffer).await?;
+        Ok(())
+    }
 }
@@ -11,6 +11,7 @@ use crate::Codec;
 pub struct JsonCodec<Req, Resp> {
     request_size_maximum: u64,
     response_size_maximum: u64,
+    streaming: bool,
     phantom: PhantomData<(Req, Resp)>,
 }
 
@@ -19,6 +20,7 @@ impl<Req, Resp> Default for JsonCodec<Req, Resp> {
         JsonCodec {
             request_size_maximum: 1024 * 1024,
             response_size_maximum: 10 * 1024 * 1024,
+            streaming: false,
             phantom: PhantomData,
         }
     }
@@ -38,6 +40,12 @@ impl<Req, Resp> JsonCodec<Req, Resp> {
         self.response_size_maximum = size;
         self
     }
+
+    /// 启用流式响应，每帧大小受 `response_size_maximum` 限制
+    pub fn streaming(mut self, streaming: bool) -> Self {
+        self.streaming = streaming;
+        self
+    }
 }
 
 #[async_trait]
@@ -50,6 +58,10 @@ where
     type Request = Req;
     type Response = Resp;
 
+    fn is_streaming(&self, _: &Self::Protocol) -> bool {
+        self.streaming
+    }
+
     async fn read_request<T>(&mut self, _: &Self::Protocol, io: &mut T) -> io::Result<Self::Request>
     where
         T: AsyncRead + Unpin + Send,
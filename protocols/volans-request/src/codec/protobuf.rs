@@ -10,6 +10,7 @@ use crate::Codec;
 pub struct ProtobufCodec<Req, Resp> {
     request_size_maximum: u64,
     response_size_maximum: u64,
+    streaming: bool,
     phantom: PhantomData<(Req, Resp)>,
 }
 
@@ -18,6 +19,7 @@ impl<Req, Resp> Default for ProtobufCodec<Req, Resp> {
         ProtobufCodec {
             request_size_maximum: 1024 * 1024,
             response_size_maximum: 10 * 1024 * 1024,
+            streaming: false,
             phantom: PhantomData,
         }
     }
@@ -37,6 +39,12 @@ impl<Req, Resp> ProtobufCodec<Req, Resp> {
         self.response_size_maximum = size;
         self
     }
+
+    /// 启用流式响应，每帧大小受 `response_size_maximum` 限制
+    pub fn streaming(mut self, streaming: bool) -> Self {
+        self.streaming = streaming;
+        self
+    }
 }
 
 #[async_trait]
@@ -49,6 +57,10 @@ where
     type Request = Req;
     type Response = Resp;
 
+    fn is_streaming(&self, _: &Self::Protocol) -> bool {
+        self.streaming
+    }
+
     async fn read_request<T>(&mut self, _: &Self::Protocol, io: &mut T) -> io::Result<Self::Request>
     where
         T: AsyncRead + Unpin + Send,
@@ -3,20 +3,30 @@ pub mod codec;
 pub mod client;
 pub mod server;
 
+mod version;
+
+pub use version::{ParseVersionError, Version};
+
 use std::{
     convert::Infallible,
     fmt, io,
-    sync::atomic::{AtomicUsize, Ordering},
+    sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    },
     time::Duration,
 };
 
 pub use codec::Codec;
-use futures::{channel::oneshot, future};
+use futures::{
+    channel::{mpsc, oneshot},
+    future,
+};
 use smallvec::SmallVec;
-use volans_core::{InboundUpgrade, OutboundUpgrade, UpgradeInfo};
+use volans_core::{InboundUpgrade, Multiaddr, OutboundUpgrade, PeerId, UpgradeInfo};
 use volans_swarm::Substream;
 
-const NEXT_REQUEST_ID: AtomicUsize = AtomicUsize::new(0);
+static NEXT_REQUEST_ID: AtomicUsize = AtomicUsize::new(0);
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub struct RequestId(usize);
@@ -51,6 +61,27 @@ where
             protocols: SmallVec::from_vec(vec![protocol]),
         }
     }
+
+    /// 同一协议的多个版本按版本从高到低排列，协商时优先选择高版本
+    ///
+    /// 不带版本的协议保持原有顺序并排在最后，重复的协议只保留一个。
+    pub fn new_versioned<I>(protocols: I) -> Self
+    where
+        I: IntoIterator<Item = P>,
+    {
+        let mut protocols: SmallVec<[P; 2]> = protocols.into_iter().collect();
+        protocols.sort_by_key(|p| std::cmp::Reverse(Version::from_protocol(p.as_ref())));
+        let mut seen = std::collections::HashSet::new();
+        protocols.retain(|p| seen.insert(p.as_ref().to_string()));
+        Self { protocols }
+    }
+
+    /// 列出的协议及其版本
+    pub fn versions(&self) -> impl Iterator<Item = (&P, Option<Version>)> {
+        self.protocols
+            .iter()
+            .map(|p| (p, Version::from_protocol(p.as_ref())))
+    }
 }
 
 impl<P> UpgradeInfo for Upgrade<P>
@@ -91,42 +122,189 @@ where
     }
 }
 
+/// 服务端进行中的请求超过上限时自动拒绝使用的拒绝码
+pub const REJECT_OVERLOADED: u32 = 503;
+
+/// 响应或拒绝码
+pub(crate) type Reply<TResponse> = Result<TResponse, u32>;
+
 #[derive(Debug)]
 pub struct Responder<TResponse> {
-    tx: oneshot::Sender<TResponse>,
+    tx: ResponderTx<TResponse>,
+    protocol: String,
+}
+
+#[derive(Debug)]
+enum ResponderTx<TResponse> {
+    Single(Option<oneshot::Sender<Reply<TResponse>>>),
+    /// 流式响应，Responder 被丢弃时结束
+    Stream(mpsc::UnboundedSender<Reply<TResponse>>),
 }
 
 impl<TResponse> Responder<TResponse> {
-    pub fn send_response(self, response: TResponse) -> Result<(), TResponse> {
-        self.tx.send(response)
+    pub(crate) fn single(tx: oneshot::Sender<Reply<TResponse>>, protocol: &str) -> Self {
+        Self {
+            tx: ResponderTx::Single(Some(tx)),
+            protocol: protocol.to_string(),
+        }
+    }
+
+    pub(crate) fn stream(tx: mpsc::UnboundedSender<Reply<TResponse>>, protocol: &str) -> Self {
+        Self {
+            tx: ResponderTx::Stream(tx),
+            protocol: protocol.to_string(),
+        }
+    }
+
+    /// 与客户端协商出的协议
+    pub fn protocol(&self) -> &str {
+        &self.protocol
+    }
+
+    /// 协商出的协议版本，协议名不带版本时为 `None`
+    pub fn version(&self) -> Option<Version> {
+        Version::from_protocol(&self.protocol)
+    }
+
+    /// 请求的协议是否使用流式响应
+    pub fn is_streaming(&self) -> bool {
+        matches!(self.tx, ResponderTx::Stream(_))
+    }
+
+    /// 发送响应，流式响应时作为最后一帧发送
+    pub fn send_response(mut self, response: TResponse) -> Result<(), TResponse> {
+        self.send_chunk(response)
+    }
+
+    /// 发送一帧响应，非流式响应只能发送一次
+    pub fn send_chunk(&mut self, chunk: TResponse) -> Result<(), TResponse> {
+        self.reply(Ok(chunk)).map_err(|reply| match reply {
+            Ok(chunk) => chunk,
+            Err(_) => unreachable!(),
+        })
+    }
+
+    /// 拒绝请求，客户端收到 [`OutboundFailure::Rejected`]
+    ///
+    /// 流式响应已发送的帧仍会送达，拒绝后响应结束。
+    pub fn reject(mut self, code: u32) -> Result<(), u32> {
+        self.reply(Err(code)).map_err(|reply| match reply {
+            Err(code) => code,
+            Ok(_) => unreachable!(),
+        })
+    }
+
+    fn reply(&mut self, reply: Reply<TResponse>) -> Result<(), Reply<TResponse>> {
+        match &mut self.tx {
+            ResponderTx::Single(tx) => match tx.take() {
+                Some(tx) => tx.send(reply),
+                None => Err(reply),
+            },
+            ResponderTx::Stream(tx) => tx.unbounded_send(reply).map_err(|e| e.into_inner()),
+        }
     }
 }
 
 #[derive(Debug, Clone)]
 pub struct Config {
     request_timeout: Duration,
-    // max_concurrent_streams: usize,
+    max_pending_requests_per_peer: usize,
+    max_concurrent_requests_per_connection: usize,
+    max_inflight_requests: usize,
+    address_resolver: Option<AddressResolver>,
+}
+
+type ResolveFn = dyn Fn(&PeerId) -> Vec<Multiaddr> + Send + Sync;
+
+/// 为没有连接的节点查找拨号地址
+#[derive(Clone)]
+pub(crate) struct AddressResolver(Arc<ResolveFn>);
+
+impl AddressResolver {
+    pub(crate) fn resolve(&self, peer_id: &PeerId) -> Vec<Multiaddr> {
+        (self.0)(peer_id)
+    }
+}
+
+impl fmt::Debug for AddressResolver {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AddressResolver").finish_non_exhaustive()
+    }
 }
 
 impl Default for Config {
     fn default() -> Self {
         Self {
             request_timeout: Duration::from_secs(30),
-            // max_concurrent_streams: 100,
+            max_pending_requests_per_peer: 32,
+            max_concurrent_requests_per_connection: 10,
+            max_inflight_requests: usize::MAX,
+            address_resolver: None,
         }
     }
 }
 
+impl Config {
+    /// 未单独指定超时的请求使用的默认超时
+    pub fn with_request_timeout(mut self, timeout: Duration) -> Self {
+        self.request_timeout = timeout;
+        self
+    }
+
+    /// 每个节点等待连接或空闲子流的请求上限，超出时请求以 [`OutboundFailure::Backpressure`] 失败
+    pub fn with_max_pending_requests_per_peer(mut self, max: usize) -> Self {
+        self.max_pending_requests_per_peer = max;
+        self
+    }
+
+    /// 每个连接上同时进行的请求上限
+    pub fn with_max_concurrent_requests_per_connection(mut self, max: usize) -> Self {
+        self.max_concurrent_requests_per_connection = max;
+        self
+    }
+
+    /// 服务端等待响应的请求上限，超出时以 [`REJECT_OVERLOADED`] 拒绝新请求，默认不限制
+    pub fn with_max_inflight_requests(mut self, max: usize) -> Self {
+        self.max_inflight_requests = max;
+        self
+    }
+
+    /// 客户端拨号时查找节点地址，返回空列表时交由 Swarm 的地址簿解析
+    pub fn with_address_resolver<F>(mut self, resolver: F) -> Self
+    where
+        F: Fn(&PeerId) -> Vec<Multiaddr> + Send + Sync + 'static,
+    {
+        self.address_resolver = Some(AddressResolver(Arc::new(resolver)));
+        self
+    }
+}
+
 #[derive(Debug, thiserror::Error)]
 pub enum OutboundFailure {
     #[error("Failed to dial the remote peer")]
     DialFailure,
+    #[error("No known address for the remote peer")]
+    NoKnownAddress,
     #[error("Timeout waiting for the response")]
     Timeout,
     #[error("Connection closed before response was received")]
     ConnectionClosed,
-    #[error("Unsupported protocol for request")]
-    UnsupportedProtocols,
+    /// `remote_protocols` 为对端列出的支持协议，未能获取时为空，`local_protocols` 为本地提议的协议
+    #[error(
+        "Unsupported protocol for request, proposed {local_protocols:?}, remote supports {remote_protocols:?}"
+    )]
+    UnsupportedProtocols {
+        remote_protocols: Vec<String>,
+        local_protocols: Vec<String>,
+    },
+    #[error("Stream reset by the remote peer")]
+    RemoteReset,
+    #[error("Request was cancelled")]
+    Cancelled,
+    #[error("Too many pending requests to the remote peer")]
+    Backpressure,
+    #[error("Request rejected by the remote peer with code {0}")]
+    Rejected(u32),
     #[error("I/O error: {0}")]
     Io(#[from] io::Error),
 }
@@ -141,6 +319,8 @@ pub enum InboundFailure {
     UnsupportedProtocols,
     #[error("Response was dropped before it could be sent")]
     Discard,
+    #[error("Request rejected with code {0}")]
+    Rejected(u32),
     #[error("I/O error: {0}")]
     Io(#[from] io::Error),
 }
@@ -150,8 +330,9 @@ impl From<InboundFailure> for io::Error {
         match err {
             InboundFailure::Timeout => io::Error::new(io::ErrorKind::TimedOut, err),
             InboundFailure::ConnectionClosed => io::Error::new(io::ErrorKind::UnexpectedEof, err),
-            InboundFailure::UnsupportedProtocols => io::Error::new(io::ErrorKind::Other, err),
-            InboundFailure::Discard => io::Error::new(io::ErrorKind::Other, err),
+            InboundFailure::UnsupportedProtocols => io::Error::other(err),
+            InboundFailure::Discard => io::Error::other(err),
+            InboundFailure::Rejected(_) => io::Error::other(err),
             InboundFailure::Io(e) => e,
         }
     }
@@ -161,9 +342,14 @@ impl From<OutboundFailure> for io::Error {
     fn from(err: OutboundFailure) -> Self {
         match err {
             OutboundFailure::DialFailure => io::Error::new(io::ErrorKind::ConnectionRefused, err),
+            OutboundFailure::NoKnownAddress => io::Error::new(io::ErrorKind::NotFound, err),
             OutboundFailure::Timeout => io::Error::new(io::ErrorKind::TimedOut, err),
             OutboundFailure::ConnectionClosed => io::Error::new(io::ErrorKind::UnexpectedEof, err),
-            OutboundFailure::UnsupportedProtocols => io::Error::new(io::ErrorKind::Other, err),
+            OutboundFailure::UnsupportedProtocols { .. } => io::Error::other(err),
+            OutboundFailure::RemoteReset => io::Error::new(io::ErrorKind::ConnectionReset, err),
+            OutboundFailure::Cancelled => io::Error::new(io::ErrorKind::Interrupted, err),
+            OutboundFailure::Backpressure => io::Error::new(io::ErrorKind::WouldBlock, err),
+            OutboundFailure::Rejected(_) => io::Error::new(io::ErrorKind::ConnectionRefused, err),
             OutboundFailure::Io(e) => e,
         }
     }
@@ -8,14 +8,14 @@ use std::{
 };
 
 use smallvec::SmallVec;
-use volans_core::{PeerId, Multiaddr};
+use volans_core::{Multiaddr, PeerId};
 use volans_swarm::{
     BehaviorEvent, ConnectionDenied, ConnectionId, ListenerEvent, NetworkBehavior,
     NetworkIncomingBehavior, THandlerAction, THandlerEvent,
-    error::{ConnectionError, ListenError},
+    error::{CloseReason, ListenError},
 };
 
-use crate::{Codec, Config, InboundFailure, RequestId, Responder};
+use crate::{Codec, Config, InboundFailure, REJECT_OVERLOADED, RequestId, Responder, Upgrade};
 
 pub struct Behavior<TCodec>
 where
@@ -26,17 +26,20 @@ where
     config: Config,
     pending_event: VecDeque<Event<TCodec::Request, TCodec::Response>>,
     pending_response: HashSet<RequestId>,
+    /// 过载时自动拒绝、未上报给调用方的请求
+    shed: HashSet<RequestId>,
 }
 
 impl<TCodec> Behavior<TCodec>
 where
     TCodec: Codec + Clone + Send + 'static,
 {
+    /// 同一协议可以同时列出多个版本，协商时优先选择高版本
     pub fn with_codec<P>(codec: TCodec, protocols: P, config: Config) -> Self
     where
         P: IntoIterator<Item = TCodec::Protocol>,
     {
-        let protocols: SmallVec<[TCodec::Protocol; 2]> = protocols.into_iter().collect();
+        let protocols = Upgrade::new_versioned(protocols).protocols;
 
         Self {
             codec,
@@ -44,11 +47,12 @@ where
             protocols,
             pending_event: VecDeque::new(),
             pending_response: HashSet::new(),
+            shed: HashSet::new(),
         }
     }
 
     fn remove_pending_response(&mut self, request_id: RequestId) -> bool {
-        self.pending_response.remove(&request_id)
+        self.pending_response.remove(&request_id) || self.shed.remove(&request_id)
     }
 }
 
@@ -69,9 +73,14 @@ where
             handler::Event::Request {
                 request_id,
                 request,
-                sender,
+                responder,
             } => {
-                let responder = Responder { tx: sender };
+                if self.pending_response.len() >= self.config.max_inflight_requests {
+                    tracing::debug!(%request_id, "Too many inflight requests, rejecting");
+                    let _ = responder.reject(REJECT_OVERLOADED);
+                    self.shed.insert(request_id);
+                    return;
+                }
                 self.pending_response.insert(request_id);
                 self.pending_event.push_back(Event::Request {
                     peer_id,
@@ -110,6 +119,16 @@ where
                     cause: error.into(),
                 });
             }
+            handler::Event::Rejected { request_id, code } => {
+                let removed = self.remove_pending_response(request_id);
+                debug_assert!(removed, "Response for unknown request: {request_id}");
+                self.pending_event.push_back(Event::Failure {
+                    peer_id,
+                    connection_id: id,
+                    request_id,
+                    cause: InboundFailure::Rejected(code),
+                });
+            }
             handler::Event::Timeout(request_id) => {
                 let removed = self.remove_pending_response(request_id);
                 debug_assert!(removed, "Response for unknown request: {request_id}");
@@ -192,7 +211,7 @@ where
         _peer_id: PeerId,
         _local_addr: &Multiaddr,
         _remote_addr: &Multiaddr,
-        _reason: Option<&ConnectionError>,
+        _reason: &CloseReason,
     ) {
     }
 
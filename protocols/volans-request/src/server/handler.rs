@@ -16,7 +16,7 @@ use volans_swarm::{
     SubstreamProtocol,
 };
 
-use crate::{Codec, RequestId, Upgrade};
+use crate::{Codec, RequestId, Responder, Upgrade, codec};
 
 pub struct Handler<TCodec>
 where
@@ -24,16 +24,8 @@ where
 {
     codec: TCodec,
     protocols: SmallVec<[TCodec::Protocol; 2]>,
-    receiver: mpsc::Receiver<(
-        RequestId,
-        TCodec::Request,
-        oneshot::Sender<TCodec::Response>,
-    )>,
-    sender: mpsc::Sender<(
-        RequestId,
-        TCodec::Request,
-        oneshot::Sender<TCodec::Response>,
-    )>,
+    receiver: mpsc::Receiver<(RequestId, TCodec::Request, Responder<TCodec::Response>)>,
+    sender: mpsc::Sender<(RequestId, TCodec::Request, Responder<TCodec::Response>)>,
     requesting: FuturesMap<RequestId, Result<Event<TCodec>, io::Error>>,
 }
 
@@ -64,7 +56,7 @@ where
     Request {
         request_id: RequestId,
         request: TCodec::Request,
-        sender: oneshot::Sender<TCodec::Response>,
+        responder: Responder<TCodec::Response>,
     },
     Error {
         request_id: RequestId,
@@ -73,6 +65,11 @@ where
     Response(RequestId),
     Discard(RequestId),
     Timeout(RequestId),
+    /// 已向客户端发送拒绝码
+    Rejected {
+        request_id: RequestId,
+        code: u32,
+    },
 }
 
 impl<TCodec> fmt::Debug for Event<TCodec>
@@ -102,6 +99,11 @@ where
                 .debug_struct("InboundEvent::Timeout")
                 .field("request_id", request_id)
                 .finish(),
+            Event::Rejected { request_id, code } => f
+                .debug_struct("InboundEvent::Rejected")
+                .field("request_id", request_id)
+                .field("code", code)
+                .finish(),
         }
     }
 }
@@ -139,11 +141,11 @@ where
         }
 
         match self.receiver.poll_next_unpin(cx) {
-            Poll::Ready(Some((request_id, request, sender))) => {
+            Poll::Ready(Some((request_id, request, responder))) => {
                 return Poll::Ready(ConnectionHandlerEvent::Notify(Event::Request {
                     request_id,
                     request,
-                    sender,
+                    responder,
                 }));
             }
             Poll::Ready(None) | Poll::Pending => {}
@@ -178,22 +180,66 @@ where
         let request_id = RequestId::next();
         let mut sender = self.sender.clone();
         let fut = async move {
-            let (response_sender, response_receiver) = oneshot::channel();
             let request = codec.read_request(&protocol, &mut stream).await?;
-            sender
-                .send((request_id, request, response_sender))
-                .await
-                .expect("Request handler sender should not be closed");
-            drop(sender);
-            if let Ok(response) = response_receiver.await {
+            if codec.is_streaming(&protocol) {
+                let (chunk_sender, mut chunk_receiver) = mpsc::unbounded();
+                sender
+                    .send((
+                        request_id,
+                        request,
+                        Responder::stream(chunk_sender, protocol.as_ref()),
+                    ))
+                    .await
+                    .expect("Request handler sender should not be closed");
+                drop(sender);
+                while let Some(chunk) = chunk_receiver.next().await {
+                    match chunk {
+                        Ok(chunk) => {
+                            codec
+                                .write_response_chunk(&protocol, &mut stream, Some(chunk))
+                                .await?
+                        }
+                        Err(code) => {
+                            codec::write_rejected(&mut stream, code).await?;
+                            stream.close().await?;
+                            return Ok(Event::Rejected { request_id, code });
+                        }
+                    }
+                }
                 codec
-                    .write_response(&protocol, &mut stream, response)
+                    .write_response_chunk(&protocol, &mut stream, None)
                     .await?;
                 stream.close().await?;
                 return Ok(Event::Response(request_id));
-            } else {
-                stream.close().await?;
-                return Ok(Event::Discard(request_id));
+            }
+            let (response_sender, response_receiver) = oneshot::channel();
+            sender
+                .send((
+                    request_id,
+                    request,
+                    Responder::single(response_sender, protocol.as_ref()),
+                ))
+                .await
+                .expect("Request handler sender should not be closed");
+            drop(sender);
+            match response_receiver.await {
+                Ok(Ok(response)) => {
+                    codec::write_accepted(&mut stream).await?;
+                    codec
+                        .write_response(&protocol, &mut stream, response)
+                        .await?;
+                    stream.close().await?;
+                    Ok(Event::Response(request_id))
+                }
+                Ok(Err(code)) => {
+                    codec::write_rejected(&mut stream, code).await?;
+                    stream.close().await?;
+                    Ok(Event::Rejected { request_id, code })
+                }
+                Err(_) => {
+                    stream.close().await?;
+                    Ok(Event::Discard(request_id))
+                }
             }
         };
         match self.requesting.try_push(request_id, fut.boxed()) {
@@ -0,0 +1,79 @@
+use std::{fmt, str::FromStr};
+
+/// 协议的语义化版本，取自协议名的最后一段，例如 `/echo/1.2.0`
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Version {
+    pub major: u32,
+    pub minor: u32,
+    pub patch: u32,
+}
+
+impl Version {
+    pub const fn new(major: u32, minor: u32, patch: u32) -> Self {
+        Self {
+            major,
+            minor,
+            patch,
+        }
+    }
+
+    /// 从协议名中解析版本，最后一段不是版本号时返回 `None`
+    pub fn from_protocol(protocol: &str) -> Option<Self> {
+        protocol.rsplit('/').next()?.parse().ok()
+    }
+
+    /// 主版本相同即视为兼容
+    pub fn is_compatible(&self, other: &Version) -> bool {
+        self.major == other.major
+    }
+}
+
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("Invalid version: {0}")]
+pub struct ParseVersionError(String);
+
+impl FromStr for Version {
+    type Err = ParseVersionError;
+
+    /// 解析 `major[.minor[.patch]]`，缺省部分为 0
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || ParseVersionError(s.to_string());
+        let mut parts = s.strip_prefix('v').unwrap_or(s).split('.');
+        let mut next = |required: bool| match parts.next() {
+            Some(part) => part.parse::<u32>().map_err(|_| invalid()),
+            None if required => Err(invalid()),
+            None => Ok(0),
+        };
+        let version = Version::new(next(true)?, next(false)?, next(false)?);
+        if parts.next().is_some() {
+            return Err(invalid());
+        }
+        Ok(version)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_protocol_version() {
+        assert_eq!(
+            Version::from_protocol("/echo/1.2.3"),
+            Some(Version::new(1, 2, 3))
+        );
+        assert_eq!(
+            Version::from_protocol("/echo/v2"),
+            Some(Version::new(2, 0, 0))
+        );
+        assert_eq!(Version::from_protocol("/echo"), None);
+        assert_eq!(Version::from_protocol("/echo/1.2.3.4"), None);
+        assert!(Version::new(1, 10, 0) > Version::new(1, 9, 9));
+    }
+}
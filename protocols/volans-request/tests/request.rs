@@ -0,0 +1,567 @@
+use futures::StreamExt;
+use volans_swarm::{client, server};
+use volans_swarm_test::{SwarmExt, connect, listen, next_behavior_event};
+
+#[tokio::test(flavor = "current_thread")]
+async fn shed_inflight_requests() {
+    use volans_request::{Config, OutboundFailure, REJECT_OVERLOADED, codec::JsonCodec};
+    use volans_swarm::StreamProtocol;
+
+    const ECHO: StreamProtocol = StreamProtocol::new("/echo/1.0.0");
+    type Codec = JsonCodec<String, String>;
+
+    let mut dialer = client::Swarm::new_ephemeral(|_| {
+        volans_request::client::Behavior::with_codec(Codec::new(), Config::default())
+    });
+    let mut listener = server::Swarm::new_ephemeral(|_| {
+        volans_request::server::Behavior::with_codec(
+            Codec::new(),
+            [ECHO],
+            Config::default().with_max_inflight_requests(0),
+        )
+    });
+    connect(&mut dialer, &mut listener).await;
+
+    let listener_peer = *listener.local_peer_id();
+    tokio::spawn(async move {
+        loop {
+            listener.next().await;
+        }
+    });
+    dialer
+        .behavior_mut()
+        .send_request(listener_peer, ECHO, "ping".to_string());
+    match next_behavior_event(&mut dialer).await {
+        volans_request::client::Event::Failure {
+            cause: OutboundFailure::Rejected(code),
+            ..
+        } => assert_eq!(code, REJECT_OVERLOADED),
+        event => panic!("unexpected event: {event:?}"),
+    }
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn resolve_request_address() {
+    use volans_request::{Config, OutboundFailure, codec::JsonCodec};
+    use volans_swarm::StreamProtocol;
+
+    const ECHO: StreamProtocol = StreamProtocol::new("/echo/1.0.0");
+    type Codec = JsonCodec<String, String>;
+
+    let mut listener = server::Swarm::new_ephemeral(|_| {
+        volans_request::server::Behavior::with_codec(Codec::new(), [ECHO], Config::default())
+    });
+    let listen_addr = listen(&mut listener).await;
+    let listener_peer = *listener.local_peer_id();
+    tokio::spawn(async move {
+        loop {
+            if let volans_request::server::Event::Request {
+                request, responder, ..
+            } = next_behavior_event(&mut listener).await
+            {
+                responder.send_response(request).unwrap();
+            }
+        }
+    });
+
+    let mut dialer = client::Swarm::new_ephemeral(|_| {
+        volans_request::client::Behavior::with_codec(Codec::new(), Config::default())
+    });
+    dialer
+        .behavior_mut()
+        .send_request(listener_peer, ECHO, "ping".to_string());
+    match next_behavior_event(&mut dialer).await {
+        volans_request::client::Event::Failure {
+            cause: OutboundFailure::NoKnownAddress,
+            ..
+        } => {}
+        event => panic!("unexpected event: {event:?}"),
+    }
+
+    let config = Config::default().with_address_resolver(move |peer_id| {
+        assert_eq!(*peer_id, listener_peer);
+        vec![listen_addr.clone()]
+    });
+    let mut dialer = client::Swarm::new_ephemeral(|_| {
+        volans_request::client::Behavior::with_codec(Codec::new(), config)
+    });
+    dialer
+        .behavior_mut()
+        .send_request(listener_peer, ECHO, "ping".to_string());
+    match next_behavior_event(&mut dialer).await {
+        volans_request::client::Event::Response { response, .. } => {
+            assert_eq!(response, "ping")
+        }
+        event => panic!("unexpected event: {event:?}"),
+    }
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn request_with_control() {
+    use volans_request::{Config, codec::JsonCodec};
+    use volans_swarm::StreamProtocol;
+
+    const ECHO: StreamProtocol = StreamProtocol::new("/echo/1.0.0");
+    type Codec = JsonCodec<String, String>;
+
+    let mut dialer = client::Swarm::new_ephemeral(|_| {
+        volans_request::client::Behavior::with_codec(Codec::new(), Config::default())
+    });
+    let mut listener = server::Swarm::new_ephemeral(|_| {
+        volans_request::server::Behavior::with_codec(Codec::new(), [ECHO], Config::default())
+    });
+    connect(&mut dialer, &mut listener).await;
+
+    let listener_peer = *listener.local_peer_id();
+    tokio::spawn(async move {
+        loop {
+            if let volans_request::server::Event::Request {
+                request, responder, ..
+            } = next_behavior_event(&mut listener).await
+            {
+                responder.send_response(request).unwrap();
+            }
+        }
+    });
+    let control = dialer.behavior().control();
+    tokio::spawn(async move {
+        loop {
+            dialer.next().await;
+        }
+    });
+
+    let other = control.clone();
+    let (first, second) = futures::join!(
+        control.request(listener_peer, ECHO, "ping".to_string()),
+        other.request(listener_peer, ECHO, "pong".to_string())
+    );
+    assert_eq!(first.unwrap(), "ping");
+    assert_eq!(second.unwrap(), "pong");
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn negotiate_request_version() {
+    use volans_request::{Config, Version, codec::JsonCodec};
+    use volans_swarm::StreamProtocol;
+
+    const ECHO_V1: StreamProtocol = StreamProtocol::new("/echo/1.0.0");
+    const ECHO_V2: StreamProtocol = StreamProtocol::new("/echo/2.1.0");
+    const ECHO_V3: StreamProtocol = StreamProtocol::new("/echo/3.0.0");
+    type Codec = JsonCodec<String, String>;
+
+    let mut listener = server::Swarm::new_ephemeral(|_| {
+        volans_request::server::Behavior::with_codec(
+            Codec::new(),
+            [ECHO_V1, ECHO_V2],
+            Config::default(),
+        )
+    });
+    let listen_addr = listen(&mut listener).await;
+    let listener_peer = *listener.local_peer_id();
+    tokio::spawn(async move {
+        loop {
+            if let volans_request::server::Event::Request { responder, .. } =
+                next_behavior_event(&mut listener).await
+            {
+                let version = responder.version().unwrap().to_string();
+                responder.send_response(version).unwrap();
+            }
+        }
+    });
+
+    let config = Config::default().with_address_resolver(move |_| vec![listen_addr.clone()]);
+    let mut dialer = client::Swarm::new_ephemeral(|_| {
+        volans_request::client::Behavior::with_codec(Codec::new(), config)
+    });
+    dialer.behavior_mut().send_request_with_versions(
+        listener_peer,
+        [ECHO_V1, ECHO_V3, ECHO_V2],
+        "ping".to_string(),
+    );
+    match next_behavior_event(&mut dialer).await {
+        volans_request::client::Event::Response {
+            protocol,
+            version,
+            response,
+            ..
+        } => {
+            assert_eq!(protocol, ECHO_V2.as_ref());
+            assert_eq!(version, Some(Version::new(2, 1, 0)));
+            assert_eq!(response, "2.1.0");
+        }
+        event => panic!("unexpected event: {event:?}"),
+    }
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn report_remote_protocols() {
+    use volans_request::{Config, OutboundFailure, codec::JsonCodec};
+    use volans_swarm::StreamProtocol;
+
+    const ECHO: StreamProtocol = StreamProtocol::new("/echo/1.0.0");
+    const UNKNOWN: StreamProtocol = StreamProtocol::new("/unknown/1.0.0");
+    type Codec = JsonCodec<String, String>;
+
+    let mut listener = server::Swarm::new_ephemeral(|_| {
+        volans_request::server::Behavior::with_codec(Codec::new(), [ECHO], Config::default())
+    });
+    let listen_addr = listen(&mut listener).await;
+    let listener_peer = *listener.local_peer_id();
+    tokio::spawn(async move {
+        loop {
+            listener.next().await;
+        }
+    });
+
+    let config = Config::default().with_address_resolver(move |_| vec![listen_addr.clone()]);
+    let mut dialer = client::Swarm::new_ephemeral(|_| {
+        volans_request::client::Behavior::with_codec(Codec::new(), config)
+    });
+    dialer
+        .behavior_mut()
+        .send_request(listener_peer, UNKNOWN, "ping".to_string());
+    match next_behavior_event(&mut dialer).await {
+        volans_request::client::Event::Failure {
+            cause:
+                OutboundFailure::UnsupportedProtocols {
+                    remote_protocols,
+                    local_protocols,
+                },
+            ..
+        } => {
+            assert_eq!(remote_protocols, vec![ECHO.to_string()]);
+            assert_eq!(local_protocols, vec![UNKNOWN.to_string()]);
+        }
+        event => panic!("unexpected event: {event:?}"),
+    }
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn reuse_negotiated_protocol() {
+    use volans_request::{Config, codec::JsonCodec};
+    use volans_swarm::StreamProtocol;
+
+    const ECHO: StreamProtocol = StreamProtocol::new("/echo/1.0.0");
+    type Codec = JsonCodec<String, String>;
+
+    let mut listener = server::Swarm::new_ephemeral(|_| {
+        volans_request::server::Behavior::with_codec(Codec::new(), [ECHO], Config::default())
+    });
+    let listen_addr = listen(&mut listener).await;
+    let listener_peer = *listener.local_peer_id();
+    tokio::spawn(async move {
+        loop {
+            if let volans_request::server::Event::Request {
+                request, responder, ..
+            } = next_behavior_event(&mut listener).await
+            {
+                responder.send_response(request).unwrap();
+            }
+        }
+    });
+
+    let config = Config::default().with_address_resolver(move |_| vec![listen_addr.clone()]);
+    let mut dialer = client::Swarm::new_ephemeral(|_| {
+        volans_request::client::Behavior::with_codec(Codec::new(), config)
+    });
+    // 第二个请求使用连接上缓存的协商结果
+    for request in ["first", "second"] {
+        dialer
+            .behavior_mut()
+            .send_request(listener_peer, ECHO, request.to_string());
+        match next_behavior_event(&mut dialer).await {
+            volans_request::client::Event::Response { response, .. } => {
+                assert_eq!(response, request)
+            }
+            event => panic!("unexpected event: {event:?}"),
+        }
+    }
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn match_responses_by_request_id() {
+    use std::collections::HashMap;
+    use volans_request::{Config, codec::JsonCodec};
+    use volans_swarm::StreamProtocol;
+
+    const ECHO: StreamProtocol = StreamProtocol::new("/echo/1.0.0");
+    type Codec = JsonCodec<String, String>;
+
+    let mut dialer = client::Swarm::new_ephemeral(|_| {
+        volans_request::client::Behavior::with_codec(Codec::new(), Config::default())
+    });
+    let mut listener = server::Swarm::new_ephemeral(|_| {
+        volans_request::server::Behavior::with_codec(Codec::new(), [ECHO], Config::default())
+    });
+    connect(&mut dialer, &mut listener).await;
+
+    let listener_peer = *listener.local_peer_id();
+    tokio::spawn(async move {
+        loop {
+            if let volans_request::server::Event::Request {
+                request, responder, ..
+            } = next_behavior_event(&mut listener).await
+            {
+                responder.send_response(request).unwrap();
+            }
+        }
+    });
+
+    let mut pending = HashMap::new();
+    for request in ["first", "second", "third"] {
+        let request_id =
+            dialer
+                .behavior_mut()
+                .send_request(listener_peer, ECHO, request.to_string());
+        assert!(pending.insert(request_id, request).is_none());
+    }
+    while !pending.is_empty() {
+        match next_behavior_event(&mut dialer).await {
+            volans_request::client::Event::Response {
+                request_id,
+                response,
+                ..
+            } => assert_eq!(pending.remove(&request_id), Some(response.as_str())),
+            event => panic!("unexpected event: {event:?}"),
+        }
+    }
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn stream_timeout_applies_per_chunk() {
+    use std::time::Duration;
+
+    use volans_request::{Config, codec::JsonCodec};
+    use volans_swarm::StreamProtocol;
+
+    const LOG: StreamProtocol = StreamProtocol::new("/log/1.0.0");
+    const CHUNKS: usize = 5;
+    type Codec = JsonCodec<String, String>;
+
+    let mut dialer = client::Swarm::new_ephemeral(|_| {
+        volans_request::client::Behavior::with_codec(
+            Codec::new().streaming(true),
+            Config::default().with_request_timeout(Duration::from_millis(200)),
+        )
+    });
+    let mut listener = server::Swarm::new_ephemeral(|_| {
+        volans_request::server::Behavior::with_codec(
+            Codec::new().streaming(true),
+            [LOG],
+            Config::default(),
+        )
+    });
+    connect(&mut dialer, &mut listener).await;
+
+    // 每帧间隔小于超时，整个响应的耗时超过超时
+    let listener_peer = *listener.local_peer_id();
+    tokio::spawn(async move {
+        loop {
+            if let volans_request::server::Event::Request { mut responder, .. } =
+                next_behavior_event(&mut listener).await
+            {
+                tokio::spawn(async move {
+                    for i in 0..CHUNKS {
+                        tokio::time::sleep(Duration::from_millis(100)).await;
+                        responder.send_chunk(i.to_string()).unwrap();
+                    }
+                });
+            }
+        }
+    });
+    dialer
+        .behavior_mut()
+        .send_request(listener_peer, LOG, "tail".to_string());
+    let mut chunks = Vec::new();
+    loop {
+        match next_behavior_event(&mut dialer).await {
+            volans_request::client::Event::ResponseChunk { chunk, .. } => chunks.push(chunk),
+            volans_request::client::Event::ResponseCompleted { .. } => break,
+            event => panic!("unexpected event: {event:?}"),
+        }
+    }
+    assert_eq!(
+        chunks,
+        (0..CHUNKS).map(|i| i.to_string()).collect::<Vec<_>>()
+    );
+}
+
+#[test]
+fn time_out_on_simulated_clock() {
+    use std::time::Duration;
+
+    use volans_request::{Config, OutboundFailure, codec::JsonCodec};
+    use volans_swarm::StreamProtocol;
+    use volans_swarm_test::sim::{self, SimSwarm, Simulation};
+
+    const ECHO: StreamProtocol = StreamProtocol::new("/echo/1.0.0");
+    type Codec = JsonCodec<String, String>;
+
+    let sim = Simulation::new();
+    let mut dialer: SimSwarm<client::Swarm<_>> = SimSwarm::new(&sim, |_| {
+        volans_request::client::Behavior::with_codec(Codec::new(), Config::default())
+    });
+    let mut listener: SimSwarm<server::Swarm<_>> = SimSwarm::new(&sim, |_| {
+        volans_request::server::Behavior::with_codec(Codec::new(), [ECHO], Config::default())
+    });
+    sim::connect(&sim, &mut dialer, &mut listener);
+    let listener_peer = *listener.local_peer_id();
+    let timeout = Duration::from_secs(5);
+
+    // 在超时之前应答
+    let answered = dialer.behavior_mut().send_request_with_timeout(
+        listener_peer,
+        ECHO,
+        "first".to_string(),
+        timeout,
+    );
+    sim::run_until_stalled(&sim, &mut [&mut dialer, &mut listener]);
+    let Some(volans_request::server::Event::Request { responder, .. }) =
+        listener.take_behavior_events().pop()
+    else {
+        panic!("listener received no request");
+    };
+    sim::advance(
+        &sim,
+        &mut [&mut dialer, &mut listener],
+        Duration::from_secs(4),
+    );
+    responder.send_response("first".to_string()).unwrap();
+    sim::run_until_stalled(&sim, &mut [&mut dialer, &mut listener]);
+    match dialer.take_behavior_events().as_slice() {
+        [volans_request::client::Event::Response { request_id, .. }] => {
+            assert_eq!(*request_id, answered)
+        }
+        events => panic!("unexpected events: {events:?}"),
+    }
+
+    // 不应答时恰好在超时到达时失败
+    let expired = dialer.behavior_mut().send_request_with_timeout(
+        listener_peer,
+        ECHO,
+        "second".to_string(),
+        timeout,
+    );
+    sim::run_until_stalled(&sim, &mut [&mut dialer, &mut listener]);
+    let _responder = listener.take_behavior_events().pop();
+    sim::advance(
+        &sim,
+        &mut [&mut dialer, &mut listener],
+        timeout - Duration::from_millis(1),
+    );
+    assert!(dialer.take_behavior_events().is_empty());
+    sim::advance(
+        &sim,
+        &mut [&mut dialer, &mut listener],
+        Duration::from_millis(1),
+    );
+    match dialer.take_behavior_events().as_slice() {
+        [
+            volans_request::client::Event::Failure {
+                request_id,
+                cause: OutboundFailure::Timeout,
+                ..
+            },
+        ] => assert_eq!(*request_id, expired),
+        events => panic!("unexpected events: {events:?}"),
+    }
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn report_stream_reset_by_remote() {
+    use volans_request::{Config, OutboundFailure, codec::JsonCodec};
+    use volans_swarm::{StreamProtocol, connection::PoolConfig};
+    use volans_swarm_test::{ephemeral_key_pair, ephemeral_parts_with_muxer};
+
+    const ECHO: StreamProtocol = StreamProtocol::new("/echo/1.0.0");
+    type Codec = JsonCodec<String, String>;
+
+    // 对端的多路复用器丢弃所有入站子流，协商期间子流被重置
+    let mut muxer = volans_muxing::Config::new();
+    muxer
+        .set_max_buffered_inbound_streams(0)
+        .set_inbound_overflow(volans_muxing::InboundOverflow::Drop);
+    let (transport, listener_peer) = ephemeral_parts_with_muxer(&ephemeral_key_pair(), muxer);
+    let mut listener = server::Swarm::from_parts(
+        transport,
+        volans_request::server::Behavior::with_codec(Codec::new(), [ECHO], Config::default()),
+        listener_peer,
+        PoolConfig::with_tokio_executor(),
+    );
+    let mut dialer = client::Swarm::new_ephemeral(|_| {
+        volans_request::client::Behavior::with_codec(Codec::new(), Config::default())
+    });
+    connect(&mut dialer, &mut listener).await;
+    tokio::spawn(async move {
+        loop {
+            listener.next().await;
+        }
+    });
+
+    dialer
+        .behavior_mut()
+        .send_request(listener_peer, ECHO, "ping".to_string());
+    match next_behavior_event(&mut dialer).await {
+        volans_request::client::Event::Failure {
+            cause: OutboundFailure::RemoteReset,
+            ..
+        } => {}
+        event => panic!("unexpected event: {event:?}"),
+    }
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn dropped_control_request_keeps_later_events() {
+    use futures::FutureExt;
+    use volans_request::{Config, codec::JsonCodec};
+    use volans_swarm::StreamProtocol;
+
+    const ECHO: StreamProtocol = StreamProtocol::new("/echo/1.0.0");
+    type Codec = JsonCodec<String, String>;
+
+    let mut dialer = client::Swarm::new_ephemeral(|_| {
+        volans_request::client::Behavior::with_codec(Codec::new(), Config::default())
+    });
+    let mut listener = server::Swarm::new_ephemeral(|_| {
+        volans_request::server::Behavior::with_codec(Codec::new(), [ECHO], Config::default())
+    });
+    connect(&mut dialer, &mut listener).await;
+    let listener_peer = *listener.local_peer_id();
+    tokio::spawn(async move {
+        loop {
+            if let volans_request::server::Event::Request {
+                request, responder, ..
+            } = next_behavior_event(&mut listener).await
+            {
+                responder.send_response(request).unwrap();
+            }
+        }
+    });
+
+    // 请求已交给行为，等待响应的 future 随即被丢弃
+    let control = dialer.behavior().control();
+    let mut dropped = Box::pin(control.request(listener_peer, ECHO, "dropped".to_string()));
+    assert!(dropped.as_mut().now_or_never().is_none());
+    drop(dropped);
+
+    let expected = dialer
+        .behavior_mut()
+        .send_request(listener_peer, ECHO, "event".to_string());
+    match next_behavior_event(&mut dialer).await {
+        volans_request::client::Event::Response {
+            request_id,
+            response,
+            ..
+        } => {
+            assert_eq!(request_id, expected);
+            assert_eq!(response, "event");
+        }
+        event => panic!("unexpected event: {event:?}"),
+    }
+
+    let response = tokio::select! {
+        response = control.request(listener_peer, ECHO, "control".to_string()) => response,
+        event = next_behavior_event(&mut dialer) => panic!("unexpected event: {event:?}"),
+    };
+    assert_eq!(response.unwrap(), "control");
+}
@@ -2,14 +2,21 @@ mod behavior;
 mod handler;
 mod shared;
 
-pub use behavior::{Behavior, Controller};
+pub use behavior::{Behavior, Control};
 pub use handler::Handler;
 
 use volans_swarm::StreamProtocol;
 
-#[derive(Debug)]
+/// 打开出站流失败
+#[derive(Debug, thiserror::Error)]
 #[non_exhaustive]
-pub enum StreamError {
+pub enum OpenStreamError {
+    #[error("Remote does not support protocol {0}")]
     Unsupported(StreamProtocol),
-    Io(std::io::Error),
+    #[error("Stream protocol negotiation timed out")]
+    Timeout,
+    #[error("Stream reset by remote during negotiation")]
+    RemoteReset,
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
 }
@@ -3,6 +3,7 @@ use std::{
     io,
     sync::Arc,
     task::{Context, Poll},
+    time::Duration,
 };
 
 use futures::{
@@ -10,24 +11,34 @@ use futures::{
     channel::{mpsc, oneshot},
 };
 use parking_lot::Mutex;
+use serde::Serialize;
 use volans_core::{Multiaddr, PeerId};
 use volans_swarm::{
     BehaviorEvent, ConnectionDenied, ConnectionId, DialOpts, NetworkBehavior,
     NetworkOutgoingBehavior, PeerCondition, StreamProtocol, Substream, THandlerAction,
     THandlerEvent,
-    error::{ConnectionError, DialError},
+    error::{CloseReason, DialError},
 };
 
-use crate::client::{StreamError, handler, shared::Shared};
+use crate::{
+    client::{OpenStreamError, handler, shared::Shared},
+    header,
+};
 
 pub struct Behavior {
     shared: Arc<Mutex<Shared>>,
-    dial_receiver: mpsc::Receiver<PeerId>,
+    dial_receiver: mpsc::UnboundedReceiver<PeerId>,
+}
+
+impl Default for Behavior {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Behavior {
     pub fn new() -> Self {
-        let (dial_sender, dial_receiver) = mpsc::channel(0);
+        let (dial_sender, dial_receiver) = mpsc::unbounded();
         let shared = Arc::new(Mutex::new(Shared::new(dial_sender)));
         Self {
             shared,
@@ -35,8 +46,14 @@ impl Behavior {
         }
     }
 
-    pub fn controller(&self) -> Controller {
-        Controller::new(self.shared.clone())
+    /// 打开出站流的句柄
+    pub fn control(&self) -> Control {
+        Control::new(self.shared.clone())
+    }
+
+    /// 该协议的出站流读写空闲超过 `timeout` 时出错，见 [`Substream::set_idle_timeout`]
+    pub fn set_idle_timeout(&mut self, protocol: StreamProtocol, timeout: Duration) {
+        Shared::lock(&self.shared).set_idle_timeout(protocol, timeout);
     }
 }
 
@@ -62,6 +79,15 @@ impl NetworkBehavior for Behavior {
 }
 
 impl NetworkOutgoingBehavior for Behavior {
+    fn handle_pending_connection(
+        &mut self,
+        _id: ConnectionId,
+        _maybe_peer: Option<PeerId>,
+        addr: &Option<Multiaddr>,
+    ) -> Result<Option<Multiaddr>, ConnectionDenied> {
+        Ok(addr.clone())
+    }
+
     fn handle_established_connection(
         &mut self,
         id: ConnectionId,
@@ -82,7 +108,7 @@ impl NetworkOutgoingBehavior for Behavior {
         id: ConnectionId,
         peer_id: PeerId,
         _addr: &Multiaddr,
-        _reason: Option<&ConnectionError>,
+        _reason: &CloseReason,
     ) {
         Shared::lock(&self.shared).on_connection_closed(peer_id, id);
     }
@@ -110,32 +136,58 @@ impl NetworkOutgoingBehavior for Behavior {
     }
 }
 
+/// 打开出站流的句柄，可克隆
+///
+/// 未连接的节点会通过 [`Behavior`] 发起拨号，连接建立后再打开子流。
 #[derive(Clone)]
-pub struct Controller {
+pub struct Control {
     shared: Arc<Mutex<Shared>>,
 }
 
-impl Controller {
+impl Control {
     fn new(shared: Arc<Mutex<Shared>>) -> Self {
         Self { shared }
     }
 
-    pub async fn open(
+    /// 打开到 `peer_id` 的出站流，协议协商完成后返回
+    pub async fn open_stream(
         &mut self,
         peer_id: PeerId,
         protocol: StreamProtocol,
-    ) -> Result<Substream, StreamError> {
-        let mut new_stream_sender = Shared::lock(&self.shared).sender(peer_id);
+    ) -> Result<Substream, OpenStreamError> {
+        let (mut new_stream_sender, idle_timeout) = {
+            let mut shared = Shared::lock(&self.shared);
+            (shared.sender(peer_id), shared.idle_timeout(&protocol))
+        };
         let (sender, receiver) = oneshot::channel();
         new_stream_sender
             .send(handler::NewStream { protocol, sender })
             .await
-            .map_err(|e| StreamError::Io(io::Error::new(io::ErrorKind::ConnectionReset, e)))?;
+            .map_err(|e| OpenStreamError::Io(io::Error::new(io::ErrorKind::ConnectionReset, e)))?;
 
-        let result = receiver
-            .await
-            .map_err(|e| StreamError::Io(io::Error::new(io::ErrorKind::ConnectionReset, e)))?;
+        let mut stream = receiver.await.map_err(|e| {
+            OpenStreamError::Io(io::Error::new(io::ErrorKind::ConnectionReset, e))
+        })??;
+        if let Some(timeout) = idle_timeout {
+            stream.set_idle_timeout(timeout);
+        }
+        Ok(stream)
+    }
 
-        result
+    /// 打开出站流并发送头部，对端需以 [`accept_with_header`] 接收
+    ///
+    /// [`accept_with_header`]: crate::server::Behavior::accept_with_header
+    pub async fn open_stream_with_header<H>(
+        &mut self,
+        peer_id: PeerId,
+        protocol: StreamProtocol,
+        header: &H,
+    ) -> Result<Substream, OpenStreamError>
+    where
+        H: Serialize,
+    {
+        let mut stream = self.open_stream(peer_id, protocol).await?;
+        header::write_header(&mut stream, header).await?;
+        Ok(stream)
     }
 }
@@ -1,6 +1,5 @@
 use std::{
     convert::Infallible,
-    io,
     task::{Context, Poll},
 };
 
@@ -13,12 +12,12 @@ use volans_swarm::{
     StreamProtocol, StreamUpgradeError, Substream, SubstreamProtocol,
 };
 
-use crate::{Upgrade, client::StreamError};
+use crate::{Upgrade, client::OpenStreamError};
 
 #[derive(Debug)]
 pub(crate) struct NewStream {
     pub(crate) protocol: StreamProtocol,
-    pub(crate) sender: oneshot::Sender<Result<Substream, StreamError>>,
+    pub(crate) sender: oneshot::Sender<Result<Substream, OpenStreamError>>,
 }
 
 pub struct Handler {
@@ -26,7 +25,7 @@ pub struct Handler {
     /// 接收的新的出站流请求
     pending_outbound: Option<(
         StreamProtocol,
-        oneshot::Sender<Result<Substream, StreamError>>,
+        oneshot::Sender<Result<Substream, OpenStreamError>>,
     )>,
 }
 
@@ -86,12 +85,11 @@ impl OutboundStreamHandler for Handler {
         };
 
         let error = match error {
-            StreamUpgradeError::Timeout => {
-                StreamError::Io(io::Error::from(io::ErrorKind::TimedOut))
-            }
+            StreamUpgradeError::Timeout => OpenStreamError::Timeout,
+            StreamUpgradeError::RemoteReset => OpenStreamError::RemoteReset,
             StreamUpgradeError::Apply(v) => unreachable!("Unexpected apply error: {:?}", v),
-            StreamUpgradeError::NegotiationFailed => StreamError::Unsupported(protocol),
-            StreamUpgradeError::Io(io) => StreamError::Io(io),
+            StreamUpgradeError::NegotiationFailed { .. } => OpenStreamError::Unsupported(protocol),
+            StreamUpgradeError::Io(io) => OpenStreamError::Io(io),
         };
 
         // 尝试发送错误到发送者
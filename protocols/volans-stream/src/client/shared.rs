@@ -2,29 +2,32 @@ use std::{
     collections::{HashMap, HashSet, hash_map::Entry},
     io,
     sync::Arc,
+    time::Duration,
 };
 
 use futures::channel::mpsc;
 use parking_lot::{Mutex, MutexGuard};
 use volans_core::PeerId;
-use volans_swarm::{ConnectionId, error::DialError};
+use volans_swarm::{ConnectionId, StreamProtocol, error::DialError};
 
-use crate::client::{StreamError, handler::NewStream};
+use crate::client::{OpenStreamError, handler::NewStream};
 
 pub(crate) struct Shared {
     connections: HashMap<PeerId, HashSet<ConnectionId>>,
     senders: HashMap<ConnectionId, mpsc::Sender<NewStream>>,
     pending_channels: HashMap<PeerId, (mpsc::Sender<NewStream>, mpsc::Receiver<NewStream>)>,
-    dial_sender: mpsc::Sender<PeerId>,
+    dial_sender: mpsc::UnboundedSender<PeerId>,
+    idle_timeouts: HashMap<StreamProtocol, Duration>,
 }
 
 impl Shared {
-    pub(crate) fn new(dial_sender: mpsc::Sender<PeerId>) -> Self {
+    pub(crate) fn new(dial_sender: mpsc::UnboundedSender<PeerId>) -> Self {
         Self {
             connections: HashMap::new(),
             senders: HashMap::new(),
             pending_channels: HashMap::new(),
             dial_sender,
+            idle_timeouts: HashMap::new(),
         }
     }
 
@@ -32,12 +35,21 @@ impl Shared {
         shared.lock()
     }
 
+    pub(crate) fn set_idle_timeout(&mut self, protocol: StreamProtocol, timeout: Duration) {
+        self.idle_timeouts.insert(protocol, timeout);
+    }
+
+    pub(crate) fn idle_timeout(&self, protocol: &StreamProtocol) -> Option<Duration> {
+        self.idle_timeouts.get(protocol).copied()
+    }
+
     pub(crate) fn on_connection_established(&mut self, peer_id: PeerId, conn_id: ConnectionId) {
         self.connections.entry(peer_id).or_default().insert(conn_id);
     }
 
     pub(crate) fn on_connection_closed(&mut self, peer_id: PeerId, conn_id: ConnectionId) {
-        match self.connections.entry(peer_id.clone()) {
+        self.senders.remove(&conn_id);
+        match self.connections.entry(peer_id) {
             Entry::Occupied(mut entry) => {
                 entry.get_mut().remove(&conn_id);
                 if entry.get().is_empty() {
@@ -49,11 +61,15 @@ impl Shared {
     }
 
     pub(crate) fn on_dial_failure(&mut self, peer_id: PeerId, error: &DialError) {
+        // 已有连接或正在拨号，等待中的请求会在连接建立后处理
+        if matches!(error, DialError::PeerCondition(_)) {
+            return;
+        }
         let Some((_, mut receiver)) = self.pending_channels.remove(&peer_id) else {
             return;
         };
         while let Ok(Some(request)) = receiver.try_next() {
-            let _ = request.sender.send(Err(StreamError::Io(io::Error::new(
+            let _ = request.sender.send(Err(OpenStreamError::Io(io::Error::new(
                 io::ErrorKind::NotConnected,
                 error.to_string(),
             ))));
@@ -70,14 +86,14 @@ impl Shared {
 
         match maybe_sender {
             Some(sender) => sender.clone(),
-            None => {
-                let (sender, _) = self
-                    .pending_channels
-                    .entry(peer)
-                    .or_insert_with(|| mpsc::channel(0));
-                let _ = self.dial_sender.try_send(peer);
-                sender.clone()
-            }
+            None => match self.pending_channels.entry(peer) {
+                Entry::Occupied(entry) => entry.get().0.clone(),
+                Entry::Vacant(entry) => {
+                    // 同一节点只排队一次拨号，请求在连接建立后交给处理器
+                    let _ = self.dial_sender.unbounded_send(peer);
+                    entry.insert(mpsc::channel(0)).0.clone()
+                }
+            },
         }
     }
 
@@ -0,0 +1,78 @@
+//! 协商完成后由拨号端发送的流头部
+//!
+//! 头部为无符号变长整数长度前缀加 JSON 编码的结构体，用于携带认证令牌、追踪 ID、内容类型
+//! 等上下文。拨号端通过 [`Control::open_stream_with_header`](crate::Control::open_stream_with_header)
+//! 发送，监听端通过 [`Behavior::accept_with_header`](crate::server::Behavior::accept_with_header)
+//! 接收。
+
+use std::io;
+
+use futures::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use serde::{Serialize, de::DeserializeOwned};
+
+/// 头部编码后的最大字节数
+pub const MAX_HEADER_LEN: usize = 8 * 1024;
+
+pub async fn write_header<S, H>(stream: &mut S, header: &H) -> io::Result<()>
+where
+    S: AsyncWrite + Unpin,
+    H: Serialize,
+{
+    let payload = serde_json::to_vec(header)?;
+    if payload.len() > MAX_HEADER_LEN {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("stream header of {} bytes exceeds limit", payload.len()),
+        ));
+    }
+    let mut len = unsigned_varint::encode::usize_buffer();
+    stream
+        .write_all(unsigned_varint::encode::usize(payload.len(), &mut len))
+        .await?;
+    stream.write_all(&payload).await?;
+    stream.flush().await
+}
+
+pub async fn read_header<S, H>(stream: &mut S) -> io::Result<H>
+where
+    S: AsyncRead + Unpin,
+    H: DeserializeOwned,
+{
+    let len = unsigned_varint::aio::read_usize(&mut *stream)
+        .await
+        .map_err(|error| match error {
+            unsigned_varint::io::ReadError::Io(error) => error,
+            error => io::Error::new(io::ErrorKind::InvalidData, error),
+        })?;
+    if len > MAX_HEADER_LEN {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("stream header of {len} bytes exceeds limit"),
+        ));
+    }
+    let mut payload = vec![0u8; len];
+    stream.read_exact(&mut payload).await?;
+    Ok(serde_json::from_slice(&payload)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::{executor::block_on, io::Cursor};
+
+    use super::*;
+
+    #[test]
+    fn header_round_trip() {
+        let mut buffer = Cursor::new(Vec::new());
+        block_on(write_header(&mut buffer, &("token", 7u32))).unwrap();
+        buffer.get_mut().extend_from_slice(b"body");
+
+        let mut stream = Cursor::new(buffer.into_inner());
+        let header: (String, u32) = block_on(read_header(&mut stream)).unwrap();
+        assert_eq!(header, ("token".to_string(), 7));
+        // 头部之后的数据保留在流中
+        let mut body = Vec::new();
+        block_on(stream.read_to_end(&mut body)).unwrap();
+        assert_eq!(body, b"body");
+    }
+}
@@ -1,6 +1,9 @@
 pub mod client;
+pub mod header;
 pub mod server;
 
+pub use client::{Control, OpenStreamError};
+
 use std::convert::Infallible;
 
 use futures::future::{Ready, ready};
@@ -4,11 +4,19 @@ use std::{
     task::{Context, Poll},
 };
 
-use futures::{Stream, StreamExt, channel::mpsc};
+use futures::{
+    FutureExt, Stream, StreamExt,
+    channel::mpsc,
+    future::BoxFuture,
+    stream::{FusedStream, FuturesUnordered},
+};
 use parking_lot::Mutex;
+use serde::de::DeserializeOwned;
 use volans_core::PeerId;
 use volans_swarm::{ConnectionId, StreamProtocol, Substream};
 
+use crate::header;
+
 mod behavior;
 mod handler;
 mod shared;
@@ -38,6 +46,72 @@ impl Stream for IncomingStreams {
     }
 }
 
+type HeaderRead<H> = BoxFuture<'static, Option<(PeerId, ConnectionId, H, Substream)>>;
+
+/// 读取拨号端头部后交付的入站流，头部无效的流被丢弃
+///
+/// 头部在交付前读取，建议为该协议设置空闲超时，避免对端迟迟不发送头部。
+pub struct HeaderStreams<H> {
+    incoming: IncomingStreams,
+    reading: FuturesUnordered<HeaderRead<H>>,
+}
+
+impl<H> HeaderStreams<H>
+where
+    H: DeserializeOwned + Send + 'static,
+{
+    pub(crate) fn new(incoming: IncomingStreams) -> Self {
+        Self {
+            incoming,
+            reading: FuturesUnordered::new(),
+        }
+    }
+
+    fn read_header(
+        peer_id: PeerId,
+        connection_id: ConnectionId,
+        mut stream: Substream,
+    ) -> HeaderRead<H> {
+        async move {
+            match header::read_header(&mut stream).await {
+                Ok(header) => Some((peer_id, connection_id, header, stream)),
+                Err(error) => {
+                    tracing::debug!(%peer_id, %error, "Dropping inbound stream with invalid header");
+                    None
+                }
+            }
+        }
+        .boxed()
+    }
+}
+
+impl<H> Stream for HeaderStreams<H>
+where
+    H: DeserializeOwned + Send + 'static,
+{
+    type Item = (PeerId, ConnectionId, H, Substream);
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = &mut *self;
+        while let Poll::Ready(Some((peer_id, connection_id, stream))) =
+            this.incoming.poll_next_unpin(cx)
+        {
+            this.reading
+                .push(Self::read_header(peer_id, connection_id, stream));
+        }
+        loop {
+            match this.reading.poll_next_unpin(cx) {
+                Poll::Ready(Some(Some(item))) => return Poll::Ready(Some(item)),
+                Poll::Ready(Some(None)) => continue,
+                Poll::Ready(None) if this.incoming.receiver.is_terminated() => {
+                    return Poll::Ready(None);
+                }
+                Poll::Ready(None) | Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct Acceptor {
     shared: Arc<Mutex<shared::Shared>>,
@@ -54,4 +128,25 @@ impl Acceptor {
     ) -> Result<IncomingStreams, AlreadyRegistered> {
         shared::Shared::lock(&self.shared).accept(protocol)
     }
+
+    /// 注册协议，入站流在读取拨号端发送的头部后交付，见 [`header`]
+    pub fn accept_with_header<H>(
+        &mut self,
+        protocol: StreamProtocol,
+    ) -> Result<HeaderStreams<H>, AlreadyRegistered>
+    where
+        H: DeserializeOwned + Send + 'static,
+    {
+        self.accept(protocol).map(HeaderStreams::new)
+    }
+
+    /// 注销协议，返回该协议此前是否已注册
+    pub fn deregister(&mut self, protocol: &StreamProtocol) -> bool {
+        shared::Shared::lock(&self.shared).deregister(protocol)
+    }
+
+    /// 当前已注册的协议
+    pub fn protocols(&self) -> Vec<StreamProtocol> {
+        shared::Shared::lock(&self.shared).supported_protocols()
+    }
 }
@@ -2,22 +2,30 @@ use std::{
     convert::Infallible,
     sync::Arc,
     task::{Context, Poll},
+    time::Duration,
 };
 
 use parking_lot::Mutex;
-use volans_core::{PeerId, Multiaddr};
+use serde::de::DeserializeOwned;
+use volans_core::{Multiaddr, PeerId};
 use volans_swarm::{
     BehaviorEvent, ConnectionDenied, ConnectionId, ListenerEvent, NetworkBehavior,
-    NetworkIncomingBehavior, THandlerAction, THandlerEvent,
-    error::{ConnectionError, ListenError},
+    NetworkIncomingBehavior, StreamProtocol, THandlerAction, THandlerEvent,
+    error::{CloseReason, ListenError},
 };
 
-use super::{Acceptor, handler, shared::Shared};
+use super::{Acceptor, AlreadyRegistered, HeaderStreams, IncomingStreams, handler, shared::Shared};
 
 pub struct Behavior {
     shared: Arc<Mutex<Shared>>,
 }
 
+impl Default for Behavior {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Behavior {
     pub fn new() -> Self {
         let shared = Arc::new(Mutex::new(Shared::new()));
@@ -27,6 +35,37 @@ impl Behavior {
     pub fn acceptor(&self) -> Acceptor {
         Acceptor::new(self.shared.clone())
     }
+
+    /// 运行时注册协议，未注册协议的入站流在协商阶段被拒绝
+    pub fn accept(
+        &mut self,
+        protocol: StreamProtocol,
+    ) -> Result<IncomingStreams, AlreadyRegistered> {
+        Shared::lock(&self.shared).accept(protocol)
+    }
+
+    /// 注册协议，入站流在读取拨号端发送的头部后交付，见 [`header`](crate::header)
+    pub fn accept_with_header<H>(
+        &mut self,
+        protocol: StreamProtocol,
+    ) -> Result<HeaderStreams<H>, AlreadyRegistered>
+    where
+        H: DeserializeOwned + Send + 'static,
+    {
+        self.accept(protocol).map(HeaderStreams::new)
+    }
+
+    /// 注销协议，返回该协议此前是否已注册
+    pub fn deregister(&mut self, protocol: &StreamProtocol) -> bool {
+        Shared::lock(&self.shared).deregister(protocol)
+    }
+
+    /// 该协议的入站流读写空闲超过 `timeout` 时出错，见 [`Substream::set_idle_timeout`]
+    ///
+    /// [`Substream::set_idle_timeout`]: volans_swarm::Substream::set_idle_timeout
+    pub fn set_idle_timeout(&mut self, protocol: StreamProtocol, timeout: Duration) {
+        Shared::lock(&self.shared).set_idle_timeout(protocol, timeout);
+    }
 }
 
 impl NetworkBehavior for Behavior {
@@ -78,7 +117,7 @@ impl NetworkIncomingBehavior for Behavior {
         _peer_id: PeerId,
         _local_addr: &Multiaddr,
         _remote_addr: &Multiaddr,
-        _reason: Option<&ConnectionError>,
+        _reason: &CloseReason,
     ) {
     }
 
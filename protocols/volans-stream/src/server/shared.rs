@@ -1,6 +1,7 @@
 use std::{
     collections::{HashMap, hash_map::Entry},
     sync::Arc,
+    time::Duration,
 };
 
 use futures::channel::mpsc;
@@ -12,6 +13,7 @@ use crate::server::{AlreadyRegistered, IncomingStreams};
 
 pub(crate) struct Shared {
     supported_protocols: HashMap<StreamProtocol, mpsc::Sender<(PeerId, ConnectionId, Substream)>>,
+    idle_timeouts: HashMap<StreamProtocol, Duration>,
 }
 
 impl Shared {
@@ -19,6 +21,7 @@ impl Shared {
         let supported_protocols = HashMap::new();
         Self {
             supported_protocols,
+            idle_timeouts: HashMap::new(),
         }
     }
 
@@ -46,13 +49,25 @@ impl Shared {
         Ok(IncomingStreams::new(receiver))
     }
 
+    /// 注销后新的入站流在协商阶段即被拒绝，已接收的 `IncomingStreams` 随之结束
+    pub(crate) fn deregister(&mut self, protocol: &StreamProtocol) -> bool {
+        self.supported_protocols.remove(protocol).is_some()
+    }
+
+    pub(crate) fn set_idle_timeout(&mut self, protocol: StreamProtocol, timeout: Duration) {
+        self.idle_timeouts.insert(protocol, timeout);
+    }
+
     pub(crate) fn on_inbound_stream(
         &mut self,
         remote: PeerId,
         connection_id: ConnectionId,
-        stream: Substream,
+        mut stream: Substream,
         protocol: StreamProtocol,
     ) {
+        if let Some(timeout) = self.idle_timeouts.get(&protocol) {
+            stream.set_idle_timeout(*timeout);
+        }
         match self.supported_protocols.entry(protocol.clone()) {
             Entry::Occupied(mut entry) => {
                 match entry.get_mut().try_send((remote, connection_id, stream)) {
@@ -73,3 +88,28 @@ impl Shared {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PROTOCOL: StreamProtocol = StreamProtocol::new("/echo/1.0.0");
+
+    #[test]
+    fn register_and_deregister() {
+        let mut shared = Shared::new();
+
+        let incoming = shared.accept(PROTOCOL).unwrap();
+        assert!(shared.accept(PROTOCOL).is_err());
+        assert_eq!(shared.supported_protocols(), vec![PROTOCOL]);
+
+        assert!(shared.deregister(&PROTOCOL));
+        assert!(!shared.deregister(&PROTOCOL));
+        assert!(shared.supported_protocols().is_empty());
+        drop(incoming);
+
+        let incoming = shared.accept(PROTOCOL).unwrap();
+        drop(incoming);
+        assert!(shared.supported_protocols().is_empty());
+    }
+}
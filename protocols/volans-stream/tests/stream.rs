@@ -0,0 +1,174 @@
+use futures::StreamExt;
+use volans_swarm::{client, server};
+use volans_swarm_test::{SwarmExt, connect};
+
+#[tokio::test(flavor = "current_thread")]
+async fn open_stream() {
+    use futures::{AsyncReadExt, AsyncWriteExt};
+    use volans_swarm::StreamProtocol;
+
+    const ECHO: StreamProtocol = StreamProtocol::new("/echo/1.0.0");
+
+    let mut dialer = client::Swarm::new_ephemeral(|_| volans_stream::client::Behavior::new());
+    let mut listener = server::Swarm::new_ephemeral(|_| volans_stream::server::Behavior::new());
+    connect(&mut dialer, &mut listener).await;
+
+    let listener_peer = *listener.local_peer_id();
+    let mut control = dialer.behavior().control();
+    let mut incoming = listener.behavior_mut().accept(ECHO).unwrap();
+    tokio::spawn(async move {
+        loop {
+            listener.next().await;
+        }
+    });
+    tokio::spawn(async move {
+        loop {
+            dialer.next().await;
+        }
+    });
+    tokio::spawn(async move {
+        while let Some((_, _, mut stream)) = incoming.next().await {
+            let mut buf = [0; 4];
+            stream.read_exact(&mut buf).await.unwrap();
+            stream.write_all(&buf).await.unwrap();
+        }
+    });
+
+    let mut stream = control.open_stream(listener_peer, ECHO).await.unwrap();
+    stream.write_all(b"ping").await.unwrap();
+    let mut buf = [0; 4];
+    stream.read_exact(&mut buf).await.unwrap();
+    assert_eq!(&buf, b"ping");
+
+    let unsupported = StreamProtocol::new("/unsupported/1.0.0");
+    assert!(matches!(
+        control.open_stream(listener_peer, unsupported).await,
+        Err(volans_stream::OpenStreamError::Unsupported(_))
+    ));
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn open_stream_with_header() {
+    use futures::{AsyncReadExt, AsyncWriteExt};
+    use volans_swarm::StreamProtocol;
+
+    const TRACED: StreamProtocol = StreamProtocol::new("/traced/1.0.0");
+
+    let mut dialer = client::Swarm::new_ephemeral(|_| volans_stream::client::Behavior::new());
+    let mut listener = server::Swarm::new_ephemeral(|_| volans_stream::server::Behavior::new());
+    connect(&mut dialer, &mut listener).await;
+
+    let listener_peer = *listener.local_peer_id();
+    let mut control = dialer.behavior().control();
+    let mut incoming = listener
+        .behavior_mut()
+        .accept_with_header::<(String, u64)>(TRACED)
+        .unwrap();
+    tokio::spawn(async move {
+        loop {
+            listener.next().await;
+        }
+    });
+    tokio::spawn(async move {
+        loop {
+            dialer.next().await;
+        }
+    });
+
+    let header = ("token".to_string(), 42);
+    let mut stream = control
+        .open_stream_with_header(listener_peer, TRACED, &header)
+        .await
+        .unwrap();
+    stream.write_all(b"body").await.unwrap();
+    stream.close().await.unwrap();
+
+    let (_, _, received, mut stream) = incoming.next().await.unwrap();
+    assert_eq!(received, header);
+    let mut body = Vec::new();
+    stream.read_to_end(&mut body).await.unwrap();
+    assert_eq!(body, b"body");
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn substream_idle_timeout() {
+    use futures::{AsyncReadExt, AsyncWriteExt};
+    use std::time::Duration;
+    use volans_swarm::{StreamProtocol, error::SubstreamIdleTimeout};
+
+    const IDLE: StreamProtocol = StreamProtocol::new("/idle/1.0.0");
+
+    let mut dialer = client::Swarm::new_ephemeral(|_| volans_stream::client::Behavior::new());
+    let mut listener = server::Swarm::new_ephemeral(|_| volans_stream::server::Behavior::new());
+    connect(&mut dialer, &mut listener).await;
+
+    let listener_peer = *listener.local_peer_id();
+    let mut control = dialer.behavior().control();
+    listener
+        .behavior_mut()
+        .set_idle_timeout(IDLE, Duration::from_millis(100));
+    let mut incoming = listener.behavior_mut().accept(IDLE).unwrap();
+    tokio::spawn(async move {
+        loop {
+            listener.next().await;
+        }
+    });
+    tokio::spawn(async move {
+        loop {
+            dialer.next().await;
+        }
+    });
+
+    // 打开后不写入数据，对端读取超时
+    let _idle = control.open_stream(listener_peer, IDLE).await.unwrap();
+    let (_, first, mut stream) = incoming.next().await.unwrap();
+    let error = stream.read(&mut [0; 1]).await.unwrap_err();
+    assert!(SubstreamIdleTimeout::is(&error));
+
+    // 连接保持不变，协商缓存命中时对端在收到数据后才接受子流
+    let mut stream = control.open_stream(listener_peer, IDLE).await.unwrap();
+    stream.write_all(b"x").await.unwrap();
+    let (_, second, _) = incoming.next().await.unwrap();
+    assert_eq!(first, second);
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn open_stream_reset_by_remote() {
+    use volans_swarm::{StreamProtocol, connection::PoolConfig};
+    use volans_swarm_test::{ephemeral_key_pair, ephemeral_parts_with_muxer};
+
+    const ECHO: StreamProtocol = StreamProtocol::new("/echo/1.0.0");
+
+    // 对端的多路复用器丢弃所有入站子流，协商期间子流被重置
+    let mut muxer = volans_muxing::Config::new();
+    muxer
+        .set_max_buffered_inbound_streams(0)
+        .set_inbound_overflow(volans_muxing::InboundOverflow::Drop);
+    let (transport, listener_peer) = ephemeral_parts_with_muxer(&ephemeral_key_pair(), muxer);
+    let mut listener = server::Swarm::from_parts(
+        transport,
+        volans_stream::server::Behavior::new(),
+        listener_peer,
+        PoolConfig::with_tokio_executor(),
+    );
+    let _incoming = listener.behavior_mut().accept(ECHO).unwrap();
+    let mut dialer = client::Swarm::new_ephemeral(|_| volans_stream::client::Behavior::new());
+    connect(&mut dialer, &mut listener).await;
+
+    let mut control = dialer.behavior().control();
+    tokio::spawn(async move {
+        loop {
+            listener.next().await;
+        }
+    });
+    tokio::spawn(async move {
+        loop {
+            dialer.next().await;
+        }
+    });
+
+    assert!(matches!(
+        control.open_stream(listener_peer, ECHO).await,
+        Err(volans_stream::OpenStreamError::RemoteReset)
+    ));
+}
@@ -0,0 +1,412 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    io,
+    net::SocketAddrV4,
+    sync::Arc,
+    task::{Context, Poll, Waker},
+};
+
+use futures::{FutureExt, StreamExt, future::BoxFuture, stream::FuturesUnordered};
+use futures_timer::Delay;
+use volans_core::{Multiaddr, PeerId, multiaddr::Protocol};
+use volans_swarm::{
+    BehaviorEvent, ConnectionDenied, ConnectionId, ListenerEvent, NetworkBehavior,
+    NetworkIncomingBehavior, NetworkOutgoingBehavior, THandlerAction, THandlerEvent,
+    handler::DummyHandler,
+};
+
+use crate::{
+    Config, Event,
+    gateway::{Gateway, PortProtocol, with_timeout},
+};
+
+enum GatewayState {
+    /// 尚未发现，或上次发现失败
+    Unknown,
+    Searching,
+    Ready(Arc<Gateway>),
+}
+
+enum MappingState {
+    /// 等待网关发现
+    Pending,
+    /// 映射请求进行中，续期时保留当前的外部地址
+    Requesting(Option<Multiaddr>),
+    Active {
+        external: Multiaddr,
+        renew: Delay,
+    },
+    Failed,
+}
+
+struct Mapping {
+    protocol: PortProtocol,
+    local: SocketAddrV4,
+    state: MappingState,
+}
+
+enum TaskOutput {
+    Gateway(io::Result<Gateway>),
+    Mapped {
+        listen_addr: Multiaddr,
+        result: io::Result<SocketAddrV4>,
+    },
+    Removed {
+        local: SocketAddrV4,
+        result: io::Result<()>,
+    },
+}
+
+/// 为私有 IPv4 监听地址维护网关端口映射
+///
+/// 行为被丢弃时不会主动删除映射，映射在租期结束后由网关回收。
+pub struct Behavior {
+    config: Config,
+    gateway: GatewayState,
+    mappings: HashMap<Multiaddr, Mapping>,
+    tasks: FuturesUnordered<BoxFuture<'static, TaskOutput>>,
+    pending_events: VecDeque<BehaviorEvent<Event, THandlerAction<Self>>>,
+    waker: Option<Waker>,
+}
+
+impl Behavior {
+    pub fn new(config: Config) -> Self {
+        Self {
+            config,
+            gateway: GatewayState::Unknown,
+            mappings: HashMap::new(),
+            tasks: FuturesUnordered::new(),
+            pending_events: VecDeque::new(),
+            waker: None,
+        }
+    }
+
+    fn on_new_listen_addr(&mut self, addr: &Multiaddr) {
+        if self.mappings.contains_key(addr) {
+            return;
+        }
+        let Some((protocol, local)) = mapping_target(addr) else {
+            return;
+        };
+        self.mappings.insert(
+            addr.clone(),
+            Mapping {
+                protocol,
+                local,
+                state: MappingState::Pending,
+            },
+        );
+        match &self.gateway {
+            GatewayState::Unknown => self.search_gateway(),
+            GatewayState::Searching => {}
+            GatewayState::Ready(_) => self.request_mapping(addr.clone(), None),
+        }
+    }
+
+    fn on_expired_listen_addr(&mut self, addr: &Multiaddr) {
+        let Some(mapping) = self.mappings.remove(addr) else {
+            return;
+        };
+        match mapping.state {
+            MappingState::Active { external, .. } | MappingState::Requesting(Some(external)) => {
+                self.expire(addr.clone(), external);
+                self.remove_mapping(mapping.protocol, mapping.local);
+            }
+            // 进行中的首次请求完成后再删除
+            MappingState::Pending | MappingState::Requesting(None) | MappingState::Failed => {}
+        }
+    }
+
+    fn search_gateway(&mut self) {
+        self.gateway = GatewayState::Searching;
+        let nat_pmp_gateway = self.config.nat_pmp_gateway;
+        let timeout = self.config.timeout;
+        self.tasks.push(
+            async move { TaskOutput::Gateway(Gateway::search(nat_pmp_gateway, timeout).await) }
+                .boxed(),
+        );
+        self.wake();
+    }
+
+    fn request_mapping(&mut self, listen_addr: Multiaddr, external: Option<Multiaddr>) {
+        let GatewayState::Ready(gateway) = &self.gateway else {
+            return;
+        };
+        let Some(mapping) = self.mappings.get_mut(&listen_addr) else {
+            return;
+        };
+        mapping.state = MappingState::Requesting(external);
+        let gateway = gateway.clone();
+        let (protocol, local) = (mapping.protocol, mapping.local);
+        let lease = self.config.lease_duration;
+        let timeout = self.config.timeout;
+        let description = self.config.description.clone();
+        self.tasks.push(
+            async move {
+                let result = with_timeout(
+                    gateway.add_port(protocol, local, lease, &description),
+                    timeout,
+                )
+                .await;
+                TaskOutput::Mapped {
+                    listen_addr,
+                    result,
+                }
+            }
+            .boxed(),
+        );
+        self.wake();
+    }
+
+    fn remove_mapping(&mut self, protocol: PortProtocol, local: SocketAddrV4) {
+        let GatewayState::Ready(gateway) = &self.gateway else {
+            return;
+        };
+        let gateway = gateway.clone();
+        let timeout = self.config.timeout;
+        self.tasks.push(
+            async move {
+                let result = with_timeout(gateway.remove_port(protocol, local), timeout).await;
+                TaskOutput::Removed { local, result }
+            }
+            .boxed(),
+        );
+        self.wake();
+    }
+
+    fn expire(&mut self, listen_addr: Multiaddr, external: Multiaddr) {
+        self.pending_events
+            .push_back(BehaviorEvent::ExternalAddrExpired {
+                addr: external.clone(),
+            });
+        self.pending_events
+            .push_back(BehaviorEvent::Behavior(Event::ExpiredExternalAddr {
+                listen_addr,
+                external,
+            }));
+    }
+
+    fn on_task_output(&mut self, output: TaskOutput) {
+        match output {
+            TaskOutput::Gateway(Ok(gateway)) => {
+                tracing::debug!(?gateway, "Gateway found");
+                self.gateway = GatewayState::Ready(Arc::new(gateway));
+                let pending = self
+                    .mappings
+                    .iter()
+                    .filter(|(_, m)| matches!(m.state, MappingState::Pending))
+                    .map(|(addr, _)| addr.clone())
+                    .collect::<Vec<_>>();
+                for addr in pending {
+                    self.request_mapping(addr, None);
+                }
+            }
+            TaskOutput::Gateway(Err(err)) => {
+                tracing::debug!("Gateway not found, {}", err);
+                self.gateway = GatewayState::Unknown;
+                self.pending_events
+                    .push_back(BehaviorEvent::Behavior(Event::GatewayNotFound(err)));
+            }
+            TaskOutput::Mapped {
+                listen_addr,
+                result,
+            } => self.on_mapped(listen_addr, result),
+            TaskOutput::Removed { local, result } => {
+                if let Err(err) = result {
+                    tracing::debug!(%local, "Failed to remove port mapping, {}", err);
+                }
+            }
+        }
+    }
+
+    fn on_mapped(&mut self, listen_addr: Multiaddr, result: io::Result<SocketAddrV4>) {
+        let Some(mapping) = self.mappings.get_mut(&listen_addr) else {
+            // 请求期间监听地址已过期
+            if let (Ok(_), Some((protocol, local))) = (&result, mapping_target(&listen_addr)) {
+                self.remove_mapping(protocol, local);
+            }
+            return;
+        };
+        let previous = match &mut mapping.state {
+            MappingState::Requesting(external) => external.take(),
+            _ => None,
+        };
+        match result {
+            Ok(external) => {
+                let external = external_addr(&listen_addr, external);
+                tracing::debug!(%listen_addr, %external, "Port mapped");
+                mapping.state = MappingState::Active {
+                    external: external.clone(),
+                    renew: Delay::new(self.config.lease_duration / 2),
+                };
+                if previous.as_ref() == Some(&external) {
+                    return;
+                }
+                if let Some(previous) = previous {
+                    self.expire(listen_addr.clone(), previous);
+                }
+                self.pending_events
+                    .push_back(BehaviorEvent::ExternalAddrConfirmed {
+                        addr: external.clone(),
+                    });
+                self.pending_events
+                    .push_back(BehaviorEvent::Behavior(Event::NewExternalAddr {
+                        listen_addr,
+                        external,
+                    }));
+            }
+            Err(error) => {
+                tracing::debug!(%listen_addr, "Port mapping failed, {}", error);
+                mapping.state = MappingState::Failed;
+                if let Some(previous) = previous {
+                    self.expire(listen_addr.clone(), previous);
+                }
+                self.pending_events
+                    .push_back(BehaviorEvent::Behavior(Event::MappingFailed {
+                        listen_addr,
+                        error,
+                    }));
+            }
+        }
+    }
+
+    fn wake(&mut self) {
+        if let Some(waker) = self.waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+impl NetworkBehavior for Behavior {
+    type ConnectionHandler = DummyHandler;
+    type Event = Event;
+
+    fn on_connection_handler_event(
+        &mut self,
+        _id: ConnectionId,
+        _peer_id: PeerId,
+        event: THandlerEvent<Self>,
+    ) {
+        unreachable!("Unexpected event: {:?}", event);
+    }
+
+    fn poll(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<BehaviorEvent<Self::Event, THandlerAction<Self>>> {
+        loop {
+            if let Some(event) = self.pending_events.pop_front() {
+                return Poll::Ready(event);
+            }
+            if let Poll::Ready(Some(output)) = self.tasks.poll_next_unpin(cx) {
+                self.on_task_output(output);
+                continue;
+            }
+            let renew = self.mappings.iter_mut().find_map(|(addr, mapping)| {
+                let MappingState::Active { external, renew } = &mut mapping.state else {
+                    return None;
+                };
+                renew
+                    .poll_unpin(cx)
+                    .is_ready()
+                    .then(|| (addr.clone(), external.clone()))
+            });
+            if let Some((addr, external)) = renew {
+                self.request_mapping(addr, Some(external));
+                continue;
+            }
+            self.waker = Some(cx.waker().clone());
+            return Poll::Pending;
+        }
+    }
+}
+
+impl NetworkIncomingBehavior for Behavior {
+    fn handle_established_connection(
+        &mut self,
+        _id: ConnectionId,
+        _peer_id: PeerId,
+        _local_addr: &Multiaddr,
+        _remote_addr: &Multiaddr,
+    ) -> Result<Self::ConnectionHandler, ConnectionDenied> {
+        Ok(DummyHandler)
+    }
+
+    fn on_listener_event(&mut self, event: ListenerEvent<'_>) {
+        match event {
+            ListenerEvent::NewListenAddr(e) => self.on_new_listen_addr(e.addr),
+            ListenerEvent::ExpiredListenAddr(e) => self.on_expired_listen_addr(e.addr),
+            _ => {}
+        }
+    }
+}
+
+impl NetworkOutgoingBehavior for Behavior {
+    fn handle_pending_connection(
+        &mut self,
+        _id: ConnectionId,
+        _maybe_peer: Option<PeerId>,
+        addr: &Option<Multiaddr>,
+    ) -> Result<Option<Multiaddr>, ConnectionDenied> {
+        Ok(addr.clone())
+    }
+
+    fn handle_established_connection(
+        &mut self,
+        _id: ConnectionId,
+        _peer_id: PeerId,
+        _addr: &Multiaddr,
+    ) -> Result<Self::ConnectionHandler, ConnectionDenied> {
+        Ok(DummyHandler)
+    }
+}
+
+/// 可映射的监听地址：私有 IPv4 之上的 TCP 或 UDP 端口
+fn mapping_target(addr: &Multiaddr) -> Option<(PortProtocol, SocketAddrV4)> {
+    let mut iter = addr.iter();
+    let Some(Protocol::Ip4(ip)) = iter.next() else {
+        return None;
+    };
+    if !ip.is_private() {
+        return None;
+    }
+    match iter.next()? {
+        Protocol::Tcp(port) => Some((PortProtocol::Tcp, SocketAddrV4::new(ip, port))),
+        Protocol::Udp(port) => Some((PortProtocol::Udp, SocketAddrV4::new(ip, port))),
+        _ => None,
+    }
+}
+
+/// 把监听地址的 IP 与端口替换为网关的公网地址
+fn external_addr(listen_addr: &Multiaddr, external: SocketAddrV4) -> Multiaddr {
+    listen_addr
+        .iter()
+        .map(|protocol| match protocol {
+            Protocol::Ip4(_) => Protocol::Ip4(*external.ip()),
+            Protocol::Tcp(_) => Protocol::Tcp(external.port()),
+            Protocol::Udp(_) => Protocol::Udp(external.port()),
+            protocol => protocol,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn map_private_listen_addr() {
+        let listen: Multiaddr = "/ip4/192.168.1.10/tcp/4001/ws".parse().unwrap();
+        let (protocol, local) = mapping_target(&listen).unwrap();
+        assert_eq!(protocol, PortProtocol::Tcp);
+        assert_eq!(local, "192.168.1.10:4001".parse().unwrap());
+        assert_eq!(
+            external_addr(&listen, "203.0.113.7:4001".parse().unwrap()),
+            "/ip4/203.0.113.7/tcp/4001/ws".parse().unwrap()
+        );
+
+        assert!(mapping_target(&"/ip4/127.0.0.1/tcp/4001".parse().unwrap()).is_none());
+        assert!(mapping_target(&"/ip4/203.0.113.7/tcp/4001".parse().unwrap()).is_none());
+        assert!(mapping_target(&"/ip6/::1/tcp/4001".parse().unwrap()).is_none());
+    }
+}
@@ -0,0 +1,90 @@
+use std::{
+    fmt, io,
+    net::{Ipv4Addr, SocketAddrV4},
+    pin::pin,
+    time::Duration,
+};
+
+use futures::future::{self, Either};
+use futures_timer::Delay;
+
+use crate::{igd, natpmp};
+
+/// 映射的传输协议
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum PortProtocol {
+    Tcp,
+    Udp,
+}
+
+impl fmt::Display for PortProtocol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PortProtocol::Tcp => write!(f, "TCP"),
+            PortProtocol::Udp => write!(f, "UDP"),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub(crate) enum Gateway {
+    Igd(igd::Gateway),
+    NatPmp(natpmp::Gateway),
+}
+
+impl Gateway {
+    /// 先通过 SSDP 发现 IGD，失败后探测 NAT-PMP 网关
+    pub(crate) async fn search(
+        nat_pmp_gateway: Option<Ipv4Addr>,
+        timeout: Duration,
+    ) -> io::Result<Self> {
+        match with_timeout(igd::search(), timeout).await {
+            Ok(gateway) => return Ok(Gateway::Igd(gateway)),
+            Err(err) => tracing::debug!("UPnP IGD not found: {}", err),
+        }
+        let Some(addr) = nat_pmp_gateway.or_else(natpmp::default_gateway) else {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                "No UPnP IGD or NAT-PMP gateway",
+            ));
+        };
+        let gateway = natpmp::Gateway::new(addr);
+        with_timeout(gateway.external_ip(), timeout).await?;
+        Ok(Gateway::NatPmp(gateway))
+    }
+
+    /// 把 `local` 的端口映射到网关同端口，返回公网地址
+    pub(crate) async fn add_port(
+        &self,
+        protocol: PortProtocol,
+        local: SocketAddrV4,
+        lease: Duration,
+        description: &str,
+    ) -> io::Result<SocketAddrV4> {
+        match self {
+            Gateway::Igd(gateway) => gateway.add_port(protocol, local, lease, description).await,
+            Gateway::NatPmp(gateway) => gateway.add_port(protocol, local, lease).await,
+        }
+    }
+
+    pub(crate) async fn remove_port(
+        &self,
+        protocol: PortProtocol,
+        local: SocketAddrV4,
+    ) -> io::Result<()> {
+        match self {
+            Gateway::Igd(gateway) => gateway.remove_port(protocol, local.port()).await,
+            Gateway::NatPmp(gateway) => gateway.remove_port(protocol, local.port()).await,
+        }
+    }
+}
+
+pub(crate) async fn with_timeout<T>(
+    future: impl Future<Output = io::Result<T>>,
+    timeout: Duration,
+) -> io::Result<T> {
+    match future::select(pin!(future), Delay::new(timeout)).await {
+        Either::Left((result, _)) => result,
+        Either::Right(_) => Err(io::ErrorKind::TimedOut.into()),
+    }
+}
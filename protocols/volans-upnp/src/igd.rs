@@ -0,0 +1,299 @@
+//! 最小化的 UPnP IGD 客户端，只实现端口映射所需的 SSDP 发现与 SOAP 调用
+
+use std::{
+    io,
+    net::{Ipv4Addr, SocketAddr, SocketAddrV4},
+    time::Duration,
+};
+
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpStream, UdpSocket},
+};
+
+use crate::gateway::PortProtocol;
+
+const SSDP_ADDR: SocketAddrV4 = SocketAddrV4::new(Ipv4Addr::new(239, 255, 255, 250), 1900);
+const SEARCH_TARGET: &str = "urn:schemas-upnp-org:device:InternetGatewayDevice:1";
+const WAN_SERVICES: [&str; 3] = [
+    "urn:schemas-upnp-org:service:WANIPConnection:1",
+    "urn:schemas-upnp-org:service:WANIPConnection:2",
+    "urn:schemas-upnp-org:service:WANPPPConnection:1",
+];
+
+#[derive(Debug)]
+pub(crate) struct Gateway {
+    addr: SocketAddr,
+    control_path: String,
+    service_type: String,
+}
+
+/// 发送 SSDP 搜索，返回第一个提供 WAN 连接服务的网关，超时由调用方控制
+pub(crate) async fn search() -> io::Result<Gateway> {
+    let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0)).await?;
+    let request = format!(
+        "M-SEARCH * HTTP/1.1\r\nHOST: {SSDP_ADDR}\r\nMAN: \"ssdp:discover\"\r\nMX: 2\r\nST: {SEARCH_TARGET}\r\n\r\n"
+    );
+    socket.send_to(request.as_bytes(), SSDP_ADDR).await?;
+    let mut buf = [0u8; 2048];
+    loop {
+        let (len, _) = socket.recv_from(&mut buf).await?;
+        let Some(location) = parse_location(&buf[..len]) else {
+            continue;
+        };
+        match Gateway::from_location(&location).await {
+            Ok(gateway) => return Ok(gateway),
+            Err(err) => tracing::debug!(%location, "Ignoring gateway, {}", err),
+        }
+    }
+}
+
+impl Gateway {
+    async fn from_location(location: &str) -> io::Result<Self> {
+        let (addr, path) = parse_url(location)?;
+        let request = format!("GET {path} HTTP/1.1\r\nHost: {addr}\r\nConnection: close\r\n\r\n");
+        let (status, description) = http_request(addr, &request).await?;
+        if status != 200 {
+            return Err(io::Error::other(format!(
+                "Device description returned status {status}"
+            )));
+        }
+        let (service_type, control_url) = parse_wan_service(&description)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "No WAN connection service"))?;
+        let (addr, control_path) = if control_url.starts_with("http://") {
+            parse_url(control_url)?
+        } else if control_url.starts_with('/') {
+            (addr, control_url.to_string())
+        } else {
+            (addr, format!("/{control_url}"))
+        };
+        Ok(Self {
+            addr,
+            control_path,
+            service_type: service_type.to_string(),
+        })
+    }
+
+    pub(crate) async fn add_port(
+        &self,
+        protocol: PortProtocol,
+        local: SocketAddrV4,
+        lease: Duration,
+        description: &str,
+    ) -> io::Result<SocketAddrV4> {
+        self.soap(
+            "AddPortMapping",
+            &[
+                ("NewRemoteHost", String::new()),
+                ("NewExternalPort", local.port().to_string()),
+                ("NewProtocol", protocol.to_string()),
+                ("NewInternalPort", local.port().to_string()),
+                ("NewInternalClient", local.ip().to_string()),
+                ("NewEnabled", "1".to_string()),
+                ("NewPortMappingDescription", escape(description)),
+                ("NewLeaseDuration", lease.as_secs().to_string()),
+            ],
+        )
+        .await?;
+        let response = self.soap("GetExternalIPAddress", &[]).await?;
+        let ip = tag_text(&response, "NewExternalIPAddress")
+            .and_then(|ip| ip.trim().parse().ok())
+            .ok_or_else(|| {
+                io::Error::new(io::ErrorKind::InvalidData, "Missing external address")
+            })?;
+        Ok(SocketAddrV4::new(ip, local.port()))
+    }
+
+    pub(crate) async fn remove_port(&self, protocol: PortProtocol, port: u16) -> io::Result<()> {
+        self.soap(
+            "DeletePortMapping",
+            &[
+                ("NewRemoteHost", String::new()),
+                ("NewExternalPort", port.to_string()),
+                ("NewProtocol", protocol.to_string()),
+            ],
+        )
+        .await
+        .map(|_| ())
+    }
+
+    async fn soap(&self, action: &str, args: &[(&str, String)]) -> io::Result<String> {
+        let service = &self.service_type;
+        let args: String = args
+            .iter()
+            .map(|(name, value)| format!("<{name}>{value}</{name}>"))
+            .collect();
+        let body = format!(
+            "<?xml version=\"1.0\"?>\
+             <s:Envelope xmlns:s=\"http://schemas.xmlsoap.org/soap/envelope/\" \
+             s:encodingStyle=\"http://schemas.xmlsoap.org/soap/encoding/\">\
+             <s:Body><u:{action} xmlns:u=\"{service}\">{args}</u:{action}></s:Body></s:Envelope>"
+        );
+        let request = format!(
+            "POST {} HTTP/1.1\r\nHost: {}\r\nContent-Type: text/xml; charset=\"utf-8\"\r\n\
+             Content-Length: {}\r\nSOAPAction: \"{service}#{action}\"\r\nConnection: close\r\n\r\n{body}",
+            self.control_path,
+            self.addr,
+            body.len(),
+        );
+        let (status, response) = http_request(self.addr, &request).await?;
+        if status != 200 {
+            let code = tag_text(&response, "errorCode").unwrap_or("unknown");
+            let description = tag_text(&response, "errorDescription").unwrap_or_default();
+            return Err(io::Error::other(format!(
+                "UPnP {action} failed with {code} {description}"
+            )));
+        }
+        Ok(response)
+    }
+}
+
+/// 发送请求并读到连接关闭，返回状态码与响应体
+async fn http_request(addr: SocketAddr, request: &str) -> io::Result<(u16, String)> {
+    let mut stream = TcpStream::connect(addr).await?;
+    stream.write_all(request.as_bytes()).await?;
+    let mut response = Vec::new();
+    stream.read_to_end(&mut response).await?;
+    parse_response(&response)
+}
+
+fn parse_response(response: &[u8]) -> io::Result<(u16, String)> {
+    let invalid = || io::Error::new(io::ErrorKind::InvalidData, "Malformed HTTP response");
+    let split = response
+        .windows(4)
+        .position(|w| w == b"\r\n\r\n")
+        .ok_or_else(invalid)?;
+    let head = String::from_utf8_lossy(&response[..split]);
+    let body = &response[split + 4..];
+    let mut lines = head.lines();
+    let status = lines
+        .next()
+        .and_then(|line| line.split_whitespace().nth(1))
+        .and_then(|code| code.parse().ok())
+        .ok_or_else(invalid)?;
+    let chunked = lines.any(|line| {
+        line.split_once(':').is_some_and(|(name, value)| {
+            name.trim().eq_ignore_ascii_case("transfer-encoding")
+                && value.trim().eq_ignore_ascii_case("chunked")
+        })
+    });
+    let body = if chunked {
+        dechunk(body).ok_or_else(invalid)?
+    } else {
+        body.to_vec()
+    };
+    Ok((status, String::from_utf8_lossy(&body).into_owned()))
+}
+
+fn dechunk(mut data: &[u8]) -> Option<Vec<u8>> {
+    let mut body = Vec::new();
+    loop {
+        let line_end = data.windows(2).position(|w| w == b"\r\n")?;
+        let line = std::str::from_utf8(&data[..line_end]).ok()?;
+        let size = line.split(';').next()?.trim();
+        let size = usize::from_str_radix(size, 16).ok()?;
+        data = &data[line_end + 2..];
+        if size == 0 {
+            return Some(body);
+        }
+        body.extend_from_slice(data.get(..size)?);
+        data = data.get(size + 2..)?;
+    }
+}
+
+fn parse_location(response: &[u8]) -> Option<String> {
+    let response = std::str::from_utf8(response).ok()?;
+    response.lines().find_map(|line| {
+        let (name, value) = line.split_once(':')?;
+        name.trim()
+            .eq_ignore_ascii_case("location")
+            .then(|| value.trim().to_string())
+    })
+}
+
+/// 解析 `http://host[:port][/path]`，网关通常以 IP 作为主机
+fn parse_url(url: &str) -> io::Result<(SocketAddr, String)> {
+    let invalid = || io::Error::new(io::ErrorKind::InvalidInput, format!("Invalid URL {url}"));
+    let rest = url.strip_prefix("http://").ok_or_else(invalid)?;
+    let (host, path) = match rest.find('/') {
+        Some(index) => (&rest[..index], &rest[index..]),
+        None => (rest, "/"),
+    };
+    let addr = match host.parse::<SocketAddr>() {
+        Ok(addr) => addr,
+        Err(_) => SocketAddr::new(host.parse().map_err(|_| invalid())?, 80),
+    };
+    Ok((addr, path.to_string()))
+}
+
+/// 在设备描述中查找 WAN 连接服务，返回服务类型与控制地址
+fn parse_wan_service(description: &str) -> Option<(&str, &str)> {
+    description.split("<service>").skip(1).find_map(|service| {
+        let service_type = tag_text(service, "serviceType")?.trim();
+        if !WAN_SERVICES.contains(&service_type) {
+            return None;
+        }
+        Some((service_type, tag_text(service, "controlURL")?.trim()))
+    })
+}
+
+/// 第一个不带命名空间前缀的 `<tag>` 的文本
+fn tag_text<'a>(xml: &'a str, tag: &str) -> Option<&'a str> {
+    let open = format!("<{tag}>");
+    let start = xml.find(&open)? + open.len();
+    let end = xml[start..].find(&format!("</{tag}>"))?;
+    Some(&xml[start..start + end])
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ssdp_location() {
+        let response = b"HTTP/1.1 200 OK\r\nCACHE-CONTROL: max-age=120\r\n\
+                         Location: http://192.168.1.1:5000/rootDesc.xml\r\n\r\n";
+        assert_eq!(
+            parse_location(response).as_deref(),
+            Some("http://192.168.1.1:5000/rootDesc.xml")
+        );
+        let (addr, path) = parse_url("http://192.168.1.1:5000/rootDesc.xml").unwrap();
+        assert_eq!(addr, "192.168.1.1:5000".parse().unwrap());
+        assert_eq!(path, "/rootDesc.xml");
+        let (addr, path) = parse_url("http://192.168.1.1").unwrap();
+        assert_eq!(addr, "192.168.1.1:80".parse().unwrap());
+        assert_eq!(path, "/");
+    }
+
+    #[test]
+    fn wan_service() {
+        let description = "<root><device><serviceList>\
+            <service><serviceType>urn:schemas-upnp-org:service:Layer3Forwarding:1</serviceType>\
+            <controlURL>/ctl/L3F</controlURL></service>\
+            <service><serviceType>urn:schemas-upnp-org:service:WANIPConnection:1</serviceType>\
+            <controlURL>/ctl/IPConn</controlURL></service>\
+            </serviceList></device></root>";
+        assert_eq!(
+            parse_wan_service(description),
+            Some((
+                "urn:schemas-upnp-org:service:WANIPConnection:1",
+                "/ctl/IPConn"
+            ))
+        );
+    }
+
+    #[test]
+    fn chunked_response() {
+        let response = b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n\
+                         5\r\nhello\r\n6\r\n world\r\n0\r\n\r\n";
+        let (status, body) = parse_response(response).unwrap();
+        assert_eq!(status, 200);
+        assert_eq!(body, "hello world");
+    }
+}
@@ -0,0 +1,85 @@
+//! UPnP IGD / NAT-PMP 端口映射
+//!
+//! 监听到私有 IPv4 地址后，向局域网网关请求把同端口映射到公网，映射成功后把
+//! 公网地址作为已确认的外部地址交给 Swarm，并在租期过半时续期；监听地址过期时
+//! 删除映射。优先使用 UPnP IGD，未发现 IGD 时回退到 NAT-PMP。
+//!
+//! 网关通信基于 tokio，行为需要在 tokio 运行时中驱动。
+
+mod behavior;
+mod gateway;
+mod igd;
+mod natpmp;
+
+pub use behavior::Behavior;
+
+use std::{io, net::Ipv4Addr, time::Duration};
+
+use volans_core::Multiaddr;
+
+/// 端口映射配置
+#[derive(Debug, Clone)]
+pub struct Config {
+    lease_duration: Duration,
+    timeout: Duration,
+    description: String,
+    nat_pmp_gateway: Option<Ipv4Addr>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            lease_duration: Duration::from_secs(60 * 60),
+            timeout: Duration::from_secs(10),
+            description: "volans".to_string(),
+            nat_pmp_gateway: None,
+        }
+    }
+}
+
+impl Config {
+    /// 映射的租期，租期过半时续期，至少为 1 分钟
+    pub fn with_lease_duration(mut self, duration: Duration) -> Self {
+        self.lease_duration = duration.max(Duration::from_secs(60));
+        self
+    }
+
+    /// 网关发现及每次网关请求的超时时间
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// 写入网关映射表的描述
+    pub fn with_description(mut self, description: impl Into<String>) -> Self {
+        self.description = description.into();
+        self
+    }
+
+    /// NAT-PMP 网关地址，默认读取系统的默认路由
+    pub fn with_nat_pmp_gateway(mut self, gateway: Option<Ipv4Addr>) -> Self {
+        self.nat_pmp_gateway = gateway;
+        self
+    }
+}
+
+#[derive(Debug)]
+pub enum Event {
+    /// 端口映射成功，`external` 已作为外部地址交给 Swarm
+    NewExternalAddr {
+        listen_addr: Multiaddr,
+        external: Multiaddr,
+    },
+    /// 映射被删除或续期失败，`external` 不再可达
+    ExpiredExternalAddr {
+        listen_addr: Multiaddr,
+        external: Multiaddr,
+    },
+    /// 没有找到支持 UPnP IGD 或 NAT-PMP 的网关，新的监听地址会触发重新发现
+    GatewayNotFound(io::Error),
+    /// 网关拒绝了端口映射
+    MappingFailed {
+        listen_addr: Multiaddr,
+        error: io::Error,
+    },
+}
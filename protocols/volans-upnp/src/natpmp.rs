@@ -0,0 +1,187 @@
+//! NAT-PMP 客户端，RFC 6886
+
+use std::{
+    io,
+    net::{Ipv4Addr, SocketAddrV4},
+    pin::pin,
+    time::Duration,
+};
+
+use futures::future::{self, Either};
+use futures_timer::Delay;
+use tokio::net::UdpSocket;
+
+use crate::gateway::PortProtocol;
+
+const PORT: u16 = 5351;
+/// 请求重发次数，间隔从 250ms 起逐次翻倍
+const ATTEMPTS: u32 = 4;
+
+#[derive(Debug)]
+pub(crate) struct Gateway {
+    addr: SocketAddrV4,
+}
+
+impl Gateway {
+    pub(crate) fn new(ip: Ipv4Addr) -> Self {
+        Self {
+            addr: SocketAddrV4::new(ip, PORT),
+        }
+    }
+
+    pub(crate) async fn external_ip(&self) -> io::Result<Ipv4Addr> {
+        let response = self.request(&[0, 0]).await?;
+        decode_external_ip(&response)
+    }
+
+    pub(crate) async fn add_port(
+        &self,
+        protocol: PortProtocol,
+        local: SocketAddrV4,
+        lease: Duration,
+    ) -> io::Result<SocketAddrV4> {
+        let lifetime = lease.as_secs().min(u32::MAX as u64) as u32;
+        let request = encode_mapping(protocol, local.port(), local.port(), lifetime);
+        let response = self.request(&request).await?;
+        let port = decode_mapping(protocol, &response)?;
+        let ip = self.external_ip().await?;
+        Ok(SocketAddrV4::new(ip, port))
+    }
+
+    /// 租期为 0 的映射请求即删除映射
+    pub(crate) async fn remove_port(&self, protocol: PortProtocol, port: u16) -> io::Result<()> {
+        let request = encode_mapping(protocol, port, 0, 0);
+        let response = self.request(&request).await?;
+        decode_mapping(protocol, &response).map(|_| ())
+    }
+
+    async fn request(&self, packet: &[u8]) -> io::Result<Vec<u8>> {
+        let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0)).await?;
+        socket.connect(self.addr).await?;
+        let mut buf = [0u8; 16];
+        for attempt in 0..ATTEMPTS {
+            socket.send(packet).await?;
+            let wait = Duration::from_millis(250 << attempt);
+            let result = match future::select(pin!(socket.recv(&mut buf)), Delay::new(wait)).await {
+                Either::Left((result, _)) => result,
+                Either::Right(_) => continue,
+            };
+            return result.map(|len| buf[..len].to_vec());
+        }
+        Err(io::ErrorKind::TimedOut.into())
+    }
+}
+
+fn opcode(protocol: PortProtocol) -> u8 {
+    match protocol {
+        PortProtocol::Udp => 1,
+        PortProtocol::Tcp => 2,
+    }
+}
+
+fn encode_mapping(protocol: PortProtocol, internal: u16, external: u16, lifetime: u32) -> [u8; 12] {
+    let mut packet = [0u8; 12];
+    packet[1] = opcode(protocol);
+    packet[4..6].copy_from_slice(&internal.to_be_bytes());
+    packet[6..8].copy_from_slice(&external.to_be_bytes());
+    packet[8..12].copy_from_slice(&lifetime.to_be_bytes());
+    packet
+}
+
+/// 校验响应头，返回结果码之后的内容
+fn decode_header(op: u8, response: &[u8], len: usize) -> io::Result<&[u8]> {
+    if response.len() < len || response[0] != 0 || response[1] != 128 + op {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "Malformed NAT-PMP response",
+        ));
+    }
+    let code = u16::from_be_bytes([response[2], response[3]]);
+    if code != 0 {
+        return Err(io::Error::other(format!(
+            "NAT-PMP request failed with result code {code}"
+        )));
+    }
+    Ok(&response[4..len])
+}
+
+fn decode_external_ip(response: &[u8]) -> io::Result<Ipv4Addr> {
+    let body = decode_header(0, response, 12)?;
+    Ok(Ipv4Addr::new(body[4], body[5], body[6], body[7]))
+}
+
+/// 返回网关分配的外部端口
+fn decode_mapping(protocol: PortProtocol, response: &[u8]) -> io::Result<u16> {
+    let body = decode_header(opcode(protocol), response, 16)?;
+    Ok(u16::from_be_bytes([body[6], body[7]]))
+}
+
+/// 从 `/proc/net/route` 读取默认路由的网关
+#[cfg(target_os = "linux")]
+pub(crate) fn default_gateway() -> Option<Ipv4Addr> {
+    let routes = std::fs::read_to_string("/proc/net/route").ok()?;
+    parse_default_route(&routes)
+}
+
+#[cfg(not(target_os = "linux"))]
+pub(crate) fn default_gateway() -> Option<Ipv4Addr> {
+    None
+}
+
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn parse_default_route(routes: &str) -> Option<Ipv4Addr> {
+    routes.lines().skip(1).find_map(|line| {
+        let mut fields = line.split_whitespace().skip(1);
+        let (destination, gateway) = (fields.next()?, fields.next()?);
+        if destination != "00000000" {
+            return None;
+        }
+        // 内核按主机字节序输出网络字节序的地址
+        let gateway = u32::from_str_radix(gateway, 16).ok()?;
+        Some(Ipv4Addr::from(gateway.to_le_bytes()))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mapping_roundtrip() {
+        let request = encode_mapping(PortProtocol::Tcp, 4001, 4001, 3600);
+        assert_eq!(
+            request,
+            [0, 2, 0, 0, 0x0f, 0xa1, 0x0f, 0xa1, 0, 0, 0x0e, 0x10]
+        );
+
+        let response = [
+            0, 130, 0, 0, 0, 0, 0, 1, 0x0f, 0xa1, 0x13, 0x88, 0, 0, 0x0e, 0x10,
+        ];
+        assert_eq!(decode_mapping(PortProtocol::Tcp, &response).unwrap(), 5000);
+        assert!(decode_mapping(PortProtocol::Udp, &response).is_err());
+
+        let mut refused = response;
+        refused[3] = 2;
+        assert!(decode_mapping(PortProtocol::Tcp, &refused).is_err());
+    }
+
+    #[test]
+    fn external_ip_response() {
+        let response = [0, 128, 0, 0, 0, 0, 0, 1, 203, 0, 113, 7];
+        assert_eq!(
+            decode_external_ip(&response).unwrap(),
+            Ipv4Addr::new(203, 0, 113, 7)
+        );
+    }
+
+    #[test]
+    fn default_route() {
+        let routes = "Iface\tDestination\tGateway \tFlags\n\
+                      eth0\t0000A8C0\t00000000\t0001\n\
+                      eth0\t00000000\t0101A8C0\t0003\n";
+        assert_eq!(
+            parse_default_route(routes),
+            Some(Ipv4Addr::new(192, 168, 1, 1))
+        );
+    }
+}
@@ -0,0 +1,433 @@
+//! 连接级透明压缩升级
+//!
+//! 在认证之后、多路复用之前协商压缩算法，之后的所有读写都经过压缩：
+//!
+//! ```ignore
+//! transport
+//!     .upgrade()
+//!     .authenticate(identity)
+//!     .apply(volans_compress::Config::default())
+//!     .multiplex(muxer)
+//! ```
+//!
+//! 算法通过协议名协商，`/v1/zstd` 优先于 `/v1/deflate`。
+
+use std::{
+    fmt, io,
+    pin::Pin,
+    task::{Context, Poll, ready},
+};
+
+use flate2::{Compress, Compression, Decompress, FlushCompress, FlushDecompress, Status};
+use futures::{AsyncRead, AsyncWrite, future};
+use volans_core::{
+    UpgradeInfo,
+    upgrade::{InboundConnectionUpgrade, OutboundConnectionUpgrade},
+};
+use zstd::stream::raw::{InBuffer, Operation, OutBuffer};
+
+/// 每次为压缩输出或读取预留的空间
+const CHUNK_SIZE: usize = 8 * 1024;
+/// 压缩输出超过该大小时先写入底层连接
+const HIGH_WATER_MARK: usize = 64 * 1024;
+
+/// 压缩算法
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Algorithm {
+    Zstd,
+    Deflate,
+}
+
+impl Algorithm {
+    pub fn protocol_name(&self) -> &'static str {
+        match self {
+            Algorithm::Zstd => "/v1/zstd",
+            Algorithm::Deflate => "/v1/deflate",
+        }
+    }
+
+    fn from_protocol_name(name: &str) -> Option<Self> {
+        [Algorithm::Zstd, Algorithm::Deflate]
+            .into_iter()
+            .find(|algorithm| algorithm.protocol_name() == name)
+    }
+}
+
+impl fmt::Display for Algorithm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.protocol_name())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Config {
+    algorithms: Vec<Algorithm>,
+    zstd_level: i32,
+    deflate_level: u32,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            algorithms: vec![Algorithm::Zstd, Algorithm::Deflate],
+            zstd_level: 3,
+            deflate_level: 6,
+        }
+    }
+}
+
+impl Config {
+    /// 支持的算法，按优先级排列
+    pub fn with_algorithms(mut self, algorithms: impl IntoIterator<Item = Algorithm>) -> Self {
+        self.algorithms = algorithms.into_iter().collect();
+        self
+    }
+
+    /// zstd 压缩级别，1-22
+    pub fn with_zstd_level(mut self, level: i32) -> Self {
+        self.zstd_level = level;
+        self
+    }
+
+    /// deflate 压缩级别，0-9
+    pub fn with_deflate_level(mut self, level: u32) -> Self {
+        self.deflate_level = level.min(9);
+        self
+    }
+
+    fn upgrade<C>(self, socket: C, info: &str) -> io::Result<Compressed<C>> {
+        let algorithm = Algorithm::from_protocol_name(info).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("Unsupported compression protocol {info}"),
+            )
+        })?;
+        tracing::trace!("Compressing connection with {}", algorithm);
+        let (encoder, decoder) = match algorithm {
+            Algorithm::Zstd => (
+                Encoder::Zstd(zstd::stream::raw::Encoder::new(self.zstd_level)?),
+                Decoder::Zstd(zstd::stream::raw::Decoder::new()?),
+            ),
+            Algorithm::Deflate => (
+                Encoder::Deflate(Compress::new(Compression::new(self.deflate_level), false)),
+                Decoder::Deflate(Decompress::new(false)),
+            ),
+        };
+        Ok(Compressed::new(socket, algorithm, encoder, decoder))
+    }
+}
+
+impl UpgradeInfo for Config {
+    type Info = &'static str;
+    type InfoIter = std::vec::IntoIter<Self::Info>;
+
+    fn protocol_info(&self) -> Self::InfoIter {
+        self.algorithms
+            .iter()
+            .map(Algorithm::protocol_name)
+            .collect::<Vec<_>>()
+            .into_iter()
+    }
+}
+
+impl<C> InboundConnectionUpgrade<C> for Config
+where
+    C: AsyncRead + AsyncWrite + Unpin,
+{
+    type Output = Compressed<C>;
+    type Error = io::Error;
+    type Future = future::Ready<Result<Self::Output, Self::Error>>;
+
+    fn upgrade_inbound(self, socket: C, info: Self::Info) -> Self::Future {
+        future::ready(self.upgrade(socket, info))
+    }
+}
+
+impl<C> OutboundConnectionUpgrade<C> for Config
+where
+    C: AsyncRead + AsyncWrite + Unpin,
+{
+    type Output = Compressed<C>;
+    type Error = io::Error;
+    type Future = future::Ready<Result<Self::Output, Self::Error>>;
+
+    fn upgrade_outbound(self, socket: C, info: Self::Info) -> Self::Future {
+        future::ready(self.upgrade(socket, info))
+    }
+}
+
+enum Encoder {
+    Zstd(zstd::stream::raw::Encoder<'static>),
+    Deflate(Compress),
+}
+
+impl Encoder {
+    /// 压缩 `input` 追加到 `output` 的剩余容量，返回消耗的输入字节数
+    fn encode(&mut self, input: &[u8], output: &mut Vec<u8>) -> io::Result<usize> {
+        match self {
+            Encoder::Zstd(encoder) => {
+                let mut input = InBuffer::around(input);
+                let mut output = OutBuffer::around_pos(output, output.len());
+                encoder.run(&mut input, &mut output)?;
+                Ok(input.pos())
+            }
+            Encoder::Deflate(compress) => {
+                let before = compress.total_in();
+                compress
+                    .compress_vec(input, output, FlushCompress::None)
+                    .map_err(io::Error::other)?;
+                Ok((compress.total_in() - before) as usize)
+            }
+        }
+    }
+
+    /// 输出已压缩的全部数据，使对端可以立即解压，返回是否已完成
+    fn flush(&mut self, output: &mut Vec<u8>) -> io::Result<bool> {
+        match self {
+            Encoder::Zstd(encoder) => {
+                let mut output = OutBuffer::around_pos(output, output.len());
+                Ok(encoder.flush(&mut output)? == 0)
+            }
+            Encoder::Deflate(compress) => {
+                compress
+                    .compress_vec(&[], output, FlushCompress::Sync)
+                    .map_err(io::Error::other)?;
+                // 输出空间有剩余说明同步标记已完整写出
+                Ok(output.len() < output.capacity())
+            }
+        }
+    }
+}
+
+enum Decoder {
+    Zstd(zstd::stream::raw::Decoder<'static>),
+    Deflate(Decompress),
+}
+
+impl Decoder {
+    /// 返回消耗的输入与写出的字节数
+    fn decode(&mut self, input: &[u8], output: &mut [u8]) -> io::Result<(usize, usize)> {
+        match self {
+            Decoder::Zstd(decoder) => {
+                let status = decoder.run_on_buffers(input, output)?;
+                Ok((status.bytes_read, status.bytes_written))
+            }
+            Decoder::Deflate(decompress) => {
+                let (total_in, total_out) = (decompress.total_in(), decompress.total_out());
+                let status = decompress
+                    .decompress(input, output, FlushDecompress::None)
+                    .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+                if status == Status::StreamEnd {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        "Unexpected end of deflate stream",
+                    ));
+                }
+                Ok((
+                    (decompress.total_in() - total_in) as usize,
+                    (decompress.total_out() - total_out) as usize,
+                ))
+            }
+        }
+    }
+}
+
+/// 经过压缩的连接
+///
+/// 写入的数据在 `flush` 时才保证送达对端。
+pub struct Compressed<S> {
+    inner: S,
+    algorithm: Algorithm,
+    encoder: Encoder,
+    decoder: Decoder,
+    /// 待写入底层连接的压缩数据
+    write_buf: Vec<u8>,
+    /// 自上次 flush 后是否有新的输入
+    dirty: bool,
+    /// 已从底层连接读取、尚未解压的数据
+    read_buf: Vec<u8>,
+    read_pos: usize,
+    eof: bool,
+}
+
+impl<S> Compressed<S> {
+    fn new(inner: S, algorithm: Algorithm, encoder: Encoder, decoder: Decoder) -> Self {
+        Self {
+            inner,
+            algorithm,
+            encoder,
+            decoder,
+            write_buf: Vec::new(),
+            dirty: false,
+            read_buf: Vec::new(),
+            read_pos: 0,
+            eof: false,
+        }
+    }
+
+    pub fn algorithm(&self) -> Algorithm {
+        self.algorithm
+    }
+
+    pub fn get_ref(&self) -> &S {
+        &self.inner
+    }
+
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+}
+
+impl<S> Compressed<S>
+where
+    S: AsyncWrite + Unpin,
+{
+    fn poll_drain(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        while !self.write_buf.is_empty() {
+            let n = ready!(Pin::new(&mut self.inner).poll_write(cx, &self.write_buf))?;
+            if n == 0 {
+                return Poll::Ready(Err(io::ErrorKind::WriteZero.into()));
+            }
+            self.write_buf.drain(..n);
+        }
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<S> AsyncRead for Compressed<S>
+where
+    S: AsyncRead + Unpin,
+{
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        if buf.is_empty() {
+            return Poll::Ready(Ok(0));
+        }
+        loop {
+            // 解码器内部可能还有未输出的数据，即使没有新的输入也先尝试解压
+            let (consumed, produced) = this.decoder.decode(&this.read_buf[this.read_pos..], buf)?;
+            this.read_pos += consumed;
+            if produced > 0 {
+                return Poll::Ready(Ok(produced));
+            }
+            if consumed > 0 {
+                continue;
+            }
+            if this.eof {
+                return Poll::Ready(Ok(0));
+            }
+            this.read_buf.drain(..this.read_pos);
+            this.read_pos = 0;
+            let filled = this.read_buf.len();
+            this.read_buf.resize(filled + CHUNK_SIZE, 0);
+            let result = Pin::new(&mut this.inner).poll_read(cx, &mut this.read_buf[filled..]);
+            let n = match result {
+                Poll::Ready(Ok(n)) => n,
+                other => {
+                    this.read_buf.truncate(filled);
+                    return other;
+                }
+            };
+            this.read_buf.truncate(filled + n);
+            this.eof = n == 0;
+        }
+    }
+}
+
+impl<S> AsyncWrite for Compressed<S>
+where
+    S: AsyncWrite + Unpin,
+{
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        if buf.is_empty() {
+            return Poll::Ready(Ok(0));
+        }
+        loop {
+            if this.write_buf.len() >= HIGH_WATER_MARK {
+                ready!(this.poll_drain(cx))?;
+            }
+            this.write_buf.reserve(CHUNK_SIZE);
+            let consumed = this.encoder.encode(buf, &mut this.write_buf)?;
+            if consumed > 0 {
+                this.dirty = true;
+                return Poll::Ready(Ok(consumed));
+            }
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        while this.dirty {
+            this.write_buf.reserve(CHUNK_SIZE);
+            this.dirty = !this.encoder.flush(&mut this.write_buf)?;
+        }
+        ready!(this.poll_drain(cx))?;
+        Pin::new(&mut this.inner).poll_flush(cx)
+    }
+
+    fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        ready!(self.as_mut().poll_flush(cx))?;
+        Pin::new(&mut self.inner).poll_close(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::{AsyncReadExt, AsyncWriteExt, io::Cursor};
+
+    use super::*;
+
+    async fn roundtrip(algorithm: Algorithm) {
+        let config = Config::default().with_algorithms([algorithm]);
+        let payload = b"volans compression ".repeat(4096);
+
+        let mut writer = config
+            .clone()
+            .upgrade(Cursor::new(Vec::new()), algorithm.protocol_name())
+            .unwrap();
+        writer.write_all(&payload).await.unwrap();
+        writer.flush().await.unwrap();
+        writer.write_all(b"tail").await.unwrap();
+        writer.close().await.unwrap();
+        let compressed = writer.into_inner().into_inner();
+        assert!(compressed.len() < payload.len() / 10);
+
+        let mut reader = config
+            .upgrade(Cursor::new(compressed), algorithm.protocol_name())
+            .unwrap();
+        let mut received = Vec::new();
+        reader.read_to_end(&mut received).await.unwrap();
+        assert_eq!(received.len(), payload.len() + 4);
+        assert_eq!(&received[..payload.len()], &payload[..]);
+        assert_eq!(&received[payload.len()..], b"tail");
+    }
+
+    #[tokio::test]
+    async fn zstd_roundtrip() {
+        roundtrip(Algorithm::Zstd).await;
+    }
+
+    #[tokio::test]
+    async fn deflate_roundtrip() {
+        roundtrip(Algorithm::Deflate).await;
+    }
+
+    #[test]
+    fn protocol_names() {
+        let config = Config::default();
+        assert_eq!(
+            config.protocol_info().collect::<Vec<_>>(),
+            ["/v1/zstd", "/v1/deflate"]
+        );
+        assert!(config.upgrade((), "/v1/gzip").is_err());
+    }
+}
@@ -0,0 +1,343 @@
+//! DNS 解析传输层包装
+//!
+//! 将地址中的 `/dns`、`/dns4`、`/dns6` 解析为 IP 地址后交给内部传输层拨号，
+//! 解析出多个地址时依次尝试，直到有一个连接成功。
+//!
+//! 以 `_` 开头的域名（如 `/dns/_volans._tcp.example.com/tcp/0`）按 SRV 记录查询，
+//! 记录中的端口替换地址中的端口。拨号时通过 [`dns::scoped`] 指定的解析器优先于
+//! 传输层自身的解析器。
+
+mod resolver;
+
+pub use resolver::TokioResolver;
+pub use volans_core::transport::dns::{Resolver, SrvTarget};
+
+use std::{
+    io,
+    net::IpAddr,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+};
+
+use futures::{FutureExt, TryFutureExt, future::BoxFuture};
+use volans_core::{
+    Listener, ListenerEvent, Multiaddr, SocketOptions, Transport, TransportError,
+    multiaddr::Protocol, transport::dns,
+};
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error<TErr> {
+    #[error("Transport error: {0}")]
+    Transport(TErr),
+    #[error("DNS resolve error: {0}")]
+    ResolveError(io::Error),
+    #[error("Multiaddr not supported: {0}")]
+    MultiaddrNotSupported(Multiaddr),
+}
+
+#[derive(Debug)]
+pub struct Config<T, R = TokioResolver> {
+    inner: Arc<T>,
+    resolver: R,
+}
+
+impl<T, R: Clone> Clone for Config<T, R> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+            resolver: self.resolver.clone(),
+        }
+    }
+}
+
+impl<T> Config<T> {
+    /// 使用系统解析器
+    pub fn new(inner: T) -> Self {
+        Self::custom(inner, TokioResolver)
+    }
+}
+
+impl<T, R> Config<T, R> {
+    /// 使用自定义解析器
+    pub fn custom(inner: T, resolver: R) -> Self {
+        Self {
+            inner: Arc::new(inner),
+            resolver,
+        }
+    }
+}
+
+impl<T, R> Transport for Config<T, R>
+where
+    T: Transport + Send + Sync + 'static,
+    T::Dial: Send,
+    T::Output: Send,
+    T::Error: Send,
+    R: Resolver + Clone,
+{
+    type Output = T::Output;
+    type Error = Error<T::Error>;
+    type Dial = BoxFuture<'static, Result<Self::Output, Self::Error>>;
+    type Incoming = futures::future::MapErr<T::Incoming, fn(T::Error) -> Self::Error>;
+    type Listener = ListenStream<T::Listener>;
+
+    fn dial(&self, addr: Multiaddr) -> Result<Self::Dial, TransportError<Self::Error>> {
+        let Some((index, name)) = find_dns(&addr) else {
+            // 无需解析，直接交给内部传输层
+            let dial = self.inner.dial(addr).map_err(|e| e.map(Error::Transport))?;
+            return Ok(dial.map_err(Error::Transport).boxed());
+        };
+
+        let inner = self.inner.clone();
+        let resolver = dns::current().unwrap_or_else(|| Arc::new(self.resolver.clone()));
+        Ok(async move {
+            let addrs = resolve(&*resolver, &addr, index, &name)
+                .await
+                .map_err(Error::ResolveError)?;
+
+            let mut last_error = None;
+            for resolved in addrs {
+                tracing::debug!("Dialing {} resolved from {}", resolved, addr);
+                match inner.dial(resolved) {
+                    Ok(dial) => match dial.await {
+                        Ok(output) => return Ok(output),
+                        Err(err) => {
+                            tracing::debug!("Dial to resolved address failed: {}", err);
+                            last_error = Some(Error::Transport(err));
+                        }
+                    },
+                    Err(TransportError::NotSupported(a)) => {
+                        last_error = Some(Error::MultiaddrNotSupported(a));
+                    }
+                    Err(TransportError::Other(err)) => {
+                        last_error = Some(Error::Transport(err));
+                    }
+                }
+            }
+            Err(last_error.unwrap_or_else(|| {
+                Error::ResolveError(io::Error::new(
+                    io::ErrorKind::NotFound,
+                    format!("No addresses resolved for {}", name),
+                ))
+            }))
+        }
+        .boxed())
+    }
+
+    fn listen(
+        &self,
+        addr: Multiaddr,
+        opts: &SocketOptions,
+    ) -> Result<Self::Listener, TransportError<Self::Error>> {
+        let listener = self
+            .inner
+            .listen(addr, opts)
+            .map_err(|e| e.map(Error::Transport))?;
+        Ok(ListenStream { inner: listener })
+    }
+}
+
+/// 待解析的域名及其地址族限制
+enum DnsName {
+    Any(String),
+    V4(String),
+    V6(String),
+}
+
+impl DnsName {
+    fn accepts(&self, ip: &IpAddr) -> bool {
+        match self {
+            DnsName::Any(_) => true,
+            DnsName::V4(_) => ip.is_ipv4(),
+            DnsName::V6(_) => ip.is_ipv6(),
+        }
+    }
+
+    fn as_str(&self) -> &str {
+        match self {
+            DnsName::Any(name) | DnsName::V4(name) | DnsName::V6(name) => name,
+        }
+    }
+
+    /// SRV 记录名以 `_` 开头，如 `_service._tcp.example.com`
+    fn is_srv(&self) -> bool {
+        self.as_str().starts_with('_')
+    }
+}
+
+impl std::fmt::Display for DnsName {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// 解析出的候选地址，单个主机解析失败时继续解析其余主机
+async fn resolve(
+    resolver: &dyn Resolver,
+    addr: &Multiaddr,
+    index: usize,
+    name: &DnsName,
+) -> io::Result<Vec<Multiaddr>> {
+    let targets = if name.is_srv() {
+        resolver
+            .lookup_srv(name.as_str())
+            .await?
+            .into_iter()
+            .map(|target| (target.host, Some(target.port)))
+            .collect()
+    } else {
+        vec![(name.as_str().to_string(), None)]
+    };
+    let mut addrs = Vec::new();
+    let mut last_error = None;
+    for (host, port) in targets {
+        let ips = match host.parse::<IpAddr>() {
+            Ok(ip) => vec![ip],
+            Err(_) => match resolver.lookup_ip(&host).await {
+                Ok(ips) => ips,
+                Err(error) => {
+                    tracing::debug!("Failed to resolve {}: {}", host, error);
+                    last_error = Some(error);
+                    continue;
+                }
+            },
+        };
+        addrs.extend(
+            ips.into_iter()
+                .filter(|ip| name.accepts(ip))
+                .map(|ip| with_target(addr, index, ip, port)),
+        );
+    }
+    match last_error {
+        Some(error) if addrs.is_empty() => Err(error),
+        _ => Ok(addrs),
+    }
+}
+
+/// 以解析出的 IP 替换域名，给定端口时替换域名之后的第一个端口
+fn with_target(addr: &Multiaddr, index: usize, ip: IpAddr, mut port: Option<u16>) -> Multiaddr {
+    let mut resolved = Multiaddr::empty();
+    for (i, protocol) in addr.iter().enumerate() {
+        let protocol = match protocol {
+            _ if i == index => ip.into(),
+            Protocol::Tcp(p) if i > index => Protocol::Tcp(port.take().unwrap_or(p)),
+            Protocol::Udp(p) if i > index => Protocol::Udp(port.take().unwrap_or(p)),
+            protocol => protocol,
+        };
+        resolved.push(protocol);
+    }
+    resolved
+}
+
+fn find_dns(addr: &Multiaddr) -> Option<(usize, DnsName)> {
+    addr.iter().enumerate().find_map(|(i, p)| match p {
+        Protocol::Dns(name) => Some((i, DnsName::Any(name.into_owned()))),
+        Protocol::Dns4(name) => Some((i, DnsName::V4(name.into_owned()))),
+        Protocol::Dns6(name) => Some((i, DnsName::V6(name.into_owned()))),
+        _ => None,
+    })
+}
+
+#[pin_project::pin_project]
+pub struct ListenStream<L> {
+    #[pin]
+    inner: L,
+}
+
+impl<L> Listener for ListenStream<L>
+where
+    L: Listener,
+{
+    type Output = L::Output;
+    type Error = Error<L::Error>;
+    type Upgrade = futures::future::MapErr<L::Upgrade, fn(L::Error) -> Self::Error>;
+
+    fn poll_event(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<ListenerEvent<Self::Upgrade, Self::Error>> {
+        self.project().inner.poll_event(cx).map(|event| {
+            event
+                .map_upgrade(|u| u.map_err(Error::Transport as fn(_) -> _))
+                .map_err(Error::Transport)
+        })
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.project()
+            .inner
+            .poll_close(cx)
+            .map_err(Error::Transport)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::{Ipv4Addr, Ipv6Addr};
+
+    use super::*;
+
+    #[derive(Clone)]
+    struct StaticResolver(Vec<IpAddr>);
+
+    impl Resolver for StaticResolver {
+        fn lookup_ip(&self, _name: &str) -> BoxFuture<'static, io::Result<Vec<IpAddr>>> {
+            futures::future::ready(Ok(self.0.clone())).boxed()
+        }
+    }
+
+    /// 只通过 SRV 记录解析 `_volans._tcp.example.com`
+    struct SrvResolver(u16);
+
+    impl Resolver for SrvResolver {
+        fn lookup_ip(&self, name: &str) -> BoxFuture<'static, io::Result<Vec<IpAddr>>> {
+            let result = match name {
+                "node.example.com" => Ok(vec![IpAddr::V4(Ipv4Addr::LOCALHOST)]),
+                _ => Err(io::ErrorKind::NotFound.into()),
+            };
+            futures::future::ready(result).boxed()
+        }
+
+        fn lookup_srv(&self, _name: &str) -> BoxFuture<'static, io::Result<Vec<SrvTarget>>> {
+            let targets = vec![
+                SrvTarget::new("missing.example.com", self.0),
+                SrvTarget::new("node.example.com", self.0),
+            ];
+            futures::future::ready(Ok(targets)).boxed()
+        }
+    }
+
+    #[tokio::test]
+    async fn dial_with_scoped_srv_resolver() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        // 传输层自身的解析器解析不到任何地址
+        let dns = Config::custom(volans_tcp::Config::new(), StaticResolver(Vec::new()));
+        let addr: Multiaddr = "/dns4/_volans._tcp.example.com/tcp/0".parse().unwrap();
+        let dial = dns::scoped(Some(Arc::new(SrvResolver(port))), || {
+            dns.dial(addr.clone()).unwrap()
+        });
+        let (dialed, _) = futures::join!(dial, listener.accept());
+        assert!(dialed.is_ok());
+
+        assert!(dns.dial(addr).unwrap().await.is_err());
+    }
+
+    #[tokio::test]
+    async fn dial_retries_resolved_addresses() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        // 第一个地址无法连接，应当继续尝试下一个
+        let resolver = StaticResolver(vec![
+            IpAddr::V6(Ipv6Addr::LOCALHOST),
+            IpAddr::V4(Ipv4Addr::LOCALHOST),
+        ]);
+        let dns = Config::custom(volans_tcp::Config::new(), resolver);
+        let addr: Multiaddr = format!("/dns/example.com/tcp/{port}").parse().unwrap();
+        let (dialed, _) = futures::join!(dns.dial(addr).unwrap(), listener.accept());
+        assert!(dialed.is_ok());
+    }
+}
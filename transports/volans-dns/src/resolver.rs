@@ -0,0 +1,25 @@
+use std::{io, net::IpAddr};
+
+use futures::{FutureExt, future::BoxFuture};
+use volans_core::transport::dns::Resolver;
+
+/// 基于 `tokio::net::lookup_host` 的系统解析器
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TokioResolver;
+
+impl Resolver for TokioResolver {
+    fn lookup_ip(&self, name: &str) -> BoxFuture<'static, io::Result<Vec<IpAddr>>> {
+        let name = name.to_string();
+        async move {
+            let addrs = tokio::net::lookup_host((name.as_str(), 0)).await?;
+            let mut ips = Vec::new();
+            for addr in addrs {
+                if !ips.contains(&addr.ip()) {
+                    ips.push(addr.ip());
+                }
+            }
+            Ok(ips)
+        }
+        .boxed()
+    }
+}
@@ -0,0 +1,103 @@
+use futures::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use volans_core::{PeerId, identity::PublicKey};
+
+use crate::{Config, Error, NoiseOutput, io::MAX_FRAME_LEN};
+
+pub(crate) async fn initiator<C>(
+    config: &Config,
+    mut socket: C,
+) -> Result<(PeerId, NoiseOutput<C>), Error>
+where
+    C: AsyncRead + AsyncWrite + Unpin,
+{
+    let mut state = config
+        .builder()
+        .local_private_key(&config.keystore.keypair().to_scalar_bytes())
+        .build_initiator()?;
+    let mut buf = vec![0u8; MAX_FRAME_LEN];
+
+    // -> e
+    let n = state.write_message(&[], &mut buf)?;
+    send_frame(&mut socket, &buf[..n]).await?;
+
+    // <- e, ee, s, es
+    let frame = recv_frame(&mut socket).await?;
+    let n = state.read_message(&frame, &mut buf)?;
+    let remote = verify_payload(&buf[..n], state.get_remote_static())?;
+
+    // -> s, se
+    let n = state.write_message(config.keystore.public_key().as_bytes(), &mut buf)?;
+    send_frame(&mut socket, &buf[..n]).await?;
+
+    let state = state.into_transport_mode()?;
+    Ok((
+        PeerId::from_public_key(&remote),
+        NoiseOutput::new(socket, state, remote),
+    ))
+}
+
+pub(crate) async fn responder<C>(
+    config: &Config,
+    mut socket: C,
+) -> Result<(PeerId, NoiseOutput<C>), Error>
+where
+    C: AsyncRead + AsyncWrite + Unpin,
+{
+    let mut state = config
+        .builder()
+        .local_private_key(&config.keystore.keypair().to_scalar_bytes())
+        .build_responder()?;
+    let mut buf = vec![0u8; MAX_FRAME_LEN];
+
+    // -> e
+    let frame = recv_frame(&mut socket).await?;
+    state.read_message(&frame, &mut buf)?;
+
+    // <- e, ee, s, es
+    let n = state.write_message(config.keystore.public_key().as_bytes(), &mut buf)?;
+    send_frame(&mut socket, &buf[..n]).await?;
+
+    // -> s, se
+    let frame = recv_frame(&mut socket).await?;
+    let n = state.read_message(&frame, &mut buf)?;
+    let remote = verify_payload(&buf[..n], state.get_remote_static())?;
+
+    let state = state.into_transport_mode()?;
+    Ok((
+        PeerId::from_public_key(&remote),
+        NoiseOutput::new(socket, state, remote),
+    ))
+}
+
+/// 负载为对端 Ed25519 公钥，其 X25519 形式必须与握手中的静态密钥一致
+fn verify_payload(payload: &[u8], remote_static: Option<&[u8]>) -> Result<PublicKey, Error> {
+    let bytes: &[u8; 32] = payload.try_into().map_err(|_| Error::InvalidPayload)?;
+    let remote = PublicKey::from_bytes(bytes)?;
+    match remote_static {
+        Some(key) if key == remote.to_montgomery().as_bytes() => Ok(remote),
+        _ => Err(Error::StaticKeyMismatch),
+    }
+}
+
+async fn send_frame<C>(socket: &mut C, frame: &[u8]) -> Result<(), Error>
+where
+    C: AsyncWrite + Unpin,
+{
+    socket
+        .write_all(&(frame.len() as u16).to_be_bytes())
+        .await?;
+    socket.write_all(frame).await?;
+    socket.flush().await?;
+    Ok(())
+}
+
+async fn recv_frame<C>(socket: &mut C) -> Result<Vec<u8>, Error>
+where
+    C: AsyncRead + Unpin,
+{
+    let mut len = [0u8; 2];
+    socket.read_exact(&mut len).await?;
+    let mut frame = vec![0u8; u16::from_be_bytes(len) as usize];
+    socket.read_exact(&mut frame).await?;
+    Ok(frame)
+}
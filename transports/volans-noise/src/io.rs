@@ -0,0 +1,176 @@
+use std::{
+    cmp, io,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use futures::{AsyncRead, AsyncWrite, ready};
+use volans_core::identity::PublicKey;
+
+/// Noise 消息的最大长度
+pub(crate) const MAX_FRAME_LEN: usize = 65535;
+/// 认证标签长度
+const TAG_LEN: usize = 16;
+/// 单帧可承载的最大明文长度
+const MAX_PLAINTEXT_LEN: usize = MAX_FRAME_LEN - TAG_LEN;
+
+/// 完成握手后的加密连接，每帧以 2 字节大端长度为前缀
+pub struct NoiseOutput<T> {
+    io: T,
+    state: snow::TransportState,
+    remote_key: PublicKey,
+
+    read_len: [u8; 2],
+    read_len_pos: usize,
+    read_frame: Vec<u8>,
+    read_frame_pos: usize,
+    decrypted: Vec<u8>,
+    decrypted_pos: usize,
+
+    write_buf: Vec<u8>,
+    encrypted: Vec<u8>,
+    encrypted_pos: usize,
+}
+
+impl<T> NoiseOutput<T> {
+    pub(crate) fn new(io: T, state: snow::TransportState, remote_key: PublicKey) -> Self {
+        Self {
+            io,
+            state,
+            remote_key,
+            read_len: [0; 2],
+            read_len_pos: 0,
+            read_frame: Vec::new(),
+            read_frame_pos: 0,
+            decrypted: Vec::new(),
+            decrypted_pos: 0,
+            write_buf: Vec::new(),
+            encrypted: Vec::new(),
+            encrypted_pos: 0,
+        }
+    }
+
+    /// 对端身份公钥
+    pub fn remote_key(&self) -> &PublicKey {
+        &self.remote_key
+    }
+}
+
+impl<T: AsyncWrite + Unpin> NoiseOutput<T> {
+    /// 写完尚未写出的帧，再加密缓冲的明文并写出
+    fn poll_write_frame(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        loop {
+            while self.encrypted_pos < self.encrypted.len() {
+                let n = ready!(
+                    Pin::new(&mut self.io).poll_write(cx, &self.encrypted[self.encrypted_pos..])
+                )?;
+                if n == 0 {
+                    return Poll::Ready(Err(io::ErrorKind::WriteZero.into()));
+                }
+                self.encrypted_pos += n;
+            }
+            if self.write_buf.is_empty() {
+                self.encrypted.clear();
+                self.encrypted_pos = 0;
+                return Poll::Ready(Ok(()));
+            }
+            self.encrypted.resize(2 + self.write_buf.len() + TAG_LEN, 0);
+            let n = self
+                .state
+                .write_message(&self.write_buf, &mut self.encrypted[2..])
+                .map_err(io::Error::other)?;
+            self.encrypted[..2].copy_from_slice(&(n as u16).to_be_bytes());
+            self.encrypted.truncate(2 + n);
+            self.encrypted_pos = 0;
+            self.write_buf.clear();
+        }
+    }
+}
+
+impl<T: AsyncRead + Unpin> AsyncRead for NoiseOutput<T> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        loop {
+            if this.decrypted_pos < this.decrypted.len() {
+                let n = cmp::min(buf.len(), this.decrypted.len() - this.decrypted_pos);
+                buf[..n]
+                    .copy_from_slice(&this.decrypted[this.decrypted_pos..this.decrypted_pos + n]);
+                this.decrypted_pos += n;
+                return Poll::Ready(Ok(n));
+            }
+
+            while this.read_len_pos < 2 {
+                let n = ready!(
+                    Pin::new(&mut this.io).poll_read(cx, &mut this.read_len[this.read_len_pos..])
+                )?;
+                if n == 0 {
+                    if this.read_len_pos == 0 {
+                        return Poll::Ready(Ok(0));
+                    }
+                    return Poll::Ready(Err(io::ErrorKind::UnexpectedEof.into()));
+                }
+                this.read_len_pos += n;
+                if this.read_len_pos == 2 {
+                    let len = u16::from_be_bytes(this.read_len) as usize;
+                    this.read_frame.resize(len, 0);
+                    this.read_frame_pos = 0;
+                }
+            }
+
+            while this.read_frame_pos < this.read_frame.len() {
+                let n = ready!(
+                    Pin::new(&mut this.io)
+                        .poll_read(cx, &mut this.read_frame[this.read_frame_pos..])
+                )?;
+                if n == 0 {
+                    return Poll::Ready(Err(io::ErrorKind::UnexpectedEof.into()));
+                }
+                this.read_frame_pos += n;
+            }
+            this.read_len_pos = 0;
+
+            if this.read_frame.is_empty() {
+                continue;
+            }
+            this.decrypted.resize(this.read_frame.len(), 0);
+            let n = this
+                .state
+                .read_message(&this.read_frame, &mut this.decrypted)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+            this.decrypted.truncate(n);
+            this.decrypted_pos = 0;
+        }
+    }
+}
+
+impl<T: AsyncWrite + Unpin> AsyncWrite for NoiseOutput<T> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        if this.write_buf.len() == MAX_PLAINTEXT_LEN {
+            ready!(this.poll_write_frame(cx))?;
+        }
+        let n = cmp::min(MAX_PLAINTEXT_LEN - this.write_buf.len(), buf.len());
+        this.write_buf.extend_from_slice(&buf[..n]);
+        Poll::Ready(Ok(n))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        ready!(this.poll_write_frame(cx))?;
+        Pin::new(&mut this.io).poll_flush(cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        ready!(this.poll_write_frame(cx))?;
+        Pin::new(&mut this.io).poll_close(cx)
+    }
+}
@@ -0,0 +1,99 @@
+//! Noise XX 安全升级
+//!
+//! 静态 DH 密钥由节点 Ed25519 密钥派生，握手负载携带身份公钥，
+//! 校验其与对端静态密钥一致后即可确认对端 `PeerId`。
+
+mod handshake;
+mod io;
+
+pub use io::NoiseOutput;
+
+use std::{iter, sync::Arc};
+
+use futures::{AsyncRead, AsyncWrite, FutureExt, future::BoxFuture};
+use volans_core::{
+    PeerId, UpgradeInfo,
+    identity::{KeyPair, Keystore, SignatureError},
+    upgrade::{InboundConnectionUpgrade, OutboundConnectionUpgrade},
+};
+
+const PATTERN: &str = "Noise_XX_25519_ChaChaPoly_SHA256";
+
+#[derive(Clone)]
+pub struct Config {
+    keystore: Arc<dyn Keystore>,
+    prologue: Vec<u8>,
+}
+
+impl Config {
+    pub fn new(keypair: &KeyPair) -> Self {
+        Self::from_keystore(Arc::new(keypair.clone()))
+    }
+
+    /// 握手时从密钥库借用节点密钥
+    pub fn from_keystore(keystore: Arc<dyn Keystore>) -> Self {
+        Self {
+            keystore,
+            prologue: Vec::new(),
+        }
+    }
+
+    /// 握手前置数据，双方不一致时握手失败
+    pub fn with_prologue(mut self, prologue: Vec<u8>) -> Self {
+        self.prologue = prologue;
+        self
+    }
+
+    fn builder(&self) -> snow::Builder<'_> {
+        snow::Builder::new(PATTERN.parse().expect("valid noise pattern")).prologue(&self.prologue)
+    }
+}
+
+impl UpgradeInfo for Config {
+    type Info = &'static str;
+    type InfoIter = iter::Once<Self::Info>;
+
+    fn protocol_info(&self) -> Self::InfoIter {
+        iter::once("/noise")
+    }
+}
+
+impl<C> InboundConnectionUpgrade<C> for Config
+where
+    C: AsyncRead + AsyncWrite + Send + Unpin + 'static,
+{
+    type Output = (PeerId, NoiseOutput<C>);
+    type Error = Error;
+    type Future = BoxFuture<'static, Result<Self::Output, Self::Error>>;
+
+    fn upgrade_inbound(self, socket: C, _: Self::Info) -> Self::Future {
+        async move { handshake::responder(&self, socket).await }.boxed()
+    }
+}
+
+impl<C> OutboundConnectionUpgrade<C> for Config
+where
+    C: AsyncRead + AsyncWrite + Send + Unpin + 'static,
+{
+    type Output = (PeerId, NoiseOutput<C>);
+    type Error = Error;
+    type Future = BoxFuture<'static, Result<Self::Output, Self::Error>>;
+
+    fn upgrade_outbound(self, socket: C, _: Self::Info) -> Self::Future {
+        async move { handshake::initiator(&self, socket).await }.boxed()
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Noise error: {0}")]
+    Noise(#[from] snow::Error),
+    #[error("Invalid handshake payload")]
+    InvalidPayload,
+    #[error("Invalid identity public key: {0}")]
+    InvalidPublicKey(#[from] SignatureError),
+    #[error("Remote static key does not match its identity")]
+    StaticKeyMismatch,
+}
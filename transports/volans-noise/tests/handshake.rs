@@ -0,0 +1,89 @@
+use futures::{AsyncReadExt, AsyncWriteExt, future};
+use tokio_util::compat::{Compat, TokioAsyncReadCompatExt};
+use volans_core::{
+    PeerId,
+    identity::KeyPair,
+    upgrade::{InboundConnectionUpgrade, OutboundConnectionUpgrade},
+};
+use volans_noise::{Config, Error, NoiseOutput};
+
+const PROTOCOL: &str = "/noise";
+
+type Io = Compat<tokio::io::DuplexStream>;
+
+fn peer_id(keypair: &KeyPair) -> PeerId {
+    PeerId::from_public_key(&keypair.verifying_key())
+}
+
+async fn handshake(
+    dialer: Config,
+    listener: Config,
+) -> (
+    Result<(PeerId, NoiseOutput<Io>), Error>,
+    Result<(PeerId, NoiseOutput<Io>), Error>,
+) {
+    let (a, b) = tokio::io::duplex(64 * 1024);
+    future::join(
+        dialer.upgrade_outbound(a.compat(), PROTOCOL),
+        listener.upgrade_inbound(b.compat(), PROTOCOL),
+    )
+    .await
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn xx_handshake_round_trip() {
+    let dialer_key = KeyPair::from_bytes(&[1; 32]);
+    let listener_key = KeyPair::from_bytes(&[2; 32]);
+    let (outbound, inbound) = handshake(Config::new(&dialer_key), Config::new(&listener_key)).await;
+    let (remote, mut outbound) = outbound.unwrap();
+    let (local, mut inbound) = inbound.unwrap();
+    assert_eq!(remote, peer_id(&listener_key));
+    assert_eq!(local, peer_id(&dialer_key));
+    assert_eq!(*inbound.remote_key(), dialer_key.verifying_key());
+
+    outbound.write_all(b"ping").await.unwrap();
+    outbound.flush().await.unwrap();
+    let mut received = [0; 4];
+    inbound.read_exact(&mut received).await.unwrap();
+    assert_eq!(&received, b"ping");
+
+    inbound.write_all(b"pong").await.unwrap();
+    inbound.flush().await.unwrap();
+    outbound.read_exact(&mut received).await.unwrap();
+    assert_eq!(&received, b"pong");
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn split_writes_larger_than_a_frame() {
+    let dialer_key = KeyPair::from_bytes(&[1; 32]);
+    let listener_key = KeyPair::from_bytes(&[2; 32]);
+    let (outbound, inbound) = handshake(Config::new(&dialer_key), Config::new(&listener_key)).await;
+    let (_, mut outbound) = outbound.unwrap();
+    let (_, mut inbound) = inbound.unwrap();
+
+    // 超过单帧 65535 字节上限的写入拆分为多帧
+    let payload: Vec<u8> = (0..3 * 65536 + 7).map(|i| i as u8).collect();
+    let write = async {
+        outbound.write_all(&payload).await.unwrap();
+        outbound.close().await.unwrap();
+    };
+    let read = async {
+        let mut received = Vec::new();
+        inbound.read_to_end(&mut received).await.unwrap();
+        received
+    };
+    let ((), received) = future::join(write, read).await;
+    assert_eq!(received, payload);
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn reject_mismatched_prologue() {
+    let dialer_key = KeyPair::from_bytes(&[1; 32]);
+    let listener_key = KeyPair::from_bytes(&[2; 32]);
+    let (outbound, inbound) = handshake(
+        Config::new(&dialer_key).with_prologue(b"a".to_vec()),
+        Config::new(&listener_key).with_prologue(b"b".to_vec()),
+    )
+    .await;
+    assert!(outbound.is_err() || inbound.is_err());
+}
@@ -7,7 +7,7 @@ use std::{
 use futures::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, future::BoxFuture};
 use volans_core::{
     PeerId, UpgradeInfo,
-    identity::{PublicKey, SignatureError},
+    identity::{Keystore, PublicKey, SignatureError},
     upgrade::{InboundConnectionUpgrade, OutboundConnectionUpgrade},
 };
 
@@ -21,6 +21,10 @@ impl Config {
         Self { local_pubkey }
     }
 
+    pub fn from_keystore(keystore: &impl Keystore) -> Self {
+        Self::new(keystore.public_key())
+    }
+
     async fn handshake<T>(self, mut socket: T) -> Result<(PeerId, IdentifyConnection<T>), Error>
     where
         T: AsyncRead + AsyncWrite + Send + Unpin + 'static,
@@ -30,7 +34,7 @@ impl Config {
         let mut key_buf = [0; 32];
         socket.read_exact(&mut key_buf).await?;
         let remote_key = PublicKey::from_bytes(&key_buf)?;
-        let peer_id = PeerId::from_bytes(remote_key.as_bytes().clone());
+        let peer_id = PeerId::from_public_key(&remote_key);
         Ok((peer_id, IdentifyConnection { socket, remote_key }))
     }
 }
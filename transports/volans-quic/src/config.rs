@@ -0,0 +1,153 @@
+use std::{
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use quinn::{
+    ClientConfig, EndpointConfig, IdleTimeout, MtuDiscoveryConfig, ServerConfig, TransportConfig,
+    VarInt,
+    crypto::rustls::{QuicClientConfig, QuicServerConfig},
+};
+use volans_core::{PeerId, identity::KeyPair};
+
+use crate::{Error, tls};
+
+#[derive(Clone)]
+pub struct Config {
+    keypair: KeyPair,
+    handshake_timeout: Duration,
+    max_idle_timeout: Duration,
+    keep_alive_interval: Duration,
+    max_concurrent_stream_limit: u32,
+    max_stream_data: u32,
+    max_connection_data: u32,
+    mtu_discovery: bool,
+    /// 拨号使用的 Endpoint，按地址族复用
+    pub(crate) dialers: Arc<Mutex<Dialers>>,
+}
+
+#[derive(Default)]
+pub(crate) struct Dialers {
+    pub(crate) v4: Option<quinn::Endpoint>,
+    pub(crate) v6: Option<quinn::Endpoint>,
+}
+
+impl Config {
+    pub fn new(keypair: &KeyPair) -> Self {
+        Self {
+            keypair: keypair.clone(),
+            handshake_timeout: Duration::from_secs(5),
+            max_idle_timeout: Duration::from_secs(10),
+            keep_alive_interval: Duration::from_secs(5),
+            max_concurrent_stream_limit: 256,
+            max_stream_data: 10_000_000,
+            max_connection_data: 15_000_000,
+            mtu_discovery: true,
+            dialers: Arc::new(Mutex::new(Dialers::default())),
+        }
+    }
+
+    /// 握手超时时间
+    pub fn handshake_timeout(mut self, timeout: Duration) -> Self {
+        self.handshake_timeout = timeout;
+        self
+    }
+
+    /// 连接空闲超时时间，超过该时间未收到任何数据则关闭连接
+    pub fn max_idle_timeout(mut self, timeout: Duration) -> Self {
+        self.max_idle_timeout = timeout;
+        self
+    }
+
+    /// 保活包发送间隔，需要小于 `max_idle_timeout`
+    pub fn keep_alive_interval(mut self, interval: Duration) -> Self {
+        self.keep_alive_interval = interval;
+        self
+    }
+
+    /// 对端允许同时打开的最大双向流数量
+    pub fn max_concurrent_stream_limit(mut self, limit: u32) -> Self {
+        self.max_concurrent_stream_limit = limit;
+        self
+    }
+
+    /// 单个流的接收窗口大小
+    pub fn max_stream_data(mut self, value: u32) -> Self {
+        self.max_stream_data = value;
+        self
+    }
+
+    /// 整个连接的接收窗口大小
+    pub fn max_connection_data(mut self, value: u32) -> Self {
+        self.max_connection_data = value;
+        self
+    }
+
+    /// 是否开启 MTU 探测
+    pub fn mtu_discovery(mut self, enabled: bool) -> Self {
+        self.mtu_discovery = enabled;
+        self
+    }
+
+    pub(crate) fn get_handshake_timeout(&self) -> Duration {
+        self.handshake_timeout
+    }
+
+    fn transport_config(&self) -> Arc<TransportConfig> {
+        let mut transport = TransportConfig::default();
+        // 不使用单向流
+        transport.max_concurrent_uni_streams(VarInt::from_u32(0));
+        transport.max_concurrent_bidi_streams(VarInt::from_u32(self.max_concurrent_stream_limit));
+        transport.max_idle_timeout(IdleTimeout::try_from(self.max_idle_timeout).ok());
+        transport.keep_alive_interval(Some(self.keep_alive_interval));
+        transport.stream_receive_window(VarInt::from_u32(self.max_stream_data));
+        transport.receive_window(VarInt::from_u32(self.max_connection_data));
+        transport.allow_spin(false);
+        transport.mtu_discovery_config(self.mtu_discovery.then(MtuDiscoveryConfig::default));
+        Arc::new(transport)
+    }
+
+    pub(crate) fn server_config(&self) -> Result<ServerConfig, Error> {
+        let crypto = tls::make_server_config(&self.keypair)?;
+        let crypto = QuicServerConfig::try_from(crypto)
+            .map_err(|_| Error::Tls(rustls::Error::General("no initial cipher suite".into())))?;
+        let mut server = ServerConfig::with_crypto(Arc::new(crypto));
+        server.transport_config(self.transport_config());
+        server.migration(false);
+        Ok(server)
+    }
+
+    pub(crate) fn client_config(&self, remote: Option<PeerId>) -> Result<ClientConfig, Error> {
+        let crypto = tls::make_client_config(&self.keypair, remote)?;
+        let crypto = QuicClientConfig::try_from(crypto)
+            .map_err(|_| Error::Tls(rustls::Error::General("no initial cipher suite".into())))?;
+        let mut client = ClientConfig::new(Arc::new(crypto));
+        client.transport_config(self.transport_config());
+        Ok(client)
+    }
+
+    pub(crate) fn endpoint_config(&self) -> EndpointConfig {
+        EndpointConfig::default()
+    }
+
+    /// 创建并绑定一个新的 Endpoint
+    pub(crate) fn new_endpoint(
+        &self,
+        socket_addr: SocketAddr,
+        server: bool,
+    ) -> Result<quinn::Endpoint, Error> {
+        let socket = std::net::UdpSocket::bind(socket_addr)?;
+        let server_config = match server {
+            true => Some(self.server_config()?),
+            false => None,
+        };
+        let endpoint = quinn::Endpoint::new(
+            self.endpoint_config(),
+            server_config,
+            socket,
+            Arc::new(quinn::TokioRuntime),
+        )?;
+        Ok(endpoint)
+    }
+}
@@ -0,0 +1,154 @@
+use std::{
+    io,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use futures::{AsyncRead, AsyncWrite, FutureExt, future::BoxFuture, ready};
+use volans_core::StreamMuxer;
+
+use crate::Error;
+
+type StreamFuture = BoxFuture<'static, Result<(quinn::SendStream, quinn::RecvStream), Error>>;
+
+/// QUIC 连接，使用 QUIC 原生的双向流作为子流
+pub struct Connection {
+    connection: quinn::Connection,
+    incoming: Option<StreamFuture>,
+    outgoing: Option<StreamFuture>,
+    closed: BoxFuture<'static, quinn::ConnectionError>,
+    closing: Option<BoxFuture<'static, quinn::ConnectionError>>,
+}
+
+impl Connection {
+    pub(crate) fn new(connection: quinn::Connection) -> Self {
+        let closed = {
+            let connection = connection.clone();
+            async move { connection.closed().await }.boxed()
+        };
+        Self {
+            connection,
+            incoming: None,
+            outgoing: None,
+            closed,
+            closing: None,
+        }
+    }
+
+    /// 底层 quinn 连接
+    pub fn inner(&self) -> &quinn::Connection {
+        &self.connection
+    }
+}
+
+impl StreamMuxer for Connection {
+    type Substream = Stream;
+    type Error = Error;
+
+    fn poll_inbound(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<Self::Substream, Self::Error>> {
+        let this = self.get_mut();
+        let connection = this.connection.clone();
+        let incoming = this.incoming.get_or_insert_with(|| {
+            async move { connection.accept_bi().await.map_err(Error::from) }.boxed()
+        });
+        let result = ready!(incoming.poll_unpin(cx));
+        this.incoming.take();
+        let (send, recv) = result?;
+        Poll::Ready(Ok(Stream::new(send, recv)))
+    }
+
+    fn poll_outbound(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<Self::Substream, Self::Error>> {
+        let this = self.get_mut();
+        let connection = this.connection.clone();
+        let outgoing = this.outgoing.get_or_insert_with(|| {
+            async move { connection.open_bi().await.map_err(Error::from) }.boxed()
+        });
+        let result = ready!(outgoing.poll_unpin(cx));
+        this.outgoing.take();
+        let (send, recv) = result?;
+        Poll::Ready(Ok(Stream::new(send, recv)))
+    }
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        // QUIC 连接由 quinn 的后台任务驱动，这里只需关注连接是否已关闭
+        let error = ready!(self.get_mut().closed.poll_unpin(cx));
+        Poll::Ready(Err(error.into()))
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        let this = self.get_mut();
+        let closing = this.closing.get_or_insert_with(|| {
+            this.connection.close(From::from(0u32), &[]);
+            let connection = this.connection.clone();
+            async move { connection.closed().await }.boxed()
+        });
+        match ready!(closing.poll_unpin(cx)) {
+            quinn::ConnectionError::LocallyClosed => Poll::Ready(Ok(())),
+            error => Poll::Ready(Err(error.into())),
+        }
+    }
+}
+
+/// QUIC 双向流
+pub struct Stream {
+    send: quinn::SendStream,
+    recv: quinn::RecvStream,
+    closed: bool,
+}
+
+impl Stream {
+    fn new(send: quinn::SendStream, recv: quinn::RecvStream) -> Self {
+        Self {
+            send,
+            recv,
+            closed: false,
+        }
+    }
+}
+
+impl AsyncRead for Stream {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        AsyncRead::poll_read(Pin::new(&mut self.get_mut().recv), cx, buf)
+    }
+}
+
+impl AsyncWrite for Stream {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        AsyncWrite::poll_write(Pin::new(&mut self.get_mut().send), cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        AsyncWrite::poll_flush(Pin::new(&mut self.get_mut().send), cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        if this.closed {
+            return Poll::Ready(Ok(()));
+        }
+        ready!(AsyncWrite::poll_close(Pin::new(&mut this.send), cx))?;
+        this.closed = true;
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl Drop for Stream {
+    fn drop(&mut self) {
+        // 通知对端不再读取该流的数据
+        let _ = self.recv.stop(From::from(0u32));
+    }
+}
@@ -0,0 +1,39 @@
+mod config;
+mod connection;
+mod tls;
+mod transport;
+
+pub use config::Config;
+pub use connection::{Connection, Stream};
+pub use transport::{Connecting, ListenStream};
+
+use std::io;
+
+use volans_core::PeerId;
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("I/O error: {0}")]
+    Io(#[from] io::Error),
+    #[error("QUIC connect error: {0}")]
+    Connect(#[from] quinn::ConnectError),
+    #[error("QUIC connection error: {0}")]
+    Connection(#[from] quinn::ConnectionError),
+    #[error("TLS error: {0}")]
+    Tls(#[from] rustls::Error),
+    #[error("Handshake timed out")]
+    HandshakeTimedOut,
+    #[error("Remote peer certificate is missing or invalid")]
+    InvalidCertificate,
+    #[error("Dialed wrong peer, expected {expected}, obtained {obtained}")]
+    WrongPeerId { expected: PeerId, obtained: PeerId },
+}
+
+impl From<Error> for io::Error {
+    fn from(err: Error) -> Self {
+        match err {
+            Error::Io(err) => err,
+            err => io::Error::other(err),
+        }
+    }
+}
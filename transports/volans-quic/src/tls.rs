@@ -0,0 +1,27 @@
+//! QUIC 握手使用的 TLS 配置
+//!
+//! 证书生成与对端校验复用 `volans-tls`，这里只附加 QUIC 要求的 ALPN。
+
+use volans_core::{PeerId, identity::KeyPair};
+use volans_tls::certificate;
+
+use crate::Error;
+
+pub(crate) use volans_tls::certificate::{SERVER_NAME, peer_id_from_certificate};
+
+const ALPN: &[u8] = b"volans";
+
+pub(crate) fn make_server_config(keypair: &KeyPair) -> Result<rustls::ServerConfig, Error> {
+    let mut config = certificate::make_server_config(keypair)?;
+    config.alpn_protocols = vec![ALPN.to_vec()];
+    Ok(config)
+}
+
+pub(crate) fn make_client_config(
+    keypair: &KeyPair,
+    remote_peer_id: Option<PeerId>,
+) -> Result<rustls::ClientConfig, Error> {
+    let mut config = certificate::make_client_config(keypair, remote_peer_id)?;
+    config.alpn_protocols = vec![ALPN.to_vec()];
+    Ok(config)
+}
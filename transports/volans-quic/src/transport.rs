@@ -0,0 +1,313 @@
+use std::{
+    collections::VecDeque,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+    pin::Pin,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use futures::{FutureExt, StreamExt, future::BoxFuture, ready};
+use futures_timer::Delay;
+use if_watch::IfEvent;
+use volans_core::{
+    Listener, ListenerEvent, Multiaddr, PeerId, SocketOptions, Transport, TransportError,
+    multiaddr::Protocol,
+};
+
+use crate::{Config, Connection, Error, tls};
+
+impl Transport for Config {
+    type Output = (PeerId, Connection);
+    type Error = Error;
+    type Dial = Connecting;
+    type Incoming = Connecting;
+    type Listener = ListenStream;
+
+    fn dial(&self, addr: Multiaddr) -> Result<Self::Dial, TransportError<Self::Error>> {
+        let (socket_addr, remote_peer_id) = match multiaddr_to_socket_addr(&addr) {
+            Some((socket, peer_id)) if socket.port() != 0 && !socket.ip().is_unspecified() => {
+                (socket, peer_id)
+            }
+            _ => return Err(TransportError::not_supported(addr, "quic")),
+        };
+
+        let endpoint = self.dialer_endpoint(socket_addr)?;
+        let client_config = self.client_config(remote_peer_id)?;
+        let connecting = endpoint
+            .connect_with(client_config, socket_addr, tls::SERVER_NAME)
+            .map_err(Error::from)?;
+
+        tracing::debug!("Dialing QUIC connection to {}", addr);
+        Ok(Connecting::new(
+            connecting,
+            remote_peer_id,
+            self.get_handshake_timeout(),
+        ))
+    }
+
+    fn listen(
+        &self,
+        addr: Multiaddr,
+        _: &SocketOptions,
+    ) -> Result<Self::Listener, TransportError<Self::Error>> {
+        tracing::debug!("Listening for QUIC connections on {}", addr);
+        let socket_addr = match multiaddr_to_socket_addr(&addr) {
+            Some((socket, None)) => socket,
+            _ => return Err(TransportError::not_supported(addr, "quic")),
+        };
+        let endpoint = self.new_endpoint(socket_addr, true)?;
+        // 端口为 0 时需要获取实际绑定的端口
+        let socket_addr = endpoint.local_addr().map_err(Error::from)?;
+
+        let mut pending_events = VecDeque::new();
+        let if_watcher = if socket_addr.ip().is_unspecified() {
+            Some(if_watch::tokio::IfWatcher::new().map_err(Error::from)?)
+        } else {
+            pending_events.push_back(ListenerEvent::NewAddress(socket_addr_to_multiaddr(
+                socket_addr,
+            )));
+            None
+        };
+
+        Ok(ListenStream {
+            endpoint: Some(endpoint.clone()),
+            accept: Some(accept(endpoint)),
+            listen_addr: socket_addr,
+            handshake_timeout: self.get_handshake_timeout(),
+            pending_events,
+            if_watcher,
+        })
+    }
+}
+
+impl Config {
+    fn dialer_endpoint(&self, remote: SocketAddr) -> Result<quinn::Endpoint, Error> {
+        let mut dialers = self.dialers.lock().expect("dialers lock poisoned");
+        let (slot, bind_addr) = match remote {
+            SocketAddr::V4(_) => (
+                &mut dialers.v4,
+                SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), 0),
+            ),
+            SocketAddr::V6(_) => (
+                &mut dialers.v6,
+                SocketAddr::new(IpAddr::V6(Ipv6Addr::UNSPECIFIED), 0),
+            ),
+        };
+        match slot {
+            Some(endpoint) => Ok(endpoint.clone()),
+            None => {
+                let endpoint = self.new_endpoint(bind_addr, false)?;
+                *slot = Some(endpoint.clone());
+                Ok(endpoint)
+            }
+        }
+    }
+}
+
+fn accept(endpoint: quinn::Endpoint) -> BoxFuture<'static, Option<quinn::Incoming>> {
+    async move { endpoint.accept().await }.boxed()
+}
+
+/// 解析 `/ip4/.../udp/.../quic[/peer/...]` 格式的地址
+fn multiaddr_to_socket_addr(addr: &Multiaddr) -> Option<(SocketAddr, Option<PeerId>)> {
+    let mut iter = addr.iter();
+    let ip = match iter.next()? {
+        Protocol::Ip4(ip) => IpAddr::V4(ip),
+        Protocol::Ip6(ip) => IpAddr::V6(ip),
+        _ => return None,
+    };
+    let port = match iter.next()? {
+        Protocol::Udp(port) => port,
+        _ => return None,
+    };
+    if iter.next()? != Protocol::Quic {
+        return None;
+    }
+    let peer_id = match iter.next() {
+        Some(Protocol::Peer(peer_id)) => Some(peer_id),
+        Some(_) => return None,
+        None => None,
+    };
+    if iter.next().is_some() {
+        return None;
+    }
+    Some((SocketAddr::new(ip, port), peer_id))
+}
+
+fn socket_addr_to_multiaddr(socket_addr: SocketAddr) -> Multiaddr {
+    ip_to_multiaddr(socket_addr.ip(), socket_addr.port())
+}
+
+fn ip_to_multiaddr(ip: IpAddr, port: u16) -> Multiaddr {
+    Multiaddr::empty()
+        .with(ip.into())
+        .with(Protocol::Udp(port))
+        .with(Protocol::Quic)
+}
+
+/// 正在进行握手的 QUIC 连接
+pub struct Connecting {
+    connecting: BoxFuture<'static, Result<quinn::Connection, Error>>,
+    remote_peer_id: Option<PeerId>,
+    timeout: Delay,
+}
+
+impl Connecting {
+    fn new(
+        connecting: quinn::Connecting,
+        remote_peer_id: Option<PeerId>,
+        timeout: Duration,
+    ) -> Self {
+        Self {
+            connecting: connecting.map(|r| r.map_err(Error::from)).boxed(),
+            remote_peer_id,
+            timeout: Delay::new(timeout),
+        }
+    }
+
+    fn from_incoming(incoming: quinn::Incoming, timeout: Duration) -> Self {
+        Self {
+            connecting: async move { Ok(incoming.accept()?.await?) }.boxed(),
+            remote_peer_id: None,
+            timeout: Delay::new(timeout),
+        }
+    }
+}
+
+impl Future for Connecting {
+    type Output = Result<(PeerId, Connection), Error>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        if self.timeout.poll_unpin(cx).is_ready() {
+            return Poll::Ready(Err(Error::HandshakeTimedOut));
+        }
+        let connection = ready!(self.connecting.poll_unpin(cx))?;
+        let peer_id = remote_peer_id(&connection).ok_or(Error::InvalidCertificate)?;
+        if let Some(expected) = self.remote_peer_id
+            && expected != peer_id
+        {
+            connection.close(From::from(0u32), b"wrong peer id");
+            return Poll::Ready(Err(Error::WrongPeerId {
+                expected,
+                obtained: peer_id,
+            }));
+        }
+        Poll::Ready(Ok((peer_id, Connection::new(connection))))
+    }
+}
+
+fn remote_peer_id(connection: &quinn::Connection) -> Option<PeerId> {
+    let identity = connection.peer_identity()?;
+    let certificates = identity
+        .downcast::<Vec<rustls::pki_types::CertificateDer<'static>>>()
+        .ok()?;
+    certificates.first().and_then(tls::peer_id_from_certificate)
+}
+
+pub struct ListenStream {
+    endpoint: Option<quinn::Endpoint>,
+    accept: Option<BoxFuture<'static, Option<quinn::Incoming>>>,
+    listen_addr: SocketAddr,
+    handshake_timeout: Duration,
+    pending_events: VecDeque<ListenerEvent<Connecting, Error>>,
+    if_watcher: Option<if_watch::tokio::IfWatcher>,
+}
+
+impl Listener for ListenStream {
+    type Output = (PeerId, Connection);
+    type Error = Error;
+    type Upgrade = Connecting;
+
+    fn poll_close(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        let this = self.get_mut();
+        this.accept.take();
+        match this.endpoint.take() {
+            Some(endpoint) => {
+                endpoint.close(From::from(0u32), b"listener closed");
+                Poll::Ready(Ok(()))
+            }
+            None => Poll::Ready(Err(Error::Io(std::io::Error::other("Listener closed")))),
+        }
+    }
+
+    fn poll_event(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<ListenerEvent<Self::Upgrade, Self::Error>> {
+        let this = self.get_mut();
+        if let Some(event) = this.pending_events.pop_front() {
+            return Poll::Ready(event);
+        }
+
+        if let Some(if_watcher) = this.if_watcher.as_mut() {
+            while let Poll::Ready(Some(if_event)) = if_watcher.poll_next_unpin(cx) {
+                match if_event {
+                    Ok(IfEvent::Up(inet)) => {
+                        let ip = inet.addr();
+                        if this.listen_addr.is_ipv4() == ip.is_ipv4() {
+                            let addr = ip_to_multiaddr(ip, this.listen_addr.port());
+                            return Poll::Ready(ListenerEvent::NewAddress(addr));
+                        }
+                    }
+                    Ok(IfEvent::Down(inet)) => {
+                        let ip = inet.addr();
+                        if this.listen_addr.is_ipv4() == ip.is_ipv4() {
+                            let addr = ip_to_multiaddr(ip, this.listen_addr.port());
+                            return Poll::Ready(ListenerEvent::AddressExpired(addr));
+                        }
+                    }
+                    Err(err) => return Poll::Ready(ListenerEvent::Error(err.into())),
+                }
+            }
+        }
+
+        let Some(accept) = this.accept.as_mut() else {
+            return Poll::Ready(ListenerEvent::Closed(Ok(())));
+        };
+        match ready!(accept.poll_unpin(cx)) {
+            Some(incoming) => {
+                let endpoint = this
+                    .endpoint
+                    .clone()
+                    .expect("endpoint exists while accepting");
+                this.accept = Some(self::accept(endpoint));
+
+                let remote_addr = socket_addr_to_multiaddr(incoming.remote_address());
+                let local_addr = match incoming.local_ip() {
+                    Some(ip) => ip_to_multiaddr(ip, this.listen_addr.port()),
+                    None => socket_addr_to_multiaddr(this.listen_addr),
+                };
+                Poll::Ready(ListenerEvent::Incoming {
+                    local_addr,
+                    remote_addr,
+                    upgrade: Connecting::from_incoming(incoming, this.handshake_timeout),
+                })
+            }
+            None => {
+                // Endpoint 已关闭
+                this.accept.take();
+                this.endpoint.take();
+                Poll::Ready(ListenerEvent::Closed(Ok(())))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_quic_multiaddr() {
+        let addr: Multiaddr = "/ip4/127.0.0.1/udp/4001/quic".parse().unwrap();
+        let (socket, peer_id) = multiaddr_to_socket_addr(&addr).unwrap();
+        assert_eq!(socket, "127.0.0.1:4001".parse().unwrap());
+        assert!(peer_id.is_none());
+
+        let addr: Multiaddr = "/ip4/127.0.0.1/tcp/4001".parse().unwrap();
+        assert!(multiaddr_to_socket_addr(&addr).is_none());
+
+        let addr: Multiaddr = "/ip4/127.0.0.1/udp/4001".parse().unwrap();
+        assert!(multiaddr_to_socket_addr(&addr).is_none());
+    }
+}
@@ -0,0 +1,66 @@
+use std::pin::Pin;
+
+use futures::future::{self, poll_fn};
+use volans_core::{
+    Listener, ListenerEvent, Multiaddr, PeerId, SocketOptions, Transport, identity::KeyPair,
+    multiaddr::Protocol,
+};
+use volans_quic::{Config, Error, ListenStream};
+
+fn peer_id(keypair: &KeyPair) -> PeerId {
+    PeerId::from_public_key(&keypair.verifying_key())
+}
+
+async fn next_event(listener: &mut ListenStream) -> ListenerEvent<volans_quic::Connecting, Error> {
+    poll_fn(|cx| Pin::new(&mut *listener).poll_event(cx)).await
+}
+
+/// 监听本地回环地址，返回监听器与实际地址
+async fn listen(config: &Config) -> (ListenStream, Multiaddr) {
+    let mut listener = config
+        .listen(
+            "/ip4/127.0.0.1/udp/0/quic".parse().unwrap(),
+            &SocketOptions::default(),
+        )
+        .unwrap();
+    match next_event(&mut listener).await {
+        ListenerEvent::NewAddress(addr) => (listener, addr),
+        _ => panic!("expected listen address"),
+    }
+}
+
+/// 驱动监听器接受一个连接并完成握手
+async fn accept(listener: &mut ListenStream) -> Result<PeerId, Error> {
+    loop {
+        if let ListenerEvent::Incoming { upgrade, .. } = next_event(listener).await {
+            return upgrade.await.map(|(peer_id, _)| peer_id);
+        }
+    }
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn dial_expected_peer() {
+    let listener_key = KeyPair::from_bytes(&[1; 32]);
+    let dialer_key = KeyPair::from_bytes(&[2; 32]);
+    let (mut listener, addr) = listen(&Config::new(&listener_key)).await;
+
+    let addr = addr.with(Protocol::Peer(peer_id(&listener_key)));
+    let dial = Config::new(&dialer_key).dial(addr).unwrap();
+    let (outbound, inbound) = future::join(dial, accept(&mut listener)).await;
+    assert_eq!(outbound.unwrap().0, peer_id(&listener_key));
+    assert_eq!(inbound.unwrap(), peer_id(&dialer_key));
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn reject_unexpected_peer() {
+    let listener_key = KeyPair::from_bytes(&[1; 32]);
+    let dialer_key = KeyPair::from_bytes(&[2; 32]);
+    let expected = KeyPair::from_bytes(&[3; 32]);
+    let (mut listener, addr) = listen(&Config::new(&listener_key)).await;
+
+    let addr = addr.with(Protocol::Peer(peer_id(&expected)));
+    let dial = Config::new(&dialer_key).dial(addr).unwrap();
+    let (outbound, _) = future::join(dial, accept(&mut listener)).await;
+    // 证书校验在握手中拒绝对端
+    assert!(matches!(outbound, Err(Error::Connection(_))));
+}
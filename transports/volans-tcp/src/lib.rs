@@ -2,38 +2,106 @@ mod listener;
 mod stream;
 
 use std::{
-    collections::VecDeque,
+    collections::{HashSet, VecDeque},
     io, mem,
     net::{IpAddr, SocketAddr},
     pin::Pin,
-    task::{Context, Poll},
+    sync::{Arc, RwLock},
+    task::{Context, Poll, ready},
+    time::Duration,
 };
 
 use futures::{
-    FutureExt, StreamExt, TryFutureExt,
+    FutureExt, StreamExt,
     future::{self, BoxFuture, Ready},
 };
 use if_watch::IfEvent;
 use volans_core::{
-    Listener, ListenerEvent, Multiaddr, Transport, TransportError, multiaddr::Protocol,
+    Listener, ListenerEvent, Multiaddr, SocketOptions, Transport, TransportError,
+    multiaddr::Protocol,
 };
 
 pub use stream::TcpStream;
-use tokio::net::TcpListener;
+use tokio::net::{TcpListener, TcpSocket};
 
 #[derive(Clone, Debug)]
 pub struct Config {
     ttl: Option<u32>,
+    ttl_v6: Option<u32>,
     nodelay: bool,
+    keepalive: Option<Duration>,
+    bind_address: Option<IpAddr>,
+    only_v6: bool,
     backlog: u32,
+    port_reuse: PortReuse,
+    rebind_attempts: u32,
+    rebind_interval: Duration,
+}
+
+type ListenAddrs = Arc<RwLock<HashSet<(IpAddr, u16)>>>;
+
+/// 当前监听中的地址，开启端口复用时拨号会绑定到这些端口
+#[derive(Clone, Debug, Default)]
+struct PortReuse {
+    listen_addrs: Option<ListenAddrs>,
+}
+
+impl PortReuse {
+    fn enabled() -> Self {
+        Self {
+            listen_addrs: Some(Arc::default()),
+        }
+    }
+
+    fn is_enabled(&self) -> bool {
+        self.listen_addrs.is_some()
+    }
+
+    fn register(&self, ip: IpAddr, port: u16) {
+        if let Some(listen_addrs) = &self.listen_addrs {
+            listen_addrs
+                .write()
+                .expect("lock poisoned")
+                .insert((ip, port));
+        }
+    }
+
+    fn unregister(&self, ip: IpAddr, port: u16) {
+        if let Some(listen_addrs) = &self.listen_addrs {
+            listen_addrs
+                .write()
+                .expect("lock poisoned")
+                .remove(&(ip, port));
+        }
+    }
+
+    /// 为远端地址选择本地绑定地址，要求协议族一致，且除通配地址外回环属性一致
+    fn local_dial_addr(&self, remote_ip: &IpAddr) -> Option<SocketAddr> {
+        let listen_addrs = self.listen_addrs.as_ref()?.read().expect("lock poisoned");
+        for (ip, port) in listen_addrs.iter() {
+            if ip.is_ipv4() == remote_ip.is_ipv4()
+                && (ip.is_unspecified() || ip.is_loopback() == remote_ip.is_loopback())
+            {
+                return Some(SocketAddr::new(*ip, *port));
+            }
+        }
+        None
+    }
 }
 
 impl Config {
     pub fn new() -> Self {
         Self {
             ttl: None,
+            ttl_v6: None,
             nodelay: true,
+            keepalive: None,
+            bind_address: None,
+            only_v6: true,
             backlog: 1024,
+            port_reuse: PortReuse::default(),
+            rebind_attempts: 3,
+            rebind_interval: Duration::from_secs(1),
         }
     }
 
@@ -42,6 +110,30 @@ impl Config {
         self
     }
 
+    /// IPv6 套接字的跳数限制，未设置时使用 [`Config::ttl`]
+    pub fn ttl_v6(mut self, value: u32) -> Self {
+        self.ttl_v6 = Some(value);
+        self
+    }
+
+    /// 开启 TCP keepalive，连接空闲指定时长后开始探测
+    pub fn keepalive(mut self, idle: Duration) -> Self {
+        self.keepalive = Some(idle);
+        self
+    }
+
+    /// 拨号时绑定的本地地址，只用于协议族相同的远端，开启端口复用时优先绑定监听端口
+    pub fn bind_address(mut self, ip: IpAddr) -> Self {
+        self.bind_address = Some(ip);
+        self
+    }
+
+    /// IPv6 套接字是否只接受 IPv6 连接，默认开启
+    pub fn only_v6(mut self, value: bool) -> Self {
+        self.only_v6 = value;
+        self
+    }
+
     pub fn nodelay(mut self, value: bool) -> Self {
         self.nodelay = value;
         self
@@ -52,29 +144,91 @@ impl Config {
         self
     }
 
-    fn create_socket(&self, socket_addr: SocketAddr) -> io::Result<socket2::Socket> {
+    /// 开启端口复用，拨号时绑定到监听端口，便于 NAT 打洞
+    pub fn port_reuse(mut self, value: bool) -> Self {
+        self.port_reuse = match value {
+            true => PortReuse::enabled(),
+            false => PortReuse::default(),
+        };
+        self
+    }
+
+    /// 监听套接字失效后重新绑定的次数与间隔，次数为 0 时不重新绑定
+    pub fn listener_rebind(mut self, attempts: u32, interval: Duration) -> Self {
+        self.rebind_attempts = attempts;
+        self.rebind_interval = interval;
+        self
+    }
+
+    fn bind_listener(
+        &self,
+        socket_addr: SocketAddr,
+        opts: &SocketOptions,
+    ) -> io::Result<TcpListener> {
+        let socket = self.create_socket(socket_addr, opts)?;
+        socket.bind(&socket_addr.into())?;
+        socket.listen(opts.backlog().unwrap_or(self.backlog) as _)?;
+        socket.set_nonblocking(true)?;
+        TcpListener::from_std(socket.into())
+    }
+
+    /// `opts` 中设置的选项覆盖传输层配置
+    fn create_socket(
+        &self,
+        socket_addr: SocketAddr,
+        opts: &SocketOptions,
+    ) -> io::Result<socket2::Socket> {
         let socket = socket2::Socket::new(
             socket2::Domain::for_address(socket_addr),
             socket2::Type::STREAM,
             Some(socket2::Protocol::TCP),
         )?;
         if socket_addr.is_ipv6() {
-            socket.set_only_v6(true)?;
+            socket.set_only_v6(opts.only_v6().unwrap_or(self.only_v6))?;
         }
 
-        if let Some(ttl) = self.ttl {
-            match socket_addr.is_ipv6() {
-                true => {}
-                false => socket.set_ttl_v4(ttl)?,
+        match socket_addr.is_ipv6() {
+            true => {
+                if let Some(hops) = opts.ttl().or(self.ttl_v6).or(self.ttl) {
+                    socket.set_unicast_hops_v6(hops)?;
+                }
             }
+            false => {
+                if let Some(ttl) = opts.ttl().or(self.ttl) {
+                    socket.set_ttl_v4(ttl)?;
+                }
+            }
+        }
+        if let Some(idle) = self.keepalive {
+            socket.set_tcp_keepalive(&socket2::TcpKeepalive::new().with_time(idle))?;
         }
-        socket.set_tcp_nodelay(self.nodelay)?;
+        if let Some(device) = opts.bind_device() {
+            bind_device(&socket, device)?;
+        }
+        socket.set_tcp_nodelay(opts.nodelay().unwrap_or(self.nodelay))?;
         socket.set_reuse_address(true)?;
+        #[cfg(unix)]
+        if self.port_reuse.is_enabled() {
+            socket.set_reuse_port(true)?;
+        }
         socket.set_nonblocking(true)?;
         Ok(socket)
     }
 }
 
+#[cfg(any(target_os = "android", target_os = "fuchsia", target_os = "linux"))]
+fn bind_device(socket: &socket2::Socket, device: &str) -> io::Result<()> {
+    socket.bind_device(Some(device.as_bytes()))
+}
+
+#[cfg(not(any(target_os = "android", target_os = "fuchsia", target_os = "linux")))]
+fn bind_device(_: &socket2::Socket, _: &str) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "Binding to a device is not supported on this platform",
+    ))
+}
+
 impl Default for Config {
     fn default() -> Self {
         Self::new()
@@ -91,43 +245,76 @@ impl Transport for Config {
     fn dial(&self, addr: Multiaddr) -> Result<Self::Dial, TransportError<Self::Error>> {
         let socket_addr = match multiaddr_to_socket_addr(addr.clone()) {
             Ok(socket) if socket.port() != 0 && !socket.ip().is_unspecified() => socket,
-            _ => return Err(TransportError::NotSupported(addr)),
+            _ => return Err(TransportError::not_supported(addr, "tcp")),
         };
 
-        let fut = tokio::net::TcpStream::connect(socket_addr)
-            .map_ok(TcpStream::from)
-            .boxed();
+        let socket = self.create_socket(socket_addr, &SocketOptions::default())?;
+        if let Some(local_addr) = self.port_reuse.local_dial_addr(&socket_addr.ip()) {
+            tracing::trace!("Binding dial socket to listen address {}", local_addr);
+            socket.bind(&local_addr.into())?;
+        } else if let Some(ip) = self.bind_address
+            && ip.is_ipv4() == socket_addr.is_ipv4()
+        {
+            tracing::trace!("Binding dial socket to {}", ip);
+            socket.bind(&SocketAddr::new(ip, 0).into())?;
+        }
+        let socket = TcpSocket::from_std_stream(socket.into());
+
+        let fut = async move {
+            let stream = socket.connect(socket_addr).await?;
+            tracing::debug!(
+                "Dialed {} from local address {}",
+                socket_addr,
+                stream.local_addr()?
+            );
+            Ok(TcpStream::from(stream))
+        }
+        .boxed();
         Ok(fut)
     }
 
-    fn listen(&self, addr: Multiaddr) -> Result<Self::Listener, TransportError<Self::Error>> {
+    fn listen(
+        &self,
+        addr: Multiaddr,
+        opts: &SocketOptions,
+    ) -> Result<Self::Listener, TransportError<Self::Error>> {
         tracing::debug!("Listening for TCP connections on {}", addr);
         let socket_addr = match multiaddr_to_socket_addr(addr.clone()) {
             Ok(socket) => socket,
-            _ => return Err(TransportError::NotSupported(addr)),
+            _ => return Err(TransportError::not_supported(addr, "tcp")),
         };
-        let socket = self.create_socket(socket_addr)?;
-        socket.bind(&socket_addr.into())?;
-        socket.listen(self.backlog as _)?;
-        socket.set_nonblocking(true)?;
-        let listener = TcpListener::from_std(socket.into())?;
+        let listener = self.bind_listener(socket_addr, opts)?;
+        // 端口为 0 时使用系统实际分配的端口
+        let listen_addr = listener.local_addr()?;
+
+        self.port_reuse
+            .register(listen_addr.ip(), listen_addr.port());
 
-        if socket_addr.ip().is_unspecified() {
+        if listen_addr.ip().is_unspecified() {
             return Ok(ListenStream {
-                listen_addr: socket_addr,
+                listen_addr,
                 pending_events: VecDeque::new(),
                 state: State::Listening { listener },
                 if_watcher: Some(if_watch::tokio::IfWatcher::new()?),
+                port_reuse: self.port_reuse.clone(),
+                config: self.clone(),
+                opts: opts.clone(),
             });
         }
         let mut pending_events = VecDeque::new();
-        pending_events.push_back(ListenerEvent::NewAddress(addr.clone()));
+        pending_events.push_back(ListenerEvent::NewAddress(ip_to_multiaddr(
+            listen_addr.ip(),
+            listen_addr.port(),
+        )));
 
         Ok(ListenStream {
-            listen_addr: socket_addr,
+            listen_addr,
             pending_events,
             state: State::Listening { listener },
             if_watcher: None,
+            port_reuse: self.port_reuse.clone(),
+            config: self.clone(),
+            opts: opts.clone(),
         })
     }
 }
@@ -160,17 +347,119 @@ pub struct ListenStream {
     pending_events: VecDeque<ListenerEvent<Ready<Result<TcpStream, io::Error>>, io::Error>>,
     state: State,
     if_watcher: Option<if_watch::tokio::IfWatcher>,
+    port_reuse: PortReuse,
+    /// 重新绑定时使用的配置
+    config: Config,
+    opts: SocketOptions,
 }
 
 enum State {
-    Listening { listener: TcpListener },
+    Listening {
+        listener: TcpListener,
+    },
+    /// 监听套接字失效，等待重新绑定
+    Rebinding {
+        attempts: u32,
+        delay: Pin<Box<tokio::time::Sleep>>,
+    },
     Closed,
 }
 
 impl ListenStream {
-    // fn poll_if_watch(self: Pin<&mut Self>, cx: &mut Context<'_>) {
-    //     if
-    // }
+    /// 当前的监听地址，监听通配地址时为同一协议族的所有接口地址
+    fn current_addrs(&self) -> Vec<Multiaddr> {
+        let port = self.listen_addr.port();
+        match &self.if_watcher {
+            Some(if_watcher) => if_watcher
+                .iter()
+                .map(|inet| inet.addr())
+                .filter(|ip| ip.is_ipv4() == self.listen_addr.is_ipv4())
+                .map(|ip| ip_to_multiaddr(ip, port))
+                .collect(),
+            None => vec![ip_to_multiaddr(self.listen_addr.ip(), port)],
+        }
+    }
+
+    /// 监听套接字失效，地址全部过期，按配置等待重新绑定
+    fn on_fatal_error(&mut self, error: io::Error) {
+        tracing::debug!("Listener socket on {} failed: {}", self.listen_addr, error);
+        self.port_reuse
+            .unregister(self.listen_addr.ip(), self.listen_addr.port());
+        self.pending_events.push_back(ListenerEvent::Error(error));
+        for addr in self.current_addrs() {
+            self.pending_events
+                .push_back(ListenerEvent::AddressExpired(addr));
+        }
+        self.state = State::Rebinding {
+            attempts: 0,
+            delay: Box::pin(tokio::time::sleep(self.config.rebind_interval)),
+        };
+    }
+
+    fn poll_rebind(&mut self, cx: &mut Context<'_>) -> Poll<()> {
+        let State::Rebinding { attempts, delay } = &mut self.state else {
+            return Poll::Ready(());
+        };
+        ready!(delay.as_mut().poll(cx));
+        match self.config.bind_listener(self.listen_addr, &self.opts) {
+            Ok(listener) => {
+                tracing::debug!("Listener socket on {} rebound", self.listen_addr);
+                self.port_reuse
+                    .register(self.listen_addr.ip(), self.listen_addr.port());
+                self.state = State::Listening { listener };
+                for addr in self.current_addrs() {
+                    self.pending_events.push_back(ListenerEvent::Rebound(addr));
+                }
+            }
+            Err(error) if *attempts + 1 >= self.config.rebind_attempts => {
+                tracing::debug!(
+                    "Failed to rebind listener on {}: {}",
+                    self.listen_addr,
+                    error
+                );
+                self.state = State::Closed;
+                self.pending_events
+                    .push_back(ListenerEvent::Closed(Err(error)));
+            }
+            Err(error) => {
+                tracing::debug!(
+                    "Failed to rebind listener on {}: {}",
+                    self.listen_addr,
+                    error
+                );
+                *attempts += 1;
+                delay
+                    .as_mut()
+                    .reset(tokio::time::Instant::now() + self.config.rebind_interval);
+            }
+        }
+        Poll::Ready(())
+    }
+}
+
+/// 接受连接的错误是否意味着监听套接字已失效
+///
+/// 连接级别的错误与文件描述符耗尽（ENFILE、EMFILE）可以恢复。
+fn is_fatal(error: &io::Error) -> bool {
+    !matches!(
+        error.kind(),
+        io::ErrorKind::ConnectionAborted
+            | io::ErrorKind::ConnectionReset
+            | io::ErrorKind::ConnectionRefused
+            | io::ErrorKind::Interrupted
+            | io::ErrorKind::WouldBlock
+            | io::ErrorKind::TimedOut
+            | io::ErrorKind::OutOfMemory
+    ) && !matches!(error.raw_os_error(), Some(23 | 24))
+}
+
+impl Drop for ListenStream {
+    fn drop(&mut self) {
+        if let State::Listening { .. } = self.state {
+            self.port_reuse
+                .unregister(self.listen_addr.ip(), self.listen_addr.port());
+        }
+    }
 }
 
 impl Listener for ListenStream {
@@ -183,12 +472,13 @@ impl Listener for ListenStream {
         match mem::replace(&mut this.state, State::Closed) {
             State::Listening { listener } => {
                 this.state = State::Closed;
+                this.port_reuse
+                    .unregister(this.listen_addr.ip(), this.listen_addr.port());
                 drop(listener);
                 Poll::Ready(Ok(()))
             }
-            State::Closed => {
-                Poll::Ready(Err(io::Error::new(io::ErrorKind::Other, "Listener closed")))
-            }
+            State::Rebinding { .. } => Poll::Ready(Ok(())),
+            State::Closed => Poll::Ready(Err(io::Error::other("Listener closed"))),
         }
     }
 
@@ -197,35 +487,47 @@ impl Listener for ListenStream {
         cx: &mut Context<'_>,
     ) -> Poll<ListenerEvent<Self::Upgrade, Self::Error>> {
         let this = self.get_mut();
-        if let Some(event) = this.pending_events.pop_front() {
-            return Poll::Ready(event);
-        }
+        loop {
+            if let Some(event) = this.pending_events.pop_front() {
+                return Poll::Ready(event);
+            }
 
-        if let Some(if_watcher) = this.if_watcher.as_mut() {
-            while let Poll::Ready(Some(if_event)) = if_watcher.poll_next_unpin(cx) {
-                match if_event {
-                    Ok(IfEvent::Up(inet)) => {
-                        let ip = inet.addr();
-                        if this.listen_addr.is_ipv4() == ip.is_ipv4() {
-                            let addr = ip_to_multiaddr(ip, this.listen_addr.port());
-                            return Poll::Ready(ListenerEvent::NewAddress(addr));
-                        }
+            if let Some(if_watcher) = this.if_watcher.as_mut() {
+                while let Poll::Ready(Some(if_event)) = if_watcher.poll_next_unpin(cx) {
+                    // 重新绑定期间的地址变化在恢复后一并上报
+                    if !matches!(this.state, State::Listening { .. }) {
+                        continue;
                     }
-                    Ok(IfEvent::Down(inet)) => {
-                        let ip = inet.addr();
-                        if this.listen_addr.is_ipv4() == ip.is_ipv4() {
-                            let addr = ip_to_multiaddr(ip, this.listen_addr.port());
-                            return Poll::Ready(ListenerEvent::AddressExpired(addr));
+                    match if_event {
+                        Ok(IfEvent::Up(inet)) => {
+                            let ip = inet.addr();
+                            if this.listen_addr.is_ipv4() == ip.is_ipv4() {
+                                let addr = ip_to_multiaddr(ip, this.listen_addr.port());
+                                return Poll::Ready(ListenerEvent::NewAddress(addr));
+                            }
                         }
+                        Ok(IfEvent::Down(inet)) => {
+                            let ip = inet.addr();
+                            if this.listen_addr.is_ipv4() == ip.is_ipv4() {
+                                let addr = ip_to_multiaddr(ip, this.listen_addr.port());
+                                return Poll::Ready(ListenerEvent::AddressExpired(addr));
+                            }
+                        }
+                        Err(err) => return Poll::Ready(ListenerEvent::Error(err)),
                     }
-                    Err(err) => return Poll::Ready(ListenerEvent::Error(err)),
                 }
             }
-        }
 
-        match &mut this.state {
-            State::Listening { listener } => match Pin::new(listener).poll_accept(cx) {
-                Poll::Ready(Ok((stream, remote_addr))) => {
+            let listener = match &mut this.state {
+                State::Listening { listener } => listener,
+                State::Rebinding { .. } => {
+                    ready!(this.poll_rebind(cx));
+                    continue;
+                }
+                State::Closed => return Poll::Ready(ListenerEvent::Closed(Ok(()))),
+            };
+            return match ready!(Pin::new(listener).poll_accept(cx)) {
+                Ok((stream, remote_addr)) => {
                     let local_addr = match stream.local_addr() {
                         Ok(addr) => addr,
                         Err(e) => return Poll::Ready(ListenerEvent::Error(e)),
@@ -241,13 +543,15 @@ impl Listener for ListenStream {
                     };
                     Poll::Ready(event)
                 }
-                Poll::Ready(Err(e)) => {
+                Err(e) if is_fatal(&e) && this.config.rebind_attempts > 0 => {
+                    this.on_fatal_error(e);
+                    continue;
+                }
+                Err(e) => {
                     let event = ListenerEvent::Error(e);
                     Poll::Ready(event)
                 }
-                Poll::Pending => return Poll::Pending,
-            },
-            State::Closed => Poll::Ready(ListenerEvent::Closed(Ok(()))),
+            };
         }
     }
 }
@@ -255,3 +559,149 @@ impl Listener for ListenStream {
 fn ip_to_multiaddr(ip: IpAddr, port: u16) -> Multiaddr {
     Multiaddr::empty().with(ip.into()).with(Protocol::Tcp(port))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn next_event(
+        listener: &mut ListenStream,
+    ) -> ListenerEvent<Ready<Result<TcpStream, io::Error>>, io::Error> {
+        future::poll_fn(|cx| Pin::new(&mut *listener).poll_event(cx)).await
+    }
+
+    #[tokio::test]
+    async fn dial_from_listen_port() {
+        let config = Config::new().port_reuse(true);
+        let local = config
+            .listen(
+                "/ip4/127.0.0.1/tcp/0".parse().unwrap(),
+                &SocketOptions::default(),
+            )
+            .unwrap();
+        let mut remote = Config::new()
+            .listen(
+                "/ip4/127.0.0.1/tcp/0".parse().unwrap(),
+                &SocketOptions::default(),
+            )
+            .unwrap();
+        let remote_addr = match next_event(&mut remote).await {
+            ListenerEvent::NewAddress(addr) => addr,
+            _ => panic!("expected listen address"),
+        };
+        let local_port = local.listen_addr.port();
+
+        let (dialed, incoming) =
+            future::join(config.dial(remote_addr).unwrap(), next_event(&mut remote)).await;
+        assert_eq!(dialed.unwrap().local_addr().unwrap().port(), local_port);
+        match incoming {
+            ListenerEvent::Incoming { remote_addr, .. } => {
+                assert_eq!(
+                    remote_addr,
+                    ip_to_multiaddr(IpAddr::from([127, 0, 0, 1]), local_port)
+                )
+            }
+            _ => panic!("expected incoming connection"),
+        }
+    }
+
+    #[tokio::test]
+    async fn listener_socket_options() {
+        let config = Config::new().ttl(64);
+        let opts = SocketOptions::new().with_ttl(32);
+        let listener = config
+            .listen("/ip4/127.0.0.1/tcp/0".parse().unwrap(), &opts)
+            .unwrap();
+        match &listener.state {
+            State::Listening { listener } => assert_eq!(listener.ttl().unwrap(), 32),
+            State::Rebinding { .. } | State::Closed => panic!("expected listening socket"),
+        }
+    }
+
+    #[tokio::test]
+    async fn dial_from_bind_address() {
+        let mut remote = Config::new()
+            .listen(
+                "/ip4/127.0.0.1/tcp/0".parse().unwrap(),
+                &SocketOptions::default(),
+            )
+            .unwrap();
+        let remote_addr = match next_event(&mut remote).await {
+            ListenerEvent::NewAddress(addr) => addr,
+            _ => panic!("expected listen address"),
+        };
+        let bind_ip = IpAddr::from([127, 0, 0, 2]);
+        let config = Config::new()
+            .bind_address(bind_ip)
+            .keepalive(Duration::from_secs(30));
+
+        let dialed = config.dial(remote_addr).unwrap().await.unwrap();
+        assert_eq!(dialed.local_addr().unwrap().ip(), bind_ip);
+    }
+
+    #[tokio::test]
+    async fn rebind_after_listener_failure() {
+        let config = Config::new().listener_rebind(3, Duration::from_millis(10));
+        let mut listener = config
+            .listen(
+                "/ip4/127.0.0.1/tcp/0".parse().unwrap(),
+                &SocketOptions::default(),
+            )
+            .unwrap();
+        let addr = match next_event(&mut listener).await {
+            ListenerEvent::NewAddress(addr) => addr,
+            _ => panic!("expected listen address"),
+        };
+
+        listener.on_fatal_error(io::Error::from(io::ErrorKind::InvalidInput));
+        assert!(matches!(
+            next_event(&mut listener).await,
+            ListenerEvent::Error(_)
+        ));
+        match next_event(&mut listener).await {
+            ListenerEvent::AddressExpired(expired) => assert_eq!(expired, addr),
+            _ => panic!("expected expired address"),
+        }
+        match next_event(&mut listener).await {
+            ListenerEvent::Rebound(rebound) => assert_eq!(rebound, addr),
+            _ => panic!("expected rebound address"),
+        }
+
+        let (dialed, incoming) =
+            future::join(Config::new().dial(addr).unwrap(), next_event(&mut listener)).await;
+        dialed.unwrap();
+        assert!(matches!(incoming, ListenerEvent::Incoming { .. }));
+    }
+
+    #[tokio::test]
+    async fn close_after_rebind_attempts_exhausted() {
+        let config = Config::new().listener_rebind(2, Duration::from_millis(10));
+        let mut listener = config
+            .listen(
+                "/ip4/127.0.0.1/tcp/0".parse().unwrap(),
+                &SocketOptions::default(),
+            )
+            .unwrap();
+        let addr = match next_event(&mut listener).await {
+            ListenerEvent::NewAddress(addr) => addr,
+            _ => panic!("expected listen address"),
+        };
+
+        listener.on_fatal_error(io::Error::from(io::ErrorKind::InvalidInput));
+        // 失效的套接字已释放，其他监听器占用端口后重新绑定失败
+        let _occupied = std::net::TcpListener::bind(listener.listen_addr).unwrap();
+        assert!(matches!(
+            next_event(&mut listener).await,
+            ListenerEvent::Error(_)
+        ));
+        match next_event(&mut listener).await {
+            ListenerEvent::AddressExpired(expired) => assert_eq!(expired, addr),
+            _ => panic!("expected expired address"),
+        }
+        assert!(matches!(
+            next_event(&mut listener).await,
+            ListenerEvent::Closed(Err(_))
+        ));
+        assert!(matches!(listener.state, State::Closed));
+    }
+}
@@ -1,6 +1,7 @@
 use futures::{AsyncRead, AsyncWrite};
 use std::{
     io,
+    net::SocketAddr,
     pin::Pin,
     task::{Context, Poll},
 };
@@ -14,6 +15,17 @@ impl From<tokio::net::TcpStream> for TcpStream {
     }
 }
 
+impl TcpStream {
+    /// 本地地址，开启端口复用时为拨号绑定的监听端口
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.0.local_addr()
+    }
+
+    pub fn peer_addr(&self) -> io::Result<SocketAddr> {
+        self.0.peer_addr()
+    }
+}
+
 impl AsyncRead for TcpStream {
     fn poll_read(
         mut self: Pin<&mut Self>,
@@ -0,0 +1,245 @@
+//! 与节点身份绑定的证书
+//!
+//! 每个节点使用自身的 Ed25519 密钥生成自签名证书，证书公钥即为节点身份。
+//! 双方均不校验证书链，只校验握手签名与证书中的公钥一致，并从证书中提取 `PeerId`。
+
+use std::sync::Arc;
+
+use rustls::{
+    CertificateError, DigitallySignedStruct, DistinguishedName, SignatureScheme,
+    client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier},
+    crypto::{CryptoProvider, WebPkiSupportedAlgorithms},
+    pki_types::{CertificateDer, PrivateKeyDer, PrivatePkcs8KeyDer, ServerName, UnixTime},
+    server::danger::{ClientCertVerified, ClientCertVerifier},
+};
+use volans_core::{PeerId, identity::KeyPair};
+use x509_parser::{certificate::X509Certificate, oid_registry::OID_SIG_ED25519, prelude::FromDer};
+
+/// 握手中使用的 SNI，证书校验不依赖该名称
+pub const SERVER_NAME: &str = "volans";
+
+/// Ed25519 PKCS#8 v1 私钥前缀，后接 32 字节私钥
+const ED25519_PKCS8_PREFIX: [u8; 16] = [
+    0x30, 0x2e, 0x02, 0x01, 0x00, 0x30, 0x05, 0x06, 0x03, 0x2b, 0x65, 0x70, 0x04, 0x22, 0x04, 0x20,
+];
+
+fn provider() -> Arc<CryptoProvider> {
+    Arc::new(rustls::crypto::ring::default_provider())
+}
+
+/// 使用节点密钥生成自签名证书
+fn generate_certificate(
+    keypair: &KeyPair,
+) -> Result<(CertificateDer<'static>, PrivateKeyDer<'static>), rustls::Error> {
+    let mut pkcs8 = Vec::with_capacity(ED25519_PKCS8_PREFIX.len() + 32);
+    pkcs8.extend_from_slice(&ED25519_PKCS8_PREFIX);
+    pkcs8.extend_from_slice(keypair.as_bytes());
+    let pkcs8 = PrivatePkcs8KeyDer::from(pkcs8);
+
+    let certificate_keypair =
+        rcgen::KeyPair::from_pkcs8_der_and_sign_algo(&pkcs8, &rcgen::PKCS_ED25519)
+            .map_err(|e| rustls::Error::General(e.to_string()))?;
+    let certificate = rcgen::CertificateParams::new(vec![SERVER_NAME.to_string()])
+        .and_then(|params| params.self_signed(&certificate_keypair))
+        .map_err(|e| rustls::Error::General(e.to_string()))?;
+
+    Ok((certificate.der().clone(), PrivateKeyDer::Pkcs8(pkcs8)))
+}
+
+/// 从证书的 SubjectPublicKeyInfo 中提取对端 `PeerId`，只接受 Ed25519 公钥
+pub fn peer_id_from_certificate(certificate: &CertificateDer<'_>) -> Option<PeerId> {
+    let (rest, certificate) = X509Certificate::from_der(certificate.as_ref()).ok()?;
+    if !rest.is_empty() {
+        return None;
+    }
+    let spki = certificate.public_key();
+    if spki.algorithm.algorithm != OID_SIG_ED25519 {
+        return None;
+    }
+    PeerId::try_from_slice(&spki.subject_public_key.data).ok()
+}
+
+/// 出示节点证书并要求客户端证书的服务端配置
+pub fn make_server_config(keypair: &KeyPair) -> Result<rustls::ServerConfig, rustls::Error> {
+    let (certificate, private_key) = generate_certificate(keypair)?;
+    let provider = provider();
+    let verifier = Arc::new(PeerCertificateVerifier::new(None, provider.clone()));
+    rustls::ServerConfig::builder_with_provider(provider)
+        .with_protocol_versions(&[&rustls::version::TLS13])?
+        .with_client_cert_verifier(verifier)
+        .with_single_cert(vec![certificate], private_key)
+}
+
+/// 出示节点证书的客户端配置，`remote_peer_id` 为 `Some` 时只接受该对端
+pub fn make_client_config(
+    keypair: &KeyPair,
+    remote_peer_id: Option<PeerId>,
+) -> Result<rustls::ClientConfig, rustls::Error> {
+    let (certificate, private_key) = generate_certificate(keypair)?;
+    let provider = provider();
+    let verifier = Arc::new(PeerCertificateVerifier::new(
+        remote_peer_id,
+        provider.clone(),
+    ));
+    rustls::ClientConfig::builder_with_provider(provider)
+        .with_protocol_versions(&[&rustls::version::TLS13])?
+        .dangerous()
+        .with_custom_certificate_verifier(verifier)
+        .with_client_auth_cert(vec![certificate], private_key)
+}
+
+#[derive(Debug)]
+struct PeerCertificateVerifier {
+    /// 期望的对端 `PeerId`，为 `None` 时接受任意对端
+    remote_peer_id: Option<PeerId>,
+    algorithms: WebPkiSupportedAlgorithms,
+}
+
+impl PeerCertificateVerifier {
+    fn new(remote_peer_id: Option<PeerId>, provider: Arc<CryptoProvider>) -> Self {
+        Self {
+            remote_peer_id,
+            algorithms: provider.signature_verification_algorithms,
+        }
+    }
+
+    fn verify_certificate(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        intermediates: &[CertificateDer<'_>],
+    ) -> Result<(), rustls::Error> {
+        if !intermediates.is_empty() {
+            return Err(rustls::Error::General(
+                "certificate chain must contain exactly one certificate".into(),
+            ));
+        }
+        let peer_id = peer_id_from_certificate(end_entity).ok_or(
+            rustls::Error::InvalidCertificate(CertificateError::BadEncoding),
+        )?;
+        match self.remote_peer_id {
+            Some(expected) if expected != peer_id => Err(rustls::Error::InvalidCertificate(
+                CertificateError::ApplicationVerificationFailure,
+            )),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl ServerCertVerifier for PeerCertificateVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        intermediates: &[CertificateDer<'_>],
+        _server_name: &ServerName<'_>,
+        _ocsp_response: &[u8],
+        _now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        self.verify_certificate(end_entity, intermediates)?;
+        Ok(ServerCertVerified::assertion())
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        _message: &[u8],
+        _cert: &CertificateDer<'_>,
+        _dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        Err(rustls::Error::PeerIncompatible(
+            rustls::PeerIncompatible::Tls12NotOffered,
+        ))
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        rustls::crypto::verify_tls13_signature(message, cert, dss, &self.algorithms)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        vec![SignatureScheme::ED25519]
+    }
+}
+
+impl ClientCertVerifier for PeerCertificateVerifier {
+    fn offer_client_auth(&self) -> bool {
+        true
+    }
+
+    fn root_hint_subjects(&self) -> &[DistinguishedName] {
+        &[]
+    }
+
+    fn verify_client_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        intermediates: &[CertificateDer<'_>],
+        _now: UnixTime,
+    ) -> Result<ClientCertVerified, rustls::Error> {
+        self.verify_certificate(end_entity, intermediates)?;
+        Ok(ClientCertVerified::assertion())
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        _message: &[u8],
+        _cert: &CertificateDer<'_>,
+        _dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        Err(rustls::Error::PeerIncompatible(
+            rustls::PeerIncompatible::Tls12NotOffered,
+        ))
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        rustls::crypto::verify_tls13_signature(message, cert, dss, &self.algorithms)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        vec![SignatureScheme::ED25519]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn extract_peer_id_from_public_key() {
+        let keypair = KeyPair::from_bytes(&[7; 32]);
+        let (certificate, _) = generate_certificate(&keypair).unwrap();
+        assert_eq!(
+            peer_id_from_certificate(&certificate),
+            Some(PeerId::from_public_key(&keypair.verifying_key()))
+        );
+    }
+
+    #[test]
+    fn ignore_public_key_bytes_outside_spki() {
+        // 序列号位于公钥之前，其中嵌入其他节点的 SubjectPublicKeyInfo
+        let victim = KeyPair::from_bytes(&[1; 32]);
+        let mut spoofed = vec![
+            0x30, 0x2a, 0x30, 0x05, 0x06, 0x03, 0x2b, 0x65, 0x70, 0x03, 0x21, 0x00,
+        ];
+        spoofed.extend_from_slice(victim.verifying_key().as_bytes());
+
+        let attacker = rcgen::KeyPair::generate_for(&rcgen::PKCS_ED25519).unwrap();
+        let mut params = rcgen::CertificateParams::new(vec![SERVER_NAME.to_string()]).unwrap();
+        params.serial_number = Some(rcgen::SerialNumber::from_slice(&spoofed));
+        let certificate = params.self_signed(&attacker).unwrap();
+
+        let peer_id = peer_id_from_certificate(certificate.der()).unwrap();
+        assert_ne!(peer_id, PeerId::from_public_key(&victim.verifying_key()));
+        assert_eq!(
+            peer_id,
+            PeerId::try_from_slice(attacker.public_key_raw()).unwrap()
+        );
+    }
+}
@@ -0,0 +1,123 @@
+//! TLS 1.3 安全升级
+//!
+//! 证书由节点 Ed25519 密钥自签名生成，握手完成后从对端证书中提取 `PeerId`。
+
+pub mod certificate;
+
+use std::{io, iter, sync::Arc};
+
+use futures::{AsyncRead, AsyncWrite, FutureExt, future::BoxFuture};
+use futures_rustls::{TlsAcceptor, TlsConnector};
+use rustls::pki_types::ServerName;
+use volans_core::{
+    PeerId, UpgradeInfo,
+    identity::{KeyPair, Keystore},
+    upgrade::{InboundConnectionUpgrade, OutboundConnectionUpgrade},
+};
+
+pub use futures_rustls::TlsStream;
+
+#[derive(Clone)]
+pub struct Config {
+    server: Arc<rustls::ServerConfig>,
+    client: Arc<rustls::ClientConfig>,
+}
+
+impl Config {
+    pub fn new(keypair: &KeyPair) -> Result<Self, Error> {
+        Ok(Self {
+            server: Arc::new(certificate::make_server_config(keypair)?),
+            client: Arc::new(certificate::make_client_config(keypair, None)?),
+        })
+    }
+
+    pub fn from_keystore(keystore: &impl Keystore) -> Result<Self, Error> {
+        Self::new(keystore.keypair())
+    }
+
+    /// 出站连接只接受指定的对端
+    pub fn with_remote_peer_id(
+        mut self,
+        keypair: &KeyPair,
+        peer_id: PeerId,
+    ) -> Result<Self, Error> {
+        self.client = Arc::new(certificate::make_client_config(keypair, Some(peer_id))?);
+        Ok(self)
+    }
+}
+
+impl UpgradeInfo for Config {
+    type Info = &'static str;
+    type InfoIter = iter::Once<Self::Info>;
+
+    fn protocol_info(&self) -> Self::InfoIter {
+        iter::once("/tls/1.0.0")
+    }
+}
+
+impl<C> InboundConnectionUpgrade<C> for Config
+where
+    C: AsyncRead + AsyncWrite + Send + Unpin + 'static,
+{
+    type Output = (PeerId, TlsStream<C>);
+    type Error = Error;
+    type Future = BoxFuture<'static, Result<Self::Output, Self::Error>>;
+
+    fn upgrade_inbound(self, socket: C, _: Self::Info) -> Self::Future {
+        async move {
+            let stream = TlsAcceptor::from(self.server)
+                .accept(socket)
+                .await
+                .map_err(Error::Handshake)?;
+            let peer_id = remote_peer_id(stream.get_ref().1.peer_certificates())?;
+            tracing::trace!("TLS inbound handshake completed with {}", peer_id);
+            Ok((peer_id, stream.into()))
+        }
+        .boxed()
+    }
+}
+
+impl<C> OutboundConnectionUpgrade<C> for Config
+where
+    C: AsyncRead + AsyncWrite + Send + Unpin + 'static,
+{
+    type Output = (PeerId, TlsStream<C>);
+    type Error = Error;
+    type Future = BoxFuture<'static, Result<Self::Output, Self::Error>>;
+
+    fn upgrade_outbound(self, socket: C, _: Self::Info) -> Self::Future {
+        async move {
+            let name = ServerName::try_from(certificate::SERVER_NAME)
+                .expect("SERVER_NAME is a valid dns name");
+            let stream = TlsConnector::from(self.client)
+                .connect(name, socket)
+                .await
+                .map_err(Error::Handshake)?;
+            let peer_id = remote_peer_id(stream.get_ref().1.peer_certificates())?;
+            tracing::trace!("TLS outbound handshake completed with {}", peer_id);
+            Ok((peer_id, stream.into()))
+        }
+        .boxed()
+    }
+}
+
+fn remote_peer_id(
+    certificates: Option<&[rustls::pki_types::CertificateDer<'_>]>,
+) -> Result<PeerId, Error> {
+    match certificates {
+        Some([certificate]) => {
+            certificate::peer_id_from_certificate(certificate).ok_or(Error::InvalidCertificate)
+        }
+        _ => Err(Error::InvalidCertificate),
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("TLS handshake error: {0}")]
+    Handshake(io::Error),
+    #[error("TLS error: {0}")]
+    Tls(#[from] rustls::Error),
+    #[error("Remote peer certificate is missing or invalid")]
+    InvalidCertificate,
+}
@@ -0,0 +1,71 @@
+use futures::{AsyncReadExt, AsyncWriteExt, future};
+use tokio_util::compat::{Compat, TokioAsyncReadCompatExt};
+use volans_core::{
+    PeerId,
+    identity::KeyPair,
+    upgrade::{InboundConnectionUpgrade, OutboundConnectionUpgrade},
+};
+use volans_tls::Config;
+
+const PROTOCOL: &str = "/tls/1.0.0";
+
+fn pipe() -> (
+    Compat<tokio::io::DuplexStream>,
+    Compat<tokio::io::DuplexStream>,
+) {
+    let (a, b) = tokio::io::duplex(64 * 1024);
+    (a.compat(), b.compat())
+}
+
+fn peer_id(keypair: &KeyPair) -> PeerId {
+    PeerId::from_public_key(&keypair.verifying_key())
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn handshake_and_exchange_data() {
+    let dialer_key = KeyPair::from_bytes(&[1; 32]);
+    let listener_key = KeyPair::from_bytes(&[2; 32]);
+    let dialer = Config::new(&dialer_key)
+        .unwrap()
+        .with_remote_peer_id(&dialer_key, peer_id(&listener_key))
+        .unwrap();
+    let listener = Config::new(&listener_key).unwrap();
+
+    let (a, b) = pipe();
+    let (outbound, inbound) = future::join(
+        dialer.upgrade_outbound(a, PROTOCOL),
+        listener.upgrade_inbound(b, PROTOCOL),
+    )
+    .await;
+    let (remote, mut outbound) = outbound.unwrap();
+    let (local, mut inbound) = inbound.unwrap();
+    assert_eq!(remote, peer_id(&listener_key));
+    assert_eq!(local, peer_id(&dialer_key));
+
+    outbound.write_all(b"hello").await.unwrap();
+    outbound.flush().await.unwrap();
+    let mut received = [0; 5];
+    inbound.read_exact(&mut received).await.unwrap();
+    assert_eq!(&received, b"hello");
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn reject_unexpected_remote_peer() {
+    let dialer_key = KeyPair::from_bytes(&[1; 32]);
+    let listener_key = KeyPair::from_bytes(&[2; 32]);
+    let expected = KeyPair::from_bytes(&[3; 32]);
+    let dialer = Config::new(&dialer_key)
+        .unwrap()
+        .with_remote_peer_id(&dialer_key, peer_id(&expected))
+        .unwrap();
+    let listener = Config::new(&listener_key).unwrap();
+
+    let (a, b) = pipe();
+    let (outbound, inbound) = future::join(
+        dialer.upgrade_outbound(a, PROTOCOL),
+        listener.upgrade_inbound(b, PROTOCOL),
+    )
+    .await;
+    assert!(matches!(outbound, Err(volans_tls::Error::Handshake(_))));
+    assert!(inbound.is_err());
+}
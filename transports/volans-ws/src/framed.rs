@@ -74,6 +74,6 @@ where
 fn into_io_error(error: tungstenite::Error) -> io::Error {
     match error {
         tungstenite::Error::Io(e) => e,
-        e => io::Error::new(io::ErrorKind::Other, e),
+        e => io::Error::other(e),
     }
 }
@@ -1,30 +1,70 @@
+mod router;
 mod stream;
+pub mod tls;
 
 use std::{
+    fmt, io,
     pin::Pin,
-    task::{Context, Poll},
+    sync::Arc,
+    task::{Context, Poll, ready},
 };
 
 use async_tungstenite::{
-    accept_async_with_config, client_async_with_config,
-    tungstenite::{self, http::Uri, protocol::WebSocketConfig},
+    accept_hdr_async_with_config, client_async_with_config,
+    tungstenite::{
+        self,
+        client::IntoClientRequest,
+        handshake::server::{Callback, ErrorResponse, Request, Response},
+        http::{HeaderName, HeaderValue, StatusCode, Uri},
+        protocol::WebSocketConfig,
+    },
 };
-use futures::{FutureExt, TryFutureExt};
+use futures::{
+    FutureExt, StreamExt, TryFutureExt,
+    channel::{mpsc, oneshot},
+    future::{self, Either},
+    stream::FuturesUnordered,
+};
+use futures_rustls::{TlsAcceptor, TlsStream};
+use router::{RoutedEvent, Router};
+use rustls::pki_types::ServerName;
 use stream::RwStreamSink;
 use volans_core::{
-    Listener, ListenerEvent, Multiaddr, Transport, TransportError, multiaddr::Protocol,
+    Listener, ListenerEvent, Multiaddr, SocketOptions, Transport, TransportError,
+    multiaddr::Protocol,
 };
 use volans_tcp::TcpStream;
 
 use crate::framed::BytesWebSocketStream;
-pub use tungstenite::Error;
+pub use tungstenite::{Error, http};
 
 mod framed;
 
+/// 底层连接，`/tls/ws` 时为 TLS 流
+pub type Connection = Either<TcpStream, TlsStream<TcpStream>>;
+
+type FilterFn = dyn Fn(&Request) -> Result<(), StatusCode> + Send + Sync;
+
+/// 监听端检查握手请求，返回错误状态码时拒绝升级
+#[derive(Clone)]
+struct RequestFilter(Arc<FilterFn>);
+
+impl fmt::Debug for RequestFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("RequestFilter").finish_non_exhaustive()
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct Config {
     pub websocket: WebSocketConfig,
     pub tcp: volans_tcp::Config,
+    pub tls: tls::Config,
+    /// 拨号时附加的 HTTP 请求头
+    headers: Vec<(HeaderName, HeaderValue)>,
+    request_filter: Option<RequestFilter>,
+    /// 同一端口上不同路径的监听器
+    router: Router,
 }
 
 impl Default for Config {
@@ -38,9 +78,34 @@ impl Config {
         Self {
             websocket: WebSocketConfig::default(),
             tcp: volans_tcp::Config::default(),
+            tls: tls::Config::default(),
+            headers: Vec::new(),
+            request_filter: None,
+            router: Router::default(),
         }
     }
 
+    /// 拨号握手时附加的 HTTP 请求头，如认证令牌
+    pub fn with_header(mut self, name: HeaderName, value: HeaderValue) -> Self {
+        self.headers.push((name, value));
+        self
+    }
+
+    /// 监听端在升级前检查握手请求的路径与请求头，返回 `Err` 时以该状态码拒绝
+    pub fn with_request_filter<F>(mut self, filter: F) -> Self
+    where
+        F: Fn(&Request) -> Result<(), StatusCode> + Send + Sync + 'static,
+    {
+        self.request_filter = Some(RequestFilter(Arc::new(filter)));
+        self
+    }
+
+    /// 监听与拨号 `/tls/ws` 使用的 TLS 配置
+    pub fn tls(mut self, tls: tls::Config) -> Self {
+        self.tls = tls;
+        self
+    }
+
     /// Set [`Self::read_buffer_size`].
     pub fn read_buffer_size(mut self, read_buffer_size: usize) -> Self {
         self.websocket.read_buffer_size = read_buffer_size;
@@ -78,31 +143,51 @@ impl Config {
     }
 }
 
-type ListenerUpgrade = Pin<
-    Box<dyn Future<Output = Result<RwStreamSink<BytesWebSocketStream<TcpStream>>, Error>> + Send>,
->;
+pub(crate) type Output = RwStreamSink<BytesWebSocketStream<Connection>>;
+
+type ListenerUpgrade = Pin<Box<dyn Future<Output = Result<Output, Error>> + Send>>;
+
+/// 共用端口时由持有者完成握手，输出匹配的路径与连接
+type RoutedHandshake =
+    Pin<Box<dyn Future<Output = Result<(Option<String>, RoutedEvent), Error>> + Send>>;
 
 impl Transport for Config {
-    type Output = RwStreamSink<BytesWebSocketStream<TcpStream>>;
+    type Output = Output;
     type Error = tungstenite::Error;
     type Dial = Pin<Box<dyn Future<Output = Result<Self::Output, Self::Error>> + Send>>;
     type Incoming = ListenerUpgrade;
     type Listener = ListenStream;
 
     fn dial(&self, addr: Multiaddr) -> Result<Self::Dial, TransportError<Self::Error>> {
-        let config = self.websocket.clone();
+        let config = self.websocket;
         tracing::debug!("Connecting to WebSocket at {}", addr);
-        let ws_addr =
-            parse_ws_dial_addr(&addr).map_err(|_| TransportError::NotSupported(addr.clone()))?;
+        let ws_addr = parse_ws_dial_addr(&addr)
+            .map_err(|_| TransportError::not_supported(addr.clone(), "ws"))?;
 
-        let request = Uri::builder()
+        let mut request = Uri::builder()
             .scheme(if ws_addr.use_tls { "wss" } else { "ws" })
             .authority(ws_addr.host_port.as_str())
             .path_and_query(ws_addr.path.as_str())
             .build()
+            .map_err(|_| TransportError::NotSupported(addr.clone()))?
+            .into_client_request()
             .map_err(|_| TransportError::NotSupported(addr.clone()))?;
-
-        tracing::debug!("Connecting to WebSocket at {}", request);
+        request.headers_mut().extend(self.headers.iter().cloned());
+
+        tracing::debug!("Connecting to WebSocket at {}", request.uri());
+
+        let tls = if ws_addr.use_tls {
+            let connector = self
+                .tls
+                .client
+                .clone()
+                .ok_or_else(|| TransportError::NotSupported(addr.clone()))?;
+            let server_name = ServerName::try_from(ws_addr.server_name)
+                .map_err(|_| TransportError::NotSupported(addr.clone()))?;
+            Some((connector, server_name))
+        } else {
+            None
+        };
 
         let dialer = self
             .tcp
@@ -111,7 +196,15 @@ impl Transport for Config {
 
         Ok(dialer
             .map_err(tungstenite::Error::from)
-            .and_then(move |stream| client_async_with_config(request, stream, Some(config)))
+            .and_then(move |stream| async move {
+                let stream = match tls {
+                    Some((connector, server_name)) => {
+                        Either::Right(connector.connect(server_name, stream).await?.into())
+                    }
+                    None => Either::Left(stream),
+                };
+                client_async_with_config(request, stream, Some(config)).await
+            })
             .map_ok(|(s, response)| {
                 tracing::debug!("WebSocket handshake response: {:?}", response);
                 BytesWebSocketStream::new(s)
@@ -120,31 +213,110 @@ impl Transport for Config {
             .boxed())
     }
 
-    fn listen(&self, addr: Multiaddr) -> Result<Self::Listener, TransportError<Self::Error>> {
-        let (inner_addr, path) = parse_ws_listen_addr(&addr)
-            .ok_or_else(|| TransportError::NotSupported(addr.clone()))?;
+    /// 同一 TCP 地址上可以监听多个 `/x-with-path` 地址，连接按握手请求的路径分发
+    fn listen(
+        &self,
+        addr: Multiaddr,
+        opts: &SocketOptions,
+    ) -> Result<Self::Listener, TransportError<Self::Error>> {
+        let (inner_addr, path, use_tls) = parse_ws_listen_addr(&addr)
+            .ok_or_else(|| TransportError::not_supported(addr.clone(), "ws"))?;
+        let key = router::shareable(&inner_addr).then(|| inner_addr.clone());
+        if let Some(key) = &key
+            && let Some(joined) = self.router.join(key, use_tls, &path)
+        {
+            let events = joined.map_err(|()| {
+                TransportError::Other(io::Error::from(io::ErrorKind::AddrInUse).into())
+            })?;
+            tracing::debug!("Sharing WebSocket listener port for {}", addr);
+            return Ok(ListenStream {
+                path,
+                use_tls,
+                inner: ListenInner::Routed(RoutedListener {
+                    router: self.router.clone(),
+                    key: key.clone(),
+                    events,
+                    closed: false,
+                }),
+            });
+        }
+        let tls = if use_tls {
+            let acceptor = self.tls.server.clone();
+            Some(acceptor.ok_or_else(|| TransportError::NotSupported(addr.clone()))?)
+        } else {
+            None
+        };
         let listener = self
             .tcp
-            .listen(inner_addr)
+            .listen(inner_addr, opts)
             .map_err(|e| e.map(tungstenite::Error::from))?;
+        if let Some(key) = &key {
+            self.router.add_port(key.clone(), use_tls, path.clone());
+        }
         tracing::debug!("Listening for WebSocket connections on {}", addr);
         Ok(ListenStream {
-            path: path.map(|r| r.to_string()),
-            config: self.websocket.clone(),
-            inner: listener,
+            path,
+            use_tls,
+            inner: ListenInner::Owner(Box::new(OwnerListener {
+                config: self.websocket,
+                tls,
+                request_filter: self.request_filter.clone(),
+                port: key.map(|key| (self.router.clone(), key)),
+                handshakes: FuturesUnordered::new(),
+                inner: listener,
+            })),
         })
     }
 }
 
-#[pin_project::pin_project]
 pub struct ListenStream {
     path: Option<String>,
+    use_tls: bool,
+    inner: ListenInner,
+}
+
+enum ListenInner {
+    /// 持有 TCP 监听器
+    Owner(Box<OwnerListener>),
+    /// 共用其他监听器的端口，由持有者按路径转发连接
+    Routed(RoutedListener),
+}
+
+struct OwnerListener {
     config: WebSocketConfig,
-    #[pin]
+    tls: Option<TlsAcceptor>,
+    request_filter: Option<RequestFilter>,
+    /// 端口可以共用时在路由表中登记的 TCP 地址
+    port: Option<(Router, Multiaddr)>,
+    /// 共用端口时等待路径的握手
+    handshakes: FuturesUnordered<RoutedHandshake>,
     inner: volans_tcp::ListenStream,
 }
 
-fn append_on_addr(mut addr: Multiaddr, path: Option<&str>) -> Multiaddr {
+struct RoutedListener {
+    router: Router,
+    key: Multiaddr,
+    events: mpsc::UnboundedReceiver<RoutedEvent>,
+    closed: bool,
+}
+
+impl Drop for ListenStream {
+    fn drop(&mut self) {
+        match &self.inner {
+            ListenInner::Owner(owner) => {
+                if let Some((router, key)) = &owner.port {
+                    router.remove_port(key);
+                }
+            }
+            ListenInner::Routed(routed) => routed.router.leave(&routed.key, &self.path),
+        }
+    }
+}
+
+fn append_on_addr(mut addr: Multiaddr, use_tls: bool, path: Option<&str>) -> Multiaddr {
+    if use_tls {
+        addr.push(Protocol::Tls);
+    }
     addr.push(Protocol::Ws);
     if let Some(path) = path {
         addr.push(Protocol::Path(path.into()));
@@ -152,73 +324,258 @@ fn append_on_addr(mut addr: Multiaddr, path: Option<&str>) -> Multiaddr {
     addr
 }
 
+impl RoutedEvent {
+    fn into_listener_event(
+        self,
+        use_tls: bool,
+        path: Option<&str>,
+    ) -> ListenerEvent<ListenerUpgrade, Error> {
+        match self {
+            RoutedEvent::NewAddress(addr) => {
+                ListenerEvent::NewAddress(append_on_addr(addr, use_tls, path))
+            }
+            RoutedEvent::AddressExpired(addr) => {
+                ListenerEvent::AddressExpired(append_on_addr(addr, use_tls, path))
+            }
+            RoutedEvent::Incoming {
+                local_addr,
+                remote_addr,
+                stream,
+            } => ListenerEvent::Incoming {
+                local_addr: append_on_addr(local_addr, use_tls, path),
+                remote_addr: append_on_addr(remote_addr, use_tls, path),
+                upgrade: future::ready(Ok(*stream)).boxed(),
+            },
+        }
+    }
+}
+
+/// 以状态码拒绝握手
+fn reject(status: StatusCode) -> ErrorResponse {
+    tracing::debug!("Rejecting WebSocket handshake with {}", status);
+    let mut response = ErrorResponse::new(None);
+    *response.status_mut() = status;
+    response
+}
+
+// 回调签名由 tungstenite 决定
+#[allow(clippy::result_large_err)]
+fn check_request(filter: Option<&RequestFilter>, request: &Request) -> Result<(), ErrorResponse> {
+    match filter {
+        Some(RequestFilter(filter)) => filter(request).map_err(reject),
+        None => Ok(()),
+    }
+}
+
+/// 完成 TLS 与 WebSocket 握手
+fn accept<F, C>(
+    upgrade: F,
+    tls: Option<TlsAcceptor>,
+    config: WebSocketConfig,
+    callback: C,
+) -> ListenerUpgrade
+where
+    F: Future<Output = io::Result<TcpStream>> + Send + 'static,
+    C: Callback + Unpin + Send + 'static,
+{
+    upgrade
+        .map_err(Error::from)
+        .and_then(move |stream| async move {
+            let stream = match tls {
+                Some(acceptor) => Either::Right(acceptor.accept(stream).await?.into()),
+                None => Either::Left(stream),
+            };
+            accept_hdr_async_with_config(stream, callback, Some(config))
+                .map_ok(BytesWebSocketStream::new)
+                .map_ok(RwStreamSink::new)
+                .await
+        })
+        .boxed()
+}
+
+impl OwnerListener {
+    fn poll_event(
+        &mut self,
+        path: Option<&str>,
+        use_tls: bool,
+        cx: &mut Context<'_>,
+    ) -> Poll<ListenerEvent<ListenerUpgrade, Error>> {
+        loop {
+            if let Poll::Ready(Some(result)) = self.handshakes.poll_next_unpin(cx) {
+                match result {
+                    Ok((route, event)) if route.as_deref() == path => {
+                        return Poll::Ready(event.into_listener_event(use_tls, path));
+                    }
+                    Ok((route, event)) => {
+                        if let Some((router, key)) = &self.port {
+                            router.dispatch(key, &route, event);
+                        }
+                    }
+                    Err(error) => {
+                        tracing::debug!("WebSocket handshake on shared port failed: {}", error);
+                    }
+                }
+                continue;
+            }
+
+            let event = match ready!(Pin::new(&mut self.inner).poll_event(cx)) {
+                ListenerEvent::AddressExpired(addr) => {
+                    if let Some((router, key)) = &self.port {
+                        router.address_expired(key, &addr);
+                    }
+                    ListenerEvent::AddressExpired(append_on_addr(addr, use_tls, path))
+                }
+                ListenerEvent::NewAddress(addr) => {
+                    if let Some((router, key)) = &self.port {
+                        router.new_address(key, &addr);
+                    }
+                    ListenerEvent::NewAddress(append_on_addr(addr, use_tls, path))
+                }
+                ListenerEvent::Rebound(addr) => {
+                    if let Some((router, key)) = &self.port {
+                        router.new_address(key, &addr);
+                    }
+                    ListenerEvent::Rebound(append_on_addr(addr, use_tls, path))
+                }
+                ListenerEvent::Incoming {
+                    local_addr,
+                    remote_addr,
+                    upgrade,
+                } => {
+                    if let Some((router, key)) = &self.port
+                        && router.is_shared(key)
+                    {
+                        let handshake = self.routed_handshake(
+                            router.clone(),
+                            key.clone(),
+                            local_addr,
+                            remote_addr,
+                            upgrade,
+                        );
+                        self.handshakes.push(handshake);
+                        continue;
+                    }
+                    let request_filter = self.request_filter.clone();
+                    // 回调签名由 tungstenite 决定
+                    #[allow(clippy::result_large_err)]
+                    let callback = move |request: &Request, response: Response| {
+                        check_request(request_filter.as_ref(), request).map(|()| response)
+                    };
+                    ListenerEvent::Incoming {
+                        local_addr: append_on_addr(local_addr, use_tls, path),
+                        remote_addr: append_on_addr(remote_addr, use_tls, path),
+                        upgrade: accept(upgrade, self.tls.clone(), self.config, callback),
+                    }
+                }
+                ListenerEvent::Closed(r) => ListenerEvent::Closed(r.map_err(Error::from)),
+                ListenerEvent::Error(err) => ListenerEvent::Error(err.into()),
+            };
+            return Poll::Ready(event);
+        }
+    }
+
+    /// 握手时按请求路径选择监听器，未知路径以 404 拒绝
+    fn routed_handshake<F>(
+        &self,
+        router: Router,
+        key: Multiaddr,
+        local_addr: Multiaddr,
+        remote_addr: Multiaddr,
+        upgrade: F,
+    ) -> RoutedHandshake
+    where
+        F: Future<Output = io::Result<TcpStream>> + Send + 'static,
+    {
+        let (route_sender, route_receiver) = oneshot::channel();
+        let request_filter = self.request_filter.clone();
+        // 回调签名由 tungstenite 决定
+        #[allow(clippy::result_large_err)]
+        let callback = move |request: &Request, response: Response| -> Result<_, ErrorResponse> {
+            let route = router
+                .route(&key, request.uri().path())
+                .ok_or_else(|| reject(StatusCode::NOT_FOUND))?;
+            check_request(request_filter.as_ref(), request)?;
+            let _ = route_sender.send(route);
+            Ok(response)
+        };
+        let upgrade = accept(upgrade, self.tls.clone(), self.config, callback);
+        async move {
+            let stream = upgrade.await?;
+            let route = route_receiver.await.map_err(|_| Error::ConnectionClosed)?;
+            Ok((
+                route,
+                RoutedEvent::Incoming {
+                    local_addr,
+                    remote_addr,
+                    stream: Box::new(stream),
+                },
+            ))
+        }
+        .boxed()
+    }
+}
+
 impl Listener for ListenStream {
-    type Output = RwStreamSink<BytesWebSocketStream<TcpStream>>;
+    type Output = Output;
     type Error = tungstenite::Error;
     type Upgrade = ListenerUpgrade;
 
     fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
-        let this = self.project();
-        this.inner.poll_close(cx).map_err(tungstenite::Error::from)
+        match &mut self.get_mut().inner {
+            ListenInner::Owner(owner) => Pin::new(&mut owner.inner)
+                .poll_close(cx)
+                .map_err(tungstenite::Error::from),
+            ListenInner::Routed(routed) => {
+                routed.events.close();
+                Poll::Ready(Ok(()))
+            }
+        }
     }
 
     fn poll_event(
         self: Pin<&mut Self>,
         cx: &mut Context<'_>,
     ) -> Poll<ListenerEvent<Self::Upgrade, Self::Error>> {
-        let this = self.project();
-
-        let inner_event = {
-            match this.inner.poll_event(cx) {
-                Poll::Ready(event) => event,
-                Poll::Pending => return Poll::Pending,
-            }
-        };
-
-        let event = match inner_event {
-            ListenerEvent::AddressExpired(addr) => {
-                ListenerEvent::AddressExpired(append_on_addr(addr, this.path.as_deref()))
-            }
-            ListenerEvent::NewAddress(multiaddr) => {
-                ListenerEvent::NewAddress(append_on_addr(multiaddr, this.path.as_deref()))
-            }
-            ListenerEvent::Incoming {
-                local_addr,
-                remote_addr,
-                upgrade,
-            } => {
-                let config = this.config.clone();
-                let upgrade = upgrade
-                    .map_err(Error::from)
-                    .and_then(move |stream| {
-                        accept_async_with_config(stream, Some(config))
-                            .map_ok(BytesWebSocketStream::new)
-                            .map_ok(RwStreamSink::new)
-                    })
-                    .boxed();
-                ListenerEvent::Incoming {
-                    local_addr: append_on_addr(local_addr, this.path.as_deref()),
-                    remote_addr: append_on_addr(remote_addr, this.path.as_deref()),
-                    upgrade,
+        let this = self.get_mut();
+        let path = this.path.as_deref();
+        match &mut this.inner {
+            ListenInner::Owner(owner) => owner.poll_event(path, this.use_tls, cx),
+            ListenInner::Routed(routed) => {
+                if routed.closed {
+                    return Poll::Pending;
+                }
+                match ready!(routed.events.poll_next_unpin(cx)) {
+                    Some(event) => Poll::Ready(event.into_listener_event(this.use_tls, path)),
+                    // 持有端口的监听器已关闭
+                    None => {
+                        routed.closed = true;
+                        Poll::Ready(ListenerEvent::Closed(Ok(())))
+                    }
                 }
             }
-            ListenerEvent::Closed(r) => ListenerEvent::Closed(r.map_err(Error::from)),
-            ListenerEvent::Error(err) => ListenerEvent::Error(err.into()),
-        };
-        Poll::Ready(event)
+        }
     }
 }
 
-fn parse_ws_listen_addr(addr: &Multiaddr) -> Option<(Multiaddr, Option<String>)> {
+/// 返回 TCP 地址、路径以及是否使用 TLS
+fn parse_ws_listen_addr(addr: &Multiaddr) -> Option<(Multiaddr, Option<String>, bool)> {
     let mut inner_addr = addr.clone();
-    let maybe_path = inner_addr.pop()?;
-    match maybe_path {
+    let path = match inner_addr.pop()? {
         Protocol::Path(path) => match inner_addr.pop()? {
-            Protocol::Ws => Some((inner_addr, Some(path.to_string()))),
-            _ => None,
+            Protocol::Ws => Some(path.to_string()),
+            _ => return None,
         },
-        Protocol::Ws => Some((inner_addr, None)),
-        _ => None,
+        Protocol::Ws => None,
+        _ => return None,
+    };
+    // 监听端的 SNI 由证书决定，地址中的 `/sni` 被忽略
+    let mut tls_addr = inner_addr.clone();
+    if let Some(Protocol::Sni(_)) = tls_addr.iter().last() {
+        tls_addr.pop();
+    }
+    match tls_addr.pop() {
+        Some(Protocol::Tls) => Some((tls_addr, path, true)),
+        _ => Some((inner_addr, path, false)),
     }
 }
 
@@ -252,12 +609,20 @@ fn parse_ws_dial_addr(addr: &Multiaddr) -> Result<WsAddress, ()> {
     let mut protocols = addr.clone();
     let mut peer = None;
     let mut path = "/".to_string();
+    let mut server_name = server_name;
     let (use_tls, path) = loop {
         match protocols.pop() {
             p @ Some(Protocol::Peer(_)) => peer = p,
             Some(Protocol::Path(x_path)) => path = x_path.to_string(),
             Some(Protocol::Ws) => match protocols.pop() {
                 Some(Protocol::Tls) => break (true, path),
+                Some(Protocol::Sni(sni)) => match protocols.pop() {
+                    Some(Protocol::Tls) => {
+                        server_name = sni.to_string();
+                        break (true, path);
+                    }
+                    _ => return Err(()),
+                },
                 Some(p) => {
                     protocols.push(p);
                     break (false, path);
@@ -284,8 +649,169 @@ fn parse_ws_dial_addr(addr: &Multiaddr) -> Result<WsAddress, ()> {
 #[derive(Debug)]
 struct WsAddress {
     host_port: String,
+    /// TLS 握手使用的名称，默认为主机名，可由 `/sni` 指定
     server_name: String,
     path: String,
     use_tls: bool,
     tcp_addr: Multiaddr,
 }
+
+#[cfg(test)]
+mod tests {
+    use futures::{AsyncReadExt, AsyncWriteExt, future};
+    use rustls::{RootCertStore, pki_types::PrivateKeyDer};
+
+    use super::*;
+
+    async fn next_event(listener: &mut ListenStream) -> ListenerEvent<ListenerUpgrade, Error> {
+        future::poll_fn(|cx| Pin::new(&mut *listener).poll_event(cx)).await
+    }
+
+    #[tokio::test]
+    async fn dial_wss_with_sni() {
+        let certified = rcgen::generate_simple_self_signed(vec!["localhost".to_string()]).unwrap();
+        let key = PrivateKeyDer::Pkcs8(certified.key_pair.serialize_der().into());
+        let mut roots = RootCertStore::empty();
+        roots.add(certified.cert.der().clone()).unwrap();
+
+        let server = Config::new().tls(
+            tls::Config::new()
+                .with_server_certificate(vec![certified.cert.der().clone()], key)
+                .unwrap(),
+        );
+        let client = Config::new().tls(tls::Config::new().with_root_certificates(roots).unwrap());
+
+        let mut listener = server
+            .listen(
+                "/ip4/127.0.0.1/tcp/0/tls/ws".parse().unwrap(),
+                &SocketOptions::default(),
+            )
+            .unwrap();
+        let mut addr = match next_event(&mut listener).await {
+            ListenerEvent::NewAddress(addr) => addr,
+            _ => panic!("expected listen address"),
+        };
+        assert_eq!(addr.pop(), Some(Protocol::Ws));
+        assert_eq!(addr.pop(), Some(Protocol::Tls));
+        let addr = addr
+            .with(Protocol::Tls)
+            .with(Protocol::Sni("localhost".into()))
+            .with(Protocol::Ws);
+
+        let accept = async {
+            match next_event(&mut listener).await {
+                ListenerEvent::Incoming { upgrade, .. } => upgrade.await.unwrap(),
+                _ => panic!("expected incoming connection"),
+            }
+        };
+        let (dialed, mut accepted) = future::join(client.dial(addr).unwrap(), accept).await;
+        let mut dialed = dialed.unwrap();
+
+        dialed.write_all(b"hello").await.unwrap();
+        dialed.flush().await.unwrap();
+        let mut buf = [0u8; 5];
+        accepted.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"hello");
+    }
+
+    #[tokio::test]
+    async fn reject_by_request_filter() {
+        let server = Config::new().with_request_filter(|request| {
+            match request.headers().get("authorization") {
+                Some(token) if token == "secret" => Ok(()),
+                _ => Err(StatusCode::UNAUTHORIZED),
+            }
+        });
+        let mut listener = server
+            .listen(
+                "/ip4/127.0.0.1/tcp/0/ws".parse().unwrap(),
+                &SocketOptions::default(),
+            )
+            .unwrap();
+        let addr = match next_event(&mut listener).await {
+            ListenerEvent::NewAddress(addr) => addr,
+            _ => panic!("expected listen address"),
+        };
+
+        for (token, accepted) in [("wrong", false), ("secret", true)] {
+            let client = Config::new().with_header(
+                HeaderName::from_static("authorization"),
+                HeaderValue::from_static(token),
+            );
+            let accept = async {
+                match next_event(&mut listener).await {
+                    ListenerEvent::Incoming { upgrade, .. } => upgrade.await,
+                    _ => panic!("expected incoming connection"),
+                }
+            };
+            let (dialed, incoming) = future::join(client.dial(addr.clone()).unwrap(), accept).await;
+            assert_eq!(incoming.is_ok(), accepted);
+            match dialed {
+                Ok(_) => assert!(accepted),
+                Err(Error::Http(response)) => {
+                    assert_eq!(response.status(), StatusCode::UNAUTHORIZED)
+                }
+                Err(e) => panic!("unexpected error: {e}"),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn route_by_path_on_shared_port() {
+        let port = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let base: Multiaddr = format!("/ip4/127.0.0.1/tcp/{port}/ws").parse().unwrap();
+        let with_path = |path: &str| base.clone().with(Protocol::Path(path.to_string().into()));
+        let server = Config::new();
+        let echo = with_path("/echo");
+        let mut owner = server
+            .listen(with_path("/chat"), &SocketOptions::default())
+            .unwrap();
+        let mut routed = server
+            .listen(echo.clone(), &SocketOptions::default())
+            .unwrap();
+        assert!(
+            server
+                .listen(echo.clone(), &SocketOptions::default())
+                .is_err()
+        );
+
+        assert!(matches!(
+            next_event(&mut owner).await,
+            ListenerEvent::NewAddress(_)
+        ));
+        let addr = match next_event(&mut routed).await {
+            ListenerEvent::NewAddress(addr) => addr,
+            _ => panic!("expected listen address"),
+        };
+        assert_eq!(addr, echo);
+        tokio::spawn(async move {
+            loop {
+                next_event(&mut owner).await;
+            }
+        });
+
+        let accept = async {
+            match next_event(&mut routed).await {
+                ListenerEvent::Incoming { upgrade, .. } => upgrade.await.unwrap(),
+                _ => panic!("expected incoming connection"),
+            }
+        };
+        let (dialed, mut accepted) =
+            future::join(Config::new().dial(addr.clone()).unwrap(), accept).await;
+        let mut dialed = dialed.unwrap();
+        dialed.write_all(b"hello").await.unwrap();
+        dialed.flush().await.unwrap();
+        let mut buf = [0u8; 5];
+        accepted.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"hello");
+
+        match Config::new().dial(with_path("/unknown")).unwrap().await {
+            Err(Error::Http(response)) => assert_eq!(response.status(), StatusCode::NOT_FOUND),
+            _ => panic!("expected 404"),
+        }
+    }
+}
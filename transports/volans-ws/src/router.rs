@@ -0,0 +1,145 @@
+use std::{
+    collections::HashMap,
+    fmt,
+    sync::{Arc, Mutex, MutexGuard},
+};
+
+use futures::channel::mpsc;
+use volans_core::{Multiaddr, multiaddr::Protocol};
+
+/// 共用同一 TCP 端口的监听器，由持有 TCP 监听器的一方按 HTTP 路径分发连接
+#[derive(Clone, Default)]
+pub(crate) struct Router(Arc<Mutex<HashMap<Multiaddr, Port>>>);
+
+impl fmt::Debug for Router {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Router").finish_non_exhaustive()
+    }
+}
+
+pub(crate) struct Port {
+    use_tls: bool,
+    /// 持有 TCP 监听器的逻辑监听器的路径
+    owner: Option<String>,
+    /// 其他逻辑监听器，`None` 为不带路径的监听器
+    routes: HashMap<Option<String>, mpsc::UnboundedSender<RoutedEvent>>,
+    /// TCP 监听器当前的地址，供后加入的监听器上报
+    addresses: Vec<Multiaddr>,
+}
+
+/// 转发给共用端口的监听器的事件，地址为 TCP 地址
+pub(crate) enum RoutedEvent {
+    NewAddress(Multiaddr),
+    AddressExpired(Multiaddr),
+    Incoming {
+        local_addr: Multiaddr,
+        remote_addr: Multiaddr,
+        stream: Box<crate::Output>,
+    },
+}
+
+/// 端口号为 0 时每次监听绑定不同的端口，无法共用
+pub(crate) fn shareable(tcp_addr: &Multiaddr) -> bool {
+    tcp_addr
+        .iter()
+        .any(|p| matches!(p, Protocol::Tcp(port) if port != 0))
+}
+
+impl Router {
+    fn lock(&self) -> MutexGuard<'_, HashMap<Multiaddr, Port>> {
+        self.0.lock().expect("Router lock poisoned")
+    }
+
+    /// 登记新的 TCP 监听器
+    pub(crate) fn add_port(&self, key: Multiaddr, use_tls: bool, owner: Option<String>) {
+        self.lock().insert(
+            key,
+            Port {
+                use_tls,
+                owner,
+                routes: HashMap::new(),
+                addresses: Vec::new(),
+            },
+        );
+    }
+
+    /// 加入已有的 TCP 监听器
+    ///
+    /// 端口未被监听时返回 `None`，TLS 设置不一致或路径已被占用时返回 `Some(Err(()))`。
+    pub(crate) fn join(
+        &self,
+        key: &Multiaddr,
+        use_tls: bool,
+        path: &Option<String>,
+    ) -> Option<Result<mpsc::UnboundedReceiver<RoutedEvent>, ()>> {
+        let mut ports = self.lock();
+        let port = ports.get_mut(key)?;
+        if port.use_tls != use_tls || port.owner == *path || port.routes.contains_key(path) {
+            return Some(Err(()));
+        }
+        let (sender, receiver) = mpsc::unbounded();
+        for addr in &port.addresses {
+            let _ = sender.unbounded_send(RoutedEvent::NewAddress(addr.clone()));
+        }
+        port.routes.insert(path.clone(), sender);
+        Some(Ok(receiver))
+    }
+
+    /// 持有者关闭，共用端口的监听器随之结束
+    pub(crate) fn remove_port(&self, key: &Multiaddr) {
+        self.lock().remove(key);
+    }
+
+    pub(crate) fn leave(&self, key: &Multiaddr, path: &Option<String>) {
+        if let Some(port) = self.lock().get_mut(key) {
+            port.routes.remove(path);
+        }
+    }
+
+    /// 是否有其他监听器共用端口，共用时由持有者完成握手后再分发
+    pub(crate) fn is_shared(&self, key: &Multiaddr) -> bool {
+        self.lock()
+            .get(key)
+            .is_some_and(|port| !port.routes.is_empty())
+    }
+
+    /// 按请求路径查找监听器，没有匹配的路径时交给不带路径的监听器
+    pub(crate) fn route(&self, key: &Multiaddr, path: &str) -> Option<Option<String>> {
+        let ports = self.lock();
+        let port = ports.get(key)?;
+        let path = Some(path.to_string());
+        if port.owner == path || port.routes.contains_key(&path) {
+            return Some(path);
+        }
+        if port.owner.is_none() || port.routes.contains_key(&None) {
+            return Some(None);
+        }
+        None
+    }
+
+    /// 把握手完成的连接交给对应的监听器，监听器已关闭时丢弃
+    pub(crate) fn dispatch(&self, key: &Multiaddr, route: &Option<String>, event: RoutedEvent) {
+        if let Some(sender) = self.lock().get(key).and_then(|port| port.routes.get(route)) {
+            let _ = sender.unbounded_send(event);
+        }
+    }
+
+    /// TCP 监听器的地址变化同步给所有共用端口的监听器
+    pub(crate) fn new_address(&self, key: &Multiaddr, addr: &Multiaddr) {
+        if let Some(port) = self.lock().get_mut(key) {
+            port.addresses.push(addr.clone());
+            for sender in port.routes.values() {
+                let _ = sender.unbounded_send(RoutedEvent::NewAddress(addr.clone()));
+            }
+        }
+    }
+
+    pub(crate) fn address_expired(&self, key: &Multiaddr, addr: &Multiaddr) {
+        if let Some(port) = self.lock().get_mut(key) {
+            port.addresses.retain(|a| a != addr);
+            for sender in port.routes.values() {
+                let _ = sender.unbounded_send(RoutedEvent::AddressExpired(addr.clone()));
+            }
+        }
+    }
+}
@@ -0,0 +1,72 @@
+//! wss（`/tls/ws`）使用的 TLS 配置
+
+use std::{fmt, sync::Arc};
+
+use futures_rustls::{TlsAcceptor, TlsConnector};
+use rustls::{
+    ClientConfig, RootCertStore, ServerConfig,
+    crypto::CryptoProvider,
+    pki_types::{CertificateDer, PrivateKeyDer},
+};
+
+pub use rustls;
+
+fn provider() -> Arc<CryptoProvider> {
+    Arc::new(rustls::crypto::ring::default_provider())
+}
+
+/// 未配置服务端时不能监听 `/tls/ws`，未配置客户端时不能拨号 `/tls/ws`
+#[derive(Clone, Default)]
+pub struct Config {
+    pub(crate) server: Option<TlsAcceptor>,
+    pub(crate) client: Option<TlsConnector>,
+}
+
+impl Config {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 监听使用的证书链与私钥
+    pub fn with_server_certificate(
+        self,
+        certificates: Vec<CertificateDer<'static>>,
+        key: PrivateKeyDer<'static>,
+    ) -> Result<Self, rustls::Error> {
+        let config = ServerConfig::builder_with_provider(provider())
+            .with_safe_default_protocol_versions()?
+            .with_no_client_auth()
+            .with_single_cert(certificates, key)?;
+        Ok(self.with_server_config(Arc::new(config)))
+    }
+
+    /// 监听使用的完整服务端配置
+    pub fn with_server_config(mut self, config: Arc<ServerConfig>) -> Self {
+        self.server = Some(TlsAcceptor::from(config));
+        self
+    }
+
+    /// 拨号时信任的根证书
+    pub fn with_root_certificates(self, roots: RootCertStore) -> Result<Self, rustls::Error> {
+        let config = ClientConfig::builder_with_provider(provider())
+            .with_safe_default_protocol_versions()?
+            .with_root_certificates(roots)
+            .with_no_client_auth();
+        Ok(self.with_client_config(Arc::new(config)))
+    }
+
+    /// 拨号使用的完整客户端配置，可以自定义证书校验
+    pub fn with_client_config(mut self, config: Arc<ClientConfig>) -> Self {
+        self.client = Some(TlsConnector::from(config));
+        self
+    }
+}
+
+impl fmt::Debug for Config {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Config")
+            .field("server", &self.server.is_some())
+            .field("client", &self.client.is_some())
+            .finish()
+    }
+}
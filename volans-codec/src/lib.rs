@@ -1,8 +1,10 @@
 mod json;
 mod protobuf;
+mod registry;
 
 pub use json::JsonUviCodec;
 pub use protobuf::ProtobufUviCodec;
+pub use registry::{AnyMessage, MessageRegistry, ProtobufRegistryCodec};
 
 pub use asynchronous_codec::*;
 pub use prost;
@@ -0,0 +1,269 @@
+use std::{
+    any::{Any, TypeId},
+    collections::HashMap,
+    fmt, io,
+    sync::Arc,
+};
+
+use asynchronous_codec::{BytesMut, Decoder, Encoder};
+use unsigned_varint::codec::UviBytes;
+
+type EncodeFn = fn(&(dyn Any + Send), &mut Vec<u8>);
+type DecodeFn = fn(&[u8]) -> Result<Box<dyn Any + Send>, prost::DecodeError>;
+
+fn encode_any<M>(message: &(dyn Any + Send), buf: &mut Vec<u8>)
+where
+    M: prost::Message + 'static,
+{
+    message
+        .downcast_ref::<M>()
+        .expect("Message type matches its registration")
+        .encode(buf)
+        .expect("Vec grows as needed");
+}
+
+fn decode_any<M>(buf: &[u8]) -> Result<Box<dyn Any + Send>, prost::DecodeError>
+where
+    M: prost::Message + Default + Send + 'static,
+{
+    Ok(Box::new(M::decode(buf)?))
+}
+
+/// 消息类型与类型标签的对应关系
+#[derive(Default)]
+pub struct MessageRegistry {
+    encoders: HashMap<TypeId, (u32, EncodeFn)>,
+    decoders: HashMap<u32, DecodeFn>,
+}
+
+impl fmt::Debug for MessageRegistry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut tags: Vec<_> = self.decoders.keys().collect();
+        tags.sort();
+        f.debug_struct("MessageRegistry")
+            .field("tags", &tags)
+            .finish()
+    }
+}
+
+impl MessageRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 注册消息类型，标签或类型重复注册时 panic
+    pub fn register<M>(mut self, tag: u32) -> Self
+    where
+        M: prost::Message + Default + Send + 'static,
+    {
+        assert!(
+            !self.decoders.contains_key(&tag),
+            "Message tag {tag} is already registered"
+        );
+        assert!(
+            !self.encoders.contains_key(&TypeId::of::<M>()),
+            "Message type {} is already registered",
+            std::any::type_name::<M>()
+        );
+        self.encoders
+            .insert(TypeId::of::<M>(), (tag, encode_any::<M>));
+        self.decoders.insert(tag, decode_any::<M>);
+        self
+    }
+
+    /// 消息类型注册的标签
+    pub fn tag_of<M: 'static>(&self) -> Option<u32> {
+        self.encoders.get(&TypeId::of::<M>()).map(|(tag, _)| *tag)
+    }
+}
+
+/// 带类型标签的消息
+pub struct AnyMessage {
+    tag: Option<u32>,
+    message: Box<dyn Any + Send>,
+}
+
+impl fmt::Debug for AnyMessage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AnyMessage")
+            .field("tag", &self.tag)
+            .finish_non_exhaustive()
+    }
+}
+
+impl AnyMessage {
+    /// 包装待发送的消息，编码时按注册表查找标签
+    pub fn new<M>(message: M) -> Self
+    where
+        M: prost::Message + Send + 'static,
+    {
+        AnyMessage {
+            tag: None,
+            message: Box::new(message),
+        }
+    }
+
+    /// 解码得到的消息标签，待发送的消息返回 `None`
+    pub fn tag(&self) -> Option<u32> {
+        self.tag
+    }
+
+    pub fn is<M: 'static>(&self) -> bool {
+        self.message.is::<M>()
+    }
+
+    pub fn downcast_ref<M: 'static>(&self) -> Option<&M> {
+        self.message.downcast_ref()
+    }
+
+    /// 取出具体类型的消息，类型不符时原样返回
+    pub fn downcast<M: 'static>(self) -> Result<M, Self> {
+        let AnyMessage { tag, message } = self;
+        message
+            .downcast::<M>()
+            .map(|message| *message)
+            .map_err(|message| AnyMessage { tag, message })
+    }
+
+    pub fn into_any(self) -> Box<dyn Any + Send> {
+        self.message
+    }
+}
+
+/// 在同一个子流上传输多种 protobuf 消息的编解码器
+///
+/// 每帧为 unsigned-varint 长度前缀，帧内以 unsigned-varint 类型标签开头，后跟消息内容。
+pub struct ProtobufRegistryCodec {
+    uvi_codec: UviBytes,
+    registry: Arc<MessageRegistry>,
+}
+
+impl Clone for ProtobufRegistryCodec {
+    fn clone(&self) -> Self {
+        let mut uvi = UviBytes::default();
+        uvi.set_max_len(self.uvi_codec.max_len());
+        ProtobufRegistryCodec {
+            uvi_codec: uvi,
+            registry: self.registry.clone(),
+        }
+    }
+}
+
+impl ProtobufRegistryCodec {
+    pub fn new(registry: impl Into<Arc<MessageRegistry>>) -> Self {
+        ProtobufRegistryCodec {
+            uvi_codec: UviBytes::default(),
+            registry: registry.into(),
+        }
+    }
+
+    pub fn set_max_len(mut self, val: usize) -> Self {
+        self.uvi_codec.set_max_len(val);
+        self
+    }
+
+    pub fn max_len(&self) -> usize {
+        self.uvi_codec.max_len()
+    }
+
+    pub fn registry(&self) -> &MessageRegistry {
+        &self.registry
+    }
+}
+
+impl Encoder for ProtobufRegistryCodec {
+    type Item<'a> = AnyMessage;
+    type Error = io::Error;
+
+    fn encode(&mut self, item: Self::Item<'_>, dst: &mut BytesMut) -> Result<(), Self::Error> {
+        let type_id = (*item.message).type_id();
+        let (tag, encode) = self.registry.encoders.get(&type_id).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "message type is not registered",
+            )
+        })?;
+        let mut tag_buf = unsigned_varint::encode::u32_buffer();
+        let mut buffer = unsigned_varint::encode::u32(*tag, &mut tag_buf).to_vec();
+        encode(item.message.as_ref(), &mut buffer);
+        self.uvi_codec.encode(buffer.into(), dst)
+    }
+}
+
+impl Decoder for ProtobufRegistryCodec {
+    type Item = AnyMessage;
+    type Error = io::Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        let bytes = match self.uvi_codec.decode(src) {
+            Ok(Some(bytes)) => bytes,
+            Ok(None) => return Ok(None),
+            Err(e) => return Err(io::Error::new(io::ErrorKind::InvalidData, e)),
+        };
+        let (tag, body) = unsigned_varint::decode::u32(&bytes)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        let decode = self.registry.decoders.get(&tag).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("unknown message tag {tag}"),
+            )
+        })?;
+        let message = decode(body).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        Ok(Some(AnyMessage {
+            tag: Some(tag),
+            message,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    struct Ping {
+        #[prost(uint64, tag = "1")]
+        nonce: u64,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    struct Text {
+        #[prost(string, tag = "1")]
+        body: String,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    struct Unregistered {}
+
+    #[test]
+    fn round_trip_registered_messages() {
+        let registry = MessageRegistry::new()
+            .register::<Ping>(1)
+            .register::<Text>(2);
+        let mut codec = ProtobufRegistryCodec::new(registry);
+
+        let mut buf = BytesMut::new();
+        codec
+            .encode(AnyMessage::new(Ping { nonce: 7 }), &mut buf)
+            .unwrap();
+        codec
+            .encode(
+                AnyMessage::new(Text {
+                    body: "hello".into(),
+                }),
+                &mut buf,
+            )
+            .unwrap();
+
+        let ping = codec.decode(&mut buf).unwrap().unwrap();
+        assert_eq!(ping.tag(), Some(1));
+        assert_eq!(ping.downcast::<Ping>().unwrap(), Ping { nonce: 7 });
+        let text = codec.decode(&mut buf).unwrap().unwrap();
+        assert_eq!(text.tag(), Some(2));
+        assert!(text.downcast::<Ping>().is_err());
+        assert!(codec.decode(&mut buf).unwrap().is_none());
+
+        let unregistered = codec.encode(AnyMessage::new(Unregistered::default()), &mut buf);
+        assert!(unregistered.is_err());
+    }
+}
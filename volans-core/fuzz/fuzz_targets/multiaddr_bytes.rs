@@ -0,0 +1,19 @@
+//! 二进制形式的 `Multiaddr` 与 `Protocol` 解析不应 panic
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use volans_core::{Multiaddr, multiaddr::Protocol};
+
+fuzz_target!(|data: &[u8]| {
+    let _ = Protocol::from_bytes(data);
+    if let Ok(mut addr) = Multiaddr::try_from(data.to_vec()) {
+        assert!(addr.try_iter().all(|p| p.is_ok()));
+        let _ = addr.to_string();
+        let _ = addr.protocol_stack().count();
+        let _ = addr.peer_id();
+        let _ = addr.is_global();
+        while addr.pop().is_some() {}
+        assert!(addr.is_empty());
+    }
+});
@@ -0,0 +1,16 @@
+//! 字符串与 URL 形式的 `Multiaddr` 解析不应 panic
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use volans_core::{Multiaddr, multiaddr};
+
+fuzz_target!(|data: &str| {
+    if let Ok(addr) = data.parse::<Multiaddr>() {
+        assert!(addr.try_iter().all(|p| p.is_ok()));
+        let _ = addr.to_string();
+        assert!(Multiaddr::try_from(addr.to_vec()).is_ok());
+    }
+    let _ = multiaddr::from_url(data);
+    let _ = multiaddr::from_url_lossy(data);
+});
@@ -0,0 +1,392 @@
+use std::fmt;
+
+use unsigned_varint::{decode, encode};
+
+use super::{Error, KeyPair, PeerId, PublicKey, Signer};
+
+/// 公钥 protobuf 编码中的 `KeyType` 字段
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum KeyType {
+    Rsa,
+    Ed25519,
+    Secp256k1,
+    Ecdsa,
+}
+
+impl KeyType {
+    fn code(self) -> u64 {
+        match self {
+            KeyType::Rsa => 0,
+            KeyType::Ed25519 => 1,
+            KeyType::Secp256k1 => 2,
+            KeyType::Ecdsa => 3,
+        }
+    }
+
+    fn from_code(code: u64) -> Option<Self> {
+        match code {
+            0 => Some(KeyType::Rsa),
+            1 => Some(KeyType::Ed25519),
+            2 => Some(KeyType::Secp256k1),
+            3 => Some(KeyType::Ecdsa),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for KeyType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KeyType::Rsa => write!(f, "RSA"),
+            KeyType::Ed25519 => write!(f, "Ed25519"),
+            KeyType::Secp256k1 => write!(f, "secp256k1"),
+            KeyType::Ecdsa => write!(f, "ECDSA"),
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("Signing failed: {0}")]
+pub struct SigningError(String);
+
+/// 任意类型的密钥对，RSA、ECDSA、secp256k1 分别由同名 feature 启用
+#[derive(Clone)]
+pub enum AnyKeypair {
+    Ed25519(KeyPair),
+    #[cfg(feature = "rsa")]
+    Rsa(rsa::RsaPrivateKey),
+    #[cfg(feature = "ecdsa")]
+    Ecdsa(p256::ecdsa::SigningKey),
+    #[cfg(feature = "secp256k1")]
+    Secp256k1(k256::ecdsa::SigningKey),
+}
+
+impl AnyKeypair {
+    pub fn generate_ed25519() -> Self {
+        AnyKeypair::Ed25519(KeyPair::from_bytes(&rand::random()))
+    }
+
+    #[cfg(feature = "ecdsa")]
+    pub fn generate_ecdsa() -> Self {
+        loop {
+            if let Ok(key) = p256::ecdsa::SigningKey::from_slice(&rand::random::<[u8; 32]>()) {
+                return AnyKeypair::Ecdsa(key);
+            }
+        }
+    }
+
+    #[cfg(feature = "secp256k1")]
+    pub fn generate_secp256k1() -> Self {
+        loop {
+            if let Ok(key) = k256::ecdsa::SigningKey::from_slice(&rand::random::<[u8; 32]>()) {
+                return AnyKeypair::Secp256k1(key);
+            }
+        }
+    }
+
+    /// 由 PKCS#8 DER 编码的 RSA 私钥构造
+    #[cfg(feature = "rsa")]
+    pub fn rsa_from_pkcs8(der: &[u8]) -> Result<Self, Error> {
+        use rsa::pkcs8::DecodePrivateKey;
+
+        rsa::RsaPrivateKey::from_pkcs8_der(der)
+            .map(AnyKeypair::Rsa)
+            .map_err(|e| Error::InvalidPrivateKey(e.to_string()))
+    }
+
+    /// 由 32 字节的 P-256 私钥标量构造
+    #[cfg(feature = "ecdsa")]
+    pub fn ecdsa_from_bytes(secret: &[u8]) -> Result<Self, Error> {
+        p256::ecdsa::SigningKey::from_slice(secret)
+            .map(AnyKeypair::Ecdsa)
+            .map_err(|e| Error::InvalidPrivateKey(e.to_string()))
+    }
+
+    /// 由 32 字节的 secp256k1 私钥标量构造
+    #[cfg(feature = "secp256k1")]
+    pub fn secp256k1_from_bytes(secret: &[u8]) -> Result<Self, Error> {
+        k256::ecdsa::SigningKey::from_slice(secret)
+            .map(AnyKeypair::Secp256k1)
+            .map_err(|e| Error::InvalidPrivateKey(e.to_string()))
+    }
+
+    pub fn key_type(&self) -> KeyType {
+        match self {
+            AnyKeypair::Ed25519(_) => KeyType::Ed25519,
+            #[cfg(feature = "rsa")]
+            AnyKeypair::Rsa(_) => KeyType::Rsa,
+            #[cfg(feature = "ecdsa")]
+            AnyKeypair::Ecdsa(_) => KeyType::Ecdsa,
+            #[cfg(feature = "secp256k1")]
+            AnyKeypair::Secp256k1(_) => KeyType::Secp256k1,
+        }
+    }
+
+    pub fn public(&self) -> AnyPublicKey {
+        match self {
+            AnyKeypair::Ed25519(key) => AnyPublicKey::Ed25519(key.verifying_key()),
+            #[cfg(feature = "rsa")]
+            AnyKeypair::Rsa(key) => AnyPublicKey::Rsa(key.to_public_key()),
+            #[cfg(feature = "ecdsa")]
+            AnyKeypair::Ecdsa(key) => AnyPublicKey::Ecdsa(*key.verifying_key()),
+            #[cfg(feature = "secp256k1")]
+            AnyKeypair::Secp256k1(key) => AnyPublicKey::Secp256k1(*key.verifying_key()),
+        }
+    }
+
+    pub fn to_peer_id(&self) -> PeerId {
+        self.public().to_peer_id()
+    }
+
+    /// 签名，RSA 使用 PKCS#1 v1.5，ECDSA 与 secp256k1 对消息的 SHA-256 摘要签名并以 DER 编码
+    pub fn sign(&self, msg: &[u8]) -> Result<Vec<u8>, SigningError> {
+        match self {
+            AnyKeypair::Ed25519(key) => Ok(key.sign(msg).to_bytes().to_vec()),
+            #[cfg(feature = "rsa")]
+            AnyKeypair::Rsa(key) => {
+                use sha2::Digest;
+
+                key.sign(
+                    rsa::Pkcs1v15Sign::new::<sha2::Sha256>(),
+                    &sha2::Sha256::digest(msg),
+                )
+                .map_err(|e| SigningError(e.to_string()))
+            }
+            #[cfg(feature = "ecdsa")]
+            AnyKeypair::Ecdsa(key) => {
+                let signature: p256::ecdsa::Signature = key.sign(msg);
+                Ok(signature.to_der().as_bytes().to_vec())
+            }
+            #[cfg(feature = "secp256k1")]
+            AnyKeypair::Secp256k1(key) => {
+                let signature: k256::ecdsa::Signature = key.sign(msg);
+                Ok(signature.to_der().as_bytes().to_vec())
+            }
+        }
+    }
+}
+
+impl From<KeyPair> for AnyKeypair {
+    fn from(key: KeyPair) -> Self {
+        AnyKeypair::Ed25519(key)
+    }
+}
+
+impl fmt::Debug for AnyKeypair {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AnyKeypair")
+            .field("key_type", &self.key_type())
+            .field("public", &self.public())
+            .finish()
+    }
+}
+
+/// 任意类型的公钥
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AnyPublicKey {
+    Ed25519(PublicKey),
+    #[cfg(feature = "rsa")]
+    Rsa(rsa::RsaPublicKey),
+    #[cfg(feature = "ecdsa")]
+    Ecdsa(p256::ecdsa::VerifyingKey),
+    #[cfg(feature = "secp256k1")]
+    Secp256k1(k256::ecdsa::VerifyingKey),
+}
+
+impl AnyPublicKey {
+    pub fn key_type(&self) -> KeyType {
+        match self {
+            AnyPublicKey::Ed25519(_) => KeyType::Ed25519,
+            #[cfg(feature = "rsa")]
+            AnyPublicKey::Rsa(_) => KeyType::Rsa,
+            #[cfg(feature = "ecdsa")]
+            AnyPublicKey::Ecdsa(_) => KeyType::Ecdsa,
+            #[cfg(feature = "secp256k1")]
+            AnyPublicKey::Secp256k1(_) => KeyType::Secp256k1,
+        }
+    }
+
+    pub fn verify(&self, msg: &[u8], signature: &[u8]) -> bool {
+        match self {
+            AnyPublicKey::Ed25519(key) => super::Signature::from_slice(signature)
+                .is_ok_and(|signature| key.verify_strict(msg, &signature).is_ok()),
+            #[cfg(feature = "rsa")]
+            AnyPublicKey::Rsa(key) => {
+                use sha2::Digest;
+
+                key.verify(
+                    rsa::Pkcs1v15Sign::new::<sha2::Sha256>(),
+                    &sha2::Sha256::digest(msg),
+                    signature,
+                )
+                .is_ok()
+            }
+            #[cfg(feature = "ecdsa")]
+            AnyPublicKey::Ecdsa(key) => {
+                use p256::ecdsa::signature::Verifier;
+
+                p256::ecdsa::Signature::from_der(signature)
+                    .is_ok_and(|signature| key.verify(msg, &signature).is_ok())
+            }
+            #[cfg(feature = "secp256k1")]
+            AnyPublicKey::Secp256k1(key) => {
+                use k256::ecdsa::signature::Verifier;
+
+                k256::ecdsa::Signature::from_der(signature)
+                    .is_ok_and(|signature| key.verify(msg, &signature).is_ok())
+            }
+        }
+    }
+
+    /// 公钥本身的编码：Ed25519 为 32 字节原始公钥，secp256k1 为 33 字节压缩点，
+    /// RSA 与 ECDSA 为 DER 编码的 SubjectPublicKeyInfo
+    fn encode_key(&self) -> Vec<u8> {
+        match self {
+            AnyPublicKey::Ed25519(key) => key.to_bytes().to_vec(),
+            #[cfg(feature = "rsa")]
+            AnyPublicKey::Rsa(key) => {
+                use rsa::pkcs8::EncodePublicKey;
+
+                key.to_public_key_der()
+                    .expect("RSA public key encodes to DER")
+                    .into_vec()
+            }
+            #[cfg(feature = "ecdsa")]
+            AnyPublicKey::Ecdsa(key) => {
+                use p256::pkcs8::EncodePublicKey;
+
+                key.to_public_key_der()
+                    .expect("P-256 public key encodes to DER")
+                    .into_vec()
+            }
+            #[cfg(feature = "secp256k1")]
+            AnyPublicKey::Secp256k1(key) => key.to_encoded_point(true).as_bytes().to_vec(),
+        }
+    }
+
+    fn decode_key(key_type: KeyType, data: &[u8]) -> Result<Self, Error> {
+        match key_type {
+            KeyType::Ed25519 => {
+                let bytes = data.try_into().map_err(|_| Error::InvalidPublicKey)?;
+                PublicKey::from_bytes(bytes)
+                    .map(AnyPublicKey::Ed25519)
+                    .map_err(|_| Error::InvalidPublicKey)
+            }
+            #[cfg(feature = "rsa")]
+            KeyType::Rsa => {
+                use rsa::pkcs8::DecodePublicKey;
+
+                rsa::RsaPublicKey::from_public_key_der(data)
+                    .map(AnyPublicKey::Rsa)
+                    .map_err(|_| Error::InvalidPublicKey)
+            }
+            #[cfg(feature = "ecdsa")]
+            KeyType::Ecdsa => {
+                use p256::pkcs8::DecodePublicKey;
+
+                p256::ecdsa::VerifyingKey::from_public_key_der(data)
+                    .map(AnyPublicKey::Ecdsa)
+                    .map_err(|_| Error::InvalidPublicKey)
+            }
+            #[cfg(feature = "secp256k1")]
+            KeyType::Secp256k1 => k256::ecdsa::VerifyingKey::from_sec1_bytes(data)
+                .map(AnyPublicKey::Secp256k1)
+                .map_err(|_| Error::InvalidPublicKey),
+            #[allow(unreachable_patterns)]
+            key_type => Err(Error::UnsupportedKeyType(key_type.code())),
+        }
+    }
+
+    /// protobuf 编码：`KeyType` 为字段 1，公钥数据为字段 2
+    pub fn encode_protobuf(&self) -> Vec<u8> {
+        encode_protobuf(self.key_type(), &self.encode_key())
+    }
+
+    pub fn try_decode_protobuf(bytes: &[u8]) -> Result<Self, Error> {
+        let mut key_type = None;
+        let mut data = None;
+        let mut input = bytes;
+        while !input.is_empty() {
+            let (tag, rest) = decode::u64(input).map_err(|_| Error::InvalidPublicKey)?;
+            match tag {
+                // 字段 1，varint
+                0x08 => {
+                    let (code, rest) = decode::u64(rest).map_err(|_| Error::InvalidPublicKey)?;
+                    key_type =
+                        Some(KeyType::from_code(code).ok_or(Error::UnsupportedKeyType(code))?);
+                    input = rest;
+                }
+                // 字段 2，length-delimited
+                0x12 => {
+                    let (len, rest) = decode::usize(rest).map_err(|_| Error::InvalidPublicKey)?;
+                    if rest.len() < len {
+                        return Err(Error::InvalidPublicKey);
+                    }
+                    data = Some(&rest[..len]);
+                    input = &rest[len..];
+                }
+                _ => return Err(Error::InvalidPublicKey),
+            }
+        }
+        match (key_type, data) {
+            (Some(key_type), Some(data)) => Self::decode_key(key_type, data),
+            _ => Err(Error::InvalidPublicKey),
+        }
+    }
+
+    pub fn to_peer_id(&self) -> PeerId {
+        PeerId::from_encoded_key(&self.encode_protobuf())
+    }
+}
+
+impl From<PublicKey> for AnyPublicKey {
+    fn from(key: PublicKey) -> Self {
+        AnyPublicKey::Ed25519(key)
+    }
+}
+
+fn encode_protobuf(key_type: KeyType, data: &[u8]) -> Vec<u8> {
+    let mut buf = encode::u64_buffer();
+    let mut out = Vec::with_capacity(data.len() + 8);
+    out.push(0x08);
+    out.extend_from_slice(encode::u64(key_type.code(), &mut buf));
+    out.push(0x12);
+    out.extend_from_slice(encode::usize(data.len(), &mut encode::usize_buffer()));
+    out.extend_from_slice(data);
+    out
+}
+
+/// 32 字节 Ed25519 公钥的 protobuf 编码，不校验公钥是否合法
+pub(crate) fn encode_ed25519(key: &[u8; 32]) -> Vec<u8> {
+    encode_protobuf(KeyType::Ed25519, key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sign_and_verify() {
+        let keypairs = [
+            AnyKeypair::generate_ed25519(),
+            #[cfg(feature = "ecdsa")]
+            AnyKeypair::generate_ecdsa(),
+            #[cfg(feature = "secp256k1")]
+            AnyKeypair::generate_secp256k1(),
+        ];
+
+        for keypair in keypairs {
+            let public = keypair.public();
+            let signature = keypair.sign(b"volans").unwrap();
+            assert!(
+                public.verify(b"volans", &signature),
+                "{}",
+                keypair.key_type()
+            );
+            assert!(!public.verify(b"other", &signature));
+
+            let decoded = AnyPublicKey::try_decode_protobuf(&public.encode_protobuf()).unwrap();
+            assert_eq!(decoded, public);
+        }
+    }
+}
@@ -0,0 +1,30 @@
+#[cfg(feature = "file-keystore")]
+mod file;
+
+#[cfg(feature = "file-keystore")]
+pub use file::{FileKeystore, KeystoreError};
+
+use super::{KeyPair, PeerId, PublicKey, Signature, Signer};
+
+/// 节点密钥的持有者，传输层升级从中借用密钥而不必自行保存
+pub trait Keystore: Send + Sync + 'static {
+    fn keypair(&self) -> &KeyPair;
+
+    fn public_key(&self) -> PublicKey {
+        self.keypair().verifying_key()
+    }
+
+    fn peer_id(&self) -> PeerId {
+        PeerId::from_public_key(&self.public_key())
+    }
+
+    fn sign(&self, msg: &[u8]) -> Signature {
+        Signer::sign(self.keypair(), msg)
+    }
+}
+
+impl Keystore for KeyPair {
+    fn keypair(&self) -> &KeyPair {
+        self
+    }
+}
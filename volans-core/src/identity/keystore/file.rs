@@ -0,0 +1,151 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use aes_gcm::{Aes256Gcm, KeyInit, aead::Aead};
+
+use super::Keystore;
+use crate::identity::KeyPair;
+
+#[derive(Debug, thiserror::Error)]
+pub enum KeystoreError {
+    #[error("I/O error: {0}")]
+    Io(#[from] io::Error),
+    #[error("Invalid keystore file format")]
+    InvalidFormat,
+    #[error("Keystore decryption failed, wrong passphrase or corrupted file")]
+    Decryption,
+}
+
+const MAGIC: &[u8; 4] = b"VKS1";
+const SALT_LENGTH: usize = 16;
+const NONCE_LENGTH: usize = 12;
+const HEADER_LENGTH: usize = MAGIC.len() + SALT_LENGTH + 4 + NONCE_LENGTH;
+const DEFAULT_ITERATIONS: u32 = 600_000;
+
+/// 以口令加密保存在磁盘上的密钥
+///
+/// 文件格式为 `VKS1 | salt(16) | iterations(u32 BE) | nonce(12) | ciphertext`，
+/// 加密密钥由 PBKDF2-HMAC-SHA256 从口令派生，私钥以 AES-256-GCM 加密。
+pub struct FileKeystore {
+    keypair: KeyPair,
+    iterations: u32,
+}
+
+impl FileKeystore {
+    pub fn new(keypair: KeyPair) -> Self {
+        Self {
+            keypair,
+            iterations: DEFAULT_ITERATIONS,
+        }
+    }
+
+    /// 保存时 PBKDF2 的迭代次数，读取时以文件中记录的为准
+    pub fn with_iterations(mut self, iterations: u32) -> Self {
+        self.iterations = iterations;
+        self
+    }
+
+    /// 读取已有的密钥文件，不存在时生成新的密钥并保存
+    pub fn load_or_generate(
+        path: impl AsRef<Path>,
+        passphrase: &str,
+    ) -> Result<Self, KeystoreError> {
+        let path = path.as_ref();
+        match Self::load(path, passphrase) {
+            Err(KeystoreError::Io(e)) if e.kind() == io::ErrorKind::NotFound => {
+                tracing::info!(path = %path.display(), "Keystore not found, generating new key");
+                let keystore = Self::new(KeyPair::from_bytes(&rand::random()));
+                keystore.save(path, passphrase)?;
+                Ok(keystore)
+            }
+            result => result,
+        }
+    }
+
+    pub fn load(path: impl AsRef<Path>, passphrase: &str) -> Result<Self, KeystoreError> {
+        let data = fs::read(path)?;
+        if data.len() <= HEADER_LENGTH || &data[..MAGIC.len()] != MAGIC {
+            return Err(KeystoreError::InvalidFormat);
+        }
+        let (salt, rest) = data[MAGIC.len()..].split_at(SALT_LENGTH);
+        let (iterations, rest) = rest.split_at(4);
+        let (nonce, ciphertext) = rest.split_at(NONCE_LENGTH);
+        let iterations = u32::from_be_bytes(iterations.try_into().expect("4 bytes"));
+
+        let secret = cipher(passphrase, salt, iterations)
+            .decrypt(nonce.into(), ciphertext)
+            .map_err(|_| KeystoreError::Decryption)?;
+        let secret = secret
+            .as_slice()
+            .try_into()
+            .map_err(|_| KeystoreError::InvalidFormat)?;
+        Ok(Self {
+            keypair: KeyPair::from_bytes(secret),
+            iterations,
+        })
+    }
+
+    /// 加密写入文件，Unix 下文件权限为 0600
+    pub fn save(&self, path: impl AsRef<Path>, passphrase: &str) -> Result<(), KeystoreError> {
+        let salt: [u8; SALT_LENGTH] = rand::random();
+        let nonce: [u8; NONCE_LENGTH] = rand::random();
+        let ciphertext = cipher(passphrase, &salt, self.iterations)
+            .encrypt(&nonce.into(), self.keypair.to_bytes().as_slice())
+            .expect("AES-GCM encryption of a 32 byte secret");
+
+        let mut data = Vec::with_capacity(HEADER_LENGTH + ciphertext.len());
+        data.extend_from_slice(MAGIC);
+        data.extend_from_slice(&salt);
+        data.extend_from_slice(&self.iterations.to_be_bytes());
+        data.extend_from_slice(&nonce);
+        data.extend_from_slice(&ciphertext);
+        write_private(path.as_ref(), &data)?;
+        Ok(())
+    }
+}
+
+impl Keystore for FileKeystore {
+    fn keypair(&self) -> &KeyPair {
+        &self.keypair
+    }
+}
+
+fn cipher(passphrase: &str, salt: &[u8], iterations: u32) -> Aes256Gcm {
+    let key =
+        pbkdf2::pbkdf2_hmac_array::<sha2::Sha256, 32>(passphrase.as_bytes(), salt, iterations);
+    Aes256Gcm::new(&key.into())
+}
+
+fn write_private(path: &Path, data: &[u8]) -> io::Result<()> {
+    // 先写入临时文件再重命名，避免中途失败留下损坏的密钥文件
+    let mut tmp = PathBuf::from(path);
+    tmp.as_mut_os_string().push(".tmp");
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    io::Write::write_all(&mut options.open(&tmp)?, data)?;
+    fs::rename(tmp, path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn save_and_load() {
+        let path = std::env::temp_dir().join(format!("volans-keystore-{}", rand::random::<u64>()));
+        let keystore = FileKeystore::new(KeyPair::from_bytes(&[7u8; 32])).with_iterations(1_000);
+        keystore.save(&path, "secret").unwrap();
+
+        let loaded = FileKeystore::load_or_generate(&path, "secret").unwrap();
+        assert_eq!(loaded.peer_id(), keystore.peer_id());
+        assert!(matches!(
+            FileKeystore::load(&path, "wrong"),
+            Err(KeystoreError::Decryption)
+        ));
+        fs::remove_file(path).unwrap();
+    }
+}
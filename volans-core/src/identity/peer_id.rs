@@ -0,0 +1,247 @@
+use std::{fmt, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+use unsigned_varint::decode;
+
+use super::{AnyPublicKey, Error, PublicKey, keypair};
+
+/// identity 多重哈希编码
+const IDENTITY: u64 = 0x00;
+/// sha2-256 多重哈希编码
+const SHA2_256: u64 = 0x12;
+/// 编码后的公钥不超过该长度时直接内联，否则取 SHA-256 摘要
+const MAX_INLINE_KEY_LENGTH: usize = 42;
+/// 两字节头部加最长的内联公钥
+const MAX_LENGTH: usize = 2 + MAX_INLINE_KEY_LENGTH;
+/// 旧版 PeerId 为 32 字节的 Ed25519 公钥
+const LEGACY_LENGTH: usize = 32;
+
+/// 节点标识，为编码后公钥的多重哈希
+///
+/// 编码后不超过 42 字节的公钥（Ed25519、secp256k1）使用 identity 哈希，可从中还原公钥；
+/// 其余（RSA、ECDSA）使用 sha2-256。旧版 32 字节 Ed25519 公钥形式在解析时自动转换。
+#[derive(Clone, Copy, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct PeerId {
+    len: u8,
+    bytes: [u8; MAX_LENGTH],
+}
+
+impl PeerId {
+    pub fn from_public_key(key: &PublicKey) -> Self {
+        Self::from_encoded_key(&keypair::encode_ed25519(key.as_bytes()))
+    }
+
+    pub(crate) fn from_encoded_key(encoded: &[u8]) -> Self {
+        if encoded.len() <= MAX_INLINE_KEY_LENGTH {
+            Self::from_digest(IDENTITY, encoded)
+        } else {
+            use sha2::Digest;
+            Self::from_digest(SHA2_256, &sha2::Sha256::digest(encoded))
+        }
+    }
+
+    fn from_digest(code: u64, digest: &[u8]) -> Self {
+        let mut bytes = [0u8; MAX_LENGTH];
+        bytes[0] = code as u8;
+        bytes[1] = digest.len() as u8;
+        bytes[2..2 + digest.len()].copy_from_slice(digest);
+        Self {
+            len: (2 + digest.len()) as u8,
+            bytes,
+        }
+    }
+
+    pub fn random() -> Self {
+        Self::from_digest(SHA2_256, &rand::random::<[u8; 32]>())
+    }
+
+    /// 由旧版 32 字节 Ed25519 公钥构造
+    pub fn from_bytes(bytes: [u8; 32]) -> Self {
+        Self::from_encoded_key(&keypair::encode_ed25519(&bytes))
+    }
+
+    /// 解析多重哈希，兼容旧版 32 字节形式
+    pub fn try_from_slice(bytes: &[u8]) -> Result<Self, Error> {
+        if bytes.len() == LEGACY_LENGTH {
+            let mut legacy = [0u8; LEGACY_LENGTH];
+            legacy.copy_from_slice(bytes);
+            return Ok(Self::from_bytes(legacy));
+        }
+        let (code, rest) = decode::u64(bytes).map_err(|_| Error::InvalidMultihash)?;
+        let (len, digest) = decode::usize(rest).map_err(|_| Error::InvalidMultihash)?;
+        if digest.len() != len {
+            return Err(Error::LengthInvalid);
+        }
+        match code {
+            IDENTITY if len <= MAX_INLINE_KEY_LENGTH => Ok(Self::from_digest(code, digest)),
+            SHA2_256 if len == 32 => Ok(Self::from_digest(code, digest)),
+            IDENTITY | SHA2_256 => Err(Error::LengthInvalid),
+            code => Err(Error::UnsupportedMultihash(code)),
+        }
+    }
+
+    pub fn try_from_base58(s: &str) -> Result<Self, Error> {
+        let bytes = bs58::decode(s).into_vec()?;
+        Self::try_from_slice(&bytes)
+    }
+
+    /// 多重哈希编码
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes[..self.len as usize]
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.as_bytes().to_vec()
+    }
+
+    pub fn into_base58(self) -> String {
+        bs58::encode(self.as_bytes()).into_string()
+    }
+
+    /// 内联在 identity 哈希中的公钥，sha2-256 形式无法还原时返回 `None`
+    pub fn public_key(&self) -> Option<AnyPublicKey> {
+        if self.bytes[0] as u64 != IDENTITY {
+            return None;
+        }
+        AnyPublicKey::try_decode_protobuf(&self.as_bytes()[2..]).ok()
+    }
+
+    /// 是否由该公钥派生
+    pub fn is_public_key(&self, key: &AnyPublicKey) -> bool {
+        key.to_peer_id() == *self
+    }
+}
+
+impl fmt::Debug for PeerId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("PeerId").field(&self.into_base58()).finish()
+    }
+}
+
+impl fmt::Display for PeerId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.into_base58().fmt(f)
+    }
+}
+
+impl Serialize for PeerId {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        if serializer.is_human_readable() {
+            serializer.serialize_str(&self.into_base58())
+        } else {
+            serializer.serialize_bytes(self.as_bytes())
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for PeerId {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        use serde::de::*;
+
+        struct PeerIdVisitor;
+
+        impl Visitor<'_> for PeerIdVisitor {
+            type Value = PeerId;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                write!(f, "valid peer id")
+            }
+
+            fn visit_bytes<E>(self, v: &[u8]) -> Result<Self::Value, E>
+            where
+                E: Error,
+            {
+                PeerId::try_from_slice(v)
+                    .map_err(|_| Error::invalid_value(Unexpected::Bytes(v), &self))
+            }
+
+            fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
+            where
+                E: Error,
+            {
+                PeerId::from_str(v).map_err(|_| Error::invalid_value(Unexpected::Str(v), &self))
+            }
+        }
+
+        if deserializer.is_human_readable() {
+            deserializer.deserialize_str(PeerIdVisitor)
+        } else {
+            deserializer.deserialize_bytes(PeerIdVisitor)
+        }
+    }
+}
+
+impl FromStr for PeerId {
+    type Err = Error;
+
+    #[inline]
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::try_from_base58(s)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::identity::KeyPair;
+
+    #[test]
+    fn legacy_ed25519_peer_id() {
+        let keypair = KeyPair::from_bytes(&[7u8; 32]);
+        let public = keypair.verifying_key();
+        let peer_id = PeerId::from_public_key(&public);
+
+        let legacy = bs58::encode(public.as_bytes()).into_string();
+        assert_eq!(legacy.parse::<PeerId>().unwrap(), peer_id);
+        assert_eq!(peer_id.to_string().parse::<PeerId>().unwrap(), peer_id);
+        assert_eq!(peer_id.public_key(), Some(AnyPublicKey::Ed25519(public)));
+    }
+
+    #[test]
+    fn multiaddr_peer_encoding() {
+        use crate::{Multiaddr, multiaddr::Protocol};
+
+        let peer_id = PeerId::from_public_key(&KeyPair::from_bytes(&[7u8; 32]).verifying_key());
+        let addr = Multiaddr::empty()
+            .with(Protocol::Peer(peer_id))
+            .with(Protocol::Tcp(1));
+        assert_eq!(Multiaddr::try_from(addr.to_vec()).unwrap(), addr);
+
+        // 旧版二进制编码为定长 32 字节公钥
+        let mut legacy = unsigned_varint::encode::u32(421, &mut Default::default()).to_vec();
+        legacy.extend_from_slice(KeyPair::from_bytes(&[7u8; 32]).verifying_key().as_bytes());
+        legacy.extend_from_slice(&Multiaddr::from(Protocol::Tcp(1)).to_vec());
+        assert_eq!(Multiaddr::try_from(legacy).unwrap(), addr);
+    }
+
+    #[test]
+    fn random_peer_id_round_trip() {
+        let peer_id = PeerId::random();
+        assert_eq!(PeerId::try_from_slice(peer_id.as_bytes()).unwrap(), peer_id);
+        assert_eq!(peer_id.public_key(), None);
+    }
+
+    #[cfg(feature = "secp256k1")]
+    #[test]
+    fn secp256k1_peer_id_is_inlined() {
+        let public = crate::identity::AnyKeypair::generate_secp256k1().public();
+        let peer_id = public.to_peer_id();
+        assert_eq!(peer_id.public_key(), Some(public));
+    }
+
+    #[cfg(feature = "ecdsa")]
+    #[test]
+    fn ecdsa_peer_id_is_hashed() {
+        let public = crate::identity::AnyKeypair::generate_ecdsa().public();
+        let peer_id = public.to_peer_id();
+        assert_eq!(peer_id.as_bytes().len(), 34);
+        assert!(peer_id.is_public_key(&public));
+        assert_eq!(peer_id.public_key(), None);
+    }
+}
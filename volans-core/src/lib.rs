@@ -14,7 +14,7 @@ pub use extensions::Extensions;
 pub use identity::PeerId;
 pub use multiaddr::Multiaddr;
 pub use muxing::StreamMuxer;
-pub use transport::{Listener, ListenerEvent, Transport, TransportError};
+pub use transport::{Listener, ListenerEvent, SocketOptions, Transport, TransportError};
 pub use upgrade::{InboundUpgrade, OutboundUpgrade, UpgradeInfo};
 
 pub use ed25519_dalek;
@@ -12,10 +12,12 @@ use crate::PeerId;
 mod error;
 mod from_url;
 mod protocol;
+mod validate;
 
 pub use error::Error;
 pub use from_url::{FromUrlErr, from_url, from_url_lossy};
 pub use protocol::Protocol;
+pub use validate::ValidationError;
 
 #[allow(clippy::rc_buffer)]
 #[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Hash)]
@@ -60,8 +62,9 @@ impl Multiaddr {
         if slice.is_empty() {
             return None;
         }
+        // 数据损坏时不修改地址
         let protocol = loop {
-            let (p, s) = Protocol::from_bytes(slice).expect("`slice` is a valid `Protocol`.");
+            let (p, s) = Protocol::from_bytes(slice).ok()?;
             if s.is_empty() {
                 break p.acquire();
             }
@@ -91,7 +94,12 @@ impl Multiaddr {
     }
 
     pub fn iter(&self) -> Iter<'_> {
-        Iter(&self.bytes)
+        Iter(self.try_iter())
+    }
+
+    /// 逐个解析组件并返回解析错误，[`Multiaddr::iter`] 遇到损坏的数据时直接结束
+    pub fn try_iter(&self) -> TryIter<'_> {
+        TryIter(&self.bytes)
     }
 
     pub fn replace<'a, F>(&self, at: usize, by: F) -> Option<Multiaddr>
@@ -104,13 +112,10 @@ impl Multiaddr {
 
         for (i, p) in self.iter().enumerate() {
             if i == at {
-                let f = fun.take().expect("i == at only happens once");
-                if let Some(q) = f(&p) {
-                    address = address.with(q);
-                    replaced = true;
-                    continue;
-                }
-                return None;
+                let q = fun.take().and_then(|f| f(&p))?;
+                address = address.with(q);
+                replaced = true;
+                continue;
             }
             address = address.with(p)
         }
@@ -136,9 +141,99 @@ impl Multiaddr {
         self.bytes[..m] == other.bytes[..]
     }
 
-    pub fn protocol_stack(&self) -> ProtoStackIter {
+    pub fn protocol_stack(&self) -> ProtoStackIter<'_> {
         ProtoStackIter { parts: self.iter() }
     }
+
+    /// 移除首个标记为 `tag` 的组件及其后的所有组件，不存在该组件时返回 `false`
+    pub fn truncate_at(&mut self, tag: &str) -> bool {
+        let mut slice = &self.bytes[..];
+        while !slice.is_empty() {
+            let Ok((p, s)) = Protocol::from_bytes(slice) else {
+                return false;
+            };
+            if p.tag() == tag {
+                let len = self.len() - slice.len();
+                self.bytes.truncate(len);
+                return true;
+            }
+            slice = s;
+        }
+        false
+    }
+
+    /// 地址末尾的 `/peer`
+    pub fn peer_id(&self) -> Option<PeerId> {
+        match self.iter().last() {
+            Some(Protocol::Peer(peer_id)) => Some(peer_id),
+            _ => None,
+        }
+    }
+
+    /// 去掉地址末尾的 `/peer`
+    pub fn without_peer(mut self) -> Self {
+        if self.peer_id().is_some() {
+            self.pop();
+        }
+        self
+    }
+
+    /// 首个组件为回环地址
+    pub fn is_loopback(&self) -> bool {
+        self.ip().is_some_and(|ip| ip.is_loopback())
+    }
+
+    /// 首个组件为私有或链路本地地址
+    pub fn is_private(&self) -> bool {
+        match self.ip() {
+            Some(IpAddr::V4(ip)) => ip.is_private() || ip.is_link_local(),
+            Some(IpAddr::V6(ip)) => {
+                let first = ip.segments()[0];
+                // fc00::/7 唯一本地地址与 fe80::/10 链路本地地址
+                (first & 0xfe00) == 0xfc00 || (first & 0xffc0) == 0xfe80
+            }
+            None => false,
+        }
+    }
+
+    /// 首个组件为公网可路由的地址，DNS 地址无法判断，返回 `false`
+    pub fn is_global(&self) -> bool {
+        let Some(ip) = self.ip() else {
+            return false;
+        };
+        if ip.is_loopback() || ip.is_unspecified() || ip.is_multicast() || self.is_private() {
+            return false;
+        }
+        match ip {
+            IpAddr::V4(ip) => {
+                let [a, b, ..] = ip.octets();
+                // 100.64.0.0/10 运营商共享地址
+                let shared = a == 100 && (b & 0xc0) == 64;
+                !(ip.is_broadcast() || ip.is_documentation() || shared)
+            }
+            // 2001:db8::/32 文档地址
+            IpAddr::V6(ip) => ip.segments()[..2] != [0x2001, 0xdb8],
+        }
+    }
+
+    /// 检查地址是否符合传输层（`tcp`、`quic`、`ws`、`memory`）的协议栈，
+    /// 返回不匹配的具体原因，供传输层在返回 [`TransportError::NotSupported`] 时诊断
+    ///
+    /// [`TransportError::NotSupported`]: crate::TransportError::NotSupported
+    pub fn validate_for_transport(&self, transport: &str) -> Result<(), ValidationError> {
+        validate::validate(self, transport)
+    }
+
+    fn ip(&self) -> Option<IpAddr> {
+        match self.iter().next()? {
+            Protocol::Ip4(ip) => Some(IpAddr::V4(ip)),
+            Protocol::Ip6(ip) => match ip.to_ipv4_mapped() {
+                Some(ip) => Some(IpAddr::V4(ip)),
+                None => Some(IpAddr::V6(ip)),
+            },
+            _ => None,
+        }
+    }
 }
 
 impl fmt::Debug for Multiaddr {
@@ -167,7 +262,7 @@ impl<'a> IntoIterator for &'a Multiaddr {
     type IntoIter = Iter<'a>;
 
     fn into_iter(self) -> Iter<'a> {
-        Iter(&self.bytes)
+        self.iter()
     }
 }
 
@@ -212,21 +307,36 @@ impl FromStr for Multiaddr {
 }
 
 /// Iterator over `Multiaddr` [`Protocol`]s.
-pub struct Iter<'a>(&'a [u8]);
+pub struct Iter<'a>(TryIter<'a>);
 
 impl<'a> Iterator for Iter<'a> {
     type Item = Protocol<'a>;
 
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next()?.ok()
+    }
+}
+
+/// 逐个解析 `Multiaddr` 的 [`Protocol`]，遇到损坏的数据时返回错误并结束
+pub struct TryIter<'a>(&'a [u8]);
+
+impl<'a> Iterator for TryIter<'a> {
+    type Item = Result<Protocol<'a>, Error>;
+
     fn next(&mut self) -> Option<Self::Item> {
         if self.0.is_empty() {
             return None;
         }
-
-        let (p, next_data) =
-            Protocol::from_bytes(self.0).expect("`Multiaddr` is known to be valid.");
-
-        self.0 = next_data;
-        Some(p)
+        match Protocol::from_bytes(self.0) {
+            Ok((p, next_data)) => {
+                self.0 = next_data;
+                Some(Ok(p))
+            }
+            Err(e) => {
+                self.0 = &[];
+                Some(Err(e))
+            }
+        }
     }
 }
 
@@ -280,10 +390,16 @@ impl TryFrom<Vec<u8>> for Multiaddr {
     fn try_from(v: Vec<u8>) -> Result<Self, Error> {
         // Check if the argument is a valid `Multiaddr` by reading its protocols.
         let mut slice = &v[..];
+        let mut has_peer = false;
         while !slice.is_empty() {
-            let (_, s) = Protocol::from_bytes(slice)?;
+            let (p, s) = Protocol::from_bytes(slice)?;
+            has_peer |= matches!(p, Protocol::Peer(_));
             slice = s
         }
+        // 重新编码，将旧版定长 PeerId 统一为多重哈希形式
+        if has_peer {
+            return TryIter(&v).collect();
+        }
         Ok(Multiaddr {
             bytes: Bytes::from(v),
         })
@@ -378,3 +494,32 @@ impl<'de> Deserialize<'de> for Multiaddr {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn malformed_bytes_do_not_panic() {
+        // 绕过校验构造损坏的地址：tcp 端口缺少一个字节
+        let mut bytes = "/ip4/127.0.0.1/tcp/80"
+            .parse::<Multiaddr>()
+            .unwrap()
+            .to_vec();
+        bytes.pop();
+        let mut addr = Multiaddr {
+            bytes: bytes.into(),
+        };
+
+        let parts: Vec<_> = addr.try_iter().collect();
+        assert!(matches!(
+            parts.as_slice(),
+            [Ok(Protocol::Ip4(_)), Err(Error::DataLessThanLen)]
+        ));
+        assert_eq!(addr.iter().count(), 1);
+        assert_eq!(addr.to_string(), "/ip4/127.0.0.1");
+        assert!(!addr.truncate_at("tcp"));
+        assert!(addr.pop().is_none());
+        assert!(Multiaddr::try_from(addr.to_vec()).is_err());
+    }
+}
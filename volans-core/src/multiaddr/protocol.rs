@@ -207,6 +207,15 @@ impl<'a> Protocol<'a> {
             WS => Ok((Protocol::Ws, input)),
             QUIC => Ok((Protocol::Quic, input)),
             PEER => {
+                // 长度前缀的多重哈希，否则按旧版 32 字节 Ed25519 公钥解析
+                if let Ok((n, data)) = decode::usize(input)
+                    && let Some(peer_id) = data
+                        .get(..n)
+                        .filter(|_| n != 32)
+                        .and_then(|d| PeerId::try_from_slice(d).ok())
+                {
+                    return Ok((Protocol::Peer(peer_id), &data[n..]));
+                }
                 let (data, rest) = split_at(32, input)?;
                 let peer_id = PeerId::try_from_slice(data)?;
                 Ok((Protocol::Peer(peer_id), rest))
@@ -288,7 +297,9 @@ impl<'a> Protocol<'a> {
 
             Protocol::Peer(p) => {
                 w.write_all(encode::u32(PEER, &mut buf))?;
-                w.write_all(p.as_bytes())?
+                let bytes = p.as_bytes();
+                w.write_all(encode::usize(bytes.len(), &mut encode::usize_buffer()))?;
+                w.write_all(bytes)?
             }
             Protocol::Circuit => {
                 w.write_all(encode::u32(CIRCUIT, &mut buf))?;
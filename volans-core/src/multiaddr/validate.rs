@@ -0,0 +1,125 @@
+use std::iter::Peekable;
+
+use super::{Iter, Multiaddr, Protocol};
+
+/// 地址与传输层期望的协议栈不匹配的原因
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum ValidationError {
+    #[error("Unknown transport: {0}")]
+    UnknownTransport(String),
+    #[error("Missing /{0} component")]
+    Missing(&'static str),
+    #[error("Unexpected /{found} component, expected /{expected}")]
+    Unexpected {
+        expected: &'static str,
+        found: &'static str,
+    },
+    #[error("Unexpected trailing /{0} component")]
+    Trailing(&'static str),
+}
+
+struct Components<'a>(Peekable<Iter<'a>>);
+
+impl Components<'_> {
+    fn expect(
+        &mut self,
+        expected: &'static str,
+        matches: fn(&Protocol<'_>) -> bool,
+    ) -> Result<(), ValidationError> {
+        match self.0.next() {
+            Some(p) if matches(&p) => Ok(()),
+            Some(p) => Err(ValidationError::Unexpected {
+                expected,
+                found: p.tag(),
+            }),
+            None => Err(ValidationError::Missing(expected)),
+        }
+    }
+
+    fn optional(&mut self, matches: fn(&Protocol<'_>) -> bool) -> bool {
+        self.0.next_if(|p| matches(p)).is_some()
+    }
+
+    /// 末尾允许一个 `/peer`
+    fn finish(mut self) -> Result<(), ValidationError> {
+        self.optional(|p| matches!(p, Protocol::Peer(_)));
+        match self.0.next() {
+            Some(p) => Err(ValidationError::Trailing(p.tag())),
+            None => Ok(()),
+        }
+    }
+}
+
+fn is_ip(p: &Protocol<'_>) -> bool {
+    matches!(p, Protocol::Ip4(_) | Protocol::Ip6(_))
+}
+
+fn is_host(p: &Protocol<'_>) -> bool {
+    is_ip(p) || matches!(p, Protocol::Dns(_) | Protocol::Dns4(_) | Protocol::Dns6(_))
+}
+
+pub(super) fn validate(addr: &Multiaddr, transport: &str) -> Result<(), ValidationError> {
+    let mut c = Components(addr.iter().peekable());
+    match transport {
+        "tcp" => {
+            c.expect("ip4", is_ip)?;
+            c.expect("tcp", |p| matches!(p, Protocol::Tcp(_)))?;
+        }
+        "quic" => {
+            c.expect("ip4", is_ip)?;
+            c.expect("udp", |p| matches!(p, Protocol::Udp(_)))?;
+            c.expect("quic", |p| matches!(p, Protocol::Quic))?;
+        }
+        "ws" => {
+            c.expect("dns", is_host)?;
+            c.expect("tcp", |p| matches!(p, Protocol::Tcp(_)))?;
+            if c.optional(|p| matches!(p, Protocol::Tls)) {
+                c.optional(|p| matches!(p, Protocol::Sni(_)));
+            }
+            c.expect("ws", |p| matches!(p, Protocol::Ws))?;
+            c.optional(|p| matches!(p, Protocol::Path(_)));
+        }
+        "memory" => c.expect("memory", |p| matches!(p, Protocol::Memory(_)))?,
+        _ => return Err(ValidationError::UnknownTransport(transport.to_string())),
+    }
+    c.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check(addr: &str, transport: &str) -> Result<(), ValidationError> {
+        addr.parse::<Multiaddr>()
+            .unwrap()
+            .validate_for_transport(transport)
+    }
+
+    #[test]
+    fn validate_transport_stacks() {
+        assert_eq!(check("/ip4/127.0.0.1/tcp/80", "tcp"), Ok(()));
+        assert_eq!(
+            check("/ip4/127.0.0.1/udp/80", "tcp"),
+            Err(ValidationError::Unexpected {
+                expected: "tcp",
+                found: "udp"
+            })
+        );
+        assert_eq!(
+            check("/ip6/::1/udp/80", "quic"),
+            Err(ValidationError::Missing("quic"))
+        );
+        assert_eq!(
+            check("/dns/example.com/tcp/443/tls/sni/example.com/ws", "ws"),
+            Ok(())
+        );
+        assert_eq!(
+            check("/ip4/127.0.0.1/tcp/80/ws/http", "ws"),
+            Err(ValidationError::Trailing("http"))
+        );
+        assert!(matches!(
+            check("/memory/1", "bluetooth"),
+            Err(ValidationError::UnknownTransport(_))
+        ));
+    }
+}
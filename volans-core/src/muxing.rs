@@ -1,4 +1,5 @@
 mod boxed;
+mod choice;
 
 use futures::{AsyncRead, AsyncWrite};
 use std::{
@@ -7,6 +8,7 @@ use std::{
 };
 
 pub use boxed::{StreamMuxerBox, SubstreamBox};
+pub use choice::MuxerChoice;
 
 pub trait StreamMuxer {
     type Substream: AsyncRead + AsyncWrite;
@@ -29,6 +31,28 @@ pub trait StreamMuxer {
 
     /// Poll 多路复用器事件
     fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>>;
+
+    /// 多路复用器使用的协议名，自带多路复用的传输返回 `None`
+    fn protocol(&self) -> Option<&'static str> {
+        None
+    }
+
+    /// 因入站缓冲区已满被丢弃的子流数量
+    fn dropped_inbound_streams(&self) -> u64 {
+        0
+    }
+}
+
+/// 入站子流缓冲区已满时的处理方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum InboundOverflow {
+    /// 丢弃新的子流，计入 [`StreamMuxer::dropped_inbound_streams`]
+    #[default]
+    Drop,
+    /// 暂停读取连接，直到缓冲的子流被取走
+    ///
+    /// 期间整个连接的数据都不再被读取，已打开的子流同样会停顿。
+    Backpressure,
 }
 
 pub trait StreamMuxerExt: StreamMuxer + Sized {
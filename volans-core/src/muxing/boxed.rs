@@ -65,6 +65,14 @@ where
     fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
         self.project().inner.poll(cx).map_err(into_io_error)
     }
+
+    fn protocol(&self) -> Option<&'static str> {
+        self.inner.protocol()
+    }
+
+    fn dropped_inbound_streams(&self) -> u64 {
+        self.inner.dropped_inbound_streams()
+    }
 }
 
 impl StreamMuxer for StreamMuxerBox {
@@ -92,6 +100,14 @@ impl StreamMuxer for StreamMuxerBox {
     fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
         self.get_mut().inner.as_mut().poll(cx)
     }
+
+    fn protocol(&self) -> Option<&'static str> {
+        self.inner.protocol()
+    }
+
+    fn dropped_inbound_streams(&self) -> u64 {
+        self.inner.dropped_inbound_streams()
+    }
 }
 
 fn into_io_error<E>(err: E) -> io::Error
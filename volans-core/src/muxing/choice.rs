@@ -0,0 +1,121 @@
+use std::{
+    io,
+    iter::{Chain, Map},
+};
+
+use either::Either;
+use futures::{FutureExt, TryFutureExt, future::BoxFuture};
+
+use crate::{
+    StreamMuxer,
+    muxing::StreamMuxerBox,
+    upgrade::{InboundConnectionUpgrade, OutboundConnectionUpgrade, UpgradeInfo},
+};
+
+/// 在协商时同时提供两种多路复用协议，按对端选中的协议升级
+///
+/// 协议按 `first`、`second` 的顺序提供，结果统一装箱为 [`StreamMuxerBox`]。
+#[derive(Debug, Clone)]
+pub struct MuxerChoice<A, B> {
+    first: A,
+    second: B,
+}
+
+impl<A, B> MuxerChoice<A, B> {
+    pub fn new(first: A, second: B) -> Self {
+        MuxerChoice { first, second }
+    }
+}
+
+impl<A, B> UpgradeInfo for MuxerChoice<A, B>
+where
+    A: UpgradeInfo,
+    B: UpgradeInfo,
+{
+    type Info = Either<A::Info, B::Info>;
+    type InfoIter = Chain<
+        Map<<A::InfoIter as IntoIterator>::IntoIter, fn(A::Info) -> Self::Info>,
+        Map<<B::InfoIter as IntoIterator>::IntoIter, fn(B::Info) -> Self::Info>,
+    >;
+
+    fn protocol_info(&self) -> Self::InfoIter {
+        let first = self
+            .first
+            .protocol_info()
+            .map(Either::Left as fn(A::Info) -> _);
+        let second = self
+            .second
+            .protocol_info()
+            .map(Either::Right as fn(B::Info) -> _);
+
+        first.chain(second)
+    }
+}
+
+impl<C, A, B, MA, MB> InboundConnectionUpgrade<C> for MuxerChoice<A, B>
+where
+    A: InboundConnectionUpgrade<C, Output = MA>,
+    A::Future: Send + 'static,
+    A::Error: std::error::Error + Send + Sync + 'static,
+    B: InboundConnectionUpgrade<C, Output = MB>,
+    B::Future: Send + 'static,
+    B::Error: std::error::Error + Send + Sync + 'static,
+    MA: StreamMuxer + Send + 'static,
+    MA::Substream: Send + 'static,
+    MA::Error: Send + Sync + 'static,
+    MB: StreamMuxer + Send + 'static,
+    MB::Substream: Send + 'static,
+    MB::Error: Send + Sync + 'static,
+{
+    type Output = StreamMuxerBox;
+    type Error = io::Error;
+    type Future = BoxFuture<'static, Result<Self::Output, Self::Error>>;
+
+    fn upgrade_inbound(self, socket: C, info: Self::Info) -> Self::Future {
+        match info {
+            Either::Left(info) => boxed(self.first.upgrade_inbound(socket, info)),
+            Either::Right(info) => boxed(self.second.upgrade_inbound(socket, info)),
+        }
+    }
+}
+
+impl<C, A, B, MA, MB> OutboundConnectionUpgrade<C> for MuxerChoice<A, B>
+where
+    A: OutboundConnectionUpgrade<C, Output = MA>,
+    A::Future: Send + 'static,
+    A::Error: std::error::Error + Send + Sync + 'static,
+    B: OutboundConnectionUpgrade<C, Output = MB>,
+    B::Future: Send + 'static,
+    B::Error: std::error::Error + Send + Sync + 'static,
+    MA: StreamMuxer + Send + 'static,
+    MA::Substream: Send + 'static,
+    MA::Error: Send + Sync + 'static,
+    MB: StreamMuxer + Send + 'static,
+    MB::Substream: Send + 'static,
+    MB::Error: Send + Sync + 'static,
+{
+    type Output = StreamMuxerBox;
+    type Error = io::Error;
+    type Future = BoxFuture<'static, Result<Self::Output, Self::Error>>;
+
+    fn upgrade_outbound(self, socket: C, info: Self::Info) -> Self::Future {
+        match info {
+            Either::Left(info) => boxed(self.first.upgrade_outbound(socket, info)),
+            Either::Right(info) => boxed(self.second.upgrade_outbound(socket, info)),
+        }
+    }
+}
+
+fn boxed<F, M, E>(upgrade: F) -> BoxFuture<'static, Result<StreamMuxerBox, io::Error>>
+where
+    F: Future<Output = Result<M, E>> + Send + 'static,
+    M: StreamMuxer + Send + 'static,
+    M::Substream: Send + 'static,
+    M::Error: Send + Sync + 'static,
+    E: std::error::Error + Send + Sync + 'static,
+{
+    upgrade
+        .map_ok(StreamMuxerBox::new)
+        .map_err(io::Error::other)
+        .boxed()
+}
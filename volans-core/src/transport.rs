@@ -3,12 +3,15 @@ pub(crate) mod boxed;
 pub mod and_then;
 pub mod apply;
 pub mod choice;
+pub mod dns;
 pub mod map;
 pub mod map_err;
+pub mod or;
 pub mod timeout;
 pub mod upgrade;
 
 pub use boxed::{Boxed, BoxedListener};
+pub use or::OrTransport;
 
 use futures::TryFuture;
 
@@ -44,7 +47,11 @@ pub trait Transport {
     type Listener: Listener<Output = Self::Output, Error = Self::Error, Upgrade = Self::Incoming>;
 
     fn dial(&self, addr: Multiaddr) -> Result<Self::Dial, TransportError<Self::Error>>;
-    fn listen(&self, addr: Multiaddr) -> Result<Self::Listener, TransportError<Self::Error>>;
+    fn listen(
+        &self,
+        addr: Multiaddr,
+        opts: &SocketOptions,
+    ) -> Result<Self::Listener, TransportError<Self::Error>>;
 
     fn and_then<D, TMap, TMapFut>(self, map: TMap) -> and_then::AndThen<Self, TMap>
     where
@@ -92,6 +99,15 @@ pub trait Transport {
         choice::Choice::new(self, other)
     }
 
+    /// 与输出类型相同的传输层组合，如两个已升级为 `(PeerId, StreamMuxerBox)` 的传输层
+    fn or_transport<B>(self, other: B) -> or::OrTransport<Self, B>
+    where
+        Self: Sized,
+        B: Transport<Output = Self::Output>,
+    {
+        or::OrTransport::new(self, other)
+    }
+
     fn timeout(self, timeout: Duration) -> timeout::Timeout<Self>
     where
         Self: Sized,
@@ -119,6 +135,72 @@ pub trait Transport {
     }
 }
 
+/// 单个监听器的套接字选项，未设置的项沿用传输层自身的配置
+///
+/// 不涉及对应选项的传输层忽略这些设置。
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SocketOptions {
+    backlog: Option<u32>,
+    nodelay: Option<bool>,
+    ttl: Option<u32>,
+    only_v6: Option<bool>,
+    bind_device: Option<String>,
+}
+
+impl SocketOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 等待接受的连接队列长度
+    pub fn with_backlog(mut self, backlog: u32) -> Self {
+        self.backlog = Some(backlog);
+        self
+    }
+
+    pub fn with_nodelay(mut self, nodelay: bool) -> Self {
+        self.nodelay = Some(nodelay);
+        self
+    }
+
+    pub fn with_ttl(mut self, ttl: u32) -> Self {
+        self.ttl = Some(ttl);
+        self
+    }
+
+    /// IPv6 套接字是否只接受 IPv6 连接
+    pub fn with_only_v6(mut self, only_v6: bool) -> Self {
+        self.only_v6 = Some(only_v6);
+        self
+    }
+
+    /// 绑定到指定网络接口，如 `eth0`
+    pub fn with_bind_device(mut self, device: impl Into<String>) -> Self {
+        self.bind_device = Some(device.into());
+        self
+    }
+
+    pub fn backlog(&self) -> Option<u32> {
+        self.backlog
+    }
+
+    pub fn nodelay(&self) -> Option<bool> {
+        self.nodelay
+    }
+
+    pub fn ttl(&self) -> Option<u32> {
+        self.ttl
+    }
+
+    pub fn only_v6(&self) -> Option<bool> {
+        self.only_v6
+    }
+
+    pub fn bind_device(&self) -> Option<&str> {
+        self.bind_device.as_deref()
+    }
+}
+
 pub enum ListenerEvent<TUpgr, TErr> {
     NewAddress(Multiaddr),
     AddressExpired(Multiaddr),
@@ -129,6 +211,8 @@ pub enum ListenerEvent<TUpgr, TErr> {
     },
     Closed(Result<(), TErr>),
     Error(TErr),
+    /// 监听套接字失效后重新绑定成功，携带恢复后的监听地址
+    Rebound(Multiaddr),
 }
 
 impl<TUpgr, TErr> ListenerEvent<TUpgr, TErr> {
@@ -139,6 +223,7 @@ impl<TUpgr, TErr> ListenerEvent<TUpgr, TErr> {
         match self {
             ListenerEvent::NewAddress(addr) => ListenerEvent::NewAddress(addr),
             ListenerEvent::AddressExpired(addr) => ListenerEvent::AddressExpired(addr),
+            ListenerEvent::Rebound(addr) => ListenerEvent::Rebound(addr),
             ListenerEvent::Incoming {
                 local_addr,
                 remote_addr,
@@ -160,6 +245,7 @@ impl<TUpgr, TErr> ListenerEvent<TUpgr, TErr> {
         match self {
             ListenerEvent::NewAddress(addr) => ListenerEvent::NewAddress(addr),
             ListenerEvent::AddressExpired(addr) => ListenerEvent::AddressExpired(addr),
+            ListenerEvent::Rebound(addr) => ListenerEvent::Rebound(addr),
             ListenerEvent::Incoming {
                 local_addr,
                 remote_addr,
@@ -188,6 +274,17 @@ impl<TErr> From<TErr> for TransportError<TErr> {
 }
 
 impl<TErr> TransportError<TErr> {
+    /// 地址不受传输层支持，通过 [`Multiaddr::validate_for_transport`] 记录具体原因
+    pub fn not_supported(addr: Multiaddr, transport: &str) -> Self {
+        match addr.validate_for_transport(transport) {
+            Ok(()) => tracing::debug!(%addr, "Address not supported by {transport} transport"),
+            Err(reason) => {
+                tracing::debug!(%addr, %reason, "Address not supported by {transport} transport")
+            }
+        }
+        TransportError::NotSupported(addr)
+    }
+
     pub fn map<E, F>(self, map: F) -> TransportError<E>
     where
         F: FnOnce(TErr) -> E,
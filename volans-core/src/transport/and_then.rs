@@ -7,7 +7,9 @@ use std::{
 use either::Either;
 use futures::TryFuture;
 
-use crate::{ConnectedPoint, Listener, ListenerEvent, Multiaddr, Transport, TransportError};
+use crate::{
+    ConnectedPoint, Listener, ListenerEvent, Multiaddr, SocketOptions, Transport, TransportError,
+};
 
 #[derive(Debug, Copy, Clone)]
 pub struct AndThen<T, TMap> {
@@ -47,8 +49,12 @@ where
         }
     }
 
-    fn listen(&self, addr: Multiaddr) -> Result<Self::Listener, TransportError<Self::Error>> {
-        match self.transport.listen(addr) {
+    fn listen(
+        &self,
+        addr: Multiaddr,
+        opts: &SocketOptions,
+    ) -> Result<Self::Listener, TransportError<Self::Error>> {
+        match self.transport.listen(addr, opts) {
             Ok(listener) => Ok(AndThenListener {
                 inner: listener,
                 map: self.map.clone(),
@@ -114,6 +120,7 @@ where
             ListenerEvent::Closed(cause) => ListenerEvent::Closed(cause.map_err(Either::Left)),
             ListenerEvent::NewAddress(addr) => ListenerEvent::NewAddress(addr),
             ListenerEvent::AddressExpired(addr) => ListenerEvent::AddressExpired(addr),
+            ListenerEvent::Rebound(addr) => ListenerEvent::Rebound(addr),
             ListenerEvent::Error(err) => ListenerEvent::Error(Either::Left(err)),
         })
     }
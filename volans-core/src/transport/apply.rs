@@ -7,7 +7,7 @@ use std::{
 use futures::{AsyncRead, AsyncWrite, TryFuture, future, ready};
 
 use crate::{
-    Listener, ListenerEvent, Multiaddr, Negotiated, Transport, TransportError,
+    Listener, ListenerEvent, Multiaddr, Negotiated, SocketOptions, Transport, TransportError,
     upgrade::{
         InboundConnectionUpgrade, InboundUpgradeApply, OutboundConnectionUpgrade,
         OutboundUpgradeApply, UpgradeError,
@@ -52,10 +52,14 @@ where
         })
     }
 
-    fn listen(&self, addr: Multiaddr) -> Result<Self::Listener, TransportError<Self::Error>> {
+    fn listen(
+        &self,
+        addr: Multiaddr,
+        opts: &SocketOptions,
+    ) -> Result<Self::Listener, TransportError<Self::Error>> {
         let inner = self
             .transport
-            .listen(addr)
+            .listen(addr, opts)
             .map_err(|e| e.map(UpgradeApplyError::Transport))?;
 
         Ok(UpgradeApplyListener {
@@ -6,11 +6,15 @@ use std::{
 
 use futures::{TryFutureExt, ready};
 
-use crate::{Listener, ListenerEvent, Multiaddr, Transport, TransportError};
+use crate::{Listener, ListenerEvent, Multiaddr, SocketOptions, Transport, TransportError};
 
 trait Abstract<O> {
     fn dial(&self, addr: Multiaddr) -> Result<BoxedUpgrade<O>, TransportError<io::Error>>;
-    fn listen(&self, addr: Multiaddr) -> Result<BoxedListener<O>, TransportError<io::Error>>;
+    fn listen(
+        &self,
+        addr: Multiaddr,
+        opts: &SocketOptions,
+    ) -> Result<BoxedListener<O>, TransportError<io::Error>>;
 }
 
 impl<T, O> Abstract<O> for T
@@ -24,12 +28,16 @@ where
     fn dial(&self, addr: Multiaddr) -> Result<BoxedUpgrade<O>, TransportError<io::Error>> {
         let fut = Transport::dial(self, addr)
             .map_err(|e| e.map(box_err))?
-            .map_err(|e| box_err(e));
+            .map_err(box_err);
         Ok(Box::pin(fut) as BoxedUpgrade<O>)
     }
 
-    fn listen(&self, addr: Multiaddr) -> Result<BoxedListener<O>, TransportError<io::Error>> {
-        let listener = Transport::listen(self, addr).map_err(|e| e.map(box_err))?;
+    fn listen(
+        &self,
+        addr: Multiaddr,
+        opts: &SocketOptions,
+    ) -> Result<BoxedListener<O>, TransportError<io::Error>> {
+        let listener = Transport::listen(self, addr, opts).map_err(|e| e.map(box_err))?;
 
         Ok(BoxedListener {
             inner: Box::pin(ListenerSendWrapper::new(listener)),
@@ -111,8 +119,12 @@ impl<O> Transport for Boxed<O> {
         self.inner.dial(addr)
     }
 
-    fn listen(&self, addr: Multiaddr) -> Result<Self::Listener, TransportError<Self::Error>> {
-        self.inner.listen(addr)
+    fn listen(
+        &self,
+        addr: Multiaddr,
+        opts: &SocketOptions,
+    ) -> Result<Self::Listener, TransportError<Self::Error>> {
+        self.inner.listen(addr, opts)
     }
 }
 
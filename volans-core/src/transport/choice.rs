@@ -6,7 +6,7 @@ use std::{
 use either::Either;
 use futures::{TryFuture, future};
 
-use crate::{Listener, ListenerEvent, Multiaddr, Transport, TransportError};
+use crate::{Listener, ListenerEvent, Multiaddr, SocketOptions, Transport, TransportError};
 
 #[derive(Debug, Copy, Clone)]
 pub struct Choice<A, B> {
@@ -51,8 +51,12 @@ where
         }
     }
 
-    fn listen(&self, addr: Multiaddr) -> Result<Self::Listener, TransportError<Self::Error>> {
-        match self.first.listen(addr.clone()) {
+    fn listen(
+        &self,
+        addr: Multiaddr,
+        opts: &SocketOptions,
+    ) -> Result<Self::Listener, TransportError<Self::Error>> {
+        match self.first.listen(addr.clone(), opts) {
             Ok(listener) => return Ok(ChoiceListener::Left(listener)),
             Err(TransportError::Other(err)) => {
                 return Err(TransportError::Other(Either::Left(err)));
@@ -64,7 +68,7 @@ where
                 );
             }
         }
-        match self.second.listen(addr) {
+        match self.second.listen(addr, opts) {
             Ok(listener) => Ok(ChoiceListener::Right(listener)),
             Err(err) => Err(err.map(Either::Right)),
         }
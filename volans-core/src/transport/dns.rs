@@ -0,0 +1,63 @@
+//! 域名解析器
+//!
+//! 解析 `/dns` 地址的传输层默认使用自身配置的解析器，拨号时可通过 [`scoped`]
+//! 为单次拨号指定其他解析器。
+
+use std::{cell::RefCell, fmt, io, net::IpAddr, sync::Arc};
+
+use futures::{FutureExt, future::BoxFuture};
+
+thread_local! {
+    static CURRENT_RESOLVER: RefCell<Option<Arc<dyn Resolver>>> = const { RefCell::new(None) };
+}
+
+/// 域名解析器
+pub trait Resolver: Send + Sync + 'static {
+    /// 解析域名对应的全部 IP 地址
+    fn lookup_ip(&self, name: &str) -> BoxFuture<'static, io::Result<Vec<IpAddr>>>;
+
+    /// 查询 SRV 记录，按优先顺序返回目标主机与端口，默认不支持
+    fn lookup_srv(&self, name: &str) -> BoxFuture<'static, io::Result<Vec<SrvTarget>>> {
+        let error = io::Error::new(
+            io::ErrorKind::Unsupported,
+            format!("SRV lookup not supported for {name}"),
+        );
+        futures::future::ready(Err(error)).boxed()
+    }
+}
+
+impl fmt::Debug for dyn Resolver {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Resolver")
+    }
+}
+
+/// SRV 记录指向的目标
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SrvTarget {
+    /// 目标主机，可以是域名或 IP 地址
+    pub host: String,
+    pub port: u16,
+}
+
+impl SrvTarget {
+    pub fn new(host: impl Into<String>, port: u16) -> Self {
+        Self {
+            host: host.into(),
+            port,
+        }
+    }
+}
+
+/// 在 `f` 中发起的拨号使用 `resolver`，为 `None` 时使用传输层自身的解析器
+pub fn scoped<T>(resolver: Option<Arc<dyn Resolver>>, f: impl FnOnce() -> T) -> T {
+    let previous = CURRENT_RESOLVER.with(|current| current.replace(resolver));
+    let output = f();
+    CURRENT_RESOLVER.with(|current| *current.borrow_mut() = previous);
+    output
+}
+
+/// 当前拨号指定的解析器
+pub fn current() -> Option<Arc<dyn Resolver>> {
+    CURRENT_RESOLVER.with(|current| current.borrow().clone())
+}
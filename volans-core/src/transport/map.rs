@@ -1,4 +1,6 @@
-use crate::{ConnectedPoint, Listener, ListenerEvent, Multiaddr, Transport, TransportError};
+use crate::{
+    ConnectedPoint, Listener, ListenerEvent, Multiaddr, SocketOptions, Transport, TransportError,
+};
 use futures::TryFuture;
 use std::{
     marker::PhantomData,
@@ -43,8 +45,12 @@ where
         }
     }
 
-    fn listen(&self, addr: Multiaddr) -> Result<Self::Listener, TransportError<Self::Error>> {
-        match self.transport.listen(addr) {
+    fn listen(
+        &self,
+        addr: Multiaddr,
+        opts: &SocketOptions,
+    ) -> Result<Self::Listener, TransportError<Self::Error>> {
+        match self.transport.listen(addr, opts) {
             Ok(listener) => Ok(MapListener {
                 inner: listener,
                 fun: self.map.clone(),
@@ -108,6 +114,7 @@ where
             ListenerEvent::Closed(cause) => ListenerEvent::Closed(cause),
             ListenerEvent::NewAddress(addr) => ListenerEvent::NewAddress(addr),
             ListenerEvent::AddressExpired(addr) => ListenerEvent::AddressExpired(addr),
+            ListenerEvent::Rebound(addr) => ListenerEvent::Rebound(addr),
             ListenerEvent::Error(err) => ListenerEvent::Error(err),
         })
     }
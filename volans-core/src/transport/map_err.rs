@@ -3,7 +3,7 @@ use std::{
     task::{Context, Poll},
 };
 
-use crate::{Listener, ListenerEvent, Multiaddr, Transport, TransportError};
+use crate::{Listener, ListenerEvent, Multiaddr, SocketOptions, Transport, TransportError};
 
 #[derive(Debug, Copy, Clone)]
 pub struct MapErr<T, F> {
@@ -41,9 +41,13 @@ where
         }
     }
 
-    fn listen(&self, addr: Multiaddr) -> Result<Self::Listener, TransportError<Self::Error>> {
+    fn listen(
+        &self,
+        addr: Multiaddr,
+        opts: &SocketOptions,
+    ) -> Result<Self::Listener, TransportError<Self::Error>> {
         let map = self.map.clone();
-        match self.transport.listen(addr) {
+        match self.transport.listen(addr, opts) {
             Ok(listener) => Ok(MapErrListener {
                 inner: listener,
                 map,
@@ -0,0 +1,202 @@
+use std::{
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use either::Either;
+use futures::TryFuture;
+
+use crate::{Listener, ListenerEvent, Multiaddr, SocketOptions, Transport, TransportError};
+
+/// 输出类型相同的两个传输层组合，输出不包装为 `Either`
+///
+/// 地址依次交给两个传输层，第一个返回 [`TransportError::NotSupported`] 时使用第二个。
+#[derive(Debug, Copy, Clone)]
+pub struct OrTransport<A, B> {
+    first: A,
+    second: B,
+}
+
+impl<A, B> OrTransport<A, B> {
+    pub(crate) fn new(first: A, second: B) -> Self {
+        Self { first, second }
+    }
+}
+
+impl<A, B> Transport for OrTransport<A, B>
+where
+    A: Transport,
+    B: Transport<Output = A::Output>,
+{
+    type Output = A::Output;
+    type Error = Either<A::Error, B::Error>;
+    type Dial = OrFuture<A::Dial, B::Dial>;
+    type Incoming = OrFuture<A::Incoming, B::Incoming>;
+    type Listener = OrListener<A, B>;
+
+    fn dial(&self, addr: Multiaddr) -> Result<Self::Dial, TransportError<Self::Error>> {
+        let addr = match self.first.dial(addr) {
+            Ok(dial) => return Ok(OrFuture::First(dial)),
+            Err(TransportError::NotSupported(addr)) => addr,
+            Err(err) => return Err(err.map(Either::Left)),
+        };
+        tracing::trace!(address=%addr, "First transport not supported, trying second");
+        self.second
+            .dial(addr)
+            .map(OrFuture::Second)
+            .map_err(|err| err.map(Either::Right))
+    }
+
+    fn listen(
+        &self,
+        addr: Multiaddr,
+        opts: &SocketOptions,
+    ) -> Result<Self::Listener, TransportError<Self::Error>> {
+        let addr = match self.first.listen(addr, opts) {
+            Ok(listener) => return Ok(OrListener::First(listener)),
+            Err(TransportError::NotSupported(addr)) => addr,
+            Err(err) => return Err(err.map(Either::Left)),
+        };
+        tracing::trace!(address=%addr, "First transport not supported, trying second");
+        self.second
+            .listen(addr, opts)
+            .map(OrListener::Second)
+            .map_err(|err| err.map(Either::Right))
+    }
+}
+
+#[pin_project::pin_project(project = OrListenerProj)]
+pub enum OrListener<A, B>
+where
+    A: Transport,
+    B: Transport,
+{
+    First(#[pin] A::Listener),
+    Second(#[pin] B::Listener),
+}
+
+impl<A, B> Listener for OrListener<A, B>
+where
+    A: Transport,
+    B: Transport<Output = A::Output>,
+{
+    type Output = A::Output;
+    type Error = Either<A::Error, B::Error>;
+    type Upgrade = OrFuture<A::Incoming, B::Incoming>;
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        match self.project() {
+            OrListenerProj::First(first) => first.poll_close(cx).map_err(Either::Left),
+            OrListenerProj::Second(second) => second.poll_close(cx).map_err(Either::Right),
+        }
+    }
+
+    fn poll_event(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<ListenerEvent<Self::Upgrade, Self::Error>> {
+        match self.project() {
+            OrListenerProj::First(first) => first
+                .poll_event(cx)
+                .map(|event| event.map_upgrade(OrFuture::First).map_err(Either::Left)),
+            OrListenerProj::Second(second) => second
+                .poll_event(cx)
+                .map(|event| event.map_upgrade(OrFuture::Second).map_err(Either::Right)),
+        }
+    }
+}
+
+#[derive(Debug)]
+#[pin_project::pin_project(project = OrFutureProj)]
+pub enum OrFuture<TFut1, TFut2> {
+    First(#[pin] TFut1),
+    Second(#[pin] TFut2),
+}
+
+impl<TFut1, TFut2, T, EA, EB> Future for OrFuture<TFut1, TFut2>
+where
+    TFut1: TryFuture<Ok = T, Error = EA>,
+    TFut2: TryFuture<Ok = T, Error = EB>,
+{
+    type Output = Result<T, Either<EA, EB>>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        match self.project() {
+            OrFutureProj::First(fut) => TryFuture::try_poll(fut, cx).map_err(Either::Left),
+            OrFutureProj::Second(fut) => TryFuture::try_poll(fut, cx).map_err(Either::Right),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+
+    use futures::{
+        FutureExt,
+        future::{self, Ready},
+    };
+
+    use super::*;
+    use crate::multiaddr::Protocol;
+
+    /// 只支持包含指定协议的地址，拨号结果为自身名称
+    struct Only(&'static str, Protocol<'static>);
+
+    struct NoListener;
+
+    impl Listener for NoListener {
+        type Output = &'static str;
+        type Error = io::Error;
+        type Upgrade = Ready<Result<&'static str, io::Error>>;
+
+        fn poll_event(
+            self: Pin<&mut Self>,
+            _: &mut Context<'_>,
+        ) -> Poll<ListenerEvent<Self::Upgrade, Self::Error>> {
+            Poll::Pending
+        }
+
+        fn poll_close(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    impl Transport for Only {
+        type Output = &'static str;
+        type Error = io::Error;
+        type Dial = Ready<Result<&'static str, io::Error>>;
+        type Incoming = Ready<Result<&'static str, io::Error>>;
+        type Listener = NoListener;
+
+        fn dial(&self, addr: Multiaddr) -> Result<Self::Dial, TransportError<Self::Error>> {
+            if addr.iter().any(|p| p == self.1) {
+                Ok(future::ok(self.0))
+            } else {
+                Err(TransportError::NotSupported(addr))
+            }
+        }
+
+        fn listen(
+            &self,
+            addr: Multiaddr,
+            _: &SocketOptions,
+        ) -> Result<Self::Listener, TransportError<Self::Error>> {
+            Err(TransportError::NotSupported(addr))
+        }
+    }
+
+    #[test]
+    fn dial_by_address() {
+        let transport = Only("tcp", Protocol::Tcp(1)).or_transport(Only("ws", Protocol::Ws));
+
+        let tcp = transport.dial("/ip4/127.0.0.1/tcp/1".parse().unwrap());
+        assert_eq!(tcp.unwrap().now_or_never().unwrap().unwrap(), "tcp");
+        let ws = transport.dial("/ip4/127.0.0.1/tcp/2/ws".parse().unwrap());
+        assert_eq!(ws.unwrap().now_or_never().unwrap().unwrap(), "ws");
+        assert!(matches!(
+            transport.dial("/ip4/127.0.0.1/udp/1".parse().unwrap()),
+            Err(TransportError::NotSupported(_))
+        ));
+    }
+}
@@ -9,7 +9,7 @@ use std::{
 use futures::TryFuture;
 use futures_timer::Delay;
 
-use crate::{Listener, Multiaddr, Transport, TransportError};
+use crate::{Listener, Multiaddr, SocketOptions, Transport, TransportError};
 
 #[derive(Debug, Clone)]
 pub struct Timeout<T> {
@@ -68,10 +68,14 @@ where
         })
     }
 
-    fn listen(&self, addr: Multiaddr) -> Result<Self::Listener, TransportError<Self::Error>> {
+    fn listen(
+        &self,
+        addr: Multiaddr,
+        opts: &SocketOptions,
+    ) -> Result<Self::Listener, TransportError<Self::Error>> {
         let listener = self
             .inner
-            .listen(addr)
+            .listen(addr, opts)
             .map_err(|e| e.map(TimeoutError::Other))?;
         Ok(TimeoutListener {
             inner: listener,
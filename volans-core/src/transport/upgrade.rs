@@ -13,8 +13,8 @@ use std::{
 use futures::{AsyncRead, AsyncWrite, TryFuture, future, ready};
 
 use crate::{
-    ConnectedPoint, Listener, ListenerEvent, Multiaddr, Negotiated, PeerId, Transport,
-    TransportError,
+    ConnectedPoint, Listener, ListenerEvent, Multiaddr, Negotiated, PeerId, SocketOptions,
+    Transport, TransportError,
     transport::{and_then::AndThen, apply::UpgradeApplyError},
     upgrade::{
         self, InboundConnectionUpgrade, InboundUpgradeApply, OutboundConnectionUpgrade,
@@ -42,6 +42,7 @@ where
     ///
     ///   * I/O 升级: `C -> (PeerId, D)`.
     ///   * Transport 输出: `C -> (PeerId, D)`
+    #[allow(clippy::type_complexity)]
     pub fn authenticate<C, D, U, E>(
         self,
         upgrade: U,
@@ -97,10 +98,14 @@ where
         })
     }
 
-    fn listen(&self, addr: Multiaddr) -> Result<Self::Listener, TransportError<Self::Error>> {
+    fn listen(
+        &self,
+        addr: Multiaddr,
+        opts: &SocketOptions,
+    ) -> Result<Self::Listener, TransportError<Self::Error>> {
         let inner = self
             .inner
-            .listen(addr)
+            .listen(addr, opts)
             .map_err(|e| e.map(UpgradeApplyError::Transport))?;
 
         Ok(UpgradeListener {
@@ -21,6 +21,7 @@ use crate::{
 pub struct Authenticated<T>(T);
 
 impl<T> Authenticated<T> {
+    #[allow(clippy::type_complexity)]
     pub fn authenticate<C, D, U, E>(
         transport: T,
         upgrade: U,
@@ -50,6 +51,7 @@ impl<T> Authenticated<T> {
         Authenticated(Upgrade::new(self.0, upgrade))
     }
 
+    #[allow(clippy::type_complexity)]
     pub fn multiplex<C, M, U, E>(
         self,
         upgrade: U,
@@ -6,7 +6,8 @@ use std::{
 use futures::{AsyncRead, AsyncWrite, future, ready};
 
 use crate::{
-    ConnectedPoint, Multiaddr, Negotiated, PeerId, StreamMuxer, Transport, TransportError,
+    ConnectedPoint, Multiaddr, Negotiated, PeerId, SocketOptions, StreamMuxer, Transport,
+    TransportError,
     muxing::StreamMuxerBox,
     transport::{Boxed, and_then::AndThen, boxed::boxed},
     upgrade::{
@@ -19,6 +20,7 @@ use crate::{
 pub struct Multiplexed<T>(T);
 
 impl<T> Multiplexed<T> {
+    #[allow(clippy::type_complexity)]
     pub fn multiplex<C, M, U, E>(
         transport: T,
         upgrade: U,
@@ -68,8 +70,12 @@ where
     fn dial(&self, addr: Multiaddr) -> Result<Self::Dial, TransportError<Self::Error>> {
         self.0.dial(addr)
     }
-    fn listen(&self, addr: Multiaddr) -> Result<Self::Listener, TransportError<Self::Error>> {
-        self.0.listen(addr)
+    fn listen(
+        &self,
+        addr: Multiaddr,
+        opts: &SocketOptions,
+    ) -> Result<Self::Listener, TransportError<Self::Error>> {
+        self.0.listen(addr, opts)
     }
 }
 
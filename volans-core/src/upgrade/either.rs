@@ -21,8 +21,8 @@ where
 
     fn protocol_info(&self) -> Self::InfoIter {
         match self {
-            Either::Left(a) => Either::Left(a.protocol_info().into_iter().map(Either::Left)),
-            Either::Right(b) => Either::Right(b.protocol_info().into_iter().map(Either::Right)),
+            Either::Left(a) => Either::Left(a.protocol_info().map(Either::Left)),
+            Either::Right(b) => Either::Right(b.protocol_info().map(Either::Right)),
         }
     }
 }
@@ -29,15 +29,10 @@ where
     >;
 
     fn protocol_info(&self) -> Self::InfoIter {
-        let a = self
-            .0
-            .protocol_info()
-            .into_iter()
-            .map(Either::Left as fn(A::Info) -> _);
+        let a = self.0.protocol_info().map(Either::Left as fn(A::Info) -> _);
         let b = self
             .1
             .protocol_info()
-            .into_iter()
             .map(Either::Right as fn(B::Info) -> _);
 
         a.chain(b)
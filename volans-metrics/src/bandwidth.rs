@@ -0,0 +1,136 @@
+use std::{
+    io,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use futures::{AsyncRead, AsyncWrite, ready};
+use prometheus_client::{
+    encoding::{EncodeLabelSet, EncodeLabelValue},
+    metrics::{counter::Counter, family::Family},
+    registry::{Registry, Unit},
+};
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq, EncodeLabelSet)]
+struct Labels {
+    transport: String,
+    direction: Direction,
+}
+
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, EncodeLabelValue)]
+enum Direction {
+    Inbound,
+    Outbound,
+}
+
+pub(crate) struct Metrics {
+    bytes: Family<Labels, Counter>,
+}
+
+impl Metrics {
+    pub(crate) fn new(registry: &mut Registry) -> Self {
+        let bytes = Family::default();
+        registry.register_with_unit(
+            "bandwidth",
+            "Bandwidth usage by transport and direction",
+            Unit::Bytes,
+            bytes.clone(),
+        );
+        Self { bytes }
+    }
+
+    pub(crate) fn for_transport(&self, transport: String) -> Bandwidth {
+        let counter = |direction| {
+            self.bytes
+                .get_or_create(&Labels {
+                    transport: transport.clone(),
+                    direction,
+                })
+                .clone()
+        };
+        Bandwidth {
+            inbound: counter(Direction::Inbound),
+            outbound: counter(Direction::Outbound),
+        }
+    }
+}
+
+/// 某个传输层的字节计数
+#[derive(Debug, Clone)]
+pub struct Bandwidth {
+    inbound: Counter,
+    outbound: Counter,
+}
+
+impl Bandwidth {
+    pub fn wrap<C>(&self, inner: C) -> Metered<C> {
+        Metered {
+            inner,
+            bandwidth: self.clone(),
+        }
+    }
+}
+
+/// 统计读写字节数的连接
+#[pin_project::pin_project]
+#[derive(Debug)]
+pub struct Metered<C> {
+    #[pin]
+    inner: C,
+    bandwidth: Bandwidth,
+}
+
+impl<C: AsyncRead> AsyncRead for Metered<C> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.project();
+        let n = ready!(this.inner.poll_read(cx, buf))?;
+        this.bandwidth.inbound.inc_by(n as u64);
+        Poll::Ready(Ok(n))
+    }
+}
+
+impl<C: AsyncWrite> AsyncWrite for Metered<C> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.project();
+        let n = ready!(this.inner.poll_write(cx, buf))?;
+        this.bandwidth.outbound.inc_by(n as u64);
+        Poll::Ready(Ok(n))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.project().inner.poll_flush(cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.project().inner.poll_close(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::{AsyncReadExt, AsyncWriteExt, executor::block_on, io::Cursor};
+
+    use super::*;
+
+    #[test]
+    fn counts_read_and_written_bytes() {
+        let metrics = Metrics::new(&mut Registry::default());
+        let bandwidth = metrics.for_transport("memory".to_string());
+        let mut conn = bandwidth.wrap(Cursor::new(vec![0u8; 8]));
+        block_on(async {
+            let mut buf = [0u8; 5];
+            conn.read_exact(&mut buf).await.unwrap();
+            conn.write_all(b"abc").await.unwrap();
+        });
+        assert_eq!(bandwidth.inbound.get(), 5);
+        assert_eq!(bandwidth.outbound.get(), 3);
+    }
+}
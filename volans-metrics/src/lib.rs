@@ -0,0 +1,55 @@
+//! 基于 `prometheus-client` 的指标
+//!
+//! 将 Swarm 与协议的事件交给 [`Metrics::record`] 记录，传输层字节数通过
+//! [`Metrics::bandwidth`] 包装连接统计。
+
+mod bandwidth;
+#[cfg(feature = "ping")]
+mod ping;
+#[cfg(feature = "request")]
+mod request;
+mod swarm;
+
+pub use bandwidth::{Bandwidth, Metered};
+
+use prometheus_client::registry::Registry;
+
+/// 记录事件
+pub trait Recorder<Event> {
+    fn record(&self, event: &Event);
+}
+
+pub struct Metrics {
+    swarm: swarm::Metrics,
+    bandwidth: bandwidth::Metrics,
+    #[cfg(feature = "ping")]
+    ping: ping::Metrics,
+    #[cfg(feature = "request")]
+    request: request::Metrics,
+}
+
+impl Metrics {
+    /// 在 `volans` 前缀下注册指标，各协议使用独立的子注册表
+    pub fn new(registry: &mut Registry) -> Self {
+        let registry = registry.sub_registry_with_prefix("volans");
+        Self {
+            swarm: swarm::Metrics::new(registry.sub_registry_with_prefix("swarm")),
+            bandwidth: bandwidth::Metrics::new(registry),
+            #[cfg(feature = "ping")]
+            ping: ping::Metrics::new(registry.sub_registry_with_prefix("ping")),
+            #[cfg(feature = "request")]
+            request: request::Metrics::new(registry.sub_registry_with_prefix("request")),
+        }
+    }
+
+    /// 按传输层名称统计收发字节数，配合 `Transport::map` 包装连接
+    pub fn bandwidth(&self, transport: impl Into<String>) -> Bandwidth {
+        self.bandwidth.for_transport(transport.into())
+    }
+
+    /// 标记出站请求已发送，收到响应或失败时记录请求耗时
+    #[cfg(feature = "request")]
+    pub fn request_sent(&self, request_id: volans_request::RequestId) {
+        self.request.request_sent(request_id);
+    }
+}
@@ -0,0 +1,92 @@
+use prometheus_client::{
+    encoding::{EncodeLabelSet, EncodeLabelValue},
+    metrics::{
+        counter::Counter,
+        family::Family,
+        histogram::{Histogram, exponential_buckets},
+    },
+    registry::{Registry, Unit},
+};
+use volans_ping::{Event, Failure, inbound};
+
+use crate::Recorder;
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq, EncodeLabelSet)]
+struct FailureLabels {
+    reason: FailureReason,
+}
+
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, EncodeLabelValue)]
+enum FailureReason {
+    Timeout,
+    Unsupported,
+    RemoteReset,
+    /// 对端超出应答限制
+    Violation,
+    Other,
+}
+
+impl From<&Failure> for FailureReason {
+    fn from(failure: &Failure) -> Self {
+        match failure {
+            Failure::Timeout => FailureReason::Timeout,
+            Failure::Unsupported => FailureReason::Unsupported,
+            Failure::RemoteReset => FailureReason::RemoteReset,
+            Failure::RateExceeded | Failure::PayloadTooLarge { .. } => FailureReason::Violation,
+            Failure::Other { .. } => FailureReason::Other,
+        }
+    }
+}
+
+pub(crate) struct Metrics {
+    rtt: Histogram,
+    failures: Family<FailureLabels, Counter>,
+}
+
+impl Metrics {
+    pub(crate) fn new(registry: &mut Registry) -> Self {
+        let rtt = Histogram::new(exponential_buckets(0.001, 2.0, 12));
+        registry.register_with_unit(
+            "rtt",
+            "Round-trip time of ping messages",
+            Unit::Seconds,
+            rtt.clone(),
+        );
+        let failures = Family::default();
+        registry.register("failure", "Number of failed pings", failures.clone());
+        Self { rtt, failures }
+    }
+}
+
+impl Metrics {
+    fn record_failure(&self, failure: &Failure) {
+        self.failures
+            .get_or_create(&FailureLabels {
+                reason: failure.into(),
+            })
+            .inc();
+    }
+}
+
+impl Recorder<Event> for crate::Metrics {
+    fn record(&self, event: &Event) {
+        match &event.result {
+            Ok(rtt) => self.ping.rtt.observe(rtt.as_secs_f64()),
+            Err(failure) => self.ping.record_failure(failure),
+        }
+    }
+}
+
+/// 监听端只记录往返时延的估计值
+impl Recorder<inbound::Event> for crate::Metrics {
+    fn record(&self, event: &inbound::Event) {
+        match &event.result {
+            Ok(ping) => {
+                if let Some(rtt) = ping.rtt {
+                    self.ping.rtt.observe(rtt.as_secs_f64());
+                }
+            }
+            Err(failure) => self.ping.record_failure(failure),
+        }
+    }
+}
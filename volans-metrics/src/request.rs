@@ -0,0 +1,183 @@
+use std::{collections::HashMap, sync::Mutex, time::Instant};
+
+use prometheus_client::{
+    encoding::{EncodeLabelSet, EncodeLabelValue},
+    metrics::{
+        counter::Counter,
+        family::Family,
+        histogram::{Histogram, exponential_buckets},
+    },
+    registry::{Registry, Unit},
+};
+use volans_request::{InboundFailure, OutboundFailure, RequestId, client, server};
+
+use crate::Recorder;
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq, EncodeLabelSet)]
+struct Labels {
+    direction: Direction,
+}
+
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, EncodeLabelValue)]
+enum Direction {
+    Inbound,
+    Outbound,
+}
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq, EncodeLabelSet)]
+struct FailureLabels {
+    direction: Direction,
+    cause: FailureCause,
+}
+
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, EncodeLabelValue)]
+enum FailureCause {
+    DialFailure,
+    NoKnownAddress,
+    Timeout,
+    ConnectionClosed,
+    UnsupportedProtocols,
+    RemoteReset,
+    Cancelled,
+    Backpressure,
+    Discard,
+    Rejected,
+    Io,
+}
+
+impl From<&OutboundFailure> for FailureCause {
+    fn from(failure: &OutboundFailure) -> Self {
+        match failure {
+            OutboundFailure::DialFailure => FailureCause::DialFailure,
+            OutboundFailure::NoKnownAddress => FailureCause::NoKnownAddress,
+            OutboundFailure::Timeout => FailureCause::Timeout,
+            OutboundFailure::ConnectionClosed => FailureCause::ConnectionClosed,
+            OutboundFailure::UnsupportedProtocols { .. } => FailureCause::UnsupportedProtocols,
+            OutboundFailure::RemoteReset => FailureCause::RemoteReset,
+            OutboundFailure::Cancelled => FailureCause::Cancelled,
+            OutboundFailure::Backpressure => FailureCause::Backpressure,
+            OutboundFailure::Rejected(_) => FailureCause::Rejected,
+            OutboundFailure::Io(_) => FailureCause::Io,
+        }
+    }
+}
+
+impl From<&InboundFailure> for FailureCause {
+    fn from(failure: &InboundFailure) -> Self {
+        match failure {
+            InboundFailure::Timeout => FailureCause::Timeout,
+            InboundFailure::ConnectionClosed => FailureCause::ConnectionClosed,
+            InboundFailure::UnsupportedProtocols => FailureCause::UnsupportedProtocols,
+            InboundFailure::Discard => FailureCause::Discard,
+            InboundFailure::Rejected(_) => FailureCause::Rejected,
+            InboundFailure::Io(_) => FailureCause::Io,
+        }
+    }
+}
+
+fn latency_histogram() -> Histogram {
+    Histogram::new(exponential_buckets(0.001, 2.0, 16))
+}
+
+pub(crate) struct Metrics {
+    requests: Family<Labels, Counter>,
+    responses: Family<Labels, Counter>,
+    failures: Family<FailureLabels, Counter>,
+    latency: Family<Labels, Histogram>,
+    /// 请求开始的时间，出站请求需由调用方标记
+    started: Mutex<HashMap<(Direction, RequestId), Instant>>,
+}
+
+impl Metrics {
+    pub(crate) fn new(registry: &mut Registry) -> Self {
+        let requests = Family::default();
+        registry.register("requests", "Number of requests", requests.clone());
+        let responses = Family::default();
+        registry.register("responses", "Number of responses", responses.clone());
+        let failures = Family::default();
+        registry.register("failures", "Number of failed requests", failures.clone());
+        let latency: Family<Labels, Histogram> = Family::new_with_constructor(latency_histogram);
+        registry.register_with_unit(
+            "latency",
+            "Time from request to response",
+            Unit::Seconds,
+            latency.clone(),
+        );
+        Self {
+            requests,
+            responses,
+            failures,
+            latency,
+            started: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub(crate) fn request_sent(&self, request_id: RequestId) {
+        self.start(Direction::Outbound, request_id);
+    }
+
+    fn start(&self, direction: Direction, request_id: RequestId) {
+        self.requests.get_or_create(&Labels { direction }).inc();
+        self.started
+            .lock()
+            .expect("poisoned")
+            .insert((direction, request_id), Instant::now());
+    }
+
+    fn succeeded(&self, direction: Direction, request_id: RequestId) {
+        let labels = Labels { direction };
+        self.responses.get_or_create(&labels).inc();
+        if let Some(started) = self.finish(direction, request_id) {
+            self.latency
+                .get_or_create(&labels)
+                .observe(started.elapsed().as_secs_f64());
+        }
+    }
+
+    fn failed(&self, direction: Direction, request_id: RequestId, cause: FailureCause) {
+        self.finish(direction, request_id);
+        self.failures
+            .get_or_create(&FailureLabels { direction, cause })
+            .inc();
+    }
+
+    fn finish(&self, direction: Direction, request_id: RequestId) -> Option<Instant> {
+        self.started
+            .lock()
+            .expect("poisoned")
+            .remove(&(direction, request_id))
+    }
+}
+
+impl<TResponse> Recorder<client::Event<TResponse>> for crate::Metrics {
+    fn record(&self, event: &client::Event<TResponse>) {
+        let metrics = &self.request;
+        match event {
+            client::Event::Response { request_id, .. }
+            | client::Event::ResponseCompleted { request_id, .. } => {
+                metrics.succeeded(Direction::Outbound, *request_id)
+            }
+            client::Event::ResponseChunk { .. } => {}
+            client::Event::Failure {
+                request_id, cause, ..
+            } => metrics.failed(Direction::Outbound, *request_id, cause.into()),
+        }
+    }
+}
+
+impl<TRequest, TResponse> Recorder<server::Event<TRequest, TResponse>> for crate::Metrics {
+    fn record(&self, event: &server::Event<TRequest, TResponse>) {
+        let metrics = &self.request;
+        match event {
+            server::Event::Request { request_id, .. } => {
+                metrics.start(Direction::Inbound, *request_id)
+            }
+            server::Event::ResponseSent { request_id, .. } => {
+                metrics.succeeded(Direction::Inbound, *request_id)
+            }
+            server::Event::Failure {
+                request_id, cause, ..
+            } => metrics.failed(Direction::Inbound, *request_id, cause.into()),
+        }
+    }
+}
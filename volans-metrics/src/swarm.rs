@@ -0,0 +1,295 @@
+use std::time::Duration;
+
+use prometheus_client::{
+    encoding::{EncodeLabelSet, EncodeLabelValue},
+    metrics::{
+        counter::Counter,
+        family::Family,
+        histogram::{Histogram, exponential_buckets},
+    },
+    registry::{Registry, Unit},
+};
+use volans_core::ConnectedPoint;
+use volans_swarm::{
+    SwarmEvent,
+    error::{CloseReason, DialError, ListenError},
+};
+
+use crate::Recorder;
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq, EncodeLabelSet)]
+struct RoleLabels {
+    role: Role,
+}
+
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, EncodeLabelValue)]
+enum Role {
+    Dialer,
+    Listener,
+}
+
+impl From<&ConnectedPoint> for Role {
+    fn from(endpoint: &ConnectedPoint) -> Self {
+        match endpoint {
+            ConnectedPoint::Dialer { .. } => Role::Dialer,
+            ConnectedPoint::Listener { .. } => Role::Listener,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq, EncodeLabelSet)]
+struct ClosedLabels {
+    role: Role,
+    cause: CloseCause,
+}
+
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, EncodeLabelValue)]
+enum CloseCause {
+    LocalRequested,
+    RemoteClosed,
+    IdleTimeout,
+    HandlerError,
+    Timeout,
+    MuxerError,
+}
+
+impl From<&CloseReason> for CloseCause {
+    fn from(reason: &CloseReason) -> Self {
+        match reason {
+            CloseReason::LocalRequested => CloseCause::LocalRequested,
+            CloseReason::RemoteClosed => CloseCause::RemoteClosed,
+            CloseReason::IdleTimeout => CloseCause::IdleTimeout,
+            CloseReason::HandlerError => CloseCause::HandlerError,
+            CloseReason::Timeout(_) => CloseCause::Timeout,
+            CloseReason::MuxerError(_) => CloseCause::MuxerError,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq, EncodeLabelSet)]
+struct ErrorLabels {
+    error: ErrorKind,
+}
+
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, EncodeLabelValue)]
+enum ErrorKind {
+    LocalPeerId,
+    NoAddress,
+    PeerCondition,
+    Aborted,
+    Closing,
+    WrongPeerId,
+    Denied,
+    Transport,
+    AllAttemptsFailed,
+    Timeout,
+    PendingLimitReached,
+    Filtered,
+}
+
+impl From<&DialError> for ErrorKind {
+    fn from(error: &DialError) -> Self {
+        match error {
+            DialError::LocalPeerId => ErrorKind::LocalPeerId,
+            DialError::NoAddress => ErrorKind::NoAddress,
+            DialError::PeerCondition(_) => ErrorKind::PeerCondition,
+            DialError::Aborted => ErrorKind::Aborted,
+            DialError::Closing => ErrorKind::Closing,
+            DialError::WrongPeerId { .. } => ErrorKind::WrongPeerId,
+            DialError::Denied { .. } => ErrorKind::Denied,
+            DialError::Transport { .. } => ErrorKind::Transport,
+            DialError::AllAttemptsFailed { .. } => ErrorKind::AllAttemptsFailed,
+            DialError::Timeout => ErrorKind::Timeout,
+        }
+    }
+}
+
+impl From<&ListenError> for ErrorKind {
+    fn from(error: &ListenError) -> Self {
+        match error {
+            ListenError::Aborted => ErrorKind::Aborted,
+            ListenError::Closing => ErrorKind::Closing,
+            ListenError::Timeout => ErrorKind::Timeout,
+            ListenError::PendingLimitReached => ErrorKind::PendingLimitReached,
+            ListenError::Filtered => ErrorKind::Filtered,
+            ListenError::WrongPeerId { .. } => ErrorKind::WrongPeerId,
+            ListenError::LocalPeerId => ErrorKind::LocalPeerId,
+            ListenError::Denied { .. } => ErrorKind::Denied,
+            ListenError::Transport(_) => ErrorKind::Transport,
+        }
+    }
+}
+
+fn establishment_histogram() -> Histogram {
+    Histogram::new(exponential_buckets(0.01, 1.5, 20))
+}
+
+pub(crate) struct Metrics {
+    dial_attempts: Counter,
+    outgoing_connection_errors: Family<ErrorLabels, Counter>,
+    incoming_connections: Counter,
+    incoming_connection_errors: Family<ErrorLabels, Counter>,
+    connections_established: Family<RoleLabels, Counter>,
+    connections_establishment_duration: Family<RoleLabels, Histogram>,
+    connections_closed: Family<ClosedLabels, Counter>,
+    new_listen_addr: Counter,
+    expired_listen_addr: Counter,
+    listener_closed: Counter,
+    listener_error: Counter,
+}
+
+impl Metrics {
+    pub(crate) fn new(registry: &mut Registry) -> Self {
+        let dial_attempts = Counter::default();
+        registry.register(
+            "dial_attempts",
+            "Number of outgoing connection attempts",
+            dial_attempts.clone(),
+        );
+        let outgoing_connection_errors = Family::default();
+        registry.register(
+            "outgoing_connection_errors",
+            "Number of outgoing connection errors",
+            outgoing_connection_errors.clone(),
+        );
+        let incoming_connections = Counter::default();
+        registry.register(
+            "incoming_connections",
+            "Number of incoming connections being upgraded",
+            incoming_connections.clone(),
+        );
+        let incoming_connection_errors = Family::default();
+        registry.register(
+            "incoming_connection_errors",
+            "Number of incoming connection errors",
+            incoming_connection_errors.clone(),
+        );
+        let connections_established = Family::default();
+        registry.register(
+            "connections_established",
+            "Number of connections established",
+            connections_established.clone(),
+        );
+        let connections_establishment_duration: Family<RoleLabels, Histogram> =
+            Family::new_with_constructor(establishment_histogram);
+        registry.register_with_unit(
+            "connections_establishment_duration",
+            "Time it took to establish connections",
+            Unit::Seconds,
+            connections_establishment_duration.clone(),
+        );
+        let connections_closed = Family::default();
+        registry.register(
+            "connections_closed",
+            "Number of connections closed",
+            connections_closed.clone(),
+        );
+        let new_listen_addr = Counter::default();
+        registry.register(
+            "new_listen_addr",
+            "Number of new listen addresses",
+            new_listen_addr.clone(),
+        );
+        let expired_listen_addr = Counter::default();
+        registry.register(
+            "expired_listen_addr",
+            "Number of expired listen addresses",
+            expired_listen_addr.clone(),
+        );
+        let listener_closed = Counter::default();
+        registry.register(
+            "listener_closed",
+            "Number of listeners closed",
+            listener_closed.clone(),
+        );
+        let listener_error = Counter::default();
+        registry.register(
+            "listener_error",
+            "Number of non-fatal listener errors",
+            listener_error.clone(),
+        );
+        Self {
+            dial_attempts,
+            outgoing_connection_errors,
+            incoming_connections,
+            incoming_connection_errors,
+            connections_established,
+            connections_establishment_duration,
+            connections_closed,
+            new_listen_addr,
+            expired_listen_addr,
+            listener_closed,
+            listener_error,
+        }
+    }
+
+    fn outgoing_error(&self, error: &DialError) {
+        self.outgoing_connection_errors
+            .get_or_create(&ErrorLabels {
+                error: error.into(),
+            })
+            .inc();
+    }
+
+    fn incoming_error(&self, error: &ListenError) {
+        self.incoming_connection_errors
+            .get_or_create(&ErrorLabels {
+                error: error.into(),
+            })
+            .inc();
+    }
+
+    fn established(&self, role: Role, established_in: Duration) {
+        let labels = RoleLabels { role };
+        self.connections_established.get_or_create(&labels).inc();
+        self.connections_establishment_duration
+            .get_or_create(&labels)
+            .observe(established_in.as_secs_f64());
+    }
+
+    fn closed(&self, role: Role, reason: &CloseReason) {
+        self.connections_closed
+            .get_or_create(&ClosedLabels {
+                role,
+                cause: reason.into(),
+            })
+            .inc();
+    }
+}
+
+impl<TBehaviorEvent> Recorder<SwarmEvent<TBehaviorEvent>> for crate::Metrics {
+    fn record(&self, event: &SwarmEvent<TBehaviorEvent>) {
+        let metrics = &self.swarm;
+        match event {
+            SwarmEvent::Dialing { .. } => {
+                metrics.dial_attempts.inc();
+            }
+            SwarmEvent::OutgoingConnectionError { error, .. } => metrics.outgoing_error(error),
+            SwarmEvent::NewListenAddr { .. } => {
+                metrics.new_listen_addr.inc();
+            }
+            SwarmEvent::ExpiredListenAddr { .. } => {
+                metrics.expired_listen_addr.inc();
+            }
+            SwarmEvent::ListenerClosed { .. } => {
+                metrics.listener_closed.inc();
+            }
+            SwarmEvent::ListenerError { .. } => {
+                metrics.listener_error.inc();
+            }
+            SwarmEvent::IncomingConnection { .. } => {
+                metrics.incoming_connections.inc();
+            }
+            SwarmEvent::IncomingConnectionError { error, .. } => metrics.incoming_error(error),
+            SwarmEvent::ConnectionEstablished {
+                endpoint,
+                established_in,
+                ..
+            } => metrics.established(endpoint.into(), *established_in),
+            SwarmEvent::ConnectionClosed {
+                endpoint, reason, ..
+            } => metrics.closed(endpoint.into(), reason),
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,179 @@
+//! 记录事件后编码注册表，检查计数器与直方图
+
+use std::time::Duration;
+
+use prometheus_client::{encoding::text::encode, registry::Registry};
+use volans_core::PeerId;
+use volans_metrics::{Metrics, Recorder};
+use volans_request::{Config, codec::JsonCodec};
+use volans_swarm::{ConnectionId, DialOpts, StreamProtocol, client, server};
+use volans_swarm_test::{SwarmExt, listen, next_behavior_event, unused_addr, wait_for_event};
+
+const ECHO: StreamProtocol = StreamProtocol::new("/echo/1.0.0");
+type Codec = JsonCodec<String, String>;
+
+fn encoded(registry: &Registry) -> String {
+    let mut out = String::new();
+    encode(&mut out, registry).unwrap();
+    out
+}
+
+fn assert_metric(encoded: &str, line: &str) {
+    assert!(
+        encoded.lines().any(|l| l == line),
+        "missing `{line}` in:\n{encoded}"
+    );
+}
+
+/// 在后台应答请求的监听节点
+async fn spawn_echo() -> (PeerId, volans_core::Multiaddr) {
+    let mut listener = server::Swarm::new_ephemeral(|_| {
+        volans_request::server::Behavior::with_codec(Codec::new(), [ECHO], Config::default())
+    });
+    let addr = listen(&mut listener).await;
+    let peer_id = *listener.local_peer_id();
+    tokio::spawn(async move {
+        loop {
+            if let volans_request::server::Event::Request {
+                request, responder, ..
+            } = next_behavior_event(&mut listener).await
+            {
+                responder.send_response(request).unwrap();
+            }
+        }
+    });
+    (peer_id, addr)
+}
+
+fn dialer() -> client::Swarm<volans_request::client::Behavior<Codec>> {
+    client::Swarm::new_ephemeral(|_| {
+        volans_request::client::Behavior::with_codec(Codec::new(), Config::default())
+    })
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn record_swarm_events() {
+    let mut registry = Registry::default();
+    let metrics = Metrics::new(&mut registry);
+    let (peer_id, addr) = spawn_echo().await;
+    let mut dialer = dialer();
+
+    dialer
+        .dial(DialOpts::new(Some(addr), Some(peer_id)))
+        .unwrap();
+    let connection_id = wait_for_event(&mut dialer, |event| {
+        metrics.record(&event);
+        match event {
+            client::SwarmEvent::ConnectionEstablished { connection_id, .. } => Some(connection_id),
+            _ => None,
+        }
+    })
+    .await;
+    assert!(dialer.close_connection(connection_id));
+    wait_for_event(&mut dialer, |event| {
+        metrics.record(&event);
+        matches!(event, client::SwarmEvent::ConnectionClosed { .. }).then_some(())
+    })
+    .await;
+    // 由行为发起的拨号产生 Dialing 事件
+    let unreachable = PeerId::random();
+    dialer
+        .peer_store_mut()
+        .add_address(unreachable, unused_addr());
+    dialer
+        .behavior_mut()
+        .send_request(unreachable, ECHO, "ping".to_string());
+    wait_for_event(&mut dialer, |event| {
+        metrics.record(&event);
+        matches!(event, client::SwarmEvent::OutgoingConnectionError { .. }).then_some(())
+    })
+    .await;
+
+    let encoded = encoded(&registry);
+    assert_metric(&encoded, "volans_swarm_dial_attempts_total 1");
+    assert_metric(
+        &encoded,
+        r#"volans_swarm_connections_established_total{role="Dialer"} 1"#,
+    );
+    assert_metric(
+        &encoded,
+        r#"volans_swarm_connections_establishment_duration_seconds_count{role="Dialer"} 1"#,
+    );
+    assert_metric(
+        &encoded,
+        r#"volans_swarm_connections_closed_total{role="Dialer",cause="LocalRequested"} 1"#,
+    );
+    assert_metric(
+        &encoded,
+        r#"volans_swarm_outgoing_connection_errors_total{error="Transport"} 1"#,
+    );
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn record_ping_rtt() {
+    use volans_ping::{Event, Failure};
+
+    let mut registry = Registry::default();
+    let metrics = Metrics::new(&mut registry);
+    let event = |result| Event {
+        connection: ConnectionId::new_unchecked(1),
+        peer_id: PeerId::random(),
+        result,
+        stats: None,
+    };
+    metrics.record(&event(Ok(Duration::from_millis(3))));
+    metrics.record(&event(Ok(Duration::from_millis(5))));
+    metrics.record(&event(Err(Failure::Timeout)));
+
+    let encoded = encoded(&registry);
+    assert_metric(&encoded, "volans_ping_rtt_seconds_count 2");
+    assert_metric(&encoded, "volans_ping_rtt_seconds_sum 0.008");
+    assert_metric(&encoded, r#"volans_ping_rtt_seconds_bucket{le="0.004"} 1"#);
+    assert_metric(&encoded, r#"volans_ping_failure_total{reason="Timeout"} 1"#);
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn record_request_latency() {
+    use volans_request::client::Event;
+
+    let mut registry = Registry::default();
+    let metrics = Metrics::new(&mut registry);
+    let (peer_id, addr) = spawn_echo().await;
+    let mut dialer = dialer();
+    dialer.peer_store_mut().add_address(peer_id, addr);
+
+    let request_id = dialer
+        .behavior_mut()
+        .send_request(peer_id, ECHO, "ping".to_string());
+    metrics.request_sent(request_id);
+    let event = next_behavior_event(&mut dialer).await;
+    assert!(matches!(event, Event::Response { .. }));
+    metrics.record(&event);
+
+    // 没有已知地址的节点立即失败
+    let request_id = dialer
+        .behavior_mut()
+        .send_request(PeerId::random(), ECHO, "ping".to_string());
+    metrics.request_sent(request_id);
+    let event = next_behavior_event(&mut dialer).await;
+    assert!(matches!(event, Event::Failure { .. }));
+    metrics.record(&event);
+
+    let encoded = encoded(&registry);
+    assert_metric(
+        &encoded,
+        r#"volans_request_requests_total{direction="Outbound"} 2"#,
+    );
+    assert_metric(
+        &encoded,
+        r#"volans_request_responses_total{direction="Outbound"} 1"#,
+    );
+    assert_metric(
+        &encoded,
+        r#"volans_request_latency_seconds_count{direction="Outbound"} 1"#,
+    );
+    assert_metric(
+        &encoded,
+        r#"volans_request_failures_total{direction="Outbound",cause="NoKnownAddress"} 1"#,
+    );
+}
@@ -0,0 +1,120 @@
+//! 协议协商的耗时，两端通过内存管道直连，不含传输开销
+
+use std::{
+    collections::VecDeque,
+    io,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{Context, Poll, Waker},
+};
+
+use criterion::{BenchmarkId, Criterion, criterion_group, criterion_main};
+use futures::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, executor::block_on, future};
+use volans_stream_select::{DialerSelectFuture, ListenerSelectFuture, NegotiationMode};
+
+#[derive(Default)]
+struct Buffer {
+    data: VecDeque<u8>,
+    waker: Option<Waker>,
+}
+
+/// 内存管道的一端，读写对端的缓冲区互不阻塞
+struct Pipe {
+    read: Arc<Mutex<Buffer>>,
+    write: Arc<Mutex<Buffer>>,
+}
+
+fn pipe() -> (Pipe, Pipe) {
+    let a = Arc::new(Mutex::new(Buffer::default()));
+    let b = Arc::new(Mutex::new(Buffer::default()));
+    (
+        Pipe {
+            read: a.clone(),
+            write: b.clone(),
+        },
+        Pipe { read: b, write: a },
+    )
+}
+
+impl AsyncRead for Pipe {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        let mut buffer = self.read.lock().unwrap();
+        if buffer.data.is_empty() {
+            buffer.waker = Some(cx.waker().clone());
+            return Poll::Pending;
+        }
+        let n = buf.len().min(buffer.data.len());
+        for (dst, src) in buf.iter_mut().zip(buffer.data.drain(..n)) {
+            *dst = src;
+        }
+        Poll::Ready(Ok(n))
+    }
+}
+
+impl AsyncWrite for Pipe {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let mut buffer = self.write.lock().unwrap();
+        buffer.data.extend(buf);
+        if let Some(waker) = buffer.waker.take() {
+            waker.wake();
+        }
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+/// 拨号端按顺序尝试 `dialer_protocols`，协商完成后写入一个字节，懒协商时协议随之发出
+async fn negotiate(dialer_protocols: &[&'static str], mode: NegotiationMode) {
+    let (dialer_io, listener_io) = pipe();
+    let dialer = async {
+        let (_, mut io) = DialerSelectFuture::new(dialer_io, dialer_protocols.iter().copied())
+            .with_mode(mode)
+            .await
+            .expect("dialer negotiation");
+        io.write_all(&[1]).await.unwrap();
+        io.flush().await.unwrap();
+    };
+    let listener = async {
+        let (_, mut io) = ListenerSelectFuture::new(listener_io, ["/perf/1.0.0"].into_iter())
+            .await
+            .expect("listener negotiation");
+        let mut byte = [0u8];
+        io.read_exact(&mut byte).await.unwrap();
+    };
+    future::join(dialer, listener).await;
+}
+
+fn negotiation(c: &mut Criterion) {
+    let mut group = c.benchmark_group("negotiation");
+    for mode in [NegotiationMode::Full, NegotiationMode::Lazy] {
+        group.bench_with_input(
+            BenchmarkId::new("single", format!("{mode:?}")),
+            &mode,
+            |b, &mode| b.iter(|| block_on(negotiate(&["/perf/1.0.0"], mode))),
+        );
+    }
+    // 前两个协议被拒绝后才协商成功
+    let protocols = ["/ping/1.0.0", "/identify/1.0.0", "/perf/1.0.0"];
+    group.bench_function("fallback", |b| {
+        b.iter(|| block_on(negotiate(&protocols, NegotiationMode::Full)))
+    });
+    group.finish();
+}
+
+criterion_group!(benches, negotiation);
+criterion_main!(benches);
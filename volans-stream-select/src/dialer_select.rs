@@ -5,16 +5,30 @@ use crate::{
     protocol::{Message, MessageIO, Protocol},
 };
 use std::{
-    iter, mem,
+    io, iter, mem,
     pin::Pin,
     task::{Context, Poll},
 };
 
+/// 拨号端的协商方式
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub enum NegotiationMode {
+    /// 等待监听端确认协议后再返回
+    #[default]
+    Full,
+    /// 最后一个候选协议不等待确认，协议与首批数据一起发送，
+    /// 只提供一个协议时可以节省一个往返
+    ///
+    /// 监听端不支持该协议时，错误在首次读取时返回。
+    Lazy,
+}
+
 #[pin_project::pin_project]
 pub struct DialerSelectFuture<R, I: Iterator> {
     protocols: iter::Peekable<I>,
     state: State<R, I::Item>,
     lazy: bool,
+    list_protocols: bool,
 }
 
 impl<R, I> DialerSelectFuture<R, I>
@@ -30,8 +44,21 @@ where
                 io: MessageIO::new(io),
             },
             lazy: false,
+            list_protocols: false,
         }
     }
+
+    /// 所有协议都不被支持时请求对端列出支持的协议，
+    /// 通过 [`NegotiationError::Failed`] 返回
+    pub fn with_list_protocols(mut self, enabled: bool) -> Self {
+        self.list_protocols = enabled;
+        self
+    }
+
+    pub fn with_mode(mut self, mode: NegotiationMode) -> Self {
+        self.lazy = mode == NegotiationMode::Lazy;
+        self
+    }
 }
 
 enum State<R, P> {
@@ -39,6 +66,9 @@ enum State<R, P> {
     SendProtocol { io: MessageIO<R>, protocol: P },
     FlushProtocol { io: MessageIO<R>, protocol: P },
     AwaitProtocol { io: MessageIO<R>, protocol: P },
+    SendList { io: MessageIO<R> },
+    FlushList { io: MessageIO<R> },
+    AwaitList { io: MessageIO<R> },
     Done,
 }
 
@@ -62,7 +92,7 @@ where
                             return Poll::Pending;
                         }
                     };
-                    let protocol = this.protocols.next().ok_or(NegotiationError::Failed)?;
+                    let protocol = this.protocols.next().ok_or_else(NegotiationError::failed)?;
                     *this.state = State::SendProtocol { io, protocol };
                 }
                 State::SendProtocol { mut io, protocol } => {
@@ -107,8 +137,11 @@ where
                     let msg = match Pin::new(&mut io).poll_next(cx)? {
                         Poll::Ready(Some(msg)) => msg,
                         Poll::Ready(None) => {
+                            // 对端在确认协议前关闭了流，不是协商失败
                             tracing::debug!("No message received, connection closed");
-                            return Poll::Ready(Err(NegotiationError::Failed));
+                            return Poll::Ready(Err(
+                                io::Error::from(io::ErrorKind::UnexpectedEof).into()
+                            ));
                         }
                         Poll::Pending => {
                             *this.state = State::AwaitProtocol { io, protocol };
@@ -124,8 +157,15 @@ where
                         Message::NotAvailable => {
                             // 不支持的协议，继续协商下一个协议
                             tracing::debug!("Protocol not available, trying next protocol");
-                            let protocol = this.protocols.next().ok_or(NegotiationError::Failed)?;
-                            *this.state = State::SendProtocol { io, protocol }
+                            match this.protocols.next() {
+                                Some(protocol) => {
+                                    *this.state = State::SendProtocol { io, protocol }
+                                }
+                                None if *this.list_protocols => {
+                                    *this.state = State::SendList { io }
+                                }
+                                None => return Poll::Ready(Err(NegotiationError::failed())),
+                            }
                         }
                         _ => {
                             // 协议不匹配，继续等待下一个协议
@@ -133,6 +173,45 @@ where
                         }
                     }
                 }
+                State::SendList { mut io } => {
+                    match Pin::new(&mut io).poll_ready(cx)? {
+                        Poll::Ready(()) => {}
+                        Poll::Pending => {
+                            *this.state = State::SendList { io };
+                            return Poll::Pending;
+                        }
+                    };
+                    if let Err(err) = Pin::new(&mut io).start_send(Message::ListProtocols) {
+                        return Poll::Ready(Err(From::from(err)));
+                    }
+                    *this.state = State::FlushList { io };
+                }
+                State::FlushList { mut io } => {
+                    match Pin::new(&mut io).poll_flush(cx)? {
+                        Poll::Ready(()) => {}
+                        Poll::Pending => {
+                            *this.state = State::FlushList { io };
+                            return Poll::Pending;
+                        }
+                    };
+                    *this.state = State::AwaitList { io };
+                }
+                State::AwaitList { mut io } => {
+                    // 对端不支持列出协议时仍以协商失败结束
+                    let remote_protocols = match Pin::new(&mut io).poll_next(cx) {
+                        Poll::Ready(Some(Ok(Message::Protocols(protocols)))) => protocols
+                            .into_iter()
+                            .map(|p| p.as_ref().to_string())
+                            .collect(),
+                        Poll::Ready(_) => Vec::new(),
+                        Poll::Pending => {
+                            *this.state = State::AwaitList { io };
+                            return Poll::Pending;
+                        }
+                    };
+                    tracing::debug!(?remote_protocols, "No common protocol with remote");
+                    return Poll::Ready(Err(NegotiationError::Failed { remote_protocols }));
+                }
                 _ => panic!("Unexpected state in DialerSelectFuture"),
             }
         }
@@ -49,7 +49,7 @@ impl<R> LengthDelimited<R> {
             inner,
             read_state: ReadState::default(),
             read_buffer: BytesMut::with_capacity(DEFAULT_BUFFER_SIZE),
-            write_buffer: BytesMut::with_capacity(DEFAULT_BUFFER_SIZE + MAX_LENGTH_SIZE as usize),
+            write_buffer: BytesMut::with_capacity(DEFAULT_BUFFER_SIZE + MAX_LENGTH_SIZE),
         }
     }
 
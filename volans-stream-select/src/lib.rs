@@ -4,7 +4,7 @@ mod listener_select;
 mod negotiated;
 mod protocol;
 
-pub use dialer_select::DialerSelectFuture;
+pub use dialer_select::{DialerSelectFuture, NegotiationMode};
 pub use listener_select::ListenerSelectFuture;
 pub use negotiated::{Negotiated, NegotiatedComplete, NegotiationError};
 pub use protocol::ProtocolError;
@@ -75,7 +75,7 @@ where
                     let msg = match Pin::new(&mut io).poll_next(cx)? {
                         Poll::Ready(Some(msg)) => msg,
                         Poll::Ready(None) => {
-                            return Poll::Ready(Err(NegotiationError::Failed));
+                            return Poll::Ready(Err(NegotiationError::failed()));
                         }
                         Poll::Pending => {
                             *this.state = State::RecvMessage { io };
@@ -104,6 +104,14 @@ where
                                 protocol,
                             };
                         }
+                        Message::ListProtocols => {
+                            let protocols = this.protocols.iter().map(|(_, p)| p.clone()).collect();
+                            *this.state = State::SendMessage {
+                                io,
+                                message: Message::Protocols(protocols),
+                                protocol: None,
+                            };
+                        }
                         _ => return Poll::Ready(Err(ProtocolError::InvalidMessage.into())),
                     }
                 }
@@ -9,6 +9,10 @@ use std::{
 use crate::length_delimited::{LengthDelimited, LengthDelimitedReader};
 
 const MSG_PROTOCOL_NA: &[u8] = b"na";
+/// 请求对端列出支持的协议
+const MSG_LS: &[u8] = b"ls";
+/// 协议列表，后跟以换行分隔的协议名
+const MSG_PROTOCOLS: &[u8] = b"ps";
 
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub(crate) struct Protocol(String);
@@ -76,6 +80,8 @@ impl From<ProtocolError> for io::Error {
 pub(crate) enum Message {
     Protocol(Protocol),
     NotAvailable,
+    ListProtocols,
+    Protocols(Vec<Protocol>),
 }
 
 impl Message {
@@ -89,6 +95,17 @@ impl Message {
                 dst.reserve(protocol.as_ref().len());
                 dst.put(protocol.0.as_ref());
             }
+            Message::ListProtocols => {
+                dst.reserve(MSG_LS.len());
+                dst.put(MSG_LS);
+            }
+            Message::Protocols(protocols) => {
+                dst.put(MSG_PROTOCOLS);
+                for protocol in protocols {
+                    dst.put_u8(b'\n');
+                    dst.put(protocol.0.as_bytes());
+                }
+            }
         }
     }
 
@@ -96,6 +113,17 @@ impl Message {
         if src == MSG_PROTOCOL_NA {
             return Ok(Message::NotAvailable);
         }
+        if src == MSG_LS {
+            return Ok(Message::ListProtocols);
+        }
+        if src.starts_with(MSG_PROTOCOLS) {
+            let protocols = src[MSG_PROTOCOLS.len()..]
+                .split(|b| *b == b'\n')
+                .skip(1)
+                .map(Protocol::try_from)
+                .collect::<Result<_, _>>()?;
+            return Ok(Message::Protocols(protocols));
+        }
         if src.first() == Some(&b'/') {
             let protocol = Protocol::try_from(src.split_to(src.len()))?;
             return Ok(Message::Protocol(protocol));
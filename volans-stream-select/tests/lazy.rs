@@ -0,0 +1,58 @@
+use futures::{AsyncReadExt, AsyncWriteExt, FutureExt};
+use tokio_util::compat::TokioAsyncReadCompatExt;
+use volans_stream_select::{DialerSelectFuture, ListenerSelectFuture, NegotiationMode};
+
+const PROTOCOL: &str = "/proto/1.0.0";
+
+#[tokio::test(flavor = "current_thread")]
+async fn full_dialer_waits_for_listener() {
+    let (a, _b) = tokio::io::duplex(1024);
+    let dialer = DialerSelectFuture::new(a.compat(), [PROTOCOL].into_iter());
+    assert!(dialer.now_or_never().is_none());
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn lazy_dialer_sends_protocol_with_data() {
+    let (a, b) = tokio::io::duplex(1024);
+    // 监听端尚未读取任何数据，拨号端已经完成协商
+    let (protocol, mut outbound) = DialerSelectFuture::new(a.compat(), [PROTOCOL].into_iter())
+        .with_mode(NegotiationMode::Lazy)
+        .now_or_never()
+        .unwrap()
+        .unwrap();
+    assert_eq!(protocol, PROTOCOL);
+    outbound.write_all(b"ping").await.unwrap();
+    outbound.flush().await.unwrap();
+
+    let (protocol, mut inbound) = ListenerSelectFuture::new(b.compat(), [PROTOCOL].into_iter())
+        .await
+        .unwrap();
+    assert_eq!(protocol, PROTOCOL);
+    let mut buf = [0; 4];
+    inbound.read_exact(&mut buf).await.unwrap();
+    assert_eq!(&buf, b"ping");
+
+    // 拨号端在首次读取时确认协议
+    inbound.write_all(b"pong").await.unwrap();
+    inbound.flush().await.unwrap();
+    outbound.read_exact(&mut buf).await.unwrap();
+    assert_eq!(&buf, b"pong");
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn lazy_dialer_fails_on_first_read() {
+    let (a, b) = tokio::io::duplex(1024);
+    let (_, mut outbound) = DialerSelectFuture::new(a.compat(), [PROTOCOL].into_iter())
+        .with_mode(NegotiationMode::Lazy)
+        .await
+        .unwrap();
+
+    // 监听端拒绝后继续等待新的提议，连接保持打开
+    let listener = tokio::spawn(ListenerSelectFuture::new(
+        b.compat(),
+        ["/other/1.0.0"].into_iter(),
+    ));
+    let mut buf = [0; 4];
+    assert!(outbound.read_exact(&mut buf).await.is_err());
+    listener.abort();
+}
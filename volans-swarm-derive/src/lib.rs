@@ -35,6 +35,13 @@ pub fn network_outgoing_macro_derive(input: TokenStream) -> TokenStream {
     build_outgoing(&ast).unwrap_or_else(|e| e.to_compile_error().into())
 }
 
+#[proc_macro_derive(NetworkDuplexBehavior, attributes(behavior))]
+pub fn network_duplex_macro_derive(input: TokenStream) -> TokenStream {
+    // 解析输入的 AST
+    let ast = parse_macro_input!(input as DeriveInput);
+    build_duplex(&ast).unwrap_or_else(|e| e.to_compile_error().into())
+}
+
 fn build_incoming(ast: &DeriveInput) -> syn::Result<TokenStream> {
     match ast.data {
         // 只能解析结构体
@@ -65,6 +72,21 @@ fn build_outgoing(ast: &DeriveInput) -> syn::Result<TokenStream> {
     }
 }
 
+fn build_duplex(ast: &DeriveInput) -> syn::Result<TokenStream> {
+    match ast.data {
+        // 只能解析结构体
+        Data::Struct(ref s) => build_duplex_struct(ast, s),
+        Data::Enum(_) => Err(syn::Error::new_spanned(
+            ast,
+            "Cannot derive `NetworkDuplexBehavior` on enums",
+        )),
+        Data::Union(_) => Err(syn::Error::new_spanned(
+            ast,
+            "Cannot derive `NetworkDuplexBehavior` on union",
+        )),
+    }
+}
+
 struct PreludeTokenStream {
     addr: proc_macro2::TokenStream,
     peer_id: proc_macro2::TokenStream,
@@ -76,6 +98,10 @@ struct PreludeTokenStream {
     network_incoming_behavior_to_impl: proc_macro2::TokenStream,
     network_outgoing_behavior_to_impl: proc_macro2::TokenStream,
     handler_select: proc_macro2::TokenStream,
+    handler_mux: proc_macro2::TokenStream,
+    mux_envelope: proc_macro2::TokenStream,
+    inbound_only_handler: proc_macro2::TokenStream,
+    outbound_only_handler: proc_macro2::TokenStream,
     t_handler: proc_macro2::TokenStream,
     t_handler_event: proc_macro2::TokenStream,
     t_handler_action: proc_macro2::TokenStream,
@@ -87,7 +113,7 @@ struct PreludeTokenStream {
     dial_opts: proc_macro2::TokenStream,
 
     // error
-    connection_error: proc_macro2::TokenStream,
+    close_reason: proc_macro2::TokenStream,
     listen_error: proc_macro2::TokenStream,
     dial_error: proc_macro2::TokenStream,
 
@@ -97,10 +123,16 @@ struct PreludeTokenStream {
 struct CommonParsed {
     prelude: PreludeTokenStream,
     attributes: BehaviorAttributes,
+    // 字段上的 #[behavior(map_event = "...")]，按字段顺序
+    event_maps: Vec<Option<syn::Path>>,
 }
 
 fn parse_common_token_stream(ast: &DeriveInput) -> syn::Result<CommonParsed> {
     let attributes = parse_attributes(ast)?;
+    let event_maps = match &ast.data {
+        Data::Struct(data_struct) => parse_field_event_maps(data_struct)?,
+        _ => Vec::new(),
+    };
     let BehaviorAttributes { prelude_path, .. } = &attributes;
 
     let impl_generics = {
@@ -121,6 +153,10 @@ fn parse_common_token_stream(ast: &DeriveInput) -> syn::Result<CommonParsed> {
         network_incoming_behavior_to_impl: quote! { #prelude_path::NetworkIncomingBehavior },
         network_outgoing_behavior_to_impl: quote! { #prelude_path::NetworkOutgoingBehavior },
         handler_select: quote! { #prelude_path::ConnectionHandlerSelect },
+        handler_mux: quote! { #prelude_path::ConnectionHandlerMux },
+        mux_envelope: quote! { #prelude_path::MuxEnvelope },
+        inbound_only_handler: quote! { #prelude_path::InboundOnlyHandler },
+        outbound_only_handler: quote! { #prelude_path::OutboundOnlyHandler },
         t_handler: quote! { #prelude_path::THandler },
         t_handler_event: quote! { #prelude_path::THandlerEvent },
         t_handler_action: quote! { #prelude_path::THandlerAction },
@@ -128,7 +164,7 @@ fn parse_common_token_stream(ast: &DeriveInput) -> syn::Result<CommonParsed> {
         // inbound_stream_handler: quote! { #prelude_path::InboundStreamHandler },
         // outbound_stream_handler: quote! { #prelude_path::OutboundStreamHandler },
         either: quote! { #prelude_path::Either },
-        connection_error: quote! { #prelude_path::ConnectionError },
+        close_reason: quote! { #prelude_path::CloseReason },
         listen_error: quote! { #prelude_path::ListenError },
         dial_error: quote! { #prelude_path::DialError },
         dial_opts: quote! { #prelude_path::DialOpts },
@@ -138,6 +174,7 @@ fn parse_common_token_stream(ast: &DeriveInput) -> syn::Result<CommonParsed> {
     Ok(CommonParsed {
         prelude,
         attributes,
+        event_maps,
     })
 }
 
@@ -162,6 +199,7 @@ fn build_event_impl(
                 user_specified_out_event,
                 ..
             },
+        event_maps,
     } = common;
 
     // 结构体名称
@@ -172,10 +210,13 @@ fn build_event_impl(
     match user_specified_out_event {
         Some(name) => {
             let definition = None;
+            // 指定了映射函数的字段不需要 `From` 实现
             let from_clauses = data_struct
                 .fields
                 .iter()
-                .map(|field| {
+                .zip(event_maps)
+                .filter(|(_, event_map)| event_map.is_none())
+                .map(|(field, _)| {
                     let ty = &field.ty;
                     quote! {#name: From< <#ty as #network_behavior_to_impl>::Event >}
                 })
@@ -267,15 +308,16 @@ fn where_clause_token(
     ast: &DeriveInput,
     data_struct: &DataStruct,
     out_event_from_clauses: Vec<proc_macro2::TokenStream>,
-    trait_to_impl: &proc_macro2::TokenStream,
+    field_traits: &[&proc_macro2::TokenStream],
 ) -> Option<proc_macro2::TokenStream> {
     let (_, _, where_clause) = ast.generics.split_for_impl();
 
-    let where_clause = {
+    {
         let additional = data_struct
             .fields
             .iter()
-            .map(|field| {
+            .zip(field_traits)
+            .map(|(field, trait_to_impl)| {
                 let ty = &field.ty;
                 quote! {#ty: #trait_to_impl}
             })
@@ -291,14 +333,14 @@ fn where_clause_token(
         } else {
             Some(quote! {where #(#additional),*})
         }
-    };
-    where_clause
+    }
 }
 
 fn build_network_behavior_impl(
     ast: &DeriveInput,
     data_struct: &DataStruct,
     common_parsed: &CommonParsed,
+    roles: &[FieldRole],
 ) -> (proc_macro2::TokenStream, Vec<proc_macro2::TokenStream>) {
     // 结构体名称
     let name = &ast.ident;
@@ -312,7 +354,7 @@ fn build_network_behavior_impl(
         ast,
         data_struct,
         out_event_from_clauses.clone(),
-        &common_parsed.prelude.network_behavior_to_impl,
+        &vec![&common_parsed.prelude.network_behavior_to_impl; roles.len()],
     );
 
     let out_event_reference = if out_event_definition.is_some() {
@@ -329,6 +371,10 @@ fn build_network_behavior_impl(
                 connection_id,
                 network_behavior_to_impl,
                 handler_select,
+                handler_mux,
+                mux_envelope,
+                inbound_only_handler,
+                outbound_only_handler,
                 t_handler,
                 t_handler_event,
                 t_handler_action,
@@ -339,21 +385,44 @@ fn build_network_behavior_impl(
         ..
     } = &common_parsed;
 
+    let use_mux = use_handler_mux(data_struct);
+
     let connection_handler_ty = {
-        let mut ph_ty = None;
-        for field in data_struct.fields.iter() {
+        let field_infos = data_struct.fields.iter().zip(roles).map(|(field, role)| {
             let ty = &field.ty;
-            let field_info = quote! { #t_handler<#ty> };
-            match ph_ty {
-                Some(ev) => ph_ty = Some(quote! { #handler_select<#ev, #field_info> }),
-                ref mut ev @ None => *ev = Some(field_info),
+            match role {
+                FieldRole::Duplex => quote! { #t_handler<#ty> },
+                FieldRole::Incoming => quote! { #inbound_only_handler<#t_handler<#ty>> },
+                FieldRole::Outgoing => quote! { #outbound_only_handler<#t_handler<#ty>> },
+            }
+        });
+        if use_mux {
+            quote! { #handler_mux<(#(#field_infos,)*)> }
+        } else {
+            let mut ph_ty = None;
+            for field_info in field_infos {
+                match ph_ty {
+                    Some(ev) => ph_ty = Some(quote! { #handler_select<#ev, #field_info> }),
+                    ref mut ev @ None => *ev = Some(field_info),
+                }
             }
+            ph_ty.unwrap_or(quote! {()})
         }
-        ph_ty.unwrap_or(quote! {()})
     };
 
     let on_connection_handler_event_stmts = data_struct.fields.iter().enumerate().enumerate().map(
         |(enum_n, (field_n, field))| {
+            if use_mux {
+                let ty = &field.ty;
+                let field_name = match field.ident {
+                    Some(ref i) => quote! { self.#i },
+                    None => quote! { self.#field_n },
+                };
+                return quote! { #enum_n => {
+                    let ev = ev.downcast::<#t_handler_event<#ty>>().expect("event matches the handler at its index");
+                    #network_behavior_to_impl::on_connection_handler_event(&mut #field_name, id, peer_id, ev)
+                }};
+            }
             let mut elem = if enum_n != 0 {
                 quote! { #either::Right(ev) }
             } else {
@@ -364,15 +433,31 @@ fn build_network_behavior_impl(
                 elem = quote! { #either::Left(#elem) };
             }
 
-            Some(match field.ident {
+            match field.ident {
                 Some(ref i) => quote! { #elem => {
                 #network_behavior_to_impl::on_connection_handler_event(&mut self.#i, id, peer_id, ev) }},
                 None => quote! { #elem => {
                 #network_behavior_to_impl::on_connection_handler_event(&mut self.#field_n, id, peer_id, ev) }},
-            })
+            }
         },
     );
 
+    let on_connection_handler_event = if use_mux {
+        quote! {
+            let ev = event;
+            match ev.index() {
+                #(#on_connection_handler_event_stmts),*
+                index => unreachable!("Handler index {} out of range", index),
+            }
+        }
+    } else {
+        quote! {
+            match event {
+                #(#on_connection_handler_event_stmts),*
+            }
+        }
+    };
+
     let poll_stmts = data_struct
         .fields
         .iter()
@@ -383,16 +468,23 @@ fn build_network_behavior_impl(
                 .clone()
                 .expect("Fields of NetworkBehavior implementation to be named.");
 
-            let mut wrapped_event = if field_n != 0 {
-                quote! { #either::Right(event) }
+            let wrapped_event = if use_mux {
+                quote! { #mux_envelope::new(#field_n, event) }
             } else {
-                quote! { event }
+                let mut wrapped_event = if field_n != 0 {
+                    quote! { #either::Right(event) }
+                } else {
+                    quote! { event }
+                };
+                for _ in 0..data_struct.fields.len() - 1 - field_n {
+                    wrapped_event = quote! { #either::Left(#wrapped_event) };
+                }
+                wrapped_event
             };
-            for _ in 0..data_struct.fields.len() - 1 - field_n {
-                wrapped_event = quote! { #either::Left(#wrapped_event) };
-            }
 
-            let map_event = if out_event_definition.is_some() {
+            let map_event = if let Some(event_map) = &common_parsed.event_maps[field_n] {
+                quote! { #event_map }
+            } else if out_event_definition.is_some() {
                 let event_variant: syn::Variant =
                     syn::parse_str(&field.to_string().to_upper_camel_case())
                         .expect("field name to be a valid enum variant name");
@@ -425,9 +517,7 @@ fn build_network_behavior_impl(
                 peer_id: #peer_id,
                 event: #t_handler_event<Self>
             ) {
-                match event {
-                    #(#on_connection_handler_event_stmts),*
-                }
+                #on_connection_handler_event
             }
 
             fn poll(
@@ -441,19 +531,42 @@ fn build_network_behavior_impl(
         }
     };
 
-    return (final_quote, out_event_from_clauses);
+    (final_quote, out_event_from_clauses)
 }
 
 fn build_incoming_struct(ast: &DeriveInput, data_struct: &DataStruct) -> syn::Result<TokenStream> {
     let common_parsed = parse_common_token_stream(ast)?;
+    let roles = vec![FieldRole::Duplex; data_struct.fields.len()];
+
+    let (network_behavior_token, out_event_from_clauses) =
+        build_network_behavior_impl(ast, data_struct, &common_parsed, &roles);
+    let incoming_token = build_incoming_impl(
+        ast,
+        data_struct,
+        &common_parsed,
+        out_event_from_clauses,
+        &roles,
+    );
+
+    Ok(quote! {
+        #network_behavior_token
+        #incoming_token
+    }
+    .into())
+}
+
+fn build_incoming_impl(
+    ast: &DeriveInput,
+    data_struct: &DataStruct,
+    common_parsed: &CommonParsed,
+    out_event_from_clauses: Vec<proc_macro2::TokenStream>,
+    roles: &[FieldRole],
+) -> proc_macro2::TokenStream {
     // 结构体名称
     let name = &ast.ident;
     // ty_generics: 泛型参数, where_clause: where 子句
     let (_, ty_generics, _) = ast.generics.split_for_impl();
 
-    let (network_behavior_token, out_event_from_clauses) =
-        build_network_behavior_impl(ast, data_struct, &common_parsed);
-
     let CommonParsed {
         prelude:
             PreludeTokenStream {
@@ -462,22 +575,31 @@ fn build_incoming_struct(ast: &DeriveInput, data_struct: &DataStruct) -> syn::Re
                 listener_event,
                 connection_id,
                 connection_denied,
+                network_behavior_to_impl,
                 network_incoming_behavior_to_impl,
+                inbound_only_handler,
+                outbound_only_handler,
                 connection_handler,
+                handler_mux,
                 listen_error,
-                connection_error,
+                close_reason,
                 impl_generics,
                 ..
             },
         ..
-    } = &common_parsed;
+    } = common_parsed;
 
-    let where_clause = where_clause_token(
-        ast,
-        data_struct,
-        out_event_from_clauses,
-        network_incoming_behavior_to_impl,
-    );
+    let field_traits = roles
+        .iter()
+        .map(|role| {
+            if role.is_incoming() {
+                network_incoming_behavior_to_impl
+            } else {
+                network_behavior_to_impl
+            }
+        })
+        .collect::<Vec<_>>();
+    let where_clause = where_clause_token(ast, data_struct, out_event_from_clauses, &field_traits);
 
     // 生成 fn handle_pending_inbound_connection
     let handle_pending_inbound_connection_stmts =
@@ -485,6 +607,7 @@ fn build_incoming_struct(ast: &DeriveInput, data_struct: &DataStruct) -> syn::Re
             .fields
             .iter()
             .enumerate()
+            .filter(|(field_n, _)| roles[*field_n].is_incoming())
             .map(|(field_n, field)| {
                 match field.ident {
                     Some(ref i) => quote! {
@@ -497,7 +620,7 @@ fn build_incoming_struct(ast: &DeriveInput, data_struct: &DataStruct) -> syn::Re
             });
 
     let handle_established_inbound_connection = {
-        let mut out_handler = None;
+        let mut builders = Vec::new();
 
         for (field_n, field) in data_struct.fields.iter().enumerate() {
             let field_name = match field.ident {
@@ -505,21 +628,23 @@ fn build_incoming_struct(ast: &DeriveInput, data_struct: &DataStruct) -> syn::Re
                 None => quote! { self.#field_n },
             };
 
-            let builder = quote! {
-                #field_name.handle_established_connection(id, peer_id, local_addr, remote_addr)?
+            let builder = match roles[field_n] {
+                FieldRole::Duplex => quote! {
+                    #network_incoming_behavior_to_impl::handle_established_connection(&mut #field_name, id, peer_id, local_addr, remote_addr)?
+                },
+                FieldRole::Incoming => quote! {
+                    #inbound_only_handler::new(#network_incoming_behavior_to_impl::handle_established_connection(&mut #field_name, id, peer_id, local_addr, remote_addr)?)
+                },
+                FieldRole::Outgoing => quote! { #outbound_only_handler::disabled() },
             };
 
-            match out_handler {
-                Some(h) => out_handler = Some(quote! { #connection_handler::select(#h, #builder) }),
-                ref mut h @ None => *h = Some(builder),
-            }
+            builders.push(builder);
         }
-
-        out_handler.unwrap_or(quote! {()})
+        combine_handlers(data_struct, connection_handler, handler_mux, builders)
     };
 
     // 生成 on_listen_failure
-    let on_listen_failure_stmts = data_struct.fields.iter().enumerate().map(
+    let on_listen_failure_stmts = data_struct.fields.iter().enumerate().filter(|(field_n, _)| roles[*field_n].is_incoming()).map(
         |(field_n, field)| {
             match field.ident {
                 Some(ref i) => quote! {
@@ -533,7 +658,7 @@ fn build_incoming_struct(ast: &DeriveInput, data_struct: &DataStruct) -> syn::Re
     );
 
     // 生成 on_connection_established
-    let on_connection_established_stmts = data_struct.fields.iter().enumerate().map(
+    let on_connection_established_stmts = data_struct.fields.iter().enumerate().filter(|(field_n, _)| roles[*field_n].is_incoming()).map(
         |(field_n, field)| {
             match field.ident {
                 Some(ref i) => quote! {
@@ -547,7 +672,7 @@ fn build_incoming_struct(ast: &DeriveInput, data_struct: &DataStruct) -> syn::Re
     );
 
     // 生成 on_connection_closed
-    let on_connection_closed_stmts = data_struct.fields.iter().enumerate().map(
+    let on_connection_closed_stmts = data_struct.fields.iter().enumerate().filter(|(field_n, _)| roles[*field_n].is_incoming()).map(
         |(field_n, field)| {
             match field.ident {
                 Some(ref i) => quote! {
@@ -566,18 +691,18 @@ fn build_incoming_struct(ast: &DeriveInput, data_struct: &DataStruct) -> syn::Re
             .fields
             .iter()
             .enumerate()
+            .filter(|(field_n, _)| roles[*field_n].is_incoming())
             .map(|(field_n, field)| match field.ident {
                 Some(ref i) => quote! {
-                    self.#i.on_listener_event(event);
+                    #network_incoming_behavior_to_impl::on_listener_event(&mut self.#i, event);
                 },
                 None => quote! {
-                    self.#field_n.on_listener_event(event);
+                    #network_incoming_behavior_to_impl::on_listener_event(&mut self.#field_n, event);
                 },
             })
     };
 
-    let final_quote = quote! {
-        #network_behavior_token
+    quote! {
         impl #impl_generics #network_incoming_behavior_to_impl for #name #ty_generics
         #where_clause
         {
@@ -598,7 +723,9 @@ fn build_incoming_struct(ast: &DeriveInput, data_struct: &DataStruct) -> syn::Re
                 local_addr: &#addr,
                 remote_addr: &#addr
             ) -> Result<Self::ConnectionHandler, #connection_denied> {
-                Ok(#handle_established_inbound_connection)
+                // 只有一个字段时直接写 `Ok(..?)` 会触发 clippy::needless_question_mark
+                let handler = #handle_established_inbound_connection;
+                Ok(handler)
             }
 
             fn on_connection_established(
@@ -617,7 +744,7 @@ fn build_incoming_struct(ast: &DeriveInput, data_struct: &DataStruct) -> syn::Re
                 peer_id: #peer_id,
                 local_addr: &#addr,
                 remote_addr: &#addr,
-                reason: Option<&#connection_error>,
+                reason: &#close_reason,
             ) {
                 #(#on_connection_closed_stmts)*
             }
@@ -637,22 +764,42 @@ fn build_incoming_struct(ast: &DeriveInput, data_struct: &DataStruct) -> syn::Re
                 #(#on_listener_event_stmts)*
             }
         }
-
-    };
-
-    return Ok(final_quote.into());
+    }
 }
 
 fn build_outgoing_struct(ast: &DeriveInput, data_struct: &DataStruct) -> syn::Result<TokenStream> {
     let common_parsed = parse_common_token_stream(ast)?;
+    let roles = vec![FieldRole::Duplex; data_struct.fields.len()];
+
+    let (network_behavior_token, out_event_from_clauses) =
+        build_network_behavior_impl(ast, data_struct, &common_parsed, &roles);
+    let outgoing_token = build_outgoing_impl(
+        ast,
+        data_struct,
+        &common_parsed,
+        out_event_from_clauses,
+        &roles,
+    );
+
+    Ok(quote! {
+        #network_behavior_token
+        #outgoing_token
+    }
+    .into())
+}
+
+fn build_outgoing_impl(
+    ast: &DeriveInput,
+    data_struct: &DataStruct,
+    common_parsed: &CommonParsed,
+    out_event_from_clauses: Vec<proc_macro2::TokenStream>,
+    roles: &[FieldRole],
+) -> proc_macro2::TokenStream {
     // 结构体名称
     let name = &ast.ident;
     // ty_generics: 泛型参数, where_clause: where 子句
     let (_, ty_generics, _) = ast.generics.split_for_impl();
 
-    let (network_behavior_token, out_event_from_clauses) =
-        build_network_behavior_impl(ast, data_struct, &common_parsed);
-
     let CommonParsed {
         prelude:
             PreludeTokenStream {
@@ -660,23 +807,32 @@ fn build_outgoing_struct(ast: &DeriveInput, data_struct: &DataStruct) -> syn::Re
                 peer_id,
                 connection_id,
                 connection_denied,
+                network_behavior_to_impl,
                 network_outgoing_behavior_to_impl,
+                inbound_only_handler,
+                outbound_only_handler,
                 connection_handler,
+                handler_mux,
                 dial_error,
-                connection_error,
+                close_reason,
                 dial_opts,
                 impl_generics,
                 ..
             },
         ..
-    } = &common_parsed;
+    } = common_parsed;
 
-    let where_clause = where_clause_token(
-        ast,
-        data_struct,
-        out_event_from_clauses,
-        network_outgoing_behavior_to_impl,
-    );
+    let field_traits = roles
+        .iter()
+        .map(|role| {
+            if role.is_outgoing() {
+                network_outgoing_behavior_to_impl
+            } else {
+                network_behavior_to_impl
+            }
+        })
+        .collect::<Vec<_>>();
+    let where_clause = where_clause_token(ast, data_struct, out_event_from_clauses, &field_traits);
 
     let handle_pending_outbound_connection = {
         let extend_stmts =
@@ -684,6 +840,7 @@ fn build_outgoing_struct(ast: &DeriveInput, data_struct: &DataStruct) -> syn::Re
                 .fields
                 .iter()
                 .enumerate()
+                .filter(|(field_n, _)| roles[*field_n].is_outgoing())
                 .map(|(field_n, field)| {
                     match field.ident {
                         Some(ref i) => quote! {
@@ -707,7 +864,7 @@ fn build_outgoing_struct(ast: &DeriveInput, data_struct: &DataStruct) -> syn::Re
     };
 
     let handle_established_outbound_connection = {
-        let mut out_handler = None;
+        let mut builders = Vec::new();
 
         for (field_n, field) in data_struct.fields.iter().enumerate() {
             let field_name = match field.ident {
@@ -715,20 +872,23 @@ fn build_outgoing_struct(ast: &DeriveInput, data_struct: &DataStruct) -> syn::Re
                 None => quote! { self.#field_n },
             };
 
-            let builder = quote! {
-                #field_name.handle_established_connection(id, peer_id, addr)?
+            let builder = match roles[field_n] {
+                FieldRole::Duplex => quote! {
+                    #network_outgoing_behavior_to_impl::handle_established_connection(&mut #field_name, id, peer_id, addr)?
+                },
+                FieldRole::Outgoing => quote! {
+                    #outbound_only_handler::new(#network_outgoing_behavior_to_impl::handle_established_connection(&mut #field_name, id, peer_id, addr)?)
+                },
+                FieldRole::Incoming => quote! { #inbound_only_handler::disabled() },
             };
 
-            match out_handler {
-                Some(h) => out_handler = Some(quote! { #connection_handler::select(#h, #builder) }),
-                ref mut h @ None => *h = Some(builder),
-            }
+            builders.push(builder);
         }
-        out_handler.unwrap_or(quote! {()})
+        combine_handlers(data_struct, connection_handler, handler_mux, builders)
     };
 
     // 生成 on_connection_established
-    let on_connection_established_stmts = data_struct.fields.iter().enumerate().map(
+    let on_connection_established_stmts = data_struct.fields.iter().enumerate().filter(|(field_n, _)| roles[*field_n].is_outgoing()).map(
         |(field_n, field)| {
             match field.ident {
                 Some(ref i) => quote! {
@@ -742,7 +902,7 @@ fn build_outgoing_struct(ast: &DeriveInput, data_struct: &DataStruct) -> syn::Re
     );
 
     // 生成 on_connection_closed
-    let on_connection_closed_stmts = data_struct.fields.iter().enumerate().map(
+    let on_connection_closed_stmts = data_struct.fields.iter().enumerate().filter(|(field_n, _)| roles[*field_n].is_outgoing()).map(
         |(field_n, field)| {
             match field.ident {
                 Some(ref i) => quote! {
@@ -756,7 +916,7 @@ fn build_outgoing_struct(ast: &DeriveInput, data_struct: &DataStruct) -> syn::Re
     );
 
     // 生成 on_dial_failure
-    let on_dial_failure_stmts = data_struct.fields.iter().enumerate().map(
+    let on_dial_failure_stmts = data_struct.fields.iter().enumerate().filter(|(field_n, _)| roles[*field_n].is_outgoing()).map(
         |(field_n, field)| {
             match field.ident {
                 Some(ref i) => quote! {
@@ -769,21 +929,25 @@ fn build_outgoing_struct(ast: &DeriveInput, data_struct: &DataStruct) -> syn::Re
         },
     );
 
-    let poll_stmts = data_struct.fields.iter().enumerate().map(|(_, field)| {
-        let field = field
-            .ident
-            .clone()
-            .expect("Fields of NetworkBehavior implementation to be named.");
-        quote! {
-            match #network_outgoing_behavior_to_impl::poll_dial(&mut self.#field, cx) {
-                std::task::Poll::Ready(opts) => return std::task::Poll::Ready(opts),
-                std::task::Poll::Pending => {},
+    let poll_stmts = data_struct
+        .fields
+        .iter()
+        .zip(roles)
+        .filter(|(_, role)| role.is_outgoing())
+        .map(|(field, _)| {
+            let field = field
+                .ident
+                .clone()
+                .expect("Fields of NetworkBehavior implementation to be named.");
+            quote! {
+                match #network_outgoing_behavior_to_impl::poll_dial(&mut self.#field, cx) {
+                    std::task::Poll::Ready(opts) => return std::task::Poll::Ready(opts),
+                    std::task::Poll::Pending => {},
+                }
             }
-        }
-    });
+        });
 
-    let final_quote = quote! {
-        #network_behavior_token
+    quote! {
         impl #impl_generics #network_outgoing_behavior_to_impl for #name #ty_generics
         #where_clause
         {
@@ -802,7 +966,9 @@ fn build_outgoing_struct(ast: &DeriveInput, data_struct: &DataStruct) -> syn::Re
                 peer_id: #peer_id,
                 addr: &#addr,
             ) -> Result<Self::ConnectionHandler, #connection_denied> {
-                Ok(#handle_established_outbound_connection)
+                // 只有一个字段时直接写 `Ok(..?)` 会触发 clippy::needless_question_mark
+                let handler = #handle_established_outbound_connection;
+                Ok(handler)
             }
 
             /// 连接处理器事件处理
@@ -820,7 +986,7 @@ fn build_outgoing_struct(ast: &DeriveInput, data_struct: &DataStruct) -> syn::Re
                 id: #connection_id,
                 peer_id: #peer_id,
                 addr: &#addr,
-                reason: Option<&#connection_error>,
+                reason: &#close_reason,
             ) {
                 #(#on_connection_closed_stmts)*
             }
@@ -840,8 +1006,141 @@ fn build_outgoing_struct(ast: &DeriveInput, data_struct: &DataStruct) -> syn::Re
                 std::task::Poll::Pending
             }
         }
-    };
-    return Ok(final_quote.into());
+    }
+}
+
+fn build_duplex_struct(ast: &DeriveInput, data_struct: &DataStruct) -> syn::Result<TokenStream> {
+    let common_parsed = parse_common_token_stream(ast)?;
+    let roles = parse_field_roles(data_struct)?;
+
+    let (network_behavior_token, out_event_from_clauses) =
+        build_network_behavior_impl(ast, data_struct, &common_parsed, &roles);
+    let incoming_token = build_incoming_impl(
+        ast,
+        data_struct,
+        &common_parsed,
+        out_event_from_clauses.clone(),
+        &roles,
+    );
+    let outgoing_token = build_outgoing_impl(
+        ast,
+        data_struct,
+        &common_parsed,
+        out_event_from_clauses,
+        &roles,
+    );
+
+    Ok(quote! {
+        #network_behavior_token
+        #incoming_token
+        #outgoing_token
+    }
+    .into())
+}
+
+/// 超过两个子行为时使用扁平的 `ConnectionHandlerMux`，避免处理器类型逐层嵌套
+const MAX_MUX_HANDLERS: usize = 16;
+
+fn use_handler_mux(data_struct: &DataStruct) -> bool {
+    (3..=MAX_MUX_HANDLERS).contains(&data_struct.fields.len())
+}
+
+// 组合各字段的连接处理器
+fn combine_handlers(
+    data_struct: &DataStruct,
+    connection_handler: &proc_macro2::TokenStream,
+    handler_mux: &proc_macro2::TokenStream,
+    builders: Vec<proc_macro2::TokenStream>,
+) -> proc_macro2::TokenStream {
+    if use_handler_mux(data_struct) {
+        return quote! { #handler_mux::new((#(#builders,)*)) };
+    }
+    builders
+        .into_iter()
+        .reduce(|h, builder| quote! { #connection_handler::select(#h, #builder) })
+        .unwrap_or(quote! {()})
+}
+
+/// 字段参与的连接方向
+#[derive(Clone, Copy)]
+enum FieldRole {
+    Duplex,
+    Incoming,
+    Outgoing,
+}
+
+impl FieldRole {
+    fn is_incoming(&self) -> bool {
+        matches!(self, FieldRole::Duplex | FieldRole::Incoming)
+    }
+
+    fn is_outgoing(&self) -> bool {
+        matches!(self, FieldRole::Duplex | FieldRole::Outgoing)
+    }
+}
+
+// 解析字段上的 #[behavior(incoming)] 或 #[behavior(outgoing)] 属性
+fn parse_field_roles(data_struct: &DataStruct) -> syn::Result<Vec<FieldRole>> {
+    data_struct
+        .fields
+        .iter()
+        .map(|field| {
+            let mut role = FieldRole::Duplex;
+            for attr in field
+                .attrs
+                .iter()
+                .filter(|attr| attr.path().is_ident("behavior"))
+            {
+                let nested =
+                    attr.parse_args_with(Punctuated::<Meta, Token![,]>::parse_terminated)?;
+                for meta in nested {
+                    if meta.path().is_ident("incoming") {
+                        meta.require_path_only()?;
+                        role = FieldRole::Incoming;
+                    } else if meta.path().is_ident("outgoing") {
+                        meta.require_path_only()?;
+                        role = FieldRole::Outgoing;
+                    } else if meta.path().is_ident("map_event") {
+                        // 由 parse_field_event_maps 处理
+                    } else {
+                        return Err(syn::Error::new_spanned(
+                            meta,
+                            "expected `incoming`, `outgoing` or `map_event`",
+                        ));
+                    }
+                }
+            }
+            Ok(role)
+        })
+        .collect()
+}
+
+// 解析字段上的 #[behavior(map_event = "path::to_fn")] 属性
+//
+// 映射函数接收子行为的事件并返回结构体的输出事件，替代 `From` 转换
+fn parse_field_event_maps(data_struct: &DataStruct) -> syn::Result<Vec<Option<syn::Path>>> {
+    data_struct
+        .fields
+        .iter()
+        .map(|field| {
+            let mut event_map = None;
+            for attr in field
+                .attrs
+                .iter()
+                .filter(|attr| attr.path().is_ident("behavior"))
+            {
+                let nested =
+                    attr.parse_args_with(Punctuated::<Meta, Token![,]>::parse_terminated)?;
+                for meta in nested {
+                    if meta.path().is_ident("map_event") {
+                        let value = meta.require_name_value()?.value.require_str_lit()?;
+                        event_map = Some(syn::parse_str(&value)?);
+                    }
+                }
+            }
+            Ok(event_map)
+        })
+        .collect()
 }
 
 struct BehaviorAttributes {
@@ -0,0 +1,194 @@
+use futures::StreamExt;
+use volans_core::{PeerId, identity::KeyPair};
+use volans_swarm::{NetworkIncomingBehavior, NetworkOutgoingBehavior, client, server};
+use volans_swarm_test::{SwarmExt, connect, next_behavior_event};
+
+fn identify(_: &KeyPair) -> volans_identify::Behavior {
+    volans_identify::Behavior::new(volans_identify::Config::new("/test/1.0.0"))
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn derived_behavior_mux() {
+    use volans_request::{Config, codec::JsonCodec};
+    use volans_swarm::StreamProtocol;
+
+    const ECHO: StreamProtocol = StreamProtocol::new("/echo/1.0.0");
+    type Codec = JsonCodec<String, String>;
+
+    #[derive(NetworkOutgoingBehavior)]
+    #[behavior(prelude = "volans_swarm::derive_prelude")]
+    struct Client {
+        identify: volans_identify::Behavior,
+        stream: volans_stream::client::Behavior,
+        request: volans_request::client::Behavior<Codec>,
+    }
+
+    #[derive(NetworkIncomingBehavior)]
+    #[behavior(prelude = "volans_swarm::derive_prelude")]
+    struct Server {
+        identify: volans_identify::Behavior,
+        stream: volans_stream::server::Behavior,
+        request: volans_request::server::Behavior<Codec>,
+    }
+
+    let mut dialer = client::Swarm::new_ephemeral(|key| Client {
+        identify: identify(key),
+        stream: volans_stream::client::Behavior::new(),
+        request: volans_request::client::Behavior::with_codec(Codec::new(), Config::default()),
+    });
+    let mut listener = server::Swarm::new_ephemeral(|key| Server {
+        identify: identify(key),
+        stream: volans_stream::server::Behavior::new(),
+        request: volans_request::server::Behavior::with_codec(
+            Codec::new(),
+            [ECHO],
+            Config::default(),
+        ),
+    });
+    connect(&mut dialer, &mut listener).await;
+
+    let listener_peer = *listener.local_peer_id();
+    tokio::spawn(async move {
+        loop {
+            if let ServerEvent::Request(volans_request::server::Event::Request {
+                request,
+                responder,
+                ..
+            }) = next_behavior_event(&mut listener).await
+            {
+                responder.send_response(request).unwrap();
+            }
+        }
+    });
+    dialer
+        .behavior_mut()
+        .request
+        .send_request(listener_peer, ECHO, "ping".to_string());
+
+    let mut identified = false;
+    let mut response = None;
+    while !identified || response.is_none() {
+        match next_behavior_event(&mut dialer).await {
+            ClientEvent::Identify(volans_identify::Event::Received { peer_id, .. }) => {
+                assert_eq!(peer_id, listener_peer);
+                identified = true;
+            }
+            ClientEvent::Request(volans_request::client::Event::Response {
+                response: r, ..
+            }) => response = Some(r),
+            event => tracing::debug!("Ignoring behavior event: {event:?}"),
+        }
+    }
+    assert_eq!(response.as_deref(), Some("ping"));
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn derived_behavior_map_event() {
+    #[derive(Debug)]
+    enum Event {
+        Identified(PeerId),
+        Other,
+    }
+
+    impl From<std::convert::Infallible> for Event {
+        fn from(event: std::convert::Infallible) -> Self {
+            match event {}
+        }
+    }
+
+    fn map_identify(event: volans_identify::Event) -> Event {
+        match event {
+            volans_identify::Event::Received { peer_id, .. } => Event::Identified(peer_id),
+            _ => Event::Other,
+        }
+    }
+
+    #[derive(NetworkOutgoingBehavior)]
+    #[behavior(prelude = "volans_swarm::derive_prelude", out_event = "Event")]
+    struct Client {
+        #[behavior(map_event = "map_identify")]
+        identify: volans_identify::Behavior,
+        stream: volans_stream::client::Behavior,
+    }
+
+    let mut dialer = client::Swarm::new_ephemeral(|key| Client {
+        identify: identify(key),
+        stream: volans_stream::client::Behavior::new(),
+    });
+    let mut listener = server::Swarm::new_ephemeral(identify);
+    connect(&mut dialer, &mut listener).await;
+
+    let listener_peer = *listener.local_peer_id();
+    tokio::spawn(async move {
+        loop {
+            listener.next().await;
+        }
+    });
+    loop {
+        if let Event::Identified(peer_id) = next_behavior_event(&mut dialer).await {
+            assert_eq!(peer_id, listener_peer);
+            break;
+        }
+    }
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn derived_duplex_behavior() {
+    use volans_request::{Config, codec::JsonCodec};
+    use volans_swarm::{NetworkDuplexBehavior, StreamProtocol, duplex};
+
+    const ECHO: StreamProtocol = StreamProtocol::new("/echo/1.0.0");
+    type Codec = JsonCodec<String, String>;
+
+    #[derive(NetworkDuplexBehavior)]
+    #[behavior(prelude = "volans_swarm::derive_prelude")]
+    struct Node {
+        identify: volans_identify::Behavior,
+        #[behavior(incoming)]
+        responder: volans_request::server::Behavior<Codec>,
+        #[behavior(outgoing)]
+        requester: volans_request::client::Behavior<Codec>,
+    }
+
+    let node = |key: &KeyPair| Node {
+        identify: identify(key),
+        responder: volans_request::server::Behavior::with_codec(
+            Codec::new(),
+            [ECHO],
+            Config::default(),
+        ),
+        requester: volans_request::client::Behavior::with_codec(Codec::new(), Config::default()),
+    };
+    let mut dialer = duplex::Swarm::new_ephemeral(node);
+    let mut listener = duplex::Swarm::new_ephemeral(node);
+    connect(&mut dialer, &mut listener).await;
+
+    // 出站连接上只有请求方，入站连接上只有应答方
+    let listener_peer = *listener.local_peer_id();
+    tokio::spawn(async move {
+        loop {
+            if let NodeEvent::Responder(volans_request::server::Event::Request {
+                request,
+                responder,
+                ..
+            }) = next_behavior_event(&mut listener).await
+            {
+                responder.send_response(request).unwrap();
+            }
+        }
+    });
+    dialer
+        .behavior_mut()
+        .requester
+        .send_request(listener_peer, ECHO, "ping".to_string());
+
+    loop {
+        match next_behavior_event(&mut dialer).await {
+            NodeEvent::Requester(volans_request::client::Event::Response { response, .. }) => {
+                assert_eq!(response, "ping");
+                break;
+            }
+            event => tracing::debug!("Ignoring behavior event: {event:?}"),
+        }
+    }
+}
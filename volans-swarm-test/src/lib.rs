@@ -0,0 +1,395 @@
+//! Swarm 测试工具
+//!
+//! 使用随机身份与本地 TCP 传输（`plaintext` 认证、`muxing` 多路复用）创建临时节点，
+//! 便于在 tokio 运行时中为行为编写集成测试。使用 `current_thread` 运行时时，
+//! [`TokioExecutor`] 派发的连接任务与测试在同一线程上按确定的顺序执行。
+//! 需要控制时间时使用 [`sim`]，节点经由进程内传输连接并跟随模拟时钟。
+
+use std::{fmt, io};
+
+use futures::{
+    StreamExt,
+    future::{self, Either},
+};
+use volans_core::{
+    Multiaddr, PeerId, Transport, TransportError, identity::KeyPair, muxing::StreamMuxerBox,
+    transport,
+};
+use volans_swarm::{
+    DialOpts, InboundStreamHandler, ListenerId, NetworkBehavior, NetworkIncomingBehavior,
+    NetworkOutgoingBehavior, OutboundStreamHandler, client, connection::PoolConfig, duplex,
+    error::DialError, server, sim::Simulation,
+};
+
+pub mod memory;
+pub mod sim;
+
+pub use volans_swarm::executor::TokioExecutor;
+
+/// 随机生成的节点身份
+pub fn ephemeral_key_pair() -> KeyPair {
+    KeyPair::from_bytes(&rand::random())
+}
+
+fn ephemeral_transport(
+    key_pair: &KeyPair,
+    muxer: volans_muxing::Config,
+) -> transport::Boxed<(PeerId, StreamMuxerBox)> {
+    volans_tcp::Config::new()
+        .upgrade()
+        .authenticate(volans_plaintext::Config::new(key_pair.verifying_key()))
+        .multiplex(muxer)
+        .boxed()
+}
+
+/// 节点身份对应的临时传输层与节点 ID，用于以自定义配置创建 Swarm
+pub fn ephemeral_parts(key_pair: &KeyPair) -> (transport::Boxed<(PeerId, StreamMuxerBox)>, PeerId) {
+    (
+        ephemeral_transport(key_pair, volans_muxing::Config::new()),
+        PeerId::from_public_key(&key_pair.verifying_key()),
+    )
+}
+
+/// 与 [`ephemeral_parts`] 相同，但使用给定的多路复用配置
+pub fn ephemeral_parts_with_muxer(
+    key_pair: &KeyPair,
+    muxer: volans_muxing::Config,
+) -> (transport::Boxed<(PeerId, StreamMuxerBox)>, PeerId) {
+    (
+        ephemeral_transport(key_pair, muxer),
+        PeerId::from_public_key(&key_pair.verifying_key()),
+    )
+}
+
+/// 绑定后立即释放，得到一个没有监听者的地址
+pub fn unused_addr() -> Multiaddr {
+    let port = std::net::TcpListener::bind("127.0.0.1:0")
+        .expect("bind loopback")
+        .local_addr()
+        .expect("local address")
+        .port();
+    format!("/ip4/127.0.0.1/tcp/{port}")
+        .parse()
+        .expect("valid multiaddr")
+}
+
+pub type BehaviorEventOf<S> = <<S as SwarmExt>::Behavior as NetworkBehavior>::Event;
+
+/// 三种 Swarm 的统一测试接口
+pub trait SwarmExt: futures::Stream<Item = Self::SwarmEvent> + Unpin + Sized {
+    type Behavior: NetworkBehavior;
+    type SwarmEvent: fmt::Debug;
+
+    /// 以给定的传输层与连接池配置创建节点
+    fn from_parts(
+        transport: transport::Boxed<(PeerId, StreamMuxerBox)>,
+        behavior: Self::Behavior,
+        peer_id: PeerId,
+        config: PoolConfig,
+    ) -> Self;
+
+    /// 以随机身份创建节点，`behavior_fn` 接收节点的密钥
+    fn new_ephemeral(behavior_fn: impl FnOnce(&KeyPair) -> Self::Behavior) -> Self {
+        let key_pair = ephemeral_key_pair();
+        let (transport, peer_id) = ephemeral_parts(&key_pair);
+        Self::from_parts(
+            transport,
+            behavior_fn(&key_pair),
+            peer_id,
+            PoolConfig::with_tokio_executor(),
+        )
+    }
+
+    /// 以随机身份创建运行在 `sim` 中的节点，使用进程内传输
+    fn new_simulated(
+        sim: &Simulation,
+        behavior_fn: impl FnOnce(&KeyPair) -> Self::Behavior,
+    ) -> Self {
+        let key_pair = ephemeral_key_pair();
+        let transport = memory::MemoryTransport
+            .upgrade()
+            .authenticate(volans_plaintext::Config::new(key_pair.verifying_key()))
+            .multiplex(volans_muxing::Config::new())
+            .boxed();
+        let peer_id = PeerId::from_public_key(&key_pair.verifying_key());
+        // 构造行为时创建的计时器同样跟随模拟时钟
+        let _guard = sim.enter();
+        Self::from_parts(
+            transport,
+            behavior_fn(&key_pair),
+            peer_id,
+            sim.pool_config(),
+        )
+    }
+
+    fn local_peer_id(&self) -> &PeerId;
+
+    /// 连接建立事件的对端
+    fn established_peer(event: &Self::SwarmEvent) -> Option<PeerId>;
+
+    fn into_behavior_event(
+        event: Self::SwarmEvent,
+    ) -> Result<BehaviorEventOf<Self>, Self::SwarmEvent>;
+}
+
+/// 可以监听的 Swarm
+pub trait ListenSwarm: SwarmExt {
+    fn listen_on(&mut self, addr: Multiaddr) -> Result<ListenerId, TransportError<io::Error>>;
+
+    fn new_listen_addr(event: &Self::SwarmEvent) -> Option<&Multiaddr>;
+}
+
+/// 可以拨号的 Swarm
+pub trait DialSwarm: SwarmExt {
+    fn dial(&mut self, opts: DialOpts) -> Result<Multiaddr, DialError>;
+}
+
+impl<TBehavior> SwarmExt for client::Swarm<TBehavior>
+where
+    TBehavior: NetworkOutgoingBehavior,
+    TBehavior::ConnectionHandler: OutboundStreamHandler,
+    TBehavior::Event: fmt::Debug,
+{
+    type Behavior = TBehavior;
+    type SwarmEvent = client::SwarmEvent<TBehavior::Event>;
+
+    fn from_parts(
+        transport: transport::Boxed<(PeerId, StreamMuxerBox)>,
+        behavior: Self::Behavior,
+        peer_id: PeerId,
+        config: PoolConfig,
+    ) -> Self {
+        client::Swarm::new(transport, behavior, peer_id, config)
+    }
+
+    fn local_peer_id(&self) -> &PeerId {
+        client::Swarm::local_peer_id(self)
+    }
+
+    fn established_peer(event: &Self::SwarmEvent) -> Option<PeerId> {
+        match event {
+            client::SwarmEvent::ConnectionEstablished { peer_id, .. } => Some(*peer_id),
+            _ => None,
+        }
+    }
+
+    fn into_behavior_event(
+        event: Self::SwarmEvent,
+    ) -> Result<BehaviorEventOf<Self>, Self::SwarmEvent> {
+        match event {
+            client::SwarmEvent::Behavior(event) => Ok(event),
+            event => Err(event),
+        }
+    }
+}
+
+impl<TBehavior> DialSwarm for client::Swarm<TBehavior>
+where
+    TBehavior: NetworkOutgoingBehavior,
+    TBehavior::ConnectionHandler: OutboundStreamHandler,
+    TBehavior::Event: fmt::Debug,
+{
+    fn dial(&mut self, opts: DialOpts) -> Result<Multiaddr, DialError> {
+        client::Swarm::dial(self, opts)
+    }
+}
+
+impl<TBehavior> SwarmExt for server::Swarm<TBehavior>
+where
+    TBehavior: NetworkIncomingBehavior,
+    TBehavior::ConnectionHandler: InboundStreamHandler,
+    TBehavior::Event: fmt::Debug,
+{
+    type Behavior = TBehavior;
+    type SwarmEvent = server::SwarmEvent<TBehavior::Event>;
+
+    fn from_parts(
+        transport: transport::Boxed<(PeerId, StreamMuxerBox)>,
+        behavior: Self::Behavior,
+        peer_id: PeerId,
+        config: PoolConfig,
+    ) -> Self {
+        server::Swarm::new(transport, behavior, peer_id, config)
+    }
+
+    fn local_peer_id(&self) -> &PeerId {
+        server::Swarm::local_peer_id(self)
+    }
+
+    fn established_peer(event: &Self::SwarmEvent) -> Option<PeerId> {
+        match event {
+            server::SwarmEvent::ConnectionEstablished { peer_id, .. } => Some(*peer_id),
+            _ => None,
+        }
+    }
+
+    fn into_behavior_event(
+        event: Self::SwarmEvent,
+    ) -> Result<BehaviorEventOf<Self>, Self::SwarmEvent> {
+        match event {
+            server::SwarmEvent::Behavior(event) => Ok(event),
+            event => Err(event),
+        }
+    }
+}
+
+impl<TBehavior> ListenSwarm for server::Swarm<TBehavior>
+where
+    TBehavior: NetworkIncomingBehavior,
+    TBehavior::ConnectionHandler: InboundStreamHandler,
+    TBehavior::Event: fmt::Debug,
+{
+    fn listen_on(&mut self, addr: Multiaddr) -> Result<ListenerId, TransportError<io::Error>> {
+        server::Swarm::listen_on(self, addr)
+    }
+
+    fn new_listen_addr(event: &Self::SwarmEvent) -> Option<&Multiaddr> {
+        match event {
+            server::SwarmEvent::NewListenAddr { addr, .. } => Some(addr),
+            _ => None,
+        }
+    }
+}
+
+impl<TBehavior> SwarmExt for duplex::Swarm<TBehavior>
+where
+    TBehavior: NetworkIncomingBehavior + NetworkOutgoingBehavior,
+    TBehavior::ConnectionHandler: InboundStreamHandler + OutboundStreamHandler,
+    TBehavior::Event: fmt::Debug,
+{
+    type Behavior = TBehavior;
+    type SwarmEvent = duplex::SwarmEvent<TBehavior::Event>;
+
+    fn from_parts(
+        transport: transport::Boxed<(PeerId, StreamMuxerBox)>,
+        behavior: Self::Behavior,
+        peer_id: PeerId,
+        config: PoolConfig,
+    ) -> Self {
+        duplex::Swarm::new(transport, behavior, peer_id, config)
+    }
+
+    fn local_peer_id(&self) -> &PeerId {
+        duplex::Swarm::local_peer_id(self)
+    }
+
+    fn established_peer(event: &Self::SwarmEvent) -> Option<PeerId> {
+        match event {
+            duplex::SwarmEvent::ConnectionEstablished { peer_id, .. } => Some(*peer_id),
+            _ => None,
+        }
+    }
+
+    fn into_behavior_event(
+        event: Self::SwarmEvent,
+    ) -> Result<BehaviorEventOf<Self>, Self::SwarmEvent> {
+        match event {
+            duplex::SwarmEvent::Behavior(event) => Ok(event),
+            event => Err(event),
+        }
+    }
+}
+
+impl<TBehavior> ListenSwarm for duplex::Swarm<TBehavior>
+where
+    TBehavior: NetworkIncomingBehavior + NetworkOutgoingBehavior,
+    TBehavior::ConnectionHandler: InboundStreamHandler + OutboundStreamHandler,
+    TBehavior::Event: fmt::Debug,
+{
+    fn listen_on(&mut self, addr: Multiaddr) -> Result<ListenerId, TransportError<io::Error>> {
+        duplex::Swarm::listen_on(self, addr)
+    }
+
+    fn new_listen_addr(event: &Self::SwarmEvent) -> Option<&Multiaddr> {
+        match event {
+            duplex::SwarmEvent::NewListenAddr { addr, .. } => Some(addr),
+            _ => None,
+        }
+    }
+}
+
+impl<TBehavior> DialSwarm for duplex::Swarm<TBehavior>
+where
+    TBehavior: NetworkIncomingBehavior + NetworkOutgoingBehavior,
+    TBehavior::ConnectionHandler: InboundStreamHandler + OutboundStreamHandler,
+    TBehavior::Event: fmt::Debug,
+{
+    fn dial(&mut self, opts: DialOpts) -> Result<Multiaddr, DialError> {
+        duplex::Swarm::dial(self, opts)
+    }
+}
+
+pub async fn next_swarm_event<S: SwarmExt>(swarm: &mut S) -> S::SwarmEvent {
+    swarm.next().await.expect("Swarm stream never ends")
+}
+
+/// 驱动节点直到 `matcher` 返回 `Some`，之前的事件被丢弃
+pub async fn wait_for_event<S, E>(
+    swarm: &mut S,
+    mut matcher: impl FnMut(S::SwarmEvent) -> Option<E>,
+) -> E
+where
+    S: SwarmExt,
+{
+    loop {
+        let event = next_swarm_event(swarm).await;
+        let description = format!("{event:?}");
+        match matcher(event) {
+            Some(matched) => return matched,
+            None => tracing::debug!("Ignoring swarm event: {description}"),
+        }
+    }
+}
+
+/// 驱动节点直到产生下一个行为事件
+pub async fn next_behavior_event<S: SwarmExt>(swarm: &mut S) -> BehaviorEventOf<S> {
+    wait_for_event(swarm, |event| S::into_behavior_event(event).ok()).await
+}
+
+/// 在本地随机端口上监听，返回实际的监听地址
+pub async fn listen<S: ListenSwarm>(swarm: &mut S) -> Multiaddr {
+    swarm
+        .listen_on("/ip4/127.0.0.1/tcp/0".parse().expect("valid multiaddr"))
+        .expect("listen on loopback");
+    wait_for_event(swarm, |event| S::new_listen_addr(&event).cloned()).await
+}
+
+/// 让 `listener` 监听新的本地地址并由 `dialer` 拨号，驱动双方直到连接建立
+///
+/// 连接建立之前双方产生的其他事件被丢弃
+pub async fn connect<A, B>(dialer: &mut A, listener: &mut B)
+where
+    A: DialSwarm,
+    B: ListenSwarm,
+{
+    let addr = listen(listener).await;
+    let listener_peer = *listener.local_peer_id();
+    let dialer_peer = *dialer.local_peer_id();
+    dialer
+        .dial(DialOpts::new(Some(addr), Some(listener_peer)))
+        .expect("dial listener");
+
+    let mut dialer_connected = false;
+    let mut listener_connected = false;
+    while !(dialer_connected && listener_connected) {
+        match future::select(dialer.next(), listener.next()).await {
+            Either::Left((Some(event), _)) => {
+                if A::established_peer(&event) == Some(listener_peer) {
+                    dialer_connected = true;
+                } else {
+                    tracing::debug!("Ignoring dialer event: {event:?}");
+                }
+            }
+            Either::Right((Some(event), _)) => {
+                if B::established_peer(&event) == Some(dialer_peer) {
+                    listener_connected = true;
+                } else {
+                    tracing::debug!("Ignoring listener event: {event:?}");
+                }
+            }
+            Either::Left((None, _)) | Either::Right((None, _)) => {
+                unreachable!("Swarm stream never ends")
+            }
+        }
+    }
+}
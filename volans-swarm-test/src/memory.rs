@@ -0,0 +1,256 @@
+//! 进程内传输
+//!
+//! 地址形如 `/memory/<port>`，端口为 0 时分配新端口。连接的两端共享内存中的缓冲区，
+//! 读写只依赖 waker，不需要运行时，可在 [`Simulation`](volans_swarm::sim::Simulation) 中使用。
+
+use std::{
+    collections::{HashMap, VecDeque},
+    io,
+    pin::Pin,
+    sync::{
+        Arc, LazyLock, Mutex,
+        atomic::{AtomicU64, Ordering},
+    },
+    task::{Context, Poll, Waker},
+};
+
+use futures::{AsyncRead, AsyncWrite, Stream, channel::mpsc, future};
+use volans_core::{
+    Multiaddr, Transport, TransportError,
+    multiaddr::Protocol,
+    transport::{Listener, ListenerEvent, SocketOptions},
+};
+
+static NEXT_PORT: AtomicU64 = AtomicU64::new(1);
+
+/// 监听端口上等待接受的连接与拨号方的端口
+type Incoming = mpsc::UnboundedSender<(Channel, u64)>;
+
+static LISTENERS: LazyLock<Mutex<HashMap<u64, Incoming>>> = LazyLock::new(Default::default);
+
+fn memory_port(addr: &Multiaddr) -> Option<u64> {
+    let mut iter = addr.iter();
+    match (iter.next(), iter.next()) {
+        (Some(Protocol::Memory(port)), None) => Some(port),
+        _ => None,
+    }
+}
+
+fn memory_addr(port: u64) -> Multiaddr {
+    Multiaddr::empty().with(Protocol::Memory(port))
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MemoryTransport;
+
+impl Transport for MemoryTransport {
+    type Output = Channel;
+    type Error = io::Error;
+    type Dial = future::Ready<Result<Channel, io::Error>>;
+    type Incoming = future::Ready<Result<Channel, io::Error>>;
+    type Listener = MemoryListener;
+
+    fn dial(&self, addr: Multiaddr) -> Result<Self::Dial, TransportError<Self::Error>> {
+        let Some(port) = memory_port(&addr) else {
+            return Err(TransportError::NotSupported(addr));
+        };
+        let listeners = LISTENERS.lock().expect("listeners lock poisoned");
+        let result = match listeners.get(&port) {
+            Some(sender) => {
+                let (local, remote) = Channel::pair();
+                let local_port = NEXT_PORT.fetch_add(1, Ordering::Relaxed);
+                sender
+                    .unbounded_send((remote, local_port))
+                    .map(|()| local)
+                    .map_err(|_| io::ErrorKind::ConnectionRefused.into())
+            }
+            None => Err(io::ErrorKind::ConnectionRefused.into()),
+        };
+        Ok(future::ready(result))
+    }
+
+    fn listen(
+        &self,
+        addr: Multiaddr,
+        _opts: &SocketOptions,
+    ) -> Result<Self::Listener, TransportError<Self::Error>> {
+        let Some(port) = memory_port(&addr) else {
+            return Err(TransportError::NotSupported(addr));
+        };
+        let port = match port {
+            0 => NEXT_PORT.fetch_add(1, Ordering::Relaxed),
+            port => port,
+        };
+        let mut listeners = LISTENERS.lock().expect("listeners lock poisoned");
+        if listeners.contains_key(&port) {
+            return Err(TransportError::Other(io::ErrorKind::AddrInUse.into()));
+        }
+        let (sender, receiver) = mpsc::unbounded();
+        listeners.insert(port, sender);
+        Ok(MemoryListener {
+            port,
+            receiver,
+            announced: false,
+            closed: false,
+        })
+    }
+}
+
+pub struct MemoryListener {
+    port: u64,
+    receiver: mpsc::UnboundedReceiver<(Channel, u64)>,
+    announced: bool,
+    closed: bool,
+}
+
+impl MemoryListener {
+    fn close(&mut self) {
+        if !self.closed {
+            self.closed = true;
+            LISTENERS
+                .lock()
+                .expect("listeners lock poisoned")
+                .remove(&self.port);
+        }
+    }
+}
+
+impl Listener for MemoryListener {
+    type Output = Channel;
+    type Error = io::Error;
+    type Upgrade = future::Ready<Result<Channel, io::Error>>;
+
+    fn poll_event(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<ListenerEvent<Self::Upgrade, Self::Error>> {
+        if self.closed {
+            return Poll::Ready(ListenerEvent::Closed(Ok(())));
+        }
+        if !self.announced {
+            self.announced = true;
+            return Poll::Ready(ListenerEvent::NewAddress(memory_addr(self.port)));
+        }
+        match Pin::new(&mut self.receiver).poll_next(cx) {
+            Poll::Ready(Some((channel, remote_port))) => Poll::Ready(ListenerEvent::Incoming {
+                local_addr: memory_addr(self.port),
+                remote_addr: memory_addr(remote_port),
+                upgrade: future::ready(Ok(channel)),
+            }),
+            Poll::Ready(None) => {
+                self.close();
+                Poll::Ready(ListenerEvent::Closed(Ok(())))
+            }
+            Poll::Pending => Poll::Pending,
+        }
+    }
+
+    fn poll_close(
+        mut self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+    ) -> Poll<Result<(), Self::Error>> {
+        self.close();
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl Drop for MemoryListener {
+    fn drop(&mut self) {
+        self.close();
+    }
+}
+
+#[derive(Default)]
+struct Pipe {
+    buffer: VecDeque<u8>,
+    closed: bool,
+    reader: Option<Waker>,
+}
+
+impl Pipe {
+    fn close(&mut self) {
+        self.closed = true;
+        if let Some(waker) = self.reader.take() {
+            waker.wake();
+        }
+    }
+}
+
+/// 内存连接的一端
+pub struct Channel {
+    incoming: Arc<Mutex<Pipe>>,
+    outgoing: Arc<Mutex<Pipe>>,
+}
+
+impl Channel {
+    fn pair() -> (Channel, Channel) {
+        let a = Arc::new(Mutex::new(Pipe::default()));
+        let b = Arc::new(Mutex::new(Pipe::default()));
+        (
+            Channel {
+                incoming: a.clone(),
+                outgoing: b.clone(),
+            },
+            Channel {
+                incoming: b,
+                outgoing: a,
+            },
+        )
+    }
+}
+
+impl AsyncRead for Channel {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        let mut pipe = self.incoming.lock().expect("pipe lock poisoned");
+        if pipe.buffer.is_empty() {
+            if pipe.closed {
+                return Poll::Ready(Ok(0));
+            }
+            pipe.reader = Some(cx.waker().clone());
+            return Poll::Pending;
+        }
+        let len = buf.len().min(pipe.buffer.len());
+        for (slot, byte) in buf.iter_mut().zip(pipe.buffer.drain(..len)) {
+            *slot = byte;
+        }
+        Poll::Ready(Ok(len))
+    }
+}
+
+impl AsyncWrite for Channel {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let mut pipe = self.outgoing.lock().expect("pipe lock poisoned");
+        if pipe.closed {
+            return Poll::Ready(Err(io::ErrorKind::BrokenPipe.into()));
+        }
+        pipe.buffer.extend(buf);
+        if let Some(waker) = pipe.reader.take() {
+            waker.wake();
+        }
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.outgoing.lock().expect("pipe lock poisoned").close();
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl Drop for Channel {
+    fn drop(&mut self) {
+        self.outgoing.lock().expect("pipe lock poisoned").close();
+        self.incoming.lock().expect("pipe lock poisoned").close();
+    }
+}
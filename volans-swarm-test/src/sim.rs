@@ -0,0 +1,163 @@
+//! 在 [`Simulation`] 中驱动节点
+//!
+//! 节点与连接任务都在当前线程上轮询，时间只在 [`advance`] 时前进。每一步之后从
+//! [`SimSwarm`] 取出节点产生的事件，同样的操作序列总是得到同样的事件。
+
+use std::{
+    collections::VecDeque,
+    ops::{Deref, DerefMut},
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use futures::StreamExt;
+use volans_core::{Multiaddr, identity::KeyPair};
+use volans_swarm::DialOpts;
+
+pub use volans_swarm::sim::Simulation;
+
+use crate::{BehaviorEventOf, DialSwarm, ListenSwarm, SwarmExt};
+
+/// 运行在模拟中的节点，记录轮询产生的事件
+pub struct SimSwarm<S: SwarmExt> {
+    swarm: S,
+    events: VecDeque<S::SwarmEvent>,
+}
+
+impl<S: SwarmExt> SimSwarm<S> {
+    pub fn new(sim: &Simulation, behavior_fn: impl FnOnce(&KeyPair) -> S::Behavior) -> Self {
+        Self {
+            swarm: S::new_simulated(sim, behavior_fn),
+            events: VecDeque::new(),
+        }
+    }
+
+    /// 取出目前产生的全部事件
+    pub fn take_events(&mut self) -> Vec<S::SwarmEvent> {
+        self.events.drain(..).collect()
+    }
+
+    /// 取出目前产生的行为事件，其他事件被丢弃
+    pub fn take_behavior_events(&mut self) -> Vec<BehaviorEventOf<S>> {
+        self.events
+            .drain(..)
+            .filter_map(|event| S::into_behavior_event(event).ok())
+            .collect()
+    }
+}
+
+impl<S: SwarmExt> Deref for SimSwarm<S> {
+    type Target = S;
+
+    fn deref(&self) -> &S {
+        &self.swarm
+    }
+}
+
+impl<S: SwarmExt> DerefMut for SimSwarm<S> {
+    fn deref_mut(&mut self) -> &mut S {
+        &mut self.swarm
+    }
+}
+
+/// 可由 [`run_until_stalled`] 轮询的节点
+pub trait Node {
+    /// 轮询直到没有新事件，返回是否产生了事件
+    fn poll_events(&mut self, cx: &mut Context<'_>) -> bool;
+}
+
+impl<S: SwarmExt> Node for SimSwarm<S> {
+    fn poll_events(&mut self, cx: &mut Context<'_>) -> bool {
+        let mut progressed = false;
+        while let Poll::Ready(Some(event)) = self.swarm.poll_next_unpin(cx) {
+            self.events.push_back(event);
+            progressed = true;
+        }
+        progressed
+    }
+}
+
+/// 交替轮询节点与连接任务，直到都不再有进展
+pub fn run_until_stalled(sim: &Simulation, nodes: &mut [&mut dyn Node]) {
+    let _guard = sim.enter();
+    let waker = futures::task::noop_waker();
+    let mut cx = Context::from_waker(&waker);
+    loop {
+        let mut progressed = false;
+        for node in nodes.iter_mut() {
+            progressed |= node.poll_events(&mut cx);
+        }
+        if sim.has_woken_tasks() {
+            sim.run_until_stalled();
+            progressed = true;
+        }
+        if !progressed {
+            return;
+        }
+    }
+}
+
+/// 推进模拟时钟，在每个到期时刻运行节点与连接任务
+pub fn advance(sim: &Simulation, nodes: &mut [&mut dyn Node], duration: Duration) {
+    let clock = sim.clock();
+    let target = clock.now() + duration;
+    run_until_stalled(sim, nodes);
+    while let Some(deadline) = clock.next_deadline()
+        && deadline <= target
+    {
+        clock.advance(deadline.saturating_sub(clock.now()));
+        run_until_stalled(sim, nodes);
+    }
+    clock.advance(target.saturating_sub(clock.now()));
+    run_until_stalled(sim, nodes);
+}
+
+/// 在新的内存地址上监听，返回监听地址，之前的事件被丢弃
+pub fn listen<S: ListenSwarm>(sim: &Simulation, swarm: &mut SimSwarm<S>) -> Multiaddr {
+    swarm
+        .listen_on("/memory/0".parse().expect("valid multiaddr"))
+        .expect("listen on memory");
+    run_until_stalled(sim, &mut [swarm]);
+    while let Some(event) = swarm.events.pop_front() {
+        if let Some(addr) = S::new_listen_addr(&event) {
+            return addr.clone();
+        }
+    }
+    panic!("listener reported no address")
+}
+
+/// 让 `listener` 监听并由 `dialer` 拨号，运行到双方连接建立
+///
+/// 连接建立之前双方产生的事件被丢弃
+pub fn connect<A, B>(sim: &Simulation, dialer: &mut SimSwarm<A>, listener: &mut SimSwarm<B>)
+where
+    A: DialSwarm,
+    B: ListenSwarm,
+{
+    let addr = listen(sim, listener);
+    let listener_peer = *listener.local_peer_id();
+    let dialer_peer = *dialer.local_peer_id();
+    dialer
+        .dial(DialOpts::new(Some(addr), Some(listener_peer)))
+        .expect("dial listener");
+    run_until_stalled(sim, &mut [dialer, listener]);
+    assert!(
+        skip_until(&mut dialer.events, |event| A::established_peer(event)
+            == Some(listener_peer)),
+        "dialer did not connect"
+    );
+    assert!(
+        skip_until(&mut listener.events, |event| B::established_peer(event)
+            == Some(dialer_peer)),
+        "listener did not accept"
+    );
+}
+
+fn skip_until<E>(events: &mut VecDeque<E>, mut matcher: impl FnMut(&E) -> bool) -> bool {
+    while let Some(event) = events.pop_front() {
+        if matcher(&event) {
+            return true;
+        }
+    }
+    false
+}
@@ -0,0 +1,300 @@
+use std::{
+    collections::HashMap,
+    io,
+    pin::Pin,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicU64, Ordering},
+    },
+    task::{Context, Poll},
+};
+
+use futures::{AsyncRead, AsyncWrite};
+use volans_core::{
+    ConnectedPoint, Multiaddr, StreamMuxer,
+    muxing::{StreamMuxerBox, SubstreamBox},
+};
+
+use crate::ConnectionId;
+
+/// 收发的字节数
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct BandwidthStats {
+    pub inbound: u64,
+    pub outbound: u64,
+}
+
+impl BandwidthStats {
+    fn add(&mut self, other: BandwidthStats) {
+        self.inbound += other.inbound;
+        self.outbound += other.outbound;
+    }
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct Counters {
+    inbound: AtomicU64,
+    outbound: AtomicU64,
+}
+
+impl Counters {
+    pub(crate) fn record_inbound(&self, n: usize) {
+        self.inbound.fetch_add(n as u64, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_outbound(&self, n: usize) {
+        self.outbound.fetch_add(n as u64, Ordering::Relaxed);
+    }
+
+    fn stats(&self) -> BandwidthStats {
+        BandwidthStats {
+            inbound: self.inbound.load(Ordering::Relaxed),
+            outbound: self.outbound.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// 连接内按子流协议统计的流量，由连接持有并在协商完成后登记子流
+#[derive(Debug, Default, Clone)]
+pub(crate) struct StreamStats(Arc<Mutex<HashMap<String, Arc<Counters>>>>);
+
+impl StreamStats {
+    /// 协议对应的计数器，同一协议的子流共用
+    pub(crate) fn counters(&self, protocol: &str) -> Arc<Counters> {
+        let mut protocols = self.0.lock().expect("lock not poisoned");
+        match protocols.get(protocol) {
+            Some(counters) => counters.clone(),
+            None => protocols.entry(protocol.to_string()).or_default().clone(),
+        }
+    }
+
+    fn stats(&self) -> HashMap<String, BandwidthStats> {
+        self.0
+            .lock()
+            .expect("lock not poisoned")
+            .iter()
+            .map(|(protocol, counters)| (protocol.clone(), counters.stats()))
+            .collect()
+    }
+}
+
+#[derive(Debug)]
+struct TrackedConnection {
+    /// 传输协议
+    tag: String,
+    counters: Arc<Counters>,
+    streams: StreamStats,
+}
+
+/// 按连接、传输协议与子流协议统计的流量
+///
+/// 统计的是子流上读写的字节数，不含多路复用与加密的开销。
+#[derive(Debug, Default)]
+pub struct Bandwidth {
+    connections: HashMap<ConnectionId, TrackedConnection>,
+    /// 已关闭连接按协议累计的流量
+    closed: HashMap<String, BandwidthStats>,
+    /// 已关闭连接按子流协议累计的流量
+    closed_streams: HashMap<String, BandwidthStats>,
+}
+
+impl Bandwidth {
+    /// 已建立连接的流量
+    pub fn connection(&self, id: &ConnectionId) -> Option<BandwidthStats> {
+        self.connections
+            .get(id)
+            .map(|connection| connection.counters.stats())
+    }
+
+    /// 已建立连接按子流协议（如 `/v1/ping`）统计的流量
+    pub fn connection_streams(&self, id: &ConnectionId) -> Option<HashMap<String, BandwidthStats>> {
+        self.connections
+            .get(id)
+            .map(|connection| connection.streams.stats())
+    }
+
+    /// 按子流协议累计的流量，包含已关闭的连接
+    ///
+    /// 子流在协议协商完成后才开始计数，协商本身的流量只计入连接。
+    pub fn by_stream_protocol(&self) -> HashMap<String, BandwidthStats> {
+        let mut protocols = self.closed_streams.clone();
+        for connection in self.connections.values() {
+            for (protocol, stats) in connection.streams.stats() {
+                protocols.entry(protocol).or_default().add(stats);
+            }
+        }
+        protocols
+    }
+
+    /// 按传输协议（如 `/ip4/tcp/ws`）累计的流量，包含已关闭的连接
+    pub fn by_protocol(&self) -> HashMap<&str, BandwidthStats> {
+        let mut protocols = self
+            .closed
+            .iter()
+            .map(|(tag, stats)| (tag.as_str(), *stats))
+            .collect::<HashMap<_, _>>();
+        for connection in self.connections.values() {
+            protocols
+                .entry(connection.tag.as_str())
+                .or_default()
+                .add(connection.counters.stats());
+        }
+        protocols
+    }
+
+    /// 所有连接累计的流量
+    pub fn total(&self) -> BandwidthStats {
+        let mut total = BandwidthStats::default();
+        for stats in self.by_protocol().into_values() {
+            total.add(stats);
+        }
+        total
+    }
+
+    /// 为新建立的连接统计流量，返回的 [`StreamStats`] 交由连接登记子流
+    pub(crate) fn track(
+        &mut self,
+        id: ConnectionId,
+        endpoint: &ConnectedPoint,
+        muxer: StreamMuxerBox,
+    ) -> (StreamMuxerBox, StreamStats) {
+        let addr = match endpoint {
+            ConnectedPoint::Dialer { addr } => addr,
+            ConnectedPoint::Listener { local_addr, .. } => local_addr,
+        };
+        let counters = Arc::new(Counters::default());
+        let streams = StreamStats::default();
+        self.connections.insert(
+            id,
+            TrackedConnection {
+                tag: protocol_tag(addr),
+                counters: counters.clone(),
+                streams: streams.clone(),
+            },
+        );
+        let muxer = StreamMuxerBox::new(InstrumentedMuxer {
+            inner: muxer,
+            counters,
+        });
+        (muxer, streams)
+    }
+
+    /// 连接关闭，返回该连接的流量
+    pub(crate) fn remove(&mut self, id: &ConnectionId) -> BandwidthStats {
+        match self.connections.remove(id) {
+            Some(connection) => {
+                for (protocol, stats) in connection.streams.stats() {
+                    self.closed_streams.entry(protocol).or_default().add(stats);
+                }
+                let stats = connection.counters.stats();
+                self.closed.entry(connection.tag).or_default().add(stats);
+                stats
+            }
+            None => BandwidthStats::default(),
+        }
+    }
+}
+
+fn protocol_tag(addr: &Multiaddr) -> String {
+    addr.protocol_stack()
+        .filter(|tag| *tag != "peer")
+        .fold(String::new(), |tag, protocol| tag + "/" + protocol)
+}
+
+struct InstrumentedMuxer {
+    inner: StreamMuxerBox,
+    counters: Arc<Counters>,
+}
+
+impl InstrumentedMuxer {
+    fn instrument(&self, substream: SubstreamBox) -> InstrumentedStream {
+        InstrumentedStream {
+            inner: substream,
+            counters: self.counters.clone(),
+        }
+    }
+}
+
+impl StreamMuxer for InstrumentedMuxer {
+    type Substream = InstrumentedStream;
+    type Error = io::Error;
+
+    fn poll_inbound(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<Self::Substream, Self::Error>> {
+        let this = self.get_mut();
+        Pin::new(&mut this.inner)
+            .poll_inbound(cx)
+            .map_ok(|substream| this.instrument(substream))
+    }
+
+    fn poll_outbound(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<Self::Substream, Self::Error>> {
+        let this = self.get_mut();
+        Pin::new(&mut this.inner)
+            .poll_outbound(cx)
+            .map_ok(|substream| this.instrument(substream))
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Pin::new(&mut self.get_mut().inner).poll_close(cx)
+    }
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Pin::new(&mut self.get_mut().inner).poll(cx)
+    }
+
+    fn protocol(&self) -> Option<&'static str> {
+        self.inner.protocol()
+    }
+
+    fn dropped_inbound_streams(&self) -> u64 {
+        self.inner.dropped_inbound_streams()
+    }
+}
+
+struct InstrumentedStream {
+    inner: SubstreamBox,
+    counters: Arc<Counters>,
+}
+
+impl AsyncRead for InstrumentedStream {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        let result = Pin::new(&mut this.inner).poll_read(cx, buf);
+        if let Poll::Ready(Ok(n)) = result {
+            this.counters.record_inbound(n);
+        }
+        result
+    }
+}
+
+impl AsyncWrite for InstrumentedStream {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        let result = Pin::new(&mut this.inner).poll_write(cx, buf);
+        if let Poll::Ready(Ok(n)) = result {
+            this.counters.record_outbound(n);
+        }
+        result
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().inner).poll_flush(cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().inner).poll_close(cx)
+    }
+}
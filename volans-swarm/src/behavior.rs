@@ -1,7 +1,11 @@
 mod either;
+mod external_addresses;
 mod listen_addresses;
+mod toggle;
 
+pub use external_addresses::ExternalAddresses;
 pub use listen_addresses::ListenAddresses;
+pub use toggle::Toggle;
 
 use std::task::{Context, Poll};
 
@@ -10,7 +14,7 @@ use volans_core::{Multiaddr, PeerId};
 use crate::{
     ConnectionDenied, ConnectionHandler, ConnectionId, DialOpts, ListenerId, THandlerAction,
     THandlerEvent,
-    error::{ConnectionError, DialError, ListenError},
+    error::{CloseReason, DialError, ListenError},
 };
 
 pub trait NetworkBehavior: Send + 'static {
@@ -66,7 +70,7 @@ pub trait NetworkIncomingBehavior: NetworkBehavior {
         _peer_id: PeerId,
         _local_addr: &Multiaddr,
         _remote_addr: &Multiaddr,
-        _reason: Option<&ConnectionError>,
+        _reason: &CloseReason,
     ) {
     }
 
@@ -116,7 +120,7 @@ pub trait NetworkOutgoingBehavior: NetworkBehavior {
         _id: ConnectionId,
         _peer_id: PeerId,
         _addr: &Multiaddr,
-        _reason: Option<&ConnectionError>,
+        _reason: &CloseReason,
     ) {
     }
 
@@ -148,6 +152,10 @@ pub enum ListenerEvent<'a> {
     ListenerError(ListenerError<'a>),
     /// 监听器关闭事件
     ListenerClosed(ListenerClosed<'a>),
+    /// 外部地址被确认可达
+    ExternalAddrConfirmed(ExternalAddrConfirmed<'a>),
+    /// 外部地址被移除
+    ExternalAddrExpired(ExternalAddrExpired<'a>),
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -179,6 +187,16 @@ pub struct ExpiredListenAddr<'a> {
     pub addr: &'a Multiaddr,
 }
 
+#[derive(Debug, Clone, Copy)]
+pub struct ExternalAddrConfirmed<'a> {
+    pub addr: &'a Multiaddr,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ExternalAddrExpired<'a> {
+    pub addr: &'a Multiaddr,
+}
+
 #[derive(Debug)]
 #[non_exhaustive]
 pub enum BehaviorEvent<TEvent, THandlerAction> {
@@ -195,6 +213,14 @@ pub enum BehaviorEvent<TEvent, THandlerAction> {
         peer_id: PeerId,
         connection: CloseConnection,
     },
+    /// 发现对端的可拨号地址，记录到 Swarm 的地址簿
+    NewPeerAddress { peer_id: PeerId, addr: Multiaddr },
+    /// 对端观察到的本地地址，由 Swarm 的外部地址簿计分确认
+    NewExternalAddrCandidate { peer_id: PeerId, addr: Multiaddr },
+    /// 行为确认的外部地址，如端口映射的结果，无需计分直接确认
+    ExternalAddrConfirmed { addr: Multiaddr },
+    /// 行为确认的外部地址失效
+    ExternalAddrExpired { addr: Multiaddr },
 }
 
 impl<TEvent, THandlerAction> BehaviorEvent<TEvent, THandlerAction> {
@@ -220,6 +246,18 @@ impl<TEvent, THandlerAction> BehaviorEvent<TEvent, THandlerAction> {
                 peer_id,
                 connection,
             },
+            BehaviorEvent::NewPeerAddress { peer_id, addr } => {
+                BehaviorEvent::NewPeerAddress { peer_id, addr }
+            }
+            BehaviorEvent::NewExternalAddrCandidate { peer_id, addr } => {
+                BehaviorEvent::NewExternalAddrCandidate { peer_id, addr }
+            }
+            BehaviorEvent::ExternalAddrConfirmed { addr } => {
+                BehaviorEvent::ExternalAddrConfirmed { addr }
+            }
+            BehaviorEvent::ExternalAddrExpired { addr } => {
+                BehaviorEvent::ExternalAddrExpired { addr }
+            }
         }
     }
 
@@ -245,6 +283,18 @@ impl<TEvent, THandlerAction> BehaviorEvent<TEvent, THandlerAction> {
                 peer_id,
                 connection,
             },
+            BehaviorEvent::NewPeerAddress { peer_id, addr } => {
+                BehaviorEvent::NewPeerAddress { peer_id, addr }
+            }
+            BehaviorEvent::NewExternalAddrCandidate { peer_id, addr } => {
+                BehaviorEvent::NewExternalAddrCandidate { peer_id, addr }
+            }
+            BehaviorEvent::ExternalAddrConfirmed { addr } => {
+                BehaviorEvent::ExternalAddrConfirmed { addr }
+            }
+            BehaviorEvent::ExternalAddrExpired { addr } => {
+                BehaviorEvent::ExternalAddrExpired { addr }
+            }
         }
     }
 }
@@ -252,7 +302,35 @@ impl<TEvent, THandlerAction> BehaviorEvent<TEvent, THandlerAction> {
 #[derive(Debug, Clone)]
 pub enum NotifyHandler {
     One(ConnectionId),
+    /// 对端的任意一个连接，按 Swarm 配置的 [`SelectionPolicy`] 选择
     Any,
+    /// 对端的任意一个连接，按指定的 [`SelectionPolicy`] 选择
+    AnyWith(SelectionPolicy),
+    /// 限定在给定连接中的任意一个，未指定策略时使用 Swarm 配置的 [`SelectionPolicy`]
+    AnyOf {
+        connections: Vec<ConnectionId>,
+        policy: Option<SelectionPolicy>,
+    },
+    /// 对端的所有连接，动作经 [`ConnectionHandler::clone_action`] 逐个复制
+    ///
+    /// 各连接独立投递，某个连接的通道已满不影响其余连接；动作不可复制时退化为 [`NotifyHandler::Any`]。
+    All,
+}
+
+/// [`NotifyHandler::Any`] 在对端多个连接之间的选择策略
+///
+/// 按策略排列连接后依次尝试，跳过命令通道已满的连接。
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SelectionPolicy {
+    /// 第一个就绪的连接
+    #[default]
+    FirstReady,
+    /// 最久未收到动作的连接
+    RoundRobin,
+    /// 尚未处理的动作最少的连接
+    LeastPendingCommands,
+    /// 随机选择
+    Random,
 }
 
 #[derive(Debug, Clone, Default)]
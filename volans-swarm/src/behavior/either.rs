@@ -1,12 +1,12 @@
 use std::task::{Context, Poll};
 
 use either::Either;
-use volans_core::{PeerId, Multiaddr};
+use volans_core::{Multiaddr, PeerId};
 
 use crate::{
     BehaviorEvent, ConnectionDenied, ConnectionId, DialOpts, ListenerEvent, NetworkBehavior,
     NetworkIncomingBehavior, NetworkOutgoingBehavior, THandler, THandlerAction, THandlerEvent,
-    error::{ConnectionError, DialError, ListenError},
+    error::{CloseReason, DialError, ListenError},
 };
 
 impl<L, R> NetworkBehavior for Either<L, R>
@@ -109,7 +109,7 @@ where
         peer_id: PeerId,
         local_addr: &Multiaddr,
         remote_addr: &Multiaddr,
-        reason: Option<&ConnectionError>,
+        reason: &CloseReason,
     ) {
         match self {
             Either::Left(left) => {
@@ -195,7 +195,7 @@ where
         id: ConnectionId,
         peer_id: PeerId,
         addr: &Multiaddr,
-        reason: Option<&ConnectionError>,
+        reason: &CloseReason,
     ) {
         match self {
             Either::Left(left) => left.on_connection_closed(id, peer_id, addr, reason),
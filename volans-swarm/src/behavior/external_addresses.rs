@@ -0,0 +1,31 @@
+use std::collections::HashSet;
+
+use volans_core::Multiaddr;
+
+use crate::ListenerEvent;
+
+/// 根据监听器事件跟踪已确认的外部地址
+#[derive(Debug, Default, Clone)]
+pub struct ExternalAddresses {
+    addresses: HashSet<Multiaddr>,
+}
+
+impl ExternalAddresses {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Multiaddr> {
+        self.addresses.iter()
+    }
+
+    pub fn on_listener_event(&mut self, event: &ListenerEvent) -> bool {
+        match event {
+            ListenerEvent::ExternalAddrConfirmed(confirmed) => {
+                self.addresses.insert(confirmed.addr.clone())
+            }
+            ListenerEvent::ExternalAddrExpired(expired) => self.addresses.remove(expired.addr),
+            _ => false,
+        }
+    }
+}
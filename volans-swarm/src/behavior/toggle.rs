@@ -0,0 +1,353 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    task::{Context, Poll},
+};
+
+use either::Either;
+use volans_core::{Multiaddr, PeerId};
+
+use crate::{
+    BehaviorEvent, ConnectionDenied, ConnectionHandler, ConnectionId, DialOpts, ListenerEvent,
+    NetworkBehavior, NetworkIncomingBehavior, NetworkOutgoingBehavior, THandler, THandlerAction,
+    THandlerEvent,
+    behavior::NotifyHandler,
+    error::{CloseReason, DialError, ListenError},
+    handler::DummyHandler,
+};
+
+/// 可在运行时启用或停用的行为
+///
+/// 停用后新建立的连接使用 [`DummyHandler`]，内部行为不会感知这些连接；
+/// 已经使用内部处理器的连接不受影响，直到关闭。
+pub struct Toggle<TBehavior>
+where
+    TBehavior: NetworkBehavior,
+{
+    inner: TBehavior,
+    enabled: bool,
+    /// 每个对端的连接及其是否使用内部处理器
+    connections: HashMap<PeerId, HashMap<ConnectionId, bool>>,
+    /// [`NotifyHandler::All`] 拆分出的逐连接动作
+    pending_actions: VecDeque<(PeerId, ConnectionId, THandlerAction<TBehavior>)>,
+}
+
+impl<TBehavior> Toggle<TBehavior>
+where
+    TBehavior: NetworkBehavior,
+{
+    pub fn new(inner: TBehavior, enabled: bool) -> Self {
+        Self {
+            inner,
+            enabled,
+            connections: HashMap::new(),
+            pending_actions: VecDeque::new(),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// 启用或停用内部行为，只对之后建立的连接生效
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+    }
+
+    pub fn inner(&self) -> &TBehavior {
+        &self.inner
+    }
+
+    pub fn inner_mut(&mut self) -> &mut TBehavior {
+        &mut self.inner
+    }
+
+    pub fn into_inner(self) -> TBehavior {
+        self.inner
+    }
+
+    /// 连接建立时记录其处理器，处理器在同一轮事件中按当前开关创建
+    fn add_connection(&mut self, id: ConnectionId, peer_id: PeerId) -> bool {
+        self.connections
+            .entry(peer_id)
+            .or_default()
+            .insert(id, self.enabled);
+        self.enabled
+    }
+
+    fn remove_connection(&mut self, id: ConnectionId, peer_id: PeerId) -> bool {
+        let Some(connections) = self.connections.get_mut(&peer_id) else {
+            return false;
+        };
+        let enabled = connections.remove(&id).unwrap_or(false);
+        if connections.is_empty() {
+            self.connections.remove(&peer_id);
+        }
+        enabled
+    }
+
+    /// 对端存在空处理器连接时，把选择任意连接的通知限定到使用内部处理器的连接，
+    /// 选择策略仍由 Swarm 决定；[`NotifyHandler::All`] 拆分为逐个连接的动作
+    fn resolve_handler(
+        &mut self,
+        peer_id: PeerId,
+        handler: NotifyHandler,
+        action: THandlerAction<TBehavior>,
+    ) -> Option<(NotifyHandler, THandlerAction<TBehavior>)> {
+        if let NotifyHandler::One(_) = handler {
+            return Some((handler, action));
+        }
+        let Some(connections) = self.connections.get(&peer_id) else {
+            return Some((handler, action));
+        };
+        if connections.values().all(|enabled| *enabled) {
+            return Some((handler, action));
+        }
+        let enabled: Vec<ConnectionId> = connections
+            .iter()
+            .filter_map(|(id, enabled)| enabled.then_some(*id))
+            .collect();
+        if enabled.is_empty() {
+            return None;
+        }
+        let handler = match handler {
+            NotifyHandler::One(_) => unreachable!("returned above"),
+            NotifyHandler::Any => NotifyHandler::AnyOf {
+                connections: enabled,
+                policy: None,
+            },
+            NotifyHandler::AnyWith(policy) => NotifyHandler::AnyOf {
+                connections: enabled,
+                policy: Some(policy),
+            },
+            NotifyHandler::AnyOf {
+                mut connections,
+                policy,
+            } => {
+                connections.retain(|id| enabled.contains(id));
+                NotifyHandler::AnyOf {
+                    connections,
+                    policy,
+                }
+            }
+            NotifyHandler::All => {
+                let mut cloned = Vec::with_capacity(enabled.len() - 1);
+                for id in &enabled[1..] {
+                    match THandler::<TBehavior>::clone_action(&action) {
+                        Some(action) => cloned.push((peer_id, *id, action)),
+                        // 动作不可复制时与 Swarm 一致，退化为任意一个连接
+                        None => {
+                            return Some((
+                                NotifyHandler::AnyOf {
+                                    connections: enabled,
+                                    policy: None,
+                                },
+                                action,
+                            ));
+                        }
+                    }
+                }
+                self.pending_actions.extend(cloned);
+                NotifyHandler::One(enabled[0])
+            }
+        };
+        Some((handler, action))
+    }
+}
+
+impl<TBehavior> NetworkBehavior for Toggle<TBehavior>
+where
+    TBehavior: NetworkBehavior,
+{
+    type ConnectionHandler = Either<THandler<TBehavior>, DummyHandler>;
+    type Event = TBehavior::Event;
+
+    fn on_connection_handler_event(
+        &mut self,
+        id: ConnectionId,
+        peer_id: PeerId,
+        event: THandlerEvent<Self>,
+    ) {
+        match event {
+            Either::Left(event) => self.inner.on_connection_handler_event(id, peer_id, event),
+            Either::Right(event) => match event {},
+        }
+    }
+
+    fn poll(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<BehaviorEvent<Self::Event, THandlerAction<Self>>> {
+        if let Some((peer_id, id, action)) = self.pending_actions.pop_front() {
+            return Poll::Ready(BehaviorEvent::HandlerAction {
+                peer_id,
+                handler: NotifyHandler::One(id),
+                action: Either::Left(action),
+            });
+        }
+        loop {
+            let event = match self.inner.poll(cx) {
+                Poll::Ready(BehaviorEvent::HandlerAction {
+                    peer_id,
+                    handler,
+                    action,
+                }) => match self.resolve_handler(peer_id, handler, action) {
+                    Some((handler, action)) => BehaviorEvent::HandlerAction {
+                        peer_id,
+                        handler,
+                        action,
+                    },
+                    None => {
+                        tracing::debug!(%peer_id, "No enabled connection for handler action");
+                        continue;
+                    }
+                },
+                Poll::Ready(event) => event,
+                Poll::Pending => return Poll::Pending,
+            };
+            return Poll::Ready(event.map_handler_action(Either::Left));
+        }
+    }
+}
+
+impl<TBehavior> NetworkIncomingBehavior for Toggle<TBehavior>
+where
+    TBehavior: NetworkIncomingBehavior,
+{
+    fn handle_pending_connection(
+        &mut self,
+        id: ConnectionId,
+        local_addr: &Multiaddr,
+        remote_addr: &Multiaddr,
+    ) -> Result<(), ConnectionDenied> {
+        if !self.enabled {
+            return Ok(());
+        }
+        self.inner
+            .handle_pending_connection(id, local_addr, remote_addr)
+    }
+
+    fn handle_established_connection(
+        &mut self,
+        id: ConnectionId,
+        peer_id: PeerId,
+        local_addr: &Multiaddr,
+        remote_addr: &Multiaddr,
+    ) -> Result<Self::ConnectionHandler, ConnectionDenied> {
+        if !self.enabled {
+            return Ok(Either::Right(DummyHandler));
+        }
+        self.inner
+            .handle_established_connection(id, peer_id, local_addr, remote_addr)
+            .map(Either::Left)
+    }
+
+    fn on_connection_established(
+        &mut self,
+        id: ConnectionId,
+        peer_id: PeerId,
+        local_addr: &Multiaddr,
+        remote_addr: &Multiaddr,
+    ) {
+        if self.add_connection(id, peer_id) {
+            self.inner
+                .on_connection_established(id, peer_id, local_addr, remote_addr);
+        }
+    }
+
+    fn on_connection_closed(
+        &mut self,
+        id: ConnectionId,
+        peer_id: PeerId,
+        local_addr: &Multiaddr,
+        remote_addr: &Multiaddr,
+        reason: &CloseReason,
+    ) {
+        if self.remove_connection(id, peer_id) {
+            self.inner
+                .on_connection_closed(id, peer_id, local_addr, remote_addr, reason);
+        }
+    }
+
+    fn on_listen_failure(
+        &mut self,
+        id: ConnectionId,
+        peer_id: Option<PeerId>,
+        local_addr: &Multiaddr,
+        remote_addr: &Multiaddr,
+        error: &ListenError,
+    ) {
+        self.inner
+            .on_listen_failure(id, peer_id, local_addr, remote_addr, error);
+    }
+
+    fn on_listener_event(&mut self, event: ListenerEvent<'_>) {
+        self.inner.on_listener_event(event);
+    }
+}
+
+impl<TBehavior> NetworkOutgoingBehavior for Toggle<TBehavior>
+where
+    TBehavior: NetworkOutgoingBehavior,
+{
+    fn handle_pending_connection(
+        &mut self,
+        id: ConnectionId,
+        maybe_peer: Option<PeerId>,
+        addr: &Option<Multiaddr>,
+    ) -> Result<Option<Multiaddr>, ConnectionDenied> {
+        if !self.enabled {
+            return Ok(addr.clone());
+        }
+        self.inner.handle_pending_connection(id, maybe_peer, addr)
+    }
+
+    fn handle_established_connection(
+        &mut self,
+        id: ConnectionId,
+        peer_id: PeerId,
+        addr: &Multiaddr,
+    ) -> Result<Self::ConnectionHandler, ConnectionDenied> {
+        if !self.enabled {
+            return Ok(Either::Right(DummyHandler));
+        }
+        self.inner
+            .handle_established_connection(id, peer_id, addr)
+            .map(Either::Left)
+    }
+
+    fn on_connection_established(&mut self, id: ConnectionId, peer_id: PeerId, addr: &Multiaddr) {
+        if self.add_connection(id, peer_id) {
+            self.inner.on_connection_established(id, peer_id, addr);
+        }
+    }
+
+    fn on_connection_closed(
+        &mut self,
+        id: ConnectionId,
+        peer_id: PeerId,
+        addr: &Multiaddr,
+        reason: &CloseReason,
+    ) {
+        if self.remove_connection(id, peer_id) {
+            self.inner.on_connection_closed(id, peer_id, addr, reason);
+        }
+    }
+
+    fn on_dial_failure(
+        &mut self,
+        id: ConnectionId,
+        peer_id: Option<PeerId>,
+        addr: Option<&Multiaddr>,
+        error: &DialError,
+    ) {
+        self.inner.on_dial_failure(id, peer_id, addr, error);
+    }
+
+    /// 停用时不再发起新的拨号
+    fn poll_dial(&mut self, cx: &mut Context<'_>) -> Poll<DialOpts> {
+        if !self.enabled {
+            return Poll::Pending;
+        }
+        self.inner.poll_dial(cx)
+    }
+}
@@ -1,36 +1,38 @@
 use std::{
-    collections::VecDeque,
     pin::Pin,
     task::{Context, Poll},
 };
 
-use futures::Stream;
-use volans_core::{
-    ConnectedPoint, Multiaddr, PeerId, Transport, muxing::StreamMuxerBox, transport,
+use futures::{
+    Stream, StreamExt,
+    channel::{mpsc, oneshot},
+    future,
 };
+use volans_core::{Multiaddr, PeerId, muxing::StreamMuxerBox, transport};
 
 use crate::{
-    BehaviorEvent, ConnectionId, DialOpts, NetworkOutgoingBehavior, OutboundStreamHandler,
-    PeerCondition, PendingNotifyHandler, THandlerAction, THandlerEvent,
-    behavior::{CloseConnection, NotifyHandler},
-    connection::{Pool, PoolConfig, PoolEvent},
-    error::{ConnectionError, DialError},
-    notify_any, notify_one,
+    Bandwidth, ConnectionId, ConnectionInfo, DialOpts, NetworkOutgoingBehavior,
+    OutboundStreamHandler, PeerStore,
+    connection::PoolConfig,
+    error::{DialError, SwarmClosed},
+    handle::{self, BehaviorCommand, CommandSender},
+    observer::SwarmObserver,
+    replay::Subscription,
+    swarm::{AddressEvent, SwarmCore},
 };
 
+pub use crate::SwarmEvent;
+
 pub struct Swarm<TBehavior>
 where
     TBehavior: NetworkOutgoingBehavior,
     TBehavior::ConnectionHandler: OutboundStreamHandler,
 {
-    behavior: TBehavior,
-    transport: transport::Boxed<(PeerId, StreamMuxerBox)>,
-    pool: Pool<TBehavior::ConnectionHandler>,
-    /// 等待Handler操作
-    pending_handler_action: Option<(PeerId, PendingNotifyHandler, THandlerAction<TBehavior>)>,
-
-    /// Swarm 等待处理的事件
-    pending_swarm_events: VecDeque<SwarmEvent<TBehavior::Event>>,
+    core: SwarmCore<TBehavior>,
+
+    /// [`SwarmHandle`] 发来的命令
+    command_sender: CommandSender<Command<TBehavior>>,
+    command_receiver: mpsc::Receiver<Command<TBehavior>>,
 }
 
 impl<TBehavior> Unpin for Swarm<TBehavior>
@@ -51,273 +53,139 @@ where
         local_peer_id: PeerId,
         config: PoolConfig,
     ) -> Self {
+        let (command_sender, command_receiver) = handle::channel();
         Self {
-            behavior,
-            transport,
-            pool: Pool::new(local_peer_id, config),
-            pending_handler_action: None,
-            pending_swarm_events: VecDeque::new(),
+            core: SwarmCore::new(transport, behavior, local_peer_id, config),
+            command_sender,
+            command_receiver,
+        }
+    }
+
+    /// 添加事件观察者，可添加多个，按添加顺序通知
+    pub fn with_observer(
+        mut self,
+        observer: impl SwarmObserver<TBehavior::Event> + 'static,
+    ) -> Self {
+        self.core.observers.push(Box::new(observer));
+        self
+    }
+
+    /// 替换默认的地址簿
+    pub fn with_peer_store(mut self, peer_store: PeerStore) -> Self {
+        self.core.dial.peer_store = peer_store;
+        self
+    }
+
+    /// 可在其他任务中使用的句柄，命令在 Swarm 被轮询时执行
+    pub fn handle(&self) -> SwarmHandle<TBehavior> {
+        SwarmHandle {
+            commands: self.command_sender.clone(),
         }
     }
 
     /// 关闭指定的连接
     pub fn close_connection(&mut self, connection_id: ConnectionId) -> bool {
-        if let Some(established) = self.pool.get_established(connection_id) {
-            established.start_close();
-            return true;
+        self.core.close_connection(connection_id)
+    }
+
+    /// 优雅关闭 Swarm
+    ///
+    /// 拒绝新的拨号，中断等待中的连接并关闭所有已建立的连接，
+    /// 期间的事件仍会通知行为与观察者，所有连接任务结束后返回。
+    pub async fn close(&mut self) {
+        self.core.start_close();
+        self.core.abort_pending_dials();
+        future::poll_fn(|cx| self.poll_close(cx)).await
+    }
+
+    fn poll_close(&mut self, cx: &mut Context<'_>) -> Poll<()> {
+        loop {
+            if self.core.is_closed() {
+                return Poll::Ready(());
+            }
+            match Pin::new(&mut *self).poll_next_event(cx) {
+                Poll::Ready(_) => continue,
+                Poll::Pending => return Poll::Pending,
+            }
         }
-        false
+    }
+
+    pub fn local_peer_id(&self) -> &PeerId {
+        self.core.pool.local_peer_id()
     }
 
     /// 检查指定的 PeerId 是否已连接
     pub fn is_peer_connected(&self, peer_id: &PeerId) -> bool {
-        self.pool.is_peer_connected(peer_id)
+        self.core.pool.is_peer_connected(peer_id)
     }
 
     /// 获取所有已连接的 PeerId
     pub fn connected_peers(&self) -> impl Iterator<Item = &PeerId> {
-        self.pool.iter_peer_connected()
+        self.core.pool.iter_peer_connected()
     }
 
     /// 获取所有已连接的连接 ID
     pub fn connected_connections(&self) -> impl Iterator<Item = &ConnectionId> {
-        self.pool.iter_connected()
+        self.core.pool.iter_connected()
+    }
+
+    /// 已建立连接的元数据
+    pub fn connection_info(&self, connection_id: ConnectionId) -> Option<ConnectionInfo> {
+        self.core.pool.connection_info(connection_id)
+    }
+
+    /// 所有已建立连接的元数据
+    pub fn iter_connections(&self) -> impl Iterator<Item = ConnectionInfo> + '_ {
+        self.core.pool.iter_connections()
     }
 
     pub fn behavior(&self) -> &TBehavior {
-        &self.behavior
+        &self.core.behavior
     }
 
     pub fn behavior_mut(&mut self) -> &mut TBehavior {
-        &mut self.behavior
+        &mut self.core.behavior
     }
 
-    /// 创建一个新的 Swarm 实例
-    pub fn dial(&mut self, opts: DialOpts) -> Result<Multiaddr, DialError> {
-        let peer_id = opts.peer_id();
-        let condition = opts.condition();
-        let connection_id = opts.connection_id();
-        let addr = opts.addr();
-
-        // 是否可以建立连接
-        let should_dial = match (condition, peer_id) {
-            (_, None) => true,
-            (PeerCondition::Always, _) => true,
-            (PeerCondition::Disconnected, Some(ref peer_id)) => {
-                !self.pool.is_peer_connected(peer_id)
-            }
-            (PeerCondition::NotDialing, Some(ref peer_id)) => !self.pool.is_peer_dialing(peer_id),
-            (PeerCondition::DisconnectedAndNotDialing, Some(ref peer_id)) => {
-                !self.pool.is_peer_dialing(peer_id) && !self.pool.is_peer_connected(peer_id)
-            }
-        };
-        if !should_dial {
-            let err = DialError::PeerCondition(condition);
-            self.behavior
-                .on_dial_failure(connection_id, peer_id, addr.as_ref(), &err);
-            return Err(err);
-        }
+    /// 已知节点的地址簿
+    pub fn peer_store(&self) -> &PeerStore {
+        &self.core.dial.peer_store
+    }
 
-        let addr = match self
-            .behavior
-            .handle_pending_connection(connection_id, peer_id, &addr)
-        {
-            Ok(Some(addr)) => addr,
-            Ok(None) => {
-                let err = DialError::NoAddress;
-                self.behavior
-                    .on_dial_failure(connection_id, peer_id, addr.as_ref(), &err);
-                return Err(err);
-            }
-            Err(cause) => {
-                let error = DialError::Denied { cause };
-                self.behavior
-                    .on_dial_failure(connection_id, peer_id, addr.as_ref(), &error);
-                return Err(error);
-            }
-        };
-
-        // 1.开始执行Transport 连接，
-        let future = match self.transport.dial(addr.clone()) {
-            Ok(dial) => dial,
-            Err(error) => {
-                let err = DialError::Transport {
-                    addr: addr.clone(),
-                    error,
-                };
-                self.behavior
-                    .on_dial_failure(connection_id, peer_id, Some(&addr), &err);
-                return Err(err);
-            }
-        };
-        // 2.加入Connection Pool
-        self.pool
-            .add_outgoing(connection_id, future, addr.clone(), peer_id);
-        Ok(addr)
+    pub fn peer_store_mut(&mut self) -> &mut PeerStore {
+        &mut self.core.dial.peer_store
     }
 
-    fn handle_behavior_event(
-        &mut self,
-        event: BehaviorEvent<TBehavior::Event, THandlerAction<TBehavior>>,
-    ) {
-        match event {
-            BehaviorEvent::Behavior(event) => {
-                self.pending_swarm_events
-                    .push_back(SwarmEvent::Behavior(event));
-            }
-            BehaviorEvent::HandlerAction {
-                peer_id,
-                handler,
-                action,
-            } => {
-                assert!(
-                    self.pending_handler_action.is_none(),
-                    "Pending handler action already exists"
-                );
-                let handler = match handler {
-                    NotifyHandler::One(connection) => PendingNotifyHandler::One(connection),
-                    NotifyHandler::Any => {
-                        let ids = self
-                            .pool
-                            .iter_established_connections_of_peer(&peer_id)
-                            .collect();
-                        PendingNotifyHandler::Any(ids)
-                    }
-                };
-                self.pending_handler_action = Some((peer_id, handler, action));
-            }
-            BehaviorEvent::CloseConnection {
-                peer_id,
-                connection,
-            } => match connection {
-                CloseConnection::One(id) => {
-                    if let Some(connection) = self.pool.get_established(id) {
-                        connection.start_close();
-                    } else {
-                        tracing::debug!(
-                            id = ?id,
-                            peer_id = ?peer_id,
-                            "Attempted to close non-existent connection"
-                        );
-                    }
-                }
-                CloseConnection::All => self.pool.disconnect(&peer_id),
-            },
-        }
+    /// 按连接与传输协议统计的流量
+    pub fn bandwidth(&self) -> &Bandwidth {
+        self.core.pool.bandwidth()
+    }
+
+    /// 发起拨号
+    ///
+    /// 立即返回的错误不会重试；配置了 [`RetryPolicy`](crate::RetryPolicy) 时，
+    /// 连接建立失败会按策略重新拨号，最终失败产生 [`SwarmEvent::DialGivenUp`]。
+    pub fn dial(&mut self, opts: DialOpts) -> Result<Multiaddr, DialError> {
+        self.core.dial(opts)
     }
 
-    fn handle_pool_event(&mut self, event: PoolEvent<THandlerEvent<TBehavior>>) {
-        match event {
-            PoolEvent::ConnectionEstablished {
-                id,
-                peer_id,
-                endpoint,
-                connection,
-                established_in,
-            } => {
-                let (handler, addr) = match &endpoint {
-                    ConnectedPoint::Dialer { addr } => match self
-                        .behavior
-                        .handle_established_connection(id, peer_id, addr)
-                    {
-                        Ok(handler) => (handler, addr.clone()),
-                        Err(cause) => {
-                            let dial_error = DialError::Denied { cause };
-                            self.behavior.on_dial_failure(
-                                id,
-                                Some(peer_id),
-                                Some(addr),
-                                &dial_error,
-                            );
-                            self.pending_swarm_events
-                                .push_back(SwarmEvent::ConnectionError {
-                                    peer_id: Some(peer_id),
-                                    connection_id: id,
-                                    addr: Some(addr.clone()),
-                                    error: dial_error,
-                                });
-                            return;
-                        }
-                    },
-                    ConnectedPoint::Listener { .. } => {
-                        unreachable!("Listener connections should not be handled here")
-                    }
-                };
-
-                let num_established = self.pool.num_peer_established(&peer_id);
-
-                self.pool.spawn_outbound_connection(
-                    id,
-                    peer_id,
-                    endpoint.clone(),
-                    connection,
-                    handler,
-                );
-                tracing::debug!(
-                    peer=%peer_id,
-                    addr=%addr,
-                    total_peers=%num_established,
-                    "Connection outbound established"
-                );
-                self.behavior.on_connection_established(id, peer_id, &addr);
-                self.pending_swarm_events
-                    .push_back(SwarmEvent::ConnectionEstablished {
-                        connection_id: id,
-                        peer_id,
-                        addr,
-                        established_in,
-                        num_established,
-                    });
+    /// 中断进行中的拨号，产生错误为 [`DialError::Aborted`] 的
+    /// [`SwarmEvent::OutgoingConnectionError`]，被中断的拨号不再重试
+    ///
+    /// 等待重试或排队中的拨号同样被取消，拨号不存在或连接已建立时返回 `false`。
+    pub fn abort_dial(&mut self, connection_id: ConnectionId) -> bool {
+        self.core.abort_dial(connection_id)
+    }
+
+    fn handle_command(&mut self, command: Command<TBehavior>) {
+        match command {
+            Command::Dial(opts, sender) => {
+                let _ = sender.send(self.dial(opts));
             }
-            PoolEvent::PendingConnectionError {
-                id,
-                peer_id,
-                endpoint,
-                error,
-            } => match endpoint {
-                ConnectedPoint::Dialer { addr } => {
-                    let dial_error = DialError::from(error);
-                    self.behavior
-                        .on_dial_failure(id, peer_id, Some(&addr), &dial_error);
-                    self.pending_swarm_events
-                        .push_back(SwarmEvent::ConnectionError {
-                            peer_id,
-                            connection_id: id,
-                            addr: Some(addr),
-                            error: dial_error,
-                        });
-                }
-                ConnectedPoint::Listener { .. } => {
-                    unreachable!("Dialer connections should not be handled here")
-                }
-            },
-            PoolEvent::ConnectionClosed {
-                id,
-                peer_id,
-                endpoint,
-                num_remaining_established,
-                error,
-            } => match endpoint {
-                ConnectedPoint::Dialer { addr } => {
-                    self.behavior
-                        .on_connection_closed(id, peer_id, &addr, error.as_ref());
-                    self.pending_swarm_events
-                        .push_back(SwarmEvent::ConnectionClosed {
-                            connection_id: id,
-                            peer_id,
-                            addr,
-                            num_remaining_established,
-                            error,
-                        });
-                }
-                ConnectedPoint::Listener { .. } => {
-                    unreachable!("Dialer connections should not be handled here")
-                }
-            },
-            PoolEvent::ConnectionEvent { id, peer_id, event } => {
-                self.behavior
-                    .on_connection_handler_event(id, peer_id, event);
+            Command::CloseConnection(connection_id, sender) => {
+                let _ = sender.send(self.close_connection(connection_id));
             }
+            Command::Behavior(command) => command(&mut self.core.behavior),
         }
     }
 
@@ -328,61 +196,31 @@ where
     ) -> Poll<SwarmEvent<TBehavior::Event>> {
         let this = &mut *self;
         loop {
-            if let Some(event) = this.pending_swarm_events.pop_front() {
+            if let Some(event) = this.core.next_event() {
                 return Poll::Ready(event);
             }
-            match this.pending_handler_action.take() {
-                Some((peer_id, handler, action)) => match handler {
-                    PendingNotifyHandler::One(id) => match this.pool.get_established(id) {
-                        Some(connection) => match notify_one(connection, action, cx) {
-                            Some(action) => {
-                                this.pending_handler_action = Some((peer_id, handler, action));
-                            }
-                            None => continue,
-                        },
-                        None => continue,
-                    },
-                    PendingNotifyHandler::Any(ids) => {
-                        match notify_any::<TBehavior>(ids, &mut this.pool, action, cx) {
-                            Some((pending, action)) => {
-                                // 写回Pending的连接ID和操作
-                                this.pending_handler_action =
-                                    Some((peer_id, PendingNotifyHandler::Any(pending), action));
-                            }
-                            None => continue,
-                        }
-                    }
-                },
-                // 如果没有Pending的Handler操作，继续处理Swarm事件
-                None => match this.behavior.poll(cx) {
-                    Poll::Pending => {}
-                    Poll::Ready(event) => {
-                        this.handle_behavior_event(event);
-                        continue;
-                    }
-                },
+            if let Poll::Ready(Some(command)) = this.command_receiver.poll_next_unpin(cx) {
+                this.handle_command(command);
+                continue;
             }
-
-            match this.behavior.poll_dial(cx) {
+            match this.core.poll_behavior(cx) {
                 Poll::Pending => {}
-                Poll::Ready(opts) => {
-                    let peer_id = opts.peer_id();
-                    let connection_id = opts.connection_id();
-                    if let Ok(addr) = this.dial(opts) {
-                        this.pending_swarm_events.push_back(SwarmEvent::Dialing {
-                            peer_id,
-                            connection_id,
-                            addr,
-                        });
-                    }
+                Poll::Ready(Some(AddressEvent::PeerAddress { peer_id, addr })) => {
+                    this.core.dial.peer_store.add_address(peer_id, addr);
+                    continue;
                 }
+                // 客户端不接受连接，外部地址没有意义
+                Poll::Ready(_) => continue,
+            }
+            if this.core.poll_dials(cx) {
+                continue;
             }
 
             // 处理连接池中的事件
-            match this.pool.poll(cx) {
+            match this.core.pool.poll(cx) {
                 Poll::Pending => {}
                 Poll::Ready(pool_event) => {
-                    this.handle_pool_event(pool_event);
+                    this.core.handle_dialer_pool_event(pool_event);
                     continue;
                 }
             }
@@ -391,6 +229,76 @@ where
     }
 }
 
+impl<TBehavior> Swarm<TBehavior>
+where
+    TBehavior: NetworkOutgoingBehavior,
+    TBehavior::ConnectionHandler: OutboundStreamHandler,
+    TBehavior::Event: Clone + Send + 'static,
+{
+    /// 保留最近 `capacity` 个事件，之后订阅的消费者先收到这些事件
+    pub fn with_replay_buffer(mut self, capacity: usize) -> Self {
+        self.core.replay().set_capacity(capacity);
+        self
+    }
+
+    /// 订阅 Swarm 事件，可在任意时刻订阅，先收到回放缓冲中的事件
+    ///
+    /// 未设置 [`Self::with_replay_buffer`] 时只收到订阅之后的事件。
+    pub fn subscribe(&mut self) -> Subscription<TBehavior::Event> {
+        self.core.replay().subscribe()
+    }
+}
+
+/// 从其他任务驱动 [`Swarm`] 的句柄
+///
+/// 命令经通道发往 Swarm，在 Swarm 所在任务轮询时依次执行，调用等待执行结果返回。
+pub struct SwarmHandle<TBehavior> {
+    commands: CommandSender<Command<TBehavior>>,
+}
+
+impl<TBehavior> Clone for SwarmHandle<TBehavior> {
+    fn clone(&self) -> Self {
+        Self {
+            commands: self.commands.clone(),
+        }
+    }
+}
+
+impl<TBehavior> SwarmHandle<TBehavior> {
+    /// 发起拨号，Swarm 已被丢弃时返回 [`DialError::Closing`]
+    pub async fn dial(&self, opts: DialOpts) -> Result<Multiaddr, DialError> {
+        self.commands
+            .call(|sender| Command::Dial(opts, sender))
+            .await
+            .unwrap_or(Err(DialError::Closing))
+    }
+
+    /// 关闭指定的连接，Swarm 已被丢弃时返回 `false`
+    pub async fn close_connection(&self, connection_id: ConnectionId) -> bool {
+        self.commands
+            .call(|sender| Command::CloseConnection(connection_id, sender))
+            .await
+            .unwrap_or(false)
+    }
+
+    /// 在 Swarm 所在任务中操作行为，返回 `f` 的结果
+    pub async fn behavior_command<R>(
+        &self,
+        f: impl FnOnce(&mut TBehavior) -> R + Send + 'static,
+    ) -> Result<R, SwarmClosed>
+    where
+        R: Send + 'static,
+    {
+        self.commands.behavior(f, Command::Behavior).await
+    }
+}
+
+enum Command<TBehavior> {
+    Dial(DialOpts, oneshot::Sender<Result<Multiaddr, DialError>>),
+    CloseConnection(ConnectionId, oneshot::Sender<bool>),
+    Behavior(BehaviorCommand<TBehavior>),
+}
+
 impl<TBehavior> Stream for Swarm<TBehavior>
 where
     TBehavior: NetworkOutgoingBehavior,
@@ -405,38 +313,3 @@ where
         }
     }
 }
-
-#[derive(Debug)]
-#[non_exhaustive]
-pub enum SwarmEvent<TBehaviorEvent> {
-    Behavior(TBehaviorEvent),
-
-    Dialing {
-        peer_id: Option<PeerId>,
-        addr: Multiaddr,
-        connection_id: ConnectionId,
-    },
-
-    ConnectionError {
-        peer_id: Option<PeerId>,
-        connection_id: ConnectionId,
-        addr: Option<Multiaddr>,
-        error: DialError,
-    },
-
-    ConnectionEstablished {
-        peer_id: PeerId,
-        connection_id: ConnectionId,
-        addr: Multiaddr,
-        num_established: usize,
-        established_in: std::time::Duration,
-    },
-
-    ConnectionClosed {
-        connection_id: ConnectionId,
-        peer_id: PeerId,
-        addr: Multiaddr,
-        num_remaining_established: usize,
-        error: Option<ConnectionError>,
-    },
-}
@@ -5,24 +5,32 @@ pub mod pool;
 
 pub use inbound::InboundConnection;
 pub use outbound::OutboundConnection;
-pub use pool::{EstablishedConnection, Pool, PoolConfig, PoolEvent};
+pub use pool::{ConnectionInfo, EstablishedConnection, Pool, PoolConfig, PoolEvent};
 
 use std::{
+    cmp,
+    collections::HashSet,
     fmt, mem,
     pin::Pin,
-    sync::atomic::{AtomicUsize, Ordering},
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicUsize, Ordering},
+    },
     task::{Context, Poll, Waker},
     time::{Duration, Instant},
 };
 
 use futures::{FutureExt, Stream, future::BoxFuture};
-use futures_timer::Delay;
 use volans_core::muxing::{Closing, StreamMuxerBox, SubstreamBox};
-use volans_stream_select::{NegotiationError, ProtocolError};
+use volans_stream_select::{NegotiationError, NegotiationMode, ProtocolError};
 
 use crate::{
-    ConnectionHandler, InboundUpgradeSend, OutboundUpgradeSend, StreamUpgradeError, Substream,
-    SubstreamProtocol, error::ConnectionError, substream::ActiveStreamCounter,
+    ConnectionHandler, InboundUpgradeSend, KeepAlive, OutboundUpgradeSend, StreamUpgradeError,
+    Substream, SubstreamPriority, SubstreamProtocol,
+    bandwidth::StreamStats,
+    error::{self, ConnectionError},
+    substream::ActiveStreamCounter,
+    timer::Delay,
 };
 
 static NEXT_CONNECTION_ID: AtomicUsize = AtomicUsize::new(1);
@@ -34,6 +42,11 @@ impl ConnectionId {
     pub(crate) fn next() -> Self {
         Self(NEXT_CONNECTION_ID.fetch_add(1, Ordering::SeqCst))
     }
+
+    /// 以指定值构造连接 ID，不保证与 Swarm 分配的 ID 不冲突，仅用于测试
+    pub fn new_unchecked(id: usize) -> Self {
+        Self(id)
+    }
 }
 
 impl fmt::Display for ConnectionId {
@@ -42,19 +55,48 @@ impl fmt::Display for ConnectionId {
     }
 }
 
+/// 连接关闭时继续产生的 Handler 事件流
+pub(crate) type ClosingEvents<E> = Pin<Box<dyn Stream<Item = E> + Send>>;
+
 pub(crate) trait ConnectionController<THandler: ConnectionHandler> {
     fn close(
         self,
     ) -> (
-        Pin<Box<dyn Stream<Item = <THandler as ConnectionHandler>::Event> + Send>>,
+        ClosingEvents<<THandler as ConnectionHandler>::Event>,
         Closing<StreamMuxerBox>,
     );
 
     fn handle_action(&mut self, action: THandler::Action);
 
+    /// 等待处理器完成初始化，期间仍轮询多路复用器以发现连接关闭
+    fn poll_init(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), ConnectionError>>;
+
     fn poll(&mut self, cx: &mut Context<'_>) -> Poll<Result<THandler::Event, ConnectionError>>;
 }
 
+/// 连接上已协商成功的出站协议
+///
+/// 再次以该协议打开子流时不等待确认，协议与数据一起发送，子流升级失败时移除。
+#[derive(Debug, Clone, Default)]
+pub(crate) struct NegotiationCache(Arc<Mutex<HashSet<String>>>);
+
+impl NegotiationCache {
+    pub(crate) fn contains(&self, protocol: &str) -> bool {
+        self.0.lock().expect("lock not poisoned").contains(protocol)
+    }
+
+    pub(crate) fn insert(&self, protocol: &str) {
+        let mut protocols = self.0.lock().expect("lock not poisoned");
+        if !protocols.contains(protocol) {
+            protocols.insert(protocol.to_string());
+        }
+    }
+
+    pub(crate) fn remove(&self, protocol: &str) {
+        self.0.lock().expect("lock not poisoned").remove(protocol);
+    }
+}
+
 struct StreamUpgrade<TData, TOk, TErr> {
     user_data: Option<TData>,
     timeout: Delay,
@@ -62,31 +104,65 @@ struct StreamUpgrade<TData, TOk, TErr> {
 }
 
 impl<TData, TOk, TErr> StreamUpgrade<TData, TOk, TErr> {
+    #[allow(clippy::too_many_arguments)]
     fn new_outbound<TUpgr>(
         substream: SubstreamBox,
         upgrade: TUpgr,
         user_data: TData,
         timeout: Delay,
+        mode: NegotiationMode,
+        cache: Option<NegotiationCache>,
         counter: ActiveStreamCounter,
+        stats: StreamStats,
     ) -> Self
     where
         TUpgr: OutboundUpgradeSend<Output = TOk, Error = TErr>,
     {
-        let protocols = upgrade.protocol_info();
+        let mut protocols: Vec<_> = upgrade.protocol_info().collect();
+        // 首选协议已在连接上协商过时只发送该协议，不等待确认
+        let cached = match (&cache, protocols.first()) {
+            (Some(cache), Some(first)) => cache.contains(first.as_ref()),
+            _ => false,
+        };
+        let mode = if cached {
+            protocols.truncate(1);
+            NegotiationMode::Lazy
+        } else {
+            mode
+        };
+        let local_protocols = protocol_names(&protocols);
         Self {
             user_data: Some(user_data),
             timeout,
             upgrade: Box::pin(async move {
                 let (info, stream) =
-                    volans_stream_select::DialerSelectFuture::new(substream, protocols)
+                    volans_stream_select::DialerSelectFuture::new(substream, protocols.into_iter())
+                        .with_mode(mode)
+                        .with_list_protocols(true)
                         .await
-                        .map_err(to_stream_upgrade_error)?;
-                let output = upgrade
-                    .upgrade_outbound(Substream::new(stream, counter), info)
-                    .await
-                    .map_err(StreamUpgradeError::Apply)?;
-
-                Ok(output)
+                        .map_err(|e| to_stream_upgrade_error(e, local_protocols))?;
+                let protocol = info.as_ref().to_string();
+                if let Some(cache) = &cache
+                    && mode == NegotiationMode::Full
+                {
+                    cache.insert(&protocol);
+                }
+                let bytes = stats.counters(&protocol);
+                let mut substream = Substream::new(stream, counter, bytes);
+                if let Some(cache) = &cache
+                    && cached
+                {
+                    substream = substream.with_unconfirmed(cache.clone(), protocol.clone());
+                }
+                let result = upgrade.upgrade_outbound(substream, info).await;
+                if let Some(cache) = &cache
+                    && cached
+                    && result.is_err()
+                {
+                    tracing::debug!(%protocol, "Upgrade failed, invalidating negotiation cache");
+                    cache.remove(&protocol);
+                }
+                result.map_err(StreamUpgradeError::Apply)
             }),
         }
     }
@@ -94,24 +170,30 @@ impl<TData, TOk, TErr> StreamUpgrade<TData, TOk, TErr> {
     fn new_inbound<TUpgr>(
         substream: SubstreamBox,
         protocol: SubstreamProtocol<TUpgr, TData>,
+        default_timeout: Duration,
         counter: ActiveStreamCounter,
+        stats: StreamStats,
     ) -> Self
     where
         TUpgr: InboundUpgradeSend<Output = TOk, Error = TErr>,
     {
         let (upgrade, user_data, timeout) = protocol.into_inner();
-        let protocols = upgrade.protocol_info();
+        let protocols: Vec<_> = upgrade.protocol_info().collect();
+        let local_protocols = protocol_names(&protocols);
 
         Self {
             user_data: Some(user_data),
-            timeout: Delay::new(timeout),
+            timeout: Delay::new(timeout.unwrap_or(default_timeout)),
             upgrade: Box::pin(async move {
-                let (info, stream) =
-                    volans_stream_select::ListenerSelectFuture::new(substream, protocols)
-                        .await
-                        .map_err(to_stream_upgrade_error)?;
+                let (info, stream) = volans_stream_select::ListenerSelectFuture::new(
+                    substream,
+                    protocols.into_iter(),
+                )
+                .await
+                .map_err(|e| to_stream_upgrade_error(e, local_protocols))?;
+                let bytes = stats.counters(info.as_ref());
                 let output = upgrade
-                    .upgrade_inbound(Substream::new(stream, counter), info)
+                    .upgrade_inbound(Substream::new(stream, counter, bytes), info)
                     .await
                     .map_err(StreamUpgradeError::Apply)?;
 
@@ -121,9 +203,24 @@ impl<TData, TOk, TErr> StreamUpgrade<TData, TOk, TErr> {
     }
 }
 
-fn to_stream_upgrade_error<T>(e: NegotiationError) -> StreamUpgradeError<T> {
+fn protocol_names<T: AsRef<str>>(protocols: &[T]) -> Vec<String> {
+    protocols.iter().map(|p| p.as_ref().to_string()).collect()
+}
+
+fn to_stream_upgrade_error<T>(
+    e: NegotiationError,
+    local_protocols: Vec<String>,
+) -> StreamUpgradeError<T> {
     match e {
-        NegotiationError::Failed => StreamUpgradeError::NegotiationFailed,
+        NegotiationError::Failed { remote_protocols } => StreamUpgradeError::NegotiationFailed {
+            remote_protocols,
+            local_protocols,
+        },
+        NegotiationError::ProtocolError(ProtocolError::IoError(e))
+            if error::is_remote_close(&e) =>
+        {
+            StreamUpgradeError::RemoteReset
+        }
         NegotiationError::ProtocolError(ProtocolError::IoError(e)) => StreamUpgradeError::Io(e),
         NegotiationError::ProtocolError(other) => {
             StreamUpgradeError::Io(std::io::Error::other(other))
@@ -164,32 +261,60 @@ enum SubstreamRequested<TUpgr, TData> {
         timeout: Delay,
         upgrade: TUpgr,
         user_data: TData,
+        mode: NegotiationMode,
+        priority: SubstreamPriority,
+        /// 请求顺序，同一优先级先到先得
+        seq: u64,
         extracted_waker: Option<Waker>,
     },
     Done,
 }
 
 impl<TUpgr, TData> SubstreamRequested<TUpgr, TData> {
-    fn new(upgrade: TUpgr, user_data: TData, timeout: Duration) -> Self {
+    fn new(
+        upgrade: TUpgr,
+        user_data: TData,
+        timeout: Duration,
+        mode: NegotiationMode,
+        priority: SubstreamPriority,
+        seq: u64,
+    ) -> Self {
         Self::Waiting {
             timeout: Delay::new(timeout),
             upgrade,
             user_data,
+            mode,
+            priority,
+            seq,
             extracted_waker: None,
         }
     }
-    fn extract(&mut self) -> (TUpgr, TData, Delay) {
+
+    /// 分配新子流的顺序，已分配的请求返回 `None`
+    fn schedule_key(&self) -> Option<(SubstreamPriority, cmp::Reverse<u64>)> {
+        match self {
+            SubstreamRequested::Waiting { priority, seq, .. } => {
+                Some((*priority, cmp::Reverse(*seq)))
+            }
+            SubstreamRequested::Done => None,
+        }
+    }
+
+    fn extract(&mut self) -> (TUpgr, TData, Delay, NegotiationMode, SubstreamPriority) {
         match mem::replace(self, Self::Done) {
             SubstreamRequested::Waiting {
                 timeout,
                 upgrade,
                 extracted_waker: waker,
                 user_data,
+                mode,
+                priority,
+                ..
             } => {
                 if let Some(waker) = waker {
                     waker.wake();
                 }
-                (upgrade, user_data, timeout)
+                (upgrade, user_data, timeout, mode, priority)
             }
             SubstreamRequested::Done => panic!("cannot extract twice"),
         }
@@ -208,6 +333,9 @@ impl<TUpgr, TData> Future for SubstreamRequested<TUpgr, TData> {
                 mut timeout,
                 user_data,
                 upgrade,
+                mode,
+                priority,
+                seq,
                 ..
             } => match timeout.poll_unpin(cx) {
                 Poll::Ready(()) => Poll::Ready(Err(user_data)),
@@ -216,6 +344,9 @@ impl<TUpgr, TData> Future for SubstreamRequested<TUpgr, TData> {
                         timeout,
                         upgrade,
                         user_data,
+                        mode,
+                        priority,
+                        seq,
                         extracted_waker: Some(cx.waker().clone()),
                     };
                     Poll::Pending
@@ -231,25 +362,31 @@ enum Shutdown {
     None,
     /// 尽快关闭
     Asap,
-    /// 计划在 `Delay` 结束时关闭
-    Later(Delay),
+    /// 计划在 `Delay` 结束时关闭，记录计算时使用的空闲超时
+    Later(Delay, Duration),
 }
 
 fn compute_new_shutdown(
-    handler_keep_alive: bool,
+    keep_alive: KeepAlive,
     current_shutdown: &Shutdown,
     idle_timeout: Duration,
 ) -> Option<Shutdown> {
-    match (current_shutdown, handler_keep_alive) {
-        (_, false) if idle_timeout == Duration::ZERO => Some(Shutdown::Asap),
-        (Shutdown::Later(_), false) => None,
-        (_, false) => {
+    let timeout = match keep_alive {
+        KeepAlive::Yes => return Some(Shutdown::None),
+        KeepAlive::Idle => idle_timeout,
+        KeepAlive::Timeout(timeout) => timeout.min(idle_timeout),
+        KeepAlive::No => Duration::ZERO,
+    };
+    match current_shutdown {
+        _ if timeout == Duration::ZERO => Some(Shutdown::Asap),
+        // 处理器要求的超时不变时沿用已有的计时
+        Shutdown::Later(_, current) if *current == timeout => None,
+        _ => {
             let now = Instant::now();
-            let safe_keep_alive = checked_add_fraction(now, idle_timeout);
+            let safe_keep_alive = checked_add_fraction(now, timeout);
 
-            Some(Shutdown::Later(Delay::new(safe_keep_alive)))
+            Some(Shutdown::Later(Delay::new(safe_keep_alive), timeout))
         }
-        (_, true) => Some(Shutdown::None),
     }
 }
 
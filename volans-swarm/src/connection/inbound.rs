@@ -5,7 +5,7 @@ use std::{
 };
 
 use futures::{
-    Stream, StreamExt,
+    StreamExt,
     stream::{self, FuturesUnordered},
 };
 use volans_core::muxing::{Closing, StreamMuxerBox, StreamMuxerExt};
@@ -13,7 +13,10 @@ use volans_core::muxing::{Closing, StreamMuxerBox, StreamMuxerExt};
 use crate::{
     ConnectionHandler, ConnectionHandlerEvent, InboundStreamHandler, InboundUpgradeSend,
     StreamUpgradeError,
-    connection::{ConnectionController, Shutdown, StreamUpgrade, compute_new_shutdown},
+    bandwidth::StreamStats,
+    connection::{
+        ClosingEvents, ConnectionController, Shutdown, StreamUpgrade, compute_new_shutdown,
+    },
     error::ConnectionError,
     substream::ActiveStreamCounter,
 };
@@ -24,6 +27,7 @@ where
 {
     muxer: StreamMuxerBox,
     handler: THandler,
+    #[allow(clippy::type_complexity)]
     negotiating_in: FuturesUnordered<
         StreamUpgrade<
             THandler::InboundUserData,
@@ -32,7 +36,9 @@ where
         >,
     >,
     max_negotiating_inbound_streams: usize,
+    substream_upgrade_timeout: Duration,
     stream_counter: ActiveStreamCounter,
+    stream_stats: StreamStats,
     closing: bool,
     idle_timeout: Duration,
     shutdown: Shutdown,
@@ -44,18 +50,22 @@ impl<THandler> InboundConnection<THandler>
 where
     THandler: InboundStreamHandler,
 {
-    pub fn new(
+    pub(crate) fn new(
         muxer: StreamMuxerBox,
         handler: THandler,
         max_negotiating_inbound_streams: usize,
+        substream_upgrade_timeout: Duration,
         idle_timeout: Duration,
+        stream_stats: StreamStats,
     ) -> Self {
         Self {
             muxer,
             handler,
             negotiating_in: FuturesUnordered::new(),
             max_negotiating_inbound_streams,
+            substream_upgrade_timeout,
             stream_counter: ActiveStreamCounter::new(),
+            stream_stats,
             closing: false,
             idle_timeout,
             shutdown: Shutdown::None,
@@ -65,7 +75,7 @@ where
     pub fn close(
         self,
     ) -> (
-        Pin<Box<dyn Stream<Item = <THandler as ConnectionHandler>::Event> + Send>>,
+        ClosingEvents<<THandler as ConnectionHandler>::Event>,
         Closing<StreamMuxerBox>,
     ) {
         let Self {
@@ -88,7 +98,9 @@ where
             handler,
             negotiating_in,
             max_negotiating_inbound_streams,
+            substream_upgrade_timeout,
             stream_counter,
+            stream_stats,
             closing,
             idle_timeout,
             shutdown,
@@ -127,10 +139,14 @@ where
                     tracing::debug!("inbound stream upgrade timed out");
                     continue;
                 }
-                Poll::Ready(Some((_, Err(StreamUpgradeError::NegotiationFailed)))) => {
+                Poll::Ready(Some((_, Err(StreamUpgradeError::NegotiationFailed { .. })))) => {
                     tracing::debug!("inbound stream upgrade negotiation failed");
                     continue;
                 }
+                Poll::Ready(Some((_, Err(StreamUpgradeError::RemoteReset)))) => {
+                    tracing::debug!("inbound stream reset by remote during upgrade");
+                    continue;
+                }
                 Poll::Ready(Some((_, Err(StreamUpgradeError::Io(error))))) => {
                     tracing::debug!("inbound stream upgrade IO error: {:?}", error);
                     continue;
@@ -139,14 +155,14 @@ where
 
             if negotiating_in.is_empty() && stream_counter.no_active_streams() {
                 if let Some(new_timeout) =
-                    compute_new_shutdown(handler.connection_keep_alive(), shutdown, *idle_timeout)
+                    compute_new_shutdown(handler.keep_alive(), shutdown, *idle_timeout)
                 {
                     *shutdown = new_timeout;
                 }
                 match shutdown {
                     Shutdown::None => {}
                     Shutdown::Asap => return Poll::Ready(Err(ConnectionError::KeepAliveTimeout)),
-                    Shutdown::Later(delay) => match Future::poll(Pin::new(delay), cx) {
+                    Shutdown::Later(delay, _) => match Future::poll(Pin::new(delay), cx) {
                         Poll::Ready(_) => {
                             return Poll::Ready(Err(ConnectionError::KeepAliveTimeout));
                         }
@@ -171,7 +187,9 @@ where
                         negotiating_in.push(StreamUpgrade::new_inbound(
                             substream,
                             protocol,
+                            *substream_upgrade_timeout,
                             stream_counter.clone(),
+                            stream_stats.clone(),
                         ));
                         continue;
                     }
@@ -190,7 +208,7 @@ where
     fn close(
         self,
     ) -> (
-        Pin<Box<dyn Stream<Item = <THandler as ConnectionHandler>::Event> + Send>>,
+        ClosingEvents<<THandler as ConnectionHandler>::Event>,
         Closing<StreamMuxerBox>,
     ) {
         self.close()
@@ -200,6 +218,14 @@ where
         self.handle_action(action)
     }
 
+    fn poll_init(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), ConnectionError>> {
+        if self.handler.poll_init(cx).is_ready() {
+            return Poll::Ready(Ok(()));
+        }
+        let _ = self.muxer.poll_unpin(cx)?;
+        Poll::Pending
+    }
+
     fn poll(&mut self, cx: &mut Context<'_>) -> Poll<Result<THandler::Event, ConnectionError>> {
         self.poll(cx)
     }
@@ -5,7 +5,7 @@ use std::{
 };
 
 use futures::{
-    Stream, StreamExt,
+    StreamExt,
     stream::{self, FuturesUnordered},
 };
 use volans_core::muxing::{Closing, StreamMuxerBox, StreamMuxerExt};
@@ -13,8 +13,10 @@ use volans_core::muxing::{Closing, StreamMuxerBox, StreamMuxerExt};
 use crate::{
     ConnectionHandler, ConnectionHandlerEvent, OutboundStreamHandler, OutboundUpgradeSend,
     StreamUpgradeError,
+    bandwidth::StreamStats,
     connection::{
-        ConnectionController, Shutdown, StreamUpgrade, SubstreamRequested, compute_new_shutdown,
+        ClosingEvents, ConnectionController, NegotiationCache, Shutdown, StreamUpgrade,
+        SubstreamRequested, compute_new_shutdown,
     },
     error::ConnectionError,
     substream::ActiveStreamCounter,
@@ -26,17 +28,25 @@ where
 {
     muxer: StreamMuxerBox,
     handler: THandler,
-    negotiating_out: FuturesUnordered<
+    /// 按 [`SubstreamPriority`](crate::SubstreamPriority) 分组，高优先级的协商结果先交给处理器
+    #[allow(clippy::type_complexity)]
+    negotiating_out: [FuturesUnordered<
         StreamUpgrade<
             THandler::OutboundUserData,
             <THandler::OutboundUpgrade as OutboundUpgradeSend>::Output,
             <THandler::OutboundUpgrade as OutboundUpgradeSend>::Error,
         >,
-    >,
+    >; 3],
     requested_substreams:
         FuturesUnordered<SubstreamRequested<THandler::OutboundUpgrade, THandler::OutboundUserData>>,
+    /// 下一个子流请求的序号
+    next_request_seq: u64,
 
+    substream_upgrade_timeout: Duration,
     stream_counter: ActiveStreamCounter,
+    stream_stats: StreamStats,
+    /// 未启用时为 `None`
+    negotiation_cache: Option<NegotiationCache>,
     closing: bool,
     idle_timeout: Duration,
     shutdown: Shutdown,
@@ -48,13 +58,24 @@ impl<THandler> OutboundConnection<THandler>
 where
     THandler: OutboundStreamHandler,
 {
-    pub fn new(muxer: StreamMuxerBox, handler: THandler, idle_timeout: Duration) -> Self {
+    pub(crate) fn new(
+        muxer: StreamMuxerBox,
+        handler: THandler,
+        substream_upgrade_timeout: Duration,
+        idle_timeout: Duration,
+        stream_stats: StreamStats,
+        negotiation_cache: bool,
+    ) -> Self {
         Self {
             muxer,
             handler,
-            negotiating_out: FuturesUnordered::new(),
+            negotiating_out: std::array::from_fn(|_| FuturesUnordered::new()),
             requested_substreams: FuturesUnordered::new(),
+            next_request_seq: 0,
+            substream_upgrade_timeout,
             stream_counter: ActiveStreamCounter::new(),
+            stream_stats,
+            negotiation_cache: negotiation_cache.then(NegotiationCache::default),
             closing: false,
             idle_timeout,
             shutdown: Shutdown::None,
@@ -68,7 +89,7 @@ where
     pub fn close(
         self,
     ) -> (
-        Pin<Box<dyn Stream<Item = <THandler as ConnectionHandler>::Event> + Send>>,
+        ClosingEvents<<THandler as ConnectionHandler>::Event>,
         Closing<StreamMuxerBox>,
     ) {
         let Self {
@@ -91,7 +112,11 @@ where
             handler,
             negotiating_out,
             requested_substreams,
+            next_request_seq,
+            substream_upgrade_timeout,
             stream_counter,
+            stream_stats,
+            negotiation_cache,
             closing,
             idle_timeout,
             shutdown,
@@ -117,8 +142,19 @@ where
             match handler.poll_outbound_request(cx) {
                 Poll::Pending => {}
                 Poll::Ready(protocol) => {
+                    let mode = protocol.negotiation_mode();
+                    let priority = protocol.priority();
                     let (upgrade, user_data, timeout) = protocol.into_inner();
-                    let substream = SubstreamRequested::new(upgrade, user_data, timeout);
+                    let timeout = timeout.unwrap_or(*substream_upgrade_timeout);
+                    let substream = SubstreamRequested::new(
+                        upgrade,
+                        user_data,
+                        timeout,
+                        mode,
+                        priority,
+                        *next_request_seq,
+                    );
+                    *next_request_seq += 1;
                     requested_substreams.push(substream);
                     continue;
                 }
@@ -137,31 +173,37 @@ where
                 }
             }
 
-            match negotiating_out.poll_next_unpin(cx) {
-                Poll::Pending | Poll::Ready(None) => {}
-                Poll::Ready(Some((info, Ok(protocol)))) => {
+            let negotiated = negotiating_out.iter_mut().rev().find_map(|negotiating| {
+                match negotiating.poll_next_unpin(cx) {
+                    Poll::Ready(Some(negotiated)) => Some(negotiated),
+                    Poll::Pending | Poll::Ready(None) => None,
+                }
+            });
+            match negotiated {
+                None => {}
+                Some((info, Ok(protocol))) => {
                     handler.on_fully_negotiated(info, protocol);
                     continue;
                 }
-                Poll::Ready(Some((info, Err(error)))) => {
+                Some((info, Err(error))) => {
                     handler.on_upgrade_error(info, error);
                     continue;
                 }
             }
 
-            if negotiating_out.is_empty()
+            if negotiating_out.iter().all(FuturesUnordered::is_empty)
                 && requested_substreams.is_empty()
                 && stream_counter.no_active_streams()
             {
                 if let Some(new_timeout) =
-                    compute_new_shutdown(handler.connection_keep_alive(), shutdown, *idle_timeout)
+                    compute_new_shutdown(handler.keep_alive(), shutdown, *idle_timeout)
                 {
                     *shutdown = new_timeout;
                 }
                 match shutdown {
                     Shutdown::None => {}
                     Shutdown::Asap => return Poll::Ready(Err(ConnectionError::KeepAliveTimeout)),
-                    Shutdown::Later(delay) => match Future::poll(Pin::new(delay), cx) {
+                    Shutdown::Later(delay, _) => match Future::poll(Pin::new(delay), cx) {
                         Poll::Ready(_) => {
                             return Poll::Ready(Err(ConnectionError::KeepAliveTimeout));
                         }
@@ -177,17 +219,27 @@ where
                 Poll::Pending => {}
                 Poll::Ready(()) => {}
             }
-            if let Some(requested_substream) = requested_substreams.iter_mut().next() {
+            // 新子流分配给优先级最高、最早的请求
+            if let Some(requested_substream) = requested_substreams
+                .iter_mut()
+                .filter_map(|requested| Some((requested.schedule_key()?, requested)))
+                .max_by_key(|(key, _)| *key)
+                .map(|(_, requested)| requested)
+            {
                 match muxer.poll_outbound_unpin(cx)? {
                     Poll::Pending => {}
                     Poll::Ready(substream) => {
-                        let (upgrade, user_data, timeout) = requested_substream.extract();
-                        negotiating_out.push(StreamUpgrade::new_outbound(
+                        let (upgrade, user_data, timeout, mode, priority) =
+                            requested_substream.extract();
+                        negotiating_out[priority as usize].push(StreamUpgrade::new_outbound(
                             substream,
                             upgrade,
                             user_data,
                             timeout,
+                            mode,
+                            negotiation_cache.clone(),
                             stream_counter.clone(),
+                            stream_stats.clone(),
                         ));
                         continue;
                     }
@@ -206,7 +258,7 @@ where
     fn close(
         self,
     ) -> (
-        Pin<Box<dyn Stream<Item = <THandler as ConnectionHandler>::Event> + Send>>,
+        ClosingEvents<<THandler as ConnectionHandler>::Event>,
         Closing<StreamMuxerBox>,
     ) {
         self.close()
@@ -216,6 +268,14 @@ where
         self.handle_action(action)
     }
 
+    fn poll_init(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), ConnectionError>> {
+        if self.handler.poll_init(cx).is_ready() {
+            return Poll::Ready(Ok(()));
+        }
+        let _ = self.muxer.poll_unpin(cx)?;
+        Poll::Pending
+    }
+
     fn poll(&mut self, cx: &mut Context<'_>) -> Poll<Result<THandler::Event, ConnectionError>> {
         self.poll(cx)
     }
@@ -4,36 +4,45 @@ use std::{
     collections::HashMap,
     convert::Infallible,
     io,
+    sync::{
+        Arc,
+        atomic::{AtomicU64, AtomicUsize, Ordering},
+    },
     task::{Context, Poll, Waker},
     time::{Duration, Instant},
 };
 
 use fnv::{FnvHashMap, FnvHashSet};
 use futures::{
-    FutureExt, StreamExt,
+    StreamExt,
     channel::{mpsc, oneshot},
     stream::{FuturesUnordered, SelectAll},
 };
+use rand::seq::SliceRandom;
+use smallvec::SmallVec;
 use tracing::Instrument;
 use volans_core::{
-    ConnectedPoint, Multiaddr, PeerId,
+    ConnectedPoint, Multiaddr, PeerId, StreamMuxer, TransportError,
     muxing::{StreamMuxerBox, StreamMuxerExt},
 };
 
 use crate::{
-    ConnectionHandler, ConnectionId, ExecSwitch, Executor, InboundStreamHandler,
-    OutboundStreamHandler,
+    Bandwidth, BandwidthStats, ConnectionHandler, ConnectionId, DialStrategy, ExecSwitch, Executor,
+    InboundStreamHandler, OutboundStreamHandler,
+    behavior::SelectionPolicy,
     connection::{InboundConnection, OutboundConnection},
-    error::{ConnectionError, PendingConnectionError},
+    error::{CloseReason, PendingConnectionError},
 };
 
+/// 动作发送序号，用于比较连接最近一次收到动作的先后
+static NEXT_NOTIFY_SEQ: AtomicU64 = AtomicU64::new(1);
+
 /// 连接池
 /// 管理连接的建立、维护和事件处理
 ///
 /// 状态机
 /// add_incoming -> pending -> Event::ConnectionEstablished -> spawn_connection -> established
 /// add_outgoing -> pending -> Event::ConnectionEstablished -> spawn_connection -> established
-
 pub struct Pool<THandler>
 where
     THandler: ConnectionHandler,
@@ -70,10 +79,26 @@ where
     task_command_buffer_size: usize,
     /// 最大协商入站流数量
     max_negotiating_inbound_streams: usize,
+    /// 子流协商的默认超时
+    substream_upgrade_timeout: Duration,
+    /// 等待连接建立的超时
+    pending_connection_timeout: Duration,
+    /// 等待握手的入站连接上限
+    max_pending_incoming: Option<usize>,
+    /// 同时进行的拨号上限
+    max_concurrent_dials: Option<usize>,
+    /// 同一对端同时进行的拨号上限
+    max_concurrent_dials_per_peer: Option<usize>,
     /// 每个连接事件缓冲区大小
     per_connection_event_buffer_size: usize,
     /// 连接空闲超时
     idle_connection_timeout: Duration,
+    /// 出站子流是否复用连接上已协商的协议
+    negotiation_cache: bool,
+    /// 通知任意连接时的默认选择策略
+    selection_policy: SelectionPolicy,
+    /// 已建立连接的流量统计
+    bandwidth: Bandwidth,
 }
 
 impl<THandler> Pool<THandler>
@@ -97,11 +122,27 @@ where
             new_connection_dropped_listeners: FuturesUnordered::new(),
             task_command_buffer_size: config.task_command_buffer_size,
             max_negotiating_inbound_streams: config.max_negotiating_inbound_streams,
+            substream_upgrade_timeout: config.substream_upgrade_timeout,
+            pending_connection_timeout: config.pending_connection_timeout,
+            max_pending_incoming: config.max_pending_incoming,
+            max_concurrent_dials: config.max_concurrent_dials,
+            max_concurrent_dials_per_peer: config.max_concurrent_dials_per_peer,
             per_connection_event_buffer_size: config.per_connection_event_buffer_size,
             idle_connection_timeout: config.idle_connection_timeout,
+            negotiation_cache: config.negotiation_cache,
+            selection_policy: config.selection_policy,
+            bandwidth: Bandwidth::default(),
         }
     }
 
+    pub fn local_peer_id(&self) -> &PeerId {
+        &self.local_id
+    }
+
+    pub(crate) fn bandwidth(&self) -> &Bandwidth {
+        &self.bandwidth
+    }
+
     pub fn disconnect(&mut self, id: &PeerId) {
         //处理 Pending 的连接：1、Remove Pending Map；2、中断连接任务
         for connection in self
@@ -117,13 +158,42 @@ where
         //处理已建立的连接: 给所有连接发送关闭命令
         if let Some(connections) = self.established_peer_connections.get(id) {
             for connection in connections.iter() {
-                if let Some(established) = self.established.get_mut(&connection) {
+                if let Some(established) = self.established.get_mut(connection) {
                     established.start_close();
                 }
             }
         }
     }
 
+    /// 中断所有等待中的连接，并关闭所有已建立的连接
+    pub(crate) fn close_all(&mut self) {
+        for pending in self.pending.values_mut() {
+            pending.abort();
+        }
+        for established in self.established.values_mut() {
+            established.start_close();
+        }
+    }
+
+    /// 没有等待中或已建立的连接
+    pub(crate) fn is_empty(&self) -> bool {
+        self.pending.is_empty() && self.established.is_empty()
+    }
+
+    /// 中断等待中的出站连接，连接任务随后以 `Aborted` 结束
+    pub(crate) fn abort_dial(&mut self, id: ConnectionId) -> bool {
+        match self.pending.get_mut(&id) {
+            Some(pending)
+                if matches!(pending.endpoint, ConnectedPoint::Dialer { .. })
+                    && pending.abort_notifier.is_some() =>
+            {
+                pending.abort();
+                true
+            }
+            _ => false,
+        }
+    }
+
     pub(crate) fn get_established(
         &mut self,
         id: ConnectionId,
@@ -138,14 +208,14 @@ where
     pub(crate) fn is_peer_dialing(&self, id: &PeerId) -> bool {
         if let Some(connections) = self.pending_peer_connections.get(id) {
             for connection in connections.iter() {
-                if let Some(pending) = self.pending.get(connection) {
-                    if matches!(pending.endpoint, ConnectedPoint::Dialer { .. }) {
-                        return true;
-                    }
+                if let Some(pending) = self.pending.get(connection)
+                    && matches!(pending.endpoint, ConnectedPoint::Dialer { .. })
+                {
+                    return true;
                 }
             }
         }
-        return false;
+        false
     }
 
     pub fn iter_established_connections_of_peer(
@@ -158,6 +228,69 @@ where
         }
     }
 
+    /// 按选择策略排列对端的连接，靠前的连接优先接收动作
+    ///
+    /// 未指定策略时使用 [`PoolConfig::with_selection_policy`] 配置的默认策略。
+    pub(crate) fn select_established_connections_of_peer(
+        &mut self,
+        peer_id: &PeerId,
+        policy: Option<SelectionPolicy>,
+    ) -> SmallVec<[ConnectionId; 10]> {
+        let mut ids: SmallVec<[ConnectionId; 10]> =
+            self.iter_established_connections_of_peer(peer_id).collect();
+        match policy.unwrap_or(self.selection_policy) {
+            SelectionPolicy::FirstReady => {}
+            SelectionPolicy::RoundRobin => {
+                ids.sort_by_key(|id| self.established[id].last_notified);
+            }
+            SelectionPolicy::LeastPendingCommands => {
+                ids.sort_by_key(|id| self.established[id].pending_commands());
+            }
+            SelectionPolicy::Random => ids.shuffle(&mut rand::rng()),
+        }
+        ids
+    }
+
+    /// 等待建立的连接数量
+    pub fn num_pending(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// 等待握手的入站连接是否已达到上限
+    pub fn is_pending_incoming_full(&self) -> bool {
+        self.max_pending_incoming.is_some_and(|max| {
+            self.pending
+                .values()
+                .filter(|pending| matches!(pending.endpoint, ConnectedPoint::Listener { .. }))
+                .count()
+                >= max
+        })
+    }
+
+    /// 是否还能发起新的拨号，未知对端的拨号只受全局上限约束
+    pub fn has_dial_capacity(&self, peer_id: Option<&PeerId>) -> bool {
+        let is_dialer =
+            |pending: &PendingConnection| matches!(pending.endpoint, ConnectedPoint::Dialer { .. });
+        if let Some(max) = self.max_concurrent_dials
+            && self.pending.values().filter(|p| is_dialer(p)).count() >= max
+        {
+            return false;
+        }
+        if let (Some(max), Some(peer_id)) = (self.max_concurrent_dials_per_peer, peer_id) {
+            let dialing = self
+                .pending_peer_connections
+                .get(peer_id)
+                .into_iter()
+                .flatten()
+                .filter(|id| self.pending.get(id).is_some_and(is_dialer))
+                .count();
+            if dialing >= max {
+                return false;
+            }
+        }
+        true
+    }
+
     pub fn num_peer_established(&self, peer_id: &PeerId) -> usize {
         self.established_peer_connections
             .get(peer_id)
@@ -172,6 +305,20 @@ where
         self.established.keys()
     }
 
+    /// 已建立连接的元数据
+    pub fn connection_info(&self, id: ConnectionId) -> Option<ConnectionInfo> {
+        self.established
+            .get(&id)
+            .map(|connection| connection.info(id))
+    }
+
+    /// 所有已建立连接的元数据
+    pub fn iter_connections(&self) -> impl Iterator<Item = ConnectionInfo> + '_ {
+        self.established
+            .iter()
+            .map(|(id, connection)| connection.info(*id))
+    }
+
     pub fn add_outgoing<TFut>(
         &mut self,
         id: ConnectionId,
@@ -189,11 +336,57 @@ where
                 id,
                 addr.clone(),
                 future,
+                self.pending_connection_timeout,
                 abort_receiver,
                 self.pending_connection_events_tx.clone(),
             )
             .instrument(span),
         );
+        self.insert_pending_outgoing(id, addr, peer_id, abort_notifier);
+    }
+
+    /// 按 `strategy` 依次拨号多个候选地址，第一个连通的地址胜出
+    ///
+    /// `failed` 为创建拨号时已失败的地址，所有地址均失败时与其余错误一并报告。
+    pub fn add_outgoing_fallback<TFut>(
+        &mut self,
+        id: ConnectionId,
+        attempts: Vec<(Multiaddr, TFut)>,
+        failed: Vec<(Multiaddr, TransportError<io::Error>)>,
+        strategy: DialStrategy,
+        peer_id: Option<PeerId>,
+    ) where
+        TFut: Future<Output = Result<(PeerId, StreamMuxerBox), io::Error>> + Send + 'static,
+    {
+        let addr = attempts
+            .first()
+            .map(|(addr, _)| addr.clone())
+            .expect("At least one dial attempt");
+        let (abort_notifier, abort_receiver) = oneshot::channel();
+        let span = tracing::debug_span!(parent: tracing::Span::none(), "new_outgoing_connection", id = %id, peer_id = ?peer_id, remote_addr = %addr, attempts = attempts.len());
+        span.follows_from(tracing::Span::current());
+        self.executor.spawn(
+            task::new_for_pending_dial(
+                id,
+                attempts,
+                failed,
+                strategy,
+                self.pending_connection_timeout,
+                abort_receiver,
+                self.pending_connection_events_tx.clone(),
+            )
+            .instrument(span),
+        );
+        self.insert_pending_outgoing(id, addr, peer_id, abort_notifier);
+    }
+
+    fn insert_pending_outgoing(
+        &mut self,
+        id: ConnectionId,
+        addr: Multiaddr,
+        peer_id: Option<PeerId>,
+        abort_notifier: oneshot::Sender<Infallible>,
+    ) {
         if let Some(peer_id) = peer_id {
             self.pending_peer_connections
                 .entry(peer_id)
@@ -228,6 +421,7 @@ where
                 id,
                 remote_addr.clone(),
                 future,
+                self.pending_connection_timeout,
                 abort_receiver,
                 self.pending_connection_events_tx.clone(),
             )
@@ -257,7 +451,8 @@ where
     ) where
         THandler: InboundStreamHandler,
     {
-        let muxer = connection.extract();
+        let (muxer, stream_stats) = self.bandwidth.track(id, &endpoint, connection.extract());
+        let muxer_protocol = muxer.protocol();
         let established_peer_connections = self
             .established_peer_connections
             .entry(obtained_peer_id)
@@ -265,12 +460,19 @@ where
 
         let (command_tx, command_rx) = mpsc::channel(self.task_command_buffer_size);
         let (event_tx, event_rx) = mpsc::channel(self.per_connection_event_buffer_size);
+        let pending_commands = Arc::new(AtomicUsize::new(0));
         // 创建连接处理器
         self.established.insert(
             id,
             EstablishedConnection {
+                peer_id: obtained_peer_id,
                 endpoint,
                 sender: command_tx,
+                pending_commands: pending_commands.clone(),
+                last_notified: 0,
+                established_at: Instant::now(),
+                last_activity: Instant::now(),
+                muxer_protocol,
             },
         );
         // 将连接 ID 添加到已建立的连接列表
@@ -285,7 +487,9 @@ where
             muxer,
             handler,
             self.max_negotiating_inbound_streams,
+            self.substream_upgrade_timeout,
             self.idle_connection_timeout,
+            stream_stats,
         );
         self.executor.spawn(
             task::new_for_established_connection(
@@ -293,6 +497,7 @@ where
                 obtained_peer_id,
                 connection,
                 command_rx,
+                pending_commands,
                 event_tx,
             )
             .instrument(span),
@@ -309,7 +514,8 @@ where
     ) where
         THandler: OutboundStreamHandler,
     {
-        let muxer = connection.extract();
+        let (muxer, stream_stats) = self.bandwidth.track(id, &endpoint, connection.extract());
+        let muxer_protocol = muxer.protocol();
         let established_peer_connections = self
             .established_peer_connections
             .entry(obtained_peer_id)
@@ -317,12 +523,19 @@ where
 
         let (command_tx, command_rx) = mpsc::channel(self.task_command_buffer_size);
         let (event_tx, event_rx) = mpsc::channel(self.per_connection_event_buffer_size);
+        let pending_commands = Arc::new(AtomicUsize::new(0));
         // 创建连接处理器
         self.established.insert(
             id,
             EstablishedConnection {
+                peer_id: obtained_peer_id,
                 endpoint,
                 sender: command_tx,
+                pending_commands: pending_commands.clone(),
+                last_notified: 0,
+                established_at: Instant::now(),
+                last_activity: Instant::now(),
+                muxer_protocol,
             },
         );
         // 将连接 ID 添加到已建立的连接列表
@@ -333,13 +546,21 @@ where
         }
         let span = tracing::debug_span!(parent: tracing::Span::none(), "new_outbound_established", %id, peer = %obtained_peer_id);
         span.follows_from(tracing::Span::current());
-        let connection = OutboundConnection::new(muxer, handler, self.idle_connection_timeout);
+        let connection = OutboundConnection::new(
+            muxer,
+            handler,
+            self.substream_upgrade_timeout,
+            self.idle_connection_timeout,
+            stream_stats,
+            self.negotiation_cache,
+        );
         self.executor.spawn(
             task::new_for_established_connection(
                 id,
                 obtained_peer_id,
                 connection,
                 command_rx,
+                pending_commands,
                 event_tx,
             )
             .instrument(span),
@@ -355,9 +576,16 @@ where
                 self.no_established_connections_waker = Some(cx.waker().clone());
             }
             Poll::Ready(Some(task::EstablishedConnectionEvent::Notify { id, peer_id, event })) => {
+                if let Some(connection) = self.established.get_mut(&id) {
+                    connection.last_activity = Instant::now();
+                }
                 return Poll::Ready(PoolEvent::ConnectionEvent { id, peer_id, event });
             }
-            Poll::Ready(Some(task::EstablishedConnectionEvent::Closed { id, peer_id, error })) => {
+            Poll::Ready(Some(task::EstablishedConnectionEvent::Closed {
+                id,
+                peer_id,
+                reason,
+            })) => {
                 if let Some(connections) = self.established_peer_connections.get_mut(&peer_id) {
                     connections.remove(&id);
                     if connections.is_empty() {
@@ -369,6 +597,7 @@ where
                     .remove(&id)
                     .expect("Connection should be established before being closed");
 
+                let bandwidth = self.bandwidth.remove(&id);
                 let num_remaining_established = self
                     .established_peer_connections
                     .get(&peer_id)
@@ -379,7 +608,8 @@ where
                     peer_id,
                     endpoint,
                     num_remaining_established,
-                    error,
+                    reason,
+                    bandwidth,
                 });
             }
         }
@@ -404,12 +634,12 @@ where
             let id = event.id();
             let PendingConnection {
                 peer_id: expected_peer_id,
-                endpoint,
+                mut endpoint,
                 abort_notifier: _,
                 accepted_at,
             } = self
                 .pending
-                .remove(&id)
+                .remove(id)
                 .expect("Pending connection should exist before being established");
 
             match event {
@@ -418,27 +648,32 @@ where
                     id,
                     peer_id: obtained_peer_id,
                     muxer,
+                    remote_addr,
                 } => {
+                    // 多地址拨号时以实际连通的地址为准
+                    if let (Some(remote_addr), ConnectedPoint::Dialer { addr }) =
+                        (remote_addr, &mut endpoint)
+                    {
+                        *addr = remote_addr;
+                    }
                     // 检查是否有预期的 PeerId
-                    if let Some(peer_id) = expected_peer_id {
-                        if peer_id != peer_id {
-                            let err_event = match &endpoint {
-                                ConnectedPoint::Dialer { .. } => {
-                                    PoolEvent::PendingConnectionError {
-                                        id,
-                                        peer_id: Some(peer_id),
-                                        endpoint,
-                                        error: PendingConnectionError::WrongPeerId {
-                                            obtained: peer_id,
-                                        },
-                                    }
-                                }
-                                ConnectedPoint::Listener { .. } => unreachable!(
-                                    "Listener connections should not have peer ID mismatch"
-                                ),
-                            };
-                            return Poll::Ready(err_event);
-                        }
+                    if let Some(peer_id) = expected_peer_id
+                        && peer_id != obtained_peer_id
+                    {
+                        let err_event = match &endpoint {
+                            ConnectedPoint::Dialer { .. } => PoolEvent::PendingConnectionError {
+                                id,
+                                peer_id: Some(peer_id),
+                                endpoint,
+                                error: PendingConnectionError::WrongPeerId {
+                                    obtained: obtained_peer_id,
+                                },
+                            },
+                            ConnectedPoint::Listener { .. } => unreachable!(
+                                "Listener connections should not have peer ID mismatch"
+                            ),
+                        };
+                        return Poll::Ready(err_event);
                     }
                     // 是否是本地回环
                     if self.local_id == obtained_peer_id {
@@ -502,8 +737,17 @@ impl PendingConnection {
 
 #[derive(Debug)]
 pub struct EstablishedConnection<TAction> {
+    peer_id: PeerId,
     endpoint: ConnectedPoint,
     sender: mpsc::Sender<task::Command<TAction>>,
+    /// 已发送但连接任务尚未处理的动作数量
+    pending_commands: Arc<AtomicUsize>,
+    /// 最近一次发送动作的序号，未发送过为 0
+    last_notified: u64,
+    established_at: Instant,
+    /// 最近一次收到处理器事件或发送动作的时间
+    last_activity: Instant,
+    muxer_protocol: Option<&'static str>,
 }
 
 impl<TAction> EstablishedConnection<TAction> {
@@ -514,7 +758,26 @@ impl<TAction> EstablishedConnection<TAction> {
     pub(crate) fn start_send(&mut self, action: TAction) -> Result<(), ()> {
         self.sender
             .start_send(task::Command::Action(action))
-            .map_err(|_| ())
+            .map_err(|_| ())?;
+        self.pending_commands.fetch_add(1, Ordering::Relaxed);
+        self.last_notified = NEXT_NOTIFY_SEQ.fetch_add(1, Ordering::Relaxed);
+        self.last_activity = Instant::now();
+        Ok(())
+    }
+
+    fn info(&self, id: ConnectionId) -> ConnectionInfo {
+        ConnectionInfo {
+            id,
+            peer_id: self.peer_id,
+            endpoint: self.endpoint.clone(),
+            established_at: self.established_at,
+            last_activity: self.last_activity,
+            muxer_protocol: self.muxer_protocol,
+        }
+    }
+
+    pub(crate) fn pending_commands(&self) -> usize {
+        self.pending_commands.load(Ordering::Relaxed)
     }
 
     pub(crate) fn start_close(&mut self) {
@@ -525,6 +788,19 @@ impl<TAction> EstablishedConnection<TAction> {
     }
 }
 
+/// 已建立连接的元数据
+#[derive(Debug, Clone)]
+pub struct ConnectionInfo {
+    pub id: ConnectionId,
+    pub peer_id: PeerId,
+    pub endpoint: ConnectedPoint,
+    pub established_at: Instant,
+    /// 最近一次收到处理器事件或向处理器发送动作的时间
+    pub last_activity: Instant,
+    /// 协商的多路复用协议，自带多路复用的传输为 `None`
+    pub muxer_protocol: Option<&'static str>,
+}
+
 #[derive(Debug)]
 pub enum PoolEvent<TEvent> {
     ConnectionEstablished {
@@ -547,7 +823,8 @@ pub enum PoolEvent<TEvent> {
         peer_id: PeerId,
         endpoint: ConnectedPoint,
         num_remaining_established: usize,
-        error: Option<ConnectionError>,
+        reason: CloseReason,
+        bandwidth: BandwidthStats,
     },
     ConnectionEvent {
         id: ConnectionId,
@@ -556,6 +833,18 @@ pub enum PoolEvent<TEvent> {
     },
 }
 
+impl<TEvent> PoolEvent<TEvent> {
+    /// 事件所属连接的端点，处理器事件不携带端点
+    pub(crate) fn endpoint(&self) -> Option<&ConnectedPoint> {
+        match self {
+            PoolEvent::ConnectionEstablished { endpoint, .. }
+            | PoolEvent::PendingConnectionError { endpoint, .. }
+            | PoolEvent::ConnectionClosed { endpoint, .. } => Some(endpoint),
+            PoolEvent::ConnectionEvent { .. } => None,
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct NewConnection {
     connection: Option<StreamMuxerBox>,
@@ -600,6 +889,13 @@ pub struct PoolConfig {
     per_connection_event_buffer_size: usize,
     idle_connection_timeout: Duration,
     max_negotiating_inbound_streams: usize,
+    substream_upgrade_timeout: Duration,
+    pending_connection_timeout: Duration,
+    max_pending_incoming: Option<usize>,
+    max_concurrent_dials: Option<usize>,
+    max_concurrent_dials_per_peer: Option<usize>,
+    negotiation_cache: bool,
+    selection_policy: SelectionPolicy,
 }
 
 impl PoolConfig {
@@ -610,9 +906,28 @@ impl PoolConfig {
             per_connection_event_buffer_size: 10,
             idle_connection_timeout: Duration::from_secs(60),
             max_negotiating_inbound_streams: 128,
+            substream_upgrade_timeout: Duration::from_secs(5),
+            pending_connection_timeout: Duration::from_secs(30),
+            max_pending_incoming: None,
+            max_concurrent_dials: None,
+            max_concurrent_dials_per_peer: None,
+            negotiation_cache: true,
+            selection_policy: SelectionPolicy::default(),
         }
     }
 
+    /// 使用 tokio 运行时派发连接任务
+    #[cfg(feature = "tokio")]
+    pub fn with_tokio_executor() -> Self {
+        Self::new(Box::new(crate::executor::TokioExecutor))
+    }
+
+    /// 使用 async-std 运行时派发连接任务
+    #[cfg(feature = "async-std")]
+    pub fn with_async_std_executor() -> Self {
+        Self::new(Box::new(crate::executor::AsyncStdExecutor))
+    }
+
     pub fn with_task_command_buffer_size(mut self, size: usize) -> Self {
         self.task_command_buffer_size = size;
         self
@@ -632,4 +947,50 @@ impl PoolConfig {
         self.max_negotiating_inbound_streams = count;
         self
     }
+
+    /// 未通过 [`SubstreamProtocol::with_timeout`](crate::SubstreamProtocol::with_timeout)
+    /// 指定超时的子流使用的协商超时
+    pub fn with_substream_upgrade_timeout(mut self, timeout: Duration) -> Self {
+        self.substream_upgrade_timeout = timeout;
+        self
+    }
+
+    /// 拨号或入站握手超过该时长仍未完成时中止，报告 [`PendingConnectionError::Timeout`](crate::error::PendingConnectionError::Timeout)
+    pub fn with_pending_connection_timeout(mut self, timeout: Duration) -> Self {
+        self.pending_connection_timeout = timeout;
+        self
+    }
+
+    /// 等待握手的入站连接上限，超出时直接拒绝新连接，默认不限制
+    pub fn with_max_pending_incoming(mut self, count: usize) -> Self {
+        self.max_pending_incoming = Some(count);
+        self
+    }
+
+    /// 同时进行的拨号上限，行为经 `poll_dial` 发起的超额拨号与拨号重试排队等待，默认不限制
+    pub fn with_max_concurrent_dials(mut self, count: usize) -> Self {
+        self.max_concurrent_dials = Some(count);
+        self
+    }
+
+    /// 同一对端同时进行的拨号上限，超额拨号的处理同 [`PoolConfig::with_max_concurrent_dials`]
+    pub fn with_max_concurrent_dials_per_peer(mut self, count: usize) -> Self {
+        self.max_concurrent_dials_per_peer = Some(count);
+        self
+    }
+
+    /// 出站子流的首选协议已在连接上协商成功时省略确认，默认启用
+    ///
+    /// 对端不再支持该协议时子流读取失败，之后的子流重新完整协商。
+    pub fn with_negotiation_cache(mut self, enabled: bool) -> Self {
+        self.negotiation_cache = enabled;
+        self
+    }
+
+    /// [`NotifyHandler::Any`](crate::behavior::NotifyHandler::Any) 选择连接的默认策略，
+    /// 默认为 [`SelectionPolicy::FirstReady`]
+    pub fn with_selection_policy(mut self, policy: SelectionPolicy) -> Self {
+        self.selection_policy = policy;
+        self
+    }
 }
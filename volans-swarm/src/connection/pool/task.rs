@@ -1,16 +1,29 @@
-use std::{convert::Infallible, pin::Pin};
+use std::{
+    collections::VecDeque,
+    convert::Infallible,
+    io, mem,
+    pin::Pin,
+    sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    },
+    task::Poll,
+    time::Duration,
+};
 
 use futures::{
-    SinkExt, StreamExt,
+    FutureExt, SinkExt, StreamExt,
     channel::{mpsc, oneshot},
     future,
+    stream::FuturesUnordered,
 };
-use volans_core::{PeerId, TransportError, Multiaddr, muxing::StreamMuxerBox};
+use volans_core::{Multiaddr, PeerId, TransportError, muxing::StreamMuxerBox};
 
 use crate::{
-    ConnectionHandler, ConnectionId,
+    ConnectionHandler, ConnectionId, DialStrategy,
     connection::ConnectionController,
-    error::{ConnectionError, PendingConnectionError},
+    error::{CloseReason, PendingConnectionError},
+    timer::Delay,
 };
 
 #[derive(Debug)]
@@ -24,6 +37,8 @@ pub(crate) enum PendingConnectionEvent {
         id: ConnectionId,
         peer_id: PeerId,
         muxer: StreamMuxerBox,
+        /// 多地址拨号时实际连通的地址
+        remote_addr: Option<Multiaddr>,
     },
     PendingFailed {
         id: ConnectionId,
@@ -50,7 +65,7 @@ pub(crate) enum EstablishedConnectionEvent<TEvent> {
     Closed {
         id: ConnectionId,
         peer_id: PeerId,
-        error: Option<ConnectionError>,
+        reason: CloseReason,
     },
 }
 
@@ -58,42 +73,119 @@ pub(crate) async fn new_for_pending_connection<TFut>(
     connection_id: ConnectionId,
     addr: Multiaddr,
     future: TFut,
+    timeout: Duration,
     abort_receiver: oneshot::Receiver<Infallible>,
-    mut events: mpsc::Sender<PendingConnectionEvent>,
+    events: mpsc::Sender<PendingConnectionEvent>,
 ) where
     TFut: Future<Output = Result<(PeerId, StreamMuxerBox), std::io::Error>> + Send + 'static,
 {
-    match future::select(abort_receiver, Box::pin(future)).await {
-        future::Either::Left((Err(oneshot::Canceled), _)) => {
-            let _ = events
-                .send(PendingConnectionEvent::PendingFailed {
-                    id: connection_id,
-                    error: PendingConnectionError::Aborted,
-                })
-                .await;
-        }
+    let upgrade = future.map(|result| match result {
+        Ok(output) => Ok((None, output)),
+        Err(e) => Err(PendingConnectionError::Transport {
+            addr,
+            error: TransportError::Other(e),
+        }),
+    });
+    run_pending(connection_id, upgrade, timeout, abort_receiver, events).await
+}
+
+pub(crate) async fn new_for_pending_dial<TFut>(
+    connection_id: ConnectionId,
+    attempts: Vec<(Multiaddr, TFut)>,
+    failed: DialErrors,
+    strategy: DialStrategy,
+    timeout: Duration,
+    abort_receiver: oneshot::Receiver<Infallible>,
+    events: mpsc::Sender<PendingConnectionEvent>,
+) where
+    TFut: Future<Output = Result<(PeerId, StreamMuxerBox), io::Error>> + Send + 'static,
+{
+    let dial = dial_fallback(attempts, failed, strategy).map(|result| match result {
+        Ok((addr, output)) => Ok((Some(addr), output)),
+        Err(errors) => Err(PendingConnectionError::AllAttemptsFailed(errors)),
+    });
+    run_pending(connection_id, dial, timeout, abort_receiver, events).await
+}
+
+/// 等待连接建立，超时或被中止时报告失败
+async fn run_pending<TFut>(
+    connection_id: ConnectionId,
+    future: TFut,
+    timeout: Duration,
+    abort_receiver: oneshot::Receiver<Infallible>,
+    mut events: mpsc::Sender<PendingConnectionEvent>,
+) where
+    TFut: Future<
+            Output = Result<(Option<Multiaddr>, (PeerId, StreamMuxerBox)), PendingConnectionError>,
+        > + Send,
+{
+    let pending = future::select(Box::pin(future), Delay::new(timeout));
+    let result = match future::select(abort_receiver, pending).await {
+        future::Either::Left((Err(oneshot::Canceled), _)) => Err(PendingConnectionError::Aborted),
         future::Either::Left((Ok(v), _)) => unreachable!("Unexpected abort: {v:?}"),
-        future::Either::Right((Ok((peer_id, muxer)), _)) => {
-            let _ = events
-                .send(PendingConnectionEvent::ConnectionEstablished {
-                    id: connection_id,
-                    peer_id,
-                    muxer,
-                })
-                .await;
+        future::Either::Right((future::Either::Left((result, _)), _)) => result,
+        future::Either::Right((future::Either::Right(((), _)), _)) => {
+            Err(PendingConnectionError::Timeout)
         }
-        future::Either::Right((Err(e), _)) => {
-            let _ = events
-                .send(PendingConnectionEvent::PendingFailed {
-                    id: connection_id,
-                    error: PendingConnectionError::Transport {
-                        addr,
-                        error: TransportError::Other(e),
-                    },
-                })
-                .await;
+    };
+    let event = match result {
+        Ok((remote_addr, (peer_id, muxer))) => PendingConnectionEvent::ConnectionEstablished {
+            id: connection_id,
+            peer_id,
+            muxer,
+            remote_addr,
+        },
+        Err(error) => PendingConnectionEvent::PendingFailed {
+            id: connection_id,
+            error,
+        },
+    };
+    let _ = events.send(event).await;
+}
+
+type DialErrors = Vec<(Multiaddr, TransportError<io::Error>)>;
+
+/// 按策略启动各地址的拨号，返回第一个成功的连接，其余拨号随之取消
+async fn dial_fallback<TFut>(
+    attempts: Vec<(Multiaddr, TFut)>,
+    mut errors: DialErrors,
+    strategy: DialStrategy,
+) -> Result<(Multiaddr, (PeerId, StreamMuxerBox)), DialErrors>
+where
+    TFut: Future<Output = Result<(PeerId, StreamMuxerBox), io::Error>>,
+{
+    let mut queued = VecDeque::from(attempts);
+    let mut inflight = FuturesUnordered::new();
+    let mut next_attempt: Option<Delay> = None;
+    future::poll_fn(|cx| {
+        loop {
+            let start_next = inflight.is_empty()
+                || strategy == DialStrategy::Concurrent
+                || next_attempt
+                    .as_mut()
+                    .is_some_and(|delay| delay.poll_unpin(cx).is_ready());
+            if start_next && let Some((addr, future)) = queued.pop_front() {
+                tracing::trace!(%addr, "Dialing candidate address");
+                inflight.push(future.map(move |result| (addr, result)));
+                next_attempt = match strategy {
+                    DialStrategy::Staggered(delay) if !queued.is_empty() => Some(Delay::new(delay)),
+                    _ => None,
+                };
+                continue;
+            }
+            match inflight.poll_next_unpin(cx) {
+                Poll::Ready(Some((addr, Ok(output)))) => return Poll::Ready(Ok((addr, output))),
+                Poll::Ready(Some((addr, Err(error)))) => {
+                    tracing::debug!(%addr, "Candidate address failed: {}", error);
+                    errors.push((addr, TransportError::Other(error)));
+                }
+                // 没有进行中的拨号说明候选地址已全部尝试
+                Poll::Ready(None) => return Poll::Ready(Err(mem::take(&mut errors))),
+                Poll::Pending => return Poll::Pending,
+            }
         }
-    }
+    })
+    .await
 }
 
 pub(crate) async fn new_for_established_connection<THandler, TConnection>(
@@ -101,20 +193,32 @@ pub(crate) async fn new_for_established_connection<THandler, TConnection>(
     peer_id: PeerId,
     mut connection: TConnection,
     mut command_receiver: mpsc::Receiver<Command<THandler::Action>>,
+    pending_commands: Arc<AtomicUsize>,
     mut events: mpsc::Sender<EstablishedConnectionEvent<THandler::Event>>,
 ) where
     THandler: ConnectionHandler,
     TConnection: ConnectionController<THandler> + Unpin,
 {
+    let mut initialized = false;
     loop {
         match future::select(
             command_receiver.next(),
-            future::poll_fn(|cx| Pin::new(&mut connection).poll(cx)),
+            future::poll_fn(|cx| {
+                // 处理器的初始化在连接任务中进行，不阻塞 Swarm，期间仍响应关闭命令
+                if !initialized {
+                    futures::ready!(connection.poll_init(cx))?;
+                    initialized = true;
+                }
+                Pin::new(&mut connection).poll(cx)
+            }),
         )
         .await
         {
             future::Either::Left((Some(command), _)) => match command {
-                Command::Action(action) => connection.handle_action(action),
+                Command::Action(action) => {
+                    pending_commands.fetch_sub(1, Ordering::Relaxed);
+                    connection.handle_action(action);
+                }
                 Command::Close => {
                     // 底层连接错误
                     command_receiver.close();
@@ -130,12 +234,14 @@ pub(crate) async fn new_for_established_connection<THandler, TConnection>(
                         }))
                         .await;
 
-                    let error = closing_muxer.await.err().map(ConnectionError::Io);
+                    if let Err(error) = closing_muxer.await {
+                        tracing::debug!(%peer_id, "Failed to close connection muxer: {}", error);
+                    }
                     let _ = events
                         .send(EstablishedConnectionEvent::Closed {
                             id: connection_id,
                             peer_id,
-                            error,
+                            reason: CloseReason::LocalRequested,
                         })
                         .await;
                     return;
@@ -170,7 +276,7 @@ pub(crate) async fn new_for_established_connection<THandler, TConnection>(
                     .send(EstablishedConnectionEvent::Closed {
                         id: connection_id,
                         peer_id,
-                        error: Some(err),
+                        reason: CloseReason::from(err),
                     })
                     .await;
                 return;
@@ -2,9 +2,12 @@ pub use crate::{
     BehaviorEvent, ConnectionDenied, ConnectionHandler, ConnectionId, DialOpts, ListenerEvent,
     NetworkBehavior, NetworkIncomingBehavior, NetworkOutgoingBehavior, THandler, THandlerAction,
     THandlerEvent,
-    error::{ConnectionError, DialError, ListenError},
-    handler::ConnectionHandlerSelect,
+    error::{CloseReason, DialError, ListenError},
+    handler::{
+        ConnectionHandlerMux, ConnectionHandlerSelect, InboundOnlyHandler, MuxEnvelope,
+        OutboundOnlyHandler,
+    },
 };
 pub use either::Either;
 pub use futures::prelude as futures;
-pub use volans_core::{ConnectedPoint, Endpoint, PeerId, Multiaddr};
+pub use volans_core::{ConnectedPoint, Endpoint, Multiaddr, PeerId};
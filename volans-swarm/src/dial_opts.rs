@@ -1,15 +1,20 @@
-use std::fmt;
+use std::{fmt, sync::Arc, time::Duration};
 
-use volans_core::{PeerId, Multiaddr};
+use volans_core::{Multiaddr, PeerId, TransportError, transport::dns::Resolver};
 
-use crate::ConnectionId;
+use crate::{ConnectionId, error::DialError};
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct DialOpts {
     peer_id: Option<PeerId>,
     condition: PeerCondition,
     addr: Option<Multiaddr>,
+    /// 首选地址之后的候选地址
+    fallback_addrs: Vec<Multiaddr>,
+    strategy: DialStrategy,
     connection_id: ConnectionId,
+    retry_policy: Option<RetryPolicy>,
+    resolver: Option<Arc<dyn Resolver>>,
 }
 
 impl DialOpts {
@@ -18,15 +23,50 @@ impl DialOpts {
             peer_id,
             condition: PeerCondition::default(),
             addr,
+            fallback_addrs: Vec::new(),
+            strategy: DialStrategy::default(),
             connection_id: ConnectionId::next(),
+            retry_policy: None,
+            resolver: None,
         }
     }
 
+    /// 拨号已知节点，地址由 Swarm 的地址簿或行为提供
+    pub fn peer(peer_id: PeerId) -> Self {
+        Self::new(None, Some(peer_id))
+    }
+
     pub fn with_condition(mut self, condition: PeerCondition) -> Self {
         self.condition = condition;
         self
     }
 
+    /// 按顺序给出的候选地址，第一个作为首选地址，其余在首选地址不通时依次尝试
+    pub fn with_addresses(mut self, addrs: impl IntoIterator<Item = Multiaddr>) -> Self {
+        let mut addrs = addrs.into_iter();
+        self.addr = addrs.next();
+        self.fallback_addrs = addrs.collect();
+        self
+    }
+
+    /// 多个候选地址之间的尝试方式
+    pub fn with_strategy(mut self, strategy: DialStrategy) -> Self {
+        self.strategy = strategy;
+        self
+    }
+
+    /// 连接失败后按策略重试，`dial` 立即返回的错误不会重试
+    pub fn with_retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.retry_policy = Some(policy);
+        self
+    }
+
+    /// 本次拨号解析 `/dns` 地址使用的解析器，替代 DNS 传输层自身的解析器
+    pub fn with_resolver(mut self, resolver: Arc<dyn Resolver>) -> Self {
+        self.resolver = Some(resolver);
+        self
+    }
+
     pub fn peer_id(&self) -> Option<PeerId> {
         self.peer_id
     }
@@ -42,6 +82,135 @@ impl DialOpts {
     pub fn addr(&self) -> Option<Multiaddr> {
         self.addr.clone()
     }
+
+    pub fn fallback_addrs(&self) -> &[Multiaddr] {
+        &self.fallback_addrs
+    }
+
+    pub fn strategy(&self) -> DialStrategy {
+        self.strategy
+    }
+
+    pub fn retry_policy(&self) -> Option<&RetryPolicy> {
+        self.retry_policy.as_ref()
+    }
+
+    pub fn resolver(&self) -> Option<&Arc<dyn Resolver>> {
+        self.resolver.as_ref()
+    }
+}
+
+/// 多地址拨号时候选地址的尝试方式
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum DialStrategy {
+    /// 前一个地址失败后才尝试下一个
+    Sequential,
+    /// 同时尝试所有地址
+    Concurrent,
+    /// 前一个地址在给定时间内未连通或已失败时开始尝试下一个（happy eyeballs）
+    Staggered(Duration),
+}
+
+impl Default for DialStrategy {
+    fn default() -> Self {
+        DialStrategy::Staggered(Duration::from_millis(250))
+    }
+}
+
+/// 拨号重试策略，重试间隔按指数增长并加入随机抖动
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    max_attempts: u32,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+    multiplier: f64,
+    jitter: f64,
+    retry_on: fn(&DialError) -> bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 5,
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(30),
+            multiplier: 2.0,
+            jitter: 0.2,
+            retry_on: is_transient,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// 最多尝试次数，包含首次拨号
+    pub fn with_max_attempts(mut self, max_attempts: u32) -> Self {
+        self.max_attempts = max_attempts.max(1);
+        self
+    }
+
+    /// 首次重试前的等待时间
+    pub fn with_initial_backoff(mut self, backoff: Duration) -> Self {
+        self.initial_backoff = backoff;
+        self
+    }
+
+    /// 等待时间上限
+    pub fn with_max_backoff(mut self, backoff: Duration) -> Self {
+        self.max_backoff = backoff;
+        self
+    }
+
+    /// 每次重试后等待时间的增长倍数
+    pub fn with_multiplier(mut self, multiplier: f64) -> Self {
+        self.multiplier = multiplier.max(1.0);
+        self
+    }
+
+    /// 抖动比例，等待时间在 `[1 - jitter, 1 + jitter]` 倍之间随机
+    pub fn with_jitter(mut self, jitter: f64) -> Self {
+        self.jitter = jitter.clamp(0.0, 1.0);
+        self
+    }
+
+    /// 判断错误是否值得重试，默认只重试传输层的 I/O 错误
+    pub fn with_retry_on(mut self, retry_on: fn(&DialError) -> bool) -> Self {
+        self.retry_on = retry_on;
+        self
+    }
+
+    pub fn max_attempts(&self) -> u32 {
+        self.max_attempts
+    }
+
+    /// 第 `attempt` 次尝试失败后是否继续重试
+    pub fn should_retry(&self, attempt: u32, error: &DialError) -> bool {
+        attempt < self.max_attempts && (self.retry_on)(error)
+    }
+
+    /// 第 `attempt` 次尝试失败后的等待时间
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let exp = attempt.saturating_sub(1).min(i32::MAX as u32) as i32;
+        let base = self.initial_backoff.as_secs_f64() * self.multiplier.powi(exp);
+        let factor = if self.jitter > 0.0 {
+            1.0 + rand::random_range(-self.jitter..=self.jitter)
+        } else {
+            1.0
+        };
+        let delay = (base * factor).min(self.max_backoff.as_secs_f64());
+        Duration::try_from_secs_f64(delay).unwrap_or(self.max_backoff)
+    }
+}
+
+/// 默认的重试判断：传输层的 I/O 错误，不支持的地址不重试
+fn is_transient(error: &DialError) -> bool {
+    match error {
+        DialError::Transport { error, .. } => matches!(error, TransportError::Other(_)),
+        DialError::AllAttemptsFailed { errors } => errors
+            .iter()
+            .any(|(_, error)| matches!(error, TransportError::Other(_))),
+        DialError::Timeout => true,
+        _ => false,
+    }
 }
 
 #[derive(Debug, Copy, Clone, Default)]
@@ -0,0 +1,421 @@
+use std::{
+    io,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use futures::{
+    Stream, StreamExt,
+    channel::{mpsc, oneshot},
+    future,
+};
+use volans_core::{
+    ConnectedPoint, Multiaddr, PeerId, TransportError, muxing::StreamMuxerBox, transport,
+};
+
+use crate::{
+    Bandwidth, ConnectionId, ConnectionInfo, DialOpts, ExternalAddrStore, InboundStreamHandler,
+    ListenOpts, ListenerId, NetworkIncomingBehavior, NetworkOutgoingBehavior,
+    OutboundStreamHandler, PeerStore, PendingConnectionFilter,
+    connection::PoolConfig,
+    error::{DialError, SwarmClosed},
+    handle::{self, BehaviorCommand, CommandSender},
+    observer::SwarmObserver,
+    replay::Subscription,
+    swarm::{AddressEvent, SwarmCore},
+};
+
+pub use crate::SwarmEvent;
+
+/// 同时支持拨号与监听的 Swarm
+///
+/// 拨出的连接由 `OutboundStreamHandler` 驱动，接入的连接由 `InboundStreamHandler` 驱动，
+/// 两者共享同一个连接池。
+pub struct Swarm<TBehavior>
+where
+    TBehavior: NetworkIncomingBehavior + NetworkOutgoingBehavior,
+    TBehavior::ConnectionHandler: InboundStreamHandler + OutboundStreamHandler,
+{
+    core: SwarmCore<TBehavior>,
+
+    /// [`SwarmHandle`] 发来的命令
+    command_sender: CommandSender<Command<TBehavior>>,
+    command_receiver: mpsc::Receiver<Command<TBehavior>>,
+}
+
+impl<TBehavior> Unpin for Swarm<TBehavior>
+where
+    TBehavior: NetworkIncomingBehavior + NetworkOutgoingBehavior,
+    TBehavior::ConnectionHandler: InboundStreamHandler + OutboundStreamHandler,
+{
+}
+
+impl<TBehavior> Swarm<TBehavior>
+where
+    TBehavior: NetworkIncomingBehavior + NetworkOutgoingBehavior,
+    TBehavior::ConnectionHandler: InboundStreamHandler + OutboundStreamHandler,
+{
+    pub fn new(
+        transport: transport::Boxed<(PeerId, StreamMuxerBox)>,
+        behavior: TBehavior,
+        local_peer_id: PeerId,
+        config: PoolConfig,
+    ) -> Self {
+        let (command_sender, command_receiver) = handle::channel();
+        Self {
+            core: SwarmCore::new(transport, behavior, local_peer_id, config),
+            command_sender,
+            command_receiver,
+        }
+    }
+
+    /// 添加事件观察者，可添加多个，按添加顺序通知
+    pub fn with_observer(
+        mut self,
+        observer: impl SwarmObserver<TBehavior::Event> + 'static,
+    ) -> Self {
+        self.core.observers.push(Box::new(observer));
+        self
+    }
+
+    /// 替换默认的地址簿
+    pub fn with_peer_store(mut self, peer_store: PeerStore) -> Self {
+        self.core.dial.peer_store = peer_store;
+        self
+    }
+
+    /// 替换默认的外部地址簿，用于调整确认阈值
+    pub fn with_external_addr_store(mut self, external_addrs: ExternalAddrStore) -> Self {
+        self.core.listen.external_addrs = external_addrs;
+        self
+    }
+
+    /// 等待握手的连接达到 `count` 时暂停接受新连接，回落后自动恢复
+    pub fn with_pending_incoming_high_water_mark(mut self, count: usize) -> Self {
+        self.core.listen.pending_incoming_high_water_mark = Some(count);
+        self
+    }
+
+    /// 设置入站连接过滤器，被拒绝的连接以 [`ListenError::Filtered`](crate::error::ListenError::Filtered) 上报
+    pub fn with_pending_connection_filter(mut self, filter: impl PendingConnectionFilter) -> Self {
+        self.core.listen.pending_connection_filter = Some(Box::new(filter));
+        self
+    }
+
+    /// 可在其他任务中使用的句柄，命令在 Swarm 被轮询时执行
+    pub fn handle(&self) -> SwarmHandle<TBehavior> {
+        SwarmHandle {
+            commands: self.command_sender.clone(),
+        }
+    }
+
+    /// 关闭指定的连接
+    pub fn close_connection(&mut self, connection_id: ConnectionId) -> bool {
+        self.core.close_connection(connection_id)
+    }
+
+    /// 优雅关闭 Swarm
+    ///
+    /// 拒绝新的拨号并移除所有监听器，中断等待中的连接并关闭所有已建立的连接，
+    /// 期间的事件仍会通知行为与观察者，所有监听器与连接任务结束后返回。
+    pub async fn close(&mut self) {
+        self.core.start_close();
+        self.core.remove_listeners();
+        self.core.abort_pending_dials();
+        future::poll_fn(|cx| self.poll_close(cx)).await
+    }
+
+    fn poll_close(&mut self, cx: &mut Context<'_>) -> Poll<()> {
+        loop {
+            if self.core.is_closed() {
+                return Poll::Ready(());
+            }
+            match Pin::new(&mut *self).poll_next_event(cx) {
+                Poll::Ready(_) => continue,
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+
+    pub fn local_peer_id(&self) -> &PeerId {
+        self.core.pool.local_peer_id()
+    }
+
+    /// 检查指定的 PeerId 是否已连接
+    pub fn is_peer_connected(&self, peer_id: &PeerId) -> bool {
+        self.core.pool.is_peer_connected(peer_id)
+    }
+
+    /// 获取所有已连接的 PeerId
+    pub fn connected_peers(&self) -> impl Iterator<Item = &PeerId> {
+        self.core.pool.iter_peer_connected()
+    }
+
+    /// 获取所有已连接的连接 ID
+    pub fn connected_connections(&self) -> impl Iterator<Item = &ConnectionId> {
+        self.core.pool.iter_connected()
+    }
+
+    /// 已建立连接的元数据
+    pub fn connection_info(&self, connection_id: ConnectionId) -> Option<ConnectionInfo> {
+        self.core.pool.connection_info(connection_id)
+    }
+
+    /// 所有已建立连接的元数据
+    pub fn iter_connections(&self) -> impl Iterator<Item = ConnectionInfo> + '_ {
+        self.core.pool.iter_connections()
+    }
+
+    pub fn behavior(&self) -> &TBehavior {
+        &self.core.behavior
+    }
+
+    pub fn behavior_mut(&mut self) -> &mut TBehavior {
+        &mut self.core.behavior
+    }
+
+    /// 已知节点的地址簿
+    pub fn peer_store(&self) -> &PeerStore {
+        &self.core.dial.peer_store
+    }
+
+    pub fn peer_store_mut(&mut self) -> &mut PeerStore {
+        &mut self.core.dial.peer_store
+    }
+
+    /// 按连接与传输协议统计的流量
+    pub fn bandwidth(&self) -> &Bandwidth {
+        self.core.pool.bandwidth()
+    }
+
+    /// 发起新的出站连接
+    ///
+    /// 立即返回的错误不会重试；配置了 [`RetryPolicy`](crate::RetryPolicy) 时，
+    /// 连接建立失败会按策略重新拨号，最终失败产生 [`SwarmEvent::DialGivenUp`]。
+    pub fn dial(&mut self, opts: DialOpts) -> Result<Multiaddr, DialError> {
+        self.core.dial(opts)
+    }
+
+    /// 中断进行中的拨号，产生错误为 [`DialError::Aborted`] 的
+    /// [`SwarmEvent::OutgoingConnectionError`]，被中断的拨号不再重试
+    ///
+    /// 等待重试或排队中的拨号同样被取消，拨号不存在或连接已建立时返回 `false`。
+    pub fn abort_dial(&mut self, connection_id: ConnectionId) -> bool {
+        self.core.abort_dial(connection_id)
+    }
+
+    /// 开始监听指定的地址
+    pub fn listen_on(&mut self, addr: Multiaddr) -> Result<ListenerId, TransportError<io::Error>> {
+        self.listen_with_opts(ListenOpts::new(addr))
+    }
+
+    /// 按 [`ListenOpts`] 开始监听，可为该监听器单独设置套接字选项
+    pub fn listen_with_opts(
+        &mut self,
+        opts: ListenOpts,
+    ) -> Result<ListenerId, TransportError<io::Error>> {
+        self.core.listen_with_opts(opts)
+    }
+
+    /// 获取所有监听的地址
+    pub fn listeners(&self) -> impl Iterator<Item = &Multiaddr> {
+        self.core.listen.addresses()
+    }
+
+    /// 暂停监听器接受新连接，监听器保持打开，期间的连接由传输层的 backlog 缓冲
+    pub fn pause_listener(&mut self, listener_id: ListenerId) -> bool {
+        self.core.set_listener_paused(listener_id, true)
+    }
+
+    /// 恢复被 [`Swarm::pause_listener`] 暂停的监听器
+    pub fn resume_listener(&mut self, listener_id: ListenerId) -> bool {
+        self.core.set_listener_paused(listener_id, false)
+    }
+
+    /// 移除指定的监听器
+    pub fn remove_listener(&mut self, listener_id: ListenerId) -> bool {
+        self.core.remove_listener(listener_id)
+    }
+
+    /// 手动添加已确认的外部地址，地址此前未被确认时通知行为
+    pub fn add_external_address(&mut self, addr: Multiaddr) {
+        self.core.add_external_address(addr)
+    }
+
+    /// 移除外部地址，地址此前已被确认时通知行为并返回 `true`
+    pub fn remove_external_address(&mut self, addr: &Multiaddr) -> bool {
+        self.core.remove_external_address(addr)
+    }
+
+    /// 已确认的外部地址
+    pub fn external_addresses(&self) -> impl Iterator<Item = &Multiaddr> {
+        self.core.listen.external_addrs.confirmed()
+    }
+
+    fn handle_command(&mut self, command: Command<TBehavior>) {
+        match command {
+            Command::Dial(opts, sender) => {
+                let _ = sender.send(self.dial(opts));
+            }
+            Command::ListenOn(addr, sender) => {
+                let _ = sender.send(self.listen_on(addr));
+            }
+            Command::CloseConnection(connection_id, sender) => {
+                let _ = sender.send(self.close_connection(connection_id));
+            }
+            Command::Behavior(command) => command(&mut self.core.behavior),
+        }
+    }
+
+    #[tracing::instrument(level = "debug", name = "Swarm::poll", skip(self, cx))]
+    fn poll_next_event(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<SwarmEvent<TBehavior::Event>> {
+        let this = &mut *self;
+        loop {
+            if let Some(event) = this.core.next_event() {
+                return Poll::Ready(event);
+            }
+            if let Poll::Ready(Some(command)) = this.command_receiver.poll_next_unpin(cx) {
+                this.handle_command(command);
+                continue;
+            }
+            match this.core.poll_behavior(cx) {
+                Poll::Pending => {}
+                Poll::Ready(None) => continue,
+                Poll::Ready(Some(AddressEvent::PeerAddress { peer_id, addr })) => {
+                    this.core.dial.peer_store.add_address(peer_id, addr);
+                    continue;
+                }
+                Poll::Ready(Some(event)) => {
+                    this.core.handle_external_addr_event(event);
+                    continue;
+                }
+            }
+            if this.core.poll_dials(cx) {
+                continue;
+            }
+
+            // 处理连接池中的事件，按连接方向分派，连接事件与方向无关
+            match this.core.pool.poll(cx) {
+                Poll::Pending => {}
+                Poll::Ready(pool_event) => {
+                    match pool_event.endpoint() {
+                        Some(ConnectedPoint::Dialer { .. }) => {
+                            this.core.handle_dialer_pool_event(pool_event)
+                        }
+                        _ => this.core.handle_listener_pool_event(pool_event),
+                    }
+                    continue;
+                }
+            }
+
+            // 处理监听器事件
+            if this.core.poll_listeners(cx) {
+                continue;
+            }
+            return Poll::Pending;
+        }
+    }
+}
+
+impl<TBehavior> Swarm<TBehavior>
+where
+    TBehavior: NetworkIncomingBehavior + NetworkOutgoingBehavior,
+    TBehavior::ConnectionHandler: InboundStreamHandler + OutboundStreamHandler,
+    TBehavior::Event: Clone + Send + 'static,
+{
+    /// 保留最近 `capacity` 个事件，之后订阅的消费者先收到这些事件
+    pub fn with_replay_buffer(mut self, capacity: usize) -> Self {
+        self.core.replay().set_capacity(capacity);
+        self
+    }
+
+    /// 订阅 Swarm 事件，可在任意时刻订阅，先收到回放缓冲中的事件
+    ///
+    /// 未设置 [`Self::with_replay_buffer`] 时只收到订阅之后的事件。
+    pub fn subscribe(&mut self) -> Subscription<TBehavior::Event> {
+        self.core.replay().subscribe()
+    }
+}
+
+/// 从其他任务驱动 [`Swarm`] 的句柄
+///
+/// 命令经通道发往 Swarm，在 Swarm 所在任务轮询时依次执行，调用等待执行结果返回。
+pub struct SwarmHandle<TBehavior> {
+    commands: CommandSender<Command<TBehavior>>,
+}
+
+impl<TBehavior> Clone for SwarmHandle<TBehavior> {
+    fn clone(&self) -> Self {
+        Self {
+            commands: self.commands.clone(),
+        }
+    }
+}
+
+impl<TBehavior> SwarmHandle<TBehavior> {
+    /// 发起拨号，Swarm 已被丢弃时返回 [`DialError::Closing`]
+    pub async fn dial(&self, opts: DialOpts) -> Result<Multiaddr, DialError> {
+        self.commands
+            .call(|sender| Command::Dial(opts, sender))
+            .await
+            .unwrap_or(Err(DialError::Closing))
+    }
+
+    /// 在指定地址上监听
+    pub async fn listen_on(
+        &self,
+        addr: Multiaddr,
+    ) -> Result<ListenerId, TransportError<io::Error>> {
+        self.commands
+            .call(|sender| Command::ListenOn(addr, sender))
+            .await
+            .unwrap_or_else(|closed| Err(TransportError::Other(io::Error::other(closed))))
+    }
+
+    /// 关闭指定的连接，Swarm 已被丢弃时返回 `false`
+    pub async fn close_connection(&self, connection_id: ConnectionId) -> bool {
+        self.commands
+            .call(|sender| Command::CloseConnection(connection_id, sender))
+            .await
+            .unwrap_or(false)
+    }
+
+    /// 在 Swarm 所在任务中操作行为，返回 `f` 的结果
+    pub async fn behavior_command<R>(
+        &self,
+        f: impl FnOnce(&mut TBehavior) -> R + Send + 'static,
+    ) -> Result<R, SwarmClosed>
+    where
+        R: Send + 'static,
+    {
+        self.commands.behavior(f, Command::Behavior).await
+    }
+}
+
+enum Command<TBehavior> {
+    Dial(DialOpts, oneshot::Sender<Result<Multiaddr, DialError>>),
+    ListenOn(
+        Multiaddr,
+        oneshot::Sender<Result<ListenerId, TransportError<io::Error>>>,
+    ),
+    CloseConnection(ConnectionId, oneshot::Sender<bool>),
+    Behavior(BehaviorCommand<TBehavior>),
+}
+
+impl<TBehavior> Stream for Swarm<TBehavior>
+where
+    TBehavior: NetworkIncomingBehavior + NetworkOutgoingBehavior,
+    TBehavior::ConnectionHandler: InboundStreamHandler + OutboundStreamHandler,
+{
+    type Item = SwarmEvent<TBehavior::Event>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        match self.poll_next_event(cx) {
+            Poll::Ready(event) => Poll::Ready(Some(event)),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
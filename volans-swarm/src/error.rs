@@ -1,9 +1,13 @@
 use std::{error, fmt, io};
 
-use volans_core::{PeerId, TransportError, Multiaddr};
+use volans_core::{Multiaddr, PeerId, TransportError};
 
 use crate::dial_opts;
 
+/// 行为拒绝连接的原因
+///
+/// 拒绝原因保留具体的错误类型，调用方可通过 [`ConnectionDenied::downcast_ref`]
+/// 区分连接数超限、节点被阻止等情况，这些类型由各行为公开提供。
 #[derive(Debug, thiserror::Error)]
 #[error("Connection denied: {inner}")]
 pub struct ConnectionDenied {
@@ -11,12 +15,20 @@ pub struct ConnectionDenied {
 }
 
 impl ConnectionDenied {
-    pub fn new(cause: impl Into<Box<dyn std::error::Error + Send + Sync + 'static>>) -> Self {
+    pub fn new(cause: impl std::error::Error + Send + Sync + 'static) -> Self {
         Self {
-            inner: cause.into(),
+            inner: Box::new(cause),
         }
     }
 
+    /// 拒绝原因是否为 `E`
+    pub fn is<E>(&self) -> bool
+    where
+        E: std::error::Error + Send + Sync + 'static,
+    {
+        self.inner.is::<E>()
+    }
+
     pub fn downcast<E>(self) -> Result<E, Self>
     where
         E: std::error::Error + Send + Sync + 'static,
@@ -43,7 +55,12 @@ pub enum DialError {
     LocalPeerId,
     NoAddress,
     PeerCondition(dial_opts::PeerCondition),
+    /// 拨号被中断，见 `Swarm::abort_dial`
     Aborted,
+    /// Swarm 正在关闭
+    Closing,
+    /// 连接未在等待时间内建立
+    Timeout,
     WrongPeerId {
         obtained: PeerId,
     },
@@ -56,6 +73,23 @@ pub enum DialError {
         #[source]
         error: TransportError<io::Error>,
     },
+    /// 多个候选地址均拨号失败，按尝试顺序记录每个地址的错误
+    AllAttemptsFailed {
+        errors: Vec<(Multiaddr, TransportError<io::Error>)>,
+    },
+}
+
+impl DialError {
+    /// 拨号被行为以 `E` 为原因拒绝时返回该原因
+    pub fn denied_by<E>(&self) -> Option<&E>
+    where
+        E: std::error::Error + Send + Sync + 'static,
+    {
+        match self {
+            DialError::Denied { cause } => cause.downcast_ref(),
+            _ => None,
+        }
+    }
 }
 
 impl From<PendingConnectionError> for DialError {
@@ -67,6 +101,10 @@ impl From<PendingConnectionError> for DialError {
             PendingConnectionError::Aborted => DialError::Aborted,
             PendingConnectionError::WrongPeerId { obtained } => DialError::WrongPeerId { obtained },
             PendingConnectionError::LocalPeerId => DialError::LocalPeerId,
+            PendingConnectionError::AllAttemptsFailed(errors) => {
+                DialError::AllAttemptsFailed { errors }
+            }
+            PendingConnectionError::Timeout => DialError::Timeout,
         }
     }
 }
@@ -78,6 +116,8 @@ impl fmt::Display for DialError {
             DialError::NoAddress => write!(f, "No address to dial"),
             DialError::PeerCondition(condition) => write!(f, "Peer condition not met: {condition}"),
             DialError::Aborted => write!(f, "Dialing was aborted"),
+            DialError::Closing => write!(f, "Swarm is closing"),
+            DialError::Timeout => write!(f, "Dialing timed out"),
             DialError::WrongPeerId { obtained } => {
                 write!(f, "Dialed wrong peer ID: {obtained}")
             }
@@ -86,6 +126,14 @@ impl fmt::Display for DialError {
                 write!(f, "Transport error while dialing `{addr}`, ")?;
                 print_error_chain(f, error)
             }
+            DialError::AllAttemptsFailed { errors } => {
+                write!(f, "All {} dial attempts failed", errors.len())?;
+                for (addr, error) in errors {
+                    write!(f, "\n  [{addr}]")?;
+                    print_error_chain(f, error)?;
+                }
+                Ok(())
+            }
         }
     }
 }
@@ -93,6 +141,14 @@ impl fmt::Display for DialError {
 #[derive(Debug, thiserror::Error)]
 pub enum ListenError {
     Aborted,
+    /// Swarm 正在关闭
+    Closing,
+    /// 握手未在等待时间内完成
+    Timeout,
+    /// 等待握手的连接已达到上限
+    PendingLimitReached,
+    /// 被 [`PendingConnectionFilter`](crate::PendingConnectionFilter) 拒绝，未开始握手
+    Filtered,
     WrongPeerId {
         obtained: PeerId,
     },
@@ -104,17 +160,32 @@ pub enum ListenError {
     Transport(#[source] TransportError<io::Error>),
 }
 
+impl ListenError {
+    /// 入站连接被行为以 `E` 为原因拒绝时返回该原因
+    pub fn denied_by<E>(&self) -> Option<&E>
+    where
+        E: std::error::Error + Send + Sync + 'static,
+    {
+        match self {
+            ListenError::Denied { cause } => cause.downcast_ref(),
+            _ => None,
+        }
+    }
+}
+
 impl From<PendingConnectionError> for ListenError {
     fn from(error: PendingConnectionError) -> Self {
         match error {
-            PendingConnectionError::Transport { addr: _, error } => {
-                ListenError::Transport(TransportError::from(error))
-            }
+            PendingConnectionError::Transport { addr: _, error } => ListenError::Transport(error),
             PendingConnectionError::Aborted => ListenError::Aborted,
             PendingConnectionError::WrongPeerId { obtained } => {
                 ListenError::WrongPeerId { obtained }
             }
             PendingConnectionError::LocalPeerId => ListenError::LocalPeerId,
+            PendingConnectionError::AllAttemptsFailed(_) => {
+                unreachable!("Only outgoing connections dial multiple addresses")
+            }
+            PendingConnectionError::Timeout => ListenError::Timeout,
         }
     }
 }
@@ -123,6 +194,12 @@ impl fmt::Display for ListenError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             ListenError::Aborted => write!(f, "Listening was aborted"),
+            ListenError::Closing => write!(f, "Swarm is closing"),
+            ListenError::Timeout => write!(f, "Incoming connection handshake timed out"),
+            ListenError::PendingLimitReached => {
+                write!(f, "Too many incoming connections pending handshake")
+            }
+            ListenError::Filtered => write!(f, "Incoming connection rejected by filter"),
             ListenError::WrongPeerId { obtained } => {
                 write!(f, "Listening on wrong peer ID: {obtained}")
             }
@@ -149,13 +226,92 @@ fn print_error_chain(f: &mut fmt::Formatter<'_>, e: &dyn error::Error) -> fmt::R
 #[derive(Debug, thiserror::Error)]
 pub enum ConnectionError {
     #[error("Connection I/O error: {0}")]
-    Io(#[from] std::io::Error),
+    Io(std::io::Error),
     #[error("Connection keep-alive timeout")]
     KeepAliveTimeout,
+    /// 对端在超时时间内没有响应，通常是对端已失联
+    #[error("Connection timed out: {0}")]
+    Timeout(#[source] std::io::Error),
     #[error("Connection closing")]
     Closing,
 }
 
+/// 连接关闭原因
+#[derive(Debug, thiserror::Error)]
+pub enum CloseReason {
+    /// 本地主动关闭
+    #[error("Connection closed locally")]
+    LocalRequested,
+    /// 对端关闭或断开了连接
+    #[error("Connection closed by remote")]
+    RemoteClosed,
+    /// 所有处理器空闲超过保活时间
+    #[error("Connection idle timeout")]
+    IdleTimeout,
+    /// 处理器要求关闭连接
+    #[error("Connection closed by handler")]
+    HandlerError,
+    /// 对端在超时时间内没有响应
+    #[error("Connection timed out: {0}")]
+    Timeout(#[source] io::Error),
+    /// 多路复用器出错
+    #[error("Connection muxer error: {0}")]
+    MuxerError(#[source] io::Error),
+}
+
+impl From<io::Error> for ConnectionError {
+    fn from(error: io::Error) -> Self {
+        if is_timeout(&error) {
+            ConnectionError::Timeout(error)
+        } else {
+            ConnectionError::Io(error)
+        }
+    }
+}
+
+impl From<ConnectionError> for CloseReason {
+    fn from(error: ConnectionError) -> Self {
+        match error {
+            ConnectionError::Io(error) if is_remote_close(&error) => CloseReason::RemoteClosed,
+            ConnectionError::Io(error) => CloseReason::MuxerError(error),
+            ConnectionError::KeepAliveTimeout => CloseReason::IdleTimeout,
+            ConnectionError::Timeout(error) => CloseReason::Timeout(error),
+            ConnectionError::Closing => CloseReason::HandlerError,
+        }
+    }
+}
+
+/// 沿错误链查找表示对端断开的 I/O 错误，多路复用器错误通常被包装在 [`io::Error::other`] 中
+pub(crate) fn is_remote_close(error: &io::Error) -> bool {
+    has_io_error_kind(error, |kind| {
+        matches!(
+            kind,
+            io::ErrorKind::UnexpectedEof
+                | io::ErrorKind::ConnectionReset
+                | io::ErrorKind::ConnectionAborted
+                | io::ErrorKind::BrokenPipe
+        )
+    })
+}
+
+/// 沿错误链查找超时的 I/O 错误，例如多路复用器保活超时
+pub(crate) fn is_timeout(error: &io::Error) -> bool {
+    has_io_error_kind(error, |kind| kind == io::ErrorKind::TimedOut)
+}
+
+fn has_io_error_kind(error: &io::Error, matches: impl Fn(io::ErrorKind) -> bool) -> bool {
+    let mut current: Option<&(dyn error::Error + 'static)> = Some(error);
+    while let Some(error) = current {
+        if let Some(error) = error.downcast_ref::<io::Error>()
+            && matches(error.kind())
+        {
+            return true;
+        }
+        current = error.source();
+    }
+    false
+}
+
 #[derive(Debug)]
 pub enum PendingConnectionError {
     Transport {
@@ -167,4 +323,28 @@ pub enum PendingConnectionError {
         obtained: PeerId,
     },
     LocalPeerId,
+    AllAttemptsFailed(Vec<(Multiaddr, TransportError<io::Error>)>),
+    /// 超过连接池配置的等待时间仍未完成握手
+    Timeout,
+}
+
+/// Swarm 已被丢弃，无法处理 [`SwarmHandle`](crate::client::SwarmHandle) 发出的命令
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+#[error("Swarm is closed")]
+pub struct SwarmClosed;
+
+/// 子流在设定的时间内没有读写进展，见 [`Substream::set_idle_timeout`](crate::Substream::set_idle_timeout)
+///
+/// 以 [`io::ErrorKind::TimedOut`] 的 I/O 错误返回，所在连接不受影响。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+#[error("Substream idle timeout")]
+pub struct SubstreamIdleTimeout;
+
+impl SubstreamIdleTimeout {
+    /// I/O 错误是否由子流空闲超时引起
+    pub fn is(error: &io::Error) -> bool {
+        error
+            .get_ref()
+            .is_some_and(|inner| inner.is::<SubstreamIdleTimeout>())
+    }
 }
@@ -0,0 +1,120 @@
+use std::{io, time::Duration};
+
+use volans_core::{ConnectedPoint, Multiaddr, PeerId};
+
+use crate::{
+    BandwidthStats, ConnectionId, ListenerId,
+    error::{CloseReason, DialError, ListenError},
+};
+
+/// Swarm 产生的事件，客户端、服务端与双向 Swarm 共用
+///
+/// 客户端不产生监听相关的事件，服务端不产生拨号相关的事件。
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum SwarmEvent<TBehaviorEvent> {
+    Behavior(TBehaviorEvent),
+
+    Dialing {
+        peer_id: Option<PeerId>,
+        addr: Multiaddr,
+        connection_id: ConnectionId,
+    },
+
+    OutgoingConnectionError {
+        peer_id: Option<PeerId>,
+        connection_id: ConnectionId,
+        addr: Option<Multiaddr>,
+        error: DialError,
+    },
+
+    /// 行为发起的拨号或拨号重试超出并发上限，排队等待其他拨号结束
+    DialQueued {
+        peer_id: Option<PeerId>,
+        connection_id: ConnectionId,
+        /// 当前排队的拨号数量
+        queued: usize,
+    },
+
+    /// 拨号失败，将在 `delay` 后进行下一次尝试
+    DialRetryScheduled {
+        peer_id: Option<PeerId>,
+        connection_id: ConnectionId,
+        /// 失败的是第几次尝试
+        attempt: u32,
+        delay: Duration,
+        error: DialError,
+    },
+
+    /// 配置了重试策略的拨号最终失败，代替 [`SwarmEvent::OutgoingConnectionError`]
+    DialGivenUp {
+        peer_id: Option<PeerId>,
+        connection_id: ConnectionId,
+        addr: Option<Multiaddr>,
+        /// 总尝试次数
+        attempts: u32,
+        error: DialError,
+    },
+
+    NewListenAddr {
+        listener_id: ListenerId,
+        addr: Multiaddr,
+    },
+
+    ExpiredListenAddr {
+        listener_id: ListenerId,
+        addr: Multiaddr,
+    },
+
+    /// 外部地址被确认可达
+    ExternalAddrConfirmed {
+        addr: Multiaddr,
+    },
+
+    /// 外部地址被移除
+    ExternalAddrExpired {
+        addr: Multiaddr,
+    },
+
+    ListenerClosed {
+        listener_id: ListenerId,
+        reason: Result<(), io::Error>,
+    },
+
+    ListenerError {
+        listener_id: ListenerId,
+        error: io::Error,
+    },
+
+    IncomingConnection {
+        connection_id: ConnectionId,
+        local_addr: Multiaddr,
+        remote_addr: Multiaddr,
+    },
+
+    IncomingConnectionError {
+        connection_id: ConnectionId,
+        local_addr: Multiaddr,
+        remote_addr: Multiaddr,
+        error: ListenError,
+        peer_id: Option<PeerId>,
+    },
+
+    ConnectionEstablished {
+        peer_id: PeerId,
+        connection_id: ConnectionId,
+        endpoint: ConnectedPoint,
+        num_established: usize,
+        established_in: Duration,
+    },
+
+    ConnectionClosed {
+        connection_id: ConnectionId,
+        peer_id: PeerId,
+        endpoint: ConnectedPoint,
+        num_remaining_established: usize,
+        reason: CloseReason,
+        /// 连接存续期间收发的字节数
+        bandwidth: BandwidthStats,
+    },
+}
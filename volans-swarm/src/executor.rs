@@ -6,6 +6,30 @@ pub trait Executor {
     fn exec(&self, future: Pin<Box<dyn Future<Output = ()> + Send>>);
 }
 
+/// 将连接任务派发到当前 tokio 运行时
+#[cfg(feature = "tokio")]
+#[derive(Debug, Default, Clone, Copy)]
+pub struct TokioExecutor;
+
+#[cfg(feature = "tokio")]
+impl Executor for TokioExecutor {
+    fn exec(&self, future: Pin<Box<dyn Future<Output = ()> + Send>>) {
+        tokio::spawn(future);
+    }
+}
+
+/// 将连接任务派发到 async-std 的全局执行器
+#[cfg(feature = "async-std")]
+#[derive(Debug, Default, Clone, Copy)]
+pub struct AsyncStdExecutor;
+
+#[cfg(feature = "async-std")]
+impl Executor for AsyncStdExecutor {
+    fn exec(&self, future: Pin<Box<dyn Future<Output = ()> + Send>>) {
+        async_std::task::spawn(future);
+    }
+}
+
 pub struct ExecSwitch(Box<dyn Executor + Send>);
 
 impl ExecSwitch {
@@ -0,0 +1,108 @@
+use fnv::{FnvHashMap, FnvHashSet};
+use volans_core::{Multiaddr, PeerId};
+
+/// 外部地址簿，记录其他节点观察到的本地可达地址
+///
+/// 行为上报的候选地址按不同观察者的数量计分，达到阈值后确认；
+/// 手动添加的地址直接确认。候选过多时淘汰得分最低的地址。
+#[derive(Debug, Clone)]
+pub struct ExternalAddrStore {
+    confirmed: Vec<Multiaddr>,
+    candidates: FnvHashMap<Multiaddr, FnvHashSet<PeerId>>,
+    confirmation_threshold: usize,
+    max_candidates: usize,
+}
+
+impl Default for ExternalAddrStore {
+    fn default() -> Self {
+        Self {
+            confirmed: Vec::new(),
+            candidates: FnvHashMap::default(),
+            confirmation_threshold: 2,
+            max_candidates: 16,
+        }
+    }
+}
+
+impl ExternalAddrStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 候选地址确认所需的不同观察者数量，至少为 1
+    pub fn with_confirmation_threshold(mut self, count: usize) -> Self {
+        self.confirmation_threshold = count.max(1);
+        self
+    }
+
+    /// 同时保留的候选地址上限
+    pub fn with_max_candidates(mut self, count: usize) -> Self {
+        self.max_candidates = count;
+        self
+    }
+
+    /// 记录 `observer` 观察到的地址，地址因此被确认时返回 `true`
+    pub fn add_candidate(&mut self, observer: PeerId, addr: Multiaddr) -> bool {
+        if self.is_confirmed(&addr) {
+            return false;
+        }
+        let observers = self.candidates.entry(addr.clone()).or_default();
+        observers.insert(observer);
+        if observers.len() >= self.confirmation_threshold {
+            self.candidates.remove(&addr);
+            self.confirmed.push(addr);
+            return true;
+        }
+        if self.candidates.len() > self.max_candidates {
+            self.evict_candidate(&addr);
+        }
+        false
+    }
+
+    /// 直接确认地址，地址此前未被确认时返回 `true`
+    pub fn add_confirmed(&mut self, addr: Multiaddr) -> bool {
+        self.candidates.remove(&addr);
+        if self.is_confirmed(&addr) {
+            return false;
+        }
+        self.confirmed.push(addr);
+        true
+    }
+
+    /// 移除地址，地址此前已被确认时返回 `true`
+    pub fn remove(&mut self, addr: &Multiaddr) -> bool {
+        self.candidates.remove(addr);
+        let len = self.confirmed.len();
+        self.confirmed.retain(|confirmed| confirmed != addr);
+        self.confirmed.len() != len
+    }
+
+    pub fn is_confirmed(&self, addr: &Multiaddr) -> bool {
+        self.confirmed.contains(addr)
+    }
+
+    /// 已确认的地址，按确认顺序排列
+    pub fn confirmed(&self) -> impl Iterator<Item = &Multiaddr> {
+        self.confirmed.iter()
+    }
+
+    /// 候选地址当前的得分，即观察到该地址的节点数量
+    pub fn score(&self, addr: &Multiaddr) -> usize {
+        self.candidates
+            .get(addr)
+            .map_or(0, |observers| observers.len())
+    }
+
+    /// 淘汰得分最低的候选地址，刚刚更新的地址除外
+    fn evict_candidate(&mut self, keep: &Multiaddr) {
+        let lowest = self
+            .candidates
+            .iter()
+            .filter(|(addr, _)| *addr != keep)
+            .min_by_key(|(_, observers)| observers.len())
+            .map(|(addr, _)| addr.clone());
+        if let Some(addr) = lowest {
+            self.candidates.remove(&addr);
+        }
+    }
+}
@@ -0,0 +1,57 @@
+use futures::{
+    SinkExt,
+    channel::{mpsc, oneshot},
+};
+
+use crate::error::SwarmClosed;
+
+/// 命令通道的缓冲区大小，写满后句柄的调用等待 Swarm 取出命令
+const COMMAND_BUFFER_SIZE: usize = 32;
+
+/// 在 Swarm 所在任务中执行的行为操作
+pub(crate) type BehaviorCommand<TBehavior> = Box<dyn FnOnce(&mut TBehavior) + Send>;
+
+pub(crate) fn channel<TCommand>() -> (CommandSender<TCommand>, mpsc::Receiver<TCommand>) {
+    let (sender, receiver) = mpsc::channel(COMMAND_BUFFER_SIZE);
+    (CommandSender(sender), receiver)
+}
+
+/// 向 Swarm 发送命令并等待其在 `poll_next_event` 中处理后的结果
+pub(crate) struct CommandSender<TCommand>(mpsc::Sender<TCommand>);
+
+impl<TCommand> Clone for CommandSender<TCommand> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<TCommand> CommandSender<TCommand> {
+    pub(crate) async fn call<R>(
+        &self,
+        command: impl FnOnce(oneshot::Sender<R>) -> TCommand,
+    ) -> Result<R, SwarmClosed> {
+        let (sender, receiver) = oneshot::channel();
+        self.0
+            .clone()
+            .send(command(sender))
+            .await
+            .map_err(|_| SwarmClosed)?;
+        receiver.await.map_err(|_| SwarmClosed)
+    }
+
+    pub(crate) async fn behavior<TBehavior, R>(
+        &self,
+        f: impl FnOnce(&mut TBehavior) -> R + Send + 'static,
+        wrap: impl FnOnce(BehaviorCommand<TBehavior>) -> TCommand,
+    ) -> Result<R, SwarmClosed>
+    where
+        R: Send + 'static,
+    {
+        self.call(|sender| {
+            wrap(Box::new(move |behavior| {
+                let _ = sender.send(f(behavior));
+            }))
+        })
+        .await
+    }
+}
@@ -1,14 +1,23 @@
 mod dummy;
 mod either;
+mod lazy;
 mod map;
 mod multi;
+mod mux;
 mod pending;
 mod select;
+mod side;
 
 pub use dummy::DummyHandler;
+pub use lazy::LazyHandler;
 pub use map::{MapAction, MapEvent};
+pub use mux::{
+    ConnectionHandlerMux, MuxEnvelope, MuxInboundUpgrade, MuxOutboundUpgrade, MuxProtocol,
+};
 pub use pending::PendingConnectionHandler;
 pub use select::ConnectionHandlerSelect;
+pub use side::{InboundOnlyHandler, OutboundOnlyHandler};
+pub use volans_stream_select::NegotiationMode;
 
 use std::{
     fmt,
@@ -26,8 +35,26 @@ pub trait ConnectionHandler: Send + 'static {
 
     fn handle_action(&mut self, action: Self::Action);
 
-    fn connection_keep_alive(&self) -> bool {
-        false
+    /// 复制动作，用于 [`NotifyHandler::All`](crate::behavior::NotifyHandler::All) 向多个连接分发
+    ///
+    /// 返回 `None` 表示动作不可复制，此时只通知对端的任意一个连接。
+    fn clone_action(_action: &Self::Action) -> Option<Self::Action>
+    where
+        Self: Sized,
+    {
+        None
+    }
+
+    /// 连接上没有活跃子流时的保活策略
+    fn keep_alive(&self) -> KeepAlive {
+        KeepAlive::Idle
+    }
+
+    /// 在连接任务中完成耗时的初始化，例如加载状态、打开文件
+    ///
+    /// 返回 `Ready` 之前连接不处理子流，行为发送的动作在命令队列中等待。
+    fn poll_init(&mut self, _cx: &mut Context<'_>) -> Poll<()> {
+        Poll::Ready(())
     }
 
     fn poll_close(&mut self, _: &mut Context<'_>) -> Poll<Option<Self::Event>> {
@@ -104,10 +131,39 @@ pub trait OutboundStreamHandler: ConnectionHandler {
     ) -> Poll<SubstreamProtocol<Self::OutboundUpgrade, Self::OutboundUserData>>;
 }
 
+/// 处理器对连接保活的要求
+///
+/// 多个处理器组合时取保活时间最长的一个。
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum KeepAlive {
+    /// 空闲时立即关闭连接
+    No,
+    /// 空闲超过指定时长后关闭，不超过连接池的空闲超时
+    Timeout(Duration),
+    /// 按连接池的空闲超时关闭
+    Idle,
+    /// 保持连接
+    Yes,
+}
+
+impl From<bool> for KeepAlive {
+    fn from(keep_alive: bool) -> Self {
+        match keep_alive {
+            true => KeepAlive::Yes,
+            false => KeepAlive::Idle,
+        }
+    }
+}
+
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub struct SubstreamProtocol<TUpgr, TData> {
     upgrade: TUpgr,
-    timeout: Duration,
+    /// 未设置时使用连接池配置的默认协商超时
+    timeout: Option<Duration>,
+    /// 出站子流的协商方式，入站子流忽略
+    negotiation_mode: NegotiationMode,
+    /// 出站子流的优先级，入站子流忽略
+    priority: SubstreamPriority,
     user_data: TData,
 }
 
@@ -115,7 +171,9 @@ impl<TUpgr, TData> SubstreamProtocol<TUpgr, TData> {
     pub fn new(upgrade: TUpgr, data: TData) -> Self {
         Self {
             upgrade,
-            timeout: Duration::from_secs(5),
+            timeout: None,
+            negotiation_mode: NegotiationMode::Full,
+            priority: SubstreamPriority::Normal,
             user_data: data,
         }
     }
@@ -124,16 +182,38 @@ impl<TUpgr, TData> SubstreamProtocol<TUpgr, TData> {
         &self.upgrade
     }
 
-    pub fn timeout(&self) -> &Duration {
-        &self.timeout
+    /// 显式设置的协商超时
+    pub fn timeout(&self) -> Option<&Duration> {
+        self.timeout.as_ref()
     }
 
+    /// 设置子流请求与协商的超时，覆盖连接池的默认值
     pub fn with_timeout(mut self, timeout: Duration) -> Self {
-        self.timeout = timeout;
+        self.timeout = Some(timeout);
         self
     }
 
-    pub fn into_inner(self) -> (TUpgr, TData, Duration) {
+    pub fn negotiation_mode(&self) -> NegotiationMode {
+        self.negotiation_mode
+    }
+
+    /// 设置出站子流的协商方式，[`NegotiationMode::Lazy`] 在只有一个协议时省去一次往返
+    pub fn with_negotiation_mode(mut self, mode: NegotiationMode) -> Self {
+        self.negotiation_mode = mode;
+        self
+    }
+
+    pub fn priority(&self) -> SubstreamPriority {
+        self.priority
+    }
+
+    /// 设置出站子流的优先级，连接优先为高优先级的请求打开子流
+    pub fn with_priority(mut self, priority: SubstreamPriority) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    pub fn into_inner(self) -> (TUpgr, TData, Option<Duration>) {
         (self.upgrade, self.user_data, self.timeout)
     }
 
@@ -145,6 +225,8 @@ impl<TUpgr, TData> SubstreamProtocol<TUpgr, TData> {
             upgrade: f(self.upgrade),
             user_data: self.user_data,
             timeout: self.timeout,
+            negotiation_mode: self.negotiation_mode,
+            priority: self.priority,
         }
     }
 
@@ -156,15 +238,37 @@ impl<TUpgr, TData> SubstreamProtocol<TUpgr, TData> {
             upgrade: self.upgrade,
             user_data: f(self.user_data),
             timeout: self.timeout,
+            negotiation_mode: self.negotiation_mode,
+            priority: self.priority,
         }
     }
 }
 
+/// 出站子流的优先级
+///
+/// 连接把多路复用器新打开的子流优先分配给高优先级的请求，同一优先级按请求顺序分配；
+/// 协商完成的子流也按优先级交给处理器，避免保活等轻量协议在负载下被饿死。
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum SubstreamPriority {
+    Low,
+    #[default]
+    Normal,
+    High,
+}
+
+/// 子流升级失败的原因，区分配置不匹配（协商失败）与网络问题（超时、对端重置）
 #[derive(Debug)]
 pub enum StreamUpgradeError<TUpgrErr> {
     Timeout,
     Apply(TUpgrErr),
-    NegotiationFailed,
+    /// 对端不支持任何候选协议，`remote_protocols` 为对端列出的支持协议，未能获取时为空
+    NegotiationFailed {
+        remote_protocols: Vec<String>,
+        /// 本地提出的候选协议
+        local_protocols: Vec<String>,
+    },
+    /// 协商过程中对端重置或关闭了子流
+    RemoteReset,
     Io(std::io::Error),
 }
 
@@ -176,7 +280,14 @@ impl<TUpgrErr> StreamUpgradeError<TUpgrErr> {
         match self {
             StreamUpgradeError::Timeout => StreamUpgradeError::Timeout,
             StreamUpgradeError::Apply(e) => StreamUpgradeError::Apply(f(e)),
-            StreamUpgradeError::NegotiationFailed => StreamUpgradeError::NegotiationFailed,
+            StreamUpgradeError::NegotiationFailed {
+                remote_protocols,
+                local_protocols,
+            } => StreamUpgradeError::NegotiationFailed {
+                remote_protocols,
+                local_protocols,
+            },
+            StreamUpgradeError::RemoteReset => StreamUpgradeError::RemoteReset,
             StreamUpgradeError::Io(e) => StreamUpgradeError::Io(e),
         }
     }
@@ -189,7 +300,14 @@ impl<TErr1, TErr2> StreamUpgradeError<Either<TErr1, TErr2>> {
             StreamUpgradeError::Apply(e) => {
                 StreamUpgradeError::Apply(e.left().expect("StreamUpgradeError Left error expected"))
             }
-            StreamUpgradeError::NegotiationFailed => StreamUpgradeError::NegotiationFailed,
+            StreamUpgradeError::NegotiationFailed {
+                remote_protocols,
+                local_protocols,
+            } => StreamUpgradeError::NegotiationFailed {
+                remote_protocols,
+                local_protocols,
+            },
+            StreamUpgradeError::RemoteReset => StreamUpgradeError::RemoteReset,
             StreamUpgradeError::Io(e) => StreamUpgradeError::Io(e),
         }
     }
@@ -200,7 +318,14 @@ impl<TErr1, TErr2> StreamUpgradeError<Either<TErr1, TErr2>> {
             StreamUpgradeError::Apply(e) => StreamUpgradeError::Apply(
                 e.right().expect("StreamUpgradeError Right error expected"),
             ),
-            StreamUpgradeError::NegotiationFailed => StreamUpgradeError::NegotiationFailed,
+            StreamUpgradeError::NegotiationFailed {
+                remote_protocols,
+                local_protocols,
+            } => StreamUpgradeError::NegotiationFailed {
+                remote_protocols,
+                local_protocols,
+            },
+            StreamUpgradeError::RemoteReset => StreamUpgradeError::RemoteReset,
             StreamUpgradeError::Io(e) => StreamUpgradeError::Io(e),
         }
     }
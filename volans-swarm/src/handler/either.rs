@@ -4,7 +4,7 @@ use either::Either;
 use futures::future;
 
 use crate::{
-    ConnectionHandler, ConnectionHandlerEvent, InboundStreamHandler, InboundUpgradeSend,
+    ConnectionHandler, ConnectionHandlerEvent, InboundStreamHandler, InboundUpgradeSend, KeepAlive,
     OutboundStreamHandler, OutboundUpgradeSend, StreamUpgradeError, SubstreamProtocol,
     upgrade::SendWrapper,
 };
@@ -25,10 +25,24 @@ where
         }
     }
 
-    fn connection_keep_alive(&self) -> bool {
+    fn clone_action(action: &Self::Action) -> Option<Self::Action> {
+        match action {
+            Either::Left(action) => L::clone_action(action).map(Either::Left),
+            Either::Right(action) => R::clone_action(action).map(Either::Right),
+        }
+    }
+
+    fn keep_alive(&self) -> KeepAlive {
+        match self {
+            Either::Left(left) => left.keep_alive(),
+            Either::Right(right) => right.keep_alive(),
+        }
+    }
+
+    fn poll_init(&mut self, cx: &mut Context<'_>) -> Poll<()> {
         match self {
-            Either::Left(left) => left.connection_keep_alive(),
-            Either::Right(right) => right.connection_keep_alive(),
+            Either::Left(left) => left.poll_init(cx),
+            Either::Right(right) => right.poll_init(cx),
         }
     }
 
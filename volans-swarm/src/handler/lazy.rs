@@ -0,0 +1,189 @@
+use std::{
+    collections::VecDeque,
+    future::Future,
+    task::{Context, Poll},
+};
+
+use futures::{FutureExt, future::BoxFuture};
+
+use crate::{
+    ConnectionHandler, ConnectionHandlerEvent, InboundStreamHandler, InboundUpgradeSend, KeepAlive,
+    OutboundStreamHandler, OutboundUpgradeSend, StreamUpgradeError, SubstreamProtocol,
+};
+
+/// 在连接任务中异步构造的处理器
+///
+/// 行为在 `handle_established_connection` 中同步决定是否拒绝连接，耗时的构造交给返回的
+/// 处理器在连接任务中完成，构造完成前连接不处理子流，收到的动作在构造完成后依次交给处理器。
+pub struct LazyHandler<H: ConnectionHandler> {
+    state: State<H>,
+    pending_actions: VecDeque<H::Action>,
+}
+
+enum State<H> {
+    Constructing(BoxFuture<'static, H>),
+    Ready(H),
+}
+
+impl<H> LazyHandler<H>
+where
+    H: ConnectionHandler,
+{
+    pub fn new<F>(construct: F) -> Self
+    where
+        F: Future<Output = H> + Send + 'static,
+    {
+        Self {
+            state: State::Constructing(construct.boxed()),
+            pending_actions: VecDeque::new(),
+        }
+    }
+
+    /// 已构造好的处理器
+    pub fn ready(handler: H) -> Self {
+        Self {
+            state: State::Ready(handler),
+            pending_actions: VecDeque::new(),
+        }
+    }
+
+    pub fn inner(&self) -> Option<&H> {
+        match &self.state {
+            State::Ready(handler) => Some(handler),
+            State::Constructing(_) => None,
+        }
+    }
+
+    fn handler_mut(&mut self) -> Option<&mut H> {
+        match &mut self.state {
+            State::Ready(handler) => Some(handler),
+            State::Constructing(_) => None,
+        }
+    }
+
+    /// 推进构造，完成后交付积压的动作
+    fn poll_construct(&mut self, cx: &mut Context<'_>) -> Poll<&mut H> {
+        if let State::Constructing(construct) = &mut self.state {
+            let handler = futures::ready!(construct.poll_unpin(cx));
+            self.state = State::Ready(handler);
+        }
+        let State::Ready(handler) = &mut self.state else {
+            unreachable!("handler constructed above")
+        };
+        for action in self.pending_actions.drain(..) {
+            handler.handle_action(action);
+        }
+        Poll::Ready(handler)
+    }
+}
+
+impl<H> ConnectionHandler for LazyHandler<H>
+where
+    H: ConnectionHandler,
+{
+    type Action = H::Action;
+    type Event = H::Event;
+
+    fn handle_action(&mut self, action: Self::Action) {
+        match self.handler_mut() {
+            Some(handler) => handler.handle_action(action),
+            None => self.pending_actions.push_back(action),
+        }
+    }
+
+    fn clone_action(action: &Self::Action) -> Option<Self::Action> {
+        H::clone_action(action)
+    }
+
+    fn keep_alive(&self) -> KeepAlive {
+        match &self.state {
+            State::Ready(handler) => handler.keep_alive(),
+            State::Constructing(_) => KeepAlive::Yes,
+        }
+    }
+
+    fn poll_init(&mut self, cx: &mut Context<'_>) -> Poll<()> {
+        futures::ready!(self.poll_construct(cx)).poll_init(cx)
+    }
+
+    fn poll_close(&mut self, cx: &mut Context<'_>) -> Poll<Option<Self::Event>> {
+        match &mut self.state {
+            State::Ready(handler) => handler.poll_close(cx),
+            State::Constructing(_) => Poll::Ready(None),
+        }
+    }
+
+    fn poll(&mut self, cx: &mut Context<'_>) -> Poll<ConnectionHandlerEvent<Self::Event>> {
+        futures::ready!(self.poll_construct(cx)).poll(cx)
+    }
+}
+
+impl<H> InboundStreamHandler for LazyHandler<H>
+where
+    H: InboundStreamHandler,
+{
+    type InboundUpgrade = H::InboundUpgrade;
+    type InboundUserData = H::InboundUserData;
+
+    /// 连接在初始化完成后才接受入站子流，构造完成前不会被调用
+    fn listen_protocol(&self) -> SubstreamProtocol<Self::InboundUpgrade, Self::InboundUserData> {
+        self.inner()
+            .expect("listen_protocol called before LazyHandler construction completed")
+            .listen_protocol()
+    }
+
+    fn on_fully_negotiated(
+        &mut self,
+        user_data: Self::InboundUserData,
+        protocol: <Self::InboundUpgrade as InboundUpgradeSend>::Output,
+    ) {
+        if let Some(handler) = self.handler_mut() {
+            InboundStreamHandler::on_fully_negotiated(handler, user_data, protocol);
+        }
+    }
+
+    fn on_upgrade_error(
+        &mut self,
+        user_data: Self::InboundUserData,
+        error: <Self::InboundUpgrade as InboundUpgradeSend>::Error,
+    ) {
+        if let Some(handler) = self.handler_mut() {
+            InboundStreamHandler::on_upgrade_error(handler, user_data, error);
+        }
+    }
+}
+
+impl<H> OutboundStreamHandler for LazyHandler<H>
+where
+    H: OutboundStreamHandler,
+{
+    type OutboundUpgrade = H::OutboundUpgrade;
+    type OutboundUserData = H::OutboundUserData;
+
+    fn on_fully_negotiated(
+        &mut self,
+        user_data: Self::OutboundUserData,
+        protocol: <Self::OutboundUpgrade as OutboundUpgradeSend>::Output,
+    ) {
+        if let Some(handler) = self.handler_mut() {
+            OutboundStreamHandler::on_fully_negotiated(handler, user_data, protocol);
+        }
+    }
+
+    fn on_upgrade_error(
+        &mut self,
+        user_data: Self::OutboundUserData,
+        error: StreamUpgradeError<<Self::OutboundUpgrade as OutboundUpgradeSend>::Error>,
+    ) {
+        if let Some(handler) = self.handler_mut() {
+            OutboundStreamHandler::on_upgrade_error(handler, user_data, error);
+        }
+    }
+
+    fn poll_outbound_request(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<SubstreamProtocol<Self::OutboundUpgrade, Self::OutboundUserData>> {
+        futures::ready!(self.poll_construct(cx)).poll_outbound_request(cx)
+    }
+}
@@ -5,7 +5,7 @@ use std::{
 };
 
 use crate::{
-    ConnectionHandler, ConnectionHandlerEvent, InboundStreamHandler, InboundUpgradeSend,
+    ConnectionHandler, ConnectionHandlerEvent, InboundStreamHandler, InboundUpgradeSend, KeepAlive,
     OutboundStreamHandler, OutboundUpgradeSend, StreamUpgradeError, SubstreamProtocol,
 };
 
@@ -35,8 +35,16 @@ where
         self.inner.handle_action(action);
     }
 
-    fn connection_keep_alive(&self) -> bool {
-        self.inner.connection_keep_alive()
+    fn clone_action(action: &Self::Action) -> Option<Self::Action> {
+        THandler::clone_action(action)
+    }
+
+    fn keep_alive(&self) -> KeepAlive {
+        self.inner.keep_alive()
+    }
+
+    fn poll_init(&mut self, cx: &mut Context<'_>) -> Poll<()> {
+        self.inner.poll_init(cx)
     }
 
     fn poll_close(&mut self, cx: &mut Context<'_>) -> Poll<Option<Self::Event>> {
@@ -146,8 +154,16 @@ where
         }
     }
 
-    fn connection_keep_alive(&self) -> bool {
-        self.inner.connection_keep_alive()
+    fn clone_action(action: &Self::Action) -> Option<Self::Action> {
+        Some(action.clone())
+    }
+
+    fn keep_alive(&self) -> KeepAlive {
+        self.inner.keep_alive()
+    }
+
+    fn poll_init(&mut self, cx: &mut Context<'_>) -> Poll<()> {
+        self.inner.poll_init(cx)
     }
 
     fn poll_close(&mut self, cx: &mut Context<'_>) -> Poll<Option<Self::Event>> {
@@ -0,0 +1,459 @@
+use std::{
+    any::Any,
+    cmp, fmt,
+    task::{Context, Poll},
+};
+
+use futures::{FutureExt, future::BoxFuture, ready};
+use volans_core::upgrade::{InboundUpgrade, OutboundUpgrade, UpgradeInfo};
+
+use crate::{
+    ConnectionHandler, ConnectionHandlerEvent, InboundStreamHandler, InboundUpgradeSend, KeepAlive,
+    NegotiationMode, OutboundStreamHandler, OutboundUpgradeSend, StreamUpgradeError, Substream,
+    SubstreamPriority, SubstreamProtocol, UpgradeInfoSend,
+};
+
+/// 带子处理器编号的类型擦除值
+///
+/// [`ConnectionHandlerMux`] 的动作、事件与升级结果均以此传递，按编号还原为子处理器的类型。
+pub struct MuxEnvelope {
+    index: usize,
+    inner: Box<dyn Any + Send>,
+    debug: fn(&(dyn Any + Send), &mut fmt::Formatter<'_>) -> fmt::Result,
+}
+
+impl MuxEnvelope {
+    pub fn new<T: fmt::Debug + Send + 'static>(index: usize, inner: T) -> Self {
+        Self {
+            index,
+            inner: Box::new(inner),
+            debug: |inner, f| {
+                let inner = inner.downcast_ref::<T>().expect("type of envelope content");
+                fmt::Debug::fmt(inner, f)
+            },
+        }
+    }
+
+    /// 内容不实现 `Debug` 时使用
+    pub fn opaque<T: Send + 'static>(index: usize, inner: T) -> Self {
+        Self {
+            index,
+            inner: Box::new(inner),
+            debug: |_, f| f.write_str("<opaque>"),
+        }
+    }
+
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    pub fn downcast<T: 'static>(self) -> Result<T, Self> {
+        let Self {
+            index,
+            inner,
+            debug,
+        } = self;
+        inner
+            .downcast::<T>()
+            .map(|inner| *inner)
+            .map_err(|inner| Self {
+                index,
+                inner,
+                debug,
+            })
+    }
+
+    fn inner_ref<T: 'static>(&self) -> &T {
+        self.inner
+            .downcast_ref()
+            .expect("envelope content matches the handler at its index")
+    }
+
+    fn into_inner<T: 'static>(self) -> T {
+        self.downcast()
+            .expect("envelope content matches the handler at its index")
+    }
+}
+
+impl fmt::Debug for MuxEnvelope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "#{}: ", self.index)?;
+        (self.debug)(&*self.inner, f)
+    }
+}
+
+/// 多个处理器的扁平组合，子处理器以元组给出，最多 16 个
+///
+/// 与嵌套的 [`ConnectionHandlerSelect`](super::ConnectionHandlerSelect) 相比，
+/// 类型不随处理器数量加深，动作与事件以 [`MuxEnvelope`] 按编号分发。
+#[derive(Debug, Clone)]
+pub struct ConnectionHandlerMux<THandlers> {
+    handlers: THandlers,
+}
+
+impl<THandlers> ConnectionHandlerMux<THandlers> {
+    pub fn new(handlers: THandlers) -> Self {
+        Self { handlers }
+    }
+
+    pub fn into_inner(self) -> THandlers {
+        self.handlers
+    }
+}
+
+trait ErasedInfo: Send + 'static {
+    fn as_str(&self) -> &str;
+
+    fn clone_box(&self) -> Box<dyn ErasedInfo>;
+
+    fn into_any(self: Box<Self>) -> Box<dyn Any + Send>;
+}
+
+impl<T> ErasedInfo for T
+where
+    T: AsRef<str> + Clone + Send + 'static,
+{
+    fn as_str(&self) -> &str {
+        self.as_ref()
+    }
+
+    fn clone_box(&self) -> Box<dyn ErasedInfo> {
+        Box::new(self.clone())
+    }
+
+    fn into_any(self: Box<Self>) -> Box<dyn Any + Send> {
+        self
+    }
+}
+
+/// 子处理器的协议信息
+pub struct MuxProtocol {
+    index: usize,
+    info: Box<dyn ErasedInfo>,
+}
+
+impl MuxProtocol {
+    fn into_info<T: 'static>(self) -> T {
+        *self
+            .info
+            .into_any()
+            .downcast()
+            .expect("protocol info matches the upgrade at its index")
+    }
+}
+
+impl Clone for MuxProtocol {
+    fn clone(&self) -> Self {
+        Self {
+            index: self.index,
+            info: self.info.clone_box(),
+        }
+    }
+}
+
+impl AsRef<str> for MuxProtocol {
+    fn as_ref(&self) -> &str {
+        self.info.as_str()
+    }
+}
+
+impl fmt::Debug for MuxProtocol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "#{}: {}", self.index, self.info.as_str())
+    }
+}
+
+type ErasedFuture = BoxFuture<'static, Result<MuxEnvelope, MuxEnvelope>>;
+
+trait ErasedInboundUpgrade: Send + 'static {
+    fn protocol_info(&self, index: usize) -> Vec<MuxProtocol>;
+
+    fn upgrade_inbound(self: Box<Self>, socket: Substream, info: MuxProtocol) -> ErasedFuture;
+}
+
+impl<U: InboundUpgradeSend> ErasedInboundUpgrade for U {
+    fn protocol_info(&self, index: usize) -> Vec<MuxProtocol> {
+        UpgradeInfoSend::protocol_info(self)
+            .map(|info| MuxProtocol {
+                index,
+                info: Box::new(info),
+            })
+            .collect()
+    }
+
+    fn upgrade_inbound(self: Box<Self>, socket: Substream, info: MuxProtocol) -> ErasedFuture {
+        let index = info.index;
+        InboundUpgradeSend::upgrade_inbound(*self, socket, info.into_info())
+            .map(move |result| {
+                result
+                    .map(|output| MuxEnvelope::opaque(index, output))
+                    .map_err(|error| MuxEnvelope::opaque(index, error))
+            })
+            .boxed()
+    }
+}
+
+trait ErasedOutboundUpgrade: Send + 'static {
+    fn protocol_info(&self, index: usize) -> Vec<MuxProtocol>;
+
+    fn upgrade_outbound(self: Box<Self>, socket: Substream, info: MuxProtocol) -> ErasedFuture;
+}
+
+impl<U: OutboundUpgradeSend> ErasedOutboundUpgrade for U {
+    fn protocol_info(&self, index: usize) -> Vec<MuxProtocol> {
+        UpgradeInfoSend::protocol_info(self)
+            .map(|info| MuxProtocol {
+                index,
+                info: Box::new(info),
+            })
+            .collect()
+    }
+
+    fn upgrade_outbound(self: Box<Self>, socket: Substream, info: MuxProtocol) -> ErasedFuture {
+        let index = info.index;
+        OutboundUpgradeSend::upgrade_outbound(*self, socket, info.into_info())
+            .map(move |result| {
+                result
+                    .map(|output| MuxEnvelope::opaque(index, output))
+                    .map_err(|error| MuxEnvelope::opaque(index, error))
+            })
+            .boxed()
+    }
+}
+
+/// 所有子处理器入站升级的组合，按协商出的协议交给对应的子处理器
+pub struct MuxInboundUpgrade {
+    upgrades: Vec<Box<dyn ErasedInboundUpgrade>>,
+}
+
+impl UpgradeInfo for MuxInboundUpgrade {
+    type Info = MuxProtocol;
+    type InfoIter = std::vec::IntoIter<MuxProtocol>;
+
+    fn protocol_info(&self) -> Self::InfoIter {
+        self.upgrades
+            .iter()
+            .enumerate()
+            .flat_map(|(index, upgrade)| upgrade.protocol_info(index))
+            .collect::<Vec<_>>()
+            .into_iter()
+    }
+}
+
+impl InboundUpgrade<Substream> for MuxInboundUpgrade {
+    type Output = MuxEnvelope;
+    type Error = MuxEnvelope;
+    type Future = ErasedFuture;
+
+    fn upgrade_inbound(self, socket: Substream, info: MuxProtocol) -> Self::Future {
+        let upgrade = self
+            .upgrades
+            .into_iter()
+            .nth(info.index)
+            .expect("protocol index within upgrades");
+        upgrade.upgrade_inbound(socket, info)
+    }
+}
+
+/// 某个子处理器请求的出站升级
+pub struct MuxOutboundUpgrade {
+    index: usize,
+    upgrade: Box<dyn ErasedOutboundUpgrade>,
+}
+
+impl UpgradeInfo for MuxOutboundUpgrade {
+    type Info = MuxProtocol;
+    type InfoIter = std::vec::IntoIter<MuxProtocol>;
+
+    fn protocol_info(&self) -> Self::InfoIter {
+        self.upgrade.protocol_info(self.index).into_iter()
+    }
+}
+
+impl OutboundUpgrade<Substream> for MuxOutboundUpgrade {
+    type Output = MuxEnvelope;
+    type Error = MuxEnvelope;
+    type Future = ErasedFuture;
+
+    fn upgrade_outbound(self, socket: Substream, info: MuxProtocol) -> Self::Future {
+        self.upgrade.upgrade_outbound(socket, info)
+    }
+}
+
+macro_rules! impl_mux {
+    ($($handler:ident $index:tt),+) => {
+        impl<$($handler),+> ConnectionHandler for ConnectionHandlerMux<($($handler,)+)>
+        where
+            $($handler: ConnectionHandler),+
+        {
+            type Action = MuxEnvelope;
+            type Event = MuxEnvelope;
+
+            fn handle_action(&mut self, action: Self::Action) {
+                match action.index() {
+                    $($index => self.handlers.$index.handle_action(action.into_inner()),)+
+                    index => unreachable!("Handler index {index} out of range"),
+                }
+            }
+
+            fn clone_action(action: &Self::Action) -> Option<Self::Action> {
+                match action.index() {
+                    $($index => $handler::clone_action(action.inner_ref())
+                        .map(|action| MuxEnvelope::new($index, action)),)+
+                    index => unreachable!("Handler index {index} out of range"),
+                }
+            }
+
+            fn keep_alive(&self) -> KeepAlive {
+                let keep_alive = KeepAlive::No;
+                $(let keep_alive = cmp::max(keep_alive, self.handlers.$index.keep_alive());)+
+                keep_alive
+            }
+
+            fn poll_init(&mut self, cx: &mut Context<'_>) -> Poll<()> {
+                let mut ready = true;
+                $(ready &= self.handlers.$index.poll_init(cx).is_ready();)+
+                if ready { Poll::Ready(()) } else { Poll::Pending }
+            }
+
+            fn poll_close(&mut self, cx: &mut Context<'_>) -> Poll<Option<Self::Event>> {
+                $(
+                    if let Some(event) = ready!(self.handlers.$index.poll_close(cx)) {
+                        return Poll::Ready(Some(MuxEnvelope::new($index, event)));
+                    }
+                )+
+                Poll::Ready(None)
+            }
+
+            fn poll(&mut self, cx: &mut Context<'_>) -> Poll<ConnectionHandlerEvent<Self::Event>> {
+                $(
+                    if let Poll::Ready(event) = self.handlers.$index.poll(cx) {
+                        return Poll::Ready(event.map_event(|event| MuxEnvelope::new($index, event)));
+                    }
+                )+
+                Poll::Pending
+            }
+        }
+
+        impl<$($handler),+> InboundStreamHandler for ConnectionHandlerMux<($($handler,)+)>
+        where
+            $($handler: InboundStreamHandler),+
+        {
+            type InboundUpgrade = MuxInboundUpgrade;
+            type InboundUserData = Vec<MuxEnvelope>;
+
+            fn listen_protocol(&self) -> SubstreamProtocol<Self::InboundUpgrade, Self::InboundUserData> {
+                let mut upgrades = Vec::<Box<dyn ErasedInboundUpgrade>>::new();
+                let mut user_data = Vec::new();
+                let mut timeout = None;
+                $(
+                    let (upgrade, data, t) = self.handlers.$index.listen_protocol().into_inner();
+                    upgrades.push(Box::new(upgrade));
+                    user_data.push(MuxEnvelope::opaque($index, data));
+                    timeout = cmp::max(timeout, t);
+                )+
+                SubstreamProtocol {
+                    upgrade: MuxInboundUpgrade { upgrades },
+                    timeout,
+                    negotiation_mode: NegotiationMode::Full,
+                    priority: SubstreamPriority::Normal,
+                    user_data,
+                }
+            }
+
+            fn on_fully_negotiated(
+                &mut self,
+                user_data: Self::InboundUserData,
+                protocol: <Self::InboundUpgrade as InboundUpgradeSend>::Output,
+            ) {
+                let index = protocol.index();
+                let data = user_data.into_iter().nth(index).expect("user data for every handler");
+                match index {
+                    $($index => self.handlers.$index.on_fully_negotiated(data.into_inner(), protocol.into_inner()),)+
+                    index => unreachable!("Handler index {index} out of range"),
+                }
+            }
+
+            fn on_upgrade_error(
+                &mut self,
+                user_data: Self::InboundUserData,
+                error: <Self::InboundUpgrade as InboundUpgradeSend>::Error,
+            ) {
+                let index = error.index();
+                let data = user_data.into_iter().nth(index).expect("user data for every handler");
+                match index {
+                    $($index => self.handlers.$index.on_upgrade_error(data.into_inner(), error.into_inner()),)+
+                    index => unreachable!("Handler index {index} out of range"),
+                }
+            }
+        }
+
+        impl<$($handler),+> OutboundStreamHandler for ConnectionHandlerMux<($($handler,)+)>
+        where
+            $($handler: OutboundStreamHandler),+
+        {
+            type OutboundUpgrade = MuxOutboundUpgrade;
+            type OutboundUserData = MuxEnvelope;
+
+            fn on_fully_negotiated(
+                &mut self,
+                user_data: Self::OutboundUserData,
+                protocol: <Self::OutboundUpgrade as OutboundUpgradeSend>::Output,
+            ) {
+                match user_data.index() {
+                    $($index => self.handlers.$index.on_fully_negotiated(user_data.into_inner(), protocol.into_inner()),)+
+                    index => unreachable!("Handler index {index} out of range"),
+                }
+            }
+
+            fn on_upgrade_error(
+                &mut self,
+                user_data: Self::OutboundUserData,
+                error: StreamUpgradeError<<Self::OutboundUpgrade as OutboundUpgradeSend>::Error>,
+            ) {
+                match user_data.index() {
+                    $($index => self.handlers.$index.on_upgrade_error(
+                        user_data.into_inner(),
+                        error.map_upgrade_err(MuxEnvelope::into_inner),
+                    ),)+
+                    index => unreachable!("Handler index {index} out of range"),
+                }
+            }
+
+            fn poll_outbound_request(
+                &mut self,
+                cx: &mut Context<'_>,
+            ) -> Poll<SubstreamProtocol<Self::OutboundUpgrade, Self::OutboundUserData>> {
+                $(
+                    if let Poll::Ready(protocol) = self.handlers.$index.poll_outbound_request(cx) {
+                        return Poll::Ready(
+                            protocol
+                                .map_upgrade(|upgrade| MuxOutboundUpgrade {
+                                    index: $index,
+                                    upgrade: Box::new(upgrade),
+                                })
+                                .map_user_data(|data| MuxEnvelope::opaque($index, data)),
+                        );
+                    }
+                )+
+                Poll::Pending
+            }
+        }
+    };
+}
+
+impl_mux!(H0 0, H1 1);
+impl_mux!(H0 0, H1 1, H2 2);
+impl_mux!(H0 0, H1 1, H2 2, H3 3);
+impl_mux!(H0 0, H1 1, H2 2, H3 3, H4 4);
+impl_mux!(H0 0, H1 1, H2 2, H3 3, H4 4, H5 5);
+impl_mux!(H0 0, H1 1, H2 2, H3 3, H4 4, H5 5, H6 6);
+impl_mux!(H0 0, H1 1, H2 2, H3 3, H4 4, H5 5, H6 6, H7 7);
+impl_mux!(H0 0, H1 1, H2 2, H3 3, H4 4, H5 5, H6 6, H7 7, H8 8);
+impl_mux!(H0 0, H1 1, H2 2, H3 3, H4 4, H5 5, H6 6, H7 7, H8 8, H9 9);
+impl_mux!(H0 0, H1 1, H2 2, H3 3, H4 4, H5 5, H6 6, H7 7, H8 8, H9 9, H10 10);
+impl_mux!(H0 0, H1 1, H2 2, H3 3, H4 4, H5 5, H6 6, H7 7, H8 8, H9 9, H10 10, H11 11);
+impl_mux!(H0 0, H1 1, H2 2, H3 3, H4 4, H5 5, H6 6, H7 7, H8 8, H9 9, H10 10, H11 11, H12 12);
+impl_mux!(H0 0, H1 1, H2 2, H3 3, H4 4, H5 5, H6 6, H7 7, H8 8, H9 9, H10 10, H11 11, H12 12, H13 13);
+impl_mux!(H0 0, H1 1, H2 2, H3 3, H4 4, H5 5, H6 6, H7 7, H8 8, H9 9, H10 10, H11 11, H12 12, H13 13, H14 14);
+impl_mux!(H0 0, H1 1, H2 2, H3 3, H4 4, H5 5, H6 6, H7 7, H8 8, H9 9, H10 10, H11 11, H12 12, H13 13, H14 14, H15 15);
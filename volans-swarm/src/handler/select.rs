@@ -8,9 +8,9 @@ use futures::{future, ready};
 use volans_core::upgrade::SelectUpgrade;
 
 use crate::{
-    ConnectionHandler, ConnectionHandlerEvent, InboundStreamHandler, InboundUpgradeSend,
-    OutboundStreamHandler, OutboundUpgradeSend, StreamUpgradeError, SubstreamProtocol,
-    upgrade::SendWrapper,
+    ConnectionHandler, ConnectionHandlerEvent, InboundStreamHandler, InboundUpgradeSend, KeepAlive,
+    NegotiationMode, OutboundStreamHandler, OutboundUpgradeSend, StreamUpgradeError,
+    SubstreamPriority, SubstreamProtocol, upgrade::SendWrapper,
 };
 
 #[derive(Debug, Clone)]
@@ -40,8 +40,24 @@ where
         }
     }
 
-    fn connection_keep_alive(&self) -> bool {
-        self.first.connection_keep_alive() || self.second.connection_keep_alive()
+    fn clone_action(action: &Self::Action) -> Option<Self::Action> {
+        match action {
+            Either::Left(action) => THandler1::clone_action(action).map(Either::Left),
+            Either::Right(action) => THandler2::clone_action(action).map(Either::Right),
+        }
+    }
+
+    fn keep_alive(&self) -> KeepAlive {
+        self.first.keep_alive().max(self.second.keep_alive())
+    }
+
+    fn poll_init(&mut self, cx: &mut Context<'_>) -> Poll<()> {
+        let first = self.first.poll_init(cx);
+        let second = self.second.poll_init(cx);
+        match (first, second) {
+            (Poll::Ready(()), Poll::Ready(())) => Poll::Ready(()),
+            _ => Poll::Pending,
+        }
     }
 
     fn poll_close(&mut self, cx: &mut Context<'_>) -> Poll<Option<Self::Event>> {
@@ -87,7 +103,13 @@ where
         let (upgrade2, info2, timeout2) = second.into_inner();
         let timeout = cmp::max(timeout1, timeout2);
         let choice = SelectUpgrade::new(SendWrapper(upgrade1), SendWrapper(upgrade2));
-        SubstreamProtocol::new(choice, (info1, info2)).with_timeout(timeout)
+        SubstreamProtocol {
+            upgrade: choice,
+            timeout,
+            negotiation_mode: NegotiationMode::Full,
+            priority: SubstreamPriority::Normal,
+            user_data: (info1, info2),
+        }
     }
 
     fn on_fully_negotiated(
@@ -0,0 +1,289 @@
+use std::{
+    convert::Infallible,
+    task::{Context, Poll},
+};
+
+use either::Either;
+use futures::future;
+use volans_core::upgrade::{DeniedUpgrade, PendingUpgrade};
+
+use crate::{
+    ConnectionHandler, ConnectionHandlerEvent, InboundStreamHandler, InboundUpgradeSend, KeepAlive,
+    OutboundStreamHandler, OutboundUpgradeSend, StreamUpgradeError, SubstreamProtocol,
+    upgrade::SendWrapper,
+};
+
+/// 只处理入站子流的处理器，用于在双向连接中承载仅入站的行为
+///
+/// 拨号端建立的连接上为空，拒绝所有入站协议。
+#[derive(Debug)]
+pub struct InboundOnlyHandler<H> {
+    inner: Option<H>,
+}
+
+impl<H> InboundOnlyHandler<H> {
+    pub fn new(inner: H) -> Self {
+        Self { inner: Some(inner) }
+    }
+
+    pub fn disabled() -> Self {
+        Self { inner: None }
+    }
+
+    pub fn inner(&self) -> Option<&H> {
+        self.inner.as_ref()
+    }
+}
+
+impl<H> ConnectionHandler for InboundOnlyHandler<H>
+where
+    H: ConnectionHandler,
+{
+    type Action = H::Action;
+    type Event = H::Event;
+
+    fn handle_action(&mut self, action: Self::Action) {
+        if let Some(inner) = &mut self.inner {
+            inner.handle_action(action);
+        }
+    }
+
+    fn clone_action(action: &Self::Action) -> Option<Self::Action> {
+        H::clone_action(action)
+    }
+
+    fn keep_alive(&self) -> KeepAlive {
+        self.inner
+            .as_ref()
+            .map_or(KeepAlive::Idle, |inner| inner.keep_alive())
+    }
+
+    fn poll_init(&mut self, cx: &mut Context<'_>) -> Poll<()> {
+        match &mut self.inner {
+            Some(inner) => inner.poll_init(cx),
+            None => Poll::Ready(()),
+        }
+    }
+
+    fn poll_close(&mut self, cx: &mut Context<'_>) -> Poll<Option<Self::Event>> {
+        match &mut self.inner {
+            Some(inner) => inner.poll_close(cx),
+            None => Poll::Ready(None),
+        }
+    }
+
+    fn poll(&mut self, cx: &mut Context<'_>) -> Poll<ConnectionHandlerEvent<Self::Event>> {
+        match &mut self.inner {
+            Some(inner) => inner.poll(cx),
+            None => Poll::Pending,
+        }
+    }
+}
+
+impl<H> InboundStreamHandler for InboundOnlyHandler<H>
+where
+    H: InboundStreamHandler,
+{
+    type InboundUpgrade = Either<SendWrapper<H::InboundUpgrade>, SendWrapper<DeniedUpgrade>>;
+    type InboundUserData = Option<H::InboundUserData>;
+
+    fn listen_protocol(&self) -> SubstreamProtocol<Self::InboundUpgrade, Self::InboundUserData> {
+        match &self.inner {
+            Some(inner) => inner
+                .listen_protocol()
+                .map_upgrade(|u| Either::Left(SendWrapper(u)))
+                .map_user_data(Some),
+            None => SubstreamProtocol::new(Either::Right(SendWrapper(DeniedUpgrade)), None),
+        }
+    }
+
+    fn on_fully_negotiated(
+        &mut self,
+        user_data: Self::InboundUserData,
+        protocol: <Self::InboundUpgrade as InboundUpgradeSend>::Output,
+    ) {
+        match (&mut self.inner, user_data, protocol) {
+            (Some(inner), Some(data), future::Either::Left(protocol)) => {
+                inner.on_fully_negotiated(data, protocol)
+            }
+            (_, _, _) => unreachable!("Invalid fully negotiated protocol for inbound only handler"),
+        }
+    }
+
+    fn on_upgrade_error(
+        &mut self,
+        user_data: Self::InboundUserData,
+        error: <Self::InboundUpgrade as InboundUpgradeSend>::Error,
+    ) {
+        match (&mut self.inner, user_data, error) {
+            (Some(inner), Some(data), Either::Left(error)) => inner.on_upgrade_error(data, error),
+            (_, _, _) => unreachable!("Invalid upgrade error for inbound only handler"),
+        }
+    }
+}
+
+impl<H> OutboundStreamHandler for InboundOnlyHandler<H>
+where
+    H: ConnectionHandler,
+{
+    type OutboundUpgrade = PendingUpgrade<String>;
+    type OutboundUserData = Infallible;
+
+    fn on_fully_negotiated(
+        &mut self,
+        _user_data: Self::OutboundUserData,
+        _protocol: <Self::OutboundUpgrade as OutboundUpgradeSend>::Output,
+    ) {
+        unreachable!("InboundOnlyHandler does not open outbound streams");
+    }
+
+    fn on_upgrade_error(
+        &mut self,
+        _user_data: Self::OutboundUserData,
+        _error: StreamUpgradeError<<Self::OutboundUpgrade as OutboundUpgradeSend>::Error>,
+    ) {
+        unreachable!("InboundOnlyHandler does not open outbound streams");
+    }
+
+    fn poll_outbound_request(
+        &mut self,
+        _cx: &mut Context<'_>,
+    ) -> Poll<SubstreamProtocol<Self::OutboundUpgrade, Self::OutboundUserData>> {
+        Poll::Pending
+    }
+}
+
+/// 只发起出站子流的处理器，用于在双向连接中承载仅出站的行为
+///
+/// 监听端建立的连接上为空，不发起任何出站请求。
+#[derive(Debug)]
+pub struct OutboundOnlyHandler<H> {
+    inner: Option<H>,
+}
+
+impl<H> OutboundOnlyHandler<H> {
+    pub fn new(inner: H) -> Self {
+        Self { inner: Some(inner) }
+    }
+
+    pub fn disabled() -> Self {
+        Self { inner: None }
+    }
+
+    pub fn inner(&self) -> Option<&H> {
+        self.inner.as_ref()
+    }
+}
+
+impl<H> ConnectionHandler for OutboundOnlyHandler<H>
+where
+    H: ConnectionHandler,
+{
+    type Action = H::Action;
+    type Event = H::Event;
+
+    fn handle_action(&mut self, action: Self::Action) {
+        if let Some(inner) = &mut self.inner {
+            inner.handle_action(action);
+        }
+    }
+
+    fn clone_action(action: &Self::Action) -> Option<Self::Action> {
+        H::clone_action(action)
+    }
+
+    fn keep_alive(&self) -> KeepAlive {
+        self.inner
+            .as_ref()
+            .map_or(KeepAlive::Idle, |inner| inner.keep_alive())
+    }
+
+    fn poll_init(&mut self, cx: &mut Context<'_>) -> Poll<()> {
+        match &mut self.inner {
+            Some(inner) => inner.poll_init(cx),
+            None => Poll::Ready(()),
+        }
+    }
+
+    fn poll_close(&mut self, cx: &mut Context<'_>) -> Poll<Option<Self::Event>> {
+        match &mut self.inner {
+            Some(inner) => inner.poll_close(cx),
+            None => Poll::Ready(None),
+        }
+    }
+
+    fn poll(&mut self, cx: &mut Context<'_>) -> Poll<ConnectionHandlerEvent<Self::Event>> {
+        match &mut self.inner {
+            Some(inner) => inner.poll(cx),
+            None => Poll::Pending,
+        }
+    }
+}
+
+impl<H> InboundStreamHandler for OutboundOnlyHandler<H>
+where
+    H: ConnectionHandler,
+{
+    type InboundUpgrade = DeniedUpgrade;
+    type InboundUserData = ();
+
+    fn listen_protocol(&self) -> SubstreamProtocol<Self::InboundUpgrade, Self::InboundUserData> {
+        SubstreamProtocol::new(DeniedUpgrade, ())
+    }
+
+    fn on_fully_negotiated(
+        &mut self,
+        _user_data: Self::InboundUserData,
+        _protocol: <Self::InboundUpgrade as InboundUpgradeSend>::Output,
+    ) {
+        unreachable!("OutboundOnlyHandler does not accept inbound streams");
+    }
+
+    fn on_upgrade_error(
+        &mut self,
+        _user_data: Self::InboundUserData,
+        _error: <Self::InboundUpgrade as InboundUpgradeSend>::Error,
+    ) {
+        unreachable!("OutboundOnlyHandler does not accept inbound streams");
+    }
+}
+
+impl<H> OutboundStreamHandler for OutboundOnlyHandler<H>
+where
+    H: OutboundStreamHandler,
+{
+    type OutboundUpgrade = H::OutboundUpgrade;
+    type OutboundUserData = H::OutboundUserData;
+
+    fn on_fully_negotiated(
+        &mut self,
+        user_data: Self::OutboundUserData,
+        protocol: <Self::OutboundUpgrade as OutboundUpgradeSend>::Output,
+    ) {
+        match &mut self.inner {
+            Some(inner) => inner.on_fully_negotiated(user_data, protocol),
+            None => unreachable!("Disabled OutboundOnlyHandler never opens outbound streams"),
+        }
+    }
+
+    fn on_upgrade_error(
+        &mut self,
+        user_data: Self::OutboundUserData,
+        error: StreamUpgradeError<<Self::OutboundUpgrade as OutboundUpgradeSend>::Error>,
+    ) {
+        match &mut self.inner {
+            Some(inner) => inner.on_upgrade_error(user_data, error),
+            None => unreachable!("Disabled OutboundOnlyHandler never opens outbound streams"),
+        }
+    }
+
+    fn poll_outbound_request(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<SubstreamProtocol<Self::OutboundUpgrade, Self::OutboundUserData>> {
+        match &mut self.inner {
+            Some(inner) => inner.poll_outbound_request(cx),
+            None => Poll::Pending,
+        }
+    }
+}
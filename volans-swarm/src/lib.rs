@@ -1,33 +1,52 @@
+mod bandwidth;
 mod dial_opts;
-mod executor;
+mod event;
+mod external_addr_store;
+mod handle;
+mod observer;
+mod peer_store;
+mod replay;
 mod substream;
+mod swarm;
 
 pub mod behavior;
 pub mod client;
 pub mod connection;
 pub mod derive_prelude;
+pub mod duplex;
 pub mod error;
+pub mod executor;
 pub mod handler;
 pub mod listener;
 pub mod server;
+pub mod sim;
+pub mod timer;
 pub mod upgrade;
 
+pub use bandwidth::{Bandwidth, BandwidthStats};
 pub use behavior::{
-    BehaviorEvent, ListenAddresses, ListenerEvent, NetworkBehavior, NetworkIncomingBehavior,
-    NetworkOutgoingBehavior,
+    BehaviorEvent, ExternalAddresses, ListenAddresses, ListenerEvent, NetworkBehavior,
+    NetworkIncomingBehavior, NetworkOutgoingBehavior, Toggle,
 };
-pub use connection::ConnectionId;
-pub use dial_opts::{DialOpts, PeerCondition};
+pub use connection::{ConnectionId, ConnectionInfo};
+pub use dial_opts::{DialOpts, DialStrategy, PeerCondition, RetryPolicy};
 pub use error::ConnectionDenied;
+pub use event::SwarmEvent;
 pub use executor::{ExecSwitch, Executor};
+pub use external_addr_store::ExternalAddrStore;
 pub use handler::{
-    ConnectionHandler, ConnectionHandlerEvent, InboundStreamHandler, OutboundStreamHandler,
-    StreamUpgradeError, SubstreamProtocol,
+    ConnectionHandler, ConnectionHandlerEvent, InboundStreamHandler, KeepAlive, NegotiationMode,
+    OutboundStreamHandler, StreamUpgradeError, SubstreamPriority, SubstreamProtocol,
 };
-pub use listener::{ListenOpts, ListenerId};
+pub use listener::{ListenOpts, ListenerId, PendingConnectionFilter};
+pub use observer::SwarmObserver;
+pub use peer_store::PeerStore;
+pub use replay::{ReplayEvent, Subscription};
 pub use substream::{InvalidProtocol, StreamProtocol, Substream};
 pub use upgrade::{InboundUpgradeSend, OutboundUpgradeSend, UpgradeInfoSend};
-pub use volans_swarm_derive::{NetworkIncomingBehavior, NetworkOutgoingBehavior};
+pub use volans_swarm_derive::{
+    NetworkDuplexBehavior, NetworkIncomingBehavior, NetworkOutgoingBehavior,
+};
 
 pub type THandler<B> = <B as NetworkBehavior>::ConnectionHandler;
 pub type THandlerAction<B> = <THandler<B> as ConnectionHandler>::Action;
@@ -36,12 +55,100 @@ pub type THandlerEvent<B> = <THandler<B> as ConnectionHandler>::Event;
 use std::task::{Context, Poll};
 
 use smallvec::SmallVec;
+use volans_core::PeerId;
+
+use crate::{
+    behavior::NotifyHandler,
+    connection::{EstablishedConnection, Pool},
+};
 
-use crate::connection::{EstablishedConnection, Pool};
+/// 等待投递给连接处理器的动作
+enum PendingHandlerAction<TAction> {
+    One(ConnectionId, TAction),
+    Any(SmallVec<[ConnectionId; 10]>, TAction),
+    /// 每个连接持有各自的动作副本，投递进度互不影响
+    All(ConnectionActions<TAction>),
+}
 
-enum PendingNotifyHandler {
-    One(ConnectionId),
-    Any(SmallVec<[ConnectionId; 10]>),
+/// 每个连接各自的动作
+type ConnectionActions<TAction> = SmallVec<[(ConnectionId, TAction); 10]>;
+
+impl<TAction> PendingHandlerAction<TAction> {
+    /// 按通知目标展开动作，[`NotifyHandler::All`] 为对端的每个连接复制一份
+    fn new<THandler>(
+        peer_id: &PeerId,
+        handler: NotifyHandler,
+        action: TAction,
+        pool: &mut Pool<THandler>,
+    ) -> Self
+    where
+        THandler: ConnectionHandler<Action = TAction>,
+    {
+        match handler {
+            NotifyHandler::One(id) => PendingHandlerAction::One(id, action),
+            NotifyHandler::Any => PendingHandlerAction::Any(
+                pool.select_established_connections_of_peer(peer_id, None),
+                action,
+            ),
+            NotifyHandler::AnyWith(policy) => PendingHandlerAction::Any(
+                pool.select_established_connections_of_peer(peer_id, Some(policy)),
+                action,
+            ),
+            NotifyHandler::AnyOf {
+                connections,
+                policy,
+            } => {
+                let mut ids = pool.select_established_connections_of_peer(peer_id, policy);
+                ids.retain(|id| connections.contains(id));
+                PendingHandlerAction::Any(ids, action)
+            }
+            NotifyHandler::All => {
+                let mut ids: SmallVec<[ConnectionId; 10]> =
+                    pool.iter_established_connections_of_peer(peer_id).collect();
+                let Some(last) = ids.pop() else {
+                    return PendingHandlerAction::All(SmallVec::new());
+                };
+                let mut actions = SmallVec::with_capacity(ids.len() + 1);
+                for id in ids.iter().copied() {
+                    match THandler::clone_action(&action) {
+                        Some(cloned) => actions.push((id, cloned)),
+                        None => {
+                            tracing::debug!(
+                                peer_id = %peer_id,
+                                "Handler action is not cloneable, notifying any connection"
+                            );
+                            ids.push(last);
+                            return PendingHandlerAction::Any(ids, action);
+                        }
+                    }
+                }
+                actions.push((last, action));
+                PendingHandlerAction::All(actions)
+            }
+        }
+    }
+}
+
+// 推进等待中的动作，返回仍未投递的部分
+fn notify_pending<TBehavior>(
+    pending: PendingHandlerAction<THandlerAction<TBehavior>>,
+    pool: &mut Pool<TBehavior::ConnectionHandler>,
+    cx: &mut Context<'_>,
+) -> Option<PendingHandlerAction<THandlerAction<TBehavior>>>
+where
+    TBehavior: NetworkBehavior,
+{
+    match pending {
+        PendingHandlerAction::One(id, action) => {
+            let connection = pool.get_established(id)?;
+            notify_one(connection, action, cx).map(|action| PendingHandlerAction::One(id, action))
+        }
+        PendingHandlerAction::Any(ids, action) => notify_any::<TBehavior>(ids, pool, action, cx)
+            .map(|(ids, action)| PendingHandlerAction::Any(ids, action)),
+        PendingHandlerAction::All(actions) => {
+            notify_all::<TBehavior>(actions, pool, cx).map(PendingHandlerAction::All)
+        }
+    }
 }
 
 // 通知单个连接
@@ -95,3 +202,22 @@ where
         }
     })
 }
+
+// 通知所有连接，每个连接独立等待通道就绪，已关闭的连接直接丢弃其动作
+fn notify_all<TBehavior>(
+    actions: ConnectionActions<THandlerAction<TBehavior>>,
+    pool: &mut Pool<TBehavior::ConnectionHandler>,
+    cx: &mut Context<'_>,
+) -> Option<ConnectionActions<THandlerAction<TBehavior>>>
+where
+    TBehavior: NetworkBehavior,
+{
+    let pending: SmallVec<[_; 10]> = actions
+        .into_iter()
+        .filter_map(|(id, action)| {
+            let connection = pool.get_established(id)?;
+            notify_one(connection, action, cx).map(|action| (id, action))
+        })
+        .collect();
+    (!pending.is_empty()).then_some(pending)
+}
@@ -2,13 +2,17 @@ use std::{
     convert::Infallible,
     fmt, io, mem,
     pin::Pin,
-    sync::atomic::{AtomicUsize, Ordering},
+    sync::{
+        Arc,
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+    },
     task::{Context, Poll},
 };
 
-use futures::{FutureExt, Stream, channel::oneshot, stream::FusedStream};
+use futures::{FutureExt, Stream, channel::oneshot, stream::FusedStream, task::AtomicWaker};
 use volans_core::{
-    Listener, ListenerEvent, Multiaddr, PeerId, muxing::StreamMuxerBox, transport::BoxedListener,
+    Listener, ListenerEvent, Multiaddr, PeerId, SocketOptions, muxing::StreamMuxerBox,
+    transport::BoxedListener,
 };
 
 static NEXT_LISTENER_ID: AtomicUsize = AtomicUsize::new(1);
@@ -33,6 +37,35 @@ impl fmt::Display for ListenerId {
 pub(crate) struct TaggedListener {
     pub(crate) id: ListenerId,
     state: ListenerState,
+    pause: Arc<ListenerPause>,
+}
+
+/// 监听器的暂停状态，由 Swarm 与 [`TaggedListener`] 共享
+///
+/// 暂停期间不再接受新连接，等待中的连接由传输层的 backlog 缓冲
+#[derive(Default)]
+pub(crate) struct ListenerPause {
+    /// 用户手动暂停
+    paused: AtomicBool,
+    /// 等待中的连接过多时自动暂停
+    throttled: AtomicBool,
+    waker: AtomicWaker,
+}
+
+impl ListenerPause {
+    pub(crate) fn set_paused(&self, paused: bool) {
+        self.paused.store(paused, Ordering::SeqCst);
+        self.waker.wake();
+    }
+
+    pub(crate) fn set_throttled(&self, throttled: bool) {
+        self.throttled.store(throttled, Ordering::SeqCst);
+        self.waker.wake();
+    }
+
+    pub(crate) fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::SeqCst) || self.throttled.load(Ordering::SeqCst)
+    }
 }
 
 enum ListenerState {
@@ -57,8 +90,13 @@ impl TaggedListener {
         TaggedListener {
             id,
             state: ListenerState::Active { listener, close_rx },
+            pause: Arc::default(),
         }
     }
+
+    pub(crate) fn pause_handle(&self) -> Arc<ListenerPause> {
+        self.pause.clone()
+    }
 }
 
 type BoxListenerUpgrade<O> = Pin<Box<dyn Future<Output = io::Result<O>> + Send>>;
@@ -87,6 +125,14 @@ impl Stream for TaggedListener {
                         }
                         Poll::Pending => {}
                     }
+                    if this.pause.is_paused() {
+                        this.pause.waker.register(cx.waker());
+                        // 注册后再次检查，避免错过恢复时的唤醒
+                        if this.pause.is_paused() {
+                            this.state = ListenerState::Active { listener, close_rx };
+                            return Poll::Pending;
+                        }
+                    }
                     match Pin::new(&mut listener).poll_event(cx) {
                         Poll::Ready(event) => match event {
                             ListenerEvent::Closed(_) => {
@@ -129,10 +175,29 @@ impl FusedStream for TaggedListener {
     }
 }
 
+/// 入站连接过滤器，在启动握手前以本地与对端地址调用，返回 `false` 时直接丢弃连接
+///
+/// 与 [`NetworkIncomingBehavior::handle_pending_connection`](crate::NetworkIncomingBehavior::handle_pending_connection)
+/// 不同，被拒绝的连接不会进入连接池，适合按 IP 做廉价的预认证过滤。
+pub trait PendingConnectionFilter: Send + 'static {
+    fn allow(&mut self, local_addr: &Multiaddr, remote_addr: &Multiaddr) -> bool;
+}
+
+impl<F> PendingConnectionFilter for F
+where
+    F: FnMut(&Multiaddr, &Multiaddr) -> bool + Send + 'static,
+{
+    fn allow(&mut self, local_addr: &Multiaddr, remote_addr: &Multiaddr) -> bool {
+        self(local_addr, remote_addr)
+    }
+}
+
+/// 监听参数，套接字选项只作用于该监听器，未设置的项沿用传输层配置
 #[derive(Debug)]
 pub struct ListenOpts {
     id: ListenerId,
     addr: Multiaddr,
+    socket: SocketOptions,
 }
 
 impl ListenOpts {
@@ -140,9 +205,41 @@ impl ListenOpts {
         ListenOpts {
             id: ListenerId::next(),
             addr,
+            socket: SocketOptions::default(),
         }
     }
 
+    pub fn with_socket_options(mut self, socket: SocketOptions) -> Self {
+        self.socket = socket;
+        self
+    }
+
+    pub fn with_backlog(mut self, backlog: u32) -> Self {
+        self.socket = self.socket.with_backlog(backlog);
+        self
+    }
+
+    pub fn with_nodelay(mut self, nodelay: bool) -> Self {
+        self.socket = self.socket.with_nodelay(nodelay);
+        self
+    }
+
+    pub fn with_ttl(mut self, ttl: u32) -> Self {
+        self.socket = self.socket.with_ttl(ttl);
+        self
+    }
+
+    pub fn with_only_v6(mut self, only_v6: bool) -> Self {
+        self.socket = self.socket.with_only_v6(only_v6);
+        self
+    }
+
+    /// 绑定到指定网络接口，如 `eth0`
+    pub fn with_bind_device(mut self, device: impl Into<String>) -> Self {
+        self.socket = self.socket.with_bind_device(device);
+        self
+    }
+
     pub fn listener_id(&self) -> ListenerId {
         self.id
     }
@@ -150,4 +247,8 @@ impl ListenOpts {
     pub fn addr(&self) -> &Multiaddr {
         &self.addr
     }
+
+    pub fn socket_options(&self) -> &SocketOptions {
+        &self.socket
+    }
 }
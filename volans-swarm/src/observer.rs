@@ -0,0 +1,105 @@
+use volans_core::{ConnectedPoint, Multiaddr, PeerId};
+
+use crate::{
+    ConnectionId, ListenerEvent,
+    error::{CloseReason, DialError},
+};
+
+/// Swarm 事件观察者，用于接入指标、日志等，不影响事件本身的处理
+///
+/// 回调均有空的默认实现，按需覆盖即可
+pub trait SwarmObserver<TBehaviorEvent>: Send {
+    fn on_connection_established(
+        &mut self,
+        _id: ConnectionId,
+        _peer_id: PeerId,
+        _endpoint: &ConnectedPoint,
+    ) {
+    }
+
+    fn on_connection_closed(
+        &mut self,
+        _id: ConnectionId,
+        _peer_id: PeerId,
+        _endpoint: &ConnectedPoint,
+        _reason: &CloseReason,
+    ) {
+    }
+
+    fn on_dial_failure(
+        &mut self,
+        _id: ConnectionId,
+        _peer_id: Option<PeerId>,
+        _addr: Option<&Multiaddr>,
+        _error: &DialError,
+    ) {
+    }
+
+    fn on_listener_event(&mut self, _event: ListenerEvent<'_>) {}
+
+    fn on_behavior_event(&mut self, _event: &TBehaviorEvent) {}
+}
+
+/// 按注册顺序依次通知的观察者列表
+pub(crate) struct Observers<TBehaviorEvent>(Vec<Box<dyn SwarmObserver<TBehaviorEvent>>>);
+
+impl<TBehaviorEvent> Default for Observers<TBehaviorEvent> {
+    fn default() -> Self {
+        Self(Vec::new())
+    }
+}
+
+impl<TBehaviorEvent> Observers<TBehaviorEvent> {
+    pub(crate) fn push(&mut self, observer: Box<dyn SwarmObserver<TBehaviorEvent>>) {
+        self.0.push(observer);
+    }
+}
+
+impl<TBehaviorEvent> SwarmObserver<TBehaviorEvent> for Observers<TBehaviorEvent> {
+    fn on_connection_established(
+        &mut self,
+        id: ConnectionId,
+        peer_id: PeerId,
+        endpoint: &ConnectedPoint,
+    ) {
+        for observer in &mut self.0 {
+            observer.on_connection_established(id, peer_id, endpoint);
+        }
+    }
+
+    fn on_connection_closed(
+        &mut self,
+        id: ConnectionId,
+        peer_id: PeerId,
+        endpoint: &ConnectedPoint,
+        reason: &CloseReason,
+    ) {
+        for observer in &mut self.0 {
+            observer.on_connection_closed(id, peer_id, endpoint, reason);
+        }
+    }
+
+    fn on_dial_failure(
+        &mut self,
+        id: ConnectionId,
+        peer_id: Option<PeerId>,
+        addr: Option<&Multiaddr>,
+        error: &DialError,
+    ) {
+        for observer in &mut self.0 {
+            observer.on_dial_failure(id, peer_id, addr, error);
+        }
+    }
+
+    fn on_listener_event(&mut self, event: ListenerEvent<'_>) {
+        for observer in &mut self.0 {
+            observer.on_listener_event(event);
+        }
+    }
+
+    fn on_behavior_event(&mut self, event: &TBehaviorEvent) {
+        for observer in &mut self.0 {
+            observer.on_behavior_event(event);
+        }
+    }
+}
@@ -0,0 +1,128 @@
+use std::time::{Duration, Instant};
+
+use fnv::FnvHashMap;
+use volans_core::{Multiaddr, PeerId};
+
+/// 地址簿，记录已知节点的可拨号地址
+///
+/// 地址来自成功的拨号与行为上报的 [`BehaviorEvent::NewPeerAddress`](crate::BehaviorEvent::NewPeerAddress)，
+/// 超过 TTL 未再次确认的地址视为过期，记录新地址时每隔一个 TTL 清理一次过期的节点。
+#[derive(Debug, Clone)]
+pub struct PeerStore {
+    peers: FnvHashMap<PeerId, Vec<AddressRecord>>,
+    ttl: Duration,
+    next_prune: Instant,
+}
+
+#[derive(Debug, Clone)]
+struct AddressRecord {
+    addr: Multiaddr,
+    expires: Instant,
+}
+
+impl Default for PeerStore {
+    fn default() -> Self {
+        Self::new(Duration::from_secs(60 * 60))
+    }
+}
+
+impl PeerStore {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            peers: FnvHashMap::default(),
+            ttl,
+            next_prune: Instant::now() + ttl,
+        }
+    }
+
+    /// 记录地址，已存在时刷新过期时间并移到最前
+    pub fn add_address(&mut self, peer_id: PeerId, addr: Multiaddr) {
+        self.add_address_with_ttl(peer_id, addr, self.ttl);
+    }
+
+    pub fn add_address_with_ttl(&mut self, peer_id: PeerId, addr: Multiaddr, ttl: Duration) {
+        let now = Instant::now();
+        if now >= self.next_prune {
+            self.next_prune = now + self.ttl;
+            self.remove_expired();
+        }
+        let records = self.peers.entry(peer_id).or_default();
+        records.retain(|record| record.expires > now && record.addr != addr);
+        records.insert(
+            0,
+            AddressRecord {
+                addr,
+                expires: now + ttl,
+            },
+        );
+    }
+
+    pub fn remove_address(&mut self, peer_id: &PeerId, addr: &Multiaddr) -> bool {
+        let Some(records) = self.peers.get_mut(peer_id) else {
+            return false;
+        };
+        let len = records.len();
+        records.retain(|record| record.addr != *addr);
+        let removed = records.len() != len;
+        if records.is_empty() {
+            self.peers.remove(peer_id);
+        }
+        removed
+    }
+
+    pub fn remove_peer(&mut self, peer_id: &PeerId) -> bool {
+        self.peers.remove(peer_id).is_some()
+    }
+
+    /// 未过期的地址，最近确认的在前
+    pub fn addresses(&self, peer_id: &PeerId) -> impl Iterator<Item = &Multiaddr> {
+        let now = Instant::now();
+        self.peers
+            .get(peer_id)
+            .into_iter()
+            .flatten()
+            .filter(move |record| record.expires > now)
+            .map(|record| &record.addr)
+    }
+
+    /// 存在未过期地址的节点
+    pub fn peers(&self) -> impl Iterator<Item = &PeerId> {
+        let now = Instant::now();
+        self.peers
+            .iter()
+            .filter(move |(_, records)| records.iter().any(|record| record.expires > now))
+            .map(|(peer_id, _)| peer_id)
+    }
+
+    /// 清理过期的地址
+    pub fn remove_expired(&mut self) {
+        let now = Instant::now();
+        self.peers.retain(|_, records| {
+            records.retain(|record| record.expires > now);
+            !records.is_empty()
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use super::*;
+
+    #[test]
+    fn prune_expired_peers_on_add() {
+        let mut store = PeerStore::new(Duration::from_millis(20));
+        let addr: Multiaddr = "/ip4/127.0.0.1/tcp/1".parse().unwrap();
+        let expired = PeerId::random();
+        store.add_address(expired, addr.clone());
+        store.add_address_with_ttl(PeerId::random(), addr.clone(), Duration::from_secs(60));
+
+        thread::sleep(Duration::from_millis(30));
+        let fresh = PeerId::random();
+        store.add_address(fresh, addr);
+        assert!(!store.peers.contains_key(&expired));
+        assert_eq!(store.peers.len(), 2);
+        assert!(store.addresses(&fresh).next().is_some());
+    }
+}
@@ -0,0 +1,225 @@
+use std::{
+    collections::VecDeque,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{Context, Poll},
+};
+
+use futures::{Stream, StreamExt, channel::mpsc};
+use volans_core::{ConnectedPoint, Multiaddr, PeerId};
+
+use crate::{
+    ConnectionId, ListenerEvent, ListenerId,
+    error::{CloseReason, DialError},
+    observer::SwarmObserver,
+};
+
+/// 订阅者通道中为新事件预留的空间，超出时丢弃事件
+const SUBSCRIBER_BUFFER: usize = 64;
+
+/// 通过 `Swarm::subscribe` 收到的事件，错误以文本保存以便复制给多个订阅者
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub enum ReplayEvent<TBehaviorEvent> {
+    Behavior(TBehaviorEvent),
+    ConnectionEstablished {
+        connection_id: ConnectionId,
+        peer_id: PeerId,
+        endpoint: ConnectedPoint,
+    },
+    ConnectionClosed {
+        connection_id: ConnectionId,
+        peer_id: PeerId,
+        endpoint: ConnectedPoint,
+        reason: String,
+    },
+    DialFailure {
+        connection_id: ConnectionId,
+        peer_id: Option<PeerId>,
+        addr: Option<Multiaddr>,
+        error: String,
+    },
+    NewListenAddr {
+        listener_id: ListenerId,
+        addr: Multiaddr,
+    },
+    ExpiredListenAddr {
+        listener_id: ListenerId,
+        addr: Multiaddr,
+    },
+    ListenerError {
+        listener_id: ListenerId,
+        error: String,
+    },
+    ListenerClosed {
+        listener_id: ListenerId,
+        error: Option<String>,
+    },
+    ExternalAddrConfirmed {
+        addr: Multiaddr,
+    },
+    ExternalAddrExpired {
+        addr: Multiaddr,
+    },
+}
+
+/// 订阅的事件流，订阅者处理不及时时丢弃新的事件
+pub struct Subscription<TBehaviorEvent> {
+    receiver: mpsc::Receiver<ReplayEvent<TBehaviorEvent>>,
+}
+
+impl<TBehaviorEvent> Stream for Subscription<TBehaviorEvent> {
+    type Item = ReplayEvent<TBehaviorEvent>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.receiver.poll_next_unpin(cx)
+    }
+}
+
+struct State<TBehaviorEvent> {
+    capacity: usize,
+    recent: VecDeque<ReplayEvent<TBehaviorEvent>>,
+    subscribers: Vec<mpsc::Sender<ReplayEvent<TBehaviorEvent>>>,
+}
+
+/// 保留最近事件并分发给订阅者，作为观察者接入 Swarm
+pub(crate) struct Replay<TBehaviorEvent>(Arc<Mutex<State<TBehaviorEvent>>>);
+
+impl<TBehaviorEvent> Clone for Replay<TBehaviorEvent> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<TBehaviorEvent> Replay<TBehaviorEvent>
+where
+    TBehaviorEvent: Clone,
+{
+    pub(crate) fn new() -> Self {
+        Self(Arc::new(Mutex::new(State {
+            capacity: 0,
+            recent: VecDeque::new(),
+            subscribers: Vec::new(),
+        })))
+    }
+
+    pub(crate) fn set_capacity(&self, capacity: usize) {
+        let mut state = self.0.lock().expect("replay lock poisoned");
+        state.capacity = capacity;
+        while state.recent.len() > capacity {
+            state.recent.pop_front();
+        }
+    }
+
+    pub(crate) fn subscribe(&self) -> Subscription<TBehaviorEvent> {
+        let mut state = self.0.lock().expect("replay lock poisoned");
+        let (mut sender, receiver) = mpsc::channel(state.capacity + SUBSCRIBER_BUFFER);
+        for event in &state.recent {
+            let _ = sender.try_send(event.clone());
+        }
+        state.subscribers.push(sender);
+        Subscription { receiver }
+    }
+
+    fn record(&self, event: ReplayEvent<TBehaviorEvent>) {
+        let mut state = self.0.lock().expect("replay lock poisoned");
+        state
+            .subscribers
+            .retain_mut(|subscriber| match subscriber.try_send(event.clone()) {
+                Ok(()) => true,
+                Err(e) if e.is_disconnected() => false,
+                Err(_) => {
+                    tracing::debug!("Subscriber lagging, dropping swarm event");
+                    true
+                }
+            });
+        if state.capacity == 0 {
+            return;
+        }
+        if state.recent.len() == state.capacity {
+            state.recent.pop_front();
+        }
+        state.recent.push_back(event);
+    }
+}
+
+impl<TBehaviorEvent> SwarmObserver<TBehaviorEvent> for Replay<TBehaviorEvent>
+where
+    TBehaviorEvent: Clone + Send,
+{
+    fn on_connection_established(
+        &mut self,
+        id: ConnectionId,
+        peer_id: PeerId,
+        endpoint: &ConnectedPoint,
+    ) {
+        self.record(ReplayEvent::ConnectionEstablished {
+            connection_id: id,
+            peer_id,
+            endpoint: endpoint.clone(),
+        });
+    }
+
+    fn on_connection_closed(
+        &mut self,
+        id: ConnectionId,
+        peer_id: PeerId,
+        endpoint: &ConnectedPoint,
+        reason: &CloseReason,
+    ) {
+        self.record(ReplayEvent::ConnectionClosed {
+            connection_id: id,
+            peer_id,
+            endpoint: endpoint.clone(),
+            reason: reason.to_string(),
+        });
+    }
+
+    fn on_dial_failure(
+        &mut self,
+        id: ConnectionId,
+        peer_id: Option<PeerId>,
+        addr: Option<&Multiaddr>,
+        error: &DialError,
+    ) {
+        self.record(ReplayEvent::DialFailure {
+            connection_id: id,
+            peer_id,
+            addr: addr.cloned(),
+            error: error.to_string(),
+        });
+    }
+
+    fn on_listener_event(&mut self, event: ListenerEvent<'_>) {
+        let event = match event {
+            ListenerEvent::NewListenAddr(e) => ReplayEvent::NewListenAddr {
+                listener_id: e.listener_id,
+                addr: e.addr.clone(),
+            },
+            ListenerEvent::ExpiredListenAddr(e) => ReplayEvent::ExpiredListenAddr {
+                listener_id: e.listener_id,
+                addr: e.addr.clone(),
+            },
+            ListenerEvent::ListenerError(e) => ReplayEvent::ListenerError {
+                listener_id: e.listener_id,
+                error: e.error.to_string(),
+            },
+            ListenerEvent::ListenerClosed(e) => ReplayEvent::ListenerClosed {
+                listener_id: e.listener_id,
+                error: e.reason.err().map(|e| e.to_string()),
+            },
+            ListenerEvent::ExternalAddrConfirmed(e) => ReplayEvent::ExternalAddrConfirmed {
+                addr: e.addr.clone(),
+            },
+            ListenerEvent::ExternalAddrExpired(e) => ReplayEvent::ExternalAddrExpired {
+                addr: e.addr.clone(),
+            },
+            ListenerEvent::NewListener(_) => return,
+        };
+        self.record(event);
+    }
+
+    fn on_behavior_event(&mut self, event: &TBehaviorEvent) {
+        self.record(ReplayEvent::Behavior(event.clone()));
+    }
+}
@@ -1,6 +1,4 @@
 use std::{
-    collections::{HashMap, VecDeque},
-    convert::Infallible,
     io,
     pin::Pin,
     task::{Context, Poll},
@@ -8,43 +6,33 @@ use std::{
 
 use futures::{
     Stream, StreamExt,
-    channel::oneshot,
-    stream::{Fuse, SelectAll},
-};
-use smallvec::SmallVec;
-use volans_core::{
-    ConnectedPoint, Multiaddr, PeerId, Transport, TransportError, muxing::StreamMuxerBox, transport,
+    channel::{mpsc, oneshot},
+    future,
 };
+use volans_core::{Multiaddr, PeerId, TransportError, muxing::StreamMuxerBox, transport};
 
 use crate::{
-    BehaviorEvent, ConnectionId, InboundStreamHandler, ListenOpts, ListenerEvent, ListenerId,
-    NetworkIncomingBehavior, PendingNotifyHandler, THandlerAction, THandlerEvent,
-    behavior::{
-        CloseConnection, ExpiredListenAddr, ListenerClosed, ListenerError, NewListenAddr,
-        NewListener, NotifyHandler,
-    },
-    connection::{Pool, PoolConfig, PoolEvent},
-    error::{ConnectionError, ListenError},
-    listener, notify_any, notify_one,
+    Bandwidth, ConnectionId, ConnectionInfo, ExternalAddrStore, InboundStreamHandler, ListenOpts,
+    ListenerId, NetworkIncomingBehavior, PendingConnectionFilter,
+    connection::PoolConfig,
+    error::SwarmClosed,
+    handle::{self, BehaviorCommand, CommandSender},
+    observer::SwarmObserver,
+    replay::Subscription,
+    swarm::{AddressEvent, SwarmCore},
 };
 
+pub use crate::SwarmEvent;
+
 pub struct Swarm<TBehavior>
 where
     TBehavior: NetworkIncomingBehavior,
 {
-    behavior: TBehavior,
-    transport: transport::Boxed<(PeerId, StreamMuxerBox)>,
-    pool: Pool<TBehavior::ConnectionHandler>,
-    /// 等待Handler操作
-    pending_handler_action: Option<(PeerId, PendingNotifyHandler, THandlerAction<TBehavior>)>,
-
-    /// listeners
-    listeners: SelectAll<Fuse<listener::TaggedListener>>,
-    listeners_abort: HashMap<ListenerId, oneshot::Sender<Infallible>>,
-    listened_addresses: HashMap<ListenerId, SmallVec<[Multiaddr; 1]>>,
-
-    /// Swarm 等待处理的事件
-    pending_swarm_events: VecDeque<SwarmEvent<TBehavior::Event>>,
+    core: SwarmCore<TBehavior>,
+
+    /// [`SwarmHandle`] 发来的命令
+    command_sender: CommandSender<Command<TBehavior>>,
+    command_receiver: mpsc::Receiver<Command<TBehavior>>,
 }
 
 impl<TBehavior> Unpin for Swarm<TBehavior> where TBehavior: NetworkIncomingBehavior {}
@@ -60,409 +48,174 @@ where
         local_peer_id: PeerId,
         config: PoolConfig,
     ) -> Self {
+        let (command_sender, command_receiver) = handle::channel();
         Self {
-            behavior,
-            transport,
-            pool: Pool::new(local_peer_id, config),
-            pending_handler_action: None,
-            listeners: SelectAll::new(),
-            listeners_abort: HashMap::new(),
-            listened_addresses: HashMap::new(),
-            pending_swarm_events: VecDeque::new(),
+            core: SwarmCore::new(transport, behavior, local_peer_id, config),
+            command_sender,
+            command_receiver,
+        }
+    }
+
+    /// 添加事件观察者，可添加多个，按添加顺序通知
+    pub fn with_observer(
+        mut self,
+        observer: impl SwarmObserver<TBehavior::Event> + 'static,
+    ) -> Self {
+        self.core.observers.push(Box::new(observer));
+        self
+    }
+
+    /// 替换默认的外部地址簿，用于调整确认阈值
+    pub fn with_external_addr_store(mut self, external_addrs: ExternalAddrStore) -> Self {
+        self.core.listen.external_addrs = external_addrs;
+        self
+    }
+
+    /// 等待握手的连接达到 `count` 时暂停接受新连接，回落后自动恢复
+    pub fn with_pending_incoming_high_water_mark(mut self, count: usize) -> Self {
+        self.core.listen.pending_incoming_high_water_mark = Some(count);
+        self
+    }
+
+    /// 设置入站连接过滤器，被拒绝的连接以 [`ListenError::Filtered`](crate::error::ListenError::Filtered) 上报
+    pub fn with_pending_connection_filter(mut self, filter: impl PendingConnectionFilter) -> Self {
+        self.core.listen.pending_connection_filter = Some(Box::new(filter));
+        self
+    }
+
+    /// 可在其他任务中使用的句柄，命令在 Swarm 被轮询时执行
+    pub fn handle(&self) -> SwarmHandle<TBehavior> {
+        SwarmHandle {
+            commands: self.command_sender.clone(),
         }
     }
 
     /// 关闭指定的连接
     pub fn close_connection(&mut self, connection_id: ConnectionId) -> bool {
-        if let Some(established) = self.pool.get_established(connection_id) {
-            established.start_close();
-            return true;
+        self.core.close_connection(connection_id)
+    }
+
+    /// 优雅关闭 Swarm
+    ///
+    /// 移除所有监听器，中断等待中的连接并关闭所有已建立的连接，
+    /// 期间的事件仍会通知行为与观察者，所有监听器与连接任务结束后返回。
+    pub async fn close(&mut self) {
+        self.core.start_close();
+        self.core.remove_listeners();
+        future::poll_fn(|cx| self.poll_close(cx)).await
+    }
+
+    fn poll_close(&mut self, cx: &mut Context<'_>) -> Poll<()> {
+        loop {
+            if self.core.is_closed() {
+                return Poll::Ready(());
+            }
+            match Pin::new(&mut *self).poll_next_event(cx) {
+                Poll::Ready(_) => continue,
+                Poll::Pending => return Poll::Pending,
+            }
         }
-        false
+    }
+
+    pub fn local_peer_id(&self) -> &PeerId {
+        self.core.pool.local_peer_id()
     }
 
     /// 检查指定的 PeerId 是否已连接
     pub fn is_peer_connected(&self, peer_id: &PeerId) -> bool {
-        self.pool.is_peer_connected(peer_id)
+        self.core.pool.is_peer_connected(peer_id)
     }
 
     /// 获取所有已连接的 PeerId
     pub fn connected_peers(&self) -> impl Iterator<Item = &PeerId> {
-        self.pool.iter_peer_connected()
+        self.core.pool.iter_peer_connected()
     }
 
     /// 获取所有已连接的连接 ID
     pub fn connected_connections(&self) -> impl Iterator<Item = &ConnectionId> {
-        self.pool.iter_connected()
+        self.core.pool.iter_connected()
+    }
+
+    /// 已建立连接的元数据
+    pub fn connection_info(&self, connection_id: ConnectionId) -> Option<ConnectionInfo> {
+        self.core.pool.connection_info(connection_id)
+    }
+
+    /// 所有已建立连接的元数据
+    pub fn iter_connections(&self) -> impl Iterator<Item = ConnectionInfo> + '_ {
+        self.core.pool.iter_connections()
     }
 
     pub fn behavior(&self) -> &TBehavior {
-        &self.behavior
+        &self.core.behavior
     }
 
     pub fn behavior_mut(&mut self) -> &mut TBehavior {
-        &mut self.behavior
+        &mut self.core.behavior
+    }
+
+    /// 按连接与传输协议统计的流量
+    pub fn bandwidth(&self) -> &Bandwidth {
+        self.core.pool.bandwidth()
     }
 
     /// 开始监听指定的地址
     pub fn listen_on(&mut self, addr: Multiaddr) -> Result<ListenerId, TransportError<io::Error>> {
-        let opts = ListenOpts::new(addr);
-        let listener_id = opts.listener_id();
-        let addr = opts.addr();
-        match self.transport.listen(addr.clone()) {
-            Ok(listener) => {
-                let (close_tx, close_rx) = oneshot::channel();
-                let tagged_listener =
-                    listener::TaggedListener::new(listener_id, listener, close_rx);
-                self.listeners.push(tagged_listener.fuse());
-                self.listeners_abort.insert(listener_id, close_tx);
-            }
-            Err(error) => {
-                self.behavior
-                    .on_listener_event(ListenerEvent::ListenerError(ListenerError {
-                        listener_id,
-                        error: &error,
-                    }));
-                return Err(error);
-            }
-        }
-        self.behavior
-            .on_listener_event(ListenerEvent::NewListener(NewListener { listener_id }));
-        Ok(listener_id)
+        self.listen_with_opts(ListenOpts::new(addr))
+    }
+
+    /// 按 [`ListenOpts`] 开始监听，可为该监听器单独设置套接字选项
+    pub fn listen_with_opts(
+        &mut self,
+        opts: ListenOpts,
+    ) -> Result<ListenerId, TransportError<io::Error>> {
+        self.core.listen_with_opts(opts)
     }
 
     /// 获取所有监听的地址
     pub fn listeners(&self) -> impl Iterator<Item = &Multiaddr> {
-        self.listened_addresses.values().flatten()
+        self.core.listen.addresses()
+    }
+
+    /// 暂停监听器接受新连接，监听器保持打开，期间的连接由传输层的 backlog 缓冲
+    pub fn pause_listener(&mut self, listener_id: ListenerId) -> bool {
+        self.core.set_listener_paused(listener_id, true)
+    }
+
+    /// 恢复被 [`Swarm::pause_listener`] 暂停的监听器
+    pub fn resume_listener(&mut self, listener_id: ListenerId) -> bool {
+        self.core.set_listener_paused(listener_id, false)
     }
 
     /// 移除指定的监听器
     pub fn remove_listener(&mut self, listener_id: ListenerId) -> bool {
-        match self.listeners_abort.remove(&listener_id) {
-            Some(abort_sender) => {
-                // Drop 掉 close_sender 以触发监听器关闭
-                drop(abort_sender);
-                true
-            }
-            None => false,
-        }
+        self.core.remove_listener(listener_id)
     }
 
-    fn handle_behavior_event(
-        &mut self,
-        event: BehaviorEvent<TBehavior::Event, THandlerAction<TBehavior>>,
-    ) {
-        match event {
-            BehaviorEvent::Behavior(event) => {
-                self.pending_swarm_events
-                    .push_back(SwarmEvent::Behavior(event));
-            }
-            BehaviorEvent::HandlerAction {
-                peer_id,
-                handler,
-                action,
-            } => {
-                assert!(
-                    self.pending_handler_action.is_none(),
-                    "Pending handler action already exists"
-                );
-                let handler = match handler {
-                    NotifyHandler::One(connection) => PendingNotifyHandler::One(connection),
-                    NotifyHandler::Any => {
-                        let ids = self
-                            .pool
-                            .iter_established_connections_of_peer(&peer_id)
-                            .collect();
-                        PendingNotifyHandler::Any(ids)
-                    }
-                };
-                self.pending_handler_action = Some((peer_id, handler, action));
-            }
-            BehaviorEvent::CloseConnection {
-                peer_id,
-                connection,
-            } => match connection {
-                CloseConnection::One(id) => {
-                    if let Some(connection) = self.pool.get_established(id) {
-                        connection.start_close();
-                    } else {
-                        tracing::debug!(
-                            id = ?id,
-                            peer_id = ?peer_id,
-                            "Attempted to close non-existent connection"
-                        );
-                    }
-                }
-                CloseConnection::All => self.pool.disconnect(&peer_id),
-            },
-        }
+    /// 手动添加已确认的外部地址，地址此前未被确认时通知行为
+    pub fn add_external_address(&mut self, addr: Multiaddr) {
+        self.core.add_external_address(addr)
     }
 
-    fn handle_pool_event(&mut self, event: PoolEvent<THandlerEvent<TBehavior>>) {
-        match event {
-            PoolEvent::ConnectionEstablished {
-                id,
-                peer_id,
-                endpoint,
-                connection,
-                established_in,
-            } => {
-                let (handler, local_addr, remote_addr) = match &endpoint {
-                    ConnectedPoint::Dialer { addr: _ } => {
-                        unreachable!("Dialer connections should not be handled here")
-                    }
-                    ConnectedPoint::Listener {
-                        local_addr,
-                        remote_addr,
-                    } => match self.behavior.handle_established_connection(
-                        id,
-                        peer_id,
-                        local_addr,
-                        remote_addr,
-                    ) {
-                        Ok(handler) => (handler, local_addr, remote_addr),
-                        Err(cause) => {
-                            let listen_error = ListenError::Denied { cause };
-                            self.behavior.on_listen_failure(
-                                id,
-                                Some(peer_id),
-                                local_addr,
-                                remote_addr,
-                                &listen_error,
-                            );
-                            self.pending_swarm_events.push_back(
-                                SwarmEvent::IncomingConnectionError {
-                                    peer_id: Some(peer_id),
-                                    connection_id: id,
-                                    local_addr: local_addr.clone(),
-                                    remote_addr: remote_addr.clone(),
-                                    error: listen_error,
-                                },
-                            );
-                            return;
-                        }
-                    },
-                };
-
-                let num_established = self.pool.num_peer_established(&peer_id);
-
-                self.pool.spawn_inbound_connection(
-                    id,
-                    peer_id,
-                    endpoint.clone(),
-                    connection,
-                    handler,
-                );
-
-                tracing::debug!(
-                    peer=%peer_id,
-                    local_addr=%local_addr,
-                    remote_addr=%remote_addr,
-                    total_peers=%num_established,
-                    "Connection inbound established"
-                );
-                self.behavior
-                    .on_connection_established(id, peer_id, local_addr, remote_addr);
-                self.pending_swarm_events
-                    .push_back(SwarmEvent::ConnectionEstablished {
-                        connection_id: id,
-                        peer_id,
-                        local_addr: local_addr.clone(),
-                        remote_addr: remote_addr.clone(),
-                        established_in,
-                        num_established,
-                    });
-            }
-            PoolEvent::PendingConnectionError {
-                id,
-                peer_id,
-                endpoint,
-                error,
-            } => match endpoint {
-                ConnectedPoint::Dialer { addr: _ } => {
-                    unreachable!("Dialer connections should not be handled here")
-                }
-                ConnectedPoint::Listener {
-                    local_addr,
-                    remote_addr,
-                } => {
-                    let listen_error = ListenError::from(error);
-                    self.behavior.on_listen_failure(
-                        id,
-                        peer_id,
-                        &local_addr,
-                        &remote_addr,
-                        &listen_error,
-                    );
-                    self.pending_swarm_events
-                        .push_back(SwarmEvent::IncomingConnectionError {
-                            peer_id,
-                            connection_id: id,
-                            local_addr,
-                            remote_addr,
-                            error: listen_error,
-                        });
-                }
-            },
-            PoolEvent::ConnectionClosed {
-                id,
-                peer_id,
-                endpoint,
-                num_remaining_established,
-                error,
-            } => match endpoint {
-                ConnectedPoint::Dialer { addr: _ } => {
-                    unreachable!("Dialer connections should not be handled here")
-                }
-                ConnectedPoint::Listener {
-                    local_addr,
-                    remote_addr,
-                } => {
-                    self.behavior.on_connection_closed(
-                        id,
-                        peer_id,
-                        &local_addr,
-                        &remote_addr,
-                        error.as_ref(),
-                    );
-                    self.pending_swarm_events
-                        .push_back(SwarmEvent::ConnectionClosed {
-                            connection_id: id,
-                            peer_id,
-                            local_addr: local_addr.clone(),
-                            remote_addr: remote_addr.clone(),
-                            num_remaining_established,
-                            error,
-                        });
-                }
-            },
-            PoolEvent::ConnectionEvent { id, peer_id, event } => {
-                self.behavior
-                    .on_connection_handler_event(id, peer_id, event);
-            }
-        }
+    /// 移除外部地址，地址此前已被确认时通知行为并返回 `true`
+    pub fn remove_external_address(&mut self, addr: &Multiaddr) -> bool {
+        self.core.remove_external_address(addr)
     }
 
-    fn handle_listener_event(
-        &mut self,
-        listener_id: ListenerId,
-        event: listener::BoxedListenerEvent,
-    ) {
-        match event {
-            transport::ListenerEvent::Incoming {
-                local_addr,
-                remote_addr,
-                upgrade,
-            } => {
-                let connection_id = ConnectionId::next();
-                match self.behavior.handle_pending_connection(
-                    connection_id,
-                    &local_addr,
-                    &remote_addr,
-                ) {
-                    Ok(()) => {}
-                    Err(cause) => {
-                        let listen_error = ListenError::Denied { cause };
-                        self.behavior.on_listen_failure(
-                            connection_id,
-                            None,
-                            &local_addr,
-                            &remote_addr,
-                            &listen_error,
-                        );
-                        self.pending_swarm_events
-                            .push_back(SwarmEvent::IncomingConnectionError {
-                                peer_id: None,
-                                connection_id,
-                                local_addr,
-                                remote_addr,
-                                error: listen_error,
-                            });
-                        return;
-                    }
-                }
-                self.pool.add_incoming(
-                    connection_id,
-                    upgrade,
-                    local_addr.clone(),
-                    remote_addr.clone(),
-                );
-
-                self.pending_swarm_events
-                    .push_back(SwarmEvent::IncomingConnection {
-                        connection_id,
-                        local_addr,
-                        remote_addr,
-                    })
-            }
-            transport::ListenerEvent::NewAddress(addr) => {
-                tracing::debug!(listener = ?listener_id, addr = %addr, "Listener started");
-                let addresses = self.listened_addresses.entry(listener_id).or_default();
-
-                if !addresses.contains(&addr) {
-                    addresses.push(addr.clone());
-                }
+    /// 已确认的外部地址
+    pub fn external_addresses(&self) -> impl Iterator<Item = &Multiaddr> {
+        self.core.listen.external_addrs.confirmed()
+    }
 
-                self.behavior
-                    .on_listener_event(ListenerEvent::NewListenAddr(NewListenAddr {
-                        listener_id,
-                        addr: &addr,
-                    }));
-                self.pending_swarm_events
-                    .push_back(SwarmEvent::NewListenAddr { listener_id, addr });
-            }
-            transport::ListenerEvent::AddressExpired(addr) => {
-                if let Some(addresses) = self.listened_addresses.get_mut(&listener_id) {
-                    addresses.retain(|a| a != &addr);
-                }
-                self.behavior
-                    .on_listener_event(ListenerEvent::ExpiredListenAddr(ExpiredListenAddr {
-                        listener_id,
-                        addr: &addr,
-                    }));
-                self.pending_swarm_events
-                    .push_back(SwarmEvent::ExpiredListenAddr { listener_id, addr });
+    fn handle_command(&mut self, command: Command<TBehavior>) {
+        match command {
+            Command::ListenOn(addr, sender) => {
+                let _ = sender.send(self.listen_on(addr));
             }
-            transport::ListenerEvent::Closed(reason) => {
-                tracing::debug!(
-                    listener=?listener_id,
-                    ?reason,
-                    "Listener closed"
-                );
-                // 移除监听器的地址
-                let addresses = self
-                    .listened_addresses
-                    .remove(&listener_id)
-                    .unwrap_or_default();
-                for addr in addresses.iter() {
-                    // 通知行为层监听器地址过期
-                    self.behavior
-                        .on_listener_event(ListenerEvent::ExpiredListenAddr(ExpiredListenAddr {
-                            listener_id,
-                            addr,
-                        }));
-                }
-                self.behavior
-                    .on_listener_event(ListenerEvent::ListenerClosed(ListenerClosed {
-                        listener_id,
-                        reason: reason.as_ref().copied(),
-                    }));
-                self.pending_swarm_events
-                    .push_back(SwarmEvent::ListenerClosed {
-                        listener_id,
-                        reason,
-                    });
-            }
-            transport::ListenerEvent::Error(error) => {
-                tracing::debug!(listener = ?listener_id, "Listener error");
-                self.behavior
-                    .on_listener_event(ListenerEvent::ListenerError(ListenerError {
-                        listener_id,
-                        error: &error,
-                    }));
-                self.pending_swarm_events
-                    .push_back(SwarmEvent::ListenerError { listener_id, error });
+            Command::CloseConnection(connection_id, sender) => {
+                let _ = sender.send(self.close_connection(connection_id));
             }
+            Command::Behavior(command) => command(&mut self.core.behavior),
         }
     }
 
@@ -473,64 +226,117 @@ where
     ) -> Poll<SwarmEvent<TBehavior::Event>> {
         let this = &mut *self;
         loop {
-            if let Some(event) = this.pending_swarm_events.pop_front() {
+            if let Some(event) = this.core.next_event() {
                 return Poll::Ready(event);
             }
-            match this.pending_handler_action.take() {
-                Some((peer_id, handler, action)) => match handler {
-                    PendingNotifyHandler::One(id) => match this.pool.get_established(id) {
-                        Some(connection) => match notify_one(connection, action, cx) {
-                            Some(action) => {
-                                this.pending_handler_action = Some((peer_id, handler, action));
-                            }
-                            None => continue,
-                        },
-                        None => continue,
-                    },
-                    PendingNotifyHandler::Any(ids) => {
-                        match notify_any::<TBehavior>(ids, &mut this.pool, action, cx) {
-                            Some((pending, action)) => {
-                                // 写回Pending的连接ID和操作
-                                this.pending_handler_action =
-                                    Some((peer_id, PendingNotifyHandler::Any(pending), action));
-                            }
-                            None => continue,
-                        }
-                    }
-                },
-                // 如果没有Pending的Handler操作，继续处理Swarm事件
-                None => match this.behavior.poll(cx) {
-                    Poll::Pending => {}
-                    Poll::Ready(event) => {
-                        this.handle_behavior_event(event);
-                        continue;
-                    }
-                },
+            if let Poll::Ready(Some(command)) = this.command_receiver.poll_next_unpin(cx) {
+                this.handle_command(command);
+                continue;
+            }
+            match this.core.poll_behavior(cx) {
+                Poll::Pending => {}
+                // 服务端不主动拨号，无需记录对端地址
+                Poll::Ready(None | Some(AddressEvent::PeerAddress { .. })) => continue,
+                Poll::Ready(Some(event)) => {
+                    this.core.handle_external_addr_event(event);
+                    continue;
+                }
             }
 
             // 处理连接池中的事件
-            match this.pool.poll(cx) {
+            match this.core.pool.poll(cx) {
                 Poll::Pending => {}
                 Poll::Ready(pool_event) => {
-                    this.handle_pool_event(pool_event);
+                    this.core.handle_listener_pool_event(pool_event);
                     continue;
                 }
             }
 
             // 处理监听器事件
-            match this.listeners.poll_next_unpin(cx) {
-                Poll::Ready(Some((id, event))) => {
-                    this.handle_listener_event(id, event);
-                    continue;
-                }
-                Poll::Ready(None) => {}
-                Poll::Pending => {}
+            if this.core.poll_listeners(cx) {
+                continue;
             }
             return Poll::Pending;
         }
     }
 }
 
+impl<TBehavior> Swarm<TBehavior>
+where
+    TBehavior: NetworkIncomingBehavior,
+    TBehavior::ConnectionHandler: InboundStreamHandler,
+    TBehavior::Event: Clone + Send + 'static,
+{
+    /// 保留最近 `capacity` 个事件，之后订阅的消费者先收到这些事件
+    pub fn with_replay_buffer(mut self, capacity: usize) -> Self {
+        self.core.replay().set_capacity(capacity);
+        self
+    }
+
+    /// 订阅 Swarm 事件，可在任意时刻订阅，先收到回放缓冲中的事件
+    ///
+    /// 未设置 [`Self::with_replay_buffer`] 时只收到订阅之后的事件。
+    pub fn subscribe(&mut self) -> Subscription<TBehavior::Event> {
+        self.core.replay().subscribe()
+    }
+}
+
+/// 从其他任务驱动 [`Swarm`] 的句柄
+///
+/// 命令经通道发往 Swarm，在 Swarm 所在任务轮询时依次执行，调用等待执行结果返回。
+pub struct SwarmHandle<TBehavior> {
+    commands: CommandSender<Command<TBehavior>>,
+}
+
+impl<TBehavior> Clone for SwarmHandle<TBehavior> {
+    fn clone(&self) -> Self {
+        Self {
+            commands: self.commands.clone(),
+        }
+    }
+}
+
+impl<TBehavior> SwarmHandle<TBehavior> {
+    /// 在指定地址上监听
+    pub async fn listen_on(
+        &self,
+        addr: Multiaddr,
+    ) -> Result<ListenerId, TransportError<io::Error>> {
+        self.commands
+            .call(|sender| Command::ListenOn(addr, sender))
+            .await
+            .unwrap_or_else(|closed| Err(TransportError::Other(io::Error::other(closed))))
+    }
+
+    /// 关闭指定的连接，Swarm 已被丢弃时返回 `false`
+    pub async fn close_connection(&self, connection_id: ConnectionId) -> bool {
+        self.commands
+            .call(|sender| Command::CloseConnection(connection_id, sender))
+            .await
+            .unwrap_or(false)
+    }
+
+    /// 在 Swarm 所在任务中操作行为，返回 `f` 的结果
+    pub async fn behavior_command<R>(
+        &self,
+        f: impl FnOnce(&mut TBehavior) -> R + Send + 'static,
+    ) -> Result<R, SwarmClosed>
+    where
+        R: Send + 'static,
+    {
+        self.commands.behavior(f, Command::Behavior).await
+    }
+}
+
+enum Command<TBehavior> {
+    ListenOn(
+        Multiaddr,
+        oneshot::Sender<Result<ListenerId, TransportError<io::Error>>>,
+    ),
+    CloseConnection(ConnectionId, oneshot::Sender<bool>),
+    Behavior(BehaviorCommand<TBehavior>),
+}
+
 impl<TBehavior> Stream for Swarm<TBehavior>
 where
     TBehavior: NetworkIncomingBehavior,
@@ -545,61 +351,3 @@ where
         }
     }
 }
-
-#[derive(Debug)]
-#[non_exhaustive]
-pub enum SwarmEvent<TBehaviorEvent> {
-    Behavior(TBehaviorEvent),
-
-    NewListenAddr {
-        listener_id: ListenerId,
-        addr: Multiaddr,
-    },
-
-    ExpiredListenAddr {
-        listener_id: ListenerId,
-        addr: Multiaddr,
-    },
-
-    ListenerClosed {
-        listener_id: ListenerId,
-        reason: Result<(), io::Error>,
-    },
-
-    ListenerError {
-        listener_id: ListenerId,
-        error: io::Error,
-    },
-
-    IncomingConnection {
-        connection_id: ConnectionId,
-        local_addr: Multiaddr,
-        remote_addr: Multiaddr,
-    },
-
-    IncomingConnectionError {
-        connection_id: ConnectionId,
-        local_addr: Multiaddr,
-        remote_addr: Multiaddr,
-        error: ListenError,
-        peer_id: Option<PeerId>,
-    },
-
-    ConnectionEstablished {
-        peer_id: PeerId,
-        connection_id: ConnectionId,
-        local_addr: Multiaddr,
-        remote_addr: Multiaddr,
-        num_established: usize,
-        established_in: std::time::Duration,
-    },
-
-    ConnectionClosed {
-        connection_id: ConnectionId,
-        peer_id: PeerId,
-        local_addr: Multiaddr,
-        remote_addr: Multiaddr,
-        num_remaining_established: usize,
-        error: Option<ConnectionError>,
-    },
-}
@@ -0,0 +1,295 @@
+//! 确定性模拟
+//!
+//! [`Simulation`] 在当前线程上按派发顺序运行连接任务，并提供模拟时钟。任务中与
+//! [`Simulation::enter`] 期间创建的 [`Delay`](crate::timer::Delay) 只在
+//! [`Simulation::advance`] 推进时钟后到期，配合进程内的传输层可以复现依赖时间的行为。
+
+use std::{
+    cell::RefCell,
+    collections::BTreeMap,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, Ordering},
+    },
+    task::{Context, Poll, Waker},
+    time::Duration,
+};
+
+use futures::{
+    future::BoxFuture,
+    task::{ArcWake, waker_ref},
+};
+
+use crate::{connection::PoolConfig, executor::Executor};
+
+thread_local! {
+    static CURRENT_CLOCK: RefCell<Option<Clock>> = const { RefCell::new(None) };
+}
+
+#[derive(Default)]
+struct ClockState {
+    now: Duration,
+    next_id: u64,
+    timers: BTreeMap<(Duration, u64), Option<Waker>>,
+}
+
+/// 模拟时钟，时间只在 [`Clock::advance`] 时前进
+#[derive(Clone, Default)]
+pub struct Clock(Arc<Mutex<ClockState>>);
+
+impl Clock {
+    /// 当前线程所在的模拟时钟
+    pub(crate) fn current() -> Option<Clock> {
+        CURRENT_CLOCK.with(|clock| clock.borrow().clone())
+    }
+
+    /// 模拟开始后经过的时间
+    pub fn now(&self) -> Duration {
+        self.lock().now
+    }
+
+    /// 推进时钟并唤醒到期的计时器
+    pub fn advance(&self, duration: Duration) {
+        let target = self.now() + duration;
+        self.advance_to(target);
+    }
+
+    /// 在此期间创建的计时器使用该时钟，离开作用域后恢复
+    pub fn enter(&self) -> EnterGuard {
+        let previous = CURRENT_CLOCK.with(|clock| clock.replace(Some(self.clone())));
+        EnterGuard { previous }
+    }
+
+    pub(crate) fn delay(&self, duration: Duration) -> SimDelay {
+        let mut state = self.lock();
+        let id = state.next_id;
+        state.next_id += 1;
+        let deadline = state.now + duration;
+        state.timers.insert((deadline, id), None);
+        SimDelay {
+            clock: self.clone(),
+            deadline,
+            id,
+        }
+    }
+
+    /// 最早到期的计时器的到期时刻
+    pub fn next_deadline(&self) -> Option<Duration> {
+        self.lock()
+            .timers
+            .keys()
+            .next()
+            .map(|(deadline, _)| *deadline)
+    }
+
+    fn advance_to(&self, target: Duration) {
+        let wakers = {
+            let mut state = self.lock();
+            state.now = state.now.max(target);
+            let now = state.now;
+            let pending = state.timers.split_off(&(now, u64::MAX));
+            std::mem::replace(&mut state.timers, pending)
+        };
+        for waker in wakers.into_values().flatten() {
+            waker.wake();
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, ClockState> {
+        self.0.lock().expect("clock lock poisoned")
+    }
+}
+
+/// [`Clock::enter`] 返回的作用域
+pub struct EnterGuard {
+    previous: Option<Clock>,
+}
+
+impl Drop for EnterGuard {
+    fn drop(&mut self) {
+        CURRENT_CLOCK.with(|clock| *clock.borrow_mut() = self.previous.take());
+    }
+}
+
+pub(crate) struct SimDelay {
+    clock: Clock,
+    pub(crate) deadline: Duration,
+    id: u64,
+}
+
+impl SimDelay {
+    pub(crate) fn reset(&mut self, duration: Duration) {
+        let mut state = self.clock.lock();
+        state.timers.remove(&(self.deadline, self.id));
+        self.deadline = state.now + duration;
+        state.timers.insert((self.deadline, self.id), None);
+    }
+
+    pub(crate) fn poll(&mut self, cx: &mut Context<'_>) -> Poll<()> {
+        let mut state = self.clock.lock();
+        if state.now >= self.deadline {
+            return Poll::Ready(());
+        }
+        state
+            .timers
+            .insert((self.deadline, self.id), Some(cx.waker().clone()));
+        Poll::Pending
+    }
+}
+
+impl Drop for SimDelay {
+    fn drop(&mut self) {
+        self.clock.lock().timers.remove(&(self.deadline, self.id));
+    }
+}
+
+struct Task {
+    future: Mutex<Option<BoxFuture<'static, ()>>>,
+    woken: AtomicBool,
+}
+
+impl ArcWake for Task {
+    fn wake_by_ref(task: &Arc<Self>) {
+        task.woken.store(true, Ordering::SeqCst);
+    }
+}
+
+/// 只记录任务，由 [`Simulation`] 在当前线程上按派发顺序运行
+#[derive(Clone, Default)]
+pub struct SimExecutor {
+    tasks: Arc<Mutex<Vec<Arc<Task>>>>,
+}
+
+impl Executor for SimExecutor {
+    fn exec(&self, future: BoxFuture<'static, ()>) {
+        self.tasks
+            .lock()
+            .expect("executor lock poisoned")
+            .push(Arc::new(Task {
+                future: Mutex::new(Some(future)),
+                woken: AtomicBool::new(true),
+            }));
+    }
+}
+
+/// 单线程的确定性执行器与模拟时钟
+#[derive(Clone, Default)]
+pub struct Simulation {
+    clock: Clock,
+    executor: SimExecutor,
+}
+
+impl Simulation {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn clock(&self) -> &Clock {
+        &self.clock
+    }
+
+    pub fn executor(&self) -> SimExecutor {
+        self.executor.clone()
+    }
+
+    /// 连接任务派发到模拟执行器的连接池配置
+    pub fn pool_config(&self) -> PoolConfig {
+        PoolConfig::new(Box::new(self.executor()))
+    }
+
+    /// 在此期间轮询 Swarm 时创建的计时器使用模拟时钟
+    pub fn enter(&self) -> EnterGuard {
+        self.clock.enter()
+    }
+
+    /// 是否有被唤醒、等待运行的任务
+    pub fn has_woken_tasks(&self) -> bool {
+        self.executor
+            .tasks
+            .lock()
+            .expect("executor lock poisoned")
+            .iter()
+            .any(|task| task.woken.load(Ordering::SeqCst))
+    }
+
+    /// 按派发顺序轮询被唤醒的任务，直到没有任务被唤醒，返回剩余的任务数
+    pub fn run_until_stalled(&self) -> usize {
+        let _guard = self.clock.enter();
+        loop {
+            let tasks = self
+                .executor
+                .tasks
+                .lock()
+                .expect("executor lock poisoned")
+                .clone();
+            let mut progressed = false;
+            for task in &tasks {
+                if !task.woken.swap(false, Ordering::SeqCst) {
+                    continue;
+                }
+                progressed = true;
+                let mut slot = task.future.lock().expect("task lock poisoned");
+                if let Some(future) = slot.as_mut() {
+                    let waker = waker_ref(task);
+                    let mut cx = Context::from_waker(&waker);
+                    if future.as_mut().poll(&mut cx).is_ready() {
+                        *slot = None;
+                    }
+                }
+            }
+            let mut tasks = self.executor.tasks.lock().expect("executor lock poisoned");
+            tasks.retain(|task| task.future.lock().expect("task lock poisoned").is_some());
+            if !progressed {
+                return tasks.len();
+            }
+        }
+    }
+
+    /// 推进时钟，依次在每个到期时刻运行被唤醒的任务
+    pub fn advance(&self, duration: Duration) {
+        let target = self.clock.now() + duration;
+        self.run_until_stalled();
+        while let Some(deadline) = self.clock.next_deadline()
+            && deadline <= target
+        {
+            self.clock.advance_to(deadline);
+            self.run_until_stalled();
+        }
+        self.clock.advance_to(target);
+        self.run_until_stalled();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::FutureExt;
+
+    use super::*;
+    use crate::timer::Delay;
+
+    #[test]
+    fn delays_follow_simulated_clock() {
+        let sim = Simulation::new();
+        let fired = Arc::new(Mutex::new(Vec::new()));
+        for (task, secs) in [(0, 3), (1, 1), (2, 2)] {
+            let fired = fired.clone();
+            sim.executor().exec(
+                async move {
+                    Delay::new(Duration::from_secs(secs)).await;
+                    fired.lock().unwrap().push(task);
+                }
+                .boxed(),
+            );
+        }
+        assert_eq!(sim.run_until_stalled(), 3);
+        assert!(fired.lock().unwrap().is_empty());
+
+        sim.advance(Duration::from_secs(1));
+        assert_eq!(*fired.lock().unwrap(), [1]);
+        // 同一次推进中按到期顺序运行
+        sim.advance(Duration::from_secs(5));
+        assert_eq!(*fired.lock().unwrap(), [1, 2, 0]);
+        assert_eq!(sim.clock().now(), Duration::from_secs(6));
+        assert_eq!(sim.run_until_stalled(), 0);
+    }
+}
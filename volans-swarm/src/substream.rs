@@ -1,6 +1,10 @@
-use volans_core::{Negotiated, muxing::SubstreamBox};
 use either::Either;
-use futures::{AsyncRead, AsyncWrite};
+use futures::{AsyncRead, AsyncWrite, FutureExt};
+use volans_core::{Negotiated, muxing::SubstreamBox};
+
+use crate::{
+    bandwidth::Counters, connection::NegotiationCache, error::SubstreamIdleTimeout, timer::Delay,
+};
 
 use std::{
     fmt,
@@ -9,6 +13,7 @@ use std::{
     pin::Pin,
     sync::Arc,
     task::{Context, Poll},
+    time::Duration,
 };
 
 #[derive(Debug, Clone)]
@@ -28,19 +33,93 @@ impl ActiveStreamCounter {
 pub struct Substream {
     stream: Negotiated<SubstreamBox>,
     counter: Option<ActiveStreamCounter>,
+    /// 所属协议的流量计数
+    bytes: Arc<Counters>,
+    /// 借助协商缓存省略确认的子流，读取出错时使缓存失效
+    unconfirmed: Option<(NegotiationCache, String)>,
+    idle: Option<IdleTimeout>,
+}
+
+struct IdleTimeout {
+    timeout: Duration,
+    delay: Delay,
+}
+
+impl fmt::Debug for IdleTimeout {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("IdleTimeout")
+            .field("timeout", &self.timeout)
+            .finish_non_exhaustive()
+    }
 }
 
 impl Substream {
-    pub(crate) fn new(stream: Negotiated<SubstreamBox>, counter: ActiveStreamCounter) -> Self {
+    pub(crate) fn new(
+        stream: Negotiated<SubstreamBox>,
+        counter: ActiveStreamCounter,
+        bytes: Arc<Counters>,
+    ) -> Self {
         Self {
             stream,
             counter: Some(counter),
+            bytes,
+            unconfirmed: None,
+            idle: None,
+        }
+    }
+
+    pub(crate) fn with_unconfirmed(mut self, cache: NegotiationCache, protocol: String) -> Self {
+        self.unconfirmed = Some((cache, protocol));
+        self
+    }
+
+    fn on_read_error(&mut self) {
+        if let Some((cache, protocol)) = self.unconfirmed.take() {
+            tracing::debug!(%protocol, "Read failed, invalidating negotiation cache");
+            cache.remove(&protocol);
         }
     }
 
     pub fn ignore_for_keep_alive(&mut self) {
         self.counter.take();
     }
+
+    /// 读写在 `timeout` 内都没有进展时返回 [`SubstreamIdleTimeout`] 错误
+    ///
+    /// 只影响当前子流，连接保持不变；计时从设置时开始。
+    pub fn set_idle_timeout(&mut self, timeout: Duration) {
+        self.idle = Some(IdleTimeout {
+            timeout,
+            delay: Delay::new(timeout),
+        });
+    }
+
+    pub fn with_idle_timeout(mut self, timeout: Duration) -> Self {
+        self.set_idle_timeout(timeout);
+        self
+    }
+
+    /// 有进展时重新计时，等待期间检查是否已空闲超时
+    fn poll_idle<T>(
+        &mut self,
+        cx: &mut Context<'_>,
+        result: Poll<io::Result<T>>,
+    ) -> Poll<io::Result<T>> {
+        let Some(idle) = self.idle.as_mut() else {
+            return result;
+        };
+        match result {
+            Poll::Ready(Ok(_)) => idle.delay.reset(idle.timeout),
+            Poll::Pending if idle.delay.poll_unpin(cx).is_ready() => {
+                return Poll::Ready(Err(io::Error::new(
+                    io::ErrorKind::TimedOut,
+                    SubstreamIdleTimeout,
+                )));
+            }
+            _ => {}
+        }
+        result
+    }
 }
 
 impl AsyncRead for Substream {
@@ -49,7 +128,15 @@ impl AsyncRead for Substream {
         cx: &mut Context<'_>,
         buf: &mut [u8],
     ) -> Poll<io::Result<usize>> {
-        Pin::new(&mut self.get_mut().stream).poll_read(cx, buf)
+        let this = self.get_mut();
+        let result = Pin::new(&mut this.stream).poll_read(cx, buf);
+        let result = this.poll_idle(cx, result);
+        match &result {
+            Poll::Ready(Ok(n)) => this.bytes.record_inbound(*n),
+            Poll::Ready(Err(e)) if !SubstreamIdleTimeout::is(e) => this.on_read_error(),
+            Poll::Ready(Err(_)) | Poll::Pending => {}
+        }
+        result
     }
 
     fn poll_read_vectored(
@@ -57,7 +144,15 @@ impl AsyncRead for Substream {
         cx: &mut Context<'_>,
         bufs: &mut [io::IoSliceMut<'_>],
     ) -> Poll<io::Result<usize>> {
-        Pin::new(&mut self.get_mut().stream).poll_read_vectored(cx, bufs)
+        let this = self.get_mut();
+        let result = Pin::new(&mut this.stream).poll_read_vectored(cx, bufs);
+        let result = this.poll_idle(cx, result);
+        match &result {
+            Poll::Ready(Ok(n)) => this.bytes.record_inbound(*n),
+            Poll::Ready(Err(e)) if !SubstreamIdleTimeout::is(e) => this.on_read_error(),
+            Poll::Ready(Err(_)) | Poll::Pending => {}
+        }
+        result
     }
 }
 
@@ -67,7 +162,13 @@ impl AsyncWrite for Substream {
         cx: &mut Context<'_>,
         buf: &[u8],
     ) -> Poll<std::io::Result<usize>> {
-        Pin::new(&mut self.get_mut().stream).poll_write(cx, buf)
+        let this = self.get_mut();
+        let result = Pin::new(&mut this.stream).poll_write(cx, buf);
+        let result = this.poll_idle(cx, result);
+        if let Poll::Ready(Ok(n)) = result {
+            this.bytes.record_outbound(n);
+        }
+        result
     }
 
     fn poll_write_vectored(
@@ -75,11 +176,19 @@ impl AsyncWrite for Substream {
         cx: &mut Context<'_>,
         bufs: &[io::IoSlice<'_>],
     ) -> Poll<io::Result<usize>> {
-        Pin::new(&mut self.get_mut().stream).poll_write_vectored(cx, bufs)
+        let this = self.get_mut();
+        let result = Pin::new(&mut this.stream).poll_write_vectored(cx, bufs);
+        let result = this.poll_idle(cx, result);
+        if let Poll::Ready(Ok(n)) = result {
+            this.bytes.record_outbound(n);
+        }
+        result
     }
 
     fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
-        Pin::new(&mut self.get_mut().stream).poll_flush(cx)
+        let this = self.get_mut();
+        let result = Pin::new(&mut this.stream).poll_flush(cx);
+        this.poll_idle(cx, result)
     }
 
     fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
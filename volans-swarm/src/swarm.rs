@@ -0,0 +1,214 @@
+//! 客户端、服务端与双向 Swarm 共用的状态
+//!
+//! 拨号相关的处理在 [`dial`] 中，要求行为实现 `NetworkOutgoingBehavior`；监听相关的处理在
+//! [`listen`] 中，要求行为实现 `NetworkIncomingBehavior`。各 Swarm 只负责轮询顺序与命令。
+
+mod dial;
+mod listen;
+
+use std::{
+    collections::VecDeque,
+    task::{Context, Poll},
+};
+
+use volans_core::{Multiaddr, PeerId, muxing::StreamMuxerBox, transport};
+
+use crate::{
+    BehaviorEvent, ConnectionId, NetworkBehavior, PendingHandlerAction, SwarmEvent, THandlerAction,
+    THandlerEvent,
+    behavior::CloseConnection,
+    connection::{Pool, PoolConfig},
+    notify_pending,
+    observer::{Observers, SwarmObserver},
+    replay::Replay,
+};
+
+pub(crate) use dial::DialState;
+pub(crate) use listen::ListenState;
+
+pub(crate) struct SwarmCore<TBehavior>
+where
+    TBehavior: NetworkBehavior,
+{
+    pub(crate) behavior: TBehavior,
+    transport: transport::Boxed<(PeerId, StreamMuxerBox)>,
+    pub(crate) pool: Pool<TBehavior::ConnectionHandler>,
+    /// 等待Handler操作
+    pending_handler_action: Option<PendingHandlerAction<THandlerAction<TBehavior>>>,
+
+    /// Swarm 等待处理的事件
+    pending_swarm_events: VecDeque<SwarmEvent<TBehavior::Event>>,
+
+    pub(crate) observers: Observers<TBehavior::Event>,
+    /// 订阅者与回放缓冲，首次订阅时创建
+    replay: Option<Replay<TBehavior::Event>>,
+
+    pub(crate) dial: DialState,
+    pub(crate) listen: ListenState,
+
+    /// 是否正在关闭，关闭后不再建立新的连接
+    closing: bool,
+}
+
+/// 行为上报的地址变化，交由具备相应能力的 Swarm 处理
+pub(crate) enum AddressEvent {
+    /// 对端的可拨号地址，只有拨号的 Swarm 记录
+    PeerAddress { peer_id: PeerId, addr: Multiaddr },
+    /// 其他节点观察到的本地地址，外部地址只有接受连接的 Swarm 处理
+    ExternalCandidate { peer_id: PeerId, addr: Multiaddr },
+    /// 外部地址被确认可达
+    ExternalConfirmed(Multiaddr),
+    /// 外部地址被移除
+    ExternalExpired(Multiaddr),
+}
+
+impl<TBehavior> SwarmCore<TBehavior>
+where
+    TBehavior: NetworkBehavior,
+{
+    pub(crate) fn new(
+        transport: transport::Boxed<(PeerId, StreamMuxerBox)>,
+        behavior: TBehavior,
+        local_peer_id: PeerId,
+        config: PoolConfig,
+    ) -> Self {
+        Self {
+            behavior,
+            transport,
+            pool: Pool::new(local_peer_id, config),
+            pending_handler_action: None,
+            pending_swarm_events: VecDeque::new(),
+            observers: Observers::default(),
+            replay: None,
+            dial: DialState::default(),
+            listen: ListenState::default(),
+            closing: false,
+        }
+    }
+
+    pub(crate) fn close_connection(&mut self, connection_id: ConnectionId) -> bool {
+        if let Some(established) = self.pool.get_established(connection_id) {
+            established.start_close();
+            return true;
+        }
+        false
+    }
+
+    /// 拒绝新的连接，中断等待中的连接并关闭所有已建立的连接
+    pub(crate) fn start_close(&mut self) {
+        self.closing = true;
+        self.pool.close_all();
+    }
+
+    /// 所有监听器与连接任务都已结束，且事件已取完
+    pub(crate) fn is_closed(&self) -> bool {
+        self.pool.is_empty() && self.listen.is_empty() && self.pending_swarm_events.is_empty()
+    }
+
+    pub(crate) fn next_event(&mut self) -> Option<SwarmEvent<TBehavior::Event>> {
+        self.pending_swarm_events.pop_front()
+    }
+
+    /// 投递等待中的动作，全部投递后轮询行为
+    ///
+    /// 地址相关的事件交还调用方处理。
+    pub(crate) fn poll_behavior(&mut self, cx: &mut Context<'_>) -> Poll<Option<AddressEvent>> {
+        match self.pending_handler_action.take() {
+            Some(pending) => match notify_pending::<TBehavior>(pending, &mut self.pool, cx) {
+                // 写回仍未投递的部分
+                Some(pending) => {
+                    self.pending_handler_action = Some(pending);
+                    Poll::Pending
+                }
+                None => Poll::Ready(None),
+            },
+            None => match self.behavior.poll(cx) {
+                Poll::Pending => Poll::Pending,
+                Poll::Ready(event) => Poll::Ready(self.handle_behavior_event(event)),
+            },
+        }
+    }
+
+    fn handle_behavior_event(
+        &mut self,
+        event: BehaviorEvent<TBehavior::Event, THandlerAction<TBehavior>>,
+    ) -> Option<AddressEvent> {
+        match event {
+            BehaviorEvent::Behavior(event) => {
+                self.observers.on_behavior_event(&event);
+                self.pending_swarm_events
+                    .push_back(SwarmEvent::Behavior(event));
+            }
+            BehaviorEvent::HandlerAction {
+                peer_id,
+                handler,
+                action,
+            } => {
+                assert!(
+                    self.pending_handler_action.is_none(),
+                    "Pending handler action already exists"
+                );
+                self.pending_handler_action = Some(PendingHandlerAction::new(
+                    &peer_id,
+                    handler,
+                    action,
+                    &mut self.pool,
+                ));
+            }
+            BehaviorEvent::CloseConnection {
+                peer_id,
+                connection,
+            } => match connection {
+                CloseConnection::One(id) => {
+                    if let Some(connection) = self.pool.get_established(id) {
+                        connection.start_close();
+                    } else {
+                        tracing::debug!(
+                            id = ?id,
+                            peer_id = ?peer_id,
+                            "Attempted to close non-existent connection"
+                        );
+                    }
+                }
+                CloseConnection::All => self.pool.disconnect(&peer_id),
+            },
+            BehaviorEvent::NewPeerAddress { peer_id, addr } => {
+                return Some(AddressEvent::PeerAddress { peer_id, addr });
+            }
+            BehaviorEvent::NewExternalAddrCandidate { peer_id, addr } => {
+                return Some(AddressEvent::ExternalCandidate { peer_id, addr });
+            }
+            BehaviorEvent::ExternalAddrConfirmed { addr } => {
+                return Some(AddressEvent::ExternalConfirmed(addr));
+            }
+            BehaviorEvent::ExternalAddrExpired { addr } => {
+                return Some(AddressEvent::ExternalExpired(addr));
+            }
+        }
+        None
+    }
+
+    fn on_connection_event(
+        &mut self,
+        id: ConnectionId,
+        peer_id: PeerId,
+        event: THandlerEvent<TBehavior>,
+    ) {
+        self.behavior
+            .on_connection_handler_event(id, peer_id, event);
+    }
+}
+
+impl<TBehavior> SwarmCore<TBehavior>
+where
+    TBehavior: NetworkBehavior,
+    TBehavior::Event: Clone + Send + 'static,
+{
+    pub(crate) fn replay(&mut self) -> &Replay<TBehavior::Event> {
+        self.replay.get_or_insert_with(|| {
+            let replay = Replay::new();
+            self.observers.push(Box::new(replay.clone()));
+            replay
+        })
+    }
+}
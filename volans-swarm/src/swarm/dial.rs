@@ -0,0 +1,510 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    task::{Context, Poll},
+};
+
+use futures::FutureExt;
+use volans_core::{ConnectedPoint, Multiaddr, PeerId, Transport, transport::dns};
+
+use crate::{
+    ConnectionId, DialOpts, NetworkOutgoingBehavior, OutboundStreamHandler, PeerCondition,
+    PeerStore, SwarmEvent, THandlerEvent, connection::PoolEvent, error::DialError,
+    observer::SwarmObserver, timer::Delay,
+};
+
+use super::SwarmCore;
+
+/// 拨号相关的状态
+#[derive(Default)]
+pub(crate) struct DialState {
+    /// 已知节点的地址，未指定地址的拨号从中查找
+    pub(crate) peer_store: PeerStore,
+    /// 配置了重试策略且正在拨号的连接
+    attempts: HashMap<ConnectionId, DialAttempt>,
+    /// 等待退避结束后重新拨号
+    pending_retries: Vec<(Delay, DialAttempt)>,
+    /// 超出拨号并发上限、等待发起的拨号及其已进行的尝试次数，行为拨号为 0
+    queued: VecDeque<(DialOpts, u32)>,
+}
+
+struct DialAttempt {
+    opts: DialOpts,
+    /// 已进行的尝试次数，从 1 开始
+    attempt: u32,
+}
+
+impl<TBehavior> SwarmCore<TBehavior>
+where
+    TBehavior: NetworkOutgoingBehavior,
+    TBehavior::ConnectionHandler: OutboundStreamHandler,
+{
+    pub(crate) fn dial(&mut self, opts: DialOpts) -> Result<Multiaddr, DialError> {
+        match self.start_dial(&opts) {
+            Ok(addr) => {
+                if opts.retry_policy().is_some() {
+                    self.dial
+                        .attempts
+                        .insert(opts.connection_id(), DialAttempt { opts, attempt: 1 });
+                }
+                Ok(addr)
+            }
+            Err(err) => {
+                let addr = dial_error_addr(&err).or(opts.addr());
+                self.notify_dial_failure(opts.connection_id(), opts.peer_id(), addr.as_ref(), &err);
+                Err(err)
+            }
+        }
+    }
+
+    pub(crate) fn abort_dial(&mut self, connection_id: ConnectionId) -> bool {
+        self.dial.attempts.remove(&connection_id);
+        if self.pool.abort_dial(connection_id) {
+            return true;
+        }
+        let opts = if let Some(index) = self
+            .dial
+            .pending_retries
+            .iter()
+            .position(|(_, attempt)| attempt.opts.connection_id() == connection_id)
+        {
+            self.dial.pending_retries.swap_remove(index).1.opts
+        } else if let Some(index) = self
+            .dial
+            .queued
+            .iter()
+            .position(|(opts, _)| opts.connection_id() == connection_id)
+        {
+            self.dial
+                .queued
+                .remove(index)
+                .expect("index is in bounds")
+                .0
+        } else {
+            return false;
+        };
+        let addr = opts.addr();
+        self.notify_dial_failure(
+            connection_id,
+            opts.peer_id(),
+            addr.as_ref(),
+            &DialError::Aborted,
+        );
+        self.pending_swarm_events
+            .push_back(SwarmEvent::OutgoingConnectionError {
+                peer_id: opts.peer_id(),
+                connection_id,
+                addr,
+                error: DialError::Aborted,
+            });
+        true
+    }
+
+    /// 关闭时放弃所有等待重试与排队中的拨号
+    pub(crate) fn abort_pending_dials(&mut self) {
+        for (_, attempt) in std::mem::take(&mut self.dial.pending_retries) {
+            self.give_up_dial(attempt, DialError::Closing);
+        }
+        for (opts, attempt) in std::mem::take(&mut self.dial.queued) {
+            if attempt > 0 {
+                self.give_up_dial(DialAttempt { opts, attempt }, DialError::Closing);
+                continue;
+            }
+            let addr = opts.addr();
+            self.notify_dial_failure(
+                opts.connection_id(),
+                opts.peer_id(),
+                addr.as_ref(),
+                &DialError::Closing,
+            );
+        }
+    }
+
+    fn start_dial(&mut self, opts: &DialOpts) -> Result<Multiaddr, DialError> {
+        let peer_id = opts.peer_id();
+        let condition = opts.condition();
+        let connection_id = opts.connection_id();
+
+        if self.closing {
+            return Err(DialError::Closing);
+        }
+
+        // 未指定地址时依次尝试地址簿中记录的地址
+        let (addr, fallback_addrs) = match (opts.addr(), peer_id) {
+            (None, Some(peer_id)) => {
+                let mut known = self.dial.peer_store.addresses(&peer_id).cloned();
+                (known.next(), known.collect())
+            }
+            (addr, _) => (addr, opts.fallback_addrs().to_vec()),
+        };
+
+        // 是否可以建立连接
+        let should_dial = match (condition, peer_id) {
+            (_, None) => true,
+            (PeerCondition::Always, _) => true,
+            (PeerCondition::Disconnected, Some(ref peer_id)) => {
+                !self.pool.is_peer_connected(peer_id)
+            }
+            (PeerCondition::NotDialing, Some(ref peer_id)) => !self.pool.is_peer_dialing(peer_id),
+            (PeerCondition::DisconnectedAndNotDialing, Some(ref peer_id)) => {
+                !self.pool.is_peer_dialing(peer_id) && !self.pool.is_peer_connected(peer_id)
+            }
+        };
+        if !should_dial {
+            return Err(DialError::PeerCondition(condition));
+        }
+
+        let addr = match self
+            .behavior
+            .handle_pending_connection(connection_id, peer_id, &addr)
+        {
+            Ok(Some(addr)) => addr,
+            Ok(None) => return Err(DialError::NoAddress),
+            Err(cause) => return Err(DialError::Denied { cause }),
+        };
+
+        // 行为确认的地址优先，其余候选地址作为后备
+        let fallback_addrs: Vec<_> = fallback_addrs
+            .into_iter()
+            .filter(|fallback| *fallback != addr)
+            .collect();
+        if !fallback_addrs.is_empty() {
+            return self.start_dial_fallback(opts, addr, fallback_addrs);
+        }
+
+        // 1.开始执行Transport 连接，
+        let future = match dns::scoped(opts.resolver().cloned(), || {
+            self.transport.dial(addr.clone())
+        }) {
+            Ok(dial) => dial,
+            Err(error) => return Err(DialError::Transport { addr, error }),
+        };
+        // 2.加入Connection Pool
+        self.pool
+            .add_outgoing(connection_id, future, addr.clone(), peer_id);
+        Ok(addr)
+    }
+
+    fn start_dial_fallback(
+        &mut self,
+        opts: &DialOpts,
+        addr: Multiaddr,
+        fallback_addrs: Vec<Multiaddr>,
+    ) -> Result<Multiaddr, DialError> {
+        let mut attempts = Vec::new();
+        let mut failed = Vec::new();
+        for addr in std::iter::once(addr).chain(fallback_addrs) {
+            match dns::scoped(opts.resolver().cloned(), || {
+                self.transport.dial(addr.clone())
+            }) {
+                Ok(dial) => attempts.push((addr, dial)),
+                Err(error) => failed.push((addr, error)),
+            }
+        }
+        let Some((addr, _)) = attempts.first() else {
+            return Err(DialError::AllAttemptsFailed { errors: failed });
+        };
+        let addr = addr.clone();
+        self.pool.add_outgoing_fallback(
+            opts.connection_id(),
+            attempts,
+            failed,
+            opts.strategy(),
+            opts.peer_id(),
+        );
+        Ok(addr)
+    }
+
+    /// 拨号失败后按重试策略安排下一次尝试，无需重试时交还错误
+    fn retry_dial(&mut self, id: ConnectionId, error: DialError) -> Result<(), DialError> {
+        let Some(attempt) = self.dial.attempts.remove(&id) else {
+            return Err(error);
+        };
+        let policy = attempt.opts.retry_policy().expect("retry policy is set");
+        if self.closing || !policy.should_retry(attempt.attempt, &error) {
+            self.give_up_dial(attempt, error);
+            return Ok(());
+        }
+        let delay = policy.backoff(attempt.attempt);
+        tracing::debug!(
+            connection_id = ?id,
+            attempt = attempt.attempt,
+            delay = ?delay,
+            "Dial failed, scheduling retry: {}",
+            error
+        );
+        self.pending_swarm_events
+            .push_back(SwarmEvent::DialRetryScheduled {
+                peer_id: attempt.opts.peer_id(),
+                connection_id: id,
+                attempt: attempt.attempt,
+                delay,
+                error,
+            });
+        self.dial.pending_retries.push((Delay::new(delay), attempt));
+        Ok(())
+    }
+
+    fn give_up_dial(&mut self, attempt: DialAttempt, error: DialError) {
+        let DialAttempt { opts, attempt } = attempt;
+        let addr = dial_error_addr(&error).or(opts.addr());
+        self.notify_dial_failure(opts.connection_id(), opts.peer_id(), addr.as_ref(), &error);
+        self.pending_swarm_events
+            .push_back(SwarmEvent::DialGivenUp {
+                peer_id: opts.peer_id(),
+                connection_id: opts.connection_id(),
+                addr,
+                attempts: attempt,
+                error,
+            });
+    }
+
+    /// 依次发起排队中、行为请求的与退避结束的拨号，返回是否有进展
+    pub(crate) fn poll_dials(&mut self, cx: &mut Context<'_>) -> bool {
+        if self.poll_queued_dials() {
+            return true;
+        }
+        // 超出拨号上限的拨号排队等待
+        if let Poll::Ready(opts) = self.behavior.poll_dial(cx) {
+            if self.pool.has_dial_capacity(opts.peer_id().as_ref()) {
+                self.dial_from_behavior(opts);
+            } else {
+                self.queue_dial(opts, 0);
+            }
+            return true;
+        }
+        self.poll_retries(cx)
+    }
+
+    /// 退避结束后重新拨号，超出拨号上限时排队等待
+    fn poll_retries(&mut self, cx: &mut Context<'_>) -> bool {
+        let mut progressed = false;
+        let mut index = 0;
+        while index < self.dial.pending_retries.len() {
+            if self.dial.pending_retries[index]
+                .0
+                .poll_unpin(cx)
+                .is_pending()
+            {
+                index += 1;
+                continue;
+            }
+            progressed = true;
+            let (_, DialAttempt { opts, attempt }) = self.dial.pending_retries.swap_remove(index);
+            if self.pool.has_dial_capacity(opts.peer_id().as_ref()) {
+                self.redial(opts, attempt);
+            } else {
+                self.queue_dial(opts, attempt);
+            }
+        }
+        progressed
+    }
+
+    /// 发起第 `attempt + 1` 次尝试，立即失败的尝试同样计入重试次数
+    fn redial(&mut self, opts: DialOpts, attempt: u32) {
+        let peer_id = opts.peer_id();
+        let connection_id = opts.connection_id();
+        let result = self.start_dial(&opts);
+        self.dial.attempts.insert(
+            connection_id,
+            DialAttempt {
+                opts,
+                attempt: attempt + 1,
+            },
+        );
+        match result {
+            Ok(addr) => self.pending_swarm_events.push_back(SwarmEvent::Dialing {
+                peer_id,
+                connection_id,
+                addr,
+            }),
+            Err(error) => {
+                let _ = self.retry_dial(connection_id, error);
+            }
+        }
+    }
+
+    fn dial_from_behavior(&mut self, opts: DialOpts) {
+        let peer_id = opts.peer_id();
+        let connection_id = opts.connection_id();
+        if let Ok(addr) = self.dial(opts) {
+            self.pending_swarm_events.push_back(SwarmEvent::Dialing {
+                peer_id,
+                connection_id,
+                addr,
+            });
+        }
+    }
+
+    fn queue_dial(&mut self, opts: DialOpts, attempt: u32) {
+        let peer_id = opts.peer_id();
+        let connection_id = opts.connection_id();
+        tracing::debug!(
+            connection_id = ?connection_id,
+            peer_id = ?peer_id,
+            queued = self.dial.queued.len() + 1,
+            "Dial concurrency limit reached, queueing dial"
+        );
+        self.dial.queued.push_back((opts, attempt));
+        self.pending_swarm_events.push_back(SwarmEvent::DialQueued {
+            peer_id,
+            connection_id,
+            queued: self.dial.queued.len(),
+        });
+    }
+
+    /// 按排队顺序发起有余量的拨号，受对端上限阻塞的拨号不影响其他对端
+    fn poll_queued_dials(&mut self) -> bool {
+        let mut progressed = false;
+        let mut index = 0;
+        while index < self.dial.queued.len() && self.pool.has_dial_capacity(None) {
+            if !self
+                .pool
+                .has_dial_capacity(self.dial.queued[index].0.peer_id().as_ref())
+            {
+                index += 1;
+                continue;
+            }
+            let (opts, attempt) = self.dial.queued.remove(index).expect("index is in bounds");
+            match attempt {
+                0 => self.dial_from_behavior(opts),
+                attempt => self.redial(opts, attempt),
+            }
+            progressed = true;
+        }
+        progressed
+    }
+
+    fn notify_dial_failure(
+        &mut self,
+        id: ConnectionId,
+        peer_id: Option<PeerId>,
+        addr: Option<&Multiaddr>,
+        error: &DialError,
+    ) {
+        self.behavior.on_dial_failure(id, peer_id, addr, error);
+        self.observers.on_dial_failure(id, peer_id, addr, error);
+    }
+
+    /// 处理拨出连接的连接池事件
+    pub(crate) fn handle_dialer_pool_event(&mut self, event: PoolEvent<THandlerEvent<TBehavior>>) {
+        match event {
+            PoolEvent::ConnectionEstablished {
+                id,
+                peer_id,
+                endpoint,
+                connection,
+                established_in,
+            } => {
+                self.dial.attempts.remove(&id);
+                let ConnectedPoint::Dialer { addr } = &endpoint else {
+                    unreachable!("Listener connections should not be handled here")
+                };
+                let handler = match self.closing {
+                    // 关闭期间完成握手的连接直接丢弃
+                    true => Err(DialError::Closing),
+                    false => self
+                        .behavior
+                        .handle_established_connection(id, peer_id, addr)
+                        .map_err(|cause| DialError::Denied { cause }),
+                };
+                let handler = match handler {
+                    Ok(handler) => handler,
+                    Err(dial_error) => {
+                        self.notify_dial_failure(id, Some(peer_id), Some(addr), &dial_error);
+                        self.pending_swarm_events
+                            .push_back(SwarmEvent::OutgoingConnectionError {
+                                peer_id: Some(peer_id),
+                                connection_id: id,
+                                addr: Some(addr.clone()),
+                                error: dial_error,
+                            });
+                        return;
+                    }
+                };
+
+                let num_established = self.pool.num_peer_established(&peer_id);
+                self.pool.spawn_outbound_connection(
+                    id,
+                    peer_id,
+                    endpoint.clone(),
+                    connection,
+                    handler,
+                );
+                tracing::debug!(
+                    peer=%peer_id,
+                    addr=%addr,
+                    total_peers=%num_established,
+                    "Connection outbound established"
+                );
+                self.dial.peer_store.add_address(peer_id, addr.clone());
+                self.behavior.on_connection_established(id, peer_id, addr);
+                self.observers
+                    .on_connection_established(id, peer_id, &endpoint);
+                self.pending_swarm_events
+                    .push_back(SwarmEvent::ConnectionEstablished {
+                        connection_id: id,
+                        peer_id,
+                        endpoint,
+                        established_in,
+                        num_established,
+                    });
+            }
+            PoolEvent::PendingConnectionError {
+                id,
+                peer_id,
+                endpoint,
+                error,
+            } => {
+                let ConnectedPoint::Dialer { addr } = endpoint else {
+                    unreachable!("Listener connections should not be handled here")
+                };
+                let Err(dial_error) = self.retry_dial(id, DialError::from(error)) else {
+                    return;
+                };
+                self.notify_dial_failure(id, peer_id, Some(&addr), &dial_error);
+                self.pending_swarm_events
+                    .push_back(SwarmEvent::OutgoingConnectionError {
+                        peer_id,
+                        connection_id: id,
+                        addr: Some(addr),
+                        error: dial_error,
+                    });
+            }
+            PoolEvent::ConnectionClosed {
+                id,
+                peer_id,
+                endpoint,
+                num_remaining_established,
+                reason,
+                bandwidth,
+            } => {
+                let ConnectedPoint::Dialer { addr } = &endpoint else {
+                    unreachable!("Listener connections should not be handled here")
+                };
+                self.observers
+                    .on_connection_closed(id, peer_id, &endpoint, &reason);
+                self.behavior
+                    .on_connection_closed(id, peer_id, addr, &reason);
+                self.pending_swarm_events
+                    .push_back(SwarmEvent::ConnectionClosed {
+                        connection_id: id,
+                        peer_id,
+                        endpoint,
+                        num_remaining_established,
+                        reason,
+                        bandwidth,
+                    });
+            }
+            PoolEvent::ConnectionEvent { id, peer_id, event } => {
+                self.on_connection_event(id, peer_id, event);
+            }
+        }
+    }
+}
+
+fn dial_error_addr(error: &DialError) -> Option<Multiaddr> {
+    match error {
+        DialError::Transport { addr, .. } => Some(addr.clone()),
+        _ => None,
+    }
+}
@@ -0,0 +1,473 @@
+use std::{
+    collections::HashMap,
+    convert::Infallible,
+    io,
+    sync::Arc,
+    task::{Context, Poll},
+};
+
+use futures::{
+    StreamExt,
+    channel::oneshot,
+    stream::{Fuse, SelectAll},
+};
+use smallvec::SmallVec;
+use volans_core::{ConnectedPoint, Multiaddr, Transport, TransportError, transport};
+
+use crate::{
+    ConnectionId, ExternalAddrStore, InboundStreamHandler, ListenOpts, ListenerEvent, ListenerId,
+    NetworkIncomingBehavior, PendingConnectionFilter, SwarmEvent, THandlerEvent,
+    behavior::{
+        ExpiredListenAddr, ExternalAddrConfirmed, ExternalAddrExpired, ListenerClosed,
+        ListenerError, NewListenAddr, NewListener,
+    },
+    connection::PoolEvent,
+    error::ListenError,
+    listener,
+    observer::SwarmObserver,
+};
+
+use super::{AddressEvent, SwarmCore};
+
+/// 监听相关的状态
+#[derive(Default)]
+pub(crate) struct ListenState {
+    listeners: SelectAll<Fuse<listener::TaggedListener>>,
+    aborts: HashMap<ListenerId, oneshot::Sender<Infallible>>,
+    pauses: HashMap<ListenerId, Arc<listener::ListenerPause>>,
+    addresses: HashMap<ListenerId, SmallVec<[Multiaddr; 1]>>,
+    /// 其他节点观察到的本地地址
+    pub(crate) external_addrs: ExternalAddrStore,
+
+    /// 等待中的连接达到该数量时暂停所有监听器
+    pub(crate) pending_incoming_high_water_mark: Option<usize>,
+    /// 监听器是否因等待中的连接过多而暂停
+    throttled: bool,
+
+    /// 启动握手前过滤入站连接
+    pub(crate) pending_connection_filter: Option<Box<dyn PendingConnectionFilter>>,
+}
+
+impl ListenState {
+    pub(crate) fn is_empty(&self) -> bool {
+        self.listeners.is_empty()
+    }
+
+    /// 所有监听的地址
+    pub(crate) fn addresses(&self) -> impl Iterator<Item = &Multiaddr> {
+        self.addresses.values().flatten()
+    }
+}
+
+impl<TBehavior> SwarmCore<TBehavior>
+where
+    TBehavior: NetworkIncomingBehavior,
+    TBehavior::ConnectionHandler: InboundStreamHandler,
+{
+    pub(crate) fn listen_with_opts(
+        &mut self,
+        opts: ListenOpts,
+    ) -> Result<ListenerId, TransportError<io::Error>> {
+        let listener_id = opts.listener_id();
+        match self
+            .transport
+            .listen(opts.addr().clone(), opts.socket_options())
+        {
+            Ok(listener) => {
+                let (close_tx, close_rx) = oneshot::channel();
+                let tagged_listener =
+                    listener::TaggedListener::new(listener_id, listener, close_rx);
+                let pause = tagged_listener.pause_handle();
+                pause.set_throttled(self.listen.throttled);
+                self.listen.pauses.insert(listener_id, pause);
+                self.listen.listeners.push(tagged_listener.fuse());
+                self.listen.aborts.insert(listener_id, close_tx);
+            }
+            Err(error) => {
+                self.notify_listener_event(ListenerEvent::ListenerError(ListenerError {
+                    listener_id,
+                    error: &error,
+                }));
+                return Err(error);
+            }
+        }
+        self.notify_listener_event(ListenerEvent::NewListener(NewListener { listener_id }));
+        Ok(listener_id)
+    }
+
+    pub(crate) fn remove_listener(&mut self, listener_id: ListenerId) -> bool {
+        self.listen.pauses.remove(&listener_id);
+        // Drop 掉 close_sender 以触发监听器关闭
+        self.listen.aborts.remove(&listener_id).is_some()
+    }
+
+    /// 关闭时移除所有监听器
+    pub(crate) fn remove_listeners(&mut self) {
+        self.listen.aborts.clear();
+        self.listen.pauses.clear();
+    }
+
+    pub(crate) fn set_listener_paused(&mut self, listener_id: ListenerId, paused: bool) -> bool {
+        match self.listen.pauses.get(&listener_id) {
+            Some(pause) => {
+                pause.set_paused(paused);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// 按等待中的连接数量暂停或恢复所有监听器
+    fn update_throttle(&mut self) {
+        let throttled = self
+            .listen
+            .pending_incoming_high_water_mark
+            .is_some_and(|mark| self.pool.num_pending() >= mark);
+        if throttled == self.listen.throttled {
+            return;
+        }
+        tracing::debug!(
+            pending = self.pool.num_pending(),
+            throttled,
+            "Pending incoming high-water mark crossed"
+        );
+        self.listen.throttled = throttled;
+        for pause in self.listen.pauses.values() {
+            pause.set_throttled(throttled);
+        }
+    }
+
+    pub(crate) fn add_external_address(&mut self, addr: Multiaddr) {
+        if self.listen.external_addrs.add_confirmed(addr.clone()) {
+            self.confirm_external_addr(addr);
+        }
+    }
+
+    pub(crate) fn remove_external_address(&mut self, addr: &Multiaddr) -> bool {
+        if !self.listen.external_addrs.remove(addr) {
+            return false;
+        }
+        tracing::debug!(%addr, "External address removed");
+        self.notify_listener_event(ListenerEvent::ExternalAddrExpired(ExternalAddrExpired {
+            addr,
+        }));
+        self.pending_swarm_events
+            .push_back(SwarmEvent::ExternalAddrExpired { addr: addr.clone() });
+        true
+    }
+
+    /// 处理外部地址变化，对端地址由拨号的 Swarm 记录
+    pub(crate) fn handle_external_addr_event(&mut self, event: AddressEvent) {
+        match event {
+            AddressEvent::ExternalCandidate { peer_id, addr } => {
+                if self
+                    .listen
+                    .external_addrs
+                    .add_candidate(peer_id, addr.clone())
+                {
+                    self.confirm_external_addr(addr);
+                }
+            }
+            AddressEvent::ExternalConfirmed(addr) => self.add_external_address(addr),
+            AddressEvent::ExternalExpired(addr) => {
+                self.remove_external_address(&addr);
+            }
+            AddressEvent::PeerAddress { .. } => {}
+        }
+    }
+
+    fn confirm_external_addr(&mut self, addr: Multiaddr) {
+        tracing::debug!(%addr, "External address confirmed");
+        self.notify_listener_event(ListenerEvent::ExternalAddrConfirmed(
+            ExternalAddrConfirmed { addr: &addr },
+        ));
+        self.pending_swarm_events
+            .push_back(SwarmEvent::ExternalAddrConfirmed { addr });
+    }
+
+    fn notify_listener_event(&mut self, event: ListenerEvent<'_>) {
+        self.behavior.on_listener_event(event);
+        self.observers.on_listener_event(event);
+    }
+
+    /// 轮询监听器，返回是否有进展
+    pub(crate) fn poll_listeners(&mut self, cx: &mut Context<'_>) -> bool {
+        self.update_throttle();
+        match self.listen.listeners.poll_next_unpin(cx) {
+            Poll::Ready(Some((id, event))) => {
+                self.handle_listener_event(id, event);
+                true
+            }
+            Poll::Ready(None) | Poll::Pending => false,
+        }
+    }
+
+    fn handle_listener_event(
+        &mut self,
+        listener_id: ListenerId,
+        event: listener::BoxedListenerEvent,
+    ) {
+        match event {
+            transport::ListenerEvent::Incoming {
+                local_addr,
+                remote_addr,
+                upgrade,
+            } => {
+                let connection_id = ConnectionId::next();
+                let filtered = self
+                    .listen
+                    .pending_connection_filter
+                    .as_mut()
+                    .is_some_and(|filter| !filter.allow(&local_addr, &remote_addr));
+                let pending = if filtered {
+                    Err(ListenError::Filtered)
+                } else if self.pool.is_pending_incoming_full() {
+                    Err(ListenError::PendingLimitReached)
+                } else {
+                    self.behavior
+                        .handle_pending_connection(connection_id, &local_addr, &remote_addr)
+                        .map_err(|cause| ListenError::Denied { cause })
+                };
+                if let Err(listen_error) = pending {
+                    self.behavior.on_listen_failure(
+                        connection_id,
+                        None,
+                        &local_addr,
+                        &remote_addr,
+                        &listen_error,
+                    );
+                    self.pending_swarm_events
+                        .push_back(SwarmEvent::IncomingConnectionError {
+                            peer_id: None,
+                            connection_id,
+                            local_addr,
+                            remote_addr,
+                            error: listen_error,
+                        });
+                    return;
+                }
+                self.pool.add_incoming(
+                    connection_id,
+                    upgrade,
+                    local_addr.clone(),
+                    remote_addr.clone(),
+                );
+                self.pending_swarm_events
+                    .push_back(SwarmEvent::IncomingConnection {
+                        connection_id,
+                        local_addr,
+                        remote_addr,
+                    })
+            }
+            transport::ListenerEvent::Rebound(addr) => {
+                tracing::debug!(listener = ?listener_id, addr = %addr, "Listener rebound");
+                self.handle_listener_event(listener_id, transport::ListenerEvent::NewAddress(addr));
+            }
+            transport::ListenerEvent::NewAddress(addr) => {
+                tracing::debug!(listener = ?listener_id, addr = %addr, "Listener started");
+                let addresses = self.listen.addresses.entry(listener_id).or_default();
+                if !addresses.contains(&addr) {
+                    addresses.push(addr.clone());
+                }
+                self.notify_listener_event(ListenerEvent::NewListenAddr(NewListenAddr {
+                    listener_id,
+                    addr: &addr,
+                }));
+                self.pending_swarm_events
+                    .push_back(SwarmEvent::NewListenAddr { listener_id, addr });
+            }
+            transport::ListenerEvent::AddressExpired(addr) => {
+                if let Some(addresses) = self.listen.addresses.get_mut(&listener_id) {
+                    addresses.retain(|a| a != &addr);
+                }
+                self.notify_listener_event(ListenerEvent::ExpiredListenAddr(ExpiredListenAddr {
+                    listener_id,
+                    addr: &addr,
+                }));
+                self.pending_swarm_events
+                    .push_back(SwarmEvent::ExpiredListenAddr { listener_id, addr });
+            }
+            transport::ListenerEvent::Closed(reason) => {
+                tracing::debug!(
+                    listener=?listener_id,
+                    ?reason,
+                    "Listener closed"
+                );
+                self.listen.pauses.remove(&listener_id);
+                // 移除监听器的地址
+                let addresses = self
+                    .listen
+                    .addresses
+                    .remove(&listener_id)
+                    .unwrap_or_default();
+                for addr in addresses.iter() {
+                    // 通知行为层监听器地址过期
+                    self.notify_listener_event(ListenerEvent::ExpiredListenAddr(
+                        ExpiredListenAddr { listener_id, addr },
+                    ));
+                }
+                self.notify_listener_event(ListenerEvent::ListenerClosed(ListenerClosed {
+                    listener_id,
+                    reason: reason.as_ref().copied(),
+                }));
+                self.pending_swarm_events
+                    .push_back(SwarmEvent::ListenerClosed {
+                        listener_id,
+                        reason,
+                    });
+            }
+            transport::ListenerEvent::Error(error) => {
+                tracing::debug!(listener = ?listener_id, "Listener error");
+                self.notify_listener_event(ListenerEvent::ListenerError(ListenerError {
+                    listener_id,
+                    error: &error,
+                }));
+                self.pending_swarm_events
+                    .push_back(SwarmEvent::ListenerError { listener_id, error });
+            }
+        }
+    }
+
+    /// 处理接入连接的连接池事件
+    pub(crate) fn handle_listener_pool_event(
+        &mut self,
+        event: PoolEvent<THandlerEvent<TBehavior>>,
+    ) {
+        match event {
+            PoolEvent::ConnectionEstablished {
+                id,
+                peer_id,
+                endpoint,
+                connection,
+                established_in,
+            } => {
+                let ConnectedPoint::Listener {
+                    local_addr,
+                    remote_addr,
+                } = &endpoint
+                else {
+                    unreachable!("Dialer connections should not be handled here")
+                };
+                let handler = match self.closing {
+                    // 关闭期间完成握手的连接直接丢弃
+                    true => Err(ListenError::Closing),
+                    false => self
+                        .behavior
+                        .handle_established_connection(id, peer_id, local_addr, remote_addr)
+                        .map_err(|cause| ListenError::Denied { cause }),
+                };
+                let handler = match handler {
+                    Ok(handler) => handler,
+                    Err(listen_error) => {
+                        self.behavior.on_listen_failure(
+                            id,
+                            Some(peer_id),
+                            local_addr,
+                            remote_addr,
+                            &listen_error,
+                        );
+                        self.pending_swarm_events
+                            .push_back(SwarmEvent::IncomingConnectionError {
+                                peer_id: Some(peer_id),
+                                connection_id: id,
+                                local_addr: local_addr.clone(),
+                                remote_addr: remote_addr.clone(),
+                                error: listen_error,
+                            });
+                        return;
+                    }
+                };
+
+                let num_established = self.pool.num_peer_established(&peer_id);
+                self.pool.spawn_inbound_connection(
+                    id,
+                    peer_id,
+                    endpoint.clone(),
+                    connection,
+                    handler,
+                );
+                tracing::debug!(
+                    peer=%peer_id,
+                    local_addr=%local_addr,
+                    remote_addr=%remote_addr,
+                    total_peers=%num_established,
+                    "Connection inbound established"
+                );
+                self.behavior
+                    .on_connection_established(id, peer_id, local_addr, remote_addr);
+                self.observers
+                    .on_connection_established(id, peer_id, &endpoint);
+                self.pending_swarm_events
+                    .push_back(SwarmEvent::ConnectionEstablished {
+                        connection_id: id,
+                        peer_id,
+                        endpoint,
+                        established_in,
+                        num_established,
+                    });
+            }
+            PoolEvent::PendingConnectionError {
+                id,
+                peer_id,
+                endpoint,
+                error,
+            } => {
+                let ConnectedPoint::Listener {
+                    local_addr,
+                    remote_addr,
+                } = endpoint
+                else {
+                    unreachable!("Dialer connections should not be handled here")
+                };
+                let listen_error = ListenError::from(error);
+                self.behavior.on_listen_failure(
+                    id,
+                    peer_id,
+                    &local_addr,
+                    &remote_addr,
+                    &listen_error,
+                );
+                self.pending_swarm_events
+                    .push_back(SwarmEvent::IncomingConnectionError {
+                        peer_id,
+                        connection_id: id,
+                        local_addr,
+                        remote_addr,
+                        error: listen_error,
+                    });
+            }
+            PoolEvent::ConnectionClosed {
+                id,
+                peer_id,
+                endpoint,
+                num_remaining_established,
+                reason,
+                bandwidth,
+            } => {
+                let ConnectedPoint::Listener {
+                    local_addr,
+                    remote_addr,
+                } = &endpoint
+                else {
+                    unreachable!("Dialer connections should not be handled here")
+                };
+                self.observers
+                    .on_connection_closed(id, peer_id, &endpoint, &reason);
+                self.behavior
+                    .on_connection_closed(id, peer_id, local_addr, remote_addr, &reason);
+                self.pending_swarm_events
+                    .push_back(SwarmEvent::ConnectionClosed {
+                        connection_id: id,
+                        peer_id,
+                        endpoint,
+                        num_remaining_established,
+                        reason,
+                        bandwidth,
+                    });
+            }
+            PoolEvent::ConnectionEvent { id, peer_id, event } => {
+                self.on_connection_event(id, peer_id, event);
+            }
+        }
+    }
+}
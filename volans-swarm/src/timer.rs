@@ -0,0 +1,60 @@
+//! 可切换到模拟时钟的计时器
+
+use std::{
+    fmt,
+    pin::Pin,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use futures::FutureExt;
+
+use crate::sim::{Clock, SimDelay};
+
+/// 在 [`Simulation`](crate::sim::Simulation) 中创建时跟随模拟时钟，否则使用真实时间
+pub struct Delay(Inner);
+
+enum Inner {
+    Real(futures_timer::Delay),
+    Sim(SimDelay),
+}
+
+impl Delay {
+    pub fn new(duration: Duration) -> Self {
+        match Clock::current() {
+            Some(clock) => Delay(Inner::Sim(clock.delay(duration))),
+            None => Delay(Inner::Real(futures_timer::Delay::new(duration))),
+        }
+    }
+
+    /// 从现在起重新计时
+    pub fn reset(&mut self, duration: Duration) {
+        match &mut self.0 {
+            Inner::Real(delay) => delay.reset(duration),
+            Inner::Sim(delay) => delay.reset(duration),
+        }
+    }
+}
+
+impl fmt::Debug for Delay {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.0 {
+            Inner::Real(delay) => f.debug_tuple("Delay").field(delay).finish(),
+            Inner::Sim(delay) => f
+                .debug_struct("Delay")
+                .field("deadline", &delay.deadline)
+                .finish(),
+        }
+    }
+}
+
+impl Future for Delay {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        match &mut self.0 {
+            Inner::Real(delay) => delay.poll_unpin(cx),
+            Inner::Sim(delay) => delay.poll(cx),
+        }
+    }
+}
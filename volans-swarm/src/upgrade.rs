@@ -19,7 +19,7 @@ where
     type InfoIter = <T::InfoIter as IntoIterator>::IntoIter;
 
     fn protocol_info(&self) -> Self::InfoIter {
-        upgrade::UpgradeInfo::protocol_info(self).into_iter()
+        upgrade::UpgradeInfo::protocol_info(self)
     }
 }
 
@@ -0,0 +1,105 @@
+//! 按连接统计的流量
+
+use futures::{AsyncReadExt, AsyncWriteExt, StreamExt, channel::oneshot};
+use volans_core::identity::KeyPair;
+use volans_swarm::{StreamProtocol, client, server};
+use volans_swarm_test::{SwarmExt, connect, next_behavior_event, next_swarm_event, wait_for_event};
+
+fn identify(_: &KeyPair) -> volans_identify::Behavior {
+    volans_identify::Behavior::new(volans_identify::Config::new("/test/1.0.0"))
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn connection_bandwidth() {
+    let mut dialer = client::Swarm::new_ephemeral(identify);
+    let mut listener = server::Swarm::new_ephemeral(identify);
+    connect(&mut dialer, &mut listener).await;
+    tokio::spawn(async move {
+        loop {
+            listener.next().await;
+        }
+    });
+    // 身份信息交换之后双向都有流量
+    next_behavior_event(&mut dialer).await;
+
+    let connection_id = *dialer.connected_connections().next().unwrap();
+    let stats = dialer.bandwidth().connection(&connection_id).unwrap();
+    assert!(stats.inbound > 0);
+    assert!(stats.outbound > 0);
+    assert_eq!(dialer.bandwidth().total(), stats);
+
+    // 关闭事件带有连接的流量，关闭后仍计入累计值
+    assert!(dialer.close_connection(connection_id));
+    let closed = wait_for_event(&mut dialer, |event| match event {
+        client::SwarmEvent::ConnectionClosed {
+            connection_id: id,
+            bandwidth,
+            ..
+        } if id == connection_id => Some(bandwidth),
+        _ => None,
+    })
+    .await;
+    assert!(closed.inbound >= stats.inbound);
+    assert!(closed.outbound >= stats.outbound);
+    assert_eq!(dialer.bandwidth().connection(&connection_id), None);
+    assert_eq!(dialer.bandwidth().total(), closed);
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn stream_protocol_bandwidth() {
+    const BULK: StreamProtocol = StreamProtocol::new("/bulk/1.0.0");
+    const LEN: usize = 32 * 1024;
+
+    let mut dialer = client::Swarm::new_ephemeral(|_| volans_stream::client::Behavior::new());
+    let mut listener = server::Swarm::new_ephemeral(|_| volans_stream::server::Behavior::new());
+    connect(&mut dialer, &mut listener).await;
+
+    let listener_peer = *listener.local_peer_id();
+    let mut control = dialer.behavior().control();
+    let mut incoming = listener.behavior_mut().accept(BULK).unwrap();
+    tokio::spawn(async move {
+        loop {
+            listener.next().await;
+        }
+    });
+    let (done_sender, mut done) = oneshot::channel();
+    tokio::spawn(async move {
+        let (_, _, mut stream) = incoming.next().await.unwrap();
+        let mut buf = vec![0; LEN];
+        stream.read_exact(&mut buf).await.unwrap();
+        let _ = done_sender.send(());
+    });
+    tokio::spawn(async move {
+        let mut stream = control.open_stream(listener_peer, BULK).await.unwrap();
+        stream.write_all(&[7; LEN]).await.unwrap();
+        stream.flush().await.unwrap();
+        futures::future::pending::<()>().await;
+    });
+    loop {
+        tokio::select! {
+            _ = &mut done => break,
+            _ = next_swarm_event(&mut dialer) => {}
+        }
+    }
+
+    // 子流的流量按协商出的协议归类，协商本身只计入连接
+    let connection_id = *dialer.connected_connections().next().unwrap();
+    let streams = dialer
+        .bandwidth()
+        .connection_streams(&connection_id)
+        .unwrap();
+    let bulk = streams[BULK.as_ref()];
+    assert!(bulk.outbound >= LEN as u64);
+    let total = dialer.bandwidth().connection(&connection_id).unwrap();
+    assert!(bulk.outbound <= total.outbound);
+
+    // 连接关闭后仍计入按子流协议的累计值
+    assert!(dialer.close_connection(connection_id));
+    wait_for_event(&mut dialer, |event| match event {
+        client::SwarmEvent::ConnectionClosed { .. } => Some(()),
+        _ => None,
+    })
+    .await;
+    assert_eq!(dialer.bandwidth().connection_streams(&connection_id), None);
+    assert_eq!(dialer.bandwidth().by_stream_protocol()[BULK.as_ref()], bulk);
+}
@@ -0,0 +1,224 @@
+//! 连接的关闭、查询与 Swarm 句柄
+
+use futures::{StreamExt, future};
+use volans_core::identity::KeyPair;
+use volans_swarm::{DialOpts, client, error::DialError, server};
+use volans_swarm_test::{
+    SwarmExt, connect, listen, next_behavior_event, next_swarm_event, wait_for_event,
+};
+
+fn identify(_: &KeyPair) -> volans_identify::Behavior {
+    volans_identify::Behavior::new(volans_identify::Config::new("/test/1.0.0"))
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn close_swarm() {
+    let mut dialer = client::Swarm::new_ephemeral(identify);
+    let mut listener = server::Swarm::new_ephemeral(identify);
+    connect(&mut dialer, &mut listener).await;
+
+    let listener_peer = *listener.local_peer_id();
+    let closed = tokio::spawn(async move {
+        listener.close().await;
+        listener.connected_peers().count()
+    });
+    while dialer.is_peer_connected(&listener_peer) {
+        next_swarm_event(&mut dialer).await;
+    }
+    assert_eq!(closed.await.unwrap(), 0);
+
+    dialer.close().await;
+    assert!(matches!(
+        dialer.dial(DialOpts::new(None, Some(listener_peer))),
+        Err(DialError::Closing)
+    ));
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn connection_close_reason() {
+    use volans_swarm::error::CloseReason;
+
+    let mut dialer = client::Swarm::new_ephemeral(identify);
+    let mut listener = server::Swarm::new_ephemeral(identify);
+    connect(&mut dialer, &mut listener).await;
+
+    let connection_id = *dialer.connected_connections().next().unwrap();
+    assert!(dialer.close_connection(connection_id));
+    let (local, remote) = future::join(
+        wait_for_event(&mut dialer, |event| match event {
+            client::SwarmEvent::ConnectionClosed { reason, .. } => Some(reason),
+            _ => None,
+        }),
+        wait_for_event(&mut listener, |event| match event {
+            server::SwarmEvent::ConnectionClosed { reason, .. } => Some(reason),
+            _ => None,
+        }),
+    )
+    .await;
+    assert!(matches!(local, CloseReason::LocalRequested));
+    assert!(matches!(remote, CloseReason::RemoteClosed));
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn connection_close_reason_on_dead_peer() {
+    use std::time::Duration;
+
+    use volans_swarm::{connection::PoolConfig, error::CloseReason};
+    use volans_swarm_test::{ephemeral_key_pair, ephemeral_parts_with_muxer};
+
+    let key_pair = ephemeral_key_pair();
+    let mut muxer = volans_muxing::Config::new();
+    muxer.set_keep_alive(Duration::from_millis(20), Duration::from_millis(200));
+    let (transport, peer_id) = ephemeral_parts_with_muxer(&key_pair, muxer);
+    let mut dialer = client::Swarm::from_parts(
+        transport,
+        identify(&key_pair),
+        peer_id,
+        PoolConfig::with_tokio_executor(),
+    );
+    // 对端的多路复用器不再读取，收不到任何 Pong
+    let key_pair = ephemeral_key_pair();
+    let mut muxer = volans_muxing::Config::new();
+    muxer
+        .set_max_buffered_inbound_streams(0)
+        .set_inbound_overflow(volans_muxing::InboundOverflow::Backpressure);
+    let (transport, peer_id) = ephemeral_parts_with_muxer(&key_pair, muxer);
+    let mut listener = server::Swarm::from_parts(
+        transport,
+        identify(&key_pair),
+        peer_id,
+        PoolConfig::with_tokio_executor(),
+    );
+    connect(&mut dialer, &mut listener).await;
+    tokio::spawn(async move {
+        loop {
+            listener.next().await;
+        }
+    });
+
+    let reason = wait_for_event(&mut dialer, |event| match event {
+        client::SwarmEvent::ConnectionClosed { reason, .. } => Some(reason),
+        _ => None,
+    })
+    .await;
+    assert!(
+        matches!(reason, CloseReason::Timeout(_)),
+        "unexpected reason: {reason:?}"
+    );
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn toggle_behavior() {
+    use volans_swarm::Toggle;
+
+    let mut dialer = client::Swarm::new_ephemeral(|key| Toggle::new(identify(key), false));
+    let mut listener = server::Swarm::new_ephemeral(identify);
+    connect(&mut dialer, &mut listener).await;
+
+    // 停用期间建立的连接使用空处理器，启用后新连接才会运行 identify
+    let addr = listener.listeners().next().unwrap().clone();
+    let listener_peer = *listener.local_peer_id();
+    tokio::spawn(async move {
+        loop {
+            listener.next().await;
+        }
+    });
+    dialer.behavior_mut().set_enabled(true);
+    dialer
+        .dial(DialOpts::new(Some(addr), Some(listener_peer)))
+        .unwrap();
+    match next_behavior_event(&mut dialer).await {
+        volans_identify::Event::Received { peer_id, .. } => assert_eq!(peer_id, listener_peer),
+        event => panic!("unexpected event: {event:?}"),
+    }
+    assert_eq!(dialer.connected_connections().count(), 2);
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn replay_events_to_late_subscriber() {
+    use volans_allow_block_list::{Behavior, BlockedPeers};
+    use volans_swarm::ReplayEvent;
+
+    let mut dialer = client::Swarm::new_ephemeral(identify);
+    let mut listener =
+        server::Swarm::new_ephemeral(|_| Behavior::<BlockedPeers>::default()).with_replay_buffer(8);
+    connect(&mut dialer, &mut listener).await;
+
+    // 连接建立之后才订阅，仍能收到之前的监听地址与连接事件
+    let listen_addr = listener.listeners().next().unwrap().clone();
+    let mut events = listener.subscribe();
+    assert!(matches!(
+        events.next().await,
+        Some(ReplayEvent::NewListenAddr { addr, .. }) if addr == listen_addr
+    ));
+    assert!(matches!(
+        events.next().await,
+        Some(ReplayEvent::ConnectionEstablished { peer_id, .. })
+            if peer_id == *dialer.local_peer_id()
+    ));
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn query_connection_info() {
+    let mut dialer = client::Swarm::new_ephemeral(identify);
+    let mut listener = server::Swarm::new_ephemeral(identify);
+    connect(&mut dialer, &mut listener).await;
+
+    let listener_peer = *listener.local_peer_id();
+    let connections = dialer.iter_connections().collect::<Vec<_>>();
+    assert_eq!(connections.len(), 1);
+    let info = &connections[0];
+    assert_eq!(info.peer_id, listener_peer);
+    assert!(info.endpoint.is_dialer());
+    assert_eq!(info.muxer_protocol, Some("/v2/muxing"));
+    assert!(info.last_activity >= info.established_at);
+
+    let id = *listener.connected_connections().next().unwrap();
+    let info = listener.connection_info(id).unwrap();
+    assert_eq!(info.peer_id, *dialer.local_peer_id());
+    assert!(info.endpoint.is_listener());
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn drive_swarm_with_handle() {
+    use std::time::Duration;
+
+    use volans_swarm::error::SwarmClosed;
+
+    let mut listener = server::Swarm::new_ephemeral(identify);
+    let addr = listen(&mut listener).await;
+    let listener_peer = *listener.local_peer_id();
+    tokio::spawn(async move {
+        loop {
+            listener.next().await;
+        }
+    });
+
+    let mut dialer = client::Swarm::new_ephemeral(identify);
+    let handle = dialer.handle();
+    tokio::spawn(async move {
+        loop {
+            dialer.next().await;
+        }
+    });
+    handle
+        .dial(DialOpts::new(Some(addr), Some(listener_peer)))
+        .await
+        .unwrap();
+    tokio::time::timeout(Duration::from_secs(5), async {
+        while !handle
+            .behavior_command(move |behavior| behavior.info(&listener_peer).is_some())
+            .await
+            .unwrap()
+        {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .expect("identify info received");
+
+    let dropped = client::Swarm::new_ephemeral(identify);
+    let handle = dropped.handle();
+    drop(dropped);
+    assert_eq!(handle.behavior_command(|_| ()).await, Err(SwarmClosed));
+}
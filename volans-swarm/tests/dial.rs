@@ -0,0 +1,246 @@
+//! 拨号、重试与拨号排队
+
+use futures::StreamExt;
+use volans_core::{ConnectedPoint, Multiaddr, identity::KeyPair};
+use volans_swarm::{DialOpts, client, connection::PoolConfig, error::DialError, server};
+use volans_swarm_test::{
+    SwarmExt, ephemeral_key_pair, ephemeral_parts, listen, next_swarm_event, unused_addr,
+    wait_for_event,
+};
+
+fn identify(_: &KeyPair) -> volans_identify::Behavior {
+    volans_identify::Behavior::new(volans_identify::Config::new("/test/1.0.0"))
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn retry_failed_dial() {
+    use std::time::Duration;
+    use volans_swarm::RetryPolicy;
+
+    let addr = unused_addr();
+    let policy = RetryPolicy::default()
+        .with_max_attempts(2)
+        .with_initial_backoff(Duration::from_millis(10));
+
+    let mut dialer = client::Swarm::new_ephemeral(identify);
+    dialer
+        .dial(DialOpts::new(Some(addr), None).with_retry_policy(policy))
+        .unwrap();
+    assert!(matches!(
+        next_swarm_event(&mut dialer).await,
+        client::SwarmEvent::DialRetryScheduled { attempt: 1, .. }
+    ));
+    assert!(matches!(
+        next_swarm_event(&mut dialer).await,
+        client::SwarmEvent::Dialing { .. }
+    ));
+    assert!(matches!(
+        next_swarm_event(&mut dialer).await,
+        client::SwarmEvent::DialGivenUp {
+            attempts: 2,
+            error: DialError::Transport { .. },
+            ..
+        }
+    ));
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn abort_pending_dial() {
+    // 只接受 TCP 连接，握手不会完成
+    let stalled = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = stalled.local_addr().unwrap().port();
+    let addr: Multiaddr = format!("/ip4/127.0.0.1/tcp/{port}").parse().unwrap();
+
+    let mut dialer = client::Swarm::new_ephemeral(identify);
+    let opts = DialOpts::new(Some(addr), None);
+    let connection_id = opts.connection_id();
+    dialer.dial(opts).unwrap();
+    assert!(dialer.abort_dial(connection_id));
+    assert!(!dialer.abort_dial(connection_id));
+
+    let (id, error) = wait_for_event(&mut dialer, |event| match event {
+        client::SwarmEvent::OutgoingConnectionError {
+            connection_id,
+            error,
+            ..
+        } => Some((connection_id, error)),
+        _ => None,
+    })
+    .await;
+    assert_eq!(id, connection_id);
+    assert!(matches!(error, DialError::Aborted));
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn queue_dials_over_limit() {
+    use volans_swarm::StreamProtocol;
+
+    const PROTOCOL: StreamProtocol = StreamProtocol::new("/queued/1.0.0");
+
+    let key_pair = ephemeral_key_pair();
+    let (transport, local_peer_id) = ephemeral_parts(&key_pair);
+    let mut dialer = client::Swarm::new(
+        transport,
+        volans_stream::client::Behavior::new(),
+        local_peer_id,
+        PoolConfig::with_tokio_executor().with_max_concurrent_dials(1),
+    );
+    let mut peers = Vec::new();
+    for _ in 0..2 {
+        let mut listener = server::Swarm::new_ephemeral(|_| volans_stream::server::Behavior::new());
+        let addr = listen(&mut listener).await;
+        let peer_id = *listener.local_peer_id();
+        dialer.peer_store_mut().add_address(peer_id, addr);
+        peers.push(peer_id);
+        tokio::spawn(async move {
+            loop {
+                listener.next().await;
+            }
+        });
+    }
+
+    // 两个对端同时触发拨号，第二个拨号排队等待
+    for peer_id in peers.clone() {
+        let mut control = dialer.behavior().control();
+        tokio::spawn(async move {
+            let _ = control.open_stream(peer_id, PROTOCOL).await;
+        });
+    }
+    let queued = wait_for_event(&mut dialer, |event| match event {
+        client::SwarmEvent::DialQueued {
+            peer_id, queued, ..
+        } => Some((peer_id, queued)),
+        _ => None,
+    })
+    .await;
+    assert!(matches!(queued, (Some(peer_id), 1) if peers.contains(&peer_id)));
+
+    let mut established = Vec::new();
+    while established.len() < 2 {
+        let peer_id = wait_for_event(&mut dialer, |event| match event {
+            client::SwarmEvent::ConnectionEstablished { peer_id, .. } => Some(peer_id),
+            _ => None,
+        })
+        .await;
+        established.push(peer_id);
+    }
+    established.sort();
+    peers.sort();
+    assert_eq!(established, peers);
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn queue_retries_over_limit() {
+    use std::time::Duration;
+    use volans_swarm::RetryPolicy;
+
+    // 只接受 TCP 连接，握手不会完成，一直占用拨号名额
+    let stalled = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = stalled.local_addr().unwrap().port();
+    let stalled_addr: Multiaddr = format!("/ip4/127.0.0.1/tcp/{port}").parse().unwrap();
+
+    let key_pair = ephemeral_key_pair();
+    let (transport, local_peer_id) = ephemeral_parts(&key_pair);
+    let mut dialer = client::Swarm::new(
+        transport,
+        identify(&key_pair),
+        local_peer_id,
+        PoolConfig::with_tokio_executor().with_max_concurrent_dials(1),
+    );
+    let policy = RetryPolicy::default()
+        .with_max_attempts(2)
+        .with_initial_backoff(Duration::from_millis(10));
+    let retrying = DialOpts::new(Some(unused_addr()), None).with_retry_policy(policy);
+    let retrying_id = retrying.connection_id();
+    dialer.dial(retrying).unwrap();
+    let stalled_opts = DialOpts::new(Some(stalled_addr), None);
+    let stalled_id = stalled_opts.connection_id();
+    dialer.dial(stalled_opts).unwrap();
+
+    // 退避结束时名额被占用，重试排队而不是直接发起
+    let queued = wait_for_event(&mut dialer, |event| match event {
+        client::SwarmEvent::DialQueued { connection_id, .. } => Some(connection_id),
+        client::SwarmEvent::Dialing { connection_id, .. } if connection_id == retrying_id => {
+            panic!("retry started over the dial limit")
+        }
+        _ => None,
+    })
+    .await;
+    assert_eq!(queued, retrying_id);
+
+    assert!(dialer.abort_dial(stalled_id));
+    let dialing = wait_for_event(&mut dialer, |event| match event {
+        client::SwarmEvent::Dialing { connection_id, .. } => Some(connection_id),
+        _ => None,
+    })
+    .await;
+    assert_eq!(dialing, retrying_id);
+    let attempts = wait_for_event(&mut dialer, |event| match event {
+        client::SwarmEvent::DialGivenUp { attempts, .. } => Some(attempts),
+        _ => None,
+    })
+    .await;
+    assert_eq!(attempts, 2);
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn dial_fallback_addresses() {
+    use volans_swarm::DialStrategy;
+
+    let mut dialer = client::Swarm::new_ephemeral(identify);
+    let mut listener = server::Swarm::new_ephemeral(identify);
+    let listen_addr = listen(&mut listener).await;
+    let listener_peer = *listener.local_peer_id();
+    tokio::spawn(async move {
+        loop {
+            listener.next().await;
+        }
+    });
+
+    let opts = DialOpts::new(None, Some(listener_peer))
+        .with_addresses([unused_addr(), listen_addr.clone()])
+        .with_strategy(DialStrategy::Sequential);
+    dialer.dial(opts).unwrap();
+    let addr = wait_for_event(&mut dialer, |event| match event {
+        client::SwarmEvent::ConnectionEstablished {
+            endpoint: ConnectedPoint::Dialer { addr },
+            ..
+        } => Some(addr),
+        _ => None,
+    })
+    .await;
+    assert_eq!(addr, listen_addr);
+
+    let opts = DialOpts::new(None, None).with_addresses([unused_addr(), unused_addr()]);
+    dialer.dial(opts).unwrap();
+    let error = wait_for_event(&mut dialer, |event| match event {
+        client::SwarmEvent::OutgoingConnectionError { error, .. } => Some(error),
+        _ => None,
+    })
+    .await;
+    assert!(matches!(error, DialError::AllAttemptsFailed { errors } if errors.len() == 2));
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn report_obtained_peer_on_wrong_peer_id() {
+    let mut dialer = client::Swarm::new_ephemeral(identify);
+    let mut listener = server::Swarm::new_ephemeral(identify);
+    let listen_addr = listen(&mut listener).await;
+    let listener_peer = *listener.local_peer_id();
+    tokio::spawn(async move {
+        loop {
+            listener.next().await;
+        }
+    });
+
+    let expected = volans_core::PeerId::random();
+    dialer
+        .dial(DialOpts::new(Some(listen_addr), Some(expected)))
+        .unwrap();
+    let error = wait_for_event(&mut dialer, |event| match event {
+        client::SwarmEvent::OutgoingConnectionError { error, .. } => Some(error),
+        _ => None,
+    })
+    .await;
+    assert!(matches!(error, DialError::WrongPeerId { obtained } if obtained == listener_peer));
+}
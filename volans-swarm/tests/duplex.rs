@@ -0,0 +1,73 @@
+//! 双向 Swarm 同时拨号与接受连接
+
+use futures::{
+    Stream, StreamExt,
+    future::{self, Either},
+};
+use volans_core::{ConnectedPoint, PeerId, identity::KeyPair};
+use volans_swarm::{DialOpts, SwarmEvent, client, duplex, server};
+use volans_swarm_test::{SwarmExt, listen};
+
+fn identify(_: &KeyPair) -> volans_identify::Behavior {
+    volans_identify::Behavior::new(volans_identify::Config::new("/test/1.0.0"))
+}
+
+/// 同时驱动双方，直到 `node` 与 `peer_id` 建立连接，返回连接的端点
+async fn wait_established<T, S>(
+    node: &mut duplex::Swarm<volans_identify::Behavior>,
+    other: &mut S,
+    peer_id: PeerId,
+) -> ConnectedPoint
+where
+    S: Stream<Item = SwarmEvent<T>> + Unpin,
+{
+    loop {
+        match future::select(node.next(), other.next()).await {
+            Either::Left((
+                Some(SwarmEvent::ConnectionEstablished {
+                    peer_id: connected,
+                    endpoint,
+                    ..
+                }),
+                _,
+            )) if connected == peer_id => return endpoint,
+            Either::Left((Some(_), _)) | Either::Right((Some(_), _)) => {}
+            Either::Left((None, _)) | Either::Right((None, _)) => {
+                unreachable!("Swarm stream never ends")
+            }
+        }
+    }
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn duplex_dials_and_accepts() {
+    let mut node = duplex::Swarm::new_ephemeral(identify);
+    let mut client = client::Swarm::new_ephemeral(identify);
+    let mut server = server::Swarm::new_ephemeral(identify);
+
+    // 作为监听方接受客户端的连接
+    let addr = listen(&mut node).await;
+    client
+        .dial(DialOpts::new(
+            Some(addr.clone()),
+            Some(*node.local_peer_id()),
+        ))
+        .unwrap();
+    let client_peer = *client.local_peer_id();
+    let endpoint = wait_established(&mut node, &mut client, client_peer).await;
+    assert!(matches!(endpoint, ConnectedPoint::Listener { local_addr, .. } if local_addr == addr));
+
+    // 作为拨号方连接服务端
+    let server_addr = listen(&mut server).await;
+    node.dial(DialOpts::new(
+        Some(server_addr.clone()),
+        Some(*server.local_peer_id()),
+    ))
+    .unwrap();
+    let server_peer = *server.local_peer_id();
+    let endpoint = wait_established(&mut node, &mut server, server_peer).await;
+    assert!(matches!(endpoint, ConnectedPoint::Dialer { addr } if addr == server_addr));
+
+    assert!(node.is_peer_connected(&client_peer));
+    assert!(node.is_peer_connected(&server_peer));
+}
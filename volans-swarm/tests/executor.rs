@@ -0,0 +1,16 @@
+//! 连接任务执行器
+
+#![cfg(feature = "async-std")]
+
+use futures::channel::oneshot;
+use volans_swarm::executor::{AsyncStdExecutor, ExecSwitch};
+
+#[test]
+fn spawn_on_async_std() {
+    let (tx, rx) = oneshot::channel();
+    let mut executor = ExecSwitch::boxed(AsyncStdExecutor);
+    executor.spawn(async move {
+        let _ = tx.send(async_std::task::try_current().is_some());
+    });
+    assert!(futures::executor::block_on(rx).unwrap());
+}
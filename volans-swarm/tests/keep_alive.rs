@@ -0,0 +1,192 @@
+//! 处理器的连接保活策略
+
+use std::{
+    convert::Infallible,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use volans_core::{Multiaddr, PeerId};
+use volans_swarm::{
+    BehaviorEvent, ConnectionDenied, ConnectionHandler, ConnectionHandlerEvent, ConnectionId,
+    InboundStreamHandler, InboundUpgradeSend, KeepAlive, NetworkBehavior, NetworkIncomingBehavior,
+    NetworkOutgoingBehavior, OutboundStreamHandler, OutboundUpgradeSend, StreamUpgradeError,
+    SubstreamProtocol, THandlerAction, THandlerEvent, client, handler::DummyHandler, server,
+};
+use volans_swarm_test::sim::{self, SimSwarm, Simulation};
+
+/// 不打开子流，只按给定策略保活的行为
+struct Hold(KeepAlive);
+
+struct HoldHandler(KeepAlive);
+
+impl ConnectionHandler for HoldHandler {
+    type Action = Infallible;
+    type Event = Infallible;
+
+    fn handle_action(&mut self, action: Self::Action) {
+        match action {}
+    }
+
+    fn keep_alive(&self) -> KeepAlive {
+        self.0
+    }
+
+    fn poll(&mut self, _cx: &mut Context<'_>) -> Poll<ConnectionHandlerEvent<Self::Event>> {
+        Poll::Pending
+    }
+}
+
+impl InboundStreamHandler for HoldHandler {
+    type InboundUpgrade = <DummyHandler as InboundStreamHandler>::InboundUpgrade;
+    type InboundUserData = ();
+
+    fn listen_protocol(&self) -> SubstreamProtocol<Self::InboundUpgrade, Self::InboundUserData> {
+        DummyHandler.listen_protocol()
+    }
+
+    fn on_fully_negotiated(
+        &mut self,
+        _user_data: Self::InboundUserData,
+        protocol: <Self::InboundUpgrade as InboundUpgradeSend>::Output,
+    ) {
+        match protocol {}
+    }
+
+    fn on_upgrade_error(
+        &mut self,
+        _user_data: Self::InboundUserData,
+        _error: <Self::InboundUpgrade as InboundUpgradeSend>::Error,
+    ) {
+    }
+}
+
+impl OutboundStreamHandler for HoldHandler {
+    type OutboundUpgrade = <DummyHandler as OutboundStreamHandler>::OutboundUpgrade;
+    type OutboundUserData = Infallible;
+
+    fn on_fully_negotiated(
+        &mut self,
+        user_data: Self::OutboundUserData,
+        _protocol: <Self::OutboundUpgrade as OutboundUpgradeSend>::Output,
+    ) {
+        match user_data {}
+    }
+
+    fn on_upgrade_error(
+        &mut self,
+        user_data: Self::OutboundUserData,
+        _error: StreamUpgradeError<<Self::OutboundUpgrade as OutboundUpgradeSend>::Error>,
+    ) {
+        match user_data {}
+    }
+
+    fn poll_outbound_request(
+        &mut self,
+        _cx: &mut Context<'_>,
+    ) -> Poll<SubstreamProtocol<Self::OutboundUpgrade, Self::OutboundUserData>> {
+        Poll::Pending
+    }
+}
+
+impl NetworkBehavior for Hold {
+    type ConnectionHandler = HoldHandler;
+    type Event = Infallible;
+
+    fn on_connection_handler_event(
+        &mut self,
+        _id: ConnectionId,
+        _peer_id: PeerId,
+        event: THandlerEvent<Self>,
+    ) {
+        match event {}
+    }
+
+    fn poll(
+        &mut self,
+        _cx: &mut Context<'_>,
+    ) -> Poll<BehaviorEvent<Self::Event, THandlerAction<Self>>> {
+        Poll::Pending
+    }
+}
+
+impl NetworkIncomingBehavior for Hold {
+    fn handle_established_connection(
+        &mut self,
+        _id: ConnectionId,
+        _peer_id: PeerId,
+        _local_addr: &Multiaddr,
+        _remote_addr: &Multiaddr,
+    ) -> Result<Self::ConnectionHandler, ConnectionDenied> {
+        Ok(HoldHandler(self.0))
+    }
+}
+
+impl NetworkOutgoingBehavior for Hold {
+    fn handle_pending_connection(
+        &mut self,
+        _id: ConnectionId,
+        _maybe_peer: Option<PeerId>,
+        addr: &Option<Multiaddr>,
+    ) -> Result<Option<Multiaddr>, ConnectionDenied> {
+        Ok(addr.clone())
+    }
+
+    fn handle_established_connection(
+        &mut self,
+        _id: ConnectionId,
+        _peer_id: PeerId,
+        _addr: &Multiaddr,
+    ) -> Result<Self::ConnectionHandler, ConnectionDenied> {
+        Ok(HoldHandler(self.0))
+    }
+}
+
+/// 以 `keep_alive` 连接始终保活的监听方，返回连接建立后到关闭经过的整秒数
+fn connection_lifetime(keep_alive: KeepAlive, limit: Duration) -> Option<Duration> {
+    let sim = Simulation::new();
+    let mut dialer: SimSwarm<client::Swarm<_>> = SimSwarm::new(&sim, |_| Hold(keep_alive));
+    let mut listener: SimSwarm<server::Swarm<_>> = SimSwarm::new(&sim, |_| Hold(KeepAlive::Yes));
+    sim::connect(&sim, &mut dialer, &mut listener);
+
+    let step = Duration::from_secs(1);
+    let mut elapsed = Duration::ZERO;
+    loop {
+        let closed = dialer
+            .take_events()
+            .into_iter()
+            .any(|event| matches!(event, client::SwarmEvent::ConnectionClosed { .. }));
+        if closed {
+            return Some(elapsed);
+        }
+        if elapsed >= limit {
+            return None;
+        }
+        sim::advance(&sim, &mut [&mut dialer, &mut listener], step);
+        elapsed += step;
+    }
+}
+
+#[test]
+fn keep_alive_policy() {
+    // 连接池的空闲超时为 60 秒
+    let limit = Duration::from_secs(120);
+    assert_eq!(
+        connection_lifetime(KeepAlive::No, limit),
+        Some(Duration::ZERO)
+    );
+    assert_eq!(
+        connection_lifetime(KeepAlive::Timeout(Duration::from_secs(5)), limit),
+        Some(Duration::from_secs(5))
+    );
+    // 处理器的超时不超过连接池的空闲超时
+    assert_eq!(
+        connection_lifetime(KeepAlive::Timeout(Duration::from_secs(90)), limit),
+        Some(Duration::from_secs(60))
+    );
+    assert_eq!(
+        connection_lifetime(KeepAlive::Idle, limit),
+        Some(Duration::from_secs(60))
+    );
+    assert_eq!(connection_lifetime(KeepAlive::Yes, limit), None);
+}
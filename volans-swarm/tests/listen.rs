@@ -0,0 +1,143 @@
+//! 监听器、入站连接限制与外部地址
+
+use futures::StreamExt;
+use volans_core::{Multiaddr, identity::KeyPair};
+use volans_swarm::{DialOpts, client, connection::PoolConfig, server};
+use volans_swarm_test::{
+    SwarmExt, connect, ephemeral_key_pair, ephemeral_parts, listen, next_swarm_event,
+    wait_for_event,
+};
+
+fn identify(_: &KeyPair) -> volans_identify::Behavior {
+    volans_identify::Behavior::new(volans_identify::Config::new("/test/1.0.0"))
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn pause_listener() {
+    use std::time::Duration;
+
+    let mut dialer = client::Swarm::new_ephemeral(identify);
+    let mut listener = server::Swarm::new_ephemeral(identify);
+    let listener_id = listener
+        .listen_on("/ip4/127.0.0.1/tcp/0".parse().unwrap())
+        .unwrap();
+    let addr = wait_for_event(&mut listener, |event| match event {
+        server::SwarmEvent::NewListenAddr { addr, .. } => Some(addr),
+        _ => None,
+    })
+    .await;
+    assert!(listener.pause_listener(listener_id));
+
+    dialer
+        .dial(DialOpts::new(Some(addr), Some(*listener.local_peer_id())))
+        .unwrap();
+    tokio::spawn(async move {
+        loop {
+            dialer.next().await;
+        }
+    });
+    let incoming = |event| match event {
+        server::SwarmEvent::IncomingConnection { .. } => Some(()),
+        _ => None,
+    };
+    let paused = tokio::time::timeout(
+        Duration::from_millis(200),
+        wait_for_event(&mut listener, incoming),
+    )
+    .await;
+    assert!(paused.is_err());
+
+    assert!(listener.resume_listener(listener_id));
+    wait_for_event(&mut listener, incoming).await;
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn limit_pending_incoming() {
+    use std::{net::TcpStream, time::Duration};
+    use volans_swarm::error::ListenError;
+
+    let key_pair = ephemeral_key_pair();
+    let (transport, peer_id) = ephemeral_parts(&key_pair);
+    let config = PoolConfig::with_tokio_executor()
+        .with_pending_connection_timeout(Duration::from_millis(100))
+        .with_max_pending_incoming(1);
+    let mut listener = server::Swarm::new(transport, identify(&key_pair), peer_id, config);
+    let addr = listen(&mut listener).await;
+    let port = addr.to_string().rsplit('/').next().unwrap().to_string();
+
+    // 只建立 TCP 连接，不进行握手
+    let _first = TcpStream::connect(format!("127.0.0.1:{port}")).unwrap();
+    wait_for_event(&mut listener, |event| match event {
+        server::SwarmEvent::IncomingConnection { .. } => Some(()),
+        _ => None,
+    })
+    .await;
+    let _second = TcpStream::connect(format!("127.0.0.1:{port}")).unwrap();
+
+    let incoming_error = |event| match event {
+        server::SwarmEvent::IncomingConnectionError { error, .. } => Some(error),
+        _ => None,
+    };
+    let error = wait_for_event(&mut listener, incoming_error).await;
+    assert!(matches!(error, ListenError::PendingLimitReached));
+    let error = wait_for_event(&mut listener, incoming_error).await;
+    assert!(matches!(error, ListenError::Timeout));
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn filter_pending_incoming() {
+    use std::net::TcpStream;
+    use volans_swarm::error::ListenError;
+
+    let mut listener = server::Swarm::new_ephemeral(identify)
+        .with_pending_connection_filter(|_: &Multiaddr, _: &Multiaddr| false);
+    let addr = listen(&mut listener).await;
+    let port = addr.to_string().rsplit('/').next().unwrap().to_string();
+
+    let _stream = TcpStream::connect(format!("127.0.0.1:{port}")).unwrap();
+    let error = wait_for_event(&mut listener, |event| match event {
+        server::SwarmEvent::IncomingConnection { .. } => panic!("filtered connection accepted"),
+        server::SwarmEvent::IncomingConnectionError { error, .. } => Some(error),
+        _ => None,
+    })
+    .await;
+    assert!(matches!(error, ListenError::Filtered));
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn confirm_external_address() {
+    use volans_swarm::ExternalAddrStore;
+
+    let mut dialer = client::Swarm::new_ephemeral(identify);
+    let mut listener = server::Swarm::new_ephemeral(identify)
+        .with_external_addr_store(ExternalAddrStore::new().with_confirmation_threshold(1));
+    connect(&mut dialer, &mut listener).await;
+    tokio::spawn(async move {
+        loop {
+            dialer.next().await;
+        }
+    });
+
+    // 拨号端通过 identify 报告它拨通的地址
+    let listen_addr = listener.listeners().next().unwrap().clone();
+    let addr = wait_for_event(&mut listener, |event| match event {
+        server::SwarmEvent::ExternalAddrConfirmed { addr } => Some(addr),
+        _ => None,
+    })
+    .await;
+    assert_eq!(addr, listen_addr);
+    assert_eq!(listener.external_addresses().count(), 1);
+
+    let manual: Multiaddr = "/ip4/203.0.113.1/tcp/4001".parse().unwrap();
+    listener.add_external_address(manual.clone());
+    assert!(listener.remove_external_address(&manual));
+    assert!(!listener.remove_external_address(&manual));
+    assert!(matches!(
+        next_swarm_event(&mut listener).await,
+        server::SwarmEvent::ExternalAddrConfirmed { addr } if addr == manual
+    ));
+    assert!(matches!(
+        next_swarm_event(&mut listener).await,
+        server::SwarmEvent::ExternalAddrExpired { addr } if addr == manual
+    ));
+}
@@ -0,0 +1,338 @@
+//! 向连接处理器投递动作与延迟构造处理器
+
+use futures::StreamExt;
+use volans_core::{PeerId, identity::KeyPair};
+use volans_swarm::{DialOpts, NetworkOutgoingBehavior, OutboundStreamHandler, client, server};
+use volans_swarm_test::{SwarmExt, connect, wait_for_event};
+
+fn identify(_: &KeyPair) -> volans_identify::Behavior {
+    volans_identify::Behavior::new(volans_identify::Config::new("/test/1.0.0"))
+}
+
+mod recorder {
+    use std::{
+        collections::{HashMap, VecDeque},
+        convert::Infallible,
+        sync::{Arc, Mutex},
+        task::{Context, Poll},
+    };
+
+    use futures::{channel::oneshot, future::Shared};
+    use volans_core::{Multiaddr, PeerId};
+    use volans_swarm::{
+        BehaviorEvent, ConnectionDenied, ConnectionHandler, ConnectionId, NetworkBehavior,
+        NetworkOutgoingBehavior, THandlerAction, THandlerEvent,
+        behavior::NotifyHandler,
+        handler::{DummyHandler, LazyHandler, MapAction},
+    };
+
+    pub type Received = Arc<Mutex<HashMap<ConnectionId, Vec<usize>>>>;
+    pub type Gate = Shared<oneshot::Receiver<()>>;
+    type Record = Box<dyn Fn(usize) -> Option<Infallible> + Send>;
+
+    /// 记录每个连接收到的动作
+    pub struct Recorder {
+        received: Received,
+        pending: VecDeque<BehaviorEvent<Infallible, usize>>,
+        gate: Option<Gate>,
+    }
+
+    impl Recorder {
+        pub fn new(received: Received) -> Self {
+            Self {
+                received,
+                pending: VecDeque::new(),
+                gate: None,
+            }
+        }
+
+        /// 处理器在 `gate` 打开后才完成构造
+        pub fn with_gate(mut self, gate: Gate) -> Self {
+            self.gate = Some(gate);
+            self
+        }
+
+        /// 依次发送 `0..count` 作为动作
+        pub fn notify(&mut self, peer_id: PeerId, handler: NotifyHandler, count: usize) {
+            self.pending
+                .extend((0..count).map(|action| BehaviorEvent::HandlerAction {
+                    peer_id,
+                    handler: handler.clone(),
+                    action,
+                }));
+        }
+    }
+
+    impl NetworkBehavior for Recorder {
+        type ConnectionHandler = LazyHandler<MapAction<DummyHandler, usize, Record>>;
+        type Event = Infallible;
+
+        fn on_connection_handler_event(
+            &mut self,
+            _: ConnectionId,
+            _: PeerId,
+            event: THandlerEvent<Self>,
+        ) {
+            match event {}
+        }
+
+        fn poll(
+            &mut self,
+            _: &mut Context<'_>,
+        ) -> Poll<BehaviorEvent<Self::Event, THandlerAction<Self>>> {
+            match self.pending.pop_front() {
+                Some(event) => Poll::Ready(event),
+                None => Poll::Pending,
+            }
+        }
+    }
+
+    impl NetworkOutgoingBehavior for Recorder {
+        fn handle_pending_connection(
+            &mut self,
+            _: ConnectionId,
+            _: Option<PeerId>,
+            addr: &Option<Multiaddr>,
+        ) -> Result<Option<Multiaddr>, ConnectionDenied> {
+            Ok(addr.clone())
+        }
+
+        fn handle_established_connection(
+            &mut self,
+            id: ConnectionId,
+            _: PeerId,
+            _: &Multiaddr,
+        ) -> Result<Self::ConnectionHandler, ConnectionDenied> {
+            let received = self.received.clone();
+            let record: Record = Box::new(move |action| {
+                received.lock().unwrap().entry(id).or_default().push(action);
+                None
+            });
+            let handler = DummyHandler.map_action(record);
+            let gate = self.gate.clone();
+            Ok(LazyHandler::new(async move {
+                if let Some(gate) = gate {
+                    let _ = gate.await;
+                }
+                handler
+            }))
+        }
+    }
+}
+
+/// 与同一个对端建立两条连接，返回后对端在后台运行
+async fn connect_twice(dialer: &mut client::Swarm<recorder::Recorder>) -> PeerId {
+    let mut listener = server::Swarm::new_ephemeral(identify);
+    connect(dialer, &mut listener).await;
+    dial_again(dialer, listener, 1).await
+}
+
+/// 在后台运行已连接的对端，并再向其拨号 `times` 次
+async fn dial_again<B>(
+    dialer: &mut client::Swarm<B>,
+    mut listener: server::Swarm<volans_identify::Behavior>,
+    times: usize,
+) -> PeerId
+where
+    B: NetworkOutgoingBehavior,
+    B::ConnectionHandler: OutboundStreamHandler,
+    B::Event: std::fmt::Debug,
+{
+    let addr = listener.listeners().next().unwrap().clone();
+    let listener_peer = *listener.local_peer_id();
+    tokio::spawn(async move {
+        loop {
+            listener.next().await;
+        }
+    });
+    let expected = dialer.connected_connections().count() + times;
+    for _ in 0..times {
+        dialer
+            .dial(DialOpts::new(Some(addr.clone()), Some(listener_peer)))
+            .unwrap();
+    }
+    while dialer.connected_connections().count() < expected {
+        wait_for_event(dialer, |event| client::Swarm::<B>::established_peer(&event)).await;
+    }
+    listener_peer
+}
+
+/// 在后台驱动节点，直到所有连接共收到 `count` 个动作
+async fn drive_until_received<S>(mut dialer: S, received: &recorder::Received, count: usize)
+where
+    S: SwarmExt + Send + 'static,
+{
+    use std::time::Duration;
+
+    tokio::spawn(async move {
+        loop {
+            dialer.next().await;
+        }
+    });
+    let delivered = || received.lock().unwrap().values().flatten().count();
+    tokio::time::timeout(Duration::from_secs(5), async {
+        while delivered() < count {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .expect("all actions delivered");
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn notify_all_connections() {
+    use volans_swarm::behavior::NotifyHandler;
+
+    // 超过连接的命令缓冲区，投递过程中通道反复写满
+    const COUNT: usize = 100;
+
+    let received = recorder::Received::default();
+    let mut dialer = client::Swarm::new_ephemeral(|_| recorder::Recorder::new(received.clone()));
+    let listener_peer = connect_twice(&mut dialer).await;
+
+    dialer
+        .behavior_mut()
+        .notify(listener_peer, NotifyHandler::All, COUNT);
+    drive_until_received(dialer, &received, 2 * COUNT).await;
+
+    let received = received.lock().unwrap();
+    assert_eq!(received.len(), 2);
+    for actions in received.values() {
+        assert_eq!(*actions, (0..COUNT).collect::<Vec<_>>());
+    }
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn notify_any_round_robin() {
+    use volans_swarm::behavior::{NotifyHandler, SelectionPolicy};
+
+    const COUNT: usize = 10;
+
+    let received = recorder::Received::default();
+    let mut dialer = client::Swarm::new_ephemeral(|_| recorder::Recorder::new(received.clone()));
+    let listener_peer = connect_twice(&mut dialer).await;
+
+    dialer.behavior_mut().notify(
+        listener_peer,
+        NotifyHandler::AnyWith(SelectionPolicy::RoundRobin),
+        COUNT,
+    );
+    drive_until_received(dialer, &received, COUNT).await;
+
+    let received = received.lock().unwrap();
+    assert_eq!(received.len(), 2);
+    for actions in received.values() {
+        assert_eq!(actions.len(), COUNT / 2);
+    }
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn construct_handler_lazily() {
+    use std::time::Duration;
+
+    use futures::{FutureExt, channel::oneshot};
+    use volans_swarm::behavior::NotifyHandler;
+
+    const COUNT: usize = 5;
+
+    let received = recorder::Received::default();
+    let (open, gate) = oneshot::channel();
+    let gate = gate.shared();
+    let mut dialer = client::Swarm::new_ephemeral(|_| {
+        recorder::Recorder::new(received.clone()).with_gate(gate.clone())
+    });
+    // 处理器构造完成前连接照常建立
+    let listener_peer = connect_twice(&mut dialer).await;
+
+    dialer
+        .behavior_mut()
+        .notify(listener_peer, NotifyHandler::All, COUNT);
+    let _ = tokio::time::timeout(Duration::from_millis(100), async {
+        loop {
+            dialer.next().await;
+        }
+    })
+    .await;
+    assert!(received.lock().unwrap().is_empty());
+
+    // 构造完成后依次处理排队的动作
+    open.send(()).unwrap();
+    drive_until_received(dialer, &received, 2 * COUNT).await;
+    for actions in received.lock().unwrap().values() {
+        assert_eq!(*actions, (0..COUNT).collect::<Vec<_>>());
+    }
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn close_connection_during_handler_construction() {
+    use futures::{FutureExt, channel::oneshot};
+    use volans_swarm::error::CloseReason;
+
+    let (_open, gate) = oneshot::channel::<()>();
+    let gate = gate.shared();
+    let mut dialer = client::Swarm::new_ephemeral(|_| {
+        recorder::Recorder::new(Default::default()).with_gate(gate.clone())
+    });
+
+    // 本地关闭
+    let mut listener = server::Swarm::new_ephemeral(identify);
+    connect(&mut dialer, &mut listener).await;
+    let id = *dialer.connected_connections().next().unwrap();
+    assert!(dialer.close_connection(id));
+    let reason = wait_for_event(&mut dialer, |event| match event {
+        client::SwarmEvent::ConnectionClosed {
+            connection_id,
+            reason,
+            ..
+        } if connection_id == id => Some(reason),
+        _ => None,
+    })
+    .await;
+    assert!(matches!(reason, CloseReason::LocalRequested));
+
+    // 对端断开
+    let mut listener = server::Swarm::new_ephemeral(identify);
+    connect(&mut dialer, &mut listener).await;
+    let id = *dialer.connected_connections().next().unwrap();
+    drop(listener);
+    let closed = wait_for_event(&mut dialer, |event| match event {
+        client::SwarmEvent::ConnectionClosed { connection_id, .. } => Some(connection_id),
+        _ => None,
+    })
+    .await;
+    assert_eq!(closed, id);
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn toggle_notify_any_skips_disabled_connections() {
+    use volans_swarm::{
+        Toggle,
+        behavior::{NotifyHandler, SelectionPolicy},
+    };
+
+    const COUNT: usize = 10;
+
+    let received = recorder::Received::default();
+    let mut dialer = client::Swarm::new_ephemeral(|_| {
+        Toggle::new(recorder::Recorder::new(received.clone()), false)
+    });
+    let mut listener = server::Swarm::new_ephemeral(identify);
+    connect(&mut dialer, &mut listener).await;
+
+    // 停用期间的连接使用空处理器，之后的两条连接使用内部处理器
+    dialer.behavior_mut().set_enabled(true);
+    let listener_peer = dial_again(&mut dialer, listener, 2).await;
+
+    dialer.behavior_mut().inner_mut().notify(
+        listener_peer,
+        NotifyHandler::AnyWith(SelectionPolicy::RoundRobin),
+        COUNT,
+    );
+    drive_until_received(dialer, &received, COUNT).await;
+
+    let received = received.lock().unwrap();
+    assert_eq!(received.len(), 2);
+    for actions in received.values() {
+        assert_eq!(actions.len(), COUNT / 2);
+    }
+}
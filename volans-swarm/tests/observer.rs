@@ -0,0 +1,98 @@
+//! Swarm 事件观察者
+
+use std::sync::{Arc, Mutex};
+
+use volans_core::{ConnectedPoint, Multiaddr, PeerId, identity::KeyPair};
+use volans_swarm::{
+    ConnectionId, DialOpts, ListenerEvent, SwarmObserver, client, error::DialError, server,
+};
+use volans_swarm_test::{
+    SwarmExt, connect, next_behavior_event, next_swarm_event, unused_addr, wait_for_event,
+};
+
+fn identify(_: &KeyPair) -> volans_identify::Behavior {
+    volans_identify::Behavior::new(volans_identify::Config::new("/test/1.0.0"))
+}
+
+#[derive(Debug, PartialEq)]
+enum Observed {
+    Established(PeerId),
+    DialFailure,
+    NewListenAddr,
+    Behavior,
+}
+
+/// 记录收到的回调
+#[derive(Clone, Default)]
+struct Recorder(Arc<Mutex<Vec<Observed>>>);
+
+impl Recorder {
+    fn contains(&self, observed: &Observed) -> bool {
+        self.0.lock().unwrap().contains(observed)
+    }
+}
+
+impl<TBehaviorEvent> SwarmObserver<TBehaviorEvent> for Recorder {
+    fn on_connection_established(
+        &mut self,
+        _id: ConnectionId,
+        peer_id: PeerId,
+        _endpoint: &ConnectedPoint,
+    ) {
+        self.0.lock().unwrap().push(Observed::Established(peer_id));
+    }
+
+    fn on_dial_failure(
+        &mut self,
+        _id: ConnectionId,
+        _peer_id: Option<PeerId>,
+        _addr: Option<&Multiaddr>,
+        _error: &DialError,
+    ) {
+        self.0.lock().unwrap().push(Observed::DialFailure);
+    }
+
+    fn on_listener_event(&mut self, event: ListenerEvent<'_>) {
+        if let ListenerEvent::NewListenAddr(_) = event {
+            self.0.lock().unwrap().push(Observed::NewListenAddr);
+        }
+    }
+
+    fn on_behavior_event(&mut self, _event: &TBehaviorEvent) {
+        self.0.lock().unwrap().push(Observed::Behavior);
+    }
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn observe_swarm_events() {
+    let dialer_observed = Recorder::default();
+    let listener_observed = Recorder::default();
+    let mut dialer = client::Swarm::new_ephemeral(identify).with_observer(dialer_observed.clone());
+    let mut listener =
+        server::Swarm::new_ephemeral(identify).with_observer(listener_observed.clone());
+    let dialer_peer = *dialer.local_peer_id();
+    let listener_peer = *listener.local_peer_id();
+
+    connect(&mut dialer, &mut listener).await;
+    assert!(listener_observed.contains(&Observed::NewListenAddr));
+    assert!(listener_observed.contains(&Observed::Established(dialer_peer)));
+    assert!(dialer_observed.contains(&Observed::Established(listener_peer)));
+
+    tokio::spawn(async move {
+        loop {
+            next_swarm_event(&mut listener).await;
+        }
+    });
+    next_behavior_event(&mut dialer).await;
+    assert!(dialer_observed.contains(&Observed::Behavior));
+
+    dialer
+        .dial(DialOpts::new(Some(unused_addr()), None))
+        .unwrap();
+    wait_for_event(&mut dialer, |event| match event {
+        client::SwarmEvent::OutgoingConnectionError { .. } => Some(()),
+        _ => None,
+    })
+    .await;
+    assert!(dialer_observed.contains(&Observed::DialFailure));
+}
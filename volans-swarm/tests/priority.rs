@@ -0,0 +1,205 @@
+//! 出站子流的优先级
+
+use std::{
+    collections::VecDeque,
+    convert::Infallible,
+    task::{Context, Poll},
+};
+
+use futures::StreamExt;
+use volans_core::{Multiaddr, PeerId, upgrade::ReadyUpgrade};
+use volans_swarm::{
+    BehaviorEvent, ConnectionDenied, ConnectionHandler, ConnectionHandlerEvent, ConnectionId,
+    InboundStreamHandler, InboundUpgradeSend, NegotiationMode, NetworkBehavior,
+    NetworkIncomingBehavior, NetworkOutgoingBehavior, OutboundStreamHandler, OutboundUpgradeSend,
+    StreamProtocol, StreamUpgradeError, SubstreamPriority, SubstreamProtocol, THandlerAction,
+    THandlerEvent, client, server,
+};
+use volans_swarm_test::{SwarmExt, connect, next_behavior_event};
+
+const PROTOCOL: StreamProtocol = StreamProtocol::new("/priority/1.0.0");
+
+/// 连接建立后一次请求所有子流，按协商完成的顺序上报请求序号
+struct Open {
+    priorities: Vec<SubstreamPriority>,
+    opened: VecDeque<usize>,
+}
+
+impl Open {
+    fn new(priorities: Vec<SubstreamPriority>) -> Self {
+        Self {
+            priorities,
+            opened: VecDeque::new(),
+        }
+    }
+
+    fn handler(&self) -> OpenHandler {
+        OpenHandler {
+            requests: self.priorities.iter().copied().enumerate().collect(),
+            opened: VecDeque::new(),
+        }
+    }
+}
+
+struct OpenHandler {
+    requests: VecDeque<(usize, SubstreamPriority)>,
+    opened: VecDeque<usize>,
+}
+
+impl ConnectionHandler for OpenHandler {
+    type Action = Infallible;
+    type Event = usize;
+
+    fn handle_action(&mut self, action: Self::Action) {
+        match action {}
+    }
+
+    fn poll(&mut self, _cx: &mut Context<'_>) -> Poll<ConnectionHandlerEvent<Self::Event>> {
+        match self.opened.pop_front() {
+            Some(index) => Poll::Ready(ConnectionHandlerEvent::Notify(index)),
+            None => Poll::Pending,
+        }
+    }
+}
+
+impl InboundStreamHandler for OpenHandler {
+    type InboundUpgrade = ReadyUpgrade<StreamProtocol>;
+    type InboundUserData = ();
+
+    fn listen_protocol(&self) -> SubstreamProtocol<Self::InboundUpgrade, Self::InboundUserData> {
+        SubstreamProtocol::new(ReadyUpgrade::new(PROTOCOL), ())
+    }
+
+    fn on_fully_negotiated(
+        &mut self,
+        _user_data: Self::InboundUserData,
+        _protocol: <Self::InboundUpgrade as InboundUpgradeSend>::Output,
+    ) {
+    }
+
+    fn on_upgrade_error(
+        &mut self,
+        _user_data: Self::InboundUserData,
+        error: <Self::InboundUpgrade as InboundUpgradeSend>::Error,
+    ) {
+        match error {}
+    }
+}
+
+impl OutboundStreamHandler for OpenHandler {
+    type OutboundUpgrade = ReadyUpgrade<StreamProtocol>;
+    type OutboundUserData = usize;
+
+    fn on_fully_negotiated(
+        &mut self,
+        user_data: Self::OutboundUserData,
+        _protocol: <Self::OutboundUpgrade as OutboundUpgradeSend>::Output,
+    ) {
+        self.opened.push_back(user_data);
+    }
+
+    fn on_upgrade_error(
+        &mut self,
+        user_data: Self::OutboundUserData,
+        _error: StreamUpgradeError<<Self::OutboundUpgrade as OutboundUpgradeSend>::Error>,
+    ) {
+        panic!("substream {user_data} failed to open");
+    }
+
+    fn poll_outbound_request(
+        &mut self,
+        _cx: &mut Context<'_>,
+    ) -> Poll<SubstreamProtocol<Self::OutboundUpgrade, Self::OutboundUserData>> {
+        let Some((index, priority)) = self.requests.pop_front() else {
+            return Poll::Pending;
+        };
+        // 不等待对端确认，协商完成的顺序即子流分配的顺序
+        Poll::Ready(
+            SubstreamProtocol::new(ReadyUpgrade::new(PROTOCOL), index)
+                .with_negotiation_mode(NegotiationMode::Lazy)
+                .with_priority(priority),
+        )
+    }
+}
+
+impl NetworkBehavior for Open {
+    type ConnectionHandler = OpenHandler;
+    type Event = usize;
+
+    fn on_connection_handler_event(
+        &mut self,
+        _id: ConnectionId,
+        _peer_id: PeerId,
+        index: THandlerEvent<Self>,
+    ) {
+        self.opened.push_back(index);
+    }
+
+    fn poll(
+        &mut self,
+        _cx: &mut Context<'_>,
+    ) -> Poll<BehaviorEvent<Self::Event, THandlerAction<Self>>> {
+        match self.opened.pop_front() {
+            Some(index) => Poll::Ready(BehaviorEvent::Behavior(index)),
+            None => Poll::Pending,
+        }
+    }
+}
+
+impl NetworkIncomingBehavior for Open {
+    fn handle_established_connection(
+        &mut self,
+        _id: ConnectionId,
+        _peer_id: PeerId,
+        _local_addr: &Multiaddr,
+        _remote_addr: &Multiaddr,
+    ) -> Result<Self::ConnectionHandler, ConnectionDenied> {
+        Ok(self.handler())
+    }
+}
+
+impl NetworkOutgoingBehavior for Open {
+    fn handle_pending_connection(
+        &mut self,
+        _id: ConnectionId,
+        _maybe_peer: Option<PeerId>,
+        addr: &Option<Multiaddr>,
+    ) -> Result<Option<Multiaddr>, ConnectionDenied> {
+        Ok(addr.clone())
+    }
+
+    fn handle_established_connection(
+        &mut self,
+        _id: ConnectionId,
+        _peer_id: PeerId,
+        _addr: &Multiaddr,
+    ) -> Result<Self::ConnectionHandler, ConnectionDenied> {
+        Ok(self.handler())
+    }
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn substreams_opened_by_priority() {
+    let mut dialer = client::Swarm::new_ephemeral(|_| {
+        Open::new(vec![
+            SubstreamPriority::Low,
+            SubstreamPriority::Normal,
+            SubstreamPriority::High,
+            SubstreamPriority::Normal,
+        ])
+    });
+    let mut listener = server::Swarm::new_ephemeral(|_| Open::new(Vec::new()));
+    connect(&mut dialer, &mut listener).await;
+    tokio::spawn(async move {
+        loop {
+            listener.next().await;
+        }
+    });
+
+    // 高优先级先打开，同一优先级按请求顺序
+    let mut opened = Vec::new();
+    for _ in 0..4 {
+        opened.push(next_behavior_event(&mut dialer).await);
+    }
+    assert_eq!(opened, [2, 1, 3, 0]);
+}
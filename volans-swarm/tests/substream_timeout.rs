@@ -0,0 +1,210 @@
+//! 子流协商超时
+
+use std::{
+    collections::VecDeque,
+    convert::Infallible,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use futures::StreamExt;
+use volans_core::{Multiaddr, PeerId, upgrade::PendingUpgrade};
+use volans_swarm::{
+    BehaviorEvent, ConnectionDenied, ConnectionHandler, ConnectionHandlerEvent, ConnectionId,
+    InboundStreamHandler, InboundUpgradeSend, NetworkBehavior, NetworkIncomingBehavior,
+    NetworkOutgoingBehavior, OutboundStreamHandler, OutboundUpgradeSend, StreamUpgradeError,
+    SubstreamProtocol, THandlerAction, THandlerEvent, client, connection::PoolConfig, server,
+};
+use volans_swarm_test::{
+    SwarmExt, connect, ephemeral_key_pair, ephemeral_parts, next_behavior_event,
+};
+
+const PROTOCOL: &str = "/stall/1.0.0";
+
+/// 协商成功后永不完成升级的子流，每个连接按给定的超时依次打开
+struct Stall {
+    timeouts: Vec<Option<Duration>>,
+    timed_out: VecDeque<usize>,
+}
+
+impl Stall {
+    fn new(timeouts: Vec<Option<Duration>>) -> Self {
+        Self {
+            timeouts,
+            timed_out: VecDeque::new(),
+        }
+    }
+}
+
+struct StallHandler {
+    requests: VecDeque<(usize, Option<Duration>)>,
+    timed_out: VecDeque<usize>,
+}
+
+impl ConnectionHandler for StallHandler {
+    type Action = Infallible;
+    type Event = usize;
+
+    fn handle_action(&mut self, action: Self::Action) {
+        match action {}
+    }
+
+    fn poll(&mut self, _cx: &mut Context<'_>) -> Poll<ConnectionHandlerEvent<Self::Event>> {
+        match self.timed_out.pop_front() {
+            Some(index) => Poll::Ready(ConnectionHandlerEvent::Notify(index)),
+            None => Poll::Pending,
+        }
+    }
+}
+
+impl InboundStreamHandler for StallHandler {
+    type InboundUpgrade = PendingUpgrade<&'static str>;
+    type InboundUserData = ();
+
+    fn listen_protocol(&self) -> SubstreamProtocol<Self::InboundUpgrade, Self::InboundUserData> {
+        SubstreamProtocol::new(PendingUpgrade::new(PROTOCOL), ())
+    }
+
+    fn on_fully_negotiated(
+        &mut self,
+        _user_data: Self::InboundUserData,
+        protocol: <Self::InboundUpgrade as InboundUpgradeSend>::Output,
+    ) {
+        match protocol {}
+    }
+
+    fn on_upgrade_error(
+        &mut self,
+        _user_data: Self::InboundUserData,
+        error: <Self::InboundUpgrade as InboundUpgradeSend>::Error,
+    ) {
+        match error {}
+    }
+}
+
+impl OutboundStreamHandler for StallHandler {
+    type OutboundUpgrade = PendingUpgrade<&'static str>;
+    type OutboundUserData = usize;
+
+    fn on_fully_negotiated(
+        &mut self,
+        _user_data: Self::OutboundUserData,
+        protocol: <Self::OutboundUpgrade as OutboundUpgradeSend>::Output,
+    ) {
+        match protocol {}
+    }
+
+    fn on_upgrade_error(
+        &mut self,
+        user_data: Self::OutboundUserData,
+        error: StreamUpgradeError<<Self::OutboundUpgrade as OutboundUpgradeSend>::Error>,
+    ) {
+        if let StreamUpgradeError::Timeout = error {
+            self.timed_out.push_back(user_data);
+        }
+    }
+
+    fn poll_outbound_request(
+        &mut self,
+        _cx: &mut Context<'_>,
+    ) -> Poll<SubstreamProtocol<Self::OutboundUpgrade, Self::OutboundUserData>> {
+        let Some((index, timeout)) = self.requests.pop_front() else {
+            return Poll::Pending;
+        };
+        let protocol = SubstreamProtocol::new(PendingUpgrade::new(PROTOCOL), index);
+        Poll::Ready(match timeout {
+            Some(timeout) => protocol.with_timeout(timeout),
+            None => protocol,
+        })
+    }
+}
+
+impl Stall {
+    fn handler(&self) -> StallHandler {
+        StallHandler {
+            requests: self.timeouts.iter().copied().enumerate().collect(),
+            timed_out: VecDeque::new(),
+        }
+    }
+}
+
+impl NetworkBehavior for Stall {
+    type ConnectionHandler = StallHandler;
+    type Event = usize;
+
+    fn on_connection_handler_event(
+        &mut self,
+        _id: ConnectionId,
+        _peer_id: PeerId,
+        index: THandlerEvent<Self>,
+    ) {
+        self.timed_out.push_back(index);
+    }
+
+    fn poll(
+        &mut self,
+        _cx: &mut Context<'_>,
+    ) -> Poll<BehaviorEvent<Self::Event, THandlerAction<Self>>> {
+        match self.timed_out.pop_front() {
+            Some(index) => Poll::Ready(BehaviorEvent::Behavior(index)),
+            None => Poll::Pending,
+        }
+    }
+}
+
+impl NetworkIncomingBehavior for Stall {
+    fn handle_established_connection(
+        &mut self,
+        _id: ConnectionId,
+        _peer_id: PeerId,
+        _local_addr: &Multiaddr,
+        _remote_addr: &Multiaddr,
+    ) -> Result<Self::ConnectionHandler, ConnectionDenied> {
+        Ok(self.handler())
+    }
+}
+
+impl NetworkOutgoingBehavior for Stall {
+    fn handle_pending_connection(
+        &mut self,
+        _id: ConnectionId,
+        _maybe_peer: Option<PeerId>,
+        addr: &Option<Multiaddr>,
+    ) -> Result<Option<Multiaddr>, ConnectionDenied> {
+        Ok(addr.clone())
+    }
+
+    fn handle_established_connection(
+        &mut self,
+        _id: ConnectionId,
+        _peer_id: PeerId,
+        _addr: &Multiaddr,
+    ) -> Result<Self::ConnectionHandler, ConnectionDenied> {
+        Ok(self.handler())
+    }
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn substream_timeout_overrides_pool_default() {
+    let key_pair = ephemeral_key_pair();
+    let (transport, peer_id) = ephemeral_parts(&key_pair);
+    // 第一个子流使用连接池的默认超时，第二个单独设置了更长的超时
+    let mut dialer = client::Swarm::from_parts(
+        transport,
+        Stall::new(vec![None, Some(Duration::from_secs(60))]),
+        peer_id,
+        PoolConfig::with_tokio_executor().with_substream_upgrade_timeout(Duration::from_millis(50)),
+    );
+    let mut listener = server::Swarm::new_ephemeral(|_| Stall::new(Vec::new()));
+    connect(&mut dialer, &mut listener).await;
+    tokio::spawn(async move {
+        loop {
+            listener.next().await;
+        }
+    });
+
+    assert_eq!(next_behavior_event(&mut dialer).await, 0);
+    let second =
+        tokio::time::timeout(Duration::from_millis(500), next_behavior_event(&mut dialer)).await;
+    assert!(second.is_err());
+}
@@ -0,0 +1,386 @@
+//! 声明式的节点配置
+//!
+//! [`NodeConfig`] 可以从 TOML、YAML 等任意 serde 格式读取，未出现的字段使用默认值。
+//! [`SwarmBuilder::from_config`] 据此构造传输层与连接池，行为的开关与限制由
+//! [`BehaviorSettings`] 提供，调用方在构造行为时读取。
+
+use std::{io, time::Duration};
+
+use serde::{Deserialize, Serialize};
+use volans_core::{
+    Multiaddr, PeerId, Transport, TransportError, identity::KeyPair, muxing::StreamMuxerBox,
+    transport,
+};
+use volans_swarm::{
+    InboundStreamHandler, NetworkIncomingBehavior, NetworkOutgoingBehavior, OutboundStreamHandler,
+    client, connection::PoolConfig, duplex, server,
+};
+
+#[derive(Debug, thiserror::Error)]
+pub enum ConfigError {
+    /// 配置使用的传输或多路复用器未在编译时启用
+    #[error("`{0}` is not enabled in this build")]
+    Unsupported(&'static str),
+    #[error("Failed to listen on {addr}: {error}")]
+    Listen {
+        addr: Multiaddr,
+        #[source]
+        error: TransportError<io::Error>,
+    },
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct NodeConfig {
+    /// 服务端与 duplex 节点构造后监听的地址
+    pub listen: Vec<Multiaddr>,
+    pub transport: TransportSettings,
+    pub muxer: MuxerSettings,
+    pub pool: PoolSettings,
+    pub behaviors: BehaviorSettings,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TransportKind {
+    #[default]
+    Tcp,
+    Ws,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TransportSettings {
+    pub kind: TransportKind,
+    pub nodelay: Option<bool>,
+    /// TCP keepalive 的空闲探测时间，单位秒
+    pub keepalive_secs: Option<u64>,
+    pub port_reuse: Option<bool>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MuxerKind {
+    #[default]
+    Muxing,
+    Yamux,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MuxerSettings {
+    pub kind: MuxerKind,
+    /// 单个连接上同时存在的子流上限
+    pub max_streams: Option<usize>,
+}
+
+/// 连接池参数，未设置的使用 [`PoolConfig`] 的默认值，时长单位为秒
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PoolSettings {
+    pub task_command_buffer_size: Option<usize>,
+    pub per_connection_event_buffer_size: Option<usize>,
+    pub idle_connection_timeout_secs: Option<u64>,
+    pub max_negotiating_inbound_streams: Option<usize>,
+    pub substream_upgrade_timeout_secs: Option<u64>,
+    pub pending_connection_timeout_secs: Option<u64>,
+    pub max_pending_incoming: Option<usize>,
+    pub max_concurrent_dials: Option<usize>,
+    pub max_concurrent_dials_per_peer: Option<usize>,
+    pub negotiation_cache: Option<bool>,
+}
+
+impl PoolSettings {
+    pub fn apply(&self, mut config: PoolConfig) -> PoolConfig {
+        if let Some(size) = self.task_command_buffer_size {
+            config = config.with_task_command_buffer_size(size);
+        }
+        if let Some(size) = self.per_connection_event_buffer_size {
+            config = config.with_per_connection_event_buffer_size(size);
+        }
+        if let Some(secs) = self.idle_connection_timeout_secs {
+            config = config.with_idle_connection_timeout(Duration::from_secs(secs));
+        }
+        if let Some(count) = self.max_negotiating_inbound_streams {
+            config = config.with_max_negotiating_inbound_streams(count);
+        }
+        if let Some(secs) = self.substream_upgrade_timeout_secs {
+            config = config.with_substream_upgrade_timeout(Duration::from_secs(secs));
+        }
+        if let Some(secs) = self.pending_connection_timeout_secs {
+            config = config.with_pending_connection_timeout(Duration::from_secs(secs));
+        }
+        if let Some(count) = self.max_pending_incoming {
+            config = config.with_max_pending_incoming(count);
+        }
+        if let Some(count) = self.max_concurrent_dials {
+            config = config.with_max_concurrent_dials(count);
+        }
+        if let Some(count) = self.max_concurrent_dials_per_peer {
+            config = config.with_max_concurrent_dials_per_peer(count);
+        }
+        if let Some(enabled) = self.negotiation_cache {
+            config = config.with_negotiation_cache(enabled);
+        }
+        config
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BehaviorSettings {
+    pub ping: PingSettings,
+    pub registry: bool,
+    pub relay: RelaySettings,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PingSettings {
+    pub enabled: bool,
+    pub interval_secs: Option<u64>,
+}
+
+impl Default for PingSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            interval_secs: None,
+        }
+    }
+}
+
+/// 中继服务的开关与资源限制，时长单位为秒
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RelaySettings {
+    pub enabled: bool,
+    pub reservation_required: Option<bool>,
+    pub max_reservations: Option<usize>,
+    pub max_circuits_per_peer: Option<usize>,
+    pub max_circuit_bytes: Option<u64>,
+    pub max_circuit_duration_secs: Option<u64>,
+    pub max_circuit_idle_secs: Option<u64>,
+}
+
+#[cfg(feature = "ping")]
+impl PingSettings {
+    pub fn config(&self) -> volans_ping::Config {
+        let config = volans_ping::Config::default();
+        match self.interval_secs {
+            Some(secs) => config.with_interval(Duration::from_secs(secs)),
+            None => config,
+        }
+    }
+
+    /// 按开关包装的出站 Ping 行为
+    pub fn outbound(&self) -> volans_swarm::behavior::Toggle<volans_ping::outbound::Behavior> {
+        let behavior = volans_ping::outbound::Behavior::new(self.config());
+        volans_swarm::behavior::Toggle::new(behavior, self.enabled)
+    }
+
+    /// 按开关包装的入站 Ping 行为
+    pub fn inbound(&self) -> volans_swarm::behavior::Toggle<volans_ping::inbound::Behavior> {
+        let behavior = volans_ping::inbound::Behavior::new(self.config());
+        volans_swarm::behavior::Toggle::new(behavior, self.enabled)
+    }
+}
+
+#[cfg(feature = "bridge")]
+impl RelaySettings {
+    pub fn config(&self) -> volans_bridge::relay::Config {
+        let mut config = volans_bridge::relay::Config::default();
+        if let Some(required) = self.reservation_required {
+            config = config.with_reservation_required(required);
+        }
+        if let Some(max) = self.max_reservations {
+            config = config.with_max_reservations(max);
+        }
+        if let Some(max) = self.max_circuits_per_peer {
+            config = config.with_max_circuits_per_peer(max);
+        }
+        if let Some(max) = self.max_circuit_bytes {
+            config = config.with_max_circuit_bytes(max);
+        }
+        if let Some(secs) = self.max_circuit_duration_secs {
+            config = config.with_max_circuit_duration(Duration::from_secs(secs));
+        }
+        if let Some(secs) = self.max_circuit_idle_secs {
+            config = config.with_max_circuit_idle(Duration::from_secs(secs));
+        }
+        config
+    }
+}
+
+/// 按配置的多路复用器完成升级，各传输升级后的类型不同，无法写成泛型函数
+macro_rules! multiplex {
+    ($authenticated:expr, $settings:expr) => {
+        match $settings.kind {
+            MuxerKind::Muxing => {
+                let mut muxing = volans_muxing::Config::new();
+                if let Some(max) = $settings.max_streams {
+                    muxing.set_max_active_streams(max);
+                }
+                Ok($authenticated.multiplex(muxing).boxed())
+            }
+            #[cfg(feature = "yamux")]
+            MuxerKind::Yamux => {
+                let mut yamux = volans_yamux::UpgradeConfig::new();
+                if let Some(max) = $settings.max_streams {
+                    yamux = yamux.with_max_num_streams(max);
+                }
+                Ok($authenticated.multiplex(yamux).boxed())
+            }
+            #[cfg(not(feature = "yamux"))]
+            MuxerKind::Yamux => Err(ConfigError::Unsupported("yamux")),
+        }
+    };
+}
+
+/// 按 [`NodeConfig`] 构造 Swarm，连接任务运行在 tokio 上
+pub struct SwarmBuilder {
+    config: NodeConfig,
+}
+
+impl SwarmBuilder {
+    pub fn from_config(config: NodeConfig) -> Self {
+        Self { config }
+    }
+
+    pub fn config(&self) -> &NodeConfig {
+        &self.config
+    }
+
+    pub fn pool_config(&self) -> PoolConfig {
+        self.config.pool.apply(PoolConfig::with_tokio_executor())
+    }
+
+    /// 以明文认证构造传输层
+    pub fn build_transport(
+        &self,
+        key_pair: &KeyPair,
+    ) -> Result<transport::Boxed<(PeerId, StreamMuxerBox)>, ConfigError> {
+        let settings = &self.config.transport;
+        let muxer = &self.config.muxer;
+        let mut tcp = volans_tcp::Config::new();
+        if let Some(nodelay) = settings.nodelay {
+            tcp = tcp.nodelay(nodelay);
+        }
+        if let Some(secs) = settings.keepalive_secs {
+            tcp = tcp.keepalive(Duration::from_secs(secs));
+        }
+        if let Some(port_reuse) = settings.port_reuse {
+            tcp = tcp.port_reuse(port_reuse);
+        }
+        let authenticate = volans_plaintext::Config::new(key_pair.verifying_key());
+        match settings.kind {
+            TransportKind::Tcp => multiplex!(tcp.upgrade().authenticate(authenticate), muxer),
+            #[cfg(feature = "ws")]
+            TransportKind::Ws => {
+                let mut ws = volans_ws::Config::new();
+                ws.tcp = tcp;
+                multiplex!(ws.upgrade().authenticate(authenticate), muxer)
+            }
+            #[cfg(not(feature = "ws"))]
+            TransportKind::Ws => Err(ConfigError::Unsupported("ws")),
+        }
+    }
+
+    pub fn build_client<B>(
+        self,
+        key_pair: &KeyPair,
+        behavior: B,
+    ) -> Result<client::Swarm<B>, ConfigError>
+    where
+        B: NetworkOutgoingBehavior,
+        B::ConnectionHandler: OutboundStreamHandler,
+    {
+        Ok(client::Swarm::new(
+            self.build_transport(key_pair)?,
+            behavior,
+            PeerId::from_public_key(&key_pair.verifying_key()),
+            self.pool_config(),
+        ))
+    }
+
+    /// 构造服务端并监听配置中的地址
+    pub fn build_server<B>(
+        self,
+        key_pair: &KeyPair,
+        behavior: B,
+    ) -> Result<server::Swarm<B>, ConfigError>
+    where
+        B: NetworkIncomingBehavior,
+        B::ConnectionHandler: InboundStreamHandler,
+    {
+        let mut swarm = server::Swarm::new(
+            self.build_transport(key_pair)?,
+            behavior,
+            PeerId::from_public_key(&key_pair.verifying_key()),
+            self.pool_config(),
+        );
+        for addr in &self.config.listen {
+            swarm
+                .listen_on(addr.clone())
+                .map_err(|error| ConfigError::Listen {
+                    addr: addr.clone(),
+                    error,
+                })?;
+        }
+        Ok(swarm)
+    }
+
+    /// 构造 duplex 节点并监听配置中的地址
+    pub fn build_duplex<B>(
+        self,
+        key_pair: &KeyPair,
+        behavior: B,
+    ) -> Result<duplex::Swarm<B>, ConfigError>
+    where
+        B: NetworkIncomingBehavior + NetworkOutgoingBehavior,
+        B::ConnectionHandler: InboundStreamHandler + OutboundStreamHandler,
+    {
+        let mut swarm = duplex::Swarm::new(
+            self.build_transport(key_pair)?,
+            behavior,
+            PeerId::from_public_key(&key_pair.verifying_key()),
+            self.pool_config(),
+        );
+        for addr in &self.config.listen {
+            swarm
+                .listen_on(addr.clone())
+                .map_err(|error| ConfigError::Listen {
+                    addr: addr.clone(),
+                    error,
+                })?;
+        }
+        Ok(swarm)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn partial_config_uses_defaults() {
+        let config: NodeConfig = serde_json::from_str(
+            r#"{
+                "listen": ["/ip4/0.0.0.0/tcp/4001"],
+                "muxer": { "kind": "yamux" },
+                "pool": { "idle_connection_timeout_secs": 30 },
+                "behaviors": { "relay": { "enabled": true, "max_reservations": 8 } }
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(config.listen, ["/ip4/0.0.0.0/tcp/4001".parse().unwrap()]);
+        assert_eq!(config.transport.kind, TransportKind::Tcp);
+        assert_eq!(config.muxer.kind, MuxerKind::Yamux);
+        assert_eq!(config.pool.idle_connection_timeout_secs, Some(30));
+        assert!(config.behaviors.ping.enabled);
+        assert_eq!(config.behaviors.relay.max_reservations, Some(8));
+
+        let unknown = serde_json::from_str::<NodeConfig>(r#"{ "pool": { "idle": 30 } }"#);
+        assert!(unknown.is_err());
+    }
+}
@@ -8,6 +8,12 @@ pub use volans_swarm as swarm;
 #[cfg(feature = "codec")]
 pub use volans_codec as codec;
 
+#[cfg(feature = "metrics")]
+pub use volans_metrics as metrics;
+
+#[cfg(feature = "config")]
+pub mod config;
+
 // transports
 #[cfg(feature = "tcp")]
 pub use volans_tcp as tcp;
@@ -15,6 +21,9 @@ pub use volans_tcp as tcp;
 #[cfg(feature = "ws")]
 pub use volans_ws as ws;
 
+#[cfg(feature = "dns")]
+pub use volans_dns as dns;
+
 #[cfg(feature = "plaintext")]
 pub use volans_plaintext as plaintext;
 
@@ -40,3 +49,39 @@ pub use volans_registry as registry;
 
 #[cfg(feature = "bridge")]
 pub use volans_bridge as bridge;
+
+#[cfg(feature = "connection-limits")]
+pub use volans_connection_limits as connection_limits;
+
+#[cfg(feature = "allow-block-list")]
+pub use volans_allow_block_list as allow_block_list;
+
+#[cfg(feature = "pubsub")]
+pub use volans_pubsub as pubsub;
+
+#[cfg(feature = "kad")]
+pub use volans_kad as kad;
+
+#[cfg(feature = "identify")]
+pub use volans_identify as identify;
+
+#[cfg(feature = "dcutr")]
+pub use volans_dcutr as dcutr;
+
+#[cfg(feature = "rate-limit")]
+pub use volans_rate_limit as rate_limit;
+
+#[cfg(feature = "upnp")]
+pub use volans_upnp as upnp;
+
+#[cfg(feature = "perf")]
+pub use volans_perf as perf;
+
+#[cfg(feature = "echo")]
+pub use volans_echo as echo;
+
+#[cfg(feature = "admin")]
+pub use volans_admin as admin;
+
+#[cfg(feature = "compress")]
+pub use volans_compress as compress;